@@ -0,0 +1,38 @@
+#!/bin/sh
+# vim-tutorial-nvim のフックの例。data/config.toml に次のように書くと、練習・章を完了するたびに実行されます:
+#
+#   on_exercise_complete = "sh docs/hooks/record_completion.sh"
+#   on_chapter_complete = "sh docs/hooks/record_completion.sh"
+#
+# 標準入力には結果の記録の JSON（練習なら title・status・duration_secs・hints_used など、
+# 章なら chapter_number・chapter_title・exercises）が1行で渡されます。
+# 環境変数:
+#   VIM_TUTORIAL_EVENT          exercise_complete / chapter_complete
+#   VIM_TUTORIAL_CHAPTER        章番号
+#   VIM_TUTORIAL_EXERCISE       練習の題名（exercise_complete のとき）
+#   VIM_TUTORIAL_CHAPTER_TITLE  章の題名（chapter_complete のとき）
+#   VIM_TUTORIAL_DURATION_SECS  かかった秒数（章は練習の合計）
+#
+# この例は、完了の記録を習慣トラッカー用のファイル（TSV）に1行ずつ、結果の JSON をその隣の .jsonl に追記し、
+# SLACK_WEBHOOK_URL が設定されていれば章の完了を Slack に投稿します（投稿には jq と curl が必要です）。
+# hook_timeout_seconds（既定10秒）で終わらなければ止められます。失敗しても学習は続きます。
+set -eu
+
+payload=$(cat)
+log="${VIM_TUTORIAL_HABIT_LOG:-$HOME/.vim_tutorial_habits.tsv}"
+
+printf '%s\t%s\t%s\t%s\t%s\n' \
+    "$(date -u +%Y-%m-%dT%H:%M:%SZ)" \
+    "$VIM_TUTORIAL_EVENT" \
+    "$VIM_TUTORIAL_CHAPTER" \
+    "${VIM_TUTORIAL_EXERCISE:-${VIM_TUTORIAL_CHAPTER_TITLE:-}}" \
+    "$VIM_TUTORIAL_DURATION_SECS" >> "$log"
+printf '%s\n' "$payload" >> "${log%.tsv}.jsonl"
+
+if [ "$VIM_TUTORIAL_EVENT" = "chapter_complete" ] && [ -n "${SLACK_WEBHOOK_URL:-}" ]; then
+    text="第${VIM_TUTORIAL_CHAPTER}章「${VIM_TUTORIAL_CHAPTER_TITLE}」を完了しました（${VIM_TUTORIAL_DURATION_SECS}秒）"
+    # 章の題名はパックの作者が書けるため、" や \ を含んでも壊れないよう jq で JSON にする
+    jq -n --arg text "$text" '{text: $text}' |
+        curl -fsS -m 5 -H 'Content-Type: application/json' -d @- "$SLACK_WEBHOOK_URL" > /dev/null
+fi
+
@@ -0,0 +1,343 @@
+// 章の終わりに書く結果のファイル（学習管理システムなど外部のツールが取り込む）。
+// 章を完了しなかった場合やエラーで終わった場合も、それまでの記録と status を付けて書く。
+// 形式を変えたら SCHEMA_VERSION を上げる
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::keystrokes::{self, KeystrokeScore};
+use crate::paths;
+use crate::progress::LearningMode;
+use crate::summary::{ChapterSummary, ExerciseStatus, ExerciseSummary};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+// --summary-out で指定した書き出し先（設定の summary_out より優先する）
+static OUTPUT: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn write_to(path: PathBuf) {
+    let _ = OUTPUT.set(path);
+}
+
+/// 章の終わり方
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChapterOutcome {
+    /// 全ての練習を完了した
+    Completed,
+    /// 未完了・時間切れ・未実施の練習を残して章を抜けた
+    Incomplete,
+    /// 練習がエラーで終わったか、章を続けられなかった
+    Failed,
+}
+
+/// 結果ファイルの形式。キーは snake_case、日時は RFC 3339（UTC）、時間は秒
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ChapterReport {
+    pub schema_version: u32,
+    /// vim-tutorial-nvim の版
+    pub tool_version: String,
+    /// 章データの内容のハッシュ（練習を書き換えると変わる。従来モードでは無い）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_version: Option<String>,
+    pub status: ChapterOutcome,
+    /// 章を続けられなかったエラー
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub mode: LearningMode,
+    pub chapter: ReportChapter,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub totals: ReportTotals,
+    pub exercises: Vec<ReportExercise>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ReportChapter {
+    pub number: u8,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ReportTotals {
+    /// 練習にかかった時間の合計（練習の合間の時間は含まない）
+    pub duration_secs: f64,
+    pub exercises_completed: usize,
+    pub exercises_total: usize,
+    pub hints_used: usize,
+    pub goals_skipped: usize,
+    /// 最適キー数のある目標全体でのキー効率 0〜1（対象が無ければ無い）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keystroke_efficiency: Option<f64>,
+    /// キー効率の評価（S / A / B / C）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grade: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ReportExercise {
+    /// 章の中での番号（1始まり）
+    pub number: usize,
+    pub title: String,
+    /// completed / incomplete / failed / not_started / timed_out
+    pub status: ExerciseStatus,
+    pub duration_secs: f64,
+    pub hints_used: usize,
+    pub goals_skipped: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keystroke_efficiency: Option<f64>,
+    /// 目標ごとの最適キー数と実際のキー数
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keystroke_scores: Vec<KeystrokeScore>,
+    /// 練習の内容のハッシュ（記録した時点のもの）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+}
+
+impl ReportExercise {
+    fn from_summary(number: usize, exercise: &ExerciseSummary) -> Self {
+        Self {
+            number,
+            title: exercise.title.clone(),
+            status: exercise.status,
+            duration_secs: exercise.duration_secs,
+            hints_used: exercise.hints_used,
+            goals_skipped: exercise.goals_skipped,
+            keystroke_efficiency: keystrokes::overall_efficiency(&exercise.keystroke_scores),
+            keystroke_scores: exercise.keystroke_scores.clone(),
+            content_hash: exercise.content_hash.clone(),
+        }
+    }
+}
+
+impl ChapterReport {
+    // error は章の途中で止まったときの理由（あれば status は failed）
+    pub fn new(
+        summary: &ChapterSummary,
+        started_at: DateTime<Utc>,
+        content_version: Option<String>,
+        error: Option<String>,
+    ) -> Self {
+        let exercises = &summary.exercises;
+        let status = if error.is_some() || exercises.iter().any(|e| e.status == ExerciseStatus::Failed) {
+            ChapterOutcome::Failed
+        } else if summary.is_complete() {
+            ChapterOutcome::Completed
+        } else {
+            ChapterOutcome::Incomplete
+        };
+        Self {
+            schema_version: SCHEMA_VERSION,
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            content_version,
+            status,
+            error,
+            mode: summary.mode,
+            chapter: ReportChapter {
+                number: summary.chapter_number,
+                title: summary.chapter_title.clone(),
+            },
+            started_at,
+            finished_at: summary.finished_at,
+            totals: ReportTotals {
+                duration_secs: exercises.iter().map(|e| e.duration_secs).sum(),
+                exercises_completed: exercises.iter().filter(|e| e.status == ExerciseStatus::Completed).count(),
+                exercises_total: exercises.len(),
+                hints_used: exercises.iter().map(|e| e.hints_used).sum(),
+                goals_skipped: exercises.iter().map(|e| e.goals_skipped).sum(),
+                keystroke_efficiency: summary.keystroke_efficiency(),
+                grade: summary.grade().map(|grade| grade.label().to_string()),
+            },
+            exercises: exercises
+                .iter()
+                .enumerate()
+                .map(|(index, exercise)| ReportExercise::from_summary(index + 1, exercise))
+                .collect(),
+        }
+    }
+}
+
+// 書き出し先: --summary-out、設定の summary_out、既定のディレクトリの章・モード・日時ごとのファイルの順
+pub fn output_path(configured: Option<&Path>, report: &ChapterReport) -> PathBuf {
+    if let Some(path) = OUTPUT.get().map(PathBuf::as_path).or(configured) {
+        return path.to_path_buf();
+    }
+    let mode = match report.mode {
+        LearningMode::Continuous => "continuous",
+        LearningMode::Classic => "classic",
+    };
+    // 既定は paths::reports_dir に1章ごとに別のファイルを書く
+    paths::reports_dir().join(format!(
+        "chapter_{:02}_{}_{}.json",
+        report.chapter.number,
+        mode,
+        report.finished_at.format("%Y%m%dT%H%M%SZ")
+    ))
+}
+
+// 一時ファイルに書いてから置き換え、取り込む側に書きかけのファイルを読ませない
+pub fn write(path: &Path, report: &ChapterReport) -> Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(parent)?;
+    let mut partial = tempfile::NamedTempFile::new_in(parent)?;
+    partial.write_all(serde_json::to_string_pretty(report)?.as_bytes())?;
+    partial.as_file().sync_all()?;
+    partial.persist(path)?;
+    Ok(())
+}
+
+// 章の終わりに書く（書けなくても章の結果の表示は続ける）
+pub fn save(report: &ChapterReport) {
+    let configured = crate::config::TutorialConfig::load().summary_out;
+    let path = output_path(configured.as_deref(), report);
+    match write(&path, report) {
+        Ok(()) => crate::output::info!("📄 結果を {} に書き出しました", path.display()),
+        Err(e) => crate::output::warning!("⚠️ 結果ファイル {} を書けません: {}", path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // モックのエディタで2つ目の練習まで進め、3つ目に入る前にエラーで止まった章
+    fn mocked_run() -> ChapterSummary {
+        ChapterSummary {
+            mode: LearningMode::Continuous,
+            chapter_number: 2,
+            chapter_title: "テキスト編集".to_string(),
+            finished_at: Utc.with_ymd_and_hms(2026, 10, 14, 9, 30, 0).unwrap(),
+            exercises: vec![
+                ExerciseSummary {
+                    title: "挿入".to_string(),
+                    status: ExerciseStatus::Completed,
+                    duration_secs: 12.5,
+                    hints_used: 1,
+                    goals_skipped: 0,
+                    attempts: 1,
+                    keystroke_scores: vec![KeystrokeScore {
+                        label: "Insert モードに入る".to_string(),
+                        optimal: 1,
+                        actual: 2,
+                    }],
+                    content_hash: Some("0123456789abcdef".to_string()),
+                },
+                ExerciseSummary {
+                    status: ExerciseStatus::Incomplete,
+                    duration_secs: 30.0,
+                    goals_skipped: 1,
+                    attempts: 1,
+                    ..ExerciseSummary::not_started("削除")
+                },
+                ExerciseSummary::not_started("置換"),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_report_snapshot() {
+        let started_at = Utc.with_ymd_and_hms(2026, 10, 14, 9, 28, 30).unwrap();
+        let mut report = ChapterReport::new(
+            &mocked_run(),
+            started_at,
+            Some("fedcba9876543210".to_string()),
+            Some("Neovim との接続が切れました".to_string()),
+        );
+        report.tool_version = "0.0.0".to_string();
+        assert_eq!(
+            serde_json::to_string_pretty(&report).unwrap(),
+            r#"{
+  "schema_version": 1,
+  "tool_version": "0.0.0",
+  "content_version": "fedcba9876543210",
+  "status": "failed",
+  "error": "Neovim との接続が切れました",
+  "mode": "continuous",
+  "chapter": {
+    "number": 2,
+    "title": "テキスト編集"
+  },
+  "started_at": "2026-10-14T09:28:30Z",
+  "finished_at": "2026-10-14T09:30:00Z",
+  "totals": {
+    "duration_secs": 42.5,
+    "exercises_completed": 1,
+    "exercises_total": 3,
+    "hints_used": 1,
+    "goals_skipped": 1,
+    "keystroke_efficiency": 0.5,
+    "grade": "B"
+  },
+  "exercises": [
+    {
+      "number": 1,
+      "title": "挿入",
+      "status": "completed",
+      "duration_secs": 12.5,
+      "hints_used": 1,
+      "goals_skipped": 0,
+      "keystroke_efficiency": 0.5,
+      "keystroke_scores": [
+        {
+          "label": "Insert モードに入る",
+          "optimal": 1,
+          "actual": 2
+        }
+      ],
+      "content_hash": "0123456789abcdef"
+    },
+    {
+      "number": 2,
+      "title": "削除",
+      "status": "incomplete",
+      "duration_secs": 30.0,
+      "hints_used": 0,
+      "goals_skipped": 1
+    },
+    {
+      "number": 3,
+      "title": "置換",
+      "status": "not_started",
+      "duration_secs": 0.0,
+      "hints_used": 0,
+      "goals_skipped": 0
+    }
+  ]
+}"#
+        );
+    }
+
+    #[test]
+    fn test_partial_chapter_is_incomplete_and_written_atomically() {
+        let started_at = Utc.with_ymd_and_hms(2026, 10, 14, 9, 28, 30).unwrap();
+        let report = ChapterReport::new(&mocked_run(), started_at, None, None);
+        assert_eq!(report.status, ChapterOutcome::Incomplete);
+        assert_eq!(
+            output_path(None, &report),
+            paths::reports_dir().join("chapter_02_continuous_20261014T093000Z.json")
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out").join("summary.json");
+        assert_eq!(output_path(Some(&path), &report), path);
+        write(&path, &report).unwrap();
+        // 一時ファイルは残らない
+        let names: Vec<_> = fs::read_dir(path.parent().unwrap()).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(names, vec![std::ffi::OsString::from("summary.json")]);
+        let written: ChapterReport = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written.exercises.len(), 3);
+        assert!(written.content_version.is_none());
+    }
+}
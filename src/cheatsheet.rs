@@ -0,0 +1,411 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use unicode_width::UnicodeWidthStr;
+
+use crate::content::{ChapterData, ContentLoader};
+use crate::continuous_content::{ContinuousChapterData, ContinuousContentLoader};
+use crate::theme::say;
+
+// Vimのキーとして扱う特別なキーの名前（ヒントでの書き方 → 表示）
+const SPECIAL_KEYS: &[(&str, &str)] = &[
+    ("esc", "<Esc>"),
+    ("escape", "<Esc>"),
+    ("enter", "<CR>"),
+    ("return", "<CR>"),
+    ("tab", "<Tab>"),
+    ("space", "<Space>"),
+    ("backspace", "<BS>"),
+    ("bs", "<BS>"),
+];
+
+// ヒントに出てくる英単語のうち、キー入力ではないもの
+const NON_KEY_WORDS: &[&str] = &[
+    "word", "words", "inner", "name", "line", "mode", "text", "with", "from", "then", "into",
+    "this", "that", "file", "code", "type", "true", "null",
+];
+
+// 英字だけのトークンをキーとみなす最大の長さ（それより長いものは単語とみなす）
+const MAX_KEY_TOKEN_LEN: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CheatsheetFormat {
+    #[value(name = "md")]
+    Markdown,
+    #[value(name = "txt")]
+    Text,
+}
+
+impl CheatsheetFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            CheatsheetFormat::Markdown => "md",
+            CheatsheetFormat::Text => "txt",
+        }
+    }
+}
+
+// 目標（従来モードではステップ）1つ分の行
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheatsheetEntry {
+    pub keys: Vec<String>,
+    pub description: String,
+}
+
+// 練習ごとのまとまり
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheatsheetSection {
+    pub title: String,
+    pub description: String,
+    pub entries: Vec<CheatsheetEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cheatsheet {
+    pub chapter_number: u8,
+    pub chapter_title: String,
+    pub description: String,
+    pub sections: Vec<CheatsheetSection>,
+}
+
+impl Cheatsheet {
+    // 連続学習の章: 目標の keys、無ければ解答のキー、それも無ければヒント・説明から推測したキー
+    pub fn from_continuous(chapter: &ContinuousChapterData) -> Self {
+        let sections = chapter
+            .continuous_exercises
+            .iter()
+            .map(|exercise| CheatsheetSection {
+                title: exercise.title.clone(),
+                description: exercise.description.clone(),
+                entries: exercise
+                    .goals
+                    .iter()
+                    .map(|goal| {
+                        let keys = if !goal.keys.is_empty() {
+                            goal.keys.clone()
+                        } else if let Some(solution) = &goal.solution_keys {
+                            vec![solution.clone()]
+                        } else {
+                            let mut keys = Vec::new();
+                            for text in goal.hint.iter().chain([&goal.description]) {
+                                for key in extract_keys(text) {
+                                    if !keys.contains(&key) {
+                                        keys.push(key);
+                                    }
+                                }
+                            }
+                            keys
+                        };
+                        CheatsheetEntry {
+                            keys,
+                            description: goal.description.clone(),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self {
+            chapter_number: chapter.chapter.number,
+            chapter_title: chapter.chapter.title.clone(),
+            description: chapter.chapter.description.clone(),
+            sections,
+        }
+    }
+
+    // 従来モードの章: ステップの expected_input がそのまま学ぶキー
+    pub fn from_classic(chapter: &ChapterData) -> Self {
+        let sections = chapter
+            .exercises
+            .iter()
+            .map(|exercise| CheatsheetSection {
+                title: exercise.title.clone(),
+                description: exercise.description.clone(),
+                entries: exercise
+                    .steps
+                    .iter()
+                    .map(|step| CheatsheetEntry {
+                        keys: Some(step.expected_input.trim().to_string())
+                            .filter(|keys| !keys.is_empty())
+                            .into_iter()
+                            .collect(),
+                        description: step.instruction.clone(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self {
+            chapter_number: chapter.chapter.number,
+            chapter_title: chapter.chapter.title.clone(),
+            description: chapter.chapter.description.clone(),
+            sections,
+        }
+    }
+
+    pub fn render(&self, format: CheatsheetFormat) -> String {
+        match format {
+            CheatsheetFormat::Markdown => self.render_markdown(),
+            CheatsheetFormat::Text => self.render_text(),
+        }
+    }
+
+    fn render_markdown(&self) -> String {
+        let mut lines = vec![
+            format!("# 第{}章: {} チートシート", self.chapter_number, self.chapter_title),
+            String::new(),
+            self.description.clone(),
+        ];
+        for (i, section) in self.sections.iter().enumerate() {
+            lines.push(String::new());
+            lines.push(format!("## {}. {}", i + 1, section.title));
+            lines.push(String::new());
+            if !section.description.is_empty() {
+                lines.push(section.description.clone());
+                lines.push(String::new());
+            }
+            lines.push("| キー | 説明 |".to_string());
+            lines.push("| --- | --- |".to_string());
+            for entry in &section.entries {
+                let keys = if entry.keys.is_empty() {
+                    "—".to_string()
+                } else {
+                    entry
+                        .keys
+                        .iter()
+                        .map(|key| markdown_code(key))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                };
+                lines.push(format!("| {} | {} |", keys, entry.description.replace('|', "\\|")));
+            }
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    fn render_text(&self) -> String {
+        let heading = format!("第{}章: {} チートシート", self.chapter_number, self.chapter_title);
+        let mut lines = vec![
+            heading.clone(),
+            "=".repeat(UnicodeWidthStr::width(heading.as_str())),
+            self.description.clone(),
+        ];
+        for (i, section) in self.sections.iter().enumerate() {
+            lines.push(String::new());
+            lines.push(format!("[{}] {}", i + 1, section.title));
+            if !section.description.is_empty() {
+                lines.push(format!("    {}", section.description));
+            }
+            let keys: Vec<String> = section
+                .entries
+                .iter()
+                .map(|entry| {
+                    if entry.keys.is_empty() {
+                        "—".to_string()
+                    } else {
+                        entry.keys.join(" ")
+                    }
+                })
+                .collect();
+            // キーの列を揃える
+            let width = keys
+                .iter()
+                .map(|keys| UnicodeWidthStr::width(keys.as_str()))
+                .max()
+                .unwrap_or(0);
+            for (keys, entry) in keys.iter().zip(&section.entries) {
+                let padding = width - UnicodeWidthStr::width(keys.as_str());
+                lines.push(format!("  {}{}  {}", keys, " ".repeat(padding), entry.description));
+            }
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+// キーを Markdown のコードにする（バッククォートを含む場合は2つで囲む）
+fn markdown_code(key: &str) -> String {
+    let key = key.replace('|', "\\|");
+    if key.contains('`') {
+        format!("`` {} ``", key)
+    } else {
+        format!("`{}`", key)
+    }
+}
+
+// ヒントや説明からキー入力らしい部分を取り出す。
+// 引用符で囲まれた部分はバッファの文字列なので除き、英数字・記号のまとまりのうち
+// 特別なキーの名前、: で始まるコマンド、短いキーの並びをキーとみなす
+pub fn extract_keys(text: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    let unquoted = strip_quoted(text);
+    let tokens = unquoted
+        .split(|c: char| !c.is_ascii() || c.is_ascii_whitespace())
+        .map(|token| token.trim_matches(|c: char| "()[],.;!?".contains(c)))
+        .filter(|token| !token.is_empty());
+    for token in tokens {
+        let key = if let Some(&(_, name)) = SPECIAL_KEYS
+            .iter()
+            .find(|(spoken, _)| token.eq_ignore_ascii_case(spoken))
+        {
+            Some(name.to_string())
+        } else if let Some(ctrl) = token
+            .strip_prefix("Ctrl-")
+            .or_else(|| token.strip_prefix("Ctrl+"))
+            .filter(|rest| rest.chars().count() == 1)
+        {
+            Some(format!("<C-{}>", ctrl.to_lowercase()))
+        } else if (token.starts_with(':') && token.len() > 1) || is_key_sequence(token) {
+            Some(token.to_string())
+        } else {
+            None
+        };
+        if let Some(key) = key
+            && !keys.contains(&key)
+        {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+// 短いキーの並び（英単語・数字だけのものは除く）
+fn is_key_sequence(token: &str) -> bool {
+    if token.starts_with('<') && token.ends_with('>') && token.len() > 2 {
+        return true;
+    }
+    let letters = token.chars().filter(|c| c.is_ascii_alphabetic()).count();
+    if letters == 0 || token.len() > MAX_KEY_TOKEN_LEN {
+        return false;
+    }
+    // 大文字で始まる単語（Insert、Vim など）や、よく出る英単語は説明の一部
+    let mut chars = token.chars();
+    let capitalized = chars.next().is_some_and(|c| c.is_ascii_uppercase())
+        && chars.clone().next().is_some_and(|c| c.is_ascii_lowercase());
+    if capitalized || NON_KEY_WORDS.contains(&token.to_lowercase().as_str()) {
+        return false;
+    }
+    token
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "$^%~/*#\"'@<>=+-_{}".contains(c))
+}
+
+// '...' と "..." で囲まれた部分を空白に置き換える（対応する引用符が無ければそのまま）
+fn strip_quoted(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(['\'', '"']) {
+        let quote = rest[start..].chars().next().unwrap();
+        let after = &rest[start + 1..];
+        let Some(end) = after.find(quote) else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        result.push(' ');
+        rest = &after[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+pub fn file_name(chapter_number: u8, format: CheatsheetFormat) -> String {
+    format!("chapter_{:02}_cheatsheet.{}", chapter_number, format.extension())
+}
+
+// 章のチートシートを作る。--all では章ごとのファイルを out のディレクトリに書き、
+// 1つの章で out が無ければ標準出力に出す
+pub fn run_cheatsheet(
+    continuous: bool,
+    chapter: Option<u8>,
+    all: bool,
+    out: Option<&Path>,
+    format: CheatsheetFormat,
+) -> Result<()> {
+    let sheets: Vec<Cheatsheet> = if continuous {
+        let (loader, report) = ContinuousContentLoader::load()?;
+        report.print_warnings();
+        loader.chapters().iter().map(Cheatsheet::from_continuous).collect()
+    } else {
+        let (loader, report) = ContentLoader::load()?;
+        report.print_warnings();
+        loader.chapters().iter().map(Cheatsheet::from_classic).collect()
+    };
+
+    if all {
+        let dir = out.map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+        fs::create_dir_all(&dir)?;
+        for sheet in &sheets {
+            let path = dir.join(file_name(sheet.chapter_number, format));
+            fs::write(&path, sheet.render(format))?;
+            say!("📝 第{}章のチートシートを作成しました: {}", sheet.chapter_number, path.display());
+        }
+        return Ok(());
+    }
+
+    let Some(number) = chapter else {
+        return Err(anyhow::anyhow!("--chapter で章番号を指定するか、--all を指定してください"));
+    };
+    let sheet = sheets
+        .iter()
+        .find(|sheet| sheet.chapter_number == number)
+        .ok_or_else(|| anyhow::anyhow!("第{}章が見つかりません", number))?;
+    match out {
+        Some(path) => {
+            fs::write(path, sheet.render(format))?;
+            say!("📝 第{}章のチートシートを作成しました: {}", number, path.display());
+        }
+        None => print!("{}", sheet.render(format)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuous_session::ExerciseGoal;
+    use crate::test_support::assert_snapshot;
+
+    #[test]
+    fn test_extract_keys_from_hints() {
+        assert_eq!(extract_keys("l キーを5回押して → → で 'W' の位置へ"), vec!["l"]);
+        assert_eq!(extract_keys("3行目の最初に移動してください (j→hhhh)"), vec!["j", "hhhh"]);
+        assert_eq!(extract_keys("iw で inner word を指定します"), vec!["iw"]);
+        assert_eq!(extract_keys("Escキーでノーマルモードに戻ります"), vec!["<Esc>"]);
+        assert_eq!(extract_keys("Insertモードで Vim を使う"), Vec::<String>::new());
+        assert_eq!(extract_keys(":w で保存、Ctrl-r でやり直し"), vec![":w", "<C-r>"]);
+        // 引用符の中はバッファの文字列
+        assert_eq!(extract_keys("' + name' を入力します"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_explicit_keys_override_extraction() {
+        let mut chapter = ContinuousContentLoader::sample_chapter();
+        chapter.continuous_exercises.truncate(1);
+        chapter.continuous_exercises[0].goals = vec![
+            ExerciseGoal {
+                description: "単語を削除して貼り付け（diwp）".to_string(),
+                keys: vec!["diw".to_string(), "p".to_string()],
+                ..Default::default()
+            },
+            ExerciseGoal {
+                description: "好きなように | 移動".to_string(),
+                ..Default::default()
+            },
+        ];
+        let sheet = Cheatsheet::from_continuous(&chapter);
+        let entries = &sheet.sections[0].entries;
+        assert_eq!(entries[0].keys, vec!["diw", "p"]);
+        assert!(entries[1].keys.is_empty());
+
+        let markdown = sheet.render(CheatsheetFormat::Markdown);
+        assert!(markdown.contains("| `diw` `p` | 単語を削除して貼り付け（diwp） |"));
+        assert!(markdown.contains("| — | 好きなように \\| 移動 |"));
+    }
+
+    #[test]
+    fn test_sample_chapter_snapshots() {
+        let sheet = Cheatsheet::from_continuous(&ContinuousContentLoader::sample_chapter());
+        assert_snapshot("cheatsheet_sample.md", &sheet.render(CheatsheetFormat::Markdown));
+        assert_snapshot("cheatsheet_sample.txt", &sheet.render(CheatsheetFormat::Text));
+        assert_eq!(file_name(1, CheatsheetFormat::Text), "chapter_01_cheatsheet.txt");
+    }
+}
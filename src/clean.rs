@@ -0,0 +1,453 @@
+use anyhow::Result;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::paths;
+use crate::theme::say;
+
+// チュートリアルが作る固定名の一時ファイル（デバッグログは調査用に残す）
+const FIXED_TEMP_FILES: &[&str] = &[
+    "vim_continuous_status.json",
+    "vim_continuous_success.flag",
+    "vim_continuous_progress.json",
+    "vim_continuous_progress.txt",
+    "vim_continuous_command.txt",
+    "vim_continuous_keys.log",
+    "vim_continuous_notice.txt",
+    "vim_continuous_instructions.txt",
+    "vim_tutorial_status.json",
+    "vim_tutorial_success.flag",
+    "vim_tutorial_pane.txt",
+    "vim_test_output.txt",
+    KEPT_SESSIONS_FILE,
+];
+
+// --keep-session で残したエディタの接続先（1行に1つ。--socket-path の場所でも片付けられるように）
+pub const KEPT_SESSIONS_FILE: &str = "vim_tutorial_kept_sessions.txt";
+
+// 連続学習モードのソケット名（vim_tutorial_continuous_<PID>.sock）
+const SOCKET_PREFIX: &str = "vim_tutorial_continuous_";
+const SOCKET_SUFFIX: &str = ".sock";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StaleResource {
+    TmuxSession(String),
+    File(PathBuf),
+    Process { pid: u32, args: String },
+}
+
+impl StaleResource {
+    pub fn describe(&self) -> String {
+        match self {
+            StaleResource::TmuxSession(name) => format!("tmuxセッション: {}", name),
+            StaleResource::File(path) => format!("ファイル: {}", path.display()),
+            StaleResource::Process { pid, args } => format!("プロセス: {} ({})", pid, args),
+        }
+    }
+
+    fn remove(&self) -> Result<()> {
+        match self {
+            StaleResource::TmuxSession(name) => {
+                // "=" で前方一致ではなく完全一致のセッションだけを対象にする
+                let output = Command::new("tmux")
+                    .args(["kill-session", "-t", &format!("={}", name)])
+                    .output()?;
+                if !output.status.success() {
+                    return Err(anyhow::anyhow!(
+                        "{}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+            }
+            StaleResource::File(path) => fs::remove_file(path)?,
+            StaleResource::Process { pid, .. } => terminate(*pid)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CleanScan {
+    pub stale: Vec<StaleResource>,
+    pub skipped: Vec<String>, // 実行中のチュートリアルが使っているため残すもの
+}
+
+// 現在のシステムの状態
+pub struct SystemSnapshot {
+    pub tmux_sessions: Vec<String>,
+    pub processes: Vec<(u32, String)>,
+    pub own_pid: u32,
+}
+
+fn program_name(args: &str) -> &str {
+    let first = args.split_whitespace().next().unwrap_or("");
+    first.rsplit('/').next().unwrap_or(first)
+}
+
+fn is_our_session(name: &str) -> bool {
+    name.starts_with("vim_tutorial")
+}
+
+// ソケットのファイル名から、それを作ったチュートリアルのPIDを取り出す
+fn socket_owner(file_name: &str) -> Option<u32> {
+    file_name
+        .strip_prefix(SOCKET_PREFIX)?
+        .strip_suffix(SOCKET_SUFFIX)?
+        .parse()
+        .ok()
+}
+
+// --keep-session で残したエディタの接続先を記録する
+pub fn record_kept_session(server: &str) {
+    let path = paths::cache_file(KEPT_SESSIONS_FILE);
+    if let Ok(mut file) = fs::OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(file, "{}", server);
+    }
+}
+
+fn kept_sessions(tmp_dir: &Path) -> Vec<String> {
+    fs::read_to_string(tmp_dir.join(KEPT_SESSIONS_FILE))
+        .map(|text| text.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn is_fixed_temp_file(file_name: &str) -> bool {
+    let base = file_name.strip_suffix(".processing").unwrap_or(file_name);
+    FIXED_TEMP_FILES.contains(&base)
+}
+
+// 我々のソケットで起動した nvim なら、ソケットを作ったチュートリアルのPID
+fn nvim_socket_owner(args: &str) -> Option<u32> {
+    if program_name(args) != "nvim" {
+        return None;
+    }
+    args.split_whitespace()
+        .filter_map(|arg| arg.rsplit('/').next())
+        .find_map(socket_owner)
+}
+
+// 片付けの対象を探す。PIDが分かるものは持ち主が生きていれば残し、
+// 固定名のもの（tmuxセッションや状態ファイル）は他のチュートリアルが動いていれば残す
+pub fn scan(tmp_dir: &Path, system: &SystemSnapshot, is_alive: impl Fn(u32) -> bool) -> CleanScan {
+    let mut result = CleanScan::default();
+    let other_running = system
+        .processes
+        .iter()
+        .any(|(pid, args)| *pid != system.own_pid && program_name(args) == "vim-tutorial-nvim");
+    let owner_alive = |owner: u32| owner != system.own_pid && is_alive(owner);
+
+    for name in system.tmux_sessions.iter().filter(|name| is_our_session(name)) {
+        if other_running {
+            result
+                .skipped
+                .push(format!("tmuxセッション: {}（実行中のチュートリアルが使用中）", name));
+        } else {
+            result.stale.push(StaleResource::TmuxSession(name.clone()));
+        }
+    }
+
+    let mut files: Vec<PathBuf> = fs::read_dir(tmp_dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    files.sort();
+    for path in files {
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let in_use = match socket_owner(file_name) {
+            Some(owner) => owner_alive(owner),
+            None if is_fixed_temp_file(file_name) => other_running,
+            None => continue,
+        };
+        if in_use {
+            result.skipped.push(format!(
+                "ファイル: {}（実行中のチュートリアルが使用中）",
+                path.display()
+            ));
+        } else {
+            result.stale.push(StaleResource::File(path));
+        }
+    }
+
+    // 残したセッションの接続先は名前から持ち主が分からないため、固定名のものと同じに扱う
+    let kept = kept_sessions(tmp_dir);
+    for server in &kept {
+        let path = Path::new(server);
+        // 一時ディレクトリの既定の名前のソケットは上で数えた
+        let known = path.parent() == Some(tmp_dir)
+            && path.file_name().and_then(|n| n.to_str()).is_some_and(|name| socket_owner(name).is_some());
+        if known || !path.exists() {
+            continue;
+        }
+        if other_running {
+            result.skipped.push(format!("ファイル: {}（実行中のチュートリアルが使用中）", server));
+        } else {
+            result.stale.push(StaleResource::File(path.to_path_buf()));
+        }
+    }
+
+    for (pid, args) in &system.processes {
+        let Some(owner) = nvim_socket_owner(args) else {
+            if program_name(args) != "nvim" || !args.split_whitespace().any(|arg| kept.iter().any(|s| s == arg)) {
+                continue;
+            }
+            if other_running {
+                result.skipped.push(format!("プロセス: {}（実行中のチュートリアルが使用中）", pid));
+            } else {
+                result.stale.push(StaleResource::Process {
+                    pid: *pid,
+                    args: args.clone(),
+                });
+            }
+            continue;
+        };
+        if owner_alive(owner) {
+            result
+                .skipped
+                .push(format!("プロセス: {}（PID {} のチュートリアルが使用中）", pid, owner));
+        } else {
+            result.stale.push(StaleResource::Process {
+                pid: *pid,
+                args: args.clone(),
+            });
+        }
+    }
+
+    result
+}
+
+fn current_system() -> SystemSnapshot {
+    let tmux_sessions = Command::new("tmux")
+        .args(["list-sessions", "-F", "#{session_name}"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    let processes = Command::new("ps")
+        .args(["-eo", "pid=,args="])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| {
+                    let (pid, args) = line.trim_start().split_once(' ')?;
+                    Some((pid.parse().ok()?, args.trim().to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    SystemSnapshot {
+        tmux_sessions,
+        processes,
+        own_pid: std::process::id(),
+    }
+}
+
+// プロセスが動いているか（シグナル 0 を送れるか。権限が無いだけなら動いている）
+#[cfg(unix)]
+pub fn pid_alive(pid: u32) -> bool {
+    use nix::errno::Errno;
+    let Ok(pid) = i32::try_from(pid) else {
+        return false;
+    };
+    match nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid), None) {
+        Ok(()) | Err(Errno::EPERM) => true,
+        Err(_) => false,
+    }
+}
+
+// 確かめる手段が無い OS では、動いているとみなす（実行中の練習の記録や一時ファイルを片付けない）
+#[cfg(not(unix))]
+pub fn pid_alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn terminate(pid: u32) -> Result<()> {
+    let pid = i32::try_from(pid).map_err(|_| anyhow::anyhow!("プロセス番号 {} は使えません", pid))?;
+    nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid), nix::sys::signal::Signal::SIGTERM)
+        .map_err(|e| anyhow::anyhow!("プロセスを終了できません: {}", e))
+}
+
+#[cfg(not(unix))]
+fn terminate(_pid: u32) -> Result<()> {
+    Err(anyhow::anyhow!("この OS ではプロセスを終了できません"))
+}
+
+pub fn run_clean(yes: bool) -> Result<()> {
+    say!("🧹 === 後片付け ===");
+    // 各モードが一時ファイルとソケットを置くキャッシュの置き場所
+    let found = scan(&paths::cache_dir(), &current_system(), pid_alive);
+
+    for skipped in &found.skipped {
+        say!("  ⏭️ {}", skipped);
+    }
+    if found.stale.is_empty() {
+        say!("✨ 片付けるものはありません");
+        return Ok(());
+    }
+    say!("見つかった残骸:");
+    for resource in &found.stale {
+        say!("  - {}", resource.describe());
+    }
+
+    if !yes {
+        print!("これらを削除しますか？ [y/N]: ");
+        io::stdout().flush()?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if !matches!(input.trim().to_lowercase().as_str(), "y" | "yes") {
+            say!("何も削除しませんでした。");
+            return Ok(());
+        }
+    }
+
+    let mut removed = 0;
+    let mut failed = 0;
+    for resource in &found.stale {
+        match resource.remove() {
+            Ok(()) => removed += 1,
+            Err(e) => {
+                failed += 1;
+                crate::output::warning!("⚠️ 削除に失敗: {}: {}", resource.describe(), e);
+            }
+        }
+    }
+    say!(
+        "✓ {}件を片付けました（失敗 {}件、使用中で残したもの {}件）",
+        removed,
+        failed,
+        found.skipped.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn touch(dir: &Path, name: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, "").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_scan_only_matches_our_resources() {
+        let tmp_dir = tempdir().unwrap();
+        let status = touch(tmp_dir.path(), "vim_continuous_status.json");
+        let processing = touch(tmp_dir.path(), "vim_continuous_keys.log.processing");
+        let dead_socket = touch(tmp_dir.path(), "vim_tutorial_continuous_111.sock");
+        let live_socket = touch(tmp_dir.path(), "vim_tutorial_continuous_222.sock");
+        touch(tmp_dir.path(), "vim_continuous_debug.log");
+        touch(tmp_dir.path(), "vim_tutorial_continuous_abc.sock");
+        touch(tmp_dir.path(), "other.sock");
+
+        let system = SystemSnapshot {
+            tmux_sessions: vec![
+                "vim_tutorial_continuous".to_string(),
+                "work".to_string(),
+                "my_vim_tutorial".to_string(),
+            ],
+            processes: vec![
+                (10, "nvim --headless --listen /tmp/vim_tutorial_continuous_111.sock".to_string()),
+                (11, "nvim --headless --listen /tmp/vim_tutorial_continuous_222.sock".to_string()),
+                (12, "nvim --listen /tmp/someone_else.sock".to_string()),
+                (99, "/usr/local/bin/vim-tutorial-nvim clean".to_string()),
+            ],
+            own_pid: 99,
+        };
+        let found = scan(tmp_dir.path(), &system, |pid| pid == 222);
+
+        assert_eq!(
+            found.stale,
+            vec![
+                StaleResource::TmuxSession("vim_tutorial_continuous".to_string()),
+                StaleResource::File(processing),
+                StaleResource::File(status),
+                StaleResource::File(dead_socket),
+                StaleResource::Process {
+                    pid: 10,
+                    args: "nvim --headless --listen /tmp/vim_tutorial_continuous_111.sock"
+                        .to_string(),
+                },
+            ]
+        );
+        assert_eq!(found.skipped.len(), 2);
+        assert!(found.skipped[0].contains(&live_socket.display().to_string()));
+        assert!(found.skipped[1].contains("PID 222"));
+    }
+
+    #[test]
+    fn test_scan_finds_kept_sessions_at_custom_sockets() {
+        let tmp_dir = tempdir().unwrap();
+        let socket_dir = tempdir().unwrap();
+        let socket = touch(socket_dir.path(), "debug.sock");
+        let record = tmp_dir.path().join(KEPT_SESSIONS_FILE);
+        fs::write(&record, format!("{}\n/nonexistent/gone.sock\n", socket.display())).unwrap();
+
+        let kept_nvim = format!("nvim --listen {} -S /tmp/script.vim", socket.display());
+        let system = SystemSnapshot {
+            tmux_sessions: Vec::new(),
+            processes: vec![(20, kept_nvim.clone()), (21, "nvim --listen /tmp/other.sock".to_string())],
+            own_pid: 99,
+        };
+        let found = scan(tmp_dir.path(), &system, |_| false);
+        assert_eq!(
+            found.stale,
+            vec![
+                StaleResource::File(record),
+                StaleResource::File(socket),
+                StaleResource::Process {
+                    pid: 20,
+                    args: kept_nvim,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_keeps_shared_resources_while_another_tutorial_runs() {
+        let tmp_dir = tempdir().unwrap();
+        touch(tmp_dir.path(), "vim_tutorial_status.json");
+
+        let system = SystemSnapshot {
+            tmux_sessions: vec!["vim_tutorial".to_string()],
+            processes: vec![
+                (50, "target/release/vim-tutorial-nvim -c".to_string()),
+                (99, "vim-tutorial-nvim clean --yes".to_string()),
+            ],
+            own_pid: 99,
+        };
+        let found = scan(tmp_dir.path(), &system, |_| true);
+        assert!(found.stale.is_empty());
+        assert_eq!(found.skipped.len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pid_alive_and_terminate() -> Result<()> {
+        assert!(pid_alive(std::process::id()));
+        let mut child = Command::new("sleep").arg("30").spawn()?;
+        assert!(pid_alive(child.id()));
+        StaleResource::Process {
+            pid: child.id(),
+            args: "sleep 30".to_string(),
+        }
+        .remove()?;
+        child.wait()?;
+        // 終了して回収したプロセスは動いていない
+        assert!(!pid_alive(child.id()));
+        assert!(!pid_alive(u32::MAX));
+        Ok(())
+    }
+}
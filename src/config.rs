@@ -0,0 +1,227 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::layout::DisplayPolicy;
+use crate::paths;
+
+// poll_interval_ms に使える範囲（状態ファイルが止まったとみなす3秒より十分に短くする）
+pub const POLL_INTERVAL_RANGE_MS: RangeInclusive<u64> = 20..=1000;
+
+// --poll-interval-ms で指定した間隔（設定の poll_interval_ms より優先する）
+static POLL_INTERVAL_MS: OnceLock<u64> = OnceLock::new();
+
+pub fn set_poll_interval_ms(ms: u64) {
+    let _ = POLL_INTERVAL_MS.set(ms);
+}
+
+// 練習の終わりに画面（tmux のペインの内容）を保存する場合
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureOn {
+    #[default]
+    Failed, // エラーで終わった練習だけ
+    Incomplete, // エラーと、中断・時間切れの練習
+    Always, // --capture-on-exit と同じ
+}
+
+impl CaptureOn {
+    pub fn captures(&self, completed: bool, failed: bool) -> bool {
+        match self {
+            CaptureOn::Failed => failed,
+            CaptureOn::Incomplete => !completed,
+            CaptureOn::Always => true,
+        }
+    }
+}
+
+// --capture-on-exit で、設定の capture_on にかかわらず毎回保存する
+static CAPTURE_ALWAYS: OnceLock<()> = OnceLock::new();
+
+pub fn set_capture_always() {
+    let _ = CAPTURE_ALWAYS.set(());
+}
+
+// --keep-session: 練習の終わりにエディタと tmux のセッションを止めずに残す（章の作者向けのデバッグ用）
+static KEEP_SESSION: OnceLock<()> = OnceLock::new();
+
+pub fn set_keep_session() {
+    let _ = KEEP_SESSION.set(());
+}
+
+pub fn keep_session() -> bool {
+    KEEP_SESSION.get().is_some()
+}
+
+// --no-records: 設定の records にかかわらず自己ベストを記録も表示もしない
+static NO_RECORDS: OnceLock<()> = OnceLock::new();
+
+pub fn set_no_records() {
+    let _ = NO_RECORDS.set(());
+}
+
+// --zen: 設定の zen にかかわらず、時間・進捗・スコアを表示しない
+static ZEN: OnceLock<()> = OnceLock::new();
+
+pub fn set_zen() {
+    let _ = ZEN.set(());
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TutorialConfig {
+    // 目標が進まない状態がこの秒数続くと自動でヒントを表示
+    pub hint_delay_seconds: u64,
+    // ヒント表示後、さらにこの秒数で解答のキー入力を表示
+    pub solution_delay_seconds: u64,
+    // :TutorialDemo で解答のキーを再生するときの、キーとキーの間隔（ミリ秒）
+    pub demo_key_delay_ms: u64,
+    // エディタが状態を書き出し、監視側が状態を確かめる間隔（ミリ秒）。変化が無い間は監視側の間隔を空ける
+    // （--poll-interval-ms と同じ）
+    pub poll_interval_ms: u64,
+    // 章を完了したら確認せずに次の章へ進む（--auto-advance と同じ）
+    pub auto_advance: bool,
+    // 目標や練習を達成したとき、Neovimのペインでベルを鳴らしてメッセージを出す
+    pub bell: bool,
+    // 練習と章を完了したときにデスクトップ通知を出す（notify-send / osascript）
+    pub desktop_notifications: bool,
+    // Neovimとの接続が切れたとき、練習を失敗にするまでに再確立を試みる回数
+    pub reconnect_attempts: u32,
+    // 練習の開始後、Neovimから最初の状態が届くまで待つ秒数（過ぎたら練習を失敗にする）
+    pub status_timeout_seconds: u64,
+    // 練習用のエディタに行番号とルーラーを出し、指示ペインにカーソル位置を表示する
+    // （false で位置の手がかりを出さずに練習する）
+    pub show_position: bool,
+    // 状態が変わらないまま、指示ペインで続けるか尋ねるまでの秒数
+    pub idle_threshold_seconds: u64,
+    // 尋ねてからさらに操作が無いとき、一時停止するまでの秒数
+    pub idle_grace_seconds: u64,
+    // テキストの目標だけが残り、バッファを最後に変えてからこの秒数たっても一致しないとき、
+    // 期待する行と実際の行を並べて違う位置を示す
+    pub stalled_text_seconds: u64,
+    // 同じ移動キーを何度も続けて押したとき、より良い移動を指示ペインで勧める
+    pub coaching: bool,
+    // 絵文字を文字の言い換えにし、指示ペインの更新をまとめるスクリーンリーダー向けの表示（--accessible と同じ）
+    pub accessible: bool,
+    // export で書き出す学習者の識別子（未設定なら $USER@ホスト名）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub learner_id: Option<String>,
+    // 章の終わりに書く結果ファイルの書き出し先（--summary-out と同じ。未設定なら data/reports に章ごとに書く）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary_out: Option<std::path::PathBuf>,
+    // 練習の終わりに画面を一時ディレクトリ（デバッグログと同じ場所）に保存する場合（failed / incomplete / always）
+    pub capture_on: CaptureOn,
+    // 初めてのセッションの最初の練習の前に、l・i・Esc が届くかを確かめる慣らしの練習を行う
+    pub warmup: bool,
+    // 練習・章を完了したときに実行するコマンド（結果の JSON を標準入力に渡す。未設定なら実行しない）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_exercise_complete: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_chapter_complete: Option<String>,
+    // フックのコマンドが終わらないとき、止めるまでの秒数
+    pub hook_timeout_seconds: u64,
+    // 練習ごとの自己ベスト（最短時間・最高のキー効率）を記録して表示する（教室の共用の端末では false）
+    pub records: bool,
+    // 時間・進捗・スコアを表示せず、目標とヒントだけを出す（--zen と同じ。記録は残す）
+    pub zen: bool,
+}
+
+impl Default for TutorialConfig {
+    fn default() -> Self {
+        Self {
+            hint_delay_seconds: 30,
+            solution_delay_seconds: 30,
+            demo_key_delay_ms: 400,
+            poll_interval_ms: 100,
+            auto_advance: false,
+            bell: true,
+            desktop_notifications: false,
+            reconnect_attempts: 5,
+            status_timeout_seconds: 30,
+            show_position: true,
+            idle_threshold_seconds: 300,
+            idle_grace_seconds: 60,
+            stalled_text_seconds: 5,
+            coaching: false,
+            accessible: false,
+            learner_id: None,
+            summary_out: None,
+            capture_on: CaptureOn::Failed,
+            warmup: true,
+            on_exercise_complete: None,
+            on_chapter_complete: None,
+            hook_timeout_seconds: 10,
+            records: true,
+            zen: false,
+        }
+    }
+}
+
+impl TutorialConfig {
+    pub fn load() -> Self {
+        // 既定は paths::config_file（環境変数 VIM_TUTORIAL_CONFIG で別のファイルを指定できる）
+        let path = std::env::var("VIM_TUTORIAL_CONFIG")
+            .unwrap_or_else(|_| paths::config_file().to_string_lossy().into_owned());
+        match Self::load_from(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                if Path::new(&path).exists() {
+                    crate::output::warning!("⚠️ 設定ファイルの読み込みに失敗: {}: {}", path, e);
+                }
+                Self::default()
+            }
+        }
+    }
+
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    // 状態を確かめる間隔（範囲の外の値は範囲に収める）
+    pub fn poll_interval(&self) -> Duration {
+        let ms = POLL_INTERVAL_MS.get().copied().unwrap_or(self.poll_interval_ms);
+        Duration::from_millis(ms.clamp(*POLL_INTERVAL_RANGE_MS.start(), *POLL_INTERVAL_RANGE_MS.end()))
+    }
+
+    pub fn records(&self) -> bool {
+        self.records && NO_RECORDS.get().is_none()
+    }
+
+    pub fn display(&self) -> DisplayPolicy {
+        if self.zen || ZEN.get().is_some() { DisplayPolicy::Zen } else { DisplayPolicy::Full }
+    }
+
+    pub fn capture_on(&self) -> CaptureOn {
+        if CAPTURE_ALWAYS.get().is_some() { CaptureOn::Always } else { self.capture_on }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_config_uses_defaults() -> Result<()> {
+        let config: TutorialConfig = toml::from_str("hint_delay_seconds = 10")?;
+        assert_eq!(config.hint_delay_seconds, 10);
+        assert_eq!(config.solution_delay_seconds, 30);
+        assert!(!config.auto_advance);
+        assert!(config.bell);
+        assert!(!config.desktop_notifications);
+        assert!(!config.accessible);
+        assert_eq!(config.poll_interval(), Duration::from_millis(100));
+        let config: TutorialConfig = toml::from_str("poll_interval_ms = 5")?;
+        assert_eq!(config.poll_interval(), Duration::from_millis(20));
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_config_file() {
+        assert!(TutorialConfig::load_from("/nonexistent/config.toml").is_err());
+    }
+}
@@ -1,8 +1,16 @@
 use anyhow::Result;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 
+use crate::error::TutorialError;
+use crate::menu::{self, ChapterFilter};
+use crate::output::{info, warning};
+use crate::paths;
+use crate::theme::say;
+use crate::validator::{self, ChapterRequirement};
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ChapterData {
     pub chapter: ChapterInfo,
@@ -14,6 +22,44 @@ pub struct ChapterInfo {
     pub number: u8,
     pub title: String,
     pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<Difficulty>,
+    // 章を終えるまでの目安の時間（分）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_minutes: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    // 先に完了しておく必要のある章の番号
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Beginner,
+    Intermediate,
+    Advanced,
+}
+
+impl Difficulty {
+    // 英語の名前（章データと同じ）と日本語の表示名のどちらも受け付ける
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "beginner" | "初級" => Some(Difficulty::Beginner),
+            "intermediate" | "中級" => Some(Difficulty::Intermediate),
+            "advanced" | "上級" => Some(Difficulty::Advanced),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Difficulty::Beginner => "初級",
+            Difficulty::Intermediate => "中級",
+            Difficulty::Advanced => "上級",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -35,52 +81,220 @@ pub struct StepData {
     pub text_change: Option<bool>,
 }
 
+// ディレクトリ内の "{prefix}NN.yaml" という名前の章ファイルの番号（昇順）
+pub fn chapter_file_numbers(dir: &Path, prefix: &str) -> Vec<u8> {
+    let mut numbers: Vec<u8> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let name = entry.file_name().into_string().ok()?;
+                    let digits = name.strip_prefix(prefix)?.strip_suffix(".yaml")?;
+                    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                        return None;
+                    }
+                    digits.parse().ok()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    numbers.sort_unstable();
+    numbers.dedup();
+    numbers
+}
+
+// 章ファイル1つ分の読み込みの結果
+#[derive(Debug)]
+pub enum ChapterLoad {
+    Loaded { number: u8, title: String },
+    Issue { number: u8, message: String }, // 読み込めたが章データに問題がある
+    Failed { number: u8, error: anyhow::Error },
+    FailedFile { file: String, error: anyhow::Error }, // 章番号の分からないファイル（章のまとめ）を読めなかった
+    Legacy { number: u8 }, // 連続学習版が無く、従来形式のファイルだけがある
+}
+
+// 章データの読み込みの経過（ローダーは表示せず、表示するかは呼び出し側が決める）
+#[derive(Debug)]
+pub struct LoadReport {
+    edition: &'static str,      // 章番号に添える版の名前（"（連続学習版）" など）
+    content_name: &'static str, // 合計の行に使うコンテンツの名前
+    pub chapters: Vec<ChapterLoad>,
+    pub total: usize,
+}
+
+impl LoadReport {
+    pub fn new(edition: &'static str, content_name: &'static str) -> Self {
+        Self {
+            edition,
+            content_name,
+            chapters: Vec::new(),
+            total: 0,
+        }
+    }
+
+    // 経過と警告を表示する（経過は --quiet で表示しない）
+    // 読み込めなかった章は、読み込めた章の経過と合計の後にまとめて表示する
+    pub fn print(&self) {
+        for load in &self.chapters {
+            match load {
+                ChapterLoad::Loaded { number, title } => {
+                    info!("✓ 第{}章{}を読み込みました: {}", number, self.edition, title);
+                }
+                ChapterLoad::Legacy { number } => {
+                    info!("🔄 第{}章を従来形式から連続学習形式に変換中...", number);
+                }
+                _ => {}
+            }
+        }
+        info!("📚 合計 {} 章の{}コンテンツを読み込みました", self.total, self.content_name);
+        self.print_warnings();
+    }
+
+    // 章データの警告と読み込めなかった章だけを表示する（JSON 出力など、標準出力を結果に使う場合）
+    pub fn print_warnings(&self) {
+        for load in &self.chapters {
+            if let ChapterLoad::Issue { number, message } = load {
+                warning!("⚠️ 第{}章: {}", number, message);
+            }
+        }
+        let failures = self.failures();
+        if !failures.is_empty() {
+            warning!("⚠️ 読み込めなかった章があります{}（{}件）:", self.edition, failures.len());
+            for (source, error) in failures {
+                warning!("  {}: {}", source, error);
+            }
+        }
+    }
+
+    // 読み込めなかった章（"第N章" か章のまとめのファイル名と、理由）
+    pub fn failures(&self) -> Vec<(String, &anyhow::Error)> {
+        self.chapters
+            .iter()
+            .filter_map(|load| match load {
+                ChapterLoad::Failed { number, error } => Some((format!("第{}章", number), error)),
+                ChapterLoad::FailedFile { file, error } => Some((file.clone(), error)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // 章データの警告の数
+    pub fn issue_count(&self) -> usize {
+        self.chapters
+            .iter()
+            .filter(|load| matches!(load, ChapterLoad::Issue { .. }))
+            .count()
+    }
+}
+
+// 章ファイルを読み、誤りには項目のパス（"chapter.number"）と行を付ける
+pub fn parse_yaml<T: DeserializeOwned>(content: &str, file: &str) -> Result<T, TutorialError> {
+    serde_path_to_error::deserialize(serde_yaml::Deserializer::from_str(content)).map_err(|e| {
+        let path = e.path().to_string();
+        let inner = e.into_inner();
+        let line = inner.location().map(|location| location.line());
+        let path = (!path.is_empty() && path != ".").then_some(path);
+        TutorialError::ContentParse {
+            file: file.into(),
+            message: yaml_error_message(&inner.to_string(), path.as_deref()),
+            path,
+            line,
+        }
+    })
+}
+
+// serde_yaml のメッセージから、別に表示するパスと位置を除き、型の誤りを読みやすくする
+fn yaml_error_message(message: &str, path: Option<&str>) -> String {
+    let message = match message.rfind(" at line ") {
+        Some(at) if message[at..].contains(" column ") => &message[..at],
+        _ => message,
+    };
+    // serde_yaml が前に付けるパス（誤りの項目か、その親）
+    let message = match (path, message.split_once(": ")) {
+        (Some(path), Some((head, rest))) if path.starts_with(head) => rest,
+        _ => message,
+    };
+    let Some((found, expected)) = message
+        .strip_prefix("invalid type: ")
+        .and_then(|rest| rest.split_once(", expected "))
+    else {
+        return message.to_string();
+    };
+    let expected = match expected {
+        "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize" | "f32" | "f64" => "a number",
+        "a sequence" => "a list",
+        other if other.starts_with("struct ") => "a mapping",
+        other => other,
+    };
+    format!("expected {}, found {}", expected, found)
+}
+
+// 章が1つも読めなかった場合は、読めなかった理由の最初のものを返す
+pub fn no_content_error(loads: Vec<ChapterLoad>, fallback: &str) -> anyhow::Error {
+    loads
+        .into_iter()
+        .find_map(|load| match load {
+            ChapterLoad::Failed { error, .. } | ChapterLoad::FailedFile { error, .. } => Some(error),
+            _ => None,
+        })
+        .unwrap_or_else(|| anyhow::anyhow!("{}", fallback))
+}
+
 pub struct ContentLoader {
     chapters: Vec<ChapterData>,
 }
 
 impl ContentLoader {
-    pub fn new() -> Result<Self> {
+    // 読み込みの経過は表示せずに返す
+    pub fn load() -> Result<(Self, LoadReport)> {
+        Self::load_from(&paths::chapters_dir())
+    }
+
+    /// `chapters_dir` の `chapter_NN.yaml` を読み込む（経過は表示せずに [`LoadReport`] で返す）
+    pub fn load_from(chapters_dir: &Path) -> Result<(Self, LoadReport)> {
         let mut chapters = Vec::new();
+        let mut files = Vec::new();
+        let mut report = LoadReport::new("", "学習");
 
-        // 各章のYAMLファイルを読み込み
-        for chapter_num in 1..=8 {
-            let file_path = format!("data/chapters/chapter_{:02}.yaml", chapter_num);
-
-            if Path::new(&file_path).exists() {
-                match Self::load_chapter_file(&file_path) {
-                    Ok(chapter) => {
-                        println!(
-                            "✓ 第{}章を読み込みました: {}",
-                            chapter_num, chapter.chapter.title
-                        );
-                        chapters.push(chapter);
-                    }
-                    Err(e) => {
-                        eprintln!("⚠️ 第{}章の読み込みに失敗: {}", chapter_num, e);
-                    }
+        // 各章のYAMLファイルを読み込み（番号は連続していなくてもよい）
+        for chapter_num in chapter_file_numbers(chapters_dir, "chapter_") {
+            let file_path = chapters_dir.join(format!("chapter_{:02}.yaml", chapter_num)).display().to_string();
+
+            match Self::load_chapter_file(&file_path) {
+                Ok(chapter) => {
+                    report.chapters.push(ChapterLoad::Loaded {
+                        number: chapter_num,
+                        title: chapter.chapter.title.clone(),
+                    });
+                    chapters.push(chapter);
+                    files.push(file_path);
                 }
-            } else {
-                println!("⚠️ ファイルが見つかりません: {}", file_path);
+                Err(error) => report.chapters.push(ChapterLoad::Failed { number: chapter_num, error }),
             }
         }
+        let requirements: Vec<ChapterRequirement> = chapters
+            .iter()
+            .zip(&files)
+            .map(|(ch, file)| ChapterRequirement {
+                number: ch.chapter.number,
+                requires: &ch.chapter.requires,
+                file,
+            })
+            .collect();
+        validator::check_prerequisites(&requirements)?;
+        chapters.sort_by_key(|chapter| chapter.chapter.number);
 
         if chapters.is_empty() {
-            return Err(anyhow::anyhow!("学習コンテンツが見つかりませんでした"));
+            return Err(no_content_error(report.chapters, "学習コンテンツが見つかりませんでした"));
         }
+        report.total = chapters.len();
 
-        println!(
-            "📚 合計 {} 章の学習コンテンツを読み込みました",
-            chapters.len()
-        );
-
-        Ok(Self { chapters })
+        Ok((Self { chapters }, report))
     }
 
     fn load_chapter_file(file_path: &str) -> Result<ChapterData> {
         let content = fs::read_to_string(file_path)?;
-        let chapter: ChapterData = serde_yaml::from_str(&content)?;
-        Ok(chapter)
+        Ok(parse_yaml(&content, file_path)?)
     }
 
     pub fn get_chapter(&self, chapter_num: u8) -> Option<&ChapterData> {
@@ -89,17 +303,78 @@ impl ContentLoader {
             .find(|ch| ch.chapter.number == chapter_num)
     }
 
-    pub fn get_chapter_count(&self) -> usize {
-        self.chapters.len()
+    pub fn chapter_numbers(&self) -> Vec<u8> {
+        self.chapters.iter().map(|ch| ch.chapter.number).collect()
+    }
+
+    pub fn chapters(&self) -> &[ChapterData] {
+        &self.chapters
+    }
+
+    // 絞り込み条件に合う章の番号
+    pub fn filtered_chapter_numbers(&self, filter: Option<&ChapterFilter>) -> Vec<u8> {
+        self.chapters
+            .iter()
+            .filter(|ch| filter.is_none_or(|f| f.matches(&ch.chapter.difficulty, &ch.chapter.tags)))
+            .map(|ch| ch.chapter.number)
+            .collect()
+    }
+
+    // 指定した章の、まだ完了していない前提の章
+    pub fn unmet_prerequisites(&self, chapter_num: u8, is_complete: &dyn Fn(u8) -> bool) -> Vec<u8> {
+        self.get_chapter(chapter_num)
+            .map(|ch| menu::unmet_prerequisites(&ch.chapter.requires, is_complete))
+            .unwrap_or_default()
     }
 
-    pub fn list_chapters(&self) {
-        println!("\n=== 利用可能な章 ===");
+    // 矢印キーで選ぶ章の一覧（completed は完了の印、is_complete は前提条件の判定に使う）
+    pub fn menu_lines(
+        &self,
+        filter: Option<&ChapterFilter>,
+        is_complete: &dyn Fn(u8) -> bool,
+        completed: &dyn Fn(u8) -> bool,
+    ) -> Vec<menu::ChapterLine> {
+        self.chapters
+            .iter()
+            .filter(|chapter| filter.is_none_or(|f| f.matches(&chapter.chapter.difficulty, &chapter.chapter.tags)))
+            .map(|chapter| {
+                let info = &chapter.chapter;
+                menu::ChapterLine {
+                    number: info.number,
+                    title: info.title.clone(),
+                    completed: completed(info.number),
+                    unmet: menu::unmet_prerequisites(&info.requires, is_complete),
+                    details: menu::format_chapter_meta(info.difficulty, info.estimated_minutes, &info.tags)
+                        .into_iter()
+                        .chain([info.description.clone(), format!("練習問題数: {}", chapter.exercises.len())])
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn list_chapters(&self, filter: Option<&ChapterFilter>, is_complete: &dyn Fn(u8) -> bool) {
+        say!("\n=== 利用可能な章 ===");
+        if let Some(filter) = filter {
+            say!("🔎 絞り込み: {}", filter.describe());
+        }
         for chapter in &self.chapters {
-            println!("第{}章: {}", chapter.chapter.number, chapter.chapter.title);
-            println!("  {}", chapter.chapter.description);
-            println!("  練習問題数: {}", chapter.exercises.len());
-            println!();
+            let info = &chapter.chapter;
+            if filter.is_some_and(|f| !f.matches(&info.difficulty, &info.tags)) {
+                continue;
+            }
+            let unmet = menu::unmet_prerequisites(&info.requires, is_complete);
+            let lock = if unmet.is_empty() { "" } else { "🔒 " };
+            say!("{}第{}章: {}", lock, info.number, info.title);
+            if !unmet.is_empty() {
+                say!("  {}", menu::lock_note(&unmet));
+            }
+            if let Some(meta) = menu::format_chapter_meta(info.difficulty, info.estimated_minutes, &info.tags) {
+                say!("  {}", meta);
+            }
+            say!("  {}", info.description);
+            say!("  練習問題数: {}", chapter.exercises.len());
+            say!();
         }
     }
 }
@@ -1,82 +1,325 @@
+use crate::content::{self, ChapterLoad, Difficulty, LoadReport, chapter_file_numbers};
+// 章の情報は通常版と同じ形式
+pub use crate::content::ChapterInfo;
 use crate::continuous_session::ContinuousExercise;
+use crate::menu::{self, ChapterFilter};
+use crate::output::info;
+use crate::packs;
+use crate::paths;
+use crate::theme::say;
+use crate::validator::{self, ChapterRequirement};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ContinuousChapterData {
     pub chapter: ChapterInfo,
     pub continuous_exercises: Vec<ContinuousExercise>,
+    // 練習の合間に端末で読む解説のページ
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lessons: Vec<LessonPage>,
 }
 
+// 解説の1ページ（body は見出し "# "・箇条書き "- " ・``` で囲むコードだけを解釈する）
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct ChapterInfo {
-    pub number: u8,
+pub struct LessonPage {
     pub title: String,
-    pub description: String,
+    pub body: String,
+    // この番号（1始まり）の練習の前に表示する。無ければ章の最初
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<usize>,
+}
+
+impl LessonPage {
+    // 表示する練習（0始まり）
+    pub fn exercise_index(&self) -> usize {
+        self.before.unwrap_or(1).saturating_sub(1)
+    }
+}
+
+impl ContinuousChapterData {
+    // 章データの内容のハッシュ（練習ごとのハッシュをまとめたもの。解説のページは含めない）
+    pub fn content_hash(&self) -> String {
+        let hashes: Vec<String> = self.continuous_exercises.iter().map(ContinuousExercise::content_hash).collect();
+        crate::continuous_session::fnv1a_hex(&hashes.join("\n"))
+    }
+
+    // exercise_index の練習を始める前に表示するページ
+    pub fn lessons_before(&self, exercise_index: usize) -> Vec<&LessonPage> {
+        self.lessons
+            .iter()
+            .filter(|page| page.exercise_index() == exercise_index)
+            .collect()
+    }
+}
+
+/// 複数の章を1つのファイルに書く形式（`chapters:` に1章の形式と同じものを並べる）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChapterBundle {
+    pub chapters: Vec<ContinuousChapterData>,
+}
+
+pub const BUNDLE_PREFIX: &str = "continuous_bundle_";
+
+// 章のまとめのファイル（continuous_bundle_*.yaml）のパス（ファイル名順）
+pub fn bundle_files(dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| {
+                    entry
+                        .file_name()
+                        .to_str()
+                        .is_some_and(|name| name.starts_with(BUNDLE_PREFIX) && name.ends_with(".yaml"))
+                })
+                .map(|entry| entry.path().display().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+// トップレベルに chapter が無く chapters がある文書を章のまとめとして読む
+// （YAML として読めない文書は1章の形式として読み、その誤りを報告する）
+fn is_bundle(content: &str) -> bool {
+    serde_yaml::from_str::<serde_yaml::Value>(content).is_ok_and(|value| {
+        value.get("chapter").is_none() && value.get("chapters").is_some()
+    })
 }
 
 pub struct ContinuousContentLoader {
     chapters: Vec<ContinuousChapterData>,
+    packs: BTreeMap<u8, String>, // パックから読んだ章の番号と、パックの名前
 }
 
 impl ContinuousContentLoader {
     pub fn empty() -> Self {
-        Self { chapters: vec![] }
+        Self {
+            chapters: vec![],
+            packs: BTreeMap::new(),
+        }
     }
 
-    pub fn new() -> Result<Self> {
-        let mut chapters = Vec::new();
+    // 読み込みの経過と章データの問題は表示せずに返す
+    pub fn load() -> Result<(Self, LoadReport)> {
+        Self::load_from(&paths::chapters_dir())
+    }
+
+    /// `chapters_dir` の `continuous_chapter_NN.yaml` を番号順に、続けて章のまとめ
+    /// （`continuous_bundle_*.yaml`。トップレベルが `chapters:` の一覧）をファイル名順に読み込む。
+    /// どちらのファイルにも1章の形式とまとめの形式のどちらで書いてもよい。
+    /// 最後に install したパック（`packs/<名前>/` の同じ名前の規則のファイル）をパックの名前順に読む。
+    /// 同じ番号の章が2か所にあると、どちらを使うかは決めずに読み込みのエラーにする。
+    /// 経過と章データの問題（[`validator::validate_chapter`] の警告）は表示せずに [`LoadReport`] で返す
+    pub fn load_from(chapters_dir: &Path) -> Result<(Self, LoadReport)> {
+        Self::load_tree(chapters_dir, None)
+    }
 
-        // 連続学習用の章ファイルを読み込み
-        for chapter_num in 1..=8 {
-            let file_path = format!("data/chapters/continuous_chapter_{:02}.yaml", chapter_num);
+    // staged はインストールする前に検証するパック（名前と置き場所）。
+    // 同じ名前のインストール済みのパックの代わりに読む
+    #[doc(hidden)]
+    pub fn load_tree(chapters_dir: &Path, staged: Option<(&str, &Path)>) -> Result<(Self, LoadReport)> {
+        // 読み込んだ章と、その読み込み元（"course.yaml（chapters[1]）" など。重複の報告に使う）
+        let mut loaded: Vec<(String, ContinuousChapterData)> = Vec::new();
+        let mut report = LoadReport::new("（連続学習版）", "連続学習");
+
+        // 連続学習用の章ファイルを読み込み（番号は連続していなくてもよい）
+        let mut chapter_nums = chapter_file_numbers(chapters_dir, "continuous_chapter_");
+        chapter_nums.extend(chapter_file_numbers(chapters_dir, "chapter_"));
+        chapter_nums.sort_unstable();
+        chapter_nums.dedup();
+        for chapter_num in chapter_nums {
+            let file_path = chapters_dir
+                .join(format!("continuous_chapter_{:02}.yaml", chapter_num))
+                .display()
+                .to_string();
 
             if Path::new(&file_path).exists() {
                 match Self::load_chapter_file(&file_path) {
-                    Ok(chapter) => {
-                        println!(
-                            "✓ 第{}章（連続学習版）を読み込みました: {}",
-                            chapter_num, chapter.chapter.title
-                        );
-                        chapters.push(chapter);
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "⚠️ 第{}章（連続学習版）の読み込みに失敗: {}",
-                            chapter_num, e
-                        );
-                    }
+                    Ok(chapters) => Self::add_chapters(&mut loaded, &mut report, chapters),
+                    Err(error) => report.chapters.push(ChapterLoad::Failed { number: chapter_num, error }),
                 }
             } else {
                 // 従来形式からの自動変換を試みる
-                let legacy_file_path = format!("data/chapters/chapter_{:02}.yaml", chapter_num);
-                if Path::new(&legacy_file_path).exists() {
-                    println!(
-                        "🔄 第{}章を従来形式から連続学習形式に変換中...",
-                        chapter_num
-                    );
+                if chapters_dir.join(format!("chapter_{:02}.yaml", chapter_num)).exists() {
+                    report.chapters.push(ChapterLoad::Legacy { number: chapter_num });
                     // TODO: 実装する場合はここで変換処理を行う
                 }
             }
         }
 
-        if chapters.is_empty() {
-            return Err(anyhow::anyhow!("連続学習コンテンツが見つかりませんでした"));
+        // 章のまとめは番号の付いた章ファイルの後に読む（番号の無いファイルは読めなくても章番号で報告できない）
+        for file_path in bundle_files(chapters_dir) {
+            match Self::load_chapter_file(&file_path) {
+                Ok(chapters) => Self::add_chapters(&mut loaded, &mut report, chapters),
+                Err(error) => report.chapters.push(ChapterLoad::FailedFile { file: file_path, error }),
+            }
         }
+        let mut pack_dirs: Vec<(String, PathBuf)> = packs::installed(chapters_dir)
+            .into_iter()
+            .filter(|pack| staged.is_none_or(|(name, _)| pack.name != name))
+            .map(|pack| {
+                let dir = packs::pack_dir(chapters_dir, &pack.name);
+                (pack.name, dir)
+            })
+            .chain(staged.map(|(name, dir)| (name.to_string(), dir.to_path_buf())))
+            .collect();
+        pack_dirs.sort();
+        let mut pack_of = BTreeMap::new();
+        for (name, dir) in pack_dirs {
+            let start = loaded.len();
+            let numbered = chapter_file_numbers(&dir, "continuous_chapter_")
+                .into_iter()
+                .map(|number| dir.join(format!("continuous_chapter_{:02}.yaml", number)).display().to_string());
+            for file_path in numbered.chain(bundle_files(&dir)) {
+                match Self::load_chapter_file(&file_path) {
+                    Ok(chapters) => Self::add_chapters(&mut loaded, &mut report, chapters),
+                    Err(error) => report.chapters.push(ChapterLoad::FailedFile { file: file_path, error }),
+                }
+            }
+            pack_of.extend(loaded[start..].iter().map(|(_, ch)| (ch.chapter.number, name.clone())));
+        }
+        // まとめやパックで読めた章は、従来形式からの変換の対象にしない
+        report.chapters.retain(|load| match load {
+            ChapterLoad::Legacy { number } => !loaded.iter().any(|(_, ch)| ch.chapter.number == *number),
+            _ => true,
+        });
 
-        println!(
-            "📚 合計 {} 章の連続学習コンテンツを読み込みました",
-            chapters.len()
-        );
+        let requirements: Vec<ChapterRequirement> = loaded
+            .iter()
+            .map(|(source, ch)| ChapterRequirement {
+                number: ch.chapter.number,
+                requires: &ch.chapter.requires,
+                file: source,
+            })
+            .collect();
+        validator::check_duplicate_chapters(&requirements)?;
+        validator::check_prerequisites(&requirements)?;
+        let mut chapters: Vec<ContinuousChapterData> = loaded.into_iter().map(|(_, chapter)| chapter).collect();
+        chapters.sort_by_key(|chapter| chapter.chapter.number);
 
-        Ok(Self { chapters })
+        if chapters.is_empty() {
+            return Err(content::no_content_error(
+                report.chapters,
+                "連続学習コンテンツが見つかりませんでした",
+            ));
+        }
+        report.total = chapters.len();
+
+        Ok((Self { chapters, packs: pack_of }, report))
     }
 
-    fn load_chapter_file(file_path: &str) -> Result<ContinuousChapterData> {
+    fn load_chapter_file(file_path: &str) -> Result<Vec<(String, ContinuousChapterData)>> {
         let content = fs::read_to_string(file_path)?;
-        let chapter: ContinuousChapterData = serde_yaml::from_str(&content)?;
+        Self::parse_sources(&content, file_path)
+    }
+
+    fn add_chapters(
+        loaded: &mut Vec<(String, ContinuousChapterData)>,
+        report: &mut LoadReport,
+        chapters: Vec<(String, ContinuousChapterData)>,
+    ) {
+        for (source, chapter) in chapters {
+            let number = chapter.chapter.number;
+            report.chapters.push(ChapterLoad::Loaded {
+                number,
+                title: chapter.chapter.title.clone(),
+            });
+            for message in validator::validate_chapter(&chapter) {
+                report.chapters.push(ChapterLoad::Issue { number, message });
+            }
+            loaded.push((source, chapter));
+        }
+    }
+
+    /// 章ファイルの内容を読む（`file` はエラーに付けるファイル名）。
+    /// 章データの問題は [`validator::validate_chapter`] で調べる
+    ///
+    /// ```
+    /// use vim_tutorial_nvim::continuous_content::ContinuousContentLoader;
+    ///
+    /// let yaml = r#"
+    /// chapter:
+    ///   number: 1
+    ///   title: "移動"
+    ///   description: "hjkl で移動します"
+    /// continuous_exercises:
+    ///   - title: "右へ"
+    ///     description: "l で右に移動します"
+    ///     flow_type: "sequential"
+    ///     sample_code: ["hello"]
+    ///     goals:
+    ///       - type: "position"
+    ///         target: [0, 4]
+    ///         description: "行末の 'o' へ"
+    /// "#;
+    /// let chapter = ContinuousContentLoader::parse_chapter(yaml, "chapter.yaml").unwrap();
+    /// assert_eq!(chapter.continuous_exercises[0].goals.len(), 1);
+    /// assert!(ContinuousContentLoader::parse_chapter("chapter: [", "broken.yaml").is_err());
+    /// ```
+    pub fn parse_chapter(content: &str, file: &str) -> Result<ContinuousChapterData> {
+        let chapter: ContinuousChapterData = content::parse_yaml(content, file)?;
+        Self::prepare(chapter, file, "")
+    }
+
+    /// 1章の形式と章のまとめ（トップレベルが `chapters:` の一覧）のどちらでも読み、書かれた順の章を返す
+    ///
+    /// ```
+    /// use vim_tutorial_nvim::continuous_content::ContinuousContentLoader;
+    ///
+    /// let yaml = r#"
+    /// chapters:
+    ///   - chapter: { number: 1, title: "移動", description: "hjkl" }
+    ///     continuous_exercises: []
+    ///   - chapter: { number: 2, title: "単語", description: "w と b" }
+    ///     continuous_exercises: []
+    /// "#;
+    /// let chapters = ContinuousContentLoader::parse_chapters(yaml, "course.yaml").unwrap();
+    /// assert_eq!(chapters.iter().map(|ch| ch.chapter.number).collect::<Vec<_>>(), [1, 2]);
+    /// ```
+    pub fn parse_chapters(content: &str, file: &str) -> Result<Vec<ContinuousChapterData>> {
+        Ok(Self::parse_sources(content, file)?.into_iter().map(|(_, chapter)| chapter).collect())
+    }
+
+    // 章と読み込み元（まとめの中の章は "file（chapters[i]）"）
+    fn parse_sources(content: &str, file: &str) -> Result<Vec<(String, ContinuousChapterData)>> {
+        if !is_bundle(content) {
+            return Ok(vec![(file.to_string(), Self::parse_chapter(content, file)?)]);
+        }
+        let bundle: ChapterBundle = content::parse_yaml(content, file)?;
+        bundle
+            .chapters
+            .into_iter()
+            .enumerate()
+            .map(|(index, chapter)| {
+                let path = format!("chapters[{}].", index);
+                Ok((format!("{}（chapters[{}]）", file, index), Self::prepare(chapter, file, &path)?))
+            })
+            .collect()
+    }
+
+    // 読み込んだ直後の章の仕上げ（path は誤りの項目のパスの前に付ける "chapters[1]." など）
+    fn prepare(mut chapter: ContinuousChapterData, file: &str, path: &str) -> Result<ContinuousChapterData> {
+        // 文字列で書いた位置は、読み込んだ時点のサンプルコードの中の行・列にする
+        for (index, exercise) in chapter.continuous_exercises.iter_mut().enumerate() {
+            if let Some(issue) = exercise.resolve_anchors().into_iter().next() {
+                return Err(crate::error::TutorialError::ContentParse {
+                    file: file.into(),
+                    path: Some(format!("{}continuous_exercises[{}]", path, index)),
+                    line: None,
+                    message: format!("練習「{}」の{}", exercise.title, issue),
+                }
+                .into());
+            }
+        }
+        for goal in chapter.continuous_exercises.iter_mut().flat_map(|exercise| &mut exercise.goals) {
+            goal.fill_description();
+        }
         Ok(chapter)
     }
 
@@ -86,38 +329,131 @@ impl ContinuousContentLoader {
             .find(|ch| ch.chapter.number == chapter_num)
     }
 
-    pub fn get_chapter_count(&self) -> usize {
-        self.chapters.len()
+    pub fn chapter_numbers(&self) -> Vec<u8> {
+        self.chapters.iter().map(|ch| ch.chapter.number).collect()
+    }
+
+    // 章を読んだパックの名前（packs/ 以外から読んだ章は None）
+    pub fn pack_of(&self, chapter_num: u8) -> Option<&str> {
+        self.packs.get(&chapter_num).map(String::as_str)
+    }
+
+    pub fn chapters(&self) -> &[ContinuousChapterData] {
+        &self.chapters
+    }
+
+    // 絞り込み条件に合う章の番号
+    pub fn filtered_chapter_numbers(&self, filter: Option<&ChapterFilter>) -> Vec<u8> {
+        self.chapters
+            .iter()
+            .filter(|ch| filter.is_none_or(|f| f.matches(&ch.chapter.difficulty, &ch.chapter.tags)))
+            .map(|ch| ch.chapter.number)
+            .collect()
     }
 
-    pub fn list_chapters(&self) {
-        println!("\n=== 利用可能な章（連続学習版） ===");
+    // 指定した章の、まだ完了していない前提の章
+    pub fn unmet_prerequisites(&self, chapter_num: u8, is_complete: &dyn Fn(u8) -> bool) -> Vec<u8> {
+        self.get_chapter(chapter_num)
+            .map(|ch| menu::unmet_prerequisites(&ch.chapter.requires, is_complete))
+            .unwrap_or_default()
+    }
+
+    // 矢印キーで選ぶ章の一覧（completed は完了の印、is_complete は前提条件の判定に使う）
+    pub fn menu_lines(
+        &self,
+        filter: Option<&ChapterFilter>,
+        is_complete: &dyn Fn(u8) -> bool,
+        completed: &dyn Fn(u8) -> bool,
+    ) -> Vec<menu::ChapterLine> {
+        self.chapters
+            .iter()
+            .filter(|chapter| filter.is_none_or(|f| f.matches(&chapter.chapter.difficulty, &chapter.chapter.tags)))
+            .map(|chapter| {
+                let info = &chapter.chapter;
+                menu::ChapterLine {
+                    number: info.number,
+                    title: info.title.clone(),
+                    completed: completed(info.number),
+                    unmet: menu::unmet_prerequisites(&info.requires, is_complete),
+                    details: menu::format_chapter_meta(info.difficulty, info.estimated_minutes, &info.tags)
+                        .into_iter()
+                        .chain([info.description.clone(), format!("連続練習問題数: {}", chapter.continuous_exercises.len())])
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn list_chapters(&self, filter: Option<&ChapterFilter>, is_complete: &dyn Fn(u8) -> bool) {
+        say!("\n=== 利用可能な章（連続学習版） ===");
+        if let Some(filter) = filter {
+            say!("🔎 絞り込み: {}", filter.describe());
+        }
         for chapter in &self.chapters {
-            println!("第{}章: {}", chapter.chapter.number, chapter.chapter.title);
-            println!("  {}", chapter.chapter.description);
-            println!("  連続練習問題数: {}", chapter.continuous_exercises.len());
+            let info = &chapter.chapter;
+            if filter.is_some_and(|f| !f.matches(&info.difficulty, &info.tags)) {
+                continue;
+            }
+            let unmet = menu::unmet_prerequisites(&info.requires, is_complete);
+            let lock = if unmet.is_empty() { "" } else { "🔒 " };
+            say!("{}第{}章: {}", lock, info.number, info.title);
+            if let Some(pack) = self.pack_of(info.number) {
+                say!("  📦 パック: {}", pack);
+            }
+            if !unmet.is_empty() {
+                say!("  {}", menu::lock_note(&unmet));
+            }
+            if let Some(meta) = menu::format_chapter_meta(info.difficulty, info.estimated_minutes, &info.tags) {
+                say!("  {}", meta);
+            }
+            say!("  {}", info.description);
+            say!("  連続練習問題数: {}", chapter.continuous_exercises.len());
 
             // 各練習の概要を表示
             for (i, exercise) in chapter.continuous_exercises.iter().enumerate() {
-                println!(
+                say!(
                     "    {}. {} (目標数: {})",
                     i + 1,
                     exercise.title,
                     exercise.goals.len()
                 );
             }
-            println!();
+            say!();
         }
     }
 
     // デバッグ用：サンプル章を生成
     pub fn create_sample_chapter(&self, output_path: &str) -> Result<()> {
-        let sample_chapter = ContinuousChapterData {
+        let yaml_content = serde_yaml::to_string(&Self::sample_chapter())?;
+        fs::write(output_path, yaml_content)?;
+        info!("📝 サンプル章を作成しました: {}", output_path);
+
+        Ok(())
+    }
+
+    // --generate-sample PATH --bundle で書き出す、2章の章のまとめ（まとめの書き方の見本）
+    pub fn create_sample_bundle(&self, output_path: &str) -> Result<()> {
+        let bundle = ChapterBundle {
+            chapters: ["motions", "words"].into_iter().filter_map(crate::sample_chapters::generate).collect(),
+        };
+        fs::write(output_path, serde_yaml::to_string(&bundle)?)?;
+        info!("📝 サンプルの章のまとめを作成しました: {}", output_path);
+
+        Ok(())
+    }
+
+    // --generate-sample で書き出すサンプル章
+    pub fn sample_chapter() -> ContinuousChapterData {
+        ContinuousChapterData {
             chapter: ChapterInfo {
                 number: 1,
                 title: "基本移動とモード切替".to_string(),
                 description: "Vimの基本的なカーソル移動とモード切替を連続して学習します"
                     .to_string(),
+                difficulty: Some(Difficulty::Beginner),
+                estimated_minutes: Some(10),
+                tags: vec!["motion".to_string(), "mode".to_string()],
+                requires: Vec::new(),
             },
             continuous_exercises: vec![
                 ContinuousExercise {
@@ -134,27 +470,35 @@ impl ContinuousContentLoader {
                             target: serde_json::json!([0, 3]),
                             description: "右に3文字移動してください（lll）".to_string(),
                             hint: Some("l キーを3回押します".to_string()),
+                            ..Default::default()
                         },
                         crate::continuous_session::ExerciseGoal {
                             goal_type: "position".to_string(),
                             target: serde_json::json!([1, 3]),
                             description: "下の行の同じ位置に移動してください（j）".to_string(),
                             hint: Some("j キーで下に移動します".to_string()),
+                            ..Default::default()
                         },
                         crate::continuous_session::ExerciseGoal {
                             goal_type: "position".to_string(),
                             target: serde_json::json!([1, 0]),
                             description: "行の最初に戻ってください（hhh）".to_string(),
                             hint: Some("h キーで左に移動します".to_string()),
+                            ..Default::default()
                         },
                         crate::continuous_session::ExerciseGoal {
                             goal_type: "position".to_string(),
                             target: serde_json::json!([0, 0]),
                             description: "最初の行に戻ってください（k）".to_string(),
                             hint: Some("k キーで上に移動します".to_string()),
+                            ..Default::default()
                         },
                     ],
-                    flow_type: crate::continuous_session::FlowType::Sequential,
+                    reference: vec![
+                        "h ← 左へ    l → 右へ".to_string(),
+                        "j ↓ 下へ    k ↑ 上へ".to_string(),
+                    ],
+                    ..Default::default()
                 },
                 ContinuousExercise {
                     title: "モード切替とテキスト入力".to_string(),
@@ -171,30 +515,35 @@ impl ContinuousContentLoader {
                             target: serde_json::json!([1, 20]),
                             description: "2行目の'Hello, 'の後に移動してください".to_string(),
                             hint: Some("jで下に移動し、lで右に移動します".to_string()),
+                            ..Default::default()
                         },
                         crate::continuous_session::ExerciseGoal {
                             goal_type: "mode".to_string(),
                             target: serde_json::json!("insert"),
                             description: "Insertモードに入ってください（i）".to_string(),
                             hint: Some("i キーでInsertモードに入ります".to_string()),
+                            ..Default::default()
                         },
                         crate::continuous_session::ExerciseGoal {
                             goal_type: "text".to_string(),
                             target: serde_json::json!({
                                 "line": 1,
-                                "expected": "  console.log('Hello, ' + name);"
+                                "expected": "  console.log('Hello, ' + name);",
+                                "normalize": ["width", "quotes"]
                             }),
                             description: "' + name'を入力してください".to_string(),
                             hint: Some("通常通りタイピングします".to_string()),
+                            ..Default::default()
                         },
                         crate::continuous_session::ExerciseGoal {
                             goal_type: "mode".to_string(),
                             target: serde_json::json!("normal"),
                             description: "Escキーでノーマルモードに戻ってください".to_string(),
                             hint: Some("Esc キーでモードを切り替えます".to_string()),
+                            ..Default::default()
                         },
                     ],
-                    flow_type: crate::continuous_session::FlowType::Sequential,
+                    ..Default::default()
                 },
                 ContinuousExercise {
                     title: "削除とヤンク操作".to_string(),
@@ -209,6 +558,7 @@ impl ContinuousContentLoader {
                             target: serde_json::json!([0, 13]),
                             description: "1行目の'Alice'の位置に移動してください".to_string(),
                             hint: None,
+                            ..Default::default()
                         },
                         crate::continuous_session::ExerciseGoal {
                             goal_type: "mode".to_string(),
@@ -217,43 +567,188 @@ impl ContinuousContentLoader {
                             hint: Some(
                                 "d キーを押してoperator-pendingモードに入ります".to_string(),
                             ),
+                            ..Default::default()
                         },
                         crate::continuous_session::ExerciseGoal {
                             goal_type: "register".to_string(),
+                            // 削除したテキストは無名レジスタに入る。前後の空白は無視して比べる
                             target: serde_json::json!({
-                                "register": "0",
-                                "expected": "Alice"
+                                "register": "\"",
+                                "expected": "Alice",
+                                "match": "trimmed"
                             }),
                             description: "単語を削除してヤンクしてください（diw）".to_string(),
                             hint: Some("iw で inner word を指定します".to_string()),
+                            ..Default::default()
                         },
                         crate::continuous_session::ExerciseGoal {
                             goal_type: "position".to_string(),
                             target: serde_json::json!([1, 13]),
                             description: "2行目の'Bob'の位置に移動してください".to_string(),
                             hint: None,
+                            ..Default::default()
                         },
                         crate::continuous_session::ExerciseGoal {
                             goal_type: "text".to_string(),
                             target: serde_json::json!({
                                 "line": 1,
-                                "expected": "const new_name = 'Alice';"
+                                "expected": "const new_name = 'Alice';",
+                                "normalize": ["width", "quotes"]
                             }),
                             description: "'Bob'を削除して'Alice'をペーストしてください（ciwp）"
                                 .to_string(),
                             hint: Some("ciw で単語を変更、p でペーストします".to_string()),
+                            ..Default::default()
                         },
                     ],
-                    flow_type: crate::continuous_session::FlowType::Sequential,
+                    ..Default::default()
+                },
+                ContinuousExercise {
+                    title: "置換モードで上書き".to_string(),
+                    description: "R で文字を上書きして書き換えます".to_string(),
+                    sample_code: vec!["let color = 'red';".to_string()],
+                    goals: vec![
+                        crate::continuous_session::ExerciseGoal {
+                            goal_type: "position".to_string(),
+                            target: serde_json::json!([0, 13]),
+                            description: "'red' の 'r' に移動してください".to_string(),
+                            hint: Some("f r で 'r' へ移動します".to_string()),
+                            ..Default::default()
+                        },
+                        crate::continuous_session::ExerciseGoal {
+                            goal_type: "mode".to_string(),
+                            target: serde_json::json!("replace"),
+                            description: "置換モードに入ってください（R）".to_string(),
+                            hint: Some("R キーで入力した文字が既存の文字を上書きします".to_string()),
+                            ..Default::default()
+                        },
+                        crate::continuous_session::ExerciseGoal {
+                            goal_type: "text".to_string(),
+                            target: serde_json::json!({
+                                "line": 0,
+                                "expected": "let color = 'tan';",
+                                "normalize": ["width", "quotes"]
+                            }),
+                            description: "'red' を 'tan' に上書きしてください".to_string(),
+                            hint: Some("そのまま tan と入力します".to_string()),
+                            ..Default::default()
+                        },
+                        crate::continuous_session::ExerciseGoal {
+                            goal_type: "mode".to_string(),
+                            target: serde_json::json!("normal"),
+                            description: "ノーマルモードに戻ってください（Esc）".to_string(),
+                            hint: None,
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                ContinuousExercise {
+                    title: "名前付きレジスタから貼り付け".to_string(),
+                    description: "レジスタ a に入っている文字列を貼り付けます".to_string(),
+                    sample_code: vec!["const greeting = '';".to_string()],
+                    goals: vec![crate::continuous_session::ExerciseGoal {
+                        goal_type: "text".to_string(),
+                        target: serde_json::json!({
+                            "line": 0,
+                            "expected": "const greeting = 'Hello';",
+                            "normalize": ["width", "quotes"]
+                        }),
+                        description: "レジスタ a の内容を '' の間に貼り付けてください"
+                            .to_string(),
+                        hint: Some(
+                            "\"ap でレジスタ a の内容をカーソルの後ろに貼り付けます".to_string(),
+                        ),
+                        solution_keys: Some("\"ap".to_string()),
+                        ..Default::default()
+                    }],
+                    // レジスタ a に文字列を入れ、カーソルを最初の ' に置いておく
+                    setup: vec![
+                        "let @a = 'Hello'".to_string(),
+                        "call cursor(1, 18)".to_string(),
+                    ],
+                    teardown: vec!["let @a = ''".to_string()],
+                    ..Default::default()
+                },
+                ContinuousExercise {
+                    title: ":s で一括置換".to_string(),
+                    description: ":%s でファイル全体の単語をまとめて置き換えます".to_string(),
+                    sample_code: vec![
+                        "let foo = 1;".to_string(),
+                        "let total = foo + foo;".to_string(),
+                    ],
+                    goals: vec![crate::continuous_session::ExerciseGoal {
+                        goal_type: "substitution".to_string(),
+                        // 置き換える前の数は目標が有効になった時点でエディタに数えさせる
+                        target: serde_json::json!({
+                            "pattern": "foo",
+                            "replacement": "bar",
+                            "min_count": 3
+                        }),
+                        description: "3つの foo を全て bar に置き換えてください（:%s/foo/bar/g）"
+                            .to_string(),
+                        hint: Some(
+                            "% で全ての行を、末尾の g で行の中の全ての foo を置き換えます".to_string(),
+                        ),
+                        solution_keys: Some(":%s/foo/bar/g<CR>".to_string()),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                ContinuousExercise {
+                    title: ". で変更を繰り返す".to_string(),
+                    description: "1回の変更を . で2回繰り返し、3行の末尾に ; を付けます".to_string(),
+                    sample_code: vec![
+                        "let a = 1".to_string(),
+                        "let b = 2".to_string(),
+                        "let c = 3".to_string(),
+                    ],
+                    goals: vec![
+                        crate::continuous_session::ExerciseGoal {
+                            goal_type: "text_block".to_string(),
+                            target: serde_json::json!({
+                                "start_line": 0,
+                                "expected": ["let a = 1;", "let b = 2;", "let c = 3;"],
+                                "normalize": ["width", "quotes"]
+                            }),
+                            description: "3行の末尾に ; を付けてください".to_string(),
+                            hint: Some("A; で1行目に付けたら、<Esc> の後 j で下の行へ".to_string()),
+                            solution_keys: Some("A;<Esc>j.j.".to_string()),
+                            ..Default::default()
+                        },
+                        crate::continuous_session::ExerciseGoal {
+                            goal_type: "dot_repeat".to_string(),
+                            // 変更が無いまま押した . は数えない
+                            target: serde_json::json!({"min_times": 2}),
+                            description: "2行目と3行目は . で付けてください".to_string(),
+                            hint: Some(". は直前の変更（A; の入力）をもう一度行います".to_string()),
+                            keys: vec![".".to_string()],
+                            ..Default::default()
+                        },
+                    ],
+                    flow_type: crate::continuous_session::FlowType::AnyOrder,
+                    ..Default::default()
                 },
             ],
-        };
-
-        let yaml_content = serde_yaml::to_string(&sample_chapter)?;
-        fs::write(output_path, yaml_content)?;
-        println!("📝 サンプル章を作成しました: {}", output_path);
-
-        Ok(())
+            lessons: vec![LessonPage {
+                title: "モードで編集する".to_string(),
+                body: [
+                    "Vim はキーの意味がモードで変わる「モード型」のエディタです。",
+                    "",
+                    "- Normal モード: 移動・削除・コピーなどのコマンドを打つ（起動直後はここ）",
+                    "- Insert モード: 打った文字がそのまま入力される",
+                    "",
+                    "# 切り替え方",
+                    "```",
+                    "i     カーソルの前から入力を始める",
+                    "Esc   Normal モードに戻る",
+                    "```",
+                    "迷ったら Esc を押して Normal モードに戻りましょう。",
+                ]
+                .join("\n"),
+                before: Some(2),
+            }],
+        }
     }
 }
 
@@ -262,12 +757,152 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    const TERSE_CHAPTER: &str = r#"
+chapter:
+  number: 9
+  title: "省略形"
+  description: "省略できる項目を省いた章"
+continuous_exercises:
+  - title: "移動"
+    description: "j と l で移動します"
+    sample_code: |
+      first line
+      second line
+      third line
+    goals:
+      - type: "position"
+        target: [2, 1]
+      - type: "mode"
+        target: "insert"
+        description: "i で入力を始める"
+        hint: "i を押します"
+"#;
+
+    const VERBOSE_CHAPTER: &str = r#"
+chapter:
+  number: 9
+  title: "省略形"
+  description: "省略できる項目を省いた章"
+continuous_exercises:
+  - title: "移動"
+    description: "j と l で移動します"
+    flow_type: "sequential"
+    sample_code:
+      - "first line"
+      - "second line"
+      - "third line"
+    goals:
+      - type: "position"
+        target: [2, 1]
+        description: "カーソルを3行2列へ"
+        hint: null
+      - type: "mode"
+        target: "insert"
+        description: "i で入力を始める"
+        hint: "i を押します"
+"#;
+
+    // 書き出して読み直しても同じ YAML になる
+    fn round_trip(chapter: &ContinuousChapterData) -> Result<String> {
+        let yaml = serde_yaml::to_string(chapter)?;
+        let reparsed = ContinuousContentLoader::parse_chapter(&yaml, "round_trip.yaml")?;
+        assert_eq!(serde_yaml::to_string(&reparsed)?, yaml);
+        Ok(yaml)
+    }
+
+    #[test]
+    fn test_terse_and_verbose_chapters_parse_identically() -> Result<()> {
+        let terse = ContinuousContentLoader::parse_chapter(TERSE_CHAPTER, "terse.yaml")?;
+        let exercise = &terse.continuous_exercises[0];
+        assert!(matches!(exercise.flow_type, crate::continuous_session::FlowType::Sequential));
+        assert_eq!(exercise.sample_code, vec!["first line", "second line", "third line"]);
+        assert_eq!(exercise.goals[0].description, "カーソルを3行2列へ");
+        assert!(exercise.goals[0].description_generated && !exercise.goals[1].description_generated);
+        assert_eq!(exercise.goals[0].hint, None);
+        assert_eq!(
+            validator::validate_chapter(&terse),
+            vec!["練習1「移動」: 目標1に description が無いため「カーソルを3行2列へ」と表示します".to_string()]
+        );
+
+        let verbose = ContinuousContentLoader::parse_chapter(VERBOSE_CHAPTER, "verbose.yaml")?;
+        assert!(validator::validate_chapter(&verbose).is_empty());
+        assert_eq!(round_trip(&terse)?, round_trip(&verbose)?);
+
+        // 全ての項目を書いた同梱の章も、書き出して読み直すと同じになる
+        let bundled = fs::read_to_string(Path::new(paths::BUNDLED_CHAPTERS_DIR).join("continuous_chapter_01.yaml"))?;
+        round_trip(&ContinuousContentLoader::parse_chapter(&bundled, "continuous_chapter_01.yaml")?)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_content_hash_ignores_formatting_but_not_goals() -> Result<()> {
+        let hash = |yaml: &str| -> Result<String> {
+            Ok(ContinuousContentLoader::parse_chapter(yaml, "hash.yaml")?.continuous_exercises[0].content_hash())
+        };
+        // 書き方が違うだけ（省いた既定値・行のリストとブロック・キーの順番）なら同じ
+        let terse = hash(TERSE_CHAPTER)?;
+        assert_eq!(terse.len(), 16);
+        assert_eq!(terse, hash(VERBOSE_CHAPTER)?);
+        let reordered = TERSE_CHAPTER.replace(
+            "      - type: \"position\"\n        target: [2, 1]",
+            "      - target: [2, 1]\n        type: \"position\"",
+        );
+        assert_ne!(reordered, TERSE_CHAPTER);
+        assert_eq!(terse, hash(&reordered)?);
+        // 目標の target が変われば別の内容
+        assert_ne!(terse, hash(&TERSE_CHAPTER.replace("target: [2, 1]", "target: [2, 2]"))?);
+        Ok(())
+    }
+
+    fn parse_error(yaml: &str) -> String {
+        let error = ContinuousContentLoader::parse_chapter(yaml, "continuous_chapter_03.yaml").unwrap_err();
+        error.downcast::<crate::error::TutorialError>().unwrap().cause()
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_field_and_line() {
+        // 型の誤り
+        let wrong_type = TERSE_CHAPTER.replace("number: 9", "number: \"3\"");
+        assert_eq!(
+            parse_error(&wrong_type),
+            "章データ continuous_chapter_03.yaml: chapter.number（3行目）: expected a number, found string \"3\""
+        );
+        // 必須の項目が無い
+        let missing = TERSE_CHAPTER.replace("    description: \"j と l で移動します\"\n", "");
+        assert!(
+            parse_error(&missing).starts_with("章データ continuous_chapter_03.yaml: continuous_exercises[0]（7行目）: missing field `description`"),
+            "{}",
+            parse_error(&missing)
+        );
+        // 知らない目標の type
+        let unknown = TERSE_CHAPTER.replace("type: \"mode\"", "type: \"modes\"");
+        assert!(
+            parse_error(&unknown).starts_with(
+                "章データ continuous_chapter_03.yaml: continuous_exercises[0].goals[1].type（16行目）: unknown goal type `modes`, expected one of position, mode,"
+            ),
+            "{}",
+            parse_error(&unknown)
+        );
+        // サンプルコードに無い目印
+        let anchor = TERSE_CHAPTER.replace(
+            "      - type: \"position\"\n        target: [2, 1]",
+            "      - type: \"position_at\"\n        target: { text: \"fourth\" }\n        description: \"4行目へ\"",
+        );
+        assert_eq!(
+            parse_error(&anchor),
+            "章データ continuous_chapter_03.yaml: continuous_exercises[0]: 練習「移動」の目標1「4行目へ」: 「fourth」がサンプルコードにありません"
+        );
+    }
+
     #[test]
     fn test_sample_chapter_creation() -> Result<()> {
         let tmp_dir = tempdir()?;
         let output_path = tmp_dir.path().join("test_chapter.yaml");
 
-        let loader = ContinuousContentLoader { chapters: vec![] };
+        let loader = ContinuousContentLoader {
+            chapters: vec![],
+            packs: BTreeMap::new(),
+        };
         loader.create_sample_chapter(output_path.to_str().unwrap())?;
 
         // 作成されたファイルを読み込んで確認
@@ -275,19 +910,159 @@ mod tests {
         let content = fs::read_to_string(&output_path)?;
         assert!(content.contains("基本移動とモード切替"));
         assert!(content.contains("hjkl移動マスター"));
+        assert!(content.contains("置換モードで上書き"));
 
         // YAMLとしてパースできるか確認
         let parsed: ContinuousChapterData = serde_yaml::from_str(&content)?;
         assert_eq!(parsed.chapter.number, 1);
-        assert_eq!(parsed.continuous_exercises.len(), 3);
+        assert_eq!(parsed.continuous_exercises.len(), 7);
+        // 解説のページはモード切替の練習の前に読む
+        assert!(parsed.lessons_before(0).is_empty());
+        assert_eq!(parsed.lessons_before(1)[0].title, "モードで編集する");
+
+        // setup / teardown もYAMLに書き出される
+        let paste = &parsed.continuous_exercises[4];
+        assert_eq!(paste.setup, vec!["let @a = 'Hello'", "call cursor(1, 18)"]);
+        assert_eq!(paste.teardown, vec!["let @a = ''"]);
+        assert!(parsed.continuous_exercises[0].setup.is_empty());
+        assert_eq!(parsed.continuous_exercises[5].goals[0].goal_type, "substitution");
+        assert_eq!(parsed.continuous_exercises[6].goals[1].goal_type, "dot_repeat");
+        assert!(!content.contains("setup: []"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chapter_file_numbers_are_not_limited_to_eight() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        for name in [
+            "continuous_chapter_12.yaml",
+            "continuous_chapter_02.yaml",
+            "continuous_chapter_x.yaml",
+            "continuous_chapter_999.yaml",
+            "chapter_05.yaml",
+        ] {
+            fs::write(tmp_dir.path().join(name), "")?;
+        }
+        assert_eq!(
+            chapter_file_numbers(tmp_dir.path(), "continuous_chapter_"),
+            vec![2, 12]
+        );
+        assert_eq!(chapter_file_numbers(tmp_dir.path(), "chapter_"), vec![5]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundles_load_after_chapter_files_and_reject_duplicate_numbers() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let single = |number: u8| {
+            format!(
+                "chapter: {{ number: {}, title: \"第{}章\", description: \"\" }}\ncontinuous_exercises: []\n",
+                number, number
+            )
+        };
+        let bundle = |numbers: &[u8]| {
+            let chapters: Vec<String> = numbers.iter().map(|&n| indent(&single(n))).collect();
+            format!("chapters:\n{}", chapters.concat())
+        };
+        fn indent(chapter: &str) -> String {
+            chapter
+                .lines()
+                .enumerate()
+                .map(|(i, line)| format!("{}{}\n", if i == 0 { "  - " } else { "    " }, line))
+                .collect()
+        }
+        fs::write(tmp_dir.path().join("continuous_chapter_01.yaml"), single(1))?;
+        fs::write(tmp_dir.path().join("continuous_bundle_b.yaml"), bundle(&[4, 3]))?;
+        fs::write(tmp_dir.path().join("continuous_bundle_a.yaml"), bundle(&[2]))?;
+        // 従来形式だけの第3章は、まとめで読めたため変換の対象にしない
+        fs::write(tmp_dir.path().join("chapter_03.yaml"), "")?;
+
+        let (loader, report) = ContinuousContentLoader::load_from(tmp_dir.path())?;
+        assert_eq!(loader.chapter_numbers(), vec![1, 2, 3, 4]);
+        let order: Vec<u8> = report
+            .chapters
+            .iter()
+            .filter_map(|load| match load {
+                ChapterLoad::Loaded { number, .. } => Some(*number),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(order, vec![1, 2, 4, 3]);
+        assert!(!report.chapters.iter().any(|load| matches!(load, ChapterLoad::Legacy { .. })));
 
+        // 読めないまとめはファイル名で報告し、他の章は読み込む
+        fs::write(tmp_dir.path().join("continuous_bundle_c.yaml"), "chapters: [")?;
+        let (_, report) = ContinuousContentLoader::load_from(tmp_dir.path())?;
+        let failures = report.failures();
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].0.ends_with("continuous_bundle_c.yaml"));
+
+        fs::write(tmp_dir.path().join("continuous_bundle_c.yaml"), bundle(&[5, 1]))?;
+        let message = ContinuousContentLoader::load_from(tmp_dir.path()).err().unwrap().to_string();
+        assert!(message.contains("第1章が "), "{}", message);
+        assert!(message.contains("continuous_chapter_01.yaml と "), "{}", message);
+        assert!(message.contains("continuous_bundle_c.yaml（chapters[1]） の両方にあります"), "{}", message);
         Ok(())
     }
 
+    #[test]
+    fn test_bundle_errors_name_the_chapter_in_the_bundle() {
+        let yaml = r#"
+chapters:
+  - chapter: { number: 1, title: "移動", description: "" }
+    continuous_exercises: []
+  - chapter: { number: 2, title: "単語", description: "" }
+    continuous_exercises:
+      - title: "移動"
+        description: ""
+        flow_type: "sequential"
+        sample_code: ["abc"]
+        goals:
+          - type: "position_at"
+            target: "fourth"
+"#;
+        let error = ContinuousContentLoader::parse_chapters(yaml, "course.yaml").unwrap_err();
+        assert!(
+            error.to_string().contains("chapters[1].continuous_exercises[0]"),
+            "{}",
+            error
+        );
+        // chapter のある文書は、chapters があっても1章の形式として読む
+        let single = "chapter: { number: 1, title: \"移動\", description: \"\" }\ncontinuous_exercises: []\nchapters: []\n";
+        assert_eq!(ContinuousContentLoader::parse_chapters(single, "one.yaml").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_load_returns_per_chapter_results_without_printing() {
+        let (loader, report) = ContinuousContentLoader::load_from(Path::new(paths::BUNDLED_CHAPTERS_DIR)).unwrap();
+        assert_eq!(report.total, loader.chapter_numbers().len());
+        let loaded: Vec<u8> = report
+            .chapters
+            .iter()
+            .filter_map(|load| match load {
+                ChapterLoad::Loaded { number, .. } => Some(*number),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(loaded, loader.chapter_numbers());
+
+        // 章が1つも読めなかったときは、読めなかった理由をそのまま返す
+        let loads = vec![
+            ChapterLoad::Legacy { number: 1 },
+            ChapterLoad::Failed { number: 2, error: anyhow::anyhow!("インデントが違います") },
+        ];
+        assert_eq!(content::no_content_error(loads, "なし").to_string(), "インデントが違います");
+        assert_eq!(content::no_content_error(Vec::new(), "なし").to_string(), "なし");
+    }
+
     #[test]
     fn test_continuous_content_loader_structure() {
-        let loader = ContinuousContentLoader { chapters: vec![] };
-        assert_eq!(loader.get_chapter_count(), 0);
+        let loader = ContinuousContentLoader {
+            chapters: vec![],
+            packs: BTreeMap::new(),
+        };
+        assert!(loader.chapter_numbers().is_empty());
         assert!(loader.get_chapter(1).is_none());
     }
 }
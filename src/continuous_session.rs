@@ -1,53 +1,588 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::fs::OpenOptions;
 // use std::io::{self, Write};
-use std::io::Write;
-use std::process::Command;
-use std::thread;
-use std::time::Duration;
-use tempfile::NamedTempFile;
-
-use crate::vim_rpc::VimRpcClient;
-use crate::vim_state::{Goal, GoalDetector, GoalType, VimMode, VimState};
-
-// デバッグログ用のマクロ
-macro_rules! debug_log {
-    ($($arg:tt)*) => {
-        let log_message = format!("[{}] 🔧 CONTINUOUS_DEBUG: {}",
-            chrono::Utc::now().format("%Y-%m-%d %H:%M:%S%.3f"),
-            format!($($arg)*));
-
-        if let Ok(mut file) = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("/tmp/vim_continuous_debug.log") {
-            let _ = writeln!(file, "{}", log_message);
-        }
-    };
-}
+use std::ops::ControlFlow;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tempfile::{NamedTempFile, TempDir};
+
+use crate::clean;
+use crate::config::{self, TutorialConfig};
+use crate::diff;
+use crate::editor::Editor;
+use crate::event_log::{EventLog, SessionEvent};
+use crate::goal_engine::{BackendStates, GoalEngine, GoalEvent, HintDelays, StateStream, StreamItem, TimedState};
+use crate::goal_registry;
+use crate::layout::{self, DisplayPolicy, PaneLayout, TerminalSize};
+use crate::keystrokes::{
+    self, DotRepeatCounter, KeystrokeScore, MotionParser, OperatorParser, RepeatRun, TextObjectParser,
+};
+use crate::normalize::Normalize;
+use crate::notify;
+use crate::output::{info, warning};
+use crate::pane::{self, DEFAULT_PANE_HEIGHT, DEFAULT_PANE_WIDTH, PaneNotes, PaneView};
+use crate::paths;
+use crate::progress::{self, PausedExercise, SessionHeartbeat};
+use crate::recovery::{self, SessionManifest};
+use crate::session_progress::{HintState, ProgressSnapshot};
+use crate::share::{SharedStatus, StatusShare};
+use crate::summary::ExerciseSummary;
+use crate::status_line;
+use crate::theme::{self, Theme};
+use crate::validator;
+use crate::output::{DebugLog, debug_log};
+use crate::vim_backend::{
+    COMMAND_FILE, GOAL_COMMAND_FILE, INSTRUCTIONS_FILE, InstructionSink, KEY_LOG_FILE, LaunchRequest,
+    NOTICE_FILE, RunningSession, STATUS_FILE, TmuxBackend, VimBackend, script_temp_file,
+};
+use crate::vim_state::{
+    DEFAULT_REGISTERS, Goal, GoalDetector, GoalGap, GoalType,
+    TextMatch, VimMode, VimState,
+};
+
+// debug_log! の書き先
+const DEBUG_LOG: DebugLog = DebugLog::CONTINUOUS;
+
+// 状態がまだ届かないとき、指示ペインで知らせるまでの時間
+const STATUS_WARN_AFTER: Duration = Duration::from_secs(5);
+// 状態ファイルがこの大きさを超えたら、エディタは最新の1行で書き直す
+const STATUS_ROTATE_BYTES: u64 = 256 * 1024;
+// 接続が切れたとき、再接続を試みてから状態ファイルを確かめ直すまでの間隔
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+// 指示ペインのカーソル位置を描き直す最短の間隔（ちらつかないよう1秒に数回まで）
+const POSITION_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+// アイドルで一時停止している間の状態の確認間隔
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+// 状態が変わらない間の確認間隔と、変わった後に設定の間隔で確認し続ける時間
+// （間隔を空けても、変化に気付くのは SLOW_POLL_INTERVAL より遅れない）
+const SLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const FAST_POLL_WINDOW: Duration = Duration::from_secs(3);
+// 進捗ファイルにハートビートを書く間隔（progress::HEARTBEAT_EXPIRES_SECS より短くする）
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+// 同じ移動キーをこの回数より多く続けて押したらコーチングの助言を出す
+const COACHING_REPEAT: usize = 5;
+// コーチングの助言を出す最短の間隔
+const COACHING_INTERVAL: Duration = Duration::from_secs(20);
+// 解答のキーを送り終えてから、目標の達成が状態に現れるのを待つ時間
+const DEMO_SETTLE: Duration = Duration::from_secs(2);
+
+// 監視用スクリプトが定義するコマンド・関数・グローバル変数（学習者の Neovim で練習した後に消す）
+const SCRIPT_COMMANDS: &[&str] = &[
+    "TutorialHint",
+    "TutorialReset",
+    "TutorialRetry",
+    "TutorialPause",
+    "TutorialResume",
+    "TutorialDemo",
+    "TutorialDemoKeep",
+    "TutorialDemoRestore",
+];
+const SCRIPT_FUNCTIONS: &[&str] = &[
+    "TutorialModeToken",
+    "TutorialWindowCount",
+    "TutorialSetCursor",
+    "TutorialCharCol",
+    "TutorialStatusLine",
+    "UpdateStatus",
+    "TimerUpdate",
+    "TutorialShowInstructions",
+    "TutorialShowNotices",
+    "TutorialFlushKeys",
+    "TutorialDiscardCommandKeys",
+    "TutorialBell",
+    "TutorialRunCommands",
+    "TutorialRunGoalCommands",
+    "TutorialReset",
+    "TutorialDemoSnapshot",
+    "TutorialDemoRestore",
+    "TutorialCountMatches",
+    "TutorialBufferLines",
+    "TutorialSyntaxNodes",
+];
+const SCRIPT_GLOBALS: &[&str] = &[
+    "tutorial_registers",
+    "tutorial_watch_buffer",
+    "tutorial_sample",
+    "tutorial_files",
+    "tutorial_setup",
+    "tutorial_teardown",
+];
+// 練習用ウィンドウのカーソルを含む treesitter のノードの種類を内側から ',' でつなぐ
+// （language が空ならバッファの filetype の言語。パーサーが無ければ 'UNSUPPORTED'）
+const NEOVIM_SYNTAX_NODES: &str = r#"
+function! TutorialSyntaxNodes(language)
+  if !has('nvim-0.9')
+    return 'UNSUPPORTED'
+  endif
+  return luaeval('(function(win, lang) local buf = vim.api.nvim_win_get_buf(win); local ok, parser = pcall(vim.treesitter.get_parser, buf, lang); if not ok or not parser then return "UNSUPPORTED" end; parser:parse(); local pos = vim.api.nvim_win_get_cursor(win); local found, node = pcall(vim.treesitter.get_node, {bufnr = buf, pos = {pos[1] - 1, pos[2]}, lang = lang}); if not found then return "UNSUPPORTED" end; local types = {}; while node do table.insert(types, node:type()); node = node:parent() end; return table.concat(types, ",") end)(_A[1], _A[2] ~= "" and _A[2] or nil)', [s:main_win, a:language])
+endfunction
+"#;
+// Vim には treesitter が無い
+const VIM_SYNTAX_NODES: &str = r#"
+function! TutorialSyntaxNodes(language)
+  return 'UNSUPPORTED'
+endfunction
+"#;
+
+// 学習者の Neovim で練習する前に取っておき、TutorialDetach で戻すもの
+// （練習ではレジスタを使い、ウィンドウごとに持てない検索のオプションも変えることがある）
+const SHARED_SAVED_STATE: &str = r#"  let s:saved_registers = {}
+  for s:name in split('"0123456789abcdefghijklmnopqrstuvwxyz-', '\zs')
+    let s:saved_registers[s:name] = getreginfo(s:name)
+  endfor
+  let s:saved_options = {'hlsearch': &hlsearch, 'incsearch': &incsearch}
+  let s:practice_buf = bufnr('%')
+  let s:return_tab = tabpagenr('#')"#;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ContinuousExercise {
     pub title: String,
     pub description: String,
+    // 行のリストか、複数行の文字列（"|" のブロック）で書ける
+    #[serde(default, deserialize_with = "lines_or_text")]
     pub sample_code: Vec<String>,
+    // sample_code の代わりに複数のファイルを開く（1つ目を表示し、残りはバッファ一覧に入れる）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<ExerciseFile>,
     pub goals: Vec<ExerciseGoal>,
+    #[serde(default)]
     pub flow_type: FlowType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_limit_seconds: Option<u64>, // チャレンジモードでの制限時間
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimal_keystrokes: Option<usize>, // 練習全体の最適キー数（目標ごとの指定が無い場合に使用）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor_start: Option<[usize; 2]>, // 開始時のカーソル位置 [行, 列]（0始まり）
+    // 練習用Neovimでファイルを開いた後、監視を始める前に実行する Ex コマンド
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub setup: Vec<String>,
+    // 練習の終了時、Neovimを閉じる前に実行する Ex コマンド
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub teardown: Vec<String>,
+    // ファイルを開いた後に :set で変える表示のオプション（EDITOR_OPTIONS にあるものだけ）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub editor_options: Vec<String>,
+    // false で、この練習ではコーチング（設定の coaching）の助言を出さない（自由に動く練習など）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coaching: Option<bool>,
+    // 練習の前に端末で読ませる文章（複数行可。最初の指示ペインにも目標が進むまで出す）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intro: Option<String>,
+    // 練習を達成した後に端末で表示する文章
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outro: Option<String>,
+    // 全ての目標で入ってよいモード（目標の allowed_modes が優先）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_modes: Vec<String>,
+    // 練習の間ずっと上の画面の下の方に出しておく参考（練習するキーの早見表など。1要素 = 1行）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reference: Vec<String>,
+    // オペレーター待機中（d の後など）に、次に打つキーの例を指示ペインに出す
+    // （現在の目標がオペレーターやテキストオブジェクトを使うものの場合だけ）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub operator_hints: bool,
+}
+
+// 複数のファイルを開く練習の1ファイル（name は練習用ディレクトリ内のファイル名）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExerciseFile {
+    pub name: String,
+    #[serde(default, deserialize_with = "lines_or_text")]
+    pub content: Vec<String>,
+}
+
+// サンプルコードを行のリストでも複数行の文字列でも読む（文字列の末尾の改行は行にしない）
+fn lines_or_text<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum LinesOrText {
+        Lines(Vec<String>),
+        Text(String),
+    }
+    Ok(match LinesOrText::deserialize(deserializer)? {
+        LinesOrText::Lines(lines) => lines,
+        LinesOrText::Text(text) => text.lines().map(str::to_string).collect(),
+    })
+}
+
+// editor_options で変えられるオプション（見た目の手がかりだけにし、章データから任意のコマンドを実行させない）
+pub const EDITOR_OPTIONS: &[&str] = &[
+    "number",
+    "relativenumber",
+    "cursorline",
+    "cursorcolumn",
+    "wrap",
+    "list",
+    "hlsearch",
+    "incsearch",
+];
+
+// editor_options の1項目から :set に渡す引数を取り出す（許可していないオプションは None）
+// "relativenumber"・"set nowrap"・"invlist"・"list!" のように1項目に1つのオプションを書く
+pub fn editor_option_argument(option: &str) -> Option<&str> {
+    let option = option.trim();
+    let argument = option.strip_prefix("set ").map_or(option, str::trim);
+    let name = argument.strip_suffix('!').unwrap_or(argument);
+    let name = ["no", "inv"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix).filter(|name| EDITOR_OPTIONS.contains(name)))
+        .unwrap_or(name);
+    EDITOR_OPTIONS.contains(&name).then_some(argument)
+}
+
+// 保存する画面1つの大きさの上限（超えたら古い方を省き、最後の画面を残す）
+const MAX_CAPTURE_BYTES: usize = 256 * 1024;
+
+// ペインの内容をデバッグログと同じディレクトリに書き、書けたファイルを返す
+fn write_captures(captures: &[(String, String)], dir: &std::path::Path, at: chrono::DateTime<chrono::Utc>) -> Vec<PathBuf> {
+    captures
+        .iter()
+        .filter_map(|(pane, text)| {
+            let path = dir.join(format!(
+                "vim_tutorial_capture_{}_{}_{}.txt",
+                at.format("%Y%m%dT%H%M%S"),
+                std::process::id(),
+                pane
+            ));
+            let mut start = text.len().saturating_sub(MAX_CAPTURE_BYTES);
+            while !text.is_char_boundary(start) {
+                start += 1;
+            }
+            let content = match start {
+                0 => text.clone(),
+                omitted => format!("（先頭の {} バイトを省きました）\n{}", omitted, &text[start..]),
+            };
+            match fs::write(&path, content) {
+                Ok(()) => Some(path),
+                Err(e) => {
+                    debug_log!("画面を保存できません: {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+// FNV-1a（標準の DefaultHasher は Rust のバージョンで値が変わりうるため使わない）
+pub fn fnv1a_hex(text: &str) -> String {
+    let hash = text
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3));
+    format!("{:016x}", hash)
+}
+
+impl ContinuousExercise {
+    // 練習の内容のハッシュ（進捗の記録が、いまの章データの練習のものか確かめるのに使う）。
+    // 読み込んだ後の構造体を serde_json の値にしてから文字列にするのでキーは名前順に並び、
+    // YAML のキーの順番・字下げ・省いた既定値では変わらない
+    pub fn content_hash(&self) -> String {
+        let canonical = serde_json::to_value(self).map(|value| value.to_string()).unwrap_or_default();
+        fnv1a_hex(&canonical)
+    }
+
+    // 練習開始直後の状態（1行1列・ノーマルモード）
+    pub fn initial_state(&self) -> VimState {
+        let [cursor_line, cursor_col] = self.cursor_start.unwrap_or([0, 0]);
+        VimState {
+            cursor_line,
+            cursor_col,
+            buffer_content: self.starting_lines().to_vec(),
+            buffer_name: self.files.first().map(|file| file.name.clone()).unwrap_or_default(),
+            buffers: self
+                .files
+                .iter()
+                .map(|file| (file.name.clone(), file.content.clone()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    // 目標の間に入ってよいモード（空なら制限しない）
+    pub fn allowed_modes_for(&self, index: usize) -> &[String] {
+        match self.goals.get(index) {
+            Some(goal_def) if !goal_def.allowed_modes.is_empty() => &goal_def.allowed_modes,
+            _ => &self.allowed_modes,
+        }
+    }
+
+    // 最初に開くファイルの内容（files があれば1つ目、無ければ sample_code）
+    pub fn starting_lines(&self) -> &[String] {
+        self.files
+            .first()
+            .map_or(&self.sample_code, |file| &file.content)
+    }
+
+    // 目標の file が指すファイルの内容（None は最初に開くファイル。files に無ければ None）
+    pub fn file_lines(&self, file: Option<&str>) -> Option<&[String]> {
+        match file {
+            None => Some(self.starting_lines()),
+            Some(name) => self
+                .files
+                .iter()
+                .find(|file| file.name == name)
+                .map(|file| file.content.as_slice()),
+        }
+    }
+
+    // 文字列で位置を書いた目標（position_at）を、対象のファイルの中の [行, 列] の position の目標にする。
+    // 位置を決められなかった目標はそのまま残し、目標の番号と説明を付けた理由を返す
+    pub fn resolve_anchors(&mut self) -> Vec<String> {
+        self.resolve_anchor_goals()
+            .into_iter()
+            .map(|(index, issue)| format!("目標{}「{}」: {}", index + 1, self.goals[index].description, issue))
+            .collect()
+    }
+
+    // resolve_anchors と同じだが、位置を決められなかった目標の番号（0始まり）と理由を分けて返す
+    pub fn resolve_anchor_goals(&mut self) -> Vec<(usize, String)> {
+        let mut issues = Vec::new();
+        for index in 0..self.goals.len() {
+            let goal_def = &self.goals[index];
+            if goal_def.goal_type != "position_at" {
+                continue;
+            }
+            let lines = self.file_lines(goal_def.file.as_deref()).unwrap_or_default();
+            match anchor_position(lines, &goal_def.target) {
+                Ok((line, col)) => {
+                    let goal_def = &mut self.goals[index];
+                    goal_def.goal_type = "position".to_string();
+                    goal_def.target = serde_json::json!([line, col]);
+                }
+                Err(e) => issues.push((index, e.to_string())),
+            }
+        }
+        issues
+    }
+
+    // 状態として取得するレジスタ（既定のものに加え、目標で指定されたもの）
+    pub fn watched_registers(&self) -> Vec<String> {
+        let mut registers: Vec<String> = DEFAULT_REGISTERS.iter().map(|r| r.to_string()).collect();
+        for goal_def in &self.goals {
+            if goal_def.goal_type == "register"
+                && let Some(name) = goal_def.target["register"].as_str()
+                && !registers.iter().any(|r| r == name)
+            {
+                registers.push(name.to_string());
+            }
+        }
+        registers
+    }
+
+    // 状態ファイルにバッファの内容を書き出す必要があるか（テキストの目標と、取り消し・やり直しの目標、
+    // 何を見るか分からない独自の種類の目標がある場合）
+    pub fn watches_buffer(&self) -> bool {
+        self.goals.iter().any(|goal_def| {
+            matches!(
+                goal_def.goal_type.as_str(),
+                "text" | "text_block" | "buffer" | "undo_performed" | "redo_performed"
+            ) || !GOAL_TYPES.contains(&goal_def.goal_type.as_str())
+        })
+    }
+
+    // 1つの目標だけを取り出した練習を作る（ドリル用）
+    pub fn single_goal(&self, goal_index: usize) -> Option<ContinuousExercise> {
+        let goal = self.goals.get(goal_index)?.clone();
+        Some(ContinuousExercise {
+            title: format!("{} ({}/{})", self.title, goal_index + 1, self.goals.len()),
+            description: goal.description.clone(),
+            goals: vec![goal],
+            flow_type: FlowType::Sequential,
+            ..self.clone()
+        })
+    }
+
+    // タイトルに出典を付けた複製を作る
+    pub fn with_title_prefix(&self, prefix: &str) -> ContinuousExercise {
+        ContinuousExercise {
+            title: format!("{} {}", prefix, self.title),
+            ..self.clone()
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ExerciseGoal {
-    #[serde(rename = "type")]
+    #[serde(rename = "type", deserialize_with = "known_goal_type")]
     pub goal_type: String,
+    // undo_performed などの target の要らない目標では省ける（null になる）
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
     pub target: serde_json::Value,
+    // 省くと目標の種類と target から作る（作ったものは description_generated が true）
+    #[serde(default)]
     pub description: String,
+    #[serde(skip)]
+    pub description_generated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub hint: Option<String>,
+    // 自動ヒント表示までの秒数（設定ファイルの値を上書き）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint_delay_seconds: Option<u64>,
+    // 最終段階のヒントとして表示する解答のキー入力
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solution_keys: Option<String>,
+    // 最適解のキー数（キー効率の採点に使用）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimal_keystrokes: Option<usize>,
+    // この目標で学ぶキー（チートシートに使う。無ければヒントや説明から推測する）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<String>,
+    // 目標までの距離の目安（「あと2文字右」など）を指示ペインに出す
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub nudge: bool,
+    // 複数のファイルを開く練習で、この目標が対象にするファイル（files の name）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    // この目標が有効になったとき・達成したときに練習用Neovimで実行する Ex コマンド
+    // （失敗は指示ペインで知らせ、練習は続ける。順番通りの流れ以外では、練習の開始時に全ての目標が有効になる）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_activate: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_complete: Vec<String>,
+    // 目標が有効になってから入ってよいモード（mode の目標の target と同じ名前で書き、空なら練習の
+    // allowed_modes に従う）。ノーマル・オペレーター待機・コマンドラインのモードには常に入れる
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_modes: Vec<String>,
+    // 目標を達成するのに使うオペレーター（"d" "c" "gU" など。keystrokes::OPERATORS のどれか）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_operator: Option<String>,
+}
+
+// position_at の target（{text, occurrence, offset}）が指す位置。occurrence は上から何番目の text か
+// （1始まり、省略時は1）、offset は text の先頭から何文字目か（省略時は0）。列は状態の列と同じ文字単位
+fn anchor_position(lines: &[String], target: &serde_json::Value) -> Result<(usize, usize)> {
+    let target = target
+        .as_object()
+        .ok_or_else(|| anyhow::anyhow!("position_at の target は {{text, occurrence, offset}} で書きます"))?;
+    let text = goal_registry::required_str(target, "text")?;
+    if text.is_empty() {
+        return Err(anyhow::anyhow!("position_at の text が空です"));
+    }
+    let occurrence = match target.get("occurrence") {
+        None => 1,
+        Some(_) => goal_registry::required_u64(target, "occurrence")? as usize,
+    };
+    if occurrence == 0 {
+        return Err(anyhow::anyhow!("position_at の occurrence は1から数えます"));
+    }
+    let offset = match target.get("offset") {
+        None => 0,
+        Some(_) => goal_registry::required_u64(target, "offset")? as usize,
+    };
+    let found: Vec<(usize, usize)> = lines
+        .iter()
+        .enumerate()
+        .flat_map(|(line, content)| content.match_indices(&text).map(move |(col, _)| (line, col)))
+        .collect();
+    let Some(&(line, start)) = found.get(occurrence - 1) else {
+        return Err(match found.len() {
+            0 => anyhow::anyhow!("「{}」がサンプルコードにありません", text),
+            count => anyhow::anyhow!(
+                "「{}」はサンプルコードに{}か所しかありません（occurrence: {}）",
+                text,
+                count,
+                occurrence
+            ),
+        });
+    };
+    let rest = &lines[line][start..];
+    let col = match rest.chars().nth(offset) {
+        Some(_) => lines[line][..start].chars().count() + offset,
+        None => {
+            return Err(anyhow::anyhow!(
+                "「{}」から offset {} 文字目は {}行目の終わりを越えています",
+                text,
+                offset,
+                line + 1
+            ));
+        }
+    };
+    Ok((line, col))
+}
+
+// 組み込みの目標の type に書ける名前（to_goal で判定できるもの。position_at は練習の開始時に position にする）。
+// goal_registry に登録した独自の種類の名前も書ける
+pub const GOAL_TYPES: &[&str] = &[
+    "position",
+    "mode",
+    "text",
+    "register",
+    "text_block",
+    "buffer",
+    "buffer_change",
+    "buffer_active",
+    "counted_motion",
+    "window_count",
+    "window_layout",
+    "substitution",
+    "undo_performed",
+    "redo_performed",
+    "dot_repeat",
+    "operator_textobject",
+    "syntax_node",
+    "position_at",
+];
+
+// 知らない目標の type は、章データを読む時点で行と項目のパスを付けて知らせる
+fn known_goal_type<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<String, D::Error> {
+    let goal_type = String::deserialize(deserializer)?;
+    if !GOAL_TYPES.contains(&goal_type.as_str()) && !goal_registry::is_registered(&goal_type) {
+        let mut expected: Vec<String> = GOAL_TYPES.iter().map(|name| name.to_string()).collect();
+        expected.extend(goal_registry::names().into_iter().filter(|name| !GOAL_TYPES.contains(&name.as_str())));
+        return Err(serde::de::Error::custom(format!(
+            "unknown goal type `{}`, expected one of {}",
+            goal_type,
+            expected.join(", ")
+        )));
+    }
+    Ok(goal_type)
+}
+
+impl ExerciseGoal {
+    // description が省かれていれば目標の種類と target から作る（target が誤っていれば種類の名前にする）
+    pub fn fill_description(&mut self) {
+        if !self.description.trim().is_empty() {
+            return;
+        }
+        self.description = match self.to_goal() {
+            Ok(goal) => goal.goal_type.default_description(),
+            Err(_) => format!("{} の目標", self.goal_type),
+        };
+        self.description_generated = true;
+    }
+
+    /// YAMLの目標定義を判定用の [`Goal`] に変換する（target の形が type に合わなければエラー）
+    pub fn to_goal(&self) -> Result<Goal> {
+        let goal_def = self;
+        debug_log!("目標変換: type={}, target={:?}", goal_def.goal_type, goal_def.target);
+        
+        if goal_def.goal_type == "position_at" {
+            return Err(anyhow::anyhow!(
+                "position_at must be resolved against the sample code first: {}",
+                goal_def.target
+            ));
+        }
+        let goal_type = goal_registry::goal_type(&goal_def.goal_type, &goal_def.target)?;
+        let goal_type = match &goal_def.file {
+            Some(file) => GoalType::InFile {
+                file: file.clone(),
+                goal: Box::new(goal_type),
+            },
+            None => goal_type,
+        };
+
+        Ok(Goal {
+            goal_type,
+            description: goal_def.description.clone(),
+        })
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum FlowType {
+    #[default]
     #[serde(rename = "sequential")]
     Sequential, // 順番に実行する必要がある
     #[serde(rename = "any_order")]
@@ -56,844 +591,5341 @@ pub enum FlowType {
     Parallel, // 複数の目標を同時に達成
 }
 
+// 一時停止の理由（再開の仕方が違う）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PauseReason {
+    Command,  // :TutorialPause（:TutorialResume で再開）
+    Detached, // tmuxから離れた（戻ると再開）
+    Idle,     // 操作が無いまま時間がたった（操作すると再開）
+}
+
+// 状態を1つ受け取ったときに、目標を判定するか
+enum Judging {
+    Check,                  // 目標を判定する
+    Skip,                   // この状態では判定しない
+    Finish(ExerciseResult), // 練習を終える
+}
+
+// :TutorialDemo で再生している目標の解答
+struct DemoPlayback {
+    goal_index: usize,
+    keys: VecDeque<String>, // まだ送っていないキー
+    next_key_at: Instant,
+    finished_at: Option<Instant>, // 最後のキーを送った時刻
+    outcome: Option<bool>,        // 送り終えて目標を達成したか（決まったら、戻すか学習者に尋ねる）
+    keystrokes: (usize, usize),   // 再生前のキー数（再生したキーは学習者のキー数に入れない）
+}
+
+// 一時停止を進捗ファイルに残すときの、章の中での練習の位置
+#[derive(Debug, Clone)]
+pub struct PauseTarget {
+    pub chapter_number: u8,
+    pub exercise_index: usize,
+    pub earlier_exercises: Vec<ExerciseSummary>,
+}
+
 pub struct ContinuousVimSession {
-    vim_client: VimRpcClient,
+    backend: Box<dyn VimBackend>,
     goal_detector: GoalDetector,
+    motion_parser: MotionParser,
+    operator_parser: OperatorParser,
+    dot_counter: DotRepeatCounter,
+    text_object_parser: TextObjectParser,
+    config: TutorialConfig,
     current_exercise: Option<ContinuousExercise>,
-    current_goal_index: usize,
-    completed_goals: Vec<bool>,
+    engine: GoalEngine, // 目標の達成状況とヒントの段階
+    states: BackendStates, // エディタから読む状態の時計
     last_state: Option<VimState>,
     monitoring_active: bool,
-    instruction_pane_id: Option<String>,
+    instructions: Option<Box<dyn InstructionSink>>, // 起動後の指示の表示先
+    hints_used: usize,             // 練習全体でのヒント要求回数
+    attempts: usize,               // :TutorialRetry でやり直した分を含む、この練習への取り組みの回数
+    countdown_shown: Option<u64>, // ペインに表示中のカウントダウン秒数
+    time_limit: Option<Duration>,  // チャレンジモードの制限時間
+    deadline: Option<Instant>,
+    remaining_shown: Option<u64>, // ペインに表示中の残り秒数
+    elapsed_shown: u64,           // ペインに表示中の、練習に取り組んだ秒数
+    pending_keystrokes: usize,    // 直前の目標達成以降のキー数
+    total_keystrokes: usize,
+    keystroke_scores: Vec<KeystrokeScore>,
+    text_diff_shown: Vec<String>, // ペインに表示中の行の差分
+    stalled_text_shown: Vec<String>, // ペインに表示中の、残ったテキストの目標と実際の行の比較
+    buffer_changed: Option<(u64, Instant)>, // 最後に見た changedtick と、それに変わった時刻
+    nudge_shown: Option<String>,  // ペインに表示中の距離の目安
+    operator_pending_shown: Option<String>, // ペインに表示中の、オペレーター待機中に次に打つキーの案内
+    repeat_run: RepeatRun,        // 同じ移動キーを続けて押している回数
+    coaching_shown: Option<String>, // ペインに表示中のコーチングの助言
+    coached_at: Option<Instant>,
+    position_shown: Option<String>, // ペインに表示中のカーソル位置
+    position_updated_at: Option<Instant>,
+    layout: PaneLayout,
+    pane_width: usize, // 指示ペインの桁数（簡易表示の切り詰めに使う）
+    pane_height: usize, // 指示ペインの行数（参考の枠に使える行の見積もりに使う）
+    event_log: EventLog, // 後から再生するためのセッションの記録
+    pause_target: Option<PauseTarget>, // None なら一時停止を保存しない（ドリルなど）
+    resume_from: Option<PausedExercise>, // 次の練習を一時停止した状態から始める
+    reattach: Option<RunningSession>, // 次の練習を起動せず、前回のプロセスが残したエディタで続ける
+    manifest_path: Option<PathBuf>, // 練習中の印（落ちた後の再接続用。テストでは書かない）
+    paused: Option<PauseReason>,
+    learner_seen: bool, // 一度でも学習者がtmuxに接続したか（接続前は離れた扱いにしない）
+    started_at: Instant,
+    paused_since: Option<Instant>,
+    paused_time: Duration,     // 練習中に一時停止していた時間の合計
+    resumed_elapsed: Duration, // 前回の一時停止までに取り組んだ時間
+    initial_cursor: Option<[usize; 2]>, // 一時停止した位置から始める場合のカーソル位置
+    share: StatusShare, // 講師の watch に見せる状態（--share-dir）
+    vim_script: Option<NamedTempFile>, // 再接続で読み直させるため、練習の間は残しておく
+    exercise_dir: Option<TempDir>, // 複数のファイルを開く練習のファイルの置き場所（練習の終了で消す）
+    reconnect_attempts: u32, // 接続が切れてから再確立を試みた回数
+    last_connection_error: Option<String>,
+    unavailable_since: Option<Instant>, // 状態がまだ届いていない間、待ち始めた時刻
+    unavailable_warned: bool,
+    last_activity: Instant, // 最後に状態が変わった時刻（アイドルの判定に使う）
+    idle_prompted: bool,    // 続けるか尋ねている
+    heartbeat_enabled: bool, // 進捗ファイルにハートビートを書く（テストでは書かない）
+    heartbeat_written: Option<(Instant, bool)>, // 最後に書いた時刻とアイドルかどうか
+    pacing: bool,       // 練習の前後で Enter を待つ（auto_advance やテストでは待たない）
+    intro_shown: bool,  // 指示ペインに練習の intro を出している
+    theme: Theme,       // 端末と指示ペインの表示の見た目（設定の accessible か --accessible）
+    display: DisplayPolicy, // 時間・進捗を指示ペインに出すか（設定の zen か --zen では出さない）
+    pane_shown_at: Option<Instant>, // 指示ペインを最後に描いた時刻
+    pending_pane: Option<Vec<String>>, // 更新をまとめる間隔の間に届いた、まだ描いていない指示
+    goal_commands_running: bool, // 目標の on_activate / on_complete を実行し終えるまで目標を判定しない
+    goal_command_failure: Option<String>, // ペインに表示中の、目標のコマンドの失敗
+    demo: Option<DemoPlayback>, // 再生中（または戻すか尋ねている）解答
+    conduct_notice: Option<String>, // ペインに表示中の、許していない操作でやり直しにした理由
+    capture_dir: Option<PathBuf>, // 練習の終わりの画面を保存する場所（None なら保存しない）
+    keep_session: bool,           // 練習の終わりにエディタを止めずに残す（--keep-session）
 }
 
 impl ContinuousVimSession {
+    #[cfg(test)] // 既定設定で使う場合（テスト）
     pub fn new(socket_path: String) -> Self {
+        Self::with_config(socket_path, TutorialConfig::default())
+    }
+
+    /// tmux の中でエディタを起動するセッション（`socket_path` はエディタとの通信に使うソケット）
+    pub fn with_config(socket_path: String, config: TutorialConfig) -> Self {
+        let mut session = Self::with_backend(Box::new(TmuxBackend::new(socket_path)), config);
+        session.event_log = EventLog::in_dir(paths::event_log_dir());
+        session.heartbeat_enabled = true;
+        session.manifest_path = Some(paths::manifest_file());
+        session.capture_dir = Some(paths::cache_dir());
+        session.keep_session = config::keep_session();
+        session
+    }
+
+    /// エディタの起動と操作を `backend` に任せるセッション。イベントログ・生存確認・再接続用の印は書かない
+    pub fn with_backend(backend: Box<dyn VimBackend>, config: TutorialConfig) -> Self {
+        let theme = if config.accessible { Theme::Accessible } else { theme::current() };
+        let display = config.display();
         Self {
-            vim_client: VimRpcClient::new(socket_path),
+            backend,
             goal_detector: GoalDetector::new(),
+            motion_parser: MotionParser::new(),
+            operator_parser: OperatorParser::default(),
+            dot_counter: DotRepeatCounter::default(),
+            text_object_parser: TextObjectParser::default(),
+            config,
             current_exercise: None,
-            current_goal_index: 0,
-            completed_goals: Vec::new(),
+            engine: GoalEngine::default(),
+            states: BackendStates::new(),
             last_state: None,
             monitoring_active: false,
-            instruction_pane_id: None,
+            instructions: None,
+            hints_used: 0,
+            attempts: 1,
+            countdown_shown: None,
+            time_limit: None,
+            deadline: None,
+            remaining_shown: None,
+            elapsed_shown: 0,
+            pending_keystrokes: 0,
+            total_keystrokes: 0,
+            keystroke_scores: Vec::new(),
+            text_diff_shown: Vec::new(),
+            stalled_text_shown: Vec::new(),
+            buffer_changed: None,
+            nudge_shown: None,
+            operator_pending_shown: None,
+            repeat_run: RepeatRun::default(),
+            coaching_shown: None,
+            coached_at: None,
+            position_shown: None,
+            position_updated_at: None,
+            layout: PaneLayout::Full,
+            pane_width: DEFAULT_PANE_WIDTH,
+            pane_height: DEFAULT_PANE_HEIGHT,
+            event_log: EventLog::disabled(),
+            pause_target: None,
+            resume_from: None,
+            reattach: None,
+            manifest_path: None,
+            paused: None,
+            learner_seen: false,
+            started_at: Instant::now(),
+            paused_since: None,
+            paused_time: Duration::ZERO,
+            resumed_elapsed: Duration::ZERO,
+            initial_cursor: None,
+            share: StatusShare::disabled(),
+            vim_script: None,
+            exercise_dir: None,
+            reconnect_attempts: 0,
+            last_connection_error: None,
+            unavailable_since: None,
+            unavailable_warned: false,
+            last_activity: Instant::now(),
+            idle_prompted: false,
+            heartbeat_enabled: false,
+            heartbeat_written: None,
+            pacing: false,
+            intro_shown: false,
+            theme,
+            display,
+            pane_shown_at: None,
+            pending_pane: None,
+            goal_commands_running: false,
+            goal_command_failure: None,
+            demo: None,
+            conduct_notice: None,
+            capture_dir: None,
+            keep_session: false,
         }
     }
 
-    pub fn start_exercise(&mut self, exercise: ContinuousExercise, file_path: &str) -> Result<()> {
-        println!("\n🎯 === {} ===", exercise.title);
-        println!("{}\n", exercise.description);
+    // 状態を共有ディレクトリに書き出す（キー入力やバッファの内容は書き出さない）
+    pub fn share_status_in(&mut self, dir: &std::path::Path) {
+        self.share = StatusShare::in_dir(dir);
+    }
 
-        // サンプルコードを表示
-        println!("📝 サンプルコード:");
-        for (i, line) in exercise.sample_code.iter().enumerate() {
-            println!("{:2}: {}", i + 1, line);
-        }
-        println!();
+    // 一時停止したときに進捗ファイルへ残す練習の位置。次の start_exercise から有効
+    pub fn set_pause_target(&mut self, target: Option<PauseTarget>) {
+        self.pause_target = target;
+    }
 
-        // 目標リストを表示
-        println!("🎯 学習目標:");
-        for (i, goal) in exercise.goals.iter().enumerate() {
-            println!("  {}. {}", i + 1, goal.description);
-            if let Some(hint) = &goal.hint {
-                println!("     💡 ヒント: {}", hint);
-            }
-        }
-        println!();
+    // 次の start_exercise を一時停止した時点の目標から始める（目標の数が違えば最初から）
+    pub fn resume_from(&mut self, paused: PausedExercise) {
+        self.resume_from = Some(paused);
+    }
 
-        // tmux分割画面でVimを起動
-        if Command::new("tmux").arg("-V").output().is_ok() {
-            println!("🖥️ tmux分割画面モードで学習を開始します");
-            self.start_tmux_session(&exercise, file_path)?;
-        } else {
-            println!("❌ tmuxが利用できません。RPCモードで実行します");
-            // fallback to RPC mode
-            self.vim_client.start_neovim(file_path, None)?;
-            thread::sleep(Duration::from_millis(500));
-        }
+    // 次の start_exercise をエディタの起動ではなく、前回のプロセスが残したエディタへの再接続で始める
+    // （目標の進み具合は resume_from で渡す）
+    pub fn reattach_to(&mut self, running: RunningSession) {
+        self.reattach = Some(running);
+    }
 
-        // 練習の初期化
-        self.current_exercise = Some(exercise.clone());
-        self.completed_goals = vec![false; exercise.goals.len()];
-        self.current_goal_index = 0;
-        self.monitoring_active = true;
+    // 練習に取り組んだ時間（一時停止していた時間を除き、再開前の時間を含む）
+    pub fn active_duration(&self) -> Duration {
+        let paused = self.paused_time + self.paused_since.map_or(Duration::ZERO, |since| since.elapsed());
+        (self.resumed_elapsed + self.started_at.elapsed()).saturating_sub(paused)
+    }
 
-        debug_log!("🚀 Vimセッション開始！");
-        debug_log!("現在の目標: {}", exercise.goals[0].description);
+    // 練習の前（intro と目標の一覧を読ませる）と達成後（outro）に Enter を待つか
+    pub fn set_pacing(&mut self, pacing: bool) {
+        self.pacing = pacing;
+    }
 
-        Ok(())
+    // チャレンジモードの制限時間（None で無制限）。次の start_exercise から有効
+    pub fn set_time_limit(&mut self, seconds: Option<u64>) {
+        self.time_limit = seconds.map(Duration::from_secs);
+    }
+
+    // 直前の練習で最適キー数が設定された目標ごとのキー効率
+    pub fn keystroke_scores(&self) -> &[KeystrokeScore] {
+        &self.keystroke_scores
     }
 
-    fn start_tmux_session(&mut self, exercise: &ContinuousExercise, file_path: &str) -> Result<()> {
-        let session_name = "vim_tutorial_continuous";
+    pub fn hints_used(&self) -> usize {
+        self.hints_used
+    }
 
-        // 既存セッションを削除
-        let _ = Command::new("tmux")
-            .args(["kill-session", "-t", session_name])
-            .output();
+    // 直前の練習に取り組んだ回数（:TutorialRetry でやり直すと増える）
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
 
-        // 新しいセッションを作成
-        let new_session_result = Command::new("tmux")
-            .args(["new-session", "-d", "-s", session_name])
-            .output()?;
+    // 直前の練習で達成した目標（目標と同じ順）
+    pub fn completed_goals(&self) -> &[bool] {
+        self.engine.completed()
+    }
 
-        if !new_session_result.status.success() {
+    /// 練習の説明を端末に表示し、`file_path`（複数のファイルを開く練習では練習ごとのディレクトリ）でエディタを起動する
+    pub fn start_exercise(&mut self, mut exercise: ContinuousExercise, file_path: &str) -> Result<()> {
+        // 達成できない目標のまま学習者に始めさせない（章データの誤りとして報告する）
+        let issues = validator::validate_exercise(&exercise);
+        if !issues.is_empty() {
             return Err(anyhow::anyhow!(
-                "tmuxセッション作成に失敗: {}",
-                String::from_utf8_lossy(&new_session_result.stderr)
+                "練習「{}」の章データに誤りがあるため開始できません:\n  - {}",
+                exercise.title,
+                issues.join("\n  - ")
             ));
         }
+        // 文字列で書いた位置の目標を、サンプルコードの中の位置にする（位置を決められることは検証済み）
+        exercise.resolve_anchors();
 
-        // 画面を水平分割
-        let split_result = Command::new("tmux")
-            .args(["split-window", "-v", "-t", session_name])
-            .output()?;
-
-        if !split_result.status.success() {
+        // 置換の目標は有効になった時点の数をエディタに数えさせるため、式を評価できないエディタでは始めない
+        if !self.backend.evaluates_expressions()
+            && Self::substitution_goals(&exercise, 0..exercise.goals.len()).next().is_some()
+        {
             return Err(anyhow::anyhow!(
-                "tmux画面分割に失敗: {}",
-                String::from_utf8_lossy(&split_result.stderr)
+                "練習「{}」の置換の目標には、エディタに式を評価させる接続が必要です（+clientserver の Vim か Neovim で練習します）",
+                exercise.title
             ));
         }
-        
-        // 分割後にペイン一覧を取得して正確なIDを確認
-        let pane_list_output = Command::new("tmux")
-            .args(["list-panes", "-t", session_name, "-F", "#{pane_index}:#{pane_id}:#{pane_current_command}"])
-            .output()?;
-        
-        let pane_info = String::from_utf8_lossy(&pane_list_output.stdout);
-        debug_log!("分割後ペイン一覧: {}", pane_info.trim());
-        
-        // pane_index 0 = 上部（指示用）、pane_index 1 = 下部（Vim用）
-        let mut top_pane_id = String::new();
-        let mut bottom_pane_id = String::new();
-        
-        for line in pane_info.lines() {
-            let parts: Vec<&str> = line.split(':').collect();
-            if parts.len() >= 3 {
-                let index = parts[0];
-                let pane_id = parts[1];
-                // index 0 = 上部（指示表示用）
-                // index 1 = 下部（Vim用）
-                if index == "0" {
-                    top_pane_id = pane_id.to_string();
-                } else if index == "1" {
-                    bottom_pane_id = pane_id.to_string();
-                }
-            }
+
+        // treesitter の目標はカーソルを含むノードをエディタに問い合わせる
+        if !self.backend.evaluates_expressions() && Self::syntax_node_goals(&exercise, 0..exercise.goals.len()).next().is_some() {
+            return Err(anyhow::anyhow!(
+                "練習「{}」の treesitter の目標には、エディタに式を評価させる接続が必要です（Neovim 0.9 以上で練習します）",
+                exercise.title
+            ));
         }
-        
-        debug_log!("上部ペインID: {}", top_pane_id);
-        debug_log!("下部ペインID: {}", bottom_pane_id);
 
-        // ペイン識別のためのテストメッセージ送信
-        if !top_pane_id.is_empty() {
-            let _ = Command::new("tmux")
-                .args(["send-keys", "-t", &top_pane_id, "echo 'TEST: 上部ペイン'", "Enter"])
-                .output();
-            debug_log!("上部ペインにテストメッセージ送信: {}", top_pane_id);
+        // . の繰り返しとテキストオブジェクトはキー入力の記録で判定するため、記録できないエディタでは始めない
+        if !self.backend.captures_keystrokes()
+            && exercise
+                .goals
+                .iter()
+                .any(|goal_def| matches!(goal_def.goal_type.as_str(), "dot_repeat" | "operator_textobject"))
+        {
+            return Err(anyhow::anyhow!(
+                "練習「{}」の . の繰り返しやテキストオブジェクトの目標には、キー入力の記録が必要です（Neovim 0.8 以上で練習します）",
+                exercise.title
+            ));
         }
-        
-        if !bottom_pane_id.is_empty() {
-            let _ = Command::new("tmux")
-                .args(["send-keys", "-t", &bottom_pane_id, "echo 'TEST: 下部ペイン'", "Enter"])
-                .output();
-            debug_log!("下部ペインにテストメッセージ送信: {}", bottom_pane_id);
+
+        for line in Self::briefing_lines(&exercise) {
+            println!("{}", self.theme.line(&line));
         }
-        
-        thread::sleep(Duration::from_millis(1000)); // テストメッセージを確認するための待機
 
-        // instruction_pane_idを保存
-        self.instruction_pane_id = Some(top_pane_id.clone());
-        
-        // 取得したペインIDを使用
-        let top_pane = &top_pane_id;    // 上部ペイン（指示）
-        let bottom_pane = &bottom_pane_id; // 下部ペイン（Vim）
+        // 採点できない目標を黙って満点や0点にしない
+        let scored = exercise.optimal_keystrokes.is_some()
+            || exercise.goals.iter().any(|goal| goal.optimal_keystrokes.is_some());
+        if scored && !self.backend.captures_keystrokes() {
+            info!(
+                "⚠️ この{}ではキー入力を記録できないため、キー効率スコアは表示しません（doctor で確認できます）\n",
+                self.backend.editor().label()
+            );
+        }
+        // tmux に切り替わると端末の表示が隠れるため、読み終えるまで待つ（再接続では読み終えている）
+        if self.pacing && self.reattach.is_none() {
+            Self::wait_for_enter(&self.theme.line("▶️ Enter で練習を始めます..."));
+        }
 
-        // Vimスクリプトを作成
-        let vim_script = self.create_vim_script()?;
+        // 練習の初期化（指示ペインの初回描画で参照するため起動前に行う）
+        self.current_exercise = Some(exercise.clone());
+        self.engine = GoalEngine::new(&exercise, self.hint_delays())?;
+        self.states = BackendStates::new();
+        self.monitoring_active = true;
+        self.hints_used = 0;
+        self.attempts = 1;
+        self.reconnect_attempts = 0;
+        self.last_connection_error = None;
+        self.unavailable_since = None;
+        self.unavailable_warned = false;
+        self.last_activity = Instant::now();
+        self.idle_prompted = false;
+        self.countdown_shown = self.engine.next_hint_in().map(Self::countdown_display);
+        self.deadline = self.time_limit.map(|limit| Instant::now() + limit);
+        self.remaining_shown = self.time_limit.map(|limit| limit.as_secs());
+        self.elapsed_shown = 0;
+        self.pending_keystrokes = 0;
+        self.total_keystrokes = 0;
+        self.keystroke_scores.clear();
+        self.goal_detector.track_motions(self.backend.captures_keystrokes());
+        self.goal_detector.set_query(self.backend.goal_query());
+        self.goal_detector.clear_substitutions();
+        self.goal_detector.clear_undo_history();
+        self.motion_parser.reset();
+        self.operator_parser = OperatorParser::default();
+        self.dot_counter.reset();
+        self.text_object_parser.reset();
+        self.conduct_notice = None;
+        self.text_diff_shown.clear();
+        self.stalled_text_shown.clear();
+        self.buffer_changed = None;
+        self.nudge_shown = None;
+        self.operator_pending_shown = None;
+        self.repeat_run.reset();
+        self.coaching_shown = None;
+        self.coached_at = None;
+        self.intro_shown = exercise.intro.is_some();
+        self.position_shown = None;
+        self.position_updated_at = None;
+        self.activate_initial_goal(&exercise);
+        self.goal_commands_running = false;
+        self.goal_command_failure = None;
+        self.demo = None;
+        self.paused = None;
+        self.learner_seen = false;
+        self.started_at = Instant::now();
+        self.paused_since = None;
+        self.paused_time = Duration::ZERO;
+        self.resumed_elapsed = Duration::ZERO;
+        self.initial_cursor = None;
+        // 一時停止から再開する場合と再接続する場合は、有効になった目標のコマンドを実行済み
+        let continuing = self.resume_from.is_some() || self.reattach.is_some();
+        if let Some(paused) = self.resume_from.take() {
+            self.restore_paused(paused);
+        }
+        self.event_log.record(SessionEvent::ExerciseStarted {
+            title: exercise.title.clone(),
+            goals: exercise.goals.iter().map(|goal| goal.description.clone()).collect(),
+        });
+        self.record_goal_activated(&exercise);
 
-        // 上部ペインに指示を表示
-        let instruction_command = self.create_instruction_command(exercise)?;
+        // 端末の大きさに合わせて分割の仕方と指示の表示形式を選んでからVimを起動
+        // （再接続ではエディタが開いているファイルをそのまま使う）
+        let terminal_size = self.choose_layout();
+        let reattach = self.reattach.take();
+        let file_path = match reattach {
+            Some(_) => file_path.to_string(),
+            None => self
+                .write_exercise_files(&exercise)?
+                .unwrap_or_else(|| file_path.to_string()),
+        };
+        let vim_script = self.create_vim_script(&exercise)?;
+        let instructions = self.theme.lines(&self.pane_lines(&exercise));
+        let request = LaunchRequest {
+            vim_script: vim_script.path(),
+            file_path: &file_path,
+            layout: self.layout,
+            terminal_size,
+            instructions: &instructions,
+        };
+        self.instructions = match &reattach {
+            Some(running) => self.backend.reattach(running, &request)?,
+            None => self.backend.start(&request)?,
+        };
+        self.vim_script = Some(vim_script);
+        if !continuing {
+            self.run_goal_commands(&exercise, &[], &self.engine.active_goals())?;
+        }
+        self.write_manifest(&exercise, &exercise.initial_state());
+        self.report_progress(&exercise);
+        self.pane_shown_at = Some(Instant::now());
+        self.event_log.record(SessionEvent::PaneUpdated {
+            lines: instructions,
+        });
 
-        debug_log!("上部ペイン({})に指示送信: {}", top_pane, instruction_command.chars().take(100).collect::<String>());
-        let instruction_result = Command::new("tmux")
-            .args(["send-keys", "-t", top_pane, &instruction_command, "Enter"])
-            .output();
-        debug_log!("指示送信結果: success={}", instruction_result.as_ref().map(|r| r.status.success()).unwrap_or(false));
+        debug_log!("🚀 Vimセッション開始！");
+        debug_log!("現在の目標: {}", exercise.goals[self.engine.current_index()].description);
 
-        thread::sleep(Duration::from_millis(200));
+        Ok(())
+    }
 
-        // 下部ペインでVimを起動
-        let nvim_command = format!(
-            "nvim -S {} {}; tmux detach-client",
-            vim_script.path().display(),
-            file_path
-        );
+    // 複数のファイルを開く練習は、練習ごとのディレクトリに files を書き、1つ目のパスを返す
+    fn write_exercise_files(&mut self, exercise: &ContinuousExercise) -> Result<Option<String>> {
+        self.exercise_dir = None;
+        if exercise.files.is_empty() {
+            return Ok(None);
+        }
+        let dir = tempfile::Builder::new().prefix("vim_tutorial_files").tempdir()?;
+        for file in &exercise.files {
+            fs::write(dir.path().join(&file.name), file.content.join("\n"))?;
+        }
+        let first_file = dir.path().join(&exercise.files[0].name);
+        debug_log!("📂 練習のファイル: {}", dir.path().display());
+        self.exercise_dir = Some(dir);
+        Ok(Some(first_file.to_string_lossy().into_owned()))
+    }
 
-        debug_log!("下部ペイン({})にVimコマンド送信: {}", bottom_pane, nvim_command);
-        let vim_result = Command::new("tmux")
-            .args(["send-keys", "-t", bottom_pane, &nvim_command, "Enter"])
-            .output();
-        debug_log!("Vim起動結果: success={}", vim_result.as_ref().map(|r| r.status.success()).unwrap_or(false));
+    // 一時停止した時点の目標の達成状況・時間・カーソル位置を引き継ぐ
+    fn restore_paused(&mut self, paused: PausedExercise) {
+        // 再開後に読み取った状態から変化を待つ
+        if !self.engine.restore(paused.completed_goals) {
+            debug_log!("一時停止の記録が練習と合わないため最初から始めます");
+            return;
+        }
+        self.hints_used = paused.hints_used;
+        self.resumed_elapsed = Duration::from_secs_f64(paused.elapsed_secs.max(0.0));
+        self.elapsed_shown = self.resumed_elapsed.as_secs();
+        self.initial_cursor = Some(paused.cursor);
+        self.goal_detector.clear_motions();
+        self.countdown_shown = self.engine.next_hint_in().map(Self::countdown_display);
+    }
 
-        thread::sleep(Duration::from_millis(500));
+    // 最初の目標は練習の初期状態（開始位置を含む）から変化を待つ。
+    // setup で変わる状態は予測できないため、その場合は最初に読み取った状態を使う
+    fn activate_initial_goal(&mut self, exercise: &ContinuousExercise) {
+        if exercise.setup.is_empty() {
+            self.engine.activate_from(&self.goal_detector, &exercise.initial_state());
+        }
+    }
 
-        // 下部ペインにフォーカス
-        let _ = Command::new("tmux")
-            .args(["select-pane", "-t", bottom_pane])
-            .output();
+    fn hint_delays(&self) -> HintDelays {
+        HintDelays {
+            hint: Duration::from_secs(self.config.hint_delay_seconds),
+            solution: Duration::from_secs(self.config.solution_delay_seconds),
+        }
+    }
 
-        // 現在のターミナルをtmuxセッションにアタッチ
-        println!("🖥️ 画面を分割してVim学習を開始します...");
-        println!("💡 操作方法:");
-        println!("   - 上部: 指示とリアルタイム進捗表示");
-        println!("   - 下部: Vim操作画面");
-        println!("   - Ctrl+b ↑/↓でペイン間移動可能");
-        println!();
+    fn choose_layout(&mut self) -> Option<TerminalSize> {
+        let terminal_size = self.backend.terminal_size();
+        self.layout = layout::choose_layout(terminal_size);
+        self.pane_width = match (self.layout, terminal_size) {
+            (PaneLayout::SideBySide, Some(size)) => size.columns / 2,
+            (_, Some(size)) => size.columns,
+            (_, None) => DEFAULT_PANE_WIDTH,
+        };
+        // 上下分割では端末の半分（区切りの1行を除く）が指示ペインになる
+        self.pane_height = match (self.layout, terminal_size) {
+            (PaneLayout::SideBySide, Some(size)) => size.lines,
+            (_, Some(size)) => size.lines.saturating_sub(1) / 2,
+            (_, None) => DEFAULT_PANE_HEIGHT,
+        };
+        debug_log!("画面レイアウト: {:?} (端末: {:?})", self.layout, terminal_size);
+        match (self.layout, terminal_size) {
+            (PaneLayout::SideBySide, Some(size)) => info!(
+                "⚠️ 端末の高さが足りないため（{}x{}）、左右に分割して表示します",
+                size.columns, size.lines
+            ),
+            (PaneLayout::Compact, Some(size)) => info!(
+                "⚠️ 端末が小さいため（{}x{}）、指示を3行の簡易表示にします",
+                size.columns, size.lines
+            ),
+            _ => {}
+        }
+        terminal_size
+    }
 
-        // 少し待ってから現在のターミナルでtmuxセッションをアタッチ
-        thread::sleep(Duration::from_millis(1000));
+    // 文字列の並びをVimの単一引用符リテラルのリスト要素にする
+    fn vim_string_list(items: &[String]) -> String {
+        items
+            .iter()
+            .map(|item| format!("'{}'", item.replace('\'', "''")))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 
-        debug_log!("tmuxアタッチ準備完了、Vimセッション開始！");
-        
-        // tmuxセッションにアタッチ（非同期で実行）
-        println!("🖥️ tmuxセッションにアタッチ中...");
-        println!("💡 操作方法:");
-        println!("   - 上部: 指示とリアルタイム進捗表示");
-        println!("   - 下部: Vim操作画面");
-        println!("   - Ctrl+b ↑/↓でペイン間移動可能");
-        println!("   - 目標達成後、自動的に次の目標に進みます");
-        println!();
-
-        let session_name_clone = session_name.to_string();
-        thread::spawn(move || {
-            debug_log!("tmuxアタッチスレッド開始");
-            let attach_result = Command::new("tmux")
-                .args(["attach-session", "-t", &session_name_clone])
-                .status();
-
-            match attach_result {
-                Ok(_) => {
-                    debug_log!("tmuxセッション正常終了");
-                }
-                Err(e) => {
-                    debug_log!("tmuxアタッチエラー: {}", e);
-                }
+    fn create_vim_script(&self, exercise: &ContinuousExercise) -> Result<NamedTempFile> {
+        // 学習者の Neovim で練習する場合は、設定・マッピング・自動コマンドを練習用のタブとバッファに限る
+        let shared = self.backend.shares_editor();
+        let set_command = if shared { "setlocal" } else { "set" };
+        // :TutorialReset で復元するサンプルコード（Vimの単一引用符リテラル）
+        let sample_lines = Self::vim_string_list(exercise.starting_lines());
+        // 複数のファイル: [['名前', [行...]], ...]（リセットと状態の書き出しに使う）
+        let files = exercise
+            .files
+            .iter()
+            .map(|file| {
+                format!(
+                    "[{}, [{}]]",
+                    Self::vim_string_list(std::slice::from_ref(&file.name)),
+                    Self::vim_string_list(&file.content)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        // 練習用ディレクトリに移り、gf と :find で開けるよう path にも加える。
+        // 残りのファイルはバッファ一覧に入れ、編集したまま :bn で移れるよう hidden にする
+        let files_options = match &self.exercise_dir {
+            Some(dir) if shared => {
+                let dir = Self::vim_string_list(&[dir.path().to_string_lossy().into_owned()]);
+                format!(
+                    r#"  setlocal bufhidden=hide
+  execute 'lcd' fnameescape({dir})
+  let &l:path = &path . ',' . escape({dir}, ' ,\')
+  for [s:name, s:lines] in g:tutorial_files[1:]
+    execute 'badd' fnameescape(s:name)
+    call setbufvar(s:name, '&bufhidden', 'hide')
+  endfor"#,
+                    dir = dir
+                )
             }
-        });
-
-        // 短時間待機してから戻る（監視スレッドを開始できるように）
-        thread::sleep(Duration::from_millis(500));
-
-        Ok(())
-    }
+            Some(dir) => {
+                let dir = Self::vim_string_list(&[dir.path().to_string_lossy().into_owned()]);
+                format!(
+                    r#"  set hidden
+  execute 'lcd' fnameescape({dir})
+  let &path .= ',' . escape({dir}, ' ,\')
+  for [name, lines] in g:tutorial_files[1:]
+    execute 'badd' fnameescape(name)
+  endfor"#,
+                    dir = dir
+                )
+            }
+            None => String::new(),
+        };
+        let registers = Self::vim_string_list(&exercise.watched_registers());
+        let setup_commands = Self::vim_string_list(&exercise.setup);
+        let teardown_commands = Self::vim_string_list(&exercise.teardown);
+        let [start_line, start_col] = exercise.cursor_start.unwrap_or([0, 0]);
+        let [initial_line, initial_col] = self.initial_cursor.unwrap_or([start_line, start_col]);
+        let tab_guard = if shared {
+            "  \" 学習者が自分のタブに移っている間は何もしない\n  if !get(t:, 'vim_tutorial', 0)\n    return\n  endif\n"
+        } else {
+            ""
+        };
 
-    fn create_vim_script(&self) -> Result<NamedTempFile> {
-        let script_content = r#"
+        let script_content = format!(
+            r#"
 " 連続学習用Vimスクリプト（拡張版）
+{status_functions}
+let g:tutorial_registers = [{registers}]
+let g:tutorial_watch_buffer = {watch_buffer}
+let s:buffer_tick = -1
+let s:buffer_json = '[]'
+let s:instruction_buf = get(s:, 'instruction_buf', -1)
+let s:main_win = get(s:, 'main_win', win_getid())
+let s:instructions = []
+let s:status_seq = get(s:, 'status_seq', 0)
+let s:last_record = ''
+let s:last_write = 0
+
 function! UpdateStatus()
-  let line_num = line('.')
-  let col_num = col('.')
-  let mode_str = mode()
-  let mode_detailed = mode(1)
-  let status_line = 'LINE:' . line_num . ',COL:' . col_num . ',MODE:' . mode_str . ',DETAILED:' . mode_detailed
-  call writefile([status_line], '/tmp/vim_continuous_status.json')
+{tab_guard}  " 指示ウィンドウにいる間は練習用ウィンドウの状態を書く
+  if bufnr('%') == s:instruction_buf
+    if win_id2win(s:main_win) > 0
+      call win_execute(s:main_win, 'call UpdateStatus()')
+    endif
+    return
+  endif
+  call TutorialFlushKeys()
+  let status_line = TutorialStatusLine()
+  " 監視対象のレジスタは改行やカンマを含むため JSON で別の行に書く
+  let regs = {{}}
+  for name in g:tutorial_registers
+    let content = getreg(name)
+    if content !=# ''
+      let regs[name] = {{'content': content, 'type': getregtype(name)}}
+    endif
+  endfor
+  let lines = [status_line, 'REGS:' . json_encode(regs)]
+  " テキストの目標がある場合はバッファ全体も書く（変更がなければ前回の結果を使う）
+  if g:tutorial_watch_buffer
+    if s:buffer_tick != b:changedtick
+      let s:buffer_tick = b:changedtick
+      let s:buffer_json = json_encode(getline(1, '$'))
+    endif
+    call add(lines, 'BUF:' . s:buffer_json)
+  endif
+  call add(lines, 'FILE:' . json_encode(expand('%:t')))
+  if !empty(g:tutorial_files)
+    let buffers = {{}}
+    for [name, _] in g:tutorial_files
+      if bufexists(name)
+        let buffers[name] = getbufline(bufnr(name), 1, '$')
+      endif
+    endfor
+    call add(lines, 'FILES:' . json_encode(buffers))
+  endif
+  " 変わっていなければ1秒に1回だけ書く（接続が切れていないことを伝える）
+  let record = join(lines, "\t")
+  if record ==# s:last_record && localtime() == s:last_write
+    return
+  endif
+  let s:last_record = record
+  let s:last_write = localtime()
+  let s:status_seq += 1
+  " 1回の更新を1行で追記する（読み取り側は改行まで届いた行だけを使う）。大きくなったら最新の1行で書き直す
+  let line = 'SEQ:' . getpid() . ':' . s:status_seq . "\t" . record
+  call writefile([line], '{status_file}', getfsize('{status_file}') > {status_rotate_bytes} ? '' : 'a')
 endfunction
 
-" 複数の状態更新トリガー
-autocmd CursorMoved,CursorMovedI,InsertEnter,InsertLeave,ModeChanged * call UpdateStatus()
+" 複数の状態更新トリガー（再接続で読み直しても重複しないよう augroup にまとめる）
+augroup vim_tutorial
+  autocmd!
+  autocmd CursorMoved,CursorMovedI,InsertEnter,InsertLeave,TextChanged,TextChangedI,BufEnter,WinEnter {autocmd_pattern} call UpdateStatus()
+  " ModeChanged は Vim 8.2.3430 より前には無い
+  if exists('##ModeChanged')
+    autocmd ModeChanged {autocmd_pattern} call UpdateStatus()
+  endif
+  " 実行した Ex コマンドを知らせる（置換の目標で :s を使ったかを見る。Tutorial* のコマンドは除く）
+  autocmd CmdlineLeave : if !v:event.abort && getcmdline() !~# '^\s*Tutorial' | call writefile(['EX_COMMAND' . "\t" . getcmdline()], '{command_file}', 'a') | endif
+  " 練習用ウィンドウを閉じて指示ウィンドウだけが残ったら終了する
+  autocmd WinEnter * if winnr('$') == 1 && bufnr('%') == s:instruction_buf | quit | endif
+augroup END
 
 " タイマーベースの定期更新（100ms間隔）
 function! TimerUpdate(timer)
+{tab_guard}  call TutorialRunGoalCommands()
   call UpdateStatus()
+  call TutorialShowNotices()
+  call TutorialShowInstructions()
+endfunction
+
+" 指示ウィンドウ（tmux が無い場合、監視側が書いた指示を上部の読み取り専用ウィンドウに表示する）
+function! TutorialShowInstructions()
+  if !filereadable('{instructions_file}')
+    return
+  endif
+  let lines = readfile('{instructions_file}')
+  if lines ==# s:instructions && bufwinid(s:instruction_buf) != -1
+    return
+  endif
+  let s:instructions = lines
+  if bufwinid(s:instruction_buf) == -1
+    let s:main_win = win_getid()
+    noautocmd topleft new
+    setlocal buftype=nofile bufhidden=wipe noswapfile nobuflisted winfixheight nonumber norelativenumber
+    " ウィンドウの目標では数えない
+    let b:tutorial_instructions = 1
+    let s:instruction_buf = bufnr('%')
+    noautocmd call win_gotoid(s:main_win)
+  endif
+  call setbufvar(s:instruction_buf, '&modifiable', 1)
+  silent call deletebufline(s:instruction_buf, 1, '$')
+  call setbufline(s:instruction_buf, 1, lines)
+  call setbufvar(s:instruction_buf, '&modifiable', 0)
+  call win_execute(bufwinid(s:instruction_buf), 'resize ' . min([len(lines), &lines / 2]))
+endfunction
+
+" 監視側からの知らせ（目標の達成など）をベルと強調表示で伝える
+function! TutorialShowNotices()
+  if !filereadable('{notice_file}')
+    return
+  endif
+  let notices = readfile('{notice_file}')
+  call delete('{notice_file}')
+  for notice in notices
+    call TutorialBell()
+    echohl MoreMsg | echo notice | echohl None
+  endfor
 endfunction
 
-let g:update_timer = timer_start(100, 'TimerUpdate', {'repeat': -1})
+if exists('g:update_timer')
+  call timer_stop(g:update_timer)
+endif
+let g:update_timer = timer_start({poll_interval_ms}, 'TimerUpdate', {{'repeat': -1}})
 
+{editor_functions}
 " 基本移動キーの即座更新マッピング
-for key in ['h', 'j', 'k', 'l', 'w', 'e', 'b', '0', '$', 'gg', 'G']
-  execute 'nnoremap <silent> ' . key . ' ' . key . ':call UpdateStatus()<CR>'
+for s:key in ['h', 'j', 'k', 'l', 'w', 'e', 'b', '0', '$', 'gg', 'G']
+  execute 'nnoremap <silent> {map_scope}' . s:key . ' ' . s:key . ':call UpdateStatus()<CR>'
 endfor
 
-" 初期状態を記録
-call UpdateStatus()
+" チュートリアル用コマンド
+let g:tutorial_sample = [{sample_lines}]
+let g:tutorial_files = [{files}]
+let g:tutorial_setup = [{setup_commands}]
+let g:tutorial_teardown = [{teardown_commands}]
 
-" カーソル位置を1行1列に設定
-call cursor(1, 1)
-call UpdateStatus()
+" Ex コマンドを順に実行し、失敗したらコマンドファイルで知らせて中断する
+function! TutorialRunCommands(commands, failure)
+  for cmd in a:commands
+    try
+      execute cmd
+    catch
+      call writefile([a:failure . "\t" . cmd . "\t" . v:exception], '{command_file}', 'a')
+      return
+    endtry
+  endfor
+endfunction
+
+" 監視側から届いた目標の on_activate / on_complete を練習用ウィンドウで実行し、終えたことを知らせる
+function! TutorialRunGoalCommands()
+  if !filereadable('{goal_command_file}')
+    return
+  endif
+  if bufnr('%') == s:instruction_buf && win_id2win(s:main_win) > 0
+    call win_execute(s:main_win, 'call TutorialRunGoalCommands()')
+    return
+  endif
+  let commands = readfile('{goal_command_file}')
+  call delete('{goal_command_file}')
+  call TutorialRunCommands(commands, 'GOAL_COMMAND_FAILED')
+  call writefile(['GOAL_COMMANDS_DONE'], '{command_file}', 'a')
+  call UpdateStatus()
+endfunction
+
+" 練習用ウィンドウのバッファの first〜last 行で pattern に一致する数（監視側が置換の目標の判定に使う）
+function! TutorialCountMatches(pattern, first, last)
+  let matches = 0
+  for line in getbufline(winbufnr(s:main_win), a:first, a:last)
+    let matches += len(split(line, a:pattern, 1)) - 1
+  endfor
+  return matches
+endfunction
+
+" 練習用ウィンドウのバッファの first〜last 行を JSON で返す（監視側がテキストの目標の比較に使う）
+function! TutorialBufferLines(first, last)
+  return json_encode(getbufline(winbufnr(s:main_win), a:first, a:last))
+endfunction
+
+" :TutorialDemo で解答を再生する前のバッファ・カーソル・レジスタを取っておき、TutorialDemoRestore で戻す
+function! TutorialDemoSnapshot()
+  let s:demo = {{'lines': getline(1, '$'), 'cursor': getcurpos(), 'registers': {{}}}}
+  for name in split('"0123456789abcdefghijklmnopqrstuvwxyz-/', '\zs')
+    let s:demo.registers[name] = getreginfo(name)
+  endfor
+endfunction
+
+function! TutorialDemoRestore()
+  if !exists('s:demo')
+    return
+  endif
+  silent %d _ | call setline(1, s:demo.lines)
+  " 無名レジスタは他のレジスタを指すため最後に戻す
+  for [name, info] in items(s:demo.registers)
+    if name !=# '"'
+      call setreg(name, info)
+    endif
+  endfor
+  call setreg('"', s:demo.registers['"'])
+  call setpos('.', s:demo.cursor)
+  unlet s:demo
+  echo '解答を再生する前の状態に戻しました'
+endfunction
+
+" TutorialReset(1) は :TutorialRetry（目標の判定のやり直しに加え、練習の開始時のレジスタにも戻す）
+function! TutorialReset(...)
+  let retry = a:0 && a:1
+  call TutorialDiscardCommandKeys()
+  if retry
+    let registers = get(s:, 'initial_registers', {{}})
+    for [name, info] in items(registers)
+      if name !=# '"'
+        call setreg(name, info)
+      endif
+    endfor
+    if has_key(registers, '"')
+      call setreg('"', registers['"'])
+    endif
+  endif
+  " 複数のファイルを開く練習は全てのファイルを戻し、1つ目を表示する
+  for [name, lines] in reverse(copy(g:tutorial_files))
+    execute 'silent buffer' fnameescape(name)
+    silent %d _ | call setline(1, lines)
+  endfor
+  if empty(g:tutorial_files)
+    silent %d _ | call setline(1, g:tutorial_sample)
+  endif
+  call TutorialSetCursor({start_line}, {start_col})
+  call TutorialRunCommands(g:tutorial_setup, 'SETUP_FAILED')
+  call writefile([retry ? 'RETRY' : 'RESET'], '{command_file}', 'a')
+  call UpdateStatus()
+  echo retry ? '練習を最初からやり直します' : 'サンプルコードを元に戻しました'
+endfunction
+
+command! TutorialHint call TutorialDiscardCommandKeys() | call writefile(['HINT_REQUEST'], '{command_file}', 'a') | echo 'ヒントを上の画面に表示しました'
+command! TutorialReset call TutorialReset()
+command! TutorialRetry call TutorialReset(1)
+command! TutorialPause call TutorialDiscardCommandKeys() | call writefile(['PAUSE' . "\t" . json_encode(getline(1, '$'))], '{command_file}', 'a') | echo '一時停止しました（:TutorialResume で再開）'
+command! TutorialResume call TutorialDiscardCommandKeys() | call writefile(['RESUME'], '{command_file}', 'a') | echo '練習を再開しました'
+command! TutorialDemo call TutorialDiscardCommandKeys() | call writefile(['DEMO_REQUEST'], '{command_file}', 'a')
+command! TutorialDemoKeep call TutorialDiscardCommandKeys() | call writefile(['DEMO_KEEP'], '{command_file}', 'a')
+command! TutorialDemoRestore call TutorialDiscardCommandKeys() | call writefile(['DEMO_RESTORE'], '{command_file}', 'a')
+
+" 再接続で読み直した場合は、学習者の編集とカーソル位置をそのまま残す
+if !exists('g:tutorial_started')
+  let g:tutorial_started = 1
+{saved_state}
+  " カーソルを開始位置（一時停止から再開する場合はその位置）に設定
+  call TutorialSetCursor({initial_line}, {initial_col})
+{position_options}
+{swap_options}
+{wrap_options}
+{editor_options}
+{files_options}
+  " :TutorialRetry で戻す、目標が見るレジスタの開始時の内容
+  let s:initial_registers = {{}}
+  for s:name in g:tutorial_registers
+    let s:initial_registers[s:name] = getreginfo(s:name)
+  endfor
 
+  " 練習の前提条件を整えてから初期状態を記録
+  call TutorialRunCommands(g:tutorial_setup, 'SETUP_FAILED')
+endif
+call UpdateStatus()
+{detach}
 " echo '🎯 連続学習開始！リアルタイム状態監視が有効です'
-"#;
+"#,
+            sample_lines = sample_lines,
+            files = files,
+            files_options = files_options,
+            status_file = script_temp_file(STATUS_FILE),
+            status_rotate_bytes = STATUS_ROTATE_BYTES,
+            command_file = script_temp_file(COMMAND_FILE),
+            notice_file = script_temp_file(NOTICE_FILE),
+            poll_interval_ms = self.config.poll_interval().as_millis(),
+            goal_command_file = script_temp_file(GOAL_COMMAND_FILE),
+            instructions_file = script_temp_file(INSTRUCTIONS_FILE),
+            status_functions = status_line::vim_functions(),
+            editor_functions =
+                Self::editor_functions(self.backend.editor(), self.backend.captures_keystrokes()),
+            registers = registers,
+            watch_buffer = u8::from(exercise.watches_buffer()),
+            setup_commands = setup_commands,
+            teardown_commands = teardown_commands,
+            start_line = start_line + 1,
+            start_col = start_col + 1,
+            tab_guard = tab_guard,
+            autocmd_pattern = if shared { "<buffer>" } else { "*" },
+            map_scope = if shared { "<buffer> " } else { "" },
+            saved_state = if shared { SHARED_SAVED_STATE } else { "" },
+            detach = if shared {
+                Self::detach_function(self.backend.editor(), self.backend.captures_keystrokes())
+            } else {
+                String::new()
+            },
+            initial_line = initial_line + 1,
+            initial_col = initial_col + 1,
+            // 行番号とルーラーで練習用のウィンドウでも位置が分かるようにする（setup で上書きできる）
+            // 学習者の Neovim では、ウィンドウごとではない ruler は変えない
+            position_options = match (self.config.show_position, shared) {
+                (false, _) => "",
+                (true, false) => "  set number ruler",
+                (true, true) => "  setlocal number",
+            },
+            // 練習用のファイルにスワップファイルを作らない（前回落ちたセッションのスワップファイルが残っていると、
+            // 確認の表示で入力待ちになり状態が届かなくなる。起動時の -n と同じ）
+            swap_options = if shared { "  setlocal noswapfile" } else { "  set noswapfile" },
+            // 長い行を折り返すと見た目の行と列が目標の行・列と合わないため、折り返さずに横へ1桁ずつスクロールする
+            // （editor_options の wrap で戻せる。学習者の Neovim では全体の sidescroll は変えない）
+            wrap_options = if shared { "  setlocal nowrap" } else { "  set nowrap sidescroll=1" },
+            editor_options = exercise
+                .editor_options
+                .iter()
+                .filter_map(|option| editor_option_argument(option))
+                .map(|argument| format!("  {} {}", set_command, argument))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
 
-        let script_file = NamedTempFile::new()?;
+        let script_file = NamedTempFile::new_in(paths::cache_dir())?;
         fs::write(&script_file, script_content)?;
         Ok(script_file)
     }
 
-    fn create_instruction_command(&self, exercise: &ContinuousExercise) -> Result<String> {
-        let success_flag = "/tmp/vim_continuous_success.flag";
-        let progress_flag = "/tmp/vim_continuous_progress.txt";
-        let _ = fs::remove_file(success_flag);
-        let _ = fs::remove_file(progress_flag);
-
-        // 最初の目標だけを表示
-        let first_goal = &exercise.goals[0];
-        let goal_display = format!("  1. {}", first_goal.description.replace("'", "'\\''"));
-        let hint_display = if let Some(hint) = &first_goal.hint {
-            format!("     💡 {}", hint.replace("'", "'\\''"))
-        } else {
-            String::new()
+    // エディタごとに異なる部分（キー入力の記録とベル・treesitter）。Vim と古い Neovim ではキー入力を記録しない。
+    // Neovim のベルは stderr に書く（0.10 からは TUI が別のプロセスで、サーバーの stdout は端末ではない）
+    fn editor_functions(editor: Editor, captures_keystrokes: bool) -> String {
+        let syntax_nodes = match editor {
+            Editor::Neovim => NEOVIM_SYNTAX_NODES,
+            Editor::Vim => VIM_SYNTAX_NODES,
         };
+        let functions = match editor {
+            Editor::Neovim if captures_keystrokes => format!(
+                r#"
+" キー入力の記録（マッピング展開前の入力を記録し、状態ファイルより先に書き出す）
+lua << LUAEOF
+local pending = {{}}
+vim.on_key(function(key, typed)
+  local keys = typed or key
+  if keys == nil or keys == '' then
+    return
+  end
+  table.insert(pending, vim.api.nvim_get_mode().mode .. '\t' .. vim.fn.keytrans(keys))
+end, vim.api.nvim_create_namespace('vim_tutorial_keys'))
 
-        // シンプルな指示表示（複雑なbashループは削除）
-        let command = format!(
-            r#"clear; echo '=== 🎯 {} ==='; echo '{}'; echo ''; echo '=== 📋 現在の目標 ==='; echo '{}'; echo '{}'; echo '=== 📊 進捗: 1/{} ==='; echo '下のNeovimで操作してください！'; echo '目標達成時に自動的に次の目標が表示されます'"#,
-            exercise.title.replace("'", "'\\''"),
-            exercise.description.replace("'", "'\\''"),
-            goal_display,
-            hint_display,
-            exercise.goals.len()
-        );
+function _G.TutorialFlushKeys()
+  -- コマンドライン入力中は確定するまで保留（チュートリアル用コマンドを除外するため）
+  if #pending == 0 or vim.startswith(vim.api.nvim_get_mode().mode, 'c') then
+    return
+  end
+  vim.fn.writefile(pending, '{key_log_file}', 'a')
+  pending = {{}}
+end
 
-        Ok(command)
-    }
+function _G.TutorialDiscardCommandKeys()
+  while #pending > 0 and vim.startswith(pending[#pending], 'c') do
+    table.remove(pending)
+  end
+  if #pending > 0 and vim.endswith(pending[#pending], '\t:') then
+    table.remove(pending)
+  end
+end
+LUAEOF
 
-    pub fn monitor_progress(&mut self) -> Result<ExerciseResult> {
-        let status_file = "/tmp/vim_continuous_status.json";
-        // let success_flag = "/tmp/vim_continuous_success.flag";
-        let progress_flag = "/tmp/vim_continuous_progress.txt";
+function! TutorialFlushKeys()
+  call v:lua.TutorialFlushKeys()
+endfunction
 
-        debug_log!("監視開始: status_file={}", status_file);
+function! TutorialDiscardCommandKeys()
+  call v:lua.TutorialDiscardCommandKeys()
+endfunction
 
-        while self.monitoring_active {
-            thread::sleep(Duration::from_millis(100));
+function! TutorialBell()
+  call chansend(v:stderr, "\x07")
+endfunction
+"#,
+                key_log_file = script_temp_file(KEY_LOG_FILE),
+            ),
+            Editor::Neovim => r#"
+" この Neovim には vim.fn.keytrans が無いため、キー入力は記録しない（0.8 以上が必要）
+function! TutorialFlushKeys()
+endfunction
 
-            // ステータスファイルから現在の状態を読み取り
-            let current_state = self.read_vim_state_from_file(status_file)?;
-            debug_log!("現在の状態: line={}, col={}, mode={:?}", 
-                      current_state.cursor_line, current_state.cursor_col, current_state.mode);
+function! TutorialDiscardCommandKeys()
+endfunction
 
-            if let Some(exercise) = self.current_exercise.clone() {
-                // 現在のゴールをチェック
-                if self.current_goal_index < exercise.goals.len() {
-                    let current_goal_def = &exercise.goals[self.current_goal_index];
-                    let goal = self.convert_goal_definition(current_goal_def)?;
-                    
-                    debug_log!("目標チェック中: goal_index={}, goal_type={:?}", 
-                              self.current_goal_index, goal.goal_type);
-
-                    let goal_achieved = self.goal_detector.check_goal(&goal, &current_state);
-                    debug_log!("目標達成判定: {}", goal_achieved);
-
-                    if goal_achieved {
-                        // 現在の目標を達成
-                        self.completed_goals[self.current_goal_index] = true;
-                        self.current_goal_index += 1;
-
-                        debug_log!("✅ 目標達成: {}", current_goal_def.description);
-
-                        if self.current_goal_index >= exercise.goals.len() {
-                            // 全ての目標を完了
-                            if let Ok(mut file) = OpenOptions::new()
-                                .create(true)
-                                .write(true)
-                                .truncate(true)
-                                .open(progress_flag)
-                            {
-                                let _ = writeln!(file, "completed");
-                            }
-                            debug_log!("🎉 全ての目標を達成しました！");
-                            
-                            // 章完了時にメニューに戻る
-                            self.show_completion_message(&exercise)?;
-                            thread::sleep(Duration::from_millis(2000));
-                            
-                            return Ok(ExerciseResult::Completed);
-                        } else {
-                            // 次の目標に進む
-                            if let Ok(mut file) = OpenOptions::new()
-                                .create(true)
-                                .write(true)
-                                .truncate(true)
-                                .open(progress_flag)
-                            {
-                                let _ = writeln!(file, "{}", self.current_goal_index + 1);
-                            }
-
-                            // 上部ペインを更新（新しい目標を表示）
-                            self.update_instruction_pane(&exercise)?;
-
-                            debug_log!(
-                                "📍 次の目標: {}",
-                                exercise.goals[self.current_goal_index].description
-                            );
-                        }
-
-                        // 少し待ってから進捗を反映
-                        thread::sleep(Duration::from_millis(500));
-                    }
-                }
-            }
+function! TutorialBell()
+  call chansend(v:stderr, "\x07")
+endfunction
+"#
+            .to_string(),
+            Editor::Vim => r#"
+" Vim には vim.on_key が無いため、キー入力は記録しない（キー効率スコアは Neovim のみ）
+function! TutorialFlushKeys()
+endfunction
 
-            self.last_state = Some(current_state);
-        }
+function! TutorialDiscardCommandKeys()
+endfunction
 
-        Ok(ExerciseResult::Incomplete)
+function! TutorialBell()
+  if exists('*echoraw')
+    call echoraw("\x07")
+  endif
+endfunction
+"#
+            .to_string(),
+        };
+        functions + syntax_nodes
     }
 
-    fn update_instruction_pane(&self, exercise: &ContinuousExercise) -> Result<()> {
-        // 保存されたペインIDを使用
-        let top_pane = match &self.instruction_pane_id {
-            Some(pane_id) => {
-                debug_log!("保存されたペインIDを使用: {}", pane_id);
-                pane_id
-            },
-            None => {
-                debug_log!("instruction_pane_id が設定されていません");
-                return Err(anyhow::anyhow!("instruction_pane_id が設定されていません"));
-            }
-        };
-        let current_goal = &exercise.goals[self.current_goal_index];
-        let goal_display = format!(
-            "  {}. {}",
-            self.current_goal_index + 1,
-            current_goal.description.replace("'", "'\\''")
-        );
-        let hint_display = if let Some(hint) = &current_goal.hint {
-            format!("     💡 {}", hint.replace("'", "'\\''"))
+    // 学習者の Neovim を練習の前の状態に戻す関数（監視側が終了時に g:tutorial_detach を実行する）。
+    // 練習用のタブで teardown を実行してからタブとバッファを閉じ、スクリプトが足したものを全て消す
+    fn detach_function(editor: Editor, captures_keystrokes: bool) -> String {
+        let key_log = if editor == Editor::Neovim && captures_keystrokes {
+            "  lua vim.on_key(nil, vim.api.nvim_create_namespace('vim_tutorial_keys')); _G.TutorialFlushKeys = nil; _G.TutorialDiscardCommandKeys = nil\n"
         } else {
-            String::new()
+            ""
         };
+        format!(
+            r#"
+function! TutorialDetach()
+  call timer_stop(g:update_timer)
+  let tabs = filter(gettabinfo(), 'has_key(v:val.variables, "vim_tutorial")')
+  if !empty(tabs)
+    execute 'tabnext' tabs[0].tabnr
+    call TutorialRunCommands(g:tutorial_teardown, 'TEARDOWN_FAILED')
+    if tabpagenr('$') > 1
+      tabclose!
+    endif
+  endif
+  if s:return_tab > 0 && s:return_tab <= tabpagenr('$')
+    execute 'tabnext' s:return_tab
+  endif
+  for buf in [s:practice_buf] + map(copy(g:tutorial_files), 'bufnr(v:val[0])')
+    if buf > 0 && bufexists(buf)
+      execute 'silent! bwipeout!' buf
+    endif
+  endfor
+  " 無名レジスタは他のレジスタを指すため最後に戻す
+  for [name, info] in items(s:saved_registers)
+    if name !=# '"'
+      call setreg(name, info)
+    endif
+  endfor
+  call setreg('"', s:saved_registers['"'])
+  let &hlsearch = s:saved_options.hlsearch
+  let &incsearch = s:saved_options.incsearch
+{key_log}  augroup vim_tutorial
+    autocmd!
+  augroup END
+  augroup! vim_tutorial
+  for name in [{commands}]
+    execute 'delcommand' name
+  endfor
+  for name in [{functions}]
+    execute 'delfunction' name
+  endfor
+  unlet g:update_timer g:tutorial_started {globals}
+endfunction
 
-        let update_command = format!(
-            "clear; echo '=== 🎯 {} ==='; echo '{}'; echo ''; echo '=== 📋 現在の目標 ==='; echo '{}'; echo '{}'; echo '=== 📊 進捗: {}/{} ==='; echo '下のNeovimで操作してください！'",
-            exercise.title.replace("'", "'\\''"),
-            exercise.description.replace("'", "'\\''"),
-            goal_display,
-            hint_display,
-            self.current_goal_index + 1,
-            exercise.goals.len()
-        );
-
-        // 上部ペインの内容を更新
-        debug_log!("上部ペイン({})を更新: {}", top_pane, update_command.chars().take(100).collect::<String>());
-        let interrupt_result = Command::new("tmux")
-            .args(["send-keys", "-t", top_pane, "C-c"]) // 現在のコマンドを中断
-            .output();
-        debug_log!("中断送信結果: success={}", interrupt_result.as_ref().map(|r| r.status.success()).unwrap_or(false));
+let g:tutorial_detach = ['call TutorialDetach()', 'delfunction TutorialDetach', 'unlet g:tutorial_detach']
+"#,
+            key_log = key_log,
+            commands = SCRIPT_COMMANDS.iter().map(|name| format!("'{}'", name)).collect::<Vec<_>>().join(", "),
+            functions = SCRIPT_FUNCTIONS.iter().map(|name| format!("'{}'", name)).collect::<Vec<_>>().join(", "),
+            globals = SCRIPT_GLOBALS.iter().map(|name| format!("g:{}", name)).collect::<Vec<_>>().join(" "),
+        )
+    }
 
-        thread::sleep(Duration::from_millis(100));
+    // 指示ペインに表示する行を組み立てる（初回描画と更新で共通）
+    fn pane_lines(&self, exercise: &ContinuousExercise) -> Vec<String> {
+        pane::render(exercise, &self.pane_snapshot(exercise), &self.pane_notes(), &self.pane_view())
+    }
 
-        let update_result = Command::new("tmux")
-            .args(["send-keys", "-t", top_pane, &update_command, "Enter"])
-            .output();
-        debug_log!("更新送信結果: success={}", update_result.as_ref().map(|r| r.status.success()).unwrap_or(false));
+    // ペインに描く進み具合（経過時間は表示中の値）
+    fn pane_snapshot(&self, exercise: &ContinuousExercise) -> ProgressSnapshot {
+        ProgressSnapshot {
+            elapsed_secs: self.elapsed_shown,
+            ..self.progress_snapshot(exercise)
+        }
+    }
 
-        Ok(())
+    fn pane_notes(&self) -> PaneNotes {
+        PaneNotes {
+            intro: self.intro_shown,
+            position: self.position_shown.clone(),
+            operator_pending: self.operator_pending_shown.clone(),
+            conduct_notice: self.conduct_notice.clone(),
+            demo: self.demo.as_ref().map(|demo| demo.outcome),
+            stalled_text: self.stalled_text_shown.clone(),
+            text_diff: self.text_diff_shown.clone(),
+            nudge: self.nudge_shown.clone(),
+            coaching: self.coaching_shown.clone(),
+            goal_command_failure: self.goal_command_failure.clone(),
+            countdown: self.countdown_shown,
+            remaining: self.remaining_shown,
+        }
     }
 
-    fn read_vim_state_from_file(&self, status_file: &str) -> Result<VimState> {
-        debug_log!("状態ファイル読み取り: {}", status_file);
-        
-        // ファイルが存在しない場合はデフォルト状態を返す
-        let content = match fs::read_to_string(status_file) {
-            Ok(content) => {
-                debug_log!("ファイル内容: {}", content.trim());
-                content
+    fn pane_view(&self) -> PaneView {
+        PaneView {
+            layout: self.layout,
+            width: self.pane_width,
+            height: self.pane_height,
+            display: self.display,
+        }
+    }
+
+    /// 全ての目標を達成するか、時間切れ・終了・接続の失敗まで、エディタの状態を監視する
+    pub fn monitor_progress(&mut self) -> Result<ExerciseResult> {
+        let result = self.watch_progress();
+        // 画面は stop_exercise がペインを閉じる前に保存する
+        let result = self.capture_on_exit(result);
+        self.event_log.record(SessionEvent::SessionEnded {
+            result: match &result {
+                Ok(result) => format!("{:?}", result),
+                Err(e) => format!("エラー: {}", e),
             },
-            Err(e) => {
-                debug_log!("ファイル読み取りエラー: {}", e);
-                // デフォルト状態
-                return Ok(VimState {
-                    mode: VimMode::Normal,
-                    cursor_line: 0,
-                    cursor_col: 0,
-                    operator: None,
-                    buffer_content: vec!["".to_string()],
-                    registers: std::collections::HashMap::new(),
-                });
-            }
+        });
+        self.event_log.flush();
+        let finished = match &result {
+            Ok(ExerciseResult::Completed) => "完了",
+            Ok(ExerciseResult::TimedOut) => "時間切れ",
+            Ok(ExerciseResult::Failed(_)) | Err(_) => "エラー",
+            Ok(ExerciseResult::Incomplete) => "中断",
+        };
+        if let Some(exercise) = self.current_exercise.clone() {
+            let snapshot = ProgressSnapshot {
+                finished: Some(finished.to_string()),
+                ..self.progress_snapshot(&exercise)
+            };
+            self.backend.report_progress(&snapshot);
+        }
+        self.share.finish(finished);
+        result
+    }
+
+    // 設定の capture_on（--capture-on-exit では毎回）に当たる終わり方なら、ペインの内容を保存し、
+    // 保存したファイルをエラーのメッセージ（エラー以外は案内）とイベントログに残す
+    fn capture_on_exit(&mut self, result: Result<ExerciseResult>) -> Result<ExerciseResult> {
+        let completed = matches!(result, Ok(ExerciseResult::Completed));
+        let failed = matches!(result, Ok(ExerciseResult::Failed(_)) | Err(_));
+        let Some(dir) = self.capture_dir.clone() else {
+            return result;
         };
+        if !self.config.capture_on().captures(completed, failed) {
+            return result;
+        }
+        let files: Vec<String> = write_captures(&self.backend.capture_panes(), &dir, chrono::Utc::now())
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        if files.is_empty() {
+            return result;
+        }
+        self.event_log.record(SessionEvent::PanesCaptured { files: files.clone() });
+        let note = format!("📄 画面を保存しました: {}", files.join(", "));
+        match result {
+            Ok(ExerciseResult::Failed(message)) => Ok(ExerciseResult::Failed(format!("{}\n{}", message, note))),
+            Err(error) => {
+                warning!("{}", note);
+                Err(error)
+            }
+            other => {
+                info!("{}", note);
+                other
+            }
+        }
+    }
 
-        // 状態ファイルから情報をパース
-        // 形式: "LINE:1,COL:1,MODE:n,DETAILED:n"
-        let mut line_num = 1;
-        let mut col_num = 1;
-        let mut mode_str = "n".to_string();
-        let mut mode_detailed = "n".to_string();
+    // 最初の状態を待つ。しばらく届かなければ指示ペインで知らせ、status_timeout_seconds で失敗にする
+    fn wait_for_first_state(&mut self) -> Result<Option<ExerciseResult>> {
+        let label = self.backend.editor().label();
+        let waited = self.unavailable_since.get_or_insert_with(Instant::now).elapsed();
+        let timeout = Duration::from_secs(self.config.status_timeout_seconds);
+        if waited >= timeout {
+            let mut message = format!(
+                "{}秒待っても{}から状態が届きませんでした（{}）",
+                timeout.as_secs(),
+                label,
+                crate::vim_backend::temp_file(STATUS_FILE)
+            );
+            if self.backend.editor_blocked() == Some(true) {
+                message.push_str(&format!(
+                    "\n{}は入力待ちで止まっていました（スワップファイルの確認などが出ていないか確かめてください）",
+                    label
+                ));
+            }
+            return Ok(Some(ExerciseResult::Failed(message)));
+        }
+        if waited >= STATUS_WARN_AFTER
+            && !self.unavailable_warned
+            && let Some(exercise) = self.current_exercise.clone()
+            && self.instructions.is_some()
+        {
+            let mut lines = self.pane_lines(&exercise);
+            lines.push(format!("⌛ {}からの状態を待っています...", label));
+            lines.push(Self::blocked_guidance(label, self.backend.editor_blocked()));
+            self.show_instructions(lines)?;
+            self.unavailable_warned = true;
+        }
+        Ok(None)
+    }
 
-        for line in content.lines() {
-            if line.starts_with("LINE:") {
-                let parts: Vec<&str> = line.split(',').collect();
-                for part in parts {
-                    if let Some(value) = part.strip_prefix("LINE:") {
-                        line_num = value.parse().unwrap_or(1);
-                    } else if let Some(value) = part.strip_prefix("COL:") {
-                        col_num = value.parse().unwrap_or(1);
-                    } else if let Some(value) = part.strip_prefix("MODE:") {
-                        mode_str = value.to_string();
-                    } else if let Some(value) = part.strip_prefix("DETAILED:") {
-                        mode_detailed = value.to_string();
-                    }
-                }
-                break;
+    // 状態が届かないときの手がかり（blocking は nvim_get_mode() で確かめた入力待ち。確かめられなければ None）
+    fn blocked_guidance(label: &str, blocking: Option<bool>) -> String {
+        match blocking {
+            Some(true) => format!("⛔ {}が入力待ちです（blocking: true） — 下のペインを確認してください", label),
+            _ => format!("💡 {}が入力待ちの可能性があります — 下のペインを確認してください", label),
+        }
+    }
+
+    // 接続が切れている間は指示ペインに知らせて再確立を試みる。試行回数を使い切ったら
+    // 最後の接続エラーを付けて失敗にする
+    fn handle_connection_lost(&mut self, error: String) -> Result<Option<ExerciseResult>> {
+        let label = self.backend.editor().label();
+        // 入力待ちで止まっているエディタは起動し直さず、学習者が答えるのを待つ
+        if self.backend.editor_blocked() == Some(true) {
+            if !self.unavailable_warned
+                && let Some(exercise) = self.current_exercise.clone()
+                && self.instructions.is_some()
+            {
+                let mut lines = self.pane_lines(&exercise);
+                lines.push(Self::blocked_guidance(label, Some(true)));
+                self.show_instructions(lines)?;
+                self.unavailable_warned = true;
             }
+            self.backend.pause(RECONNECT_INTERVAL);
+            return Ok(None);
+        }
+        let max_attempts = self.config.reconnect_attempts;
+        if self.reconnect_attempts >= max_attempts {
+            let last_error = self.last_connection_error.take().unwrap_or(error);
+            return Ok(Some(ExerciseResult::Failed(format!(
+                "{}への接続を再確立できませんでした（{}回試行）: {}",
+                label, max_attempts, last_error
+            ))));
+        }
+        self.reconnect_attempts += 1;
+        debug_log!("接続が切れました（{}）。再確立を試みます: {}/{}", error, self.reconnect_attempts, max_attempts);
+
+        if let Some(exercise) = self.current_exercise.clone()
+            && self.instructions.is_some()
+        {
+            let mut lines = self.pane_lines(&exercise);
+            lines.push(format!(
+                "🔌 {}への接続を再確立中... ({}/{})",
+                label, self.reconnect_attempts, max_attempts
+            ));
+            self.show_instructions(lines)?;
         }
+        self.last_connection_error = Some(match self.backend.reconnect() {
+            Ok(()) => error,
+            Err(e) => e.to_string(),
+        });
+        self.backend.pause(RECONNECT_INTERVAL);
+        Ok(None)
+    }
 
-        let vim_mode = VimMode::from_vim_mode(&mode_str, &mode_detailed, None);
+    fn publish_status(&mut self, exercise: &ContinuousExercise, current_state: &VimState) {
+        let status = SharedStatus::new(self.progress_snapshot(exercise), current_state);
+        self.share.update(status);
+    }
 
-        let final_state = VimState {
-            mode: vim_mode,
-            cursor_line: (line_num - 1) as usize, // Vimは1ベース、内部は0ベース
-            cursor_col: (col_num - 1) as usize,
-            operator: None,
-            buffer_content: vec!["".to_string()], // 簡略化
-            registers: std::collections::HashMap::new(),
+    // 練習の進み具合（進捗ファイルと講師の watch に渡す）
+    fn progress_snapshot(&self, exercise: &ContinuousExercise) -> ProgressSnapshot {
+        let descriptions: Vec<String> = exercise.goals.iter().map(|goal_def| goal_def.description.clone()).collect();
+        let mut snapshot = ProgressSnapshot::new(
+            &exercise.title,
+            &descriptions,
+            self.engine.completed(),
+            Some(self.engine.current_index()),
+        );
+        snapshot.hint = HintState {
+            level: self.engine.hint_level(),
+            hints_used: self.hints_used,
         };
+        snapshot.elapsed_secs = self.active_duration().as_secs();
+        snapshot.counters.keystrokes = self.total_keystrokes + self.pending_keystrokes;
+        snapshot.paused = self.paused.is_some();
+        snapshot
+    }
 
-        debug_log!("パース結果: line_num={} -> {}, col_num={} -> {}, mode={}",
-                  line_num, final_state.cursor_line, col_num, final_state.cursor_col, mode_str);
+    // 節目（目標の達成・ヒント・一時停止・再開）ごとに進み具合を書き出す
+    fn report_progress(&mut self, exercise: &ContinuousExercise) {
+        let snapshot = self.progress_snapshot(exercise);
+        self.backend.report_progress(&snapshot);
+    }
 
-        Ok(final_state)
+    // indices の目標のうち置換の目標の pattern と範囲
+    fn substitution_goals(
+        exercise: &ContinuousExercise,
+        indices: impl IntoIterator<Item = usize>,
+    ) -> impl Iterator<Item = (String, Option<(usize, usize)>)> {
+        indices
+            .into_iter()
+            .filter_map(|i| exercise.goals[i].to_goal().ok())
+            .filter_map(|goal| match goal.goal_type {
+                GoalType::SubstitutionResult { pattern, lines, .. } => Some((pattern, lines)),
+                _ => None,
+            })
     }
 
-    // fn check_goals(
-    //     &mut self,
-    //     current_state: &VimState,
-    //     exercise: &ContinuousExercise,
-    // ) -> Result<Option<ExerciseResult>> {
-    //     match exercise.flow_type {
-    //         FlowType::Sequential => self.check_sequential_goals(current_state, exercise),
-    //         FlowType::AnyOrder => self.check_any_order_goals(current_state, exercise),
-    //         FlowType::Parallel => self.check_parallel_goals(current_state, exercise),
-    //     }
-    // }
+    // treesitter の目標（file を付けたものを含む）と、その言語
+    fn syntax_node_goals(
+        exercise: &ContinuousExercise,
+        indices: impl IntoIterator<Item = usize>,
+    ) -> impl Iterator<Item = (usize, Option<String>)> {
+        indices.into_iter().filter_map(|i| {
+            let goal_type = match exercise.goals[i].to_goal().ok()?.goal_type {
+                GoalType::InFile { goal, .. } => *goal,
+                goal_type => goal_type,
+            };
+            match goal_type {
+                GoalType::SyntaxNode { language, .. } => Some((i, language)),
+                _ => None,
+            }
+        })
+    }
 
-    // fn check_sequential_goals(
-    //     &mut self,
-    //     current_state: &VimState,
-    //     exercise: &ContinuousExercise,
-    // ) -> Result<Option<ExerciseResult>> {
-    //     if self.current_goal_index >= exercise.goals.len() {
-    //         return Ok(Some(ExerciseResult::Completed));
-    //     }
+    // 有効な treesitter の目標があれば、カーソルが動くかバッファが変わるたびにカーソルを含むノードを
+    // エディタに問い合わせる（問い合わせられなければ次の状態で試し直す）。
+    // パーサーが無いと分かったら、達成できないまま続けずに理由を返す
+    fn follow_syntax_nodes(&mut self, exercise: &ContinuousExercise, current_state: &VimState) -> Option<String> {
+        let active: Vec<_> = Self::syntax_node_goals(exercise, self.engine.active_goals()).collect();
+        for (index, language) in active {
+            if self.goal_detector.syntax_nodes_current(language.as_deref(), current_state) {
+                continue;
+            }
+            match self.backend.syntax_nodes(language.as_deref()) {
+                Ok(Some(nodes)) => {
+                    debug_log!("🌳 カーソルを含むノード: {}", nodes.join(" < "));
+                    self.goal_detector
+                        .record_syntax_nodes(language.as_deref(), nodes, current_state);
+                }
+                Ok(None) => {
+                    let parser = match &language {
+                        Some(language) => format!("treesitter の {} のパーサー", language),
+                        None => "このファイルの種類の treesitter のパーサー".to_string(),
+                    };
+                    return Some(format!(
+                        "目標{}「{}」は判定できません: {}がありません（:checkhealth nvim-treesitter で確かめられます）",
+                        index + 1,
+                        exercise.goals[index].description,
+                        parser
+                    ));
+                }
+                Err(e) => {
+                    debug_log!("⚠️ カーソルを含むノードを問い合わせられません: {}", e);
+                }
+            }
+        }
+        None
+    }
 
-    //     let current_goal_def = &exercise.goals[self.current_goal_index];
-    //     let goal = self.convert_goal_definition(current_goal_def)?;
+    // 有効な置換の目標の pattern を、有効になった時点とバッファが変わるたびにエディタに数えさせる
+    // （数えられなければ次の状態で試し直す）
+    fn follow_substitutions(&mut self, exercise: &ContinuousExercise, current_state: &VimState) {
+        let active: Vec<_> = Self::substitution_goals(exercise, self.engine.active_goals()).collect();
+        self.goal_detector.retain_substitutions(&active);
+        for (pattern, lines) in active {
+            if self.goal_detector.substitution_counted(&pattern, lines, current_state.changedtick) {
+                continue;
+            }
+            match self.backend.count_matches(&pattern, lines) {
+                Ok(count) => {
+                    debug_log!("🔢 「{}」の数: {}", pattern, count);
+                    self.goal_detector
+                        .count_substitution(&pattern, lines, count, current_state.changedtick);
+                }
+                Err(e) => {
+                    debug_log!("⚠️ 「{}」の数を数えられません: {}", pattern, e);
+                }
+            }
+        }
+    }
 
-    //     if self.goal_detector.check_goal(&goal, current_state) {
-    //         println!("✅ 目標達成: {}", current_goal_def.description);
-    //         self.completed_goals[self.current_goal_index] = true;
-    //         self.current_goal_index += 1;
+    // 達成した目標の on_complete と、有効になった目標の on_activate をまとめて実行させる。
+    // 実行し終えるまでは目標を判定せず、その後の状態から変化を待つ
+    fn run_goal_commands(
+        &mut self,
+        exercise: &ContinuousExercise,
+        completed: &[usize],
+        activated: &[usize],
+    ) -> Result<()> {
+        let mut commands = Vec::new();
+        let triggers = completed
+            .iter()
+            .map(|&i| (i, "on_complete", &exercise.goals[i].on_complete))
+            .chain(activated.iter().map(|&i| (i, "on_activate", &exercise.goals[i].on_activate)));
+        for (index, trigger, goal_commands) in triggers {
+            if goal_commands.is_empty() {
+                continue;
+            }
+            debug_log!("⚙️ 目標{}の{}: {:?}", index + 1, trigger, goal_commands);
+            self.event_log.record(SessionEvent::GoalCommands {
+                index,
+                trigger: trigger.to_string(),
+                commands: goal_commands.clone(),
+            });
+            commands.extend(goal_commands.iter().cloned());
+        }
+        if commands.is_empty() {
+            return Ok(());
+        }
+        self.backend.run_commands(&commands)?;
+        self.goal_commands_running = true;
+        self.engine.wait_for_change();
+        Ok(())
+    }
 
-    //         if self.current_goal_index >= exercise.goals.len() {
-    //             println!("\n🎉 全ての目標を達成しました！");
-    //             return Ok(Some(ExerciseResult::Completed));
-    //         } else {
-    //             println!(
-    //                 "📍 次の目標: {}",
-    //                 exercise.goals[self.current_goal_index].description
-    //             );
-    //             if let Some(hint) = &exercise.goals[self.current_goal_index].hint {
-    //                 println!("💡 ヒント: {}", hint);
-    //             }
-    //         }
-    //     }
+    // 練習用Neovimから届いた目標のコマンドの失敗（"GOAL_COMMAND_FAILED\t<コマンド>\t<エラー>"）
+    fn goal_command_failure(command: &str) -> Option<String> {
+        let detail = command.strip_prefix("GOAL_COMMAND_FAILED\t")?;
+        let (ex_command, error) = detail.split_once('\t').unwrap_or((detail, ""));
+        Some(format!("目標のコマンド「{}」の実行に失敗しました: {}", ex_command, error))
+    }
 
-    //     Ok(None)
-    // }
+    // 順番通りの流れで現在の目標が有効になったことを記録する
+    fn record_goal_activated(&mut self, exercise: &ContinuousExercise) {
+        if matches!(exercise.flow_type, FlowType::Sequential)
+            && let Some(goal_def) = exercise.goals.get(self.engine.current_index())
+        {
+            self.event_log.record(SessionEvent::GoalActivated {
+                index: self.engine.current_index(),
+                description: goal_def.description.clone(),
+            });
+        }
+    }
 
-    // fn check_any_order_goals(
-    //     &mut self,
-    //     current_state: &VimState,
-    //     exercise: &ContinuousExercise,
-    // ) -> Result<Option<ExerciseResult>> {
-    //     let mut progress_made = false;
+    // 次に状態を確かめるまでの間隔（アイドルで一時停止している間は、さらに空ける）
+    fn poll_interval(&self) -> Duration {
+        if self.paused == Some(PauseReason::Idle) {
+            return IDLE_POLL_INTERVAL;
+        }
+        Self::backoff_interval(self.config.poll_interval(), self.last_activity.elapsed())
+    }
 
-    //     for (i, goal_def) in exercise.goals.iter().enumerate() {
-    //         if self.completed_goals[i] {
-    //             continue; // 既に完了している目標はスキップ
-    //         }
+    // 最後の変化から FAST_POLL_WINDOW の間は設定の間隔で、その後は SLOW_POLL_INTERVAL まで空ける
+    fn backoff_interval(fast: Duration, quiet_for: Duration) -> Duration {
+        if quiet_for < FAST_POLL_WINDOW {
+            fast
+        } else {
+            fast.max(SLOW_POLL_INTERVAL)
+        }
+    }
 
-    //         let goal = self.convert_goal_definition(goal_def)?;
-    //         if self.goal_detector.check_goal(&goal, current_state) {
-    //             println!("✅ 目標達成: {}", goal_def.description);
-    //             self.completed_goals[i] = true;
-    //             progress_made = true;
-    //         }
-    //     }
+    // 状態を読んではエンジンに渡し、起きたイベントを処理する。練習が終わったら結果を返す
+    fn watch_progress(&mut self) -> Result<ExerciseResult> {
+        debug_log!("監視開始: status_file={}", STATUS_FILE);
 
-    //     // 全ての目標が完了したかチェック
-    //     if self.completed_goals.iter().all(|&completed| completed) {
-    //         println!("\n🎉 全ての目標を達成しました！");
-    //         return Ok(Some(ExerciseResult::Completed));
-    //     }
+        while self.monitoring_active {
+            self.backend.pause(self.poll_interval());
+            if let Some(result) = self.tend_session()? {
+                return Ok(result);
+            }
 
-    //     if progress_made {
-    //         self.show_remaining_goals(exercise);
-    //     }
+            // 新しい状態が届いていなければ目標を判定しない
+            let timed_state = match self.states.stream(self.backend.as_mut()).next_state()? {
+                StreamItem::Fresh(timed_state) => timed_state,
+                missing => {
+                    if let Some(result) = self.handle_missing_state(missing)? {
+                        return Ok(result);
+                    }
+                    continue;
+                }
+            };
+            self.receive_state(&timed_state.state)?;
 
-    //     Ok(None)
-    // }
+            if let Some(exercise) = self.current_exercise.clone() {
+                let result = match self.judging(&exercise, &timed_state)? {
+                    Judging::Check => {
+                        let events = self.engine.feed(&mut self.goal_detector, &timed_state);
+                        self.goal_detector.record_undo_state(&timed_state.state);
+                        self.goal_detector.record_checked_state(&timed_state.state);
+                        self.dispatch_goal_events(&exercise, &timed_state.state, &events)?
+                    }
+                    Judging::Skip => None,
+                    Judging::Finish(result) => Some(result),
+                };
+                if let Some(result) = result {
+                    return Ok(result);
+                }
+            }
 
-    // fn check_parallel_goals(
-    //     &mut self,
-    //     current_state: &VimState,
-    //     exercise: &ContinuousExercise,
-    // ) -> Result<Option<ExerciseResult>> {
-    //     // 並列目標：全ての目標を同時に満たす必要がある
-    //     let mut all_satisfied = true;
+            self.last_state = Some(timed_state.state);
+        }
 
-    //     for goal_def in &exercise.goals {
-    //         let goal = self.convert_goal_definition(goal_def)?;
-    //         if !self.goal_detector.check_goal(&goal, current_state) {
-    //             all_satisfied = false;
-    //             break;
-    //         }
-    //     }
+        Ok(ExerciseResult::Incomplete)
+    }
 
-    //     if all_satisfied {
-    //         println!("\n🎉 全ての目標を同時に達成しました！");
-    //         return Ok(Some(ExerciseResult::Completed));
-    //     }
+    // 状態を読む前の見回り（生存通知・エディタの終了・制限時間）。練習を終えるなら結果を返す
+    fn tend_session(&mut self) -> Result<Option<ExerciseResult>> {
+        self.write_heartbeat();
+        self.flush_pending_pane()?;
 
-    //     Ok(None)
-    // }
+        // 同じ端末で起動したエディタを学習者が閉じたら、この練習を中断する
+        if self.backend.editor_exited() {
+            debug_log!("エディタが終了しました");
+            return Ok(Some(ExerciseResult::Incomplete));
+        }
 
-    fn convert_goal_definition(&self, goal_def: &ExerciseGoal) -> Result<Goal> {
-        debug_log!("目標変換: type={}, target={:?}", goal_def.goal_type, goal_def.target);
-        
-        let goal_type = match goal_def.goal_type.as_str() {
-            "position" => {
-                let target = goal_def
-                    .target
-                    .as_array()
-                    .ok_or_else(|| anyhow::anyhow!("Position target must be an array"))?;
-                let line = target[0].as_u64().unwrap_or(0) as usize;
-                let col = target[1].as_u64().unwrap_or(0) as usize;
-                debug_log!("Position目標: line={}, col={}", line, col);
-                GoalType::Position { line, col }
-            }
-            "mode" => {
-                let mode_str = goal_def
-                    .target
-                    .as_str()
-                    .ok_or_else(|| anyhow::anyhow!("Mode target must be a string"))?;
-                let vim_mode = match mode_str {
-                    "normal" => VimMode::Normal,
-                    "insert" => VimMode::Insert,
-                    "visual" => VimMode::Visual,
-                    "visual_line" => VimMode::VisualLine,
-                    "visual_block" => VimMode::VisualBlock,
-                    "command" => VimMode::Command,
-                    op if op.starts_with("operator_") => {
-                        let operator = op.strip_prefix("operator_").unwrap_or("");
-                        VimMode::OperatorPending(operator.to_string())
-                    }
-                    _ => return Err(anyhow::anyhow!("Unknown mode: {}", mode_str)),
-                };
-                GoalType::Mode(vim_mode)
-            }
-            "text" => {
-                let target = goal_def
-                    .target
-                    .as_object()
-                    .ok_or_else(|| anyhow::anyhow!("Text target must be an object"))?;
-                let line = target["line"].as_u64().unwrap_or(0) as usize;
-                let expected = target["expected"].as_str().unwrap_or("").to_string();
-                GoalType::TextContent { line, expected }
-            }
-            "register" => {
-                let target = goal_def
-                    .target
-                    .as_object()
-                    .ok_or_else(|| anyhow::anyhow!("Register target must be an object"))?;
-                let register = target["register"].as_str().unwrap_or("").to_string();
-                let expected = target["expected"].as_str().unwrap_or("").to_string();
-                GoalType::RegisterContent { register, expected }
-            }
-            "buffer_change" => GoalType::BufferChange,
-            _ => return Err(anyhow::anyhow!("Unknown goal type: {}", goal_def.goal_type)),
+        // 制限時間の確認（ポーリングごとに判定し、期限切れなら即座に終了）
+        let Some(exercise) = self.current_exercise.clone() else {
+            return Ok(None);
         };
+        if self.update_time_limit(&exercise)? {
+            self.show_timeout_message(&exercise)?;
+            self.backend.pause(Duration::from_millis(1500));
+            return Ok(Some(ExerciseResult::TimedOut));
+        }
+        self.update_clock(&exercise)?;
+        Ok(None)
+    }
 
-        Ok(Goal {
-            goal_type,
-            description: goal_def.description.clone(),
-        })
+    // 新しい状態が届かなかったときの処理。練習を終えるなら結果を返す
+    fn handle_missing_state(&mut self, missing: StreamItem) -> Result<Option<ExerciseResult>> {
+        match missing {
+            // Neovimが setup を終えて状態を書き出すまで待つ（いつまでも届かなければ失敗にする）
+            StreamItem::Unavailable => self.wait_for_first_state(),
+            // 状態ファイルが止まったら再接続を試み、回数を使い切ったら練習を失敗にする
+            StreamItem::Stale { age, .. } => {
+                let error = format!("状態ファイルが{}秒間更新されていません", age.as_secs());
+                self.handle_connection_lost(error)
+            }
+            StreamItem::Ended => Ok(Some(ExerciseResult::Incomplete)),
+            StreamItem::Fresh(_) => Ok(None),
+        }
     }
 
-    // fn show_remaining_goals(&self, exercise: &ContinuousExercise) {
-    //     println!("📋 残りの目標:");
-    //     for (i, goal_def) in exercise.goals.iter().enumerate() {
-    //         if !self.completed_goals[i] {
-    //             println!("  • {}", goal_def.description);
-    //         }
-    //     }
-    //     println!();
-    // }
+    // 届いた状態を記録し、再接続できたことを指示ペインに反映して、キー入力を集計する
+    fn receive_state(&mut self, current_state: &VimState) -> Result<()> {
+        if self.unavailable_warned && self.reconnect_attempts == 0
+            && let Some(exercise) = self.current_exercise.clone()
+            && self.instructions.is_some()
+        {
+            self.update_instruction_pane(&exercise)?;
+        }
+        self.unavailable_since = None;
+        self.unavailable_warned = false;
+        if self.reconnect_attempts > 0 {
+            debug_log!("接続を再確立しました");
+            self.reconnect_attempts = 0;
+            self.last_connection_error = None;
+            if let Some(exercise) = self.current_exercise.clone()
+                && self.instructions.is_some()
+            {
+                self.update_instruction_pane(&exercise)?;
+            }
+        }
 
-    fn show_completion_message(&self, exercise: &ContinuousExercise) -> Result<()> {
-        if let Some(pane_id) = &self.instruction_pane_id {
-            let completion_command = format!(
-                "clear; echo '=== 🎉 章完了！ ==='; echo '{}'; echo ''; echo '✅ 全ての目標を達成しました！'; echo ''; echo '📋 達成した目標:'; {} echo '';",
-                exercise.title.replace("'", "'\\''"),
-                exercise.goals.iter().enumerate().map(|(i, goal)| 
-                    format!("echo '  {}. {}'", i + 1, goal.description.replace("'", "'\\''"))
-                ).collect::<Vec<_>>().join("; ")
-            );
-            
-            let _ = Command::new("tmux")
-                .args(["send-keys", "-t", pane_id, &completion_command, "Enter"])
-                .output();
-            
-            debug_log!("完了メッセージ表示: {}", pane_id);
+        if self.last_state.as_ref() != Some(current_state) {
+            self.event_log.record(SessionEvent::StateSample {
+                state: Box::new(current_state.clone()),
+            });
+        }
+        debug_log!("現在の状態: line={}, col={}, mode={:?}", 
+                  current_state.cursor_line, current_state.cursor_col, current_state.mode);
+
+        // 状態ファイルより先に書き出されたキー入力を集計（一時停止中の入力は数えない）
+        if self.paused.is_some() {
+            self.backend.take_key_log();
+            self.motion_parser.reset();
+            self.operator_parser = OperatorParser::default();
+            self.dot_counter.reset();
+            self.text_object_parser.reset();
+        } else {
+            self.collect_keystrokes(current_state);
         }
         Ok(())
     }
 
-    pub fn stop_exercise(&mut self) -> Result<()> {
-        self.monitoring_active = false;
+    // 届いた要求を処理し、この状態で目標を判定するかを決める
+    fn judging(&mut self, exercise: &ContinuousExercise, timed_state: &TimedState) -> Result<Judging> {
+        let current_state = &timed_state.state;
+        let goal_commands_done = match self.take_commands(exercise, current_state)? {
+            ControlFlow::Break(result) => return Ok(Judging::Finish(result)),
+            ControlFlow::Continue(done) => done,
+        };
+
+        // 一時停止中は目標を判定せず、ヒントの時間も数えない
+        self.follow_attachment(exercise, current_state)?;
+        self.follow_activity(exercise, current_state)?;
+        self.publish_status(exercise, current_state);
+        if self.paused.is_some() {
+            return Ok(Judging::Skip);
+        }
 
-        // tmuxセッションをクリーンアップ
-        let session_name = "vim_tutorial_continuous";
-        let _ = Command::new("tmux")
-            .args(["kill-session", "-t", session_name])
-            .output();
+        // 解答の再生中は、再生が終わって学習者が選ぶまで目標を判定しない
+        if self.demo.is_some() {
+            if !self.goal_commands_running && !goal_commands_done {
+                self.follow_demo(exercise, current_state)?;
+            }
+            return Ok(Judging::Skip);
+        }
 
-        // 状態ファイルをクリーンアップ
-        let _ = fs::remove_file("/tmp/vim_continuous_status.json");
-        let _ = fs::remove_file("/tmp/vim_continuous_success.flag");
+        // 目標が進まない時間を計測し、必要なら自動でヒントを表示
+        self.update_hint_timer(exercise, timed_state)?;
+        self.follow_coaching(exercise)?;
 
-        // RPC クライアントも停止
-        self.vim_client.stop()?;
+        // 目標のコマンドが変えた状態では目標を判定しない（実行し終えた次の状態から変化を待つ）
+        if self.goal_commands_running || goal_commands_done {
+            self.engine.wait_for_change();
+            return Ok(Judging::Skip);
+        }
+        self.follow_substitutions(exercise, current_state);
+        if let Some(failure) = self.follow_syntax_nodes(exercise, current_state) {
+            return Ok(Judging::Finish(ExerciseResult::Failed(failure)));
+        }
 
-        println!("📱 セッションを終了しました");
-        Ok(())
+        // リセット直後の状態では目標を判定しない
+        if !self.engine.accepts(current_state) {
+            return Ok(Judging::Skip);
+        }
+        self.follow_conduct(exercise, current_state)?;
+        Ok(Judging::Check)
     }
 
-    // pub fn send_keys(&self, keys: &str) -> Result<()> {
-    //     self.vim_client.send_keys(keys)
-    // }
+    // :TutorialHint / :TutorialReset / :TutorialPause などの要求を処理する。
+    // 練習を終えるなら Break、続けるなら目標のコマンドを実行し終えたかを Continue で返す
+    fn take_commands(&mut self, exercise: &ContinuousExercise, current_state: &VimState) -> Result<ControlFlow<ExerciseResult, bool>> {
+        let mut goal_commands_done = false;
+        for command in self.backend.take_commands() {
+            if let Some(failure) = Self::setup_failure(&command) {
+                return Ok(ControlFlow::Break(ExerciseResult::Failed(failure)));
+            }
+            if command == "GOAL_COMMANDS_DONE" {
+                self.goal_commands_running = false;
+                goal_commands_done = true;
+                continue;
+            }
+            if let Some(failure) = Self::goal_command_failure(&command) {
+                debug_log!("⚠️ {}", failure);
+                self.goal_command_failure = Some(failure);
+                self.update_instruction_pane(exercise)?;
+                continue;
+            }
+            self.handle_tutorial_command(&command, exercise, current_state)?;
+        }
+        Ok(ControlFlow::Continue(goal_commands_done))
+    }
 
-    // pub fn get_current_state(&self) -> Result<VimState> {
-    //     self.vim_client.get_current_state()
-    // }
-}
+    // 目標を判定して起きたイベントを処理する。練習を終えるなら結果を返す
+    fn dispatch_goal_events(
+        &mut self,
+        exercise: &ContinuousExercise,
+        current_state: &VimState,
+        events: &[GoalEvent],
+    ) -> Result<Option<ExerciseResult>> {
+        let previous_notice = self.conduct_notice.clone();
+        self.follow_conduct_events(events);
 
-#[derive(Debug, PartialEq)]
-pub enum ExerciseResult {
-    Completed,
-    Incomplete,
-    #[allow(dead_code)] // エラーハンドリング用
-    Failed(String),
-}
+        let newly_completed: Vec<usize> = events
+            .iter()
+            .filter_map(|event| match event {
+                GoalEvent::Completed { index } => Some(*index),
+                _ => None,
+            })
+            .collect();
+        if newly_completed.is_empty() {
+            if self.conduct_notice != previous_notice {
+                self.update_instruction_pane(exercise)?;
+            }
+            self.update_operator_pending(exercise, current_state)?;
+            self.update_text_diff(exercise, current_state)?;
+            self.update_stalled_text(exercise, current_state)?;
+            self.update_position(exercise, current_state)?;
+            return Ok(None);
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
-    // use std::fs;
-    use tempfile::tempdir;
+        // 今回の判定より前に達成していた目標
+        let previously_completed: Vec<bool> = self
+            .engine
+            .completed()
+            .iter()
+            .enumerate()
+            .map(|(i, &done)| done && !newly_completed.contains(&i))
+            .collect();
+        self.record_keystroke_score(exercise, &previously_completed);
+        for &i in &newly_completed {
+            debug_log!("✅ 目標達成: {}", exercise.goals[i].description);
+            self.event_log.record(SessionEvent::GoalCompleted {
+                index: i,
+                description: exercise.goals[i].description.clone(),
+            });
+        }
+        let activated: Vec<usize> = events
+            .iter()
+            .filter_map(|event| match event {
+                GoalEvent::Activated { index } => Some(*index),
+                _ => None,
+            })
+            .collect();
+        if !activated.is_empty() {
+            self.record_goal_activated(exercise);
+        }
+        self.run_goal_commands(exercise, &newly_completed, &activated)?;
 
-    // fn create_test_exercise() -> ContinuousExercise {
-    //     ContinuousExercise {
-    //         title: "Test Exercise".to_string(),
-    //         description: "A test exercise".to_string(),
-    //         sample_code: vec!["hello world".to_string()],
-    //         goals: vec![
-    //             ExerciseGoal {
-    //                 goal_type: "position".to_string(),
-    //                 target: json!([0, 5]),
-    //                 description: "Move to position 0,5".to_string(),
-    //                 hint: Some("Use 'l' key to move right".to_string()),
-    //             },
-    //             ExerciseGoal {
-    //                 goal_type: "mode".to_string(),
-    //                 target: json!("insert"),
-    //                 description: "Enter insert mode".to_string(),
-    //                 hint: None,
-    //             },
-    //         ],
-    //         flow_type: FlowType::Sequential,
+        if events.contains(&GoalEvent::ExerciseDone) {
+            return self.complete_exercise(exercise, current_state).map(Some);
+        }
+        self.advance_goal(exercise, current_state)?;
+        Ok(None)
+    }
+
+    // 全ての目標を達成した練習を締めくくる（完了を知らせ、メニューに戻る）
+    fn complete_exercise(&mut self, exercise: &ContinuousExercise, current_state: &VimState) -> Result<ExerciseResult> {
+        self.report_progress(exercise);
+        self.publish_status(exercise, current_state);
+        if self.config.bell || self.theme.announces_goals() {
+            self.announce(&format!("🎉 練習「{}」を完了しました！", exercise.title));
+        }
+        if self.config.desktop_notifications {
+            notify::send_desktop_notification(
+                "🎉 練習完了",
+                &format!("練習「{}」を完了しました", exercise.title),
+            );
+        }
+        debug_log!("🎉 全ての目標を達成しました！");
+
+        // 章完了時にメニューに戻る
+        self.show_completion_message(exercise)?;
+        self.backend.pause(Duration::from_millis(2000));
+        Ok(ExerciseResult::Completed)
+    }
+
+    // 次の目標に進む（前の目標の表示を片付け、指示ペインに新しい目標を出す）
+    fn advance_goal(&mut self, exercise: &ContinuousExercise, current_state: &VimState) -> Result<()> {
+        let current_index = self.engine.current_index();
+        self.report_progress(exercise);
+        self.write_manifest(exercise, current_state);
+        if self.config.bell || self.theme.announces_goals() {
+            self.announce(&format!(
+                "✅ 目標達成！（{}/{}）次: {}",
+                self.engine.completed().iter().filter(|&&done| done).count(),
+                exercise.goals.len(),
+                exercise.goals[current_index].description
+            ));
+        }
+
+        self.text_diff_shown.clear();
+        self.stalled_text_shown.clear();
+        self.nudge_shown = None;
+        self.operator_pending_shown = None;
+        self.coaching_shown = None;
+        self.goal_command_failure = None;
+        self.conduct_notice = None;
+        self.intro_shown = false;
+        self.countdown_shown = self.engine.next_hint_in().map(Self::countdown_display);
+
+        // 上部ペインを更新（新しい目標を表示）
+        self.update_instruction_pane(exercise)?;
+
+        debug_log!("📍 次の目標: {}", exercise.goals[current_index].description);
+
+        // 少し待ってから進捗を反映
+        self.backend.pause(Duration::from_millis(500));
+        Ok(())
+    }
+
+    // 練習を始める前に端末に表示する説明・サンプルコード・目標の一覧
+    pub fn briefing_lines(exercise: &ContinuousExercise) -> Vec<String> {
+        let mut lines = vec![
+            String::new(),
+            format!("🎯 === {} ===", exercise.title),
+            exercise.description.clone(),
+            String::new(),
+        ];
+        if let Some(intro) = &exercise.intro {
+            lines.extend(intro.lines().map(|line| format!("  {}", line)));
+            lines.push(String::new());
+        }
+
+        // サンプルコード（複数のファイルを開く練習はファイルごと）
+        if exercise.files.is_empty() {
+            lines.push("📝 サンプルコード:".to_string());
+            lines.extend(exercise.sample_code.iter().enumerate().map(|(i, line)| format!("{:2}: {}", i + 1, line)));
+        }
+        for file in &exercise.files {
+            lines.push(format!("📄 {}:", file.name));
+            lines.extend(file.content.iter().enumerate().map(|(i, line)| format!("{:2}: {}", i + 1, line)));
+        }
+        if let Some([line, col]) = exercise.cursor_start {
+            lines.push(format!("📍 開始位置: {}行{}列", line + 1, col + 1));
+        }
+        lines.push(String::new());
+
+        lines.push("🎯 学習目標:".to_string());
+        for (i, goal) in exercise.goals.iter().enumerate() {
+            lines.push(format!("  {}. {}", i + 1, goal.description));
+            if let Some(hint) = &goal.hint {
+                lines.push(format!("     💡 ヒント: {}", hint));
+            }
+        }
+        lines.push(String::new());
+        lines
+    }
+
+    // 達成した練習の outro（無ければ空）
+    pub fn outro_lines(exercise: &ContinuousExercise) -> Vec<String> {
+        let Some(outro) = &exercise.outro else {
+            return Vec::new();
+        };
+        let mut lines = vec![String::new(), format!("🏁 {}", exercise.title)];
+        lines.extend(outro.lines().map(|line| format!("  {}", line)));
+        lines.push(String::new());
+        lines
+    }
+
+    // 達成した練習の outro を端末に表示する（tmux から戻った後に呼ぶ）
+    pub fn show_outro(&self, exercise: &ContinuousExercise) {
+        let lines = Self::outro_lines(exercise);
+        if lines.is_empty() {
+            return;
+        }
+        for line in lines {
+            println!("{}", self.theme.line(&line));
+        }
+        if self.pacing {
+            Self::wait_for_enter(&self.theme.line("▶️ Enter で次へ進みます..."));
+        }
+    }
+
+    fn wait_for_enter(prompt: &str) {
+        print!("{}", prompt);
+        let _ = io::stdout().flush();
+        let mut input = String::new();
+        let _ = io::stdin().read_line(&mut input);
+    }
+
+    // 練習用Neovimから届いた setup の失敗（"SETUP_FAILED\t<コマンド>\t<エラー>"）
+    fn setup_failure(command: &str) -> Option<String> {
+        let detail = command.strip_prefix("SETUP_FAILED\t")?;
+        let (ex_command, error) = detail.split_once('\t').unwrap_or((detail, ""));
+        Some(format!(
+            "setup コマンド「{}」の実行に失敗しました: {}",
+            ex_command, error
+        ))
+    }
+
+    // teardown を実行してからNeovimを閉じ、失敗したコマンドがあれば知らせる
+    fn run_teardown(&mut self) -> Result<()> {
+        let Some(exercise) = &self.current_exercise else {
+            return Ok(());
+        };
+        if exercise.teardown.is_empty() {
+            return Ok(());
+        }
+        self.backend.send_keys(&["Escape"])?;
+        self.backend.send_keys(&[
+            ":call TutorialRunCommands(g:tutorial_teardown, 'TEARDOWN_FAILED') | qa!",
+            "Enter",
+        ])?;
+        self.backend.pause(Duration::from_millis(300));
+
+        for command in self.backend.take_commands() {
+            if let Some(detail) = command.strip_prefix("TEARDOWN_FAILED\t") {
+                let (ex_command, error) = detail.split_once('\t').unwrap_or((detail, ""));
+                info!(
+                    "⚠️ teardown コマンド「{}」の実行に失敗しました: {}",
+                    ex_command, error
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_tutorial_command(
+        &mut self,
+        command: &str,
+        exercise: &ContinuousExercise,
+        current_state: &VimState,
+    ) -> Result<()> {
+        match command {
+            "RESUME" => self.resume(exercise)?,
+            pause if pause == "PAUSE" || pause.starts_with("PAUSE\t") => {
+                // Neovimから届いたバッファの内容（一時停止した時点のサンプルファイル）
+                let buffer = pause
+                    .strip_prefix("PAUSE\t")
+                    .and_then(|json| serde_json::from_str(json).ok());
+                self.pause(PauseReason::Command, exercise, current_state, buffer)?;
+            }
+            ex_command if ex_command.starts_with("EX_COMMAND\t") => {
+                self.goal_detector.record_ex_command(&ex_command["EX_COMMAND\t".len()..]);
+            }
+            // 一時停止中はヒントやリセットを受け付けない
+            other if self.paused.is_some() => {
+                debug_log!("一時停止中のため無視: {}", other);
+            }
+            // 解答まで表示した後のヒントの要求は、解答の再生にする
+            "HINT_REQUEST" if self.engine.hint_level() >= 2 && self.demo.is_none() => self.start_demo(exercise)?,
+            "DEMO_REQUEST" => self.start_demo(exercise)?,
+            "DEMO_KEEP" => self.finish_demo(exercise, false)?,
+            "DEMO_RESTORE" => self.finish_demo(exercise, true)?,
+            "HINT_REQUEST" => {
+                // これ以上の段階が無い場合は現在のヒントを再表示するだけ
+                if self.engine.request_hint() {
+                    self.hints_used += 1;
+                    self.countdown_shown = self.engine.next_hint_in().map(Self::countdown_display);
+                }
+                debug_log!("💡 ヒント要求: level={}", self.engine.hint_level());
+                self.event_log.record(SessionEvent::HintShown {
+                    index: self.engine.current_index(),
+                    level: self.engine.hint_level(),
+                });
+                self.report_progress(exercise);
+                self.update_instruction_pane(exercise)?;
+            }
+            "RESET" => {
+                debug_log!("🔄 リセット要求");
+                // 順不同の場合は途中まで満たした目標も取り消す
+                if matches!(exercise.flow_type, FlowType::AnyOrder) {
+                    self.engine.restart();
+                    self.keystroke_scores.clear();
+                }
+                self.engine.hold_until_moved(current_state);
+                self.pending_keystrokes = 0;
+                self.goal_detector.clear_motions();
+                self.goal_detector.clear_substitutions();
+                self.engine.clear_conduct();
+                self.conduct_notice = None;
+                // 元に戻したバッファに、有効な目標の on_activate をもう一度反映する
+                let active = self.engine.active_goals();
+                self.run_goal_commands(exercise, &[], &active)?;
+                self.update_instruction_pane(exercise)?;
+            }
+            "RETRY" => {
+                // サンプルコード・カーソル・レジスタはエディタ側で戻してあるため、目標を全て最初からにする
+                self.attempts += 1;
+                debug_log!("🔁 やり直し: {}回目", self.attempts);
+                self.engine.restart();
+                self.engine.clear_conduct();
+                self.engine.reset_hint_timer();
+                self.engine.activate_from(&self.goal_detector, current_state);
+                self.keystroke_scores.clear();
+                self.pending_keystrokes = 0;
+                self.total_keystrokes = 0;
+                self.engine.hold_until_moved(current_state);
+                self.goal_detector.clear_motions();
+                self.goal_detector.clear_substitutions();
+                self.conduct_notice = None;
+                self.countdown_shown = self.engine.next_hint_in().map(Self::countdown_display);
+                self.event_log.record(SessionEvent::Retried { attempt: self.attempts });
+                let active = self.engine.active_goals();
+                self.run_goal_commands(exercise, &[], &active)?;
+                self.record_goal_activated(exercise);
+                self.report_progress(exercise);
+                self.update_instruction_pane(exercise)?;
+            }
+            other => {
+                debug_log!("未知のコマンド: {}", other);
+            }
+        }
+        Ok(())
+    }
+
+    // tmuxから離れたら一時停止し、戻ったら再開する（:TutorialPause の停止は :TutorialResume まで続く）
+    fn follow_attachment(&mut self, exercise: &ContinuousExercise, current_state: &VimState) -> Result<()> {
+        let attached = self.backend.learner_attached();
+        self.learner_seen |= attached;
+        match self.paused {
+            None if !attached && self.learner_seen => {
+                self.pause(PauseReason::Detached, exercise, current_state, None)
+            }
+            Some(PauseReason::Detached) if attached => self.resume(exercise),
+            _ => Ok(()),
+        }
+    }
+
+    // 状態が変わらないまま idle_threshold_seconds たったら続けるか尋ね、
+    // さらに idle_grace_seconds たっても変わらなければ一時停止する（状態が変われば再開する）
+    fn follow_activity(&mut self, exercise: &ContinuousExercise, current_state: &VimState) -> Result<()> {
+        let changed = self
+            .last_state
+            .as_ref()
+            .is_some_and(|last| last != current_state);
+        if changed {
+            self.last_activity = Instant::now();
+            let prompted = std::mem::take(&mut self.idle_prompted);
+            return match self.paused {
+                Some(PauseReason::Idle) => self.resume(exercise),
+                None if prompted => self.update_instruction_pane(exercise),
+                _ => Ok(()),
+            };
+        }
+        // 他の理由で止めている間とタイムチャレンジ中は尋ねない
+        if self.paused.is_some() || self.time_limit.is_some() {
+            return Ok(());
+        }
+        let idle = self.last_activity.elapsed();
+        let threshold = Duration::from_secs(self.config.idle_threshold_seconds);
+        let grace = Duration::from_secs(self.config.idle_grace_seconds);
+        if self.idle_prompted && idle >= threshold + grace {
+            debug_log!("💤 アイドルのため一時停止: {}秒", idle.as_secs());
+            self.idle_prompted = false;
+            return self.pause(PauseReason::Idle, exercise, current_state, None);
+        }
+        if !self.idle_prompted && idle >= threshold {
+            debug_log!("💤 アイドル状態: {}秒", idle.as_secs());
+            self.idle_prompted = true;
+            // 目に入るよう先頭に出す
+            let mut lines = self.pane_lines(exercise);
+            lines.insert(0, "💤 アイドル状態です — 続けますか？（何か操作すると続けます）".to_string());
+            self.show_instructions(lines)?;
+        }
+        Ok(())
+    }
+
+    // 同じ移動キーを COACHING_REPEAT 回より多く続けて押していたら、より良い移動を勧める
+    // （目標の判定とは関係なく、助言は COACHING_INTERVAL に1回まで）
+    fn follow_coaching(&mut self, exercise: &ContinuousExercise) -> Result<()> {
+        if !self.config.coaching || exercise.coaching == Some(false) {
+            return Ok(());
+        }
+        let Some((motion, times)) = self.repeat_run.current() else {
+            return Ok(());
+        };
+        if times <= COACHING_REPEAT || self.coached_at.is_some_and(|at| at.elapsed() < COACHING_INTERVAL) {
+            return Ok(());
+        }
+        let Some(tip) = keystrokes::coaching_tip(motion) else {
+            return Ok(());
+        };
+        let tip = format!("{}を{}回続けて押しています。{}", motion, times, tip);
+        debug_log!("💬 コーチング: {}", tip);
+        self.repeat_run.reset();
+        self.coached_at = Some(Instant::now());
+        self.coaching_shown = Some(tip);
+        self.update_instruction_pane(exercise)
+    }
+
+    // 一時停止と練習中の印に残す目標の進み具合（一時停止を保存しない練習では None）
+    fn paused_record(
+        &self,
+        exercise: &ContinuousExercise,
+        current_state: &VimState,
+        buffer: Vec<String>,
+    ) -> Option<PausedExercise> {
+        let target = self.pause_target.as_ref()?;
+        Some(PausedExercise {
+            chapter_number: target.chapter_number,
+            exercise_index: target.exercise_index,
+            exercise_title: exercise.title.clone(),
+            earlier_exercises: target.earlier_exercises.clone(),
+            current_goal_index: self.engine.current_index(),
+            completed_goals: self.engine.completed().to_vec(),
+            elapsed_secs: self.active_duration().as_secs_f64(),
+            hints_used: self.hints_used,
+            buffer,
+            cursor: [current_state.cursor_line, current_state.cursor_col],
+            paused_at: chrono::Utc::now(),
+            content_hash: Some(exercise.content_hash()),
+        })
+    }
+
+    // 監視側のプロセスが落ちても次の起動で再接続できるよう、エディタの居場所と目標の進み具合を書く
+    fn write_manifest(&self, exercise: &ContinuousExercise, current_state: &VimState) {
+        let Some(path) = &self.manifest_path else {
+            return;
+        };
+        let (Some(running), Some(progress)) = (
+            self.backend.running_session(),
+            self.paused_record(exercise, current_state, Vec::new()),
+        ) else {
+            return;
+        };
+        let manifest = SessionManifest {
+            pid: std::process::id(),
+            updated_at: chrono::Utc::now(),
+            running,
+            progress,
+        };
+        if let Err(e) = recovery::write(path, &manifest) {
+            debug_log!("練習中の印を書けません: {}", e);
+        }
+    }
+
+    // 進捗ファイルにハートビートを書く（HEARTBEAT_INTERVAL ごとと、アイドルかどうかが変わったとき）
+    fn write_heartbeat(&mut self) {
+        if !self.heartbeat_enabled {
+            return;
+        }
+        let idle = self.idle_prompted || self.paused == Some(PauseReason::Idle);
+        let due = self.heartbeat_written.is_none_or(|(written, written_idle)| {
+            written.elapsed() >= HEARTBEAT_INTERVAL || written_idle != idle
+        });
+        if due {
+            progress::record_heartbeat(Some(SessionHeartbeat::now(idle)));
+            self.heartbeat_written = Some((Instant::now(), idle));
+        }
+    }
+
+    fn pause(
+        &mut self,
+        reason: PauseReason,
+        exercise: &ContinuousExercise,
+        current_state: &VimState,
+        buffer: Option<Vec<String>>,
+    ) -> Result<()> {
+        if self.paused.is_some() {
+            return Ok(());
+        }
+        // 制限時間のある練習では止めない
+        if self.time_limit.is_some() {
+            if reason == PauseReason::Command {
+                self.announce("⏱️ タイムチャレンジ中は一時停止できません");
+            }
+            return Ok(());
+        }
+        debug_log!("⏸️ 一時停止: {:?} goal_index={}", reason, self.engine.current_index());
+        self.paused = Some(reason);
+        self.paused_since = Some(Instant::now());
+        self.event_log.record(SessionEvent::Paused {
+            index: self.engine.current_index(),
+        });
+
+        // プロセスが終了しても同じ章の開始時に続きから始められるように保存する
+        // （バッファを受け取れなかった場合は、監視しているバッファかサンプルコードを使う）
+        let buffer = buffer
+            .or_else(|| Some(current_state.buffer_content.clone()).filter(|lines| !lines.is_empty()))
+            .unwrap_or_else(|| exercise.starting_lines().to_vec());
+        if let Some(paused) = self.paused_record(exercise, current_state, buffer) {
+            progress::record_paused_exercise(Some(paused));
+        }
+        self.report_progress(exercise);
+
+        let lines = self.paused_pane_lines(exercise, reason);
+        self.show_instructions(lines)
+    }
+
+    fn resume(&mut self, exercise: &ContinuousExercise) -> Result<()> {
+        let Some(since) = self.paused_since.take() else {
+            return Ok(());
+        };
+        debug_log!("▶️ 再開: goal_index={}", self.engine.current_index());
+        self.paused = None;
+        self.paused_time += since.elapsed();
+        self.engine.hold_hint_timer();
+        self.last_activity = Instant::now();
+        // 一時停止中の操作を達成扱いにしないよう、再開後の状態から変化を待つ
+        self.engine.wait_for_change();
+        self.goal_detector.clear_motions();
+        if self.pause_target.is_some() {
+            progress::record_paused_exercise(None);
+        }
+        self.event_log.record(SessionEvent::Resumed {
+            index: self.engine.current_index(),
+        });
+        self.report_progress(exercise);
+        self.update_instruction_pane(exercise)
+    }
+
+    fn paused_pane_lines(&self, exercise: &ContinuousExercise, reason: PauseReason) -> Vec<String> {
+        let resume_line = match reason {
+            PauseReason::Command => "▶️ :TutorialResume で再開します",
+            PauseReason::Detached => "▶️ tmuxセッションに戻ると再開します",
+            PauseReason::Idle => "▶️ 何か操作すると再開します",
+        };
+        pane::paused(exercise, &self.progress_snapshot(exercise), resume_line, &self.pane_view())
+    }
+
+    // キーログは状態ファイルより先に書き出されるため、current_state はこの分のキーを打った後の状態
+    fn collect_keystrokes(&mut self, current_state: &VimState) {
+        let key_log = self.backend.take_key_log();
+        self.goal_detector.record_key_log(&key_log);
+        let count: usize = key_log.iter().map(|line| keystrokes::count_log_line(line)).sum();
+        self.pending_keystrokes += count;
+        self.total_keystrokes += count;
+        let motions: Vec<_> = key_log
+            .iter()
+            .flat_map(|line| self.motion_parser.feed(line))
+            .collect();
+        motions.iter().for_each(|motion_use| self.repeat_run.record(motion_use));
+        let dots = self.dot_counter.feed(&motions);
+        let text_objects = key_log.iter().filter_map(|line| self.text_object_parser.feed(line)).collect();
+        self.goal_detector.record_key_uses(dots, text_objects, current_state.changedtick);
+        self.goal_detector.record_motions(motions);
+        let operators: Vec<_> = key_log.iter().filter_map(|line| self.operator_parser.feed(line)).collect();
+        for operator in operators {
+            self.engine.record_operator(operator);
+        }
+    }
+
+    // 有効な目標ごとに、許していないモードに入ったかを状態の移り変わりから記録する。
+    // 入ったら目標をやり直しにして理由を指示ペインに出す
+    fn follow_conduct(&mut self, exercise: &ContinuousExercise, current_state: &VimState) -> Result<()> {
+        let events = self.engine.follow_conduct(&self.goal_detector, current_state);
+        if self.follow_conduct_events(&events) {
+            self.update_instruction_pane(exercise)?;
+        }
+        Ok(())
+    }
+
+    // 達成のしかたの決まりを破ったイベントを、指示ペインに出す理由にする（理由が変われば true）
+    fn follow_conduct_events(&mut self, events: &[GoalEvent]) -> bool {
+        let mut notice = None;
+        for event in events {
+            match event {
+                GoalEvent::ModeRefused { index, mode } => {
+                    debug_log!("🚫 目標{}で許していないモード: {:?}", index + 1, mode);
+                    notice = Some(format!(
+                        "🚫 目標{}は{}モードを使わずに達成してください（やり直しになります）",
+                        index + 1,
+                        mode.label()
+                    ));
+                }
+                GoalEvent::OperatorMissing { index, operator } => {
+                    debug_log!("🚫 目標{}はオペレーター{}を使わずに届きました", index + 1, operator);
+                    notice = Some(format!(
+                        "🚫 目標{}は {} を使って達成してください（u で戻してやり直せます）",
+                        index + 1,
+                        operator
+                    ));
+                }
+                _ => {}
+            }
+        }
+        if notice.is_none() {
+            return false;
+        }
+        self.conduct_notice = notice;
+        true
+    }
+
+    // 今回達成した目標にキー数を割り当てる（同時に達成した目標はまとめて1件の採点にする）
+    fn record_keystroke_score(&mut self, exercise: &ContinuousExercise, previously_completed: &[bool]) {
+        // キー入力を記録できないエディタ（Vim・古い Neovim）では採点しない（0キー扱いで満点にしない）
+        if !self.backend.captures_keystrokes() {
+            return;
+        }
+        let newly_completed: Vec<&ExerciseGoal> = exercise
+            .goals
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| self.engine.completed()[i] && !previously_completed[i])
+            .map(|(_, goal_def)| goal_def)
+            .collect();
+        if newly_completed.is_empty() {
+            return;
+        }
+
+        let optimal: Option<usize> = newly_completed
+            .iter()
+            .map(|goal_def| goal_def.optimal_keystrokes)
+            .sum();
+        if let Some(optimal) = optimal {
+            self.keystroke_scores.push(KeystrokeScore {
+                label: newly_completed
+                    .iter()
+                    .map(|goal_def| goal_def.description.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" + "),
+                optimal,
+                actual: self.pending_keystrokes,
+            });
+        }
+        self.pending_keystrokes = 0;
+
+        // 目標ごとの指定が無く、練習全体の最適キー数がある場合は練習単位で採点
+        if self.engine.completed().iter().all(|&done| done)
+            && self.keystroke_scores.is_empty()
+            && let Some(optimal) = exercise.optimal_keystrokes
+        {
+            self.keystroke_scores.push(KeystrokeScore {
+                label: exercise.title.clone(),
+                optimal,
+                actual: self.total_keystrokes,
+            });
+        }
+    }
+
+    // 解答の再生を始める。先に再生前の状態を練習用のエディタに取っておかせ、それを終えてから
+    // キーを送る（解答の再生はヒントの1回として数える）
+    fn start_demo(&mut self, exercise: &ContinuousExercise) -> Result<()> {
+        if self.demo.is_some() {
+            return Ok(());
+        }
+        let Some(keys) = exercise
+            .goals
+            .get(self.engine.current_index())
+            .and_then(|goal_def| goal_def.solution_keys.clone())
+        else {
+            self.announce("🎬 この目標には再生できる解答がありません");
+            return Ok(());
+        };
+        debug_log!("🎬 解答の再生: {}", keys);
+        self.event_log.record(SessionEvent::DemoStarted {
+            index: self.engine.current_index(),
+            keys: keys.clone(),
+        });
+        self.backend.run_commands(&["call TutorialDemoSnapshot()".to_string()])?;
+        self.goal_commands_running = true;
+        self.hints_used += 1;
+        self.demo = Some(DemoPlayback {
+            goal_index: self.engine.current_index(),
+            keys: keystrokes::split_keys(&keys).into(),
+            next_key_at: Instant::now(),
+            finished_at: None,
+            outcome: None,
+            keystrokes: (self.pending_keystrokes, self.total_keystrokes),
+        });
+        self.report_progress(exercise);
+        self.update_instruction_pane(exercise)
+    }
+
+    // 解答のキーを間を空けて1つずつ送り、送り終えたら目標を達成したかを確かめる
+    fn follow_demo(&mut self, exercise: &ContinuousExercise, current_state: &VimState) -> Result<()> {
+        let Some(demo) = &self.demo else {
+            return Ok(());
+        };
+        if demo.outcome.is_some() {
+            return Ok(());
+        }
+        let goal_index = demo.goal_index;
+
+        if !demo.keys.is_empty() {
+            if Instant::now() < demo.next_key_at {
+                return Ok(());
+            }
+            // 再生前の状態から変化を待つ
+            self.engine.arm(&self.goal_detector, current_state);
+            let delay = Duration::from_millis(self.config.demo_key_delay_ms);
+            let Some(demo) = self.demo.as_mut() else {
+                return Ok(());
+            };
+            if let Some(key) = demo.keys.pop_front() {
+                self.backend.send_editor_keys(&key)?;
+            }
+            demo.next_key_at = Instant::now() + delay;
+            if demo.keys.is_empty() {
+                demo.finished_at = Some(Instant::now());
+            }
+            return Ok(());
+        }
+
+        let settled = demo.finished_at.is_none_or(|at| at.elapsed() >= DEMO_SETTLE);
+        let check = self.engine.check(&self.goal_detector, goal_index, current_state);
+        if !check.is_met() && !settled {
+            return Ok(());
+        }
+        if let Some(demo) = self.demo.as_mut() {
+            demo.outcome = Some(check.is_met());
+        }
+        self.update_instruction_pane(exercise)
+    }
+
+    // 解答の再生を終える。restore なら再生前の状態に戻させ、目標は学習者がもう一度自分で試す
+    // （戻さなければ、再生した後の状態のまま目標を判定して進む）
+    fn finish_demo(&mut self, exercise: &ContinuousExercise, restore: bool) -> Result<()> {
+        let Some(demo) = self.demo.take() else {
+            return Ok(());
+        };
+        (self.pending_keystrokes, self.total_keystrokes) = demo.keystrokes;
+        self.motion_parser.reset();
+        self.dot_counter.reset();
+        self.text_object_parser.reset();
+        self.repeat_run.reset();
+        if restore {
+            debug_log!("↩️ 解答を再生する前の状態に戻します");
+            self.event_log.record(SessionEvent::DemoRestored { index: demo.goal_index });
+            self.backend.send_editor_keys("<C-\\><C-N>")?;
+            self.backend.run_commands(&["call TutorialDemoRestore()".to_string()])?;
+            self.goal_commands_running = true;
+            self.engine.wait_for_change();
+            self.goal_detector.clear_motions();
+        }
+        self.engine.reset_hint_timer();
+        self.update_instruction_pane(exercise)
+    }
+
+    // 残り時間の表示値。ちらつきを抑えるため10秒より多い間は5秒刻みにする
+    fn countdown_display(remaining: Duration) -> u64 {
+        let secs = remaining.as_millis().div_ceil(1000) as u64;
+        if secs > 10 { secs.div_ceil(5) * 5 } else { secs }
+    }
+
+    // 目標が進まない時間をエンジンに数えさせ、自動で上がったヒントと残り時間を表示する
+    fn update_hint_timer(&mut self, exercise: &ContinuousExercise, now: &TimedState) -> Result<()> {
+        let mut hinted = false;
+        if let Some(GoalEvent::Hinted { index, level }) = self.engine.tick(now, self.last_state.as_ref()) {
+            hinted = true;
+            self.hints_used += 1;
+            debug_log!("⏰ 自動ヒント表示: level={}", level);
+            self.event_log.record(SessionEvent::HintShown { index, level });
+            self.report_progress(exercise);
+        }
+        if self.engine.current_index() >= exercise.goals.len() {
+            return Ok(());
+        }
+        let countdown = self.engine.next_hint_in().map(Self::countdown_display);
+        // Zen ではカウントダウンを出さないため、ヒントの段階が上がったときだけ描き直す
+        let redraw = if self.display.shows_numbers() { countdown != self.countdown_shown } else { hinted };
+        self.countdown_shown = countdown;
+        if redraw && self.instructions.is_some() {
+            self.update_instruction_pane(exercise)?;
+        }
+
+        Ok(())
+    }
+
+    // 現在のテキストの目標と実際の内容の違い（目標がテキストでない・一致している場合は空）
+    fn text_diff(&self, exercise: &ContinuousExercise, current_state: &VimState) -> Vec<String> {
+        let Some(goal) = exercise
+            .goals
+            .get(self.engine.current_index())
+            .and_then(|goal_def| goal_def.to_goal().ok())
+        else {
+            return Vec::new();
+        };
+        if self.goal_detector.check_goal(&goal, current_state).is_met() {
+            return Vec::new();
+        }
+
+        let buffer = &current_state.buffer_content;
+        match &goal.goal_type {
+            GoalType::TextContent {
+                line,
+                expected,
+                match_mode: TextMatch::Regex,
+                ..
+            } => vec![
+                format!("期待(正規表現): {}", expected),
+                format!(
+                    "現在: {}",
+                    buffer
+                        .get(*line)
+                        .map_or("(行がありません)".to_string(), |actual| {
+                            diff::visible_whitespace(actual)
+                        })
+                ),
+            ],
+            // 正規化する目標は、判定と同じく正規化した行どうしの違いを見せる
+            GoalType::TextContent {
+                line,
+                expected,
+                normalize,
+                ..
+            } => diff::char_diff(
+                &normalize.apply(expected),
+                buffer.get(*line).map(|actual| normalize.apply(actual)).as_deref(),
+            ),
+            GoalType::TextBlock {
+                start_line,
+                expected,
+                normalize,
+            } => {
+                let actual: Vec<String> = buffer
+                    .iter()
+                    .skip(*start_line)
+                    .take(expected.len())
+                    .map(|line| normalize.apply(line).into_owned())
+                    .collect();
+                diff::unified_diff(&normalize.apply_lines(expected), &actual, *start_line)
+            }
+            GoalType::BufferEquals { expected } => diff::unified_diff(expected, buffer, 0),
+            _ => Vec::new(),
+        }
+    }
+
+    // 未達成の目標がテキストの目標1つだけなら、その期待する行と先頭行と正規化（正規表現の目標は除く）
+    fn remaining_text_goal(&self, exercise: &ContinuousExercise) -> Option<(Vec<String>, usize, Normalize)> {
+        let mut remaining = (0..exercise.goals.len()).filter(|&i| !self.engine.completed().get(i).copied().unwrap_or(false));
+        let (index, None) = (remaining.next()?, remaining.next()) else {
+            return None;
+        };
+        match exercise.goals[index].to_goal().ok()?.goal_type {
+            GoalType::TextContent {
+                line,
+                expected,
+                match_mode,
+                normalize,
+            } if match_mode != TextMatch::Regex => Some((vec![expected], line, normalize)),
+            GoalType::TextBlock {
+                start_line,
+                expected,
+                normalize,
+            } => Some((expected, start_line, normalize)),
+            _ => None,
+        }
+    }
+
+    // テキストの目標だけが残り、バッファを最後に変えてから stalled_text_seconds たっても一致しなければ、
+    // 実際の行をエディタに問い合わせて期待する行と並べる（バッファが変わったら消す）
+    fn update_stalled_text(&mut self, exercise: &ContinuousExercise, current_state: &VimState) -> Result<()> {
+        let now = Instant::now();
+        let changed = match self.buffer_changed {
+            Some((tick, _)) if tick == current_state.changedtick => false,
+            _ => {
+                self.buffer_changed = Some((current_state.changedtick, now));
+                true
+            }
+        };
+        let stalled_for = self.buffer_changed.map_or(Duration::ZERO, |(_, at)| now.duration_since(at));
+        let comparison = match self.remaining_text_goal(exercise) {
+            Some(_) if !self.stalled_text_shown.is_empty() && !changed => return Ok(()),
+            Some((expected, first_line, normalize))
+                if !changed
+                    && stalled_for >= Duration::from_secs(self.config.stalled_text_seconds)
+                    && !matches!(current_state.mode, VimMode::Insert | VimMode::Replace) =>
+            {
+                let actual = self.backend.buffer_lines(first_line, expected.len()).unwrap_or_else(|_| {
+                    current_state.buffer_content.iter().skip(first_line).take(expected.len()).cloned().collect()
+                });
+                diff::stalled_comparison(&normalize.apply_lines(&expected), &normalize.apply_lines(&actual), first_line)
+            }
+            _ => Vec::new(),
+        };
+        if comparison != self.stalled_text_shown {
+            self.stalled_text_shown = comparison;
+            self.update_instruction_pane(exercise)?;
+        }
+        Ok(())
+    }
+
+    // 現在の目標までの距離の目安（目標で nudge を有効にした場合だけ）
+    fn nudge(&self, exercise: &ContinuousExercise, current_state: &VimState) -> Option<String> {
+        let goal_def = exercise.goals.get(self.engine.current_index())?;
+        let goal = goal_def.to_goal().ok()?;
+        // 数を付けずに繰り返して着地したことと、置き換えた後に残っていることは、nudge の指定が無くても伝える
+        self.goal_detector
+            .check_goal(&goal, current_state)
+            .gap()
+            .filter(|gap| {
+                goal_def.nudge || matches!(gap, GoalGap::RepeatedMotion { .. } | GoalGap::PatternRemains { .. })
+            })
+            .and_then(GoalGap::nudge)
+    }
+
+    // オペレーター待機中に次に打つキーの案内（練習で operator_hints を有効にし、現在の目標が
+    // オペレーターやテキストオブジェクトを使うものの場合だけ）
+    fn operator_pending_hint(&self, exercise: &ContinuousExercise, current_state: &VimState) -> Option<String> {
+        let VimMode::OperatorPending(operator) = &current_state.mode else {
+            return None;
+        };
+        let goal_def = exercise.goals.get(self.engine.current_index())?;
+        let uses_operator = goal_def.required_operator.is_some()
+            || match goal_def.goal_type.as_str() {
+                "operator_textobject" | "dot_repeat" => true,
+                "mode" => goal_def.target.as_str().is_some_and(|mode| mode.starts_with("operator")),
+                _ => false,
+            };
+        if !exercise.operator_hints || !uses_operator {
+            return None;
+        }
+        let examples = keystrokes::operator_suggestions(operator);
+        Some(format!(
+            "⌛ オペレーター '{}' 待機中 — 次にテキストオブジェクト（例: {}）を入力",
+            operator,
+            examples.join(" / ")
+        ))
+    }
+
+    // オペレーター待機の案内が変わったらペインを更新する（モードが変われば消す）
+    fn update_operator_pending(&mut self, exercise: &ContinuousExercise, current_state: &VimState) -> Result<()> {
+        let pending = self.operator_pending_hint(exercise, current_state);
+        if pending != self.operator_pending_shown {
+            self.operator_pending_shown = pending;
+            self.update_instruction_pane(exercise)?;
+        }
+        Ok(())
+    }
+
+    // ペインに出すカーソル位置とモード（位置の目標なら目標の位置も。行・列は1始まり）
+    fn position_strip(&self, exercise: &ContinuousExercise, current_state: &VimState) -> String {
+        let mut strip = format!(
+            "行 {} / 列 {} | {}",
+            current_state.cursor_line + 1,
+            current_state.cursor_col + 1,
+            current_state.mode.status_name()
+        );
+        if let Some(
+            GoalType::Position { line, col }
+            | GoalType::CountedMotion {
+                position: Some((line, col)),
+                ..
+            },
+        ) = exercise
+            .goals
+            .get(self.engine.current_index())
+            .and_then(|goal_def| goal_def.to_goal().ok())
+            .map(|goal| goal.goal_type)
+        {
+            strip.push_str(&format!(" | 目標: 行 {} / 列 {}", line + 1, col + 1));
+        }
+        strip
+    }
+
+    // カーソル位置の表示が変わったらペインを更新する
+    // （ちらつかずに描き直せる表示先だけ。POSITION_UPDATE_INTERVAL より短い間隔では描き直さない）
+    fn update_position(&mut self, exercise: &ContinuousExercise, current_state: &VimState) -> Result<()> {
+        let live = self.instructions.as_ref().is_some_and(|sink| sink.live());
+        if !self.config.show_position || !live {
+            return Ok(());
+        }
+        let position = Some(self.position_strip(exercise, current_state));
+        let due = self
+            .position_updated_at
+            .is_none_or(|updated| updated.elapsed() >= POSITION_UPDATE_INTERVAL);
+        if position != self.position_shown && due {
+            self.position_shown = position;
+            self.position_updated_at = Some(Instant::now());
+            self.update_instruction_pane(exercise)?;
+        }
+        Ok(())
+    }
+
+    // 差分の表示が変わったらペインを更新する（入力中はちらつかないよう更新しない）
+    fn update_text_diff(
+        &mut self,
+        exercise: &ContinuousExercise,
+        current_state: &VimState,
+    ) -> Result<()> {
+        // 距離の目安はモードの目標でも出すため、入力中も更新する
+        let nudge = self.nudge(exercise, current_state);
+        if nudge != self.nudge_shown {
+            self.nudge_shown = nudge;
+            self.update_instruction_pane(exercise)?;
+        }
+        if matches!(current_state.mode, VimMode::Insert | VimMode::Replace) {
+            return Ok(());
+        }
+        let text_diff = self.text_diff(exercise, current_state);
+        if text_diff != self.text_diff_shown {
+            self.text_diff_shown = text_diff;
+            self.update_instruction_pane(exercise)?;
+        }
+        Ok(())
+    }
+
+    fn update_instruction_pane(&mut self, exercise: &ContinuousExercise) -> Result<()> {
+        let lines = self.pane_lines(exercise);
+        if self.instructions.is_none() {
+            debug_log!("指示の表示先が設定されていません");
+            return Err(anyhow::anyhow!("指示の表示先が設定されていません"));
+        }
+        // 読み上げ向けの表示では、続けて届く更新をまとめて描く（残りは flush_pending_pane で描く）
+        let debounce = self.theme.pane_debounce();
+        if !debounce.is_zero() && self.pane_shown_at.is_some_and(|at| at.elapsed() < debounce) {
+            self.pending_pane = Some(lines);
+            return Ok(());
+        }
+        self.show_instructions(lines)
+    }
+
+    // まとめていた指示の更新を、間隔が過ぎていれば描く
+    fn flush_pending_pane(&mut self) -> Result<()> {
+        let due = self
+            .pane_shown_at
+            .is_none_or(|at| at.elapsed() >= self.theme.pane_debounce());
+        if due && let Some(lines) = self.pending_pane.take() {
+            self.show_instructions(lines)?;
+        }
+        Ok(())
+    }
+
+    // 表示先があれば指示を表示し、記録にも残す（まとめていた更新はこの表示で置き換える）
+    fn show_instructions(&mut self, lines: Vec<String>) -> Result<()> {
+        let Some(instructions) = &mut self.instructions else {
+            return Ok(());
+        };
+        let lines = self.theme.lines(&lines);
+        instructions.show(&lines)?;
+        self.pending_pane = None;
+        self.pane_shown_at = Some(Instant::now());
+        self.event_log.record(SessionEvent::PaneUpdated { lines });
+        Ok(())
+    }
+
+    // エディタの画面に1行のお知らせを出す
+    fn announce(&mut self, message: &str) {
+        let message = self.theme.line(message);
+        self.backend.announce(&message);
+    }
+
+    // fn check_goals(
+    //     &mut self,
+    //     current_state: &VimState,
+    //     exercise: &ContinuousExercise,
+    // ) -> Result<Option<ExerciseResult>> {
+    //     match exercise.flow_type {
+    //         FlowType::Sequential => self.check_sequential_goals(current_state, exercise),
+    //         FlowType::AnyOrder => self.check_any_order_goals(current_state, exercise),
+    //         FlowType::Parallel => self.check_parallel_goals(current_state, exercise),
     //     }
     // }
 
+    // fn check_sequential_goals(
+    //     &mut self,
+    //     current_state: &VimState,
+    //     exercise: &ContinuousExercise,
+    // ) -> Result<Option<ExerciseResult>> {
+    //     if self.current_goal_index >= exercise.goals.len() {
+    //         return Ok(Some(ExerciseResult::Completed));
+    //     }
+
+    //     let current_goal_def = &exercise.goals[self.current_goal_index];
+    //     let goal = self.convert_goal_definition(current_goal_def)?;
+
+    //     if self.goal_detector.check_goal(&goal, current_state) {
+    //         println!("✅ 目標達成: {}", current_goal_def.description);
+    //         self.completed_goals[self.current_goal_index] = true;
+    //         self.current_goal_index += 1;
+
+    //         if self.current_goal_index >= exercise.goals.len() {
+    //             println!("\n🎉 全ての目標を達成しました！");
+    //             return Ok(Some(ExerciseResult::Completed));
+    //         } else {
+    //             println!(
+    //                 "📍 次の目標: {}",
+    //                 exercise.goals[self.current_goal_index].description
+    //             );
+    //             if let Some(hint) = &exercise.goals[self.current_goal_index].hint {
+    //                 println!("💡 ヒント: {}", hint);
+    //             }
+    //         }
+    //     }
+
+    //     Ok(None)
+    // }
+
+    // fn check_any_order_goals(
+    //     &mut self,
+    //     current_state: &VimState,
+    //     exercise: &ContinuousExercise,
+    // ) -> Result<Option<ExerciseResult>> {
+    //     let mut progress_made = false;
+
+    //     for (i, goal_def) in exercise.goals.iter().enumerate() {
+    //         if self.completed_goals[i] {
+    //             continue; // 既に完了している目標はスキップ
+    //         }
+
+    //         let goal = self.convert_goal_definition(goal_def)?;
+    //         if self.goal_detector.check_goal(&goal, current_state) {
+    //             println!("✅ 目標達成: {}", goal_def.description);
+    //             self.completed_goals[i] = true;
+    //             progress_made = true;
+    //         }
+    //     }
+
+    //     // 全ての目標が完了したかチェック
+    //     if self.completed_goals.iter().all(|&completed| completed) {
+    //         println!("\n🎉 全ての目標を達成しました！");
+    //         return Ok(Some(ExerciseResult::Completed));
+    //     }
+
+    //     if progress_made {
+    //         self.show_remaining_goals(exercise);
+    //     }
+
+    //     Ok(None)
+    // }
+
+    // fn check_parallel_goals(
+    //     &mut self,
+    //     current_state: &VimState,
+    //     exercise: &ContinuousExercise,
+    // ) -> Result<Option<ExerciseResult>> {
+    //     // 並列目標：全ての目標を同時に満たす必要がある
+    //     let mut all_satisfied = true;
+
+    //     for goal_def in &exercise.goals {
+    //         let goal = self.convert_goal_definition(goal_def)?;
+    //         if !self.goal_detector.check_goal(&goal, current_state) {
+    //             all_satisfied = false;
+    //             break;
+    //         }
+    //     }
+
+    //     if all_satisfied {
+    //         println!("\n🎉 全ての目標を同時に達成しました！");
+    //         return Ok(Some(ExerciseResult::Completed));
+    //     }
+
+    //     Ok(None)
+    // }
+
+    #[cfg(test)]
+    fn convert_goal_definition(&self, goal_def: &ExerciseGoal) -> Result<Goal> {
+        goal_def.to_goal()
+    }
+
+    // fn show_remaining_goals(&self, exercise: &ContinuousExercise) {
+    //     println!("📋 残りの目標:");
+    //     for (i, goal_def) in exercise.goals.iter().enumerate() {
+    //         if !self.completed_goals[i] {
+    //             println!("  • {}", goal_def.description);
+    //         }
+    //     }
+    //     println!();
+    // }
+
+    // 残り時間を秒単位でペインに反映し、期限切れなら true を返す
+    fn update_time_limit(&mut self, exercise: &ContinuousExercise) -> Result<bool> {
+        let Some(deadline) = self.deadline else {
+            return Ok(false);
+        };
+        let remaining = Self::remaining_seconds(deadline, Instant::now());
+        if remaining == 0 {
+            return Ok(true);
+        }
+        if self.remaining_shown != Some(remaining) {
+            self.remaining_shown = Some(remaining);
+            if self.display.shows_numbers() && self.instructions.is_some() {
+                self.update_instruction_pane(exercise)?;
+            }
+        }
+        Ok(false)
+    }
+
+    // 経過時間を秒単位でペインに反映する（一時停止中は進まない）。
+    // 読み上げ向けの表示では毎秒の読み上げにならないよう、他の更新のときだけ新しい値を出す
+    fn update_clock(&mut self, exercise: &ContinuousExercise) -> Result<()> {
+        let elapsed = self.active_duration().as_secs();
+        // 状態を待っている間の案内は上書きしない
+        if elapsed == self.elapsed_shown || self.unavailable_since.is_some() {
+            return Ok(());
+        }
+        self.elapsed_shown = elapsed;
+        if self.theme != Theme::Accessible && self.display.shows_numbers() && self.instructions.is_some() {
+            self.update_instruction_pane(exercise)?;
+        }
+        Ok(())
+    }
+
+    // 残り時間を切り上げた秒数（0 は期限切れ）
+    fn remaining_seconds(deadline: Instant, now: Instant) -> u64 {
+        let remaining = deadline.saturating_duration_since(now);
+        let secs = remaining.as_secs();
+        if remaining.subsec_nanos() > 0 { secs + 1 } else { secs }
+    }
+
+    fn show_timeout_message(&mut self, exercise: &ContinuousExercise) -> Result<()> {
+        let lines = pane::timed_out(exercise, &self.progress_snapshot(exercise), self.display);
+        self.show_instructions(lines)?;
+        debug_log!("時間切れメッセージ表示");
+        Ok(())
+    }
+
+    fn show_completion_message(&mut self, exercise: &ContinuousExercise) -> Result<()> {
+        let lines = pane::completion(exercise, self.display);
+        self.show_instructions(lines)?;
+        debug_log!("完了メッセージ表示");
+        Ok(())
+    }
+
+    /// エディタを終了し、練習用の一時ファイルを片付ける
+    pub fn stop_exercise(&mut self) -> Result<()> {
+        self.monitoring_active = false;
+        let kept = self.keep_session.then(|| self.backend.running_session()).flatten();
+        if let Some(running) = &kept {
+            self.keep_running_session(running);
+        } else {
+            self.run_teardown()?;
+            self.backend.stop()?;
+        }
+        self.instructions = None;
+        self.vim_script = None;
+        self.exercise_dir = None;
+        if let Some(path) = &self.manifest_path {
+            recovery::remove(path);
+        }
+        if self.heartbeat_written.take().is_some() {
+            progress::record_heartbeat(None);
+        }
+
+        if kept.is_none() {
+            info!("📱 セッションを終了しました");
+        }
+        Ok(())
+    }
+
+    // --keep-session: teardown もせずにエディタと tmux のセッションを残し、調べるための居場所を表示する。
+    // 監視用のスクリプトと練習のファイルも消さない（後で vim-tutorial-nvim clean が片付ける）
+    fn keep_running_session(&mut self, running: &RunningSession) {
+        let script = self.vim_script.take().and_then(|script| script.keep().ok()).map(|(_, path)| path);
+        if let Some(dir) = self.exercise_dir.take() {
+            let _ = dir.keep();
+        }
+        self.backend.keep_running();
+        clean::record_kept_session(&running.server);
+        debug_log!("🔧 セッションを残します: {:?}", running);
+        info!("🔧 練習のセッションを残しました（--keep-session）");
+        info!("  tmux セッション: {}（tmux attach -t {}）", running.session_name, running.session_name);
+        info!("  ソケット: {}（nvim --server {} --remote-ui）", running.server, running.server);
+        for file in [STATUS_FILE, COMMAND_FILE, KEY_LOG_FILE] {
+            info!("  {}: {}", file, crate::vim_backend::temp_file(file));
+        }
+        if let Some(script) = script {
+            info!("  監視用のスクリプト: {}", script.display());
+        }
+        info!("  片付けるには: vim-tutorial-nvim clean");
+    }
+
+    // pub fn send_keys(&self, keys: &str) -> Result<()> {
+    //     self.vim_client.send_keys(keys)
+    // }
+
+    // pub fn get_current_state(&self) -> Result<VimState> {
+    //     self.vim_client.get_current_state()
+    // }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ExerciseResult {
+    Completed,
+    Incomplete,
+    TimedOut, // チャレンジモードの制限時間切れ
+    #[allow(dead_code)] // エラーハンドリング用
+    Failed(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vim_backend::mock::{MockBackend, MockStep};
+    use crate::vim_backend::{SESSION_NAME, STATE_FILES};
+    use serde_json::json;
+    use unicode_width::UnicodeWidthStr;
+    // use std::fs;
+    use tempfile::tempdir;
+
+    // fn create_test_exercise() -> ContinuousExercise {
+    //     ContinuousExercise {
+    //         title: "Test Exercise".to_string(),
+    //         description: "A test exercise".to_string(),
+    //         sample_code: vec!["hello world".to_string()],
+    //         goals: vec![
+    //             ExerciseGoal {
+    //                 goal_type: "position".to_string(),
+    //                 target: json!([0, 5]),
+    //                 description: "Move to position 0,5".to_string(),
+    //                 hint: Some("Use 'l' key to move right".to_string()),
+    //             },
+    //             ExerciseGoal {
+    //                 goal_type: "mode".to_string(),
+    //                 target: json!("insert"),
+    //                 description: "Enter insert mode".to_string(),
+    //                 hint: None,
+    //             },
+    //         ],
+    //         flow_type: FlowType::Sequential,
+    //     }
+    // }
+
+    #[test]
+    fn test_continuous_session_creation() {
+        let session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        assert!(session.current_exercise.is_none());
+        assert_eq!(session.engine.current_index(), 0);
+    }
+
+    #[test]
+    fn test_goal_conversion() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let socket_path = tmp_dir
+            .path()
+            .join("test.sock")
+            .to_string_lossy()
+            .to_string();
+        let session = ContinuousVimSession::new(socket_path);
+
+        // Position goal
+        let pos_goal_def = ExerciseGoal {
+            goal_type: "position".to_string(),
+            target: json!([1, 2]),
+            description: "Test position".to_string(),
+            hint: None,
+            ..Default::default()
+        };
+        let goal = session.convert_goal_definition(&pos_goal_def)?;
+        match goal.goal_type {
+            GoalType::Position { line, col } => {
+                assert_eq!(line, 1);
+                assert_eq!(col, 2);
+            }
+            _ => panic!("Expected Position goal type"),
+        }
+
+        // Mode goal
+        let mode_goal_def = ExerciseGoal {
+            goal_type: "mode".to_string(),
+            target: json!("insert"),
+            description: "Test mode".to_string(),
+            hint: None,
+            ..Default::default()
+        };
+        let goal = session.convert_goal_definition(&mode_goal_def)?;
+        match goal.goal_type {
+            GoalType::Mode(VimMode::Insert) => {}
+            _ => panic!("Expected Insert mode goal type"),
+        }
+
+        // Replace mode goal
+        let replace_goal_def = ExerciseGoal {
+            goal_type: "mode".to_string(),
+            target: json!("replace"),
+            description: "Test replace mode".to_string(),
+            ..Default::default()
+        };
+        let goal = session.convert_goal_definition(&replace_goal_def)?;
+        assert_eq!(goal.goal_type, GoalType::Mode(VimMode::Replace));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_goal_conversion() -> Result<()> {
+        let window_goal = |goal_type: &str, target| ExerciseGoal {
+            goal_type: goal_type.to_string(),
+            target,
+            description: "ウィンドウ".to_string(),
+            ..Default::default()
+        };
+        let goal = window_goal("window_count", json!({"expected": 2, "current": 2})).to_goal()?;
+        assert_eq!(
+            goal.goal_type,
+            GoalType::WindowCount {
+                expected: 2,
+                current: Some(2)
+            }
+        );
+        let goal = window_goal("window_layout", json!({"min_splits": 2})).to_goal()?;
+        assert_eq!(goal.goal_type, GoalType::WindowLayout { min_splits: 2 });
+
+        // 今いるウィンドウはウィンドウの数を超えられない
+        assert!(window_goal("window_count", json!({"expected": 2, "current": 3})).to_goal().is_err());
+        assert!(window_goal("window_count", json!({"expected": 0})).to_goal().is_err());
+        assert!(window_goal("window_layout", json!({"min_splits": 0})).to_goal().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_operator_pending_goal_conversion() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let socket_path = tmp_dir
+            .path()
+            .join("test.sock")
+            .to_string_lossy()
+            .to_string();
+        let session = ContinuousVimSession::new(socket_path);
+
+        let op_goal_def = ExerciseGoal {
+            goal_type: "mode".to_string(),
+            target: json!("operator_d"),
+            description: "Press 'd' for delete".to_string(),
+            hint: None,
+            ..Default::default()
+        };
+
+        let goal = session.convert_goal_definition(&op_goal_def)?;
+        match goal.goal_type {
+            GoalType::Mode(VimMode::OperatorPending(op)) => {
+                assert_eq!(op, "d");
+            }
+            _ => panic!("Expected OperatorPending goal type"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vim_script_defines_tutorial_commands() -> Result<()> {
+        let session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let exercise = ContinuousExercise {
+            title: "Test".to_string(),
+            description: "Test".to_string(),
+            sample_code: vec!["it's".to_string(), "second".to_string()],
+            ..Default::default()
+        };
+
+        let script = session.create_vim_script(&exercise)?;
+        let content = fs::read_to_string(script.path())?;
+        assert!(content.contains("command! TutorialHint"));
+        assert!(content.contains("command! TutorialReset"));
+        assert!(content.contains("command! TutorialRetry call TutorialReset(1)"));
+        assert!(content.contains("let s:initial_registers[s:name] = getreginfo(s:name)"));
+        assert!(content.contains("command! TutorialPause"));
+        assert!(content.contains("command! TutorialResume"));
+        // 単一引用符はVimの流儀で二重化される
+        assert!(content.contains("let g:tutorial_sample = ['it''s', 'second']"));
+        // tmux が無い場合の指示ウィンドウと、一時ディレクトリの状態ファイル
+        assert!(content.contains("call TutorialShowInstructions()"));
+        assert!(content.contains(&format!("call writefile([line], '{}', ", script_temp_file(STATUS_FILE))));
+        // キー入力ログは状態ファイルより先に書き出す
+        assert!(content.contains(&format!("vim.fn.writefile(pending, '{}', 'a')", script_temp_file(KEY_LOG_FILE))));
+        assert!(content.contains("local pending = {}"));
+        // 制御文字を含むモードはトークンに変換して書き出す
+        assert!(content.contains("function! TutorialModeToken(mode)"));
+        assert!(content.contains("TutorialModeToken(mode(1))"));
+        assert!(content.contains("let g:tutorial_registers = ['\"', '0', '1', 'a', 'b', 'c']"));
+        // 行番号とルーラーは設定で消せる
+        assert!(content.contains("  set number ruler\n"));
+        let hard_mode = TutorialConfig {
+            show_position: false,
+            ..TutorialConfig::default()
+        };
+        let session = ContinuousVimSession::with_config("/tmp/test.sock".to_string(), hard_mode);
+        let content = fs::read_to_string(session.create_vim_script(&exercise)?.path())?;
+        assert!(!content.contains("set number"));
+
+        // 練習ごとの表示のオプションは許可したものだけを書く
+        let exercise = ContinuousExercise {
+            editor_options: vec!["set relativenumber".to_string(), "shell=/bin/sh".to_string()],
+            ..exercise
+        };
+        let content = fs::read_to_string(session.create_vim_script(&exercise)?.path())?;
+        assert!(content.contains("  set relativenumber\n"));
+        assert!(content.contains("  set noswapfile\n"));
+        assert!(!content.contains("shell"));
+        // 長い行は折り返さない（editor_options は後に書くので wrap で戻せる）
+        let nowrap = content.find("  set nowrap sidescroll=1\n").unwrap();
+        assert!(nowrap < content.find("  set relativenumber\n").unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bell_is_written_to_the_terminal() {
+        for captures_keystrokes in [true, false] {
+            let functions = ContinuousVimSession::editor_functions(Editor::Neovim, captures_keystrokes);
+            assert!(functions.contains("function! TutorialBell()\n  call chansend(v:stderr, \"\\x07\")\n"));
+            assert!(!functions.contains("io.stdout"));
+        }
+        let functions = ContinuousVimSession::editor_functions(Editor::Vim, false);
+        assert!(functions.contains("call echoraw(\"\\x07\")"));
+    }
+
+    #[test]
+    fn test_vim_script_runs_setup_before_first_status() -> Result<()> {
+        let session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let exercise = ContinuousExercise {
+            setup: vec!["let @a = 'it''s'".to_string(), "set nowrap".to_string()],
+            teardown: vec!["let @a = ''".to_string()],
+            editor_options: Vec::new(),
+            coaching: None,
+            intro: None,
+            outro: None,
+            allowed_modes: Vec::new(),
+            reference: Vec::new(),
+            operator_hints: false,
+            files: Vec::new(),
+            ..ordered_exercise()
+        };
+
+        let content = fs::read_to_string(session.create_vim_script(&exercise)?.path())?;
+        assert!(content.contains("let g:tutorial_setup = ['let @a = ''it''''s''', 'set nowrap']"));
+        assert!(content.contains("let g:tutorial_teardown = ['let @a = ''''']"));
+        // スクリプト末尾での初期状態の書き出しは setup の後
+        let setup_call = content
+            .rfind("call TutorialRunCommands(g:tutorial_setup, 'SETUP_FAILED')")
+            .unwrap();
+        let initial_status = content.rfind("call UpdateStatus()").unwrap();
+        assert!(setup_call < initial_status);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exercise_files_written_and_removed_with_exercise() -> Result<()> {
+        let file = |name: &str, content: &str| ExerciseFile {
+            name: name.to_string(),
+            content: vec![content.to_string()],
+        };
+        let exercise = ContinuousExercise {
+            sample_code: Vec::new(),
+            operator_hints: false,
+            files: vec![file("main.txt", "see notes.txt"), file("notes.txt", "memo")],
+            ..ordered_exercise()
+        };
+        let backend = MockBackend::new(vec![MockStep::state(sequence_state(VimMode::Normal, ""))]);
+        let mut session = mock_session(backend);
+
+        session.start_exercise(exercise.clone(), "/tmp/sample.txt")?;
+        let dir = session.exercise_dir.as_ref().unwrap().path().to_path_buf();
+        assert_eq!(fs::read_to_string(dir.join("notes.txt"))?, "memo");
+
+        let content = fs::read_to_string(session.create_vim_script(&exercise)?.path())?;
+        assert!(content.contains("let g:tutorial_files = [['main.txt', ['see notes.txt']], ['notes.txt', ['memo']]]"));
+        assert!(content.contains("execute 'badd' fnameescape(name)"));
+        assert!(content.contains(&format!("execute 'lcd' fnameescape('{}')", dir.display())));
+        assert!(content.contains("'FILE:' . json_encode(expand('%:t'))"));
+
+        // 練習を終えるとディレクトリごと消す
+        session.stop_exercise()?;
+        assert!(!dir.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cursor_start_sets_initial_position() -> Result<()> {
+        let mut session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let exercise = ContinuousExercise {
+            sample_code: vec!["one".to_string(), "two".to_string(), "three".to_string()],
+            goals: vec![ExerciseGoal {
+                goal_type: "position".to_string(),
+                target: json!([2, 1]),
+                description: "3行目の2文字目へ".to_string(),
+                ..Default::default()
+            }],
+            cursor_start: Some([2, 1]),
+            ..ordered_exercise()
+        };
+
+        let content = fs::read_to_string(session.create_vim_script(&exercise)?.path())?;
+        assert!(content.contains("call TutorialSetCursor(3, 2)"));
+        assert!(!content.contains("call TutorialSetCursor(1, 1)"));
+
+        // 開始位置で既に満たしている目標は、一度離れて戻るまで達成にしない
+        start_ordered(&mut session, &exercise)?;
+        let mut state = exercise.initial_state();
+        assert_eq!((state.cursor_line, state.cursor_col), (2, 1));
+        assert!(!evaluate(&mut session, &state));
+        state.cursor_col = 0;
+        assert!(!evaluate(&mut session, &state));
+        state.cursor_col = 1;
+        assert!(evaluate(&mut session, &state));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_setup_failure_names_command() {
+        assert_eq!(
+            ContinuousVimSession::setup_failure("SETUP_FAILED\tset nowarp\tVim(set):E518: Unknown option: nowarp"),
+            Some("setup コマンド「set nowarp」の実行に失敗しました: Vim(set):E518: Unknown option: nowarp".to_string())
+        );
+        assert_eq!(ContinuousVimSession::setup_failure("HINT_REQUEST"), None);
+    }
+
+    #[test]
+    fn test_remaining_seconds_rounds_up() {
+        let now = Instant::now();
+        let remaining = |millis| {
+            ContinuousVimSession::remaining_seconds(now + Duration::from_millis(millis), now)
+        };
+        assert_eq!(remaining(35_000), 35);
+        assert_eq!(remaining(34_100), 35);
+        assert_eq!(remaining(1), 1);
+        assert_eq!(ContinuousVimSession::remaining_seconds(now, now), 0);
+        assert_eq!(
+            ContinuousVimSession::remaining_seconds(now, now + Duration::from_secs(3)),
+            0
+        );
+    }
+
+    #[test]
+    fn test_countdown_display() {
+        assert_eq!(
+            ContinuousVimSession::countdown_display(Duration::from_millis(27_300)),
+            30
+        );
+        assert_eq!(
+            ContinuousVimSession::countdown_display(Duration::from_millis(9_200)),
+            10
+        );
+        assert_eq!(
+            ContinuousVimSession::countdown_display(Duration::from_millis(3_000)),
+            3
+        );
+    }
+
+    #[test]
+    fn test_hint_delay_escalation() {
+        let mut session = ContinuousVimSession::with_config(
+            "/tmp/test.sock".to_string(),
+            TutorialConfig {
+                hint_delay_seconds: 20,
+                solution_delay_seconds: 15,
+                ..Default::default()
+            },
+        );
+        let mut goal = ExerciseGoal {
+            goal_type: "position".to_string(),
+            target: json!([0, 1]),
+            description: "Move".to_string(),
+            hint: Some("l".to_string()),
+            ..Default::default()
+        };
+        let start = |session: &mut ContinuousVimSession, goal: &ExerciseGoal| {
+            let exercise = ContinuousExercise {
+                goals: vec![goal.clone()],
+                ..ordered_exercise()
+            };
+            start_ordered(session, &exercise).unwrap();
+            session.engine.next_hint_in()
+        };
+
+        assert_eq!(start(&mut session, &goal), Some(Duration::from_secs(20)));
+        goal.hint_delay_seconds = Some(5);
+        assert_eq!(start(&mut session, &goal), Some(Duration::from_secs(5)));
+
+        // 解答が無い目標はヒント表示で打ち止め
+        start(&mut session, &goal);
+        assert!(session.engine.request_hint());
+        assert_eq!(session.engine.next_hint_in(), None);
+        assert!(!session.engine.request_hint());
+        goal.solution_keys = Some("l".to_string());
+        start(&mut session, &goal);
+        assert!(session.engine.request_hint());
+        assert_eq!(session.engine.next_hint_in(), Some(Duration::from_secs(15)));
+        assert!(session.engine.request_hint());
+        assert_eq!(session.engine.hint_level(), 2);
+        assert_eq!(session.engine.next_hint_in(), None);
+    }
+
+    #[test]
+    fn test_keystroke_scores_per_goal() {
+        let mut session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let goal = |description: &str, optimal| ExerciseGoal {
+            goal_type: "position".to_string(),
+            target: json!([0, 0]),
+            description: description.to_string(),
+            optimal_keystrokes: optimal,
+            ..Default::default()
+        };
+        let exercise = ContinuousExercise {
+            title: "Keys".to_string(),
+            sample_code: vec!["text".to_string()],
+            goals: vec![goal("A", Some(2)), goal("B", Some(1)), goal("C", None)],
+            flow_type: FlowType::AnyOrder,
+            optimal_keystrokes: Some(3),
+            ..Default::default()
+        };
+        start_ordered(&mut session, &exercise).unwrap();
+
+        // A を5キーで達成
+        session.pending_keystrokes = 5;
+        session.total_keystrokes = 5;
+        session.engine.set_completed(vec![true, false, false]);
+        session.record_keystroke_score(&exercise, &[false, false, false]);
+        // B と C を同時に達成（C に最適キー数が無いためまとめて採点対象外）
+        session.pending_keystrokes = 3;
+        session.total_keystrokes = 8;
+        session.engine.set_completed(vec![true; 3]);
+        session.record_keystroke_score(&exercise, &[true, false, false]);
+
+        assert_eq!(
+            session.keystroke_scores(),
+            &[KeystrokeScore {
+                label: "A".to_string(),
+                optimal: 2,
+                actual: 5,
+            }]
+        );
+        assert_eq!(session.pending_keystrokes, 0);
+    }
+
+    fn sequence_state(mode: VimMode, line: &str) -> VimState {
+        VimState {
+            mode,
+            buffer_content: vec![line.to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn ordered_exercise() -> ContinuousExercise {
+        ContinuousExercise {
+            title: "Order".to_string(),
+            sample_code: vec!["".to_string()],
+            goals: vec![
+                ExerciseGoal {
+                    goal_type: "mode".to_string(),
+                    target: json!("insert"),
+                    description: "Insert モードに入る".to_string(),
+                    ..Default::default()
+                },
+                ExerciseGoal {
+                    goal_type: "text".to_string(),
+                    target: json!({"line": 0, "expected": "X"}),
+                    description: "X と入力する".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn start_ordered(session: &mut ContinuousVimSession, exercise: &ContinuousExercise) -> Result<()> {
+        session.engine = GoalEngine::new(exercise, session.hint_delays())?;
+        session.activate_initial_goal(exercise);
+        Ok(())
+    }
+
+    // 状態を1つ判定し、新たに達成した目標があれば true を返す
+    fn evaluate(session: &mut ContinuousVimSession, state: &VimState) -> bool {
+        let now = TimedState {
+            at: Duration::ZERO,
+            state: state.clone(),
+        };
+        let events = session.engine.feed(&mut session.goal_detector, &now);
+        events.iter().any(|event| matches!(event, GoalEvent::Completed { .. }))
+    }
+
+    #[test]
+    fn test_sequential_goal_ignores_state_reached_before_activation() -> Result<()> {
+        let mut session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let exercise = ordered_exercise();
+        start_ordered(&mut session, &exercise)?;
+
+        // 先にテキストを入力してしまった（目標1はまだ有効）
+        assert!(!evaluate(&mut session, &sequence_state(VimMode::Normal, "X")));
+        // Insert モードに入り目標1を達成
+        assert!(evaluate(&mut session, &sequence_state(VimMode::Insert, "X")));
+        assert_eq!(session.engine.current_index(), 1);
+        // バッファは既に一致しているが、目標2が有効になってからの変化ではない
+        for _ in 0..3 {
+            assert!(!evaluate(&mut session, &sequence_state(VimMode::Insert, "X")));
+        }
+        assert!(!session.engine.completed()[1]);
+
+        // 一度変えてから入力し直すと達成
+        assert!(!evaluate(&mut session, &sequence_state(VimMode::Insert, "")));
+        assert!(evaluate(&mut session, &sequence_state(VimMode::Insert, "X")));
+        assert!(session.engine.is_done());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequential_goals_in_order_still_complete() -> Result<()> {
+        let mut session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let exercise = ordered_exercise();
+        start_ordered(&mut session, &exercise)?;
+
+        assert!(evaluate(&mut session, &sequence_state(VimMode::Insert, "")));
+        assert!(!evaluate(&mut session, &sequence_state(VimMode::Insert, "x")));
+        assert!(evaluate(&mut session, &sequence_state(VimMode::Insert, "X")));
+        assert!(session.engine.is_done());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_goal_match_mode_conversion() -> Result<()> {
+        let text_goal = |target: serde_json::Value| ExerciseGoal {
+            goal_type: "text".to_string(),
+            target,
+            description: "Edit".to_string(),
+            ..Default::default()
+        };
+
+        let goal = text_goal(json!({"line": 1, "expected": "a b", "match_mode": "collapse_whitespace"}))
+            .to_goal()?;
+        assert_eq!(
+            goal.goal_type,
+            GoalType::TextContent {
+                line: 1,
+                expected: "a b".to_string(),
+                match_mode: TextMatch::CollapseWhitespace,
+                normalize: Normalize::default(),
+            }
+        );
+        let goal = text_goal(json!({"line": 0, "expected": "a"})).to_goal()?;
+        assert!(matches!(
+            goal.goal_type,
+            GoalType::TextContent { match_mode: TextMatch::Exact, .. }
+        ));
+        assert!(text_goal(json!({"line": 0, "expected": "a", "match_mode": "fuzzy"})).to_goal().is_err());
+        assert!(text_goal(json!({"line": 0, "expected": "(", "match_mode": "regex"})).to_goal().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_goal_normalize_conversion_and_diff() -> Result<()> {
+        let text_goal = |target: serde_json::Value| ExerciseGoal {
+            goal_type: "text".to_string(),
+            target,
+            description: "Edit".to_string(),
+            ..Default::default()
+        };
+        let goal = text_goal(json!({"line": 0, "expected": "X", "normalize": ["width", "trailing_space"]})).to_goal()?;
+        assert!(matches!(
+            goal.goal_type,
+            GoalType::TextContent {
+                normalize: Normalize { width: true, quotes: false, trailing_space: true },
+                ..
+            }
+        ));
+        let block = ExerciseGoal {
+            goal_type: "text_block".to_string(),
+            ..text_goal(json!({"start_line": 0, "expected": ["X"], "normalize": ["quotes"]}))
+        };
+        assert!(matches!(
+            block.to_goal()?.goal_type,
+            GoalType::TextBlock { normalize: Normalize { quotes: true, .. }, .. }
+        ));
+        assert!(text_goal(json!({"line": 0, "expected": "X", "normalize": ["nfkc"]})).to_goal().is_err());
+        assert!(text_goal(json!({"line": 0, "expected": "X", "normalize": "width"})).to_goal().is_err());
+        assert!(text_goal(json!({"line": 0, "expected": "^X", "match_mode": "regex", "normalize": ["width"]})).to_goal().is_err());
+
+        // 差分は判定と同じく正規化した行どうしで見せる（全角の括弧は違いにしない）
+        let mut session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let mut exercise = ordered_exercise();
+        exercise.goals[1].target = json!({"line": 0, "expected": "print(x)", "normalize": ["width"]});
+        start_ordered(&mut session, &exercise)?;
+        session.engine.set_completed(vec![true, false]);
+        assert!(session.text_diff(&exercise, &sequence_state(VimMode::Normal, "print（x）")).is_empty());
+        let text_diff = session.text_diff(&exercise, &sequence_state(VimMode::Normal, "print（ｙ）"));
+        assert_eq!(text_diff[1], "現在: print(y)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_diff_shown_for_mismatched_line() -> Result<()> {
+        let mut session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let exercise = ordered_exercise();
+        start_ordered(&mut session, &exercise)?;
+
+        // 目標がテキストでなければ差分は出さない
+        assert!(session.text_diff(&exercise, &sequence_state(VimMode::Normal, "x")).is_empty());
+
+        session.engine.set_completed(vec![true, false]);
+        assert!(session.text_diff(&exercise, &sequence_state(VimMode::Normal, "X")).is_empty());
+        let text_diff = session.text_diff(&exercise, &sequence_state(VimMode::Normal, "x "));
+        assert_eq!(text_diff[1], "現在: x·");
+
+        session.text_diff_shown = text_diff;
+        let lines = session.pane_lines(&exercise);
+        assert!(lines.iter().any(|line| line.contains("目標との違い")));
+        assert!(lines.iter().any(|line| line.contains("期待: X")));
+        assert!(!lines.iter().any(|line| line.contains("右端を省いています")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_long_lines_beyond_80_columns() -> Result<()> {
+        let long_line = format!("{}target{}", "a".repeat(95), "b".repeat(30));
+        let mut session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let exercise = ContinuousExercise {
+            sample_code: vec![long_line.clone()],
+            goals: vec![
+                ExerciseGoal {
+                    goal_type: "position".to_string(),
+                    target: json!([0, 95]),
+                    description: "target の先頭へ".to_string(),
+                    ..Default::default()
+                },
+                ExerciseGoal {
+                    goal_type: "text".to_string(),
+                    target: json!({"line": 0, "expected": long_line.replace("target", "TARGET")}),
+                    description: "大文字にする".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..ordered_exercise()
+        };
+        let content = fs::read_to_string(session.create_vim_script(&exercise)?.path())?;
+        assert!(content.contains("  set nowrap sidescroll=1\n"));
+
+        // 折り返さないため、画面の列と目標の列は同じ行の論理的な列で比べる
+        start_ordered(&mut session, &exercise)?;
+        let at = |col| VimState { cursor_col: col, ..sequence_state(VimMode::Normal, &long_line) };
+        assert!(!evaluate(&mut session, &at(15)));
+        assert!(evaluate(&mut session, &at(95)));
+        assert_eq!(session.engine.current_index(), 1);
+
+        // ペインに入らない差分の行は右端を省いたことを示す
+        session.text_diff_shown = session.text_diff(&exercise, &at(95));
+        let lines = session.pane_lines(&exercise);
+        let shown: Vec<&String> = lines.iter().filter(|line| line.contains("期待: ")).collect();
+        assert!(shown[0].ends_with('…'));
+        assert!(UnicodeWidthStr::width(shown[0].as_str()) < DEFAULT_PANE_WIDTH);
+        assert!(lines.iter().any(|line| line.contains("↔ 長い行は右端を省いています")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_nudge_shown_only_when_enabled_for_goal() -> Result<()> {
+        let mut session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let mut exercise = ordered_exercise();
+        start_ordered(&mut session, &exercise)?;
+        let normal = sequence_state(VimMode::Normal, "");
+        assert_eq!(session.nudge(&exercise, &normal), None);
+
+        exercise.goals[0].nudge = true;
+        let nudge = session.nudge(&exercise, &normal);
+        assert_eq!(nudge.as_deref(), Some("今はノーマルモードです（挿入モードにします）"));
+        assert_eq!(session.nudge(&exercise, &sequence_state(VimMode::Insert, "")), None);
+
+        session.nudge_shown = nudge;
+        let lines = session.pane_lines(&exercise);
+        assert!(lines.contains(&"     🧭 今はノーマルモードです（挿入モードにします）".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_line_goal_conversion_and_diff() -> Result<()> {
+        let mut session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let block_goal = ExerciseGoal {
+            goal_type: "text_block".to_string(),
+            target: json!({"start_line": 1, "expected": ["b", "c"]}),
+            description: "行を入れ替える".to_string(),
+            ..Default::default()
+        };
+        let buffer_goal = ExerciseGoal {
+            goal_type: "buffer".to_string(),
+            target: json!(["a", "b", "c"]),
+            description: "バッファ全体".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            block_goal.to_goal()?.goal_type,
+            GoalType::TextBlock {
+                start_line: 1,
+                expected: vec!["b".to_string(), "c".to_string()],
+                normalize: Normalize::default(),
+            }
+        );
+        let invalid = ExerciseGoal {
+            target: json!(["a", 1]),
+            ..buffer_goal.clone()
+        };
+        assert!(invalid.to_goal().is_err());
+
+        let exercise = ContinuousExercise {
+            title: "Lines".to_string(),
+            sample_code: vec!["a".to_string(), "c".to_string(), "b".to_string()],
+            goals: vec![block_goal, buffer_goal],
+            ..Default::default()
+        };
+        assert!(exercise.watches_buffer());
+
+        let mut state = sequence_state(VimMode::Normal, "a");
+        state.buffer_content = exercise.sample_code.clone();
+        let text_diff = session.text_diff(&exercise, &state);
+        assert_eq!(text_diff[2], "@@ -2,2 +2,2 @@");
+
+        // バッファが短い場合も期待の行を示す
+        start_ordered(&mut session, &exercise)?;
+        session.engine.set_completed(vec![true, false]);
+        state.buffer_content.truncate(1);
+        assert_eq!(
+            session.text_diff(&exercise, &state)[2..],
+            ["@@ -1,3 +1,1 @@", " a", "-b", "-c"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact_pane_fits_three_lines() {
+        let mut session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        session.layout = PaneLayout::Compact;
+        session.pane_width = 30;
+        let mut exercise = ordered_exercise();
+        exercise.goals[0].description = "Insert モードに入って、続けて長い説明を読む".to_string();
+        exercise.goals[0].hint = Some("i を押します".to_string());
+
+        let lines = session.pane_lines(&exercise);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("🎯 1/2 Insert"));
+        assert!(lines[0].ends_with('…'));
+        assert!(lines.iter().all(|line| UnicodeWidthStr::width(line.as_str()) <= 29));
+        assert_eq!(lines[2], "📊 0/2 ⏱️ 0:00  📚 Order");
+
+        // ヒントを要求すると2行目がヒントになり、残り時間は3行目に並ぶ
+        start_ordered(&mut session, &exercise).unwrap();
+        assert!(session.engine.request_hint());
+        session.remaining_shown = Some(12);
+        session.countdown_shown = Some(5);
+        let lines = session.pane_lines(&exercise);
+        assert_eq!(lines[1], "💡 i を押します");
+        assert!(lines[2].starts_with("⏱️ 残り 12s  ⏳ ヒントまで 5"), "{}", lines[2]);
+        assert_eq!(session.pane_lines(&exercise), lines);
+
+        // 進捗はその後ろに数だけを出す（制限時間があれば経過時間は出さない）
+        session.pane_width = 60;
+        session.engine.set_completed(vec![true, false]);
+        assert_eq!(session.pane_lines(&exercise)[2], "⏱️ 残り 12s  ⏳ ヒントまで 5s  📊 1/2");
+    }
+
+    #[test]
+    fn test_zen_pane_hides_numbers_but_still_escalates_hints() -> Result<()> {
+        let config = TutorialConfig {
+            zen: true,
+            hint_delay_seconds: 20,
+            ..TutorialConfig::default()
+        };
+        let mut session = ContinuousVimSession::with_backend(Box::new(MockBackend::new(Vec::new())), config);
+        let mut exercise = ordered_exercise();
+        exercise.goals[0].hint = Some("i を押します".to_string());
+        start_ordered(&mut session, &exercise)?;
+        session.remaining_shown = Some(12);
+        session.countdown_shown = Some(5);
+
+        let lines = session.pane_lines(&exercise);
+        let numbers = ["⏳", "⏱️", "📊", "目標一覧"];
+        assert!(lines.iter().all(|line| numbers.iter().all(|mark| !line.contains(mark))), "{:?}", lines);
+        assert!(lines.iter().any(|line| line.ends_with("1. Insert モードに入る")));
+
+        // カウントダウンは出さないが、時間が来ればヒントは上がる
+        let state = TimedState { at: Duration::from_secs(21), state: sequence_state(VimMode::Normal, "") };
+        session.update_hint_timer(&exercise, &state)?;
+        assert_eq!(session.engine.hint_level(), 1);
+        assert!(session.pane_lines(&exercise).iter().any(|line| line.contains("💡 i を押します")));
+
+        session.layout = PaneLayout::Compact;
+        let lines = session.pane_lines(&exercise);
+        assert_eq!(lines[0], "🎯 Insert モードに入る");
+        assert_eq!(lines[2], "📚 Order");
+        Ok(())
+    }
+
+    #[test]
+    fn test_reference_stays_below_goal_and_is_cut_to_the_pane() {
+        let mut session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let exercise = ContinuousExercise {
+            reference: (1..=6).map(|n| format!("参考{}", n)).collect(),
+            ..ordered_exercise()
+        };
+        start_ordered(&mut session, &exercise).unwrap();
+        assert!(session.engine.request_hint());
+        let lines = session.pane_lines(&exercise);
+        let header = lines.iter().position(|line| line == "=== 📌 参考 ===").unwrap();
+        assert_eq!(lines[header + 1..header + 7], (1..=6).map(|n| format!("  参考{}", n)).collect::<Vec<_>>());
+        assert_eq!(lines.last().map(String::as_str), Some(pane::COMMAND_FOOTER));
+
+        // 行が足りなければ目標とヒントを残し、参考を … で省く
+        session.pane_height = header + 5;
+        let lines = session.pane_lines(&exercise);
+        assert!(lines.iter().any(|line| line.contains("Insert")));
+        assert_eq!(lines[header..header + 3], ["=== 📌 参考 ===", "  参考1", "  …（ほか5行）"]);
+        assert_eq!(lines.len(), session.pane_height - 1);
+        assert_eq!(lines.last().map(String::as_str), Some(pane::COMMAND_FOOTER));
+        session.pane_height = header + 2;
+        assert!(!session.pane_lines(&exercise).iter().any(|line| line.contains("参考")));
+    }
+
+    #[test]
+    fn test_position_at_resolves_occurrence_and_multibyte_columns() {
+        let mut exercise = ContinuousExercise {
+            sample_code: vec![
+                "// 表示する: console.log".to_string(),
+                "console.log(1);".to_string(),
+                "let 名前 = 1; console.log(名前);".to_string(),
+            ],
+            ..ordered_exercise()
+        };
+        let anchor = |target: serde_json::Value| ExerciseGoal {
+            goal_type: "position_at".to_string(),
+            target,
+            description: "log へ".to_string(),
+            ..Default::default()
+        };
+        exercise.goals = vec![
+            anchor(json!({"text": "console.log", "offset": 8})),
+            anchor(json!({"text": "console.log", "occurrence": 3, "offset": 8})),
+            anchor(json!({"text": "名前", "occurrence": 2, "offset": 1})),
+            anchor(json!({"text": "console.log", "occurrence": 4})),
+        ];
+        assert_eq!(
+            exercise.resolve_anchors(),
+            vec!["目標4「log へ」: 「console.log」はサンプルコードに3か所しかありません（occurrence: 4）".to_string()]
+        );
+        // 列は状態の列と同じ文字単位（前にある日本語も1文字と数える）
+        let positions: Vec<_> = exercise.goals[..3]
+            .iter()
+            .map(|goal_def| goal_def.to_goal().unwrap().goal_type)
+            .collect();
+        assert!(matches!(positions[0], GoalType::Position { line: 0, col: 17 }));
+        assert!(matches!(positions[1], GoalType::Position { line: 2, col: 20 }));
+        assert!(matches!(positions[2], GoalType::Position { line: 2, col: 25 }));
+        assert_eq!(exercise.goals[3].goal_type, "position_at");
+    }
+
+    #[test]
+    fn test_poll_interval_backs_off_when_idle_and_follows_config() -> Result<()> {
+        let fast = Duration::from_millis(100);
+        let interval = ContinuousVimSession::backoff_interval;
+        assert_eq!(interval(fast, Duration::from_secs(1)), fast);
+        assert_eq!(interval(fast, FAST_POLL_WINDOW), SLOW_POLL_INTERVAL);
+        // 設定の間隔が遅い間隔より長ければ、そのまま使う
+        assert_eq!(interval(Duration::from_millis(800), FAST_POLL_WINDOW), Duration::from_millis(800));
+
+        // エディタのタイマーも設定の間隔で動かす
+        let config = TutorialConfig {
+            poll_interval_ms: 250,
+            ..TutorialConfig::default()
+        };
+        let session = ContinuousVimSession::with_config("/tmp/test.sock".to_string(), config);
+        let content = fs::read_to_string(session.create_vim_script(&ordered_exercise())?.path())?;
+        assert!(content.contains("let g:update_timer = timer_start(250, 'TimerUpdate', {'repeat': -1})"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_vim_script_writes_buffer_only_for_text_goals() -> Result<()> {
+        let session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let mut exercise = ordered_exercise();
+        assert!(exercise.watches_buffer());
+        let content = fs::read_to_string(session.create_vim_script(&exercise)?.path())?;
+        assert!(content.contains("let g:tutorial_watch_buffer = 1"));
+        assert!(content.contains("call add(lines, 'BUF:' . s:buffer_json)"));
+
+        exercise.goals.truncate(1);
+        assert!(!exercise.watches_buffer());
+        let content = fs::read_to_string(session.create_vim_script(&exercise)?.path())?;
+        assert!(content.contains("let g:tutorial_watch_buffer = 0"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_editor_script_removes_everything_it_defines() -> Result<()> {
+        let session = mock_session(MockBackend::new(Vec::new()).sharing_editor());
+        let content = fs::read_to_string(session.create_vim_script(&ordered_exercise())?.path())?;
+        // 監視はバッファとタブに限り、学習者の設定を変えない
+        assert!(content.contains("BufEnter,WinEnter <buffer> call UpdateStatus()"));
+        assert!(content.contains("'nnoremap <silent> <buffer> ' . s:key"));
+        assert!(content.contains("if !get(t:, 'vim_tutorial', 0)"));
+        assert!(content.contains("  setlocal number\n"));
+        assert!(!content.contains("ruler"));
+
+        // 定義したコマンド・関数・グローバル変数は全て TutorialDetach で消す
+        for line in content.lines() {
+            if let Some(name) = line.strip_prefix("function! ").and_then(|rest| rest.split('(').next()) {
+                assert!(name == "TutorialDetach" || SCRIPT_FUNCTIONS.contains(&name), "{} を消していません", name);
+            }
+            if let Some(name) = line.strip_prefix("command! ").and_then(|rest| rest.split(' ').next()) {
+                assert!(SCRIPT_COMMANDS.contains(&name), "{} を消していません", name);
+            }
+            if let Some(name) = line.trim().strip_prefix("let g:").and_then(|rest| rest.split(' ').next()) {
+                assert!(
+                    SCRIPT_GLOBALS.contains(&name) || ["update_timer", "tutorial_started", "tutorial_detach"].contains(&name),
+                    "g:{} を消していません",
+                    name
+                );
+            }
+        }
+        assert!(content.contains("let g:tutorial_detach = ['call TutorialDetach()'"));
+        assert!(content.contains("lua vim.on_key(nil, vim.api.nvim_create_namespace('vim_tutorial_keys'))"));
+
+        // 新しく起動したエディタでは片付けない
+        let session = ContinuousVimSession::new("/tmp/test.sock".to_string());
+        let content = fs::read_to_string(session.create_vim_script(&ordered_exercise())?.path())?;
+        assert!(!content.contains("TutorialDetach"));
+        assert!(content.contains(",WinEnter * call UpdateStatus()"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_watched_registers_follow_goals() {
+        let register_goal = |name: &str| ExerciseGoal {
+            goal_type: "register".to_string(),
+            target: json!({"register": name, "expected": "text"}),
+            description: "Yank".to_string(),
+            ..Default::default()
+        };
+        let exercise = ContinuousExercise {
+            title: "Registers".to_string(),
+            sample_code: vec!["text".to_string()],
+            goals: vec![register_goal("z"), register_goal("a"), register_goal("+")],
+            flow_type: FlowType::AnyOrder,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            exercise.watched_registers(),
+            vec!["\"", "0", "1", "a", "b", "c", "z", "+"]
+        );
+    }
+
+    fn mock_session(backend: MockBackend) -> ContinuousVimSession {
+        ContinuousVimSession::with_backend(Box::new(backend), TutorialConfig::default())
+    }
+
+    fn cursor_state(mode: VimMode, line: &str, cursor_col: usize) -> VimState {
+        VimState {
+            cursor_col,
+            ..sequence_state(mode, line)
+        }
+    }
+
+    #[test]
+    fn test_counted_motion_rejects_repeated_keys() -> Result<()> {
+        let lines: Vec<String> = ["a", "b", "c", "d", "e"].iter().map(|line| line.to_string()).collect();
+        let exercise = ContinuousExercise {
+            title: "5j".to_string(),
+            sample_code: lines.clone(),
+            goals: vec![ExerciseGoal {
+                goal_type: "counted_motion".to_string(),
+                target: json!({"motion": "j", "min_count": 4, "position": [4, 0]}),
+                description: "4j で一気に下へ".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let at_line = |cursor_line| VimState {
+            cursor_line,
+            buffer_content: lines.clone(),
+            ..sequence_state(VimMode::Normal, "")
+        };
+        let backend = MockBackend::new(vec![
+            MockStep::state(at_line(0)),
+            // j を4回押して着地しても達成にしない
+            MockStep::state(at_line(4)).with_key_log(&["n\tj", "n\tj", "n\tj", "n\tj"]),
+            MockStep::state(at_line(0)).with_key_log(&["n\t4k"]),
+            // カウントと移動が別の行で届く
+            MockStep::state(at_line(4)).with_key_log(&["n\t4", "n\tj"]),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+        session.start_exercise(exercise, "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+
+        let nudge = "     🧭 jを4回押す代わりに、4j のように数を付けて一度で移動します".to_string();
+        let record = record.borrow();
+        assert!(record.instructions.iter().any(|lines| lines.contains(&nudge)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_object_goal_requires_the_keys_not_the_result() -> Result<()> {
+        let exercise = ContinuousExercise {
+            title: "ciw".to_string(),
+            sample_code: vec!["let colour = 1;".to_string()],
+            goals: vec![ExerciseGoal {
+                goal_type: "operator_textobject".to_string(),
+                target: json!({"operator": "c", "text_object": "iw"}),
+                description: "ciw で書き換える".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let buffer = |line: &str, changedtick| VimState {
+            buffer_content: vec![line.to_string()],
+            changedtick,
+            ..sequence_state(VimMode::Normal, "")
+        };
+        let mut steps = vec![
+            MockStep::state(buffer("let colour = 1;", 0)),
+            // 1文字ずつ消して同じ結果にしても達成にしない
+            MockStep::state(buffer("let  = 1;", 6)).with_key_log(&["n\tw", "n\t6x"]),
+            MockStep::state(buffer("let colour = 1;", 7)).with_key_log(&["n\tu"]),
+        ];
+        let mut session = mock_session(MockBackend::new(steps.clone()));
+        session.start_exercise(exercise.clone(), "/tmp/sample.txt")?;
+        assert!(session.monitor_progress().is_err());
+
+        // オペレーター待機を状態として見ていなくても、キーの並びで判定する
+        steps.push(MockStep::state(buffer("let  = 1;", 8)).with_key_log(&["n\tc", "no\ti", "no\tw"]));
+        let mut session = mock_session(MockBackend::new(steps));
+        session.start_exercise(exercise, "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_operator_pending_hint_follows_mode_changes() -> Result<()> {
+        let exercise = |operator_hints| ContinuousExercise {
+            title: "diw".to_string(),
+            sample_code: vec!["let colour = 1;".to_string()],
+            goals: vec![ExerciseGoal {
+                goal_type: "operator_textobject".to_string(),
+                target: json!({"operator": "d", "text_object": "iw"}),
+                description: "diw で単語を消す".to_string(),
+                ..Default::default()
+            }],
+            operator_hints,
+            ..Default::default()
+        };
+        let steps = || {
+            vec![
+                MockStep::state(sequence_state(VimMode::Normal, "let colour = 1;")),
+                MockStep::state(sequence_state(VimMode::OperatorPending("d".to_string()), "let colour = 1;"))
+                    .with_key_log(&["n\td"]),
+                // Esc で取り消すと案内も消える
+                MockStep::state(sequence_state(VimMode::Normal, "let colour = 1;")).with_key_log(&["no\t<Esc>"]),
+                MockStep::state(VimState {
+                    changedtick: 1,
+                    ..sequence_state(VimMode::Normal, "let  = 1;")
+                })
+                .with_key_log(&["n\td", "no\ti", "no\tw"]),
+            ]
+        };
+        let hint = "     ⌛ オペレーター 'd' 待機中 — 次にテキストオブジェクト（例: iw / aw / i\" / w / $ / dd）を入力";
+
+        let backend = MockBackend::new(steps());
+        let record = backend.record();
+        let mut session = mock_session(backend);
+        session.start_exercise(exercise(true), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        let record = record.borrow();
+        let frames: Vec<bool> = record.instructions.iter().map(|lines| lines.iter().any(|line| line == hint)).collect();
+        let shown = frames.iter().position(|&shown| shown).expect("待機中の案内が出ていない");
+        assert!(frames[..shown].iter().all(|&shown| !shown));
+        assert!(!frames[shown + 1], "モードが戻っても案内が残っている");
+        // 目標の行は案内に置き換えずに残す
+        assert!(record.instructions[shown].contains(&"  1. diw で単語を消す".to_string()));
+
+        // 練習で有効にしていなければ出さない
+        let backend = MockBackend::new(steps());
+        let record = backend.record();
+        let mut session = mock_session(backend);
+        session.start_exercise(exercise(false), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        assert!(record.borrow().instructions.iter().flatten().all(|line| !line.contains("⌛")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_stalled_text_goal_shows_aligned_comparison() -> Result<()> {
+        let config = TutorialConfig {
+            stalled_text_seconds: 0,
+            ..TutorialConfig::default()
+        };
+        let edited = |changedtick, line: &str| VimState {
+            changedtick,
+            ..sequence_state(VimMode::Normal, line)
+        };
+
+        // 残りがテキストの目標だけになり、バッファが変わらないまま止まると比較を出す
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(VimState { changedtick: 1, ..sequence_state(VimMode::Insert, "x ") }),
+            MockStep::state(edited(1, "x ")),
+            MockStep::state(edited(1, "x ")),
+            MockStep::state(edited(2, "X")),
+        ]);
+        let record = backend.record();
+        let mut session = ContinuousVimSession::with_backend(Box::new(backend), config.clone());
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        let record = record.borrow();
+        let shown = record
+            .instructions
+            .iter()
+            .find(|lines| lines.iter().any(|line| line.contains("🔎")))
+            .expect("比較が出ていない");
+        let comparison: Vec<&str> = shown.iter().map(|line| line.trim()).skip_while(|line| !line.starts_with("1行目")).take(4).collect();
+        assert_eq!(comparison, ["1行目:", "期待: X", "現在: x·", "^"]);
+        assert!(record.buffer_reads > 0);
+
+        // テキストでない目標が残っているあいだは出さない
+        let backend = MockBackend::new(vec![
+            MockStep::state(edited(1, "x")),
+            MockStep::state(edited(1, "x")),
+            MockStep::state(edited(1, "x")),
+            MockStep::state(sequence_state(VimMode::Insert, "x")),
+        ]);
+        let record = backend.record();
+        let mut session = ContinuousVimSession::with_backend(Box::new(backend), config);
+        let exercise = ContinuousExercise {
+            goals: ordered_exercise().goals[..1].to_vec(),
+            ..ordered_exercise()
+        };
+        session.start_exercise(exercise, "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        assert!(record.borrow().instructions.iter().flatten().all(|line| !line.contains("🔎")));
+        assert_eq!(record.borrow().buffer_reads, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dot_repeat_counts_only_repeats_that_change_the_buffer() -> Result<()> {
+        let exercise = |min_times| ContinuousExercise {
+            title: ".".to_string(),
+            sample_code: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            goals: vec![
+                ExerciseGoal {
+                    goal_type: "text_block".to_string(),
+                    target: json!({"start_line": 0, "expected": ["a;", "b;", "c;"]}),
+                    description: "3行の末尾に ; を付ける".to_string(),
+                    ..Default::default()
+                },
+                ExerciseGoal {
+                    goal_type: "dot_repeat".to_string(),
+                    target: json!({"min_times": min_times}),
+                    description: ". で繰り返す".to_string(),
+                    ..Default::default()
+                },
+            ],
+            flow_type: FlowType::AnyOrder,
+            ..Default::default()
+        };
+        let buffer = |lines: &[&str], changedtick| VimState {
+            buffer_content: lines.iter().map(|line| line.to_string()).collect(),
+            changedtick,
+            ..sequence_state(VimMode::Normal, "")
+        };
+        let steps = || {
+            vec![
+                MockStep::state(buffer(&["a", "b", "c"], 0)),
+                // 繰り返す変更が無いまま . を押しても数えない
+                MockStep::state(buffer(&["a", "b", "c"], 0)).with_key_log(&["n\t.", "n\t."]),
+                MockStep::state(buffer(&["a;", "b", "c"], 2)).with_key_log(&["n\tA", "i\t;", "i\t<Esc>"]),
+                MockStep::state(buffer(&["a;", "b;", "c"], 3)).with_key_log(&["n\tj", "n\t."]),
+                // 手で打ち直した分は . の繰り返しではない
+                MockStep::state(buffer(&["a;", "b;", "c;"], 5))
+                    .with_key_log(&["n\tj", "n\tA", "i\t;", "i\t<Esc>"]),
+            ]
+        };
+
+        let mut session = mock_session(MockBackend::new(steps()));
+        session.start_exercise(exercise(2), "/tmp/sample.txt")?;
+        assert!(session.monitor_progress().is_err());
+        assert_eq!(session.completed_goals(), [true, false]);
+
+        let mut session = mock_session(MockBackend::new(steps()));
+        session.start_exercise(exercise(1), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_substitution_goal_counts_from_activation() -> Result<()> {
+        let exercise = ContinuousExercise {
+            title: ":s".to_string(),
+            sample_code: vec!["foo foo".to_string(), "foo".to_string()],
+            goals: vec![ExerciseGoal {
+                goal_type: "substitution".to_string(),
+                target: json!({"pattern": "foo", "replacement": "bar", "min_count": 3}),
+                description: "foo を全て bar に".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let buffer = |lines: &[&str], changedtick| VimState {
+            buffer_content: lines.iter().map(|line| line.to_string()).collect(),
+            changedtick,
+            ..sequence_state(VimMode::Normal, "")
+        };
+        let backend = MockBackend::new(vec![
+            MockStep::state(buffer(&["foo foo", "foo"], 1)),
+            // g を付けずに1行目だけを置き換えた
+            MockStep::state(buffer(&["bar foo", "foo"], 2)).with_command("EX_COMMAND\ts/foo/bar/"),
+            MockStep::state(buffer(&["bar bar", "bar"], 3)).with_command("EX_COMMAND\t%s/foo/bar/g"),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+        session.start_exercise(exercise, "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+
+        let nudge = "     🧭 あと2か所（行の中の全てを置き換えるには g を、全ての行には % を付けます）".to_string();
+        assert!(record.borrow().instructions.iter().any(|lines| lines.contains(&nudge)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_coaching_tip_for_repeated_motion_is_rate_limited() -> Result<()> {
+        let normal = sequence_state(VimMode::Normal, "");
+        let backend = MockBackend::new(vec![
+            MockStep::state(normal.clone()).with_key_log(&["n\tlll", "n\tllll"]),
+            // 20秒以内の繰り返しには助言しない
+            MockStep::state(normal).with_key_log(&["n\tlllllllll"]),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+        ]);
+        let record = backend.record();
+        let config = TutorialConfig {
+            coaching: true,
+            ..TutorialConfig::default()
+        };
+        let mut session = ContinuousVimSession::with_backend(Box::new(backend), config);
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        // 助言は目標の進行を妨げない
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+
+        let record = record.borrow();
+        let tips: Vec<&String> = record
+            .instructions
+            .iter()
+            .flatten()
+            .filter(|line| line.contains("💬"))
+            .collect();
+        assert!(tips[0].starts_with("     💬 lを7回続けて押しています。f<文字> で目的の文字へ"));
+        assert!(tips.iter().all(|tip| !tip.contains("9回")));
+
+        // 練習ごとに止められる
+        let mut exercise = ordered_exercise();
+        exercise.coaching = Some(false);
+        let mut session = mock_session(MockBackend::new(Vec::new()));
+        session.config.coaching = true;
+        let repeated = keystrokes::MotionUse {
+            count: None,
+            motion: "l".to_string(),
+        };
+        (0..10).for_each(|_| session.repeat_run.record(&repeated));
+        session.follow_coaching(&exercise)?;
+        assert_eq!(session.coaching_shown, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_intro_shown_in_first_pane_until_goal_progress() -> Result<()> {
+        let mut exercise = ordered_exercise();
+        exercise.intro = Some("挿入モードで文字を打ちます\nEsc で戻ります".to_string());
+        exercise.outro = Some("よくできました".to_string());
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+        session.start_exercise(exercise.clone(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        // テストのセッションでは Enter を待たない
+        session.show_outro(&exercise);
+
+        let record = record.borrow();
+        assert_eq!(record.instructions[0][2], "📖 挿入モードで文字を打ちます");
+        assert_eq!(record.instructions[0][3], "📖 Esc で戻ります");
+        let last = record.instructions.last().unwrap();
+        assert!(!last.iter().any(|line| line.starts_with("📖")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_keep_session_skips_teardown_and_stop() -> Result<()> {
+        let exercise = ContinuousExercise {
+            teardown: vec!["let @a = ''".to_string()],
+            ..ordered_exercise()
+        };
+        let backend = MockBackend::new(vec![MockStep::state(sequence_state(VimMode::Normal, ""))]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+        session.keep_session = true;
+
+        session.start_exercise(exercise, "/tmp/sample.txt")?;
+        session.stop_exercise()?;
+
+        let record = record.borrow();
+        assert_eq!((record.started, record.stopped, record.kept), (1, 0, 1));
+        assert!(record.sent_keys.is_empty());
+        assert!(session.vim_script.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_restarts_every_goal_and_counts_attempts() -> Result<()> {
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "")).with_keystrokes(1),
+            MockStep::state(sequence_state(VimMode::Normal, "")).with_command("RETRY"),
+            MockStep::state(sequence_state(VimMode::Insert, "")).with_keystrokes(1),
+            MockStep::state(sequence_state(VimMode::Insert, "X")).with_keystrokes(1),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        assert_eq!(session.attempts(), 2);
+        // やり直す前のキーは数えない
+        assert_eq!(session.total_keystrokes, 2);
+
+        let record = record.borrow();
+        assert_eq!(record.goal_marks(), vec!["1", "2", "1", "2", "completed"]);
+        let retried = record
+            .instructions
+            .iter()
+            .rposition(|lines| lines.contains(&"  ▶️ 1. Insert モードに入る".to_string()))
+            .unwrap();
+        assert!(retried > 1);
+        assert!(record.instructions[retried].contains(&"  ⬜ 2. X と入力する".to_string()));
+
+        // 次の練習は1回目から数える
+        drop(record);
+        session.stop_exercise()?;
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(session.attempts(), 1);
+        Ok(())
+    }
+
     #[test]
-    fn test_continuous_session_creation() {
-        let session = ContinuousVimSession::new("/tmp/test.sock".to_string());
-        assert!(session.current_exercise.is_none());
-        assert_eq!(session.current_goal_index, 0);
+    fn test_sequential_exercise_through_mock_backend() -> Result<()> {
+        let mut exercise = ordered_exercise();
+        exercise.goals[0].hint = Some("i を押します".to_string());
+        let backend = MockBackend::new(vec![
+            MockStep::default(), // 状態ファイルがまだ無い
+            MockStep::state(sequence_state(VimMode::Normal, "")).with_command("HINT_REQUEST"),
+            MockStep::state(sequence_state(VimMode::Insert, "")).with_keystrokes(1),
+            MockStep::state(sequence_state(VimMode::Insert, "X")).with_keystrokes(1),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+
+        session.start_exercise(exercise, "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        session.stop_exercise()?;
+
+        let record = record.borrow();
+        assert_eq!((record.started, record.stopped), (1, 1));
+        assert_eq!(record.goal_marks(), vec!["1", "2", "completed"]);
+        assert!(record.sent_keys.is_empty()); // teardown が無ければキーは送らない
+
+        // 初回表示・ヒント・次の目標・完了の順に表示される
+        let updates = &record.instructions;
+        assert_eq!(updates.len(), 4);
+        assert!(updates[0].contains(&"  1. Insert モードに入る".to_string()));
+        assert!(!updates[0].iter().any(|line| line.contains("💡")));
+        assert!(updates[1].contains(&"     💡 i を押します".to_string()));
+        assert!(updates[2].contains(&"  2. X と入力する".to_string()));
+        assert!(updates[2].contains(&"📊 ██████████░░░░░░░░░░ 1/2 目標  ⏱️ 0:00".to_string()));
+        // 一覧では達成した目標と現在の目標が分かる
+        assert!(updates[0].contains(&"  ▶️ 1. Insert モードに入る".to_string()));
+        assert!(updates[0].contains(&"  ⬜ 2. X と入力する".to_string()));
+        assert!(updates[2].contains(&"  ✅ 1. Insert モードに入る".to_string()));
+        assert!(updates[2].contains(&"  ▶️ 2. X と入力する".to_string()));
+        assert!(!updates[2].iter().any(|line| line.contains("💡"))); // 目標が変わるとヒントは戻る
+        assert_eq!(updates[3][0], "=== 🎉 章完了！ ===");
+        assert!(updates[3].contains(&"  2. X と入力する".to_string()));
+
+        assert_eq!(session.hints_used(), 1);
+        assert_eq!(
+            record.announcements,
+            vec![
+                "✅ 目標達成！（1/2）次: X と入力する".to_string(),
+                "🎉 練習「Order」を完了しました！".to_string(),
+            ]
+        );
+        Ok(())
     }
 
     #[test]
-    fn test_goal_conversion() -> Result<()> {
-        let tmp_dir = tempdir()?;
-        let socket_path = tmp_dir
-            .path()
-            .join("test.sock")
-            .to_string_lossy()
-            .to_string();
-        let session = ContinuousVimSession::new(socket_path);
+    fn test_reconnects_after_short_disconnect() -> Result<()> {
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Normal, "")).disconnected(),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
 
-        // Position goal
-        let pos_goal_def = ExerciseGoal {
-            goal_type: "position".to_string(),
-            target: json!([1, 2]),
-            description: "Test position".to_string(),
-            hint: None,
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+
+        let record = record.borrow();
+        assert_eq!(record.reconnects, 1);
+        assert!(record.instructions[1].contains(&"🔌 Neovimへの接続を再確立中... (1/5)".to_string()));
+        // 接続が戻ったら元の表示に戻す
+        assert!(!record.instructions[2].iter().any(|line| line.contains("🔌")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_gives_up_after_reconnect_attempts() -> Result<()> {
+        let config = TutorialConfig {
+            reconnect_attempts: 2,
+            ..TutorialConfig::default()
         };
-        let goal = session.convert_goal_definition(&pos_goal_def)?;
-        match goal.goal_type {
-            GoalType::Position { line, col } => {
-                assert_eq!(line, 1);
-                assert_eq!(col, 2);
+        let mut steps = vec![MockStep::state(sequence_state(VimMode::Normal, ""))];
+        steps.extend(
+            (0..3).map(|_| MockStep::state(sequence_state(VimMode::Normal, "")).disconnected()),
+        );
+        let backend = MockBackend::new(steps);
+        let record = backend.record();
+        let mut session = ContinuousVimSession::with_backend(Box::new(backend), config);
+
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(
+            session.monitor_progress()?,
+            ExerciseResult::Failed(
+                "Neovimへの接続を再確立できませんでした（2回試行）: MockBackend: ソケットがありません"
+                    .to_string()
+            )
+        );
+        assert_eq!(record.borrow().reconnects, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fails_when_first_state_never_arrives() -> Result<()> {
+        let config = TutorialConfig {
+            status_timeout_seconds: 0,
+            ..TutorialConfig::default()
+        };
+        // 状態が届かない間は仮の状態で目標を判定しない
+        let backend = MockBackend::new(vec![MockStep::default(), MockStep::default()]);
+        let mut session = ContinuousVimSession::with_backend(Box::new(backend), config);
+
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        let ExerciseResult::Failed(message) = session.monitor_progress()? else {
+            panic!("状態が届かないのに失敗になりませんでした");
+        };
+        assert!(message.starts_with("0秒待ってもNeovimから状態が届きませんでした"));
+        assert!(!message.contains("入力待ち"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocked_editor_is_reported_instead_of_restarted() -> Result<()> {
+        // 練習の途中でスワップファイルの確認などに止められても、起動し直さずに学習者が答えるのを待つ
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Normal, "")).disconnected().blocking(),
+            MockStep::state(sequence_state(VimMode::Normal, "")).disconnected().blocking(),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        let record = record.borrow();
+        assert_eq!(record.reconnects, 0);
+        let guidance = "⛔ Neovimが入力待ちです（blocking: true） — 下のペインを確認してください".to_string();
+        assert!(record.instructions[1].contains(&guidance));
+        // 続けて止まっている間は表示し直さず、答えた後は元の表示に戻す
+        assert_eq!(record.instructions.iter().filter(|lines| lines.contains(&guidance)).count(), 1);
+        assert!(!record.instructions[2].contains(&guidance));
+
+        // 状態が一度も届かないまま止まっていれば、失敗の理由に入力待ちを添える
+        let config = TutorialConfig {
+            status_timeout_seconds: 0,
+            ..TutorialConfig::default()
+        };
+        let backend = MockBackend::new(vec![MockStep::default().blocking()]);
+        let mut session = ContinuousVimSession::with_backend(Box::new(backend), config);
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        let ExerciseResult::Failed(message) = session.monitor_progress()? else {
+            panic!("状態が届かないのに失敗になりませんでした");
+        };
+        assert!(message.ends_with("Neovimは入力待ちで止まっていました（スワップファイルの確認などが出ていないか確かめてください）"));
+        assert_eq!(
+            ContinuousVimSession::blocked_guidance("Neovim", None),
+            "💡 Neovimが入力待ちの可能性があります — 下のペインを確認してください"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_vim_editor_skips_lua_and_keystroke_scores() -> Result<()> {
+        let exercise = ContinuousExercise {
+            optimal_keystrokes: Some(2),
+            ..ordered_exercise()
+        };
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+        ])
+        .with_editor(Editor::Vim);
+        let mut session = mock_session(backend);
+
+        // Vim のスクリプトは Lua と Neovim の API を使わない
+        let content = fs::read_to_string(session.create_vim_script(&exercise)?.path())?;
+        assert!(!content.contains("lua"));
+        assert!(!content.contains("nvim_"));
+        assert!(content.contains("call echoraw(\"\\x07\")"));
+        assert!(content.contains("if exists('##ModeChanged')"));
+
+        session.start_exercise(exercise, "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        // キー入力を記録できないため、0キーで満点にはしない
+        assert!(session.keystroke_scores().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_share_dir_receives_goal_progress() -> Result<()> {
+        let dir = tempdir()?;
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+        ]);
+        let mut session = mock_session(backend);
+        session.share_status_in(dir.path());
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+
+        let content = fs::read_to_string(dir.path().join(crate::share::SHARE_STATUS_FILE))?;
+        let shared: SharedStatus = crate::session_progress::parse_versioned(&content)?;
+        assert_eq!(shared.progress.exercise_title, "Order");
+        assert!(shared.progress.goals.iter().all(|goal| goal.status == crate::session_progress::GoalStatus::Done));
+        assert_eq!(shared.progress.finished.as_deref(), Some("完了"));
+        assert!(!content.contains("\"X\"")); // バッファの内容は共有しない
+        Ok(())
+    }
+
+    #[test]
+    fn test_pause_command_stops_goal_evaluation() -> Result<()> {
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Normal, "")).with_command("PAUSE\t[\"\"]"),
+            // 一時停止中の操作・ヒント要求は無視する
+            MockStep::state(sequence_state(VimMode::Insert, "")).with_keystrokes(3),
+            MockStep::state(sequence_state(VimMode::Normal, "")).with_command("HINT_REQUEST"),
+            MockStep::state(sequence_state(VimMode::Normal, "")).with_command("RESUME"),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        assert_eq!(session.hints_used(), 0);
+        assert_eq!(session.total_keystrokes, 0);
+
+        let record = record.borrow();
+        assert_eq!(record.goal_marks(), vec!["1", "2", "completed"]);
+        let updates = &record.instructions;
+        assert_eq!(updates[1][0], "=== ⏸️ 一時停止中 ===");
+        assert!(updates[1].contains(&"▶️ :TutorialResume で再開します".to_string()));
+        // 再開すると同じ目標を表示し直す
+        assert!(updates[2].contains(&"  ▶️ 1. Insert モードに入る".to_string()));
+
+        // タイムチャレンジ中は一時停止しない
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, "")).with_command("PAUSE"),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+        session.set_time_limit(Some(30));
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        assert_eq!(
+            record.borrow().announcements[0],
+            "⏱️ タイムチャレンジ中は一時停止できません"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_detach_pauses_until_learner_returns() -> Result<()> {
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Normal, "")).detached(),
+            MockStep::state(sequence_state(VimMode::Insert, "")).detached(),
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+
+        let record = record.borrow();
+        assert_eq!(record.goal_marks(), vec!["1", "2", "completed"]);
+        assert!(record.instructions[1].contains(&"▶️ tmuxセッションに戻ると再開します".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_live_pane_shows_cursor_position() -> Result<()> {
+        let mut exercise = ordered_exercise();
+        exercise.sample_code = vec!["abc".to_string()];
+        exercise.goals.insert(
+            0,
+            ExerciseGoal {
+                goal_type: "position".to_string(),
+                target: json!([0, 2]),
+                description: "右へ移動".to_string(),
+                ..Default::default()
+            },
+        );
+        let backend = MockBackend::from_states(vec![
+            cursor_state(VimMode::Normal, "abc", 0),
+            cursor_state(VimMode::Normal, "abc", 2),
+            sequence_state(VimMode::Insert, "abc"),
+            sequence_state(VimMode::Insert, "X"),
+        ])
+        .with_live_pane();
+        let record = backend.record();
+        let mut session = mock_session(backend);
+
+        session.start_exercise(exercise, "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        let record = record.borrow();
+        assert!(record.instructions[1].contains(&"📍 行 1 / 列 1 | NORMAL | 目標: 行 1 / 列 3".to_string()));
+
+        // 描き直すとちらつく表示先には出さない
+        let backend = MockBackend::from_states(vec![
+            sequence_state(VimMode::Normal, ""),
+            sequence_state(VimMode::Insert, ""),
+            sequence_state(VimMode::Insert, "X"),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        let record = record.borrow();
+        assert!(record.instructions.iter().flatten().all(|line| !line.starts_with("📍 行")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_idle_session_prompts_then_pauses_until_activity() -> Result<()> {
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            // 操作すると再開し、再開後の変化から判定する
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+        ]);
+        let record = backend.record();
+        let config = TutorialConfig {
+            idle_threshold_seconds: 0,
+            idle_grace_seconds: 0,
+            ..TutorialConfig::default()
+        };
+        let mut session = ContinuousVimSession::with_backend(Box::new(backend), config);
+
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+
+        let record = record.borrow();
+        assert_eq!(record.goal_marks(), vec!["1", "2", "completed"]);
+        let updates = &record.instructions;
+        assert_eq!(
+            updates[1][0],
+            "💤 アイドル状態です — 続けますか？（何か操作すると続けます）"
+        );
+        assert_eq!(updates[2][0], "=== ⏸️ 一時停止中 ===");
+        assert!(updates[2].contains(&"▶️ 何か操作すると再開します".to_string()));
+        assert!(updates[3].contains(&"  ▶️ 1. Insert モードに入る".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_resume_from_paused_exercise() -> Result<()> {
+        let backend = MockBackend::from_states(vec![
+            sequence_state(VimMode::Insert, ""),
+            sequence_state(VimMode::Insert, "X"),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+        session.resume_from(PausedExercise {
+            chapter_number: 1,
+            exercise_index: 0,
+            exercise_title: "Order".to_string(),
+            earlier_exercises: Vec::new(),
+            current_goal_index: 1,
+            completed_goals: vec![true, false],
+            elapsed_secs: 10.0,
+            hints_used: 2,
+            buffer: vec![String::new()],
+            cursor: [0, 0],
+            paused_at: chrono::Utc::now(),
+            content_hash: None,
+        });
+
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert!(session.active_duration() >= Duration::from_secs(10));
+        let initial = record.borrow().instructions[0].clone();
+        assert!(initial.contains(&"  ✅ 1. Insert モードに入る".to_string()));
+        assert!(initial.contains(&"  2. X と入力する".to_string()));
+
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        assert_eq!(session.hints_used(), 2);
+        // 再開した練習は、引き継いだ目標とヒントの数から書き出す
+        assert_eq!(record.borrow().goal_marks(), vec!["2", "completed"]);
+        assert_eq!(record.borrow().progress[0].hint.hints_used, 2);
+        assert_eq!(record.borrow().progress.last().unwrap().finished.as_deref(), Some("完了"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_manifest_tracks_goal_and_reattach_skips_launch() -> Result<()> {
+        let dir = tempdir()?;
+        let manifest_path = dir.path().join("running_session.json");
+        let target = || PauseTarget {
+            chapter_number: 1,
+            exercise_index: 0,
+            earlier_exercises: Vec::new(),
+        };
+        // 状態を使い切ると監視ループが止まる（プロセスが落ちた場合と同じく印が残る）
+        let backend = MockBackend::from_states(vec![
+            sequence_state(VimMode::Normal, ""),
+            sequence_state(VimMode::Insert, ""),
+        ]);
+        let mut session = mock_session(backend);
+        session.manifest_path = Some(manifest_path.clone());
+        session.set_pause_target(Some(target()));
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert!(session.monitor_progress().is_err());
+        let manifest = recovery::load(&manifest_path).unwrap();
+        assert_eq!(manifest.progress.current_goal_index, 1);
+        assert_eq!(manifest.progress.completed_goals, vec![true, false]);
+        assert_eq!(manifest.running.editor_pane, "%1");
+
+        // 再接続ではエディタを起動せず、記録した目標から続ける
+        let backend = MockBackend::from_states(vec![
+            sequence_state(VimMode::Insert, ""),
+            sequence_state(VimMode::Insert, "X"),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+        session.manifest_path = Some(manifest_path.clone());
+        session.set_pause_target(Some(target()));
+        session.resume_from(manifest.progress);
+        session.reattach_to(manifest.running);
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!((record.borrow().started, record.borrow().reattached), (0, 1));
+        assert!(record.borrow().instructions[0].contains(&"  2. X と入力する".to_string()));
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        session.stop_exercise()?;
+        assert!(!manifest_path.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_accessible_session_shows_no_pictographs() -> Result<()> {
+        let backend = MockBackend::new(vec![
+            MockStep::default(),
+            MockStep::state(sequence_state(VimMode::Normal, "")).with_command("HINT_REQUEST"),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+        ]);
+        let record = backend.record();
+        let config = TutorialConfig {
+            accessible: true,
+            bell: false,
+            ..TutorialConfig::default()
+        };
+        let mut session = ContinuousVimSession::with_backend(Box::new(backend), config);
+        let mut exercise = ordered_exercise();
+        exercise.outro = Some("🎉 よくできました".to_string());
+
+        session.start_exercise(exercise.clone(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+
+        let record = record.borrow();
+        let shown: Vec<String> = record
+            .instructions
+            .iter()
+            .flatten()
+            .chain(&record.announcements)
+            .cloned()
+            .chain(session.theme.lines(&ContinuousVimSession::briefing_lines(&exercise)))
+            .chain(session.theme.lines(&ContinuousVimSession::outro_lines(&exercise)))
+            .collect();
+        assert!(shown.iter().all(|line| !line.chars().any(theme::is_pictograph)), "{:?}", shown);
+        assert!(shown.contains(&"GOAL: Order".to_string()));
+        // bell を切っていても、目標の達成は1行で知らせる
+        assert_eq!(record.announcements[0], "DONE: 目標達成！（1/2）次: X と入力する");
+        Ok(())
+    }
+
+    #[test]
+    fn test_bell_can_be_disabled() -> Result<()> {
+        let backend = MockBackend::from_states(vec![
+            sequence_state(VimMode::Insert, ""),
+            sequence_state(VimMode::Insert, "X"),
+        ]);
+        let record = backend.record();
+        let config = TutorialConfig {
+            bell: false,
+            ..TutorialConfig::default()
+        };
+        let mut session = ContinuousVimSession::with_backend(Box::new(backend), config);
+
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        assert!(record.borrow().announcements.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_any_order_exercise_through_mock_backend() -> Result<()> {
+        let exercise = ContinuousExercise {
+            title: "AnyOrder".to_string(),
+            sample_code: vec!["hello world".to_string()],
+            goals: vec![
+                ExerciseGoal {
+                    goal_type: "position".to_string(),
+                    target: json!([0, 3]),
+                    description: "4文字目に移動".to_string(),
+                    ..Default::default()
+                },
+                ExerciseGoal {
+                    goal_type: "mode".to_string(),
+                    target: json!("insert"),
+                    description: "Insert モードに入る".to_string(),
+                    ..Default::default()
+                },
+            ],
+            flow_type: FlowType::AnyOrder,
+            teardown: vec!["echo 'bye'".to_string()],
+            editor_options: Vec::new(),
+            coaching: None,
+            intro: None,
+            outro: None,
+            allowed_modes: Vec::new(),
+            reference: Vec::new(),
+            operator_hints: false,
+            files: Vec::new(),
+            ..ordered_exercise()
+        };
+        // 2つ目の目標を先に達成する
+        let backend = MockBackend::from_states(vec![
+            cursor_state(VimMode::Insert, "hello world", 0),
+            cursor_state(VimMode::Normal, "hello world", 3),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+
+        session.start_exercise(exercise, "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        session.stop_exercise()?;
+
+        let record = record.borrow();
+        assert_eq!(record.goal_marks(), vec!["1", "completed"]);
+        let updates = &record.instructions;
+        assert_eq!(updates.len(), 3);
+        // 残っている最初の目標を表示し続ける
+        assert!(updates[1].contains(&"  1. 4文字目に移動".to_string()));
+        assert_eq!(updates[1][3], "=== ☑️ 目標一覧（どの順番でも可） ===");
+        assert!(updates[1].contains(&"  ✅ 2. Insert モードに入る".to_string()));
+        assert_eq!(updates[2][0], "=== 🎉 章完了！ ===");
+        assert_eq!(record.sent_keys[0], vec!["Escape"]);
+        assert!(record.sent_keys[1][0].starts_with(":call TutorialRunCommands(g:tutorial_teardown"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_events_are_recorded() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let backend = MockBackend::from_states(vec![
+            sequence_state(VimMode::Insert, ""),
+            sequence_state(VimMode::Insert, ""),
+            sequence_state(VimMode::Insert, "X"),
+        ]);
+        let mut session = mock_session(backend);
+        session.event_log = EventLog::in_dir(tmp_dir.path());
+
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        let path = session.event_log.path().unwrap().to_path_buf();
+
+        let (records, skipped) = crate::event_log::read_events(&path)?;
+        assert_eq!(skipped, 0);
+        let kinds: Vec<String> = records
+            .iter()
+            .map(|record| {
+                let json = serde_json::to_value(record).unwrap();
+                json["event"].as_str().unwrap().to_string()
+            })
+            .collect();
+        // 同じ状態が続いても状態の記録は増えない
+        assert_eq!(
+            kinds,
+            vec![
+                "exercise_started",
+                "goal_activated",
+                "pane_updated",
+                "state_sample",
+                "goal_completed",
+                "goal_activated",
+                "pane_updated",
+                "state_sample",
+                "goal_completed",
+                "pane_updated",
+                "session_ended",
+            ]
+        );
+        assert_eq!(
+            records.last().unwrap().event,
+            SessionEvent::SessionEnded {
+                result: "Completed".to_string()
             }
-            _ => panic!("Expected Position goal type"),
-        }
+        );
+        Ok(())
+    }
 
-        // Mode goal
-        let mode_goal_def = ExerciseGoal {
-            goal_type: "mode".to_string(),
-            target: json!("insert"),
-            description: "Test mode".to_string(),
-            hint: None,
+    #[test]
+    fn test_goal_commands_run_at_transitions_and_failures_do_not_abort() -> Result<()> {
+        let mut exercise = ordered_exercise();
+        exercise.goals[0].on_activate = vec!["set hlsearch".to_string()];
+        exercise.goals[0].on_complete = vec!["call append(0, 'Y')".to_string()];
+        exercise.goals[1].on_activate = vec!["let g:x = 1".to_string()];
+        let backend = MockBackend::new(vec![
+            // 最初の目標の on_activate が失敗しても続ける
+            MockStep::state(sequence_state(VimMode::Normal, ""))
+                .with_command("GOAL_COMMAND_FAILED\tset hlsearch\tE518: Unknown option"),
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            // コマンドを実行し終えるまでの入力は判定しない
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+
+        session.start_exercise(exercise, "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        let record = record.borrow();
+        assert_eq!(
+            record.commands_run,
+            vec![
+                vec!["set hlsearch".to_string()],
+                vec!["call append(0, 'Y')".to_string(), "let g:x = 1".to_string()],
+            ]
+        );
+        assert!(record.instructions.iter().flatten().any(|line| line
+            == "     ⚠️ 目標のコマンド「set hlsearch」の実行に失敗しました: E518: Unknown option"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_demo_plays_solution_and_restore_leaves_goal_to_learner() -> Result<()> {
+        let mut exercise = ordered_exercise();
+        exercise.goals[0].solution_keys = Some("i".to_string());
+        let offer = "     🎬 解答の操作で目標を達成しました。:TutorialDemoRestore で戻して自分で試す / :TutorialDemoKeep でこのまま進む";
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, "")).with_command("DEMO_REQUEST"),
+            // 再生前の状態を取っておき終えてからキーを送る
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            // 戻した場合は目標を達成にしない
+            MockStep::state(sequence_state(VimMode::Insert, "")).with_command("DEMO_RESTORE"),
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            // もう一度再生し、今度はそのまま進める
+            MockStep::state(sequence_state(VimMode::Normal, "")).with_command("DEMO_REQUEST"),
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Normal, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "")).with_command("DEMO_KEEP"),
+            MockStep::state(sequence_state(VimMode::Insert, "")),
+            MockStep::state(sequence_state(VimMode::Insert, "X")),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+
+        session.start_exercise(exercise, "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        assert_eq!(session.hints_used(), 2);
+        let record = record.borrow();
+        assert_eq!(record.editor_keys, vec!["i", "<C-\\><C-N>", "i"]);
+        assert_eq!(
+            record.commands_run,
+            vec![
+                vec!["call TutorialDemoSnapshot()".to_string()],
+                vec!["call TutorialDemoRestore()".to_string()],
+                vec!["call TutorialDemoSnapshot()".to_string()],
+            ]
+        );
+        // 戻した後は、もう一度 Insert モードに入るまで目標1のまま
+        let restore_at = record.instructions.iter().position(|lines| lines.iter().any(|line| line == offer)).unwrap();
+        assert!(record.instructions[restore_at + 1].iter().any(|line| line == "  1. Insert モードに入る"));
+        assert_eq!(record.goal_marks(), vec!["1", "2", "completed"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_allowed_modes_and_required_operator_restart_goal() -> Result<()> {
+        let mut exercise = ordered_exercise();
+        exercise.sample_code = vec!["foo bar".to_string()];
+        exercise.goals = vec![ExerciseGoal {
+            goal_type: "text".to_string(),
+            target: json!({"line": 0, "expected": "bar"}),
+            description: "foo を消す".to_string(),
+            required_operator: Some("d".to_string()),
+            ..Default::default()
+        }];
+        exercise.allowed_modes = vec!["normal".to_string()];
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, "foo bar")),
+            // Insert モードで書き換えても達成にしない
+            MockStep::state(sequence_state(VimMode::Insert, "foo bar")),
+            MockStep::state(sequence_state(VimMode::Insert, "bar")),
+            MockStep::state(sequence_state(VimMode::Normal, "bar")),
+            MockStep::state(sequence_state(VimMode::Normal, "foo bar")),
+            // d を使わずに届いた
+            MockStep::state(sequence_state(VimMode::Normal, "bar")),
+            MockStep::state(sequence_state(VimMode::Normal, "foo bar")),
+            MockStep::state(sequence_state(VimMode::Normal, "bar")).with_key_log(&["n\td", "no\tw"]),
+        ]);
+        let record = backend.record();
+        let mut session = mock_session(backend);
+
+        session.start_exercise(exercise, "/tmp/sample.txt")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+        let record = record.borrow();
+        let shown = |expected: &str| {
+            record.instructions.iter().any(|lines| lines.iter().any(|line| line == expected))
         };
-        let goal = session.convert_goal_definition(&mode_goal_def)?;
-        match goal.goal_type {
-            GoalType::Mode(VimMode::Insert) => {}
-            _ => panic!("Expected Insert mode goal type"),
+        assert!(shown("     📏 使えるモード: ノーマル / オペレーター d を使う"));
+        assert!(shown("     🚫 目標1は挿入モードを使わずに達成してください（やり直しになります）"));
+        assert!(shown("     🚫 目標1は d を使って達成してください（u で戻してやり直せます）"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mock_backend_reports_setup_failure() -> Result<()> {
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, ""))
+                .with_command("SETUP_FAILED\tnormal! G\tE492"),
+        ]);
+        let mut session = mock_session(backend);
+
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        assert_eq!(
+            session.monitor_progress()?,
+            ExerciseResult::Failed("setup コマンド「normal! G」の実行に失敗しました: E492".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_exercise_saves_pane_captures() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let long = format!("{}最後の画面", "あ".repeat(MAX_CAPTURE_BYTES));
+        let backend = MockBackend::new(vec![
+            MockStep::state(sequence_state(VimMode::Normal, "")).with_command("SETUP_FAILED\tnormal! G\tE492"),
+        ])
+        .with_captures(&[("editor", &long), ("instructions", "🎯 目標1")]);
+        let mut session = mock_session(backend);
+        session.capture_dir = Some(dir.path().to_path_buf());
+        session.event_log = EventLog::in_dir(dir.path().join("events"));
+
+        session.start_exercise(ordered_exercise(), "/tmp/sample.txt")?;
+        let ExerciseResult::Failed(message) = session.monitor_progress()? else {
+            panic!("失敗になるはず");
+        };
+        let mut files: Vec<PathBuf> = fs::read_dir(dir.path())?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .filter(|path| path.as_ref().map(|path| path.is_file()).unwrap_or(true))
+            .collect::<std::io::Result<_>>()?;
+        files.sort();
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().all(|path| message.contains(&path.display().to_string())));
+        assert!(message.starts_with("setup コマンド「normal! G」の実行に失敗しました: E492\n📄 画面を保存しました: "));
+        let editor = fs::read_to_string(&files[0])?;
+        assert!(editor.starts_with("（先頭の") && editor.ends_with("最後の画面"));
+        assert!(editor.len() <= MAX_CAPTURE_BYTES + 64);
+        assert_eq!(fs::read_to_string(&files[1])?, "🎯 目標1");
+
+        // 成功した練習は既定（capture_on: failed）では保存しない
+        use crate::config::CaptureOn;
+        assert!(!CaptureOn::Failed.captures(true, false));
+        assert!(CaptureOn::Incomplete.captures(false, false));
+        assert!(CaptureOn::Always.captures(true, false));
+
+        session.event_log.flush();
+        let log = session.event_log.path().unwrap().to_path_buf();
+        let (events, _) = crate::event_log::read_events(&log)?;
+        assert!(events.iter().any(|record| matches!(&record.event, SessionEvent::PanesCaptured { files } if files.len() == 2)));
+        Ok(())
+    }
+
+    fn greet_exercise() -> ContinuousExercise {
+        ContinuousExercise {
+            title: "greet の中へ".to_string(),
+            sample_code: ["// 挨拶", "function greet(name) {", "  return name;", "}"].map(str::to_string).to_vec(),
+            goals: vec![ExerciseGoal {
+                goal_type: "syntax_node".to_string(),
+                target: json!({"node": "function_declaration", "language": "javascript"}),
+                description: "greet 関数の中へ移動".to_string(),
+                ..Default::default()
+            }],
+            ..ordered_exercise()
         }
+    }
+
+    #[test]
+    fn test_syntax_node_goal_follows_cursor_into_node() -> Result<()> {
+        let at_line = |line: usize| VimState {
+            cursor_line: line,
+            ..sequence_state(VimMode::Normal, "")
+        };
+        let tree = [(0, 3, "program"), (1, 3, "function_declaration"), (2, 2, "return_statement")];
+        let backend = MockBackend::new(vec![MockStep::state(at_line(0)), MockStep::state(at_line(2))])
+            .with_syntax_tree(Some(&tree));
+        let mut session = mock_session(backend);
 
+        session.start_exercise(greet_exercise(), "/tmp/sample.js")?;
+        assert_eq!(session.monitor_progress()?, ExerciseResult::Completed);
+
+        let goal = greet_exercise().goals[0].to_goal()?;
+        let state = at_line(0);
+        session.goal_detector.record_syntax_nodes(Some("javascript"), vec!["comment".to_string()], &state);
+        assert_eq!(
+            session.goal_detector.check_goal(&goal, &state).gap().map(|gap| gap.to_string()),
+            Some("カーソルがfunction_declarationの中にありません（今はcommentの中）".to_string())
+        );
         Ok(())
     }
 
     #[test]
-    fn test_operator_pending_goal_conversion() -> Result<()> {
-        let tmp_dir = tempdir()?;
-        let socket_path = tmp_dir
-            .path()
-            .join("test.sock")
-            .to_string_lossy()
-            .to_string();
-        let session = ContinuousVimSession::new(socket_path);
+    fn test_syntax_node_goal_without_parser_fails_with_reason() -> Result<()> {
+        let backend = MockBackend::new(vec![MockStep::state(sequence_state(VimMode::Normal, ""))]).with_syntax_tree(None);
+        let mut session = mock_session(backend);
 
-        let op_goal_def = ExerciseGoal {
-            goal_type: "mode".to_string(),
-            target: json!("operator_d"),
-            description: "Press 'd' for delete".to_string(),
-            hint: None,
+        session.start_exercise(greet_exercise(), "/tmp/sample.js")?;
+        let ExerciseResult::Failed(message) = session.monitor_progress()? else {
+            panic!("パーサーが無ければ失敗になるはず");
         };
+        assert!(message.starts_with("目標1「greet 関数の中へ移動」は判定できません: treesitter の javascript のパーサーがありません"));
 
-        let goal = session.convert_goal_definition(&op_goal_def)?;
-        match goal.goal_type {
-            GoalType::Mode(VimMode::OperatorPending(op)) => {
-                assert_eq!(op, "d");
+        // 言語を決められない目標は作者に知らせる
+        let mut exercise = greet_exercise();
+        exercise.goals[0].target = json!({"node": "function_declaration"});
+        assert_eq!(validator::exercise_warnings(&exercise).len(), 1);
+        exercise.goals[0].file = Some("greet.js".to_string());
+        assert!(validator::exercise_warnings(&exercise).is_empty());
+        assert!(ExerciseGoal {
+            goal_type: "syntax_node".to_string(),
+            target: json!({"node": "x'); os.exit()"}),
+            ..Default::default()
+        }
+        .to_goal()
+        .is_err());
+        Ok(())
+    }
+
+    // 実際の tmux と Neovim を使う結合テスト（VIM_TUTORIAL_INTEGRATION=1 のときだけ実行）。
+    // /tmp の固定名のファイルと tmux セッションを使うため、チュートリアルの実行中には動かさない
+    fn integration_enabled() -> bool {
+        if std::env::var("VIM_TUTORIAL_INTEGRATION").as_deref() != Ok("1") {
+            eprintln!("VIM_TUTORIAL_INTEGRATION=1 が無いため結合テストを省略します");
+            return false;
+        }
+        for (program, version_flag) in [("tmux", "-V"), ("nvim", "--version")] {
+            if std::process::Command::new(program).arg(version_flag).output().is_err() {
+                eprintln!("{} が見つからないため結合テストを省略します", program);
+                return false;
             }
-            _ => panic!("Expected OperatorPending goal type"),
         }
+        true
+    }
+
+    fn tmux_output(args: &[&str]) -> Option<String> {
+        std::process::Command::new("tmux")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    // 練習用のペイン（pane_index 1）のID
+    fn vim_pane() -> Option<String> {
+        tmux_output(&["list-panes", "-t", SESSION_NAME, "-F", "#{pane_index}:#{pane_id}"])?
+            .lines()
+            .find_map(|line| line.strip_prefix("1:").map(str::to_string))
+    }
+
+    // 失敗の調査用に、両方のペインの表示とデバッグログの末尾をまとめる
+    fn integration_dump() -> String {
+        let mut dump = String::new();
+        for pane in ["0", "1"] {
+            let target = format!("{}:.{}", SESSION_NAME, pane);
+            let content = tmux_output(&["capture-pane", "-p", "-t", &target])
+                .unwrap_or_else(|| "(取得できません)".to_string());
+            dump.push_str(&format!("--- ペイン {} ---\n{}\n", pane, content.trim_end()));
+        }
+        let log = fs::read_to_string(std::env::temp_dir().join("vim_continuous_debug.log")).unwrap_or_default();
+        let tail: Vec<&str> = log.lines().rev().take(40).collect();
+        dump.push_str("--- デバッグログ（末尾） ---\n");
+        for line in tail.iter().rev() {
+            dump.push_str(line);
+            dump.push('\n');
+        }
+        dump
+    }
+
+    // 学習者の代わりに、状態ファイルができてから練習用のペインにキーを送る
+    fn spawn_scripted_learner(keys: Vec<&'static str>) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || {
+            let ready = Instant::now();
+            while !std::path::Path::new(&crate::vim_backend::temp_file(crate::vim_backend::STATUS_FILE)).exists() {
+                if ready.elapsed() > Duration::from_secs(10) {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            let Some(pane) = vim_pane() else {
+                return;
+            };
+            for key in keys {
+                std::thread::sleep(Duration::from_millis(500));
+                let _ = tmux_output(&["send-keys", "-t", &pane, key]);
+            }
+        })
+    }
+
+    #[test]
+    fn test_tmux_session_completes_exercise_and_cleans_up() -> Result<()> {
+        if !integration_enabled() {
+            return Ok(());
+        }
+        let exercise = ContinuousExercise {
+            title: "Integration".to_string(),
+            sample_code: vec!["hello world".to_string()],
+            goals: vec![
+                ExerciseGoal {
+                    goal_type: "position".to_string(),
+                    target: json!([0, 4]),
+                    description: "単語の末尾に移動".to_string(),
+                    ..Default::default()
+                },
+                ExerciseGoal {
+                    goal_type: "mode".to_string(),
+                    target: json!("insert"),
+                    description: "Insert モードに入る".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..ordered_exercise()
+        };
+        let sample_file = tempfile::NamedTempFile::new()?;
+        fs::write(&sample_file, exercise.sample_code.join("\n"))?;
+        let socket_path = crate::vim_rpc::default_address(std::process::id());
+
+        let mut session = ContinuousVimSession::new(socket_path.clone());
+        // 学習者の操作が届かない場合でも止まらないように制限時間を付ける
+        session.set_time_limit(Some(30));
+        session.start_exercise(exercise, sample_file.path().to_str().unwrap())?;
+        let learner = spawn_scripted_learner(vec!["e", "i"]);
+        let result = session.monitor_progress();
+        let dump = integration_dump();
+        session.stop_exercise()?;
+        let _ = learner.join();
+
+        assert!(
+            matches!(result, Ok(ExerciseResult::Completed)),
+            "練習が完了しませんでした: {:?}\n{}",
+            result,
+            dump
+        );
+
+        let leftovers: Vec<String> = STATE_FILES
+            .iter()
+            .map(|file| crate::vim_backend::temp_file(file))
+            .flat_map(|file| [format!("{}.processing", file), file])
+            .chain([socket_path])
+            .filter(|path| std::path::Path::new(path).exists())
+            .collect();
+        let session_left = tmux_output(&["has-session", "-t", &format!("={}", SESSION_NAME)]).is_some();
+        assert!(
+            leftovers.is_empty() && !session_left,
+            "終了後に残っているもの: {:?}（tmuxセッション: {}）\n{}",
+            leftovers,
+            session_left,
+            integration_dump()
+        );
+        Ok(())
+    }
+
+    // nvim に javascript の treesitter のパーサーがあるか（同梱されていない版が多いため、無ければ省略する）
+    fn javascript_parser_available() -> bool {
+        std::process::Command::new("nvim")
+            .args(["--headless", "--clean", "-c"])
+            .arg("lua local ok, parser = pcall(vim.treesitter.get_string_parser, 'x', 'javascript'); io.write(ok and parser and 'ok' or 'missing')")
+            .args(["-c", "qa!"])
+            .output()
+            .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("ok"))
+    }
+
+    #[test]
+    fn test_tmux_session_completes_syntax_node_goal() -> Result<()> {
+        if !integration_enabled() {
+            return Ok(());
+        }
+        if !javascript_parser_available() {
+            eprintln!("nvim に javascript のパーサーが無いため結合テストを省略します");
+            return Ok(());
+        }
+        let exercise = greet_exercise();
+        let sample_file = tempfile::NamedTempFile::new()?;
+        fs::write(&sample_file, exercise.sample_code.join("\n"))?;
+        let mut session = ContinuousVimSession::new(crate::vim_rpc::default_address(std::process::id()));
+        session.set_time_limit(Some(30));
+        session.start_exercise(exercise, sample_file.path().to_str().unwrap())?;
+        let learner = spawn_scripted_learner(vec!["j", "j"]);
+        let result = session.monitor_progress();
+        let dump = integration_dump();
+        session.stop_exercise()?;
+        let _ = learner.join();
+        assert!(
+            matches!(result, Ok(ExerciseResult::Completed)),
+            "練習が完了しませんでした: {:?}\n{}",
+            result,
+            dump
+        );
+        Ok(())
+    }
+
+    // 学習者のエディタで練習してから片付けると、自動コマンド・コマンド・マッピング・関数・変数・
+    // オプション・バッファ・レジスタ・タブが練習の前と同じに戻る（VIM_TUTORIAL_INTEGRATION=1 のときだけ。
+    // nvim が無ければ vim で確かめる）
+    #[test]
+    fn test_shared_editor_is_restored_after_exercise() -> Result<()> {
+        if std::env::var("VIM_TUTORIAL_INTEGRATION").as_deref() != Ok("1") {
+            eprintln!("VIM_TUTORIAL_INTEGRATION=1 が無いため結合テストを省略します");
+            return Ok(());
+        }
+        let available = |editor: Editor| {
+            std::process::Command::new(editor.program()).arg("--version").output().is_ok()
+        };
+        let Some(editor) = [Editor::Neovim, Editor::Vim].into_iter().find(|editor| available(*editor)) else {
+            eprintln!("nvim も vim も見つからないため結合テストを省略します");
+            return Ok(());
+        };
+
+        let mut session = mock_session(MockBackend::new(Vec::new()).with_editor(editor).sharing_editor());
+        let exercise = ContinuousExercise {
+            setup: vec!["let @a = 'practice'".to_string(), "set hlsearch".to_string()],
+            teardown: vec!["let g:teardown_ran = 1".to_string()],
+            editor_options: vec!["relativenumber".to_string()],
+            ..ordered_exercise()
+        };
+        session.current_exercise = Some(exercise.clone());
+        let script = session.create_vim_script(&exercise)?;
+        let dir = tempfile::tempdir()?;
+        let sample = dir.path().join("sample.txt");
+        fs::write(&sample, exercise.sample_code.join("\n"))?;
+        let quote = |path: &std::path::Path| path.to_string_lossy().replace('\'', "''");
+        let (before, after, attached) = (dir.path().join("before"), dir.path().join("after"), dir.path().join("attached"));
+        let harness = dir.path().join("harness.vim");
+        fs::write(
+            &harness,
+            format!(
+                r#"function! s:Snapshot(path)
+  let parts = [execute('autocmd'), execute('command'), execute('map'), execute('function'), string(g:), execute('set'), execute('ls!'), execute('registers'), tabpagenr('$')]
+  call writefile(split(join(map(parts, 'type(v:val) == v:t_string ? v:val : string(v:val)'), "\n"), "\n"), a:path)
+endfunction
+let @b = 'mine'
+" 端末の無い Vim ではタブを開いて閉じるだけで画面の大きさのオプションが変わるため、一度開いて閉じておく
+tabnew | tabclose!
+call s:Snapshot('{before}')
+execute 'noswapfile tabnew' fnameescape('{sample}')
+let t:vim_tutorial = 1
+execute 'source' fnameescape('{script}')
+call writefile([exists('*TutorialDetach') && tabpagenr('$') == 2 && @a ==# 'practice' ? 'yes' : 'no'], '{attached}')
+call execute(g:tutorial_detach)
+unlet g:teardown_ran
+call s:Snapshot('{after}')
+qa!
+"#,
+                before = quote(&before),
+                after = quote(&after),
+                attached = quote(&attached),
+                sample = quote(&sample),
+                script = quote(script.path()),
+            ),
+        )?;
+        let source = format!("source {}", harness.display());
+        let args: &[&str] = match editor {
+            Editor::Neovim => &["--headless", "-u", "NONE", "-i", "NONE", "-n", "-c"],
+            Editor::Vim => &["-N", "-u", "NONE", "-i", "NONE", "-n", "-es", "-c"],
+        };
+        let output = std::process::Command::new(editor.program()).args(args).arg(&source).output()?;
+        for file in STATE_FILES {
+            let _ = fs::remove_file(crate::vim_backend::temp_file(file));
+        }
+        let read = |path: &std::path::Path| fs::read_to_string(path).unwrap_or_default();
+        assert_eq!(read(&attached).trim(), "yes", "練習用のタブで監視が始まりませんでした: {:?}", output);
 
+        let lines = |path: &std::path::Path| read(path).lines().map(str::to_string).collect::<Vec<_>>();
+        let changed = crate::diff::unified_diff(&lines(&before), &lines(&after), 0);
+        assert!(changed.is_empty(), "練習の前と後で違います:\n{}", changed.join("\n"));
         Ok(())
     }
 }
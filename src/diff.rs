@@ -0,0 +1,326 @@
+use unicode_width::UnicodeWidthStr;
+
+use crate::normalize::{half_width, straight_quote};
+
+// 空白の違い（タブとスペース、末尾の空白、全角の空白）が見えるように置き換える
+// （全角の空白は同じ幅の □ にして、^ の位置がずれないようにする）
+pub fn visible_whitespace(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            ' ' => '·',
+            '\t' => '→',
+            '\u{3000}' => '□',
+            other => other,
+        })
+        .collect()
+}
+
+// 期待する行と現在の行を文字単位で比べ、最初に異なる位置を示す表示行を返す（一致していれば空）
+pub fn char_diff(expected: &str, actual: Option<&str>) -> Vec<String> {
+    let Some(actual) = actual else {
+        return vec![
+            format!("期待: {}", visible_whitespace(expected)),
+            "現在: (行がありません)".to_string(),
+        ];
+    };
+    if expected == actual {
+        return Vec::new();
+    }
+
+    let expected_chars: Vec<char> = expected.chars().collect();
+    let actual_chars: Vec<char> = actual.chars().collect();
+    let prefix = expected_chars
+        .iter()
+        .zip(actual_chars.iter())
+        .take_while(|(e, a)| e == a)
+        .count();
+    let suffix = expected_chars[prefix..]
+        .iter()
+        .rev()
+        .zip(actual_chars[prefix..].iter().rev())
+        .take_while(|(e, a)| e == a)
+        .count();
+
+    let segment = |chars: &[char]| -> String { chars.iter().collect() };
+    let expected_part = segment(&expected_chars[prefix..expected_chars.len() - suffix]);
+    let actual_part = segment(&actual_chars[prefix..actual_chars.len() - suffix]);
+    let common = visible_whitespace(&segment(&actual_chars[..prefix]));
+    let indent = UnicodeWidthStr::width(common.as_str());
+
+    vec![
+        format!("期待: {}", visible_whitespace(expected)),
+        format!("現在: {}", visible_whitespace(actual)),
+        format!(
+            "      {}^ {}文字目: 「{}」→「{}」",
+            " ".repeat(indent),
+            prefix + 1,
+            visible_whitespace(&actual_part),
+            visible_whitespace(&expected_part)
+        ),
+    ]
+}
+
+// 期待する行と実際の行が違う、よくある原因（行末の空白・全角と半角・飾りの引用符）
+fn likely_causes(expected: &str, actual: &str) -> Vec<&'static str> {
+    let mut causes = Vec::new();
+    if expected.trim_end() == actual.trim_end() {
+        causes.push("行末の空白が違います（$ で行末に移ると、余分な空白や足りない空白を確かめられます）");
+    }
+    let first_difference = expected
+        .chars()
+        .zip(actual.chars())
+        .find(|(e, a)| e != a);
+    if let Some((e, a)) = first_difference {
+        if half_width(e) == half_width(a) {
+            causes.push("全角と半角の文字が違います（「（」と「(」など。IME をオフにして入力してください）");
+        }
+        if straight_quote(e) == straight_quote(a) {
+            causes.push("引用符の種類が違います（“ ” や ‘ ’ ではなく \" や ' を使います）");
+        }
+    }
+    causes
+}
+
+// テキストの目標だけが残ったまま止まっているときに出す、期待する行と実際の行を縦に並べた比較。
+// 最初に違う行について、違いの始まる位置に ^ を付け（全角の文字は2桁として揃える）、よくある原因を添える。
+// first_line は expected の先頭行（0始まり）。全ての行が一致していれば空
+pub fn stalled_comparison(expected: &[String], actual: &[String], first_line: usize) -> Vec<String> {
+    let Some((offset, expected_line)) = expected
+        .iter()
+        .enumerate()
+        .find(|(i, line)| actual.get(*i) != Some(*line))
+    else {
+        return Vec::new();
+    };
+    let mut lines = vec![format!("{}行目:", first_line + offset + 1)];
+    let Some(actual_line) = actual.get(offset) else {
+        lines.push(format!("期待: {}", visible_whitespace(expected_line)));
+        lines.push("現在: (行がありません)".to_string());
+        return lines;
+    };
+
+    let prefix: String = expected_line
+        .chars()
+        .zip(actual_line.chars())
+        .take_while(|(e, a)| e == a)
+        .map(|(e, _)| e)
+        .collect();
+    let indent = UnicodeWidthStr::width(visible_whitespace(&prefix).as_str());
+    lines.push(format!("期待: {}", visible_whitespace(expected_line)));
+    lines.push(format!("現在: {}", visible_whitespace(actual_line)));
+    lines.push(format!("      {}^", " ".repeat(indent)));
+    lines.extend(likely_causes(expected_line, actual_line).into_iter().map(|cause| format!("💡 {}", cause)));
+    lines
+}
+
+// 統一形式の差分で、変更行の前後に表示する行数
+const CONTEXT_LINES: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LineOp {
+    Same,
+    Expected, // 期待にだけある行（-）
+    Actual,   // 現在にだけある行（+）
+}
+
+// 最長共通部分列で行の対応を取る（練習のバッファは小さいので O(n*m) で十分）
+fn line_ops<'a>(expected: &'a [String], actual: &'a [String]) -> Vec<(LineOp, &'a str)> {
+    let (n, m) = (expected.len(), actual.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::new();
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            ops.push((LineOp::Same, expected[i].as_str()));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push((LineOp::Expected, expected[i].as_str()));
+            i += 1;
+        } else {
+            ops.push((LineOp::Actual, actual[j].as_str()));
+            j += 1;
+        }
+    }
+    ops
+}
+
+// 期待する行の並びと現在の行の並びの統一形式の差分（一致していれば空）
+// first_line は比べる範囲の先頭行（0始まり）で、@@ 行の行番号に使う
+pub fn unified_diff(expected: &[String], actual: &[String], first_line: usize) -> Vec<String> {
+    let ops = line_ops(expected, actual);
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != LineOp::Same)
+        .map(|(index, _)| index)
+        .collect();
+    if changes.is_empty() {
+        return Vec::new();
+    }
+
+    // 変更行の前後を含む範囲を、重なるものはまとめてハンクにする
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &index in &changes {
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + CONTEXT_LINES + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut lines = vec!["--- 期待".to_string(), "+++ 現在".to_string()];
+    for (start, end) in hunks {
+        let before = &ops[..start];
+        let old_start = before.iter().filter(|(op, _)| *op != LineOp::Actual).count();
+        let new_start = before.iter().filter(|(op, _)| *op != LineOp::Expected).count();
+        let hunk = &ops[start..end];
+        let old_len = hunk.iter().filter(|(op, _)| *op != LineOp::Actual).count();
+        let new_len = hunk.iter().filter(|(op, _)| *op != LineOp::Expected).count();
+        lines.push(format!(
+            "@@ -{},{} +{},{} @@",
+            first_line + old_start + 1,
+            old_len,
+            first_line + new_start + 1,
+            new_len
+        ));
+        for (op, text) in hunk {
+            let marker = match op {
+                LineOp::Same => ' ',
+                LineOp::Expected => '-',
+                LineOp::Actual => '+',
+            };
+            lines.push(format!("{}{}", marker, visible_whitespace(text)));
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_char_diff_matching_line_is_empty() {
+        assert!(char_diff("let x = 1;", Some("let x = 1;")).is_empty());
+    }
+
+    #[test]
+    fn test_char_diff_marks_first_difference() {
+        let lines = char_diff("let x = 1;", Some("let  x = 1;"));
+        assert_eq!(lines[0], "期待: let·x·=·1;");
+        assert_eq!(lines[1], "現在: let··x·=·1;");
+        // 「現在:」の後ろの4文字分だけ字下げして ^ を置く
+        assert_eq!(lines[2], "          ^ 5文字目: 「·」→「」");
+    }
+
+    #[test]
+    fn test_char_diff_tab_and_missing_line() {
+        let lines = char_diff("\tfoo()", Some("    foo()"));
+        assert_eq!(lines[2], "      ^ 1文字目: 「····」→「→」");
+
+        let lines = char_diff("foo", None);
+        assert_eq!(lines[1], "現在: (行がありません)");
+    }
+
+    #[test]
+    fn test_stalled_comparison_aligns_caret_under_full_width_text() {
+        let lines = stalled_comparison(
+            &strings(&["「日本語」の文章です。"]),
+            &strings(&["「日本語」の文章です."]),
+            2,
+        );
+        assert_eq!(lines[0], "3行目:");
+        assert_eq!(lines[1], "期待: 「日本語」の文章です。");
+        assert_eq!(lines[2], "現在: 「日本語」の文章です.");
+        // 「日本語」の文章です の10文字は20桁
+        assert_eq!(lines[3], format!("      {}^", " ".repeat(20)));
+        assert_eq!(
+            UnicodeWidthStr::width(&lines[3][..lines[3].len() - 1]),
+            UnicodeWidthStr::width("現在: 「日本語」の文章です")
+        );
+        assert_eq!(lines.len(), 4);
+    }
+
+    #[test]
+    fn test_stalled_comparison_names_common_causes() {
+        let causes = |expected: &str, actual: &str| -> Vec<String> {
+            stalled_comparison(&strings(&[expected]), &strings(&[actual]), 0)
+                .into_iter()
+                .filter(|line| line.starts_with("💡"))
+                .collect()
+        };
+        assert_eq!(causes("let x = 1;", "let x = 1; ").len(), 1);
+        assert!(causes("let x = 1;", "let x = 1; ")[0].contains("行末の空白"));
+        assert!(causes("foo(1)", "foo（1)")[0].contains("全角と半角"));
+        assert!(causes("a b", "a\u{3000}b")[0].contains("全角と半角"));
+        assert!(causes("say \"hi\"", "say “hi”")[0].contains("引用符"));
+        assert!(causes("abc", "abd").is_empty());
+
+        // 全角の空白は □ で見せ、2桁として ^ を揃える
+        let lines = stalled_comparison(&strings(&["a b"]), &strings(&["\u{3000}a b"]), 0);
+        assert_eq!(lines[2], "現在: □a·b");
+        assert_eq!(lines[3], "      ^");
+    }
+
+    #[test]
+    fn test_stalled_comparison_picks_first_differing_line() {
+        let expected = strings(&["same", "want", "more"]);
+        assert!(stalled_comparison(&expected, &expected, 0).is_empty());
+        let lines = stalled_comparison(&expected, &strings(&["same", "wont", "more"]), 4);
+        assert_eq!(&lines[..4], &["6行目:", "期待: want", "現在: wont", "       ^"]);
+        let lines = stalled_comparison(&expected, &strings(&["same"]), 0);
+        assert_eq!(lines, vec!["2行目:", "期待: want", "現在: (行がありません)"]);
+    }
+
+    fn strings(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_unified_diff_swapped_lines() {
+        let expected = strings(&["first", "second", "third"]);
+        let actual = strings(&["second", "first", "third"]);
+        assert_eq!(
+            unified_diff(&expected, &actual, 0),
+            vec![
+                "--- 期待",
+                "+++ 現在",
+                "@@ -1,3 +1,3 @@",
+                "-first",
+                " second",
+                "+first",
+                " third",
+            ]
+        );
+        assert!(unified_diff(&expected, &expected, 0).is_empty());
+    }
+
+    #[test]
+    fn test_unified_diff_separate_hunks_and_offset() {
+        let expected = strings(&["a", "b", "c", "d", "e", "f", "g", "h"]);
+        let mut actual = expected.clone();
+        actual[0] = "A".to_string();
+        actual.pop();
+        let lines = unified_diff(&expected, &actual, 10);
+        assert_eq!(lines[2], "@@ -11,3 +11,3 @@");
+        assert_eq!(&lines[3..5], &["-a", "+A"]);
+        assert_eq!(lines[7], "@@ -16,3 +16,2 @@");
+        assert_eq!(lines.last().unwrap(), "-h");
+
+        // 現在の行が足りない場合も期待の行を - で示す
+        let lines = unified_diff(&strings(&["x", "y"]), &[], 0);
+        assert_eq!(&lines[2..], &["@@ -1,2 +1,0 @@", "-x", "-y"]);
+    }
+}
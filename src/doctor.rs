@@ -0,0 +1,99 @@
+use std::process::Command;
+
+use crate::editor::{self, Editor, EditorChoice, EditorInfo};
+use crate::paths;
+use crate::theme::say;
+use crate::vim_backend;
+use crate::vim_rpc;
+
+fn print_editor(info: Option<&EditorInfo>, editor: Editor) {
+    match info {
+        Some(info) => {
+            let mut notes = Vec::new();
+            if editor == Editor::Vim {
+                notes.push(if info.clientserver { "+clientserver" } else { "-clientserver" });
+                notes.push(if info.timers { "+timers" } else { "-timers" });
+            }
+            let notes = if notes.is_empty() {
+                String::new()
+            } else {
+                format!("（{}）", notes.join(" "))
+            };
+            say!("  ✓ {}: {}{}", editor.label(), info.version, notes);
+            if let Some(reason) = info.unsupported_reason() {
+                say!("    ✗ 練習には使えません: {}", reason);
+            }
+        }
+        None => say!("  - {}: 見つかりません", editor.label()),
+    }
+}
+
+fn detect_known(editor: Editor) -> Option<EditorInfo> {
+    let choice = match editor {
+        Editor::Neovim => EditorChoice::Nvim,
+        Editor::Vim => EditorChoice::Vim,
+    };
+    match editor::detect(choice) {
+        Ok(info) => Some(info),
+        // バージョンが足りない場合も、見つかったことは表示する
+        Err(_) => Command::new(editor.program())
+            .arg("--version")
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| {
+                EditorInfo::from_version_output(editor, &String::from_utf8_lossy(&output.stdout))
+            }),
+    }
+}
+
+// 実行環境を確認し、選ばれるエディタと使えない機能を表示する
+pub fn run_doctor(choice: EditorChoice) {
+    say!("🩺 === 実行環境の確認 ===");
+    say!("\n📝 エディタ:");
+    print_editor(detect_known(Editor::Neovim).as_ref(), Editor::Neovim);
+    print_editor(detect_known(Editor::Vim).as_ref(), Editor::Vim);
+
+    say!("\n🖥️ tmux:");
+    match Command::new("tmux").arg("-V").output() {
+        Ok(output) if output.status.success() => {
+            say!("  ✓ {}", String::from_utf8_lossy(&output.stdout).trim());
+        }
+        _ => say!("  - 見つかりません（エディタ内の指示ウィンドウで練習します）"),
+    }
+
+    say!("\n🪟 画面の構成:");
+    if vim_backend::tmux_available() {
+        say!("  tmux の分割画面（上に指示、下にエディタ）");
+    } else {
+        say!("  エディタ内の指示ウィンドウ（同じ端末でエディタを起動し、上部に指示を表示）");
+    }
+    let roots = paths::roots();
+    say!("  データ: {}", roots.data.display());
+    say!("  一時ファイル: {}", roots.cache.display());
+    say!("  設定: {}", roots.config.display());
+    say!(
+        "  Neovim の接続先（--listen）: {}",
+        vim_rpc::default_address(std::process::id())
+    );
+
+    let choice_name = clap::ValueEnum::to_possible_value(&choice)
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default();
+    say!("\n🔌 練習に使うエディタ（--editor {}）:", choice_name);
+    match editor::detect(choice) {
+        Ok(info) => {
+            say!("  {} を使います（状態ファイルで連携）", info.editor.label());
+            let unavailable = info.unavailable_features();
+            if unavailable.is_empty() {
+                say!("  ✓ 全ての機能を使えます");
+            } else {
+                say!("  ⚠️ 使えない機能:");
+                for feature in unavailable {
+                    say!("    - {}", feature);
+                }
+            }
+        }
+        Err(e) => say!("  ✗ {}", e),
+    }
+}
@@ -0,0 +1,153 @@
+use crate::continuous_content::ContinuousChapterData;
+use crate::continuous_session::ContinuousExercise;
+use crate::vim_state::GoalDetector;
+
+// 再現可能な乱数列を作るための SplitMix64
+pub struct DrillRng {
+    state: u64,
+}
+
+impl DrillRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+// 開始直後に既に満たされている目標は単独の問題にしても意味がない
+fn is_trivial_goal(exercise: &ContinuousExercise) -> bool {
+    let detector = GoalDetector::new();
+    let state = exercise.initial_state();
+    exercise.goals.iter().all(|goal_def| {
+        goal_def
+            .to_goal()
+            .map(|goal| detector.check_goal(&goal, &state).is_met())
+            .unwrap_or(true)
+    })
+}
+
+// 出題候補を作る。per_goal の場合は目標ごとに1問の練習に分解する
+pub fn build_pool(chapters: &[&ContinuousChapterData], per_goal: bool) -> Vec<ContinuousExercise> {
+    let mut pool = Vec::new();
+    for chapter in chapters {
+        let prefix = format!("[第{}章]", chapter.chapter.number);
+        for exercise in &chapter.continuous_exercises {
+            if per_goal {
+                pool.extend(
+                    (0..exercise.goals.len())
+                        .filter_map(|i| exercise.single_goal(i))
+                        .filter(|single| !is_trivial_goal(single))
+                        .map(|single| single.with_title_prefix(&prefix)),
+                );
+            } else if !exercise.goals.is_empty() {
+                pool.push(exercise.with_title_prefix(&prefix));
+            }
+        }
+    }
+    pool
+}
+
+// 候補から重複なしで count 問を選ぶ
+pub fn sample(pool: &[ContinuousExercise], count: usize, seed: u64) -> Vec<ContinuousExercise> {
+    let mut indices: Vec<usize> = (0..pool.len()).collect();
+    let mut rng = DrillRng::new(seed);
+    let take = count.min(pool.len());
+    for i in 0..take {
+        let j = i + rng.below(indices.len() - i);
+        indices.swap(i, j);
+    }
+    indices[..take].iter().map(|&i| pool[i].clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuous_content::ChapterInfo;
+    use crate::continuous_session::{ExerciseGoal, FlowType};
+    use serde_json::json;
+
+    fn create_test_chapter() -> ContinuousChapterData {
+        ContinuousChapterData {
+            chapter: ChapterInfo {
+                number: 2,
+                title: "テスト".to_string(),
+                description: "テスト用".to_string(),
+                difficulty: None,
+                estimated_minutes: None,
+                tags: Vec::new(),
+                requires: Vec::new(),
+            },
+            continuous_exercises: vec![ContinuousExercise {
+                title: "移動".to_string(),
+                description: "移動します".to_string(),
+                sample_code: vec!["hello world".to_string()],
+                goals: vec![
+                    ExerciseGoal {
+                        goal_type: "position".to_string(),
+                        target: json!([0, 6]),
+                        description: "wへ".to_string(),
+                        ..Default::default()
+                    },
+                    ExerciseGoal {
+                        goal_type: "mode".to_string(),
+                        target: json!("normal"),
+                        description: "ノーマルモードへ".to_string(),
+                        ..Default::default()
+                    },
+                    ExerciseGoal {
+                        goal_type: "mode".to_string(),
+                        target: json!("insert"),
+                        description: "Insertモードへ".to_string(),
+                        ..Default::default()
+                    },
+                ],
+                flow_type: FlowType::AnyOrder,
+                ..Default::default()
+            }],
+            lessons: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_build_pool_per_goal_skips_trivial_goals() {
+        let chapter = create_test_chapter();
+        let pool = build_pool(&[&chapter], true);
+
+        // ノーマルモードの目標は開始直後に満たされるので除外される
+        assert_eq!(pool.len(), 2);
+        assert_eq!(pool[0].title, "[第2章] 移動 (1/3)");
+        assert_eq!(pool[1].title, "[第2章] 移動 (3/3)");
+        assert!(pool.iter().all(|e| e.goals.len() == 1));
+        assert!(pool.iter().all(|e| matches!(e.flow_type, FlowType::Sequential)));
+
+        // 元の章データは変更されない
+        assert_eq!(chapter.continuous_exercises[0].goals.len(), 3);
+    }
+
+    #[test]
+    fn test_sample_is_reproducible_with_seed() {
+        let chapter = create_test_chapter();
+        let mut pool = build_pool(&[&chapter], true);
+        pool.extend(build_pool(&[&chapter], false));
+
+        let first: Vec<String> = sample(&pool, 2, 42).into_iter().map(|e| e.title).collect();
+        let second: Vec<String> = sample(&pool, 2, 42).into_iter().map(|e| e.title).collect();
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 2);
+        assert_ne!(first[0], first[1]);
+
+        // 候補より多く要求しても候補数までしか出題しない
+        assert_eq!(sample(&pool, 10, 7).len(), pool.len());
+    }
+}
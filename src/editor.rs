@@ -0,0 +1,365 @@
+use anyhow::Result;
+use std::fmt;
+
+use crate::error::TutorialError;
+use std::process::Command;
+use std::sync::OnceLock;
+
+// 練習に使うエディタ（--editor）。auto は Neovim を優先し、無ければ Vim を使う
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EditorChoice {
+    Auto,
+    Vim,
+    Nvim,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Editor {
+    Neovim,
+    Vim,
+}
+
+// Vim で必要な最小バージョン（timer_start・json_encode・echoraw を使う）
+const MIN_VIM_VERSION: Version = Version::new(8, 2, 0);
+// Neovim で必要な最小バージョン（--server による --remote-send / --remote-expr を使う）
+const MIN_NEOVIM_VERSION: Version = Version::new(0, 7, 0);
+// キー入力の記録（vim.on_key と vim.fn.keytrans）に必要な Neovim のバージョン
+const KEY_CAPTURE_NEOVIM_VERSION: Version = Version::new(0, 8, 0);
+// カーソル位置の treesitter のノード（vim.treesitter.get_node）に必要な Neovim のバージョン
+const TREESITTER_NEOVIM_VERSION: Version = Version::new(0, 9, 0);
+
+// エディタのバージョン（--version の1行目の "v0.9.5" や "8.2" から読む）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self { major, minor, patch }
+    }
+
+    // "NVIM v0.11.0-dev-123+gabc" や "VIM - Vi IMproved 8.2 (...)" から最初の数字の並びを読む
+    // （-dev やディストリビューションの付記は無視する）
+    pub fn parse(line: &str) -> Option<Self> {
+        let word = line.split_whitespace().find_map(|word| {
+            let word = word.strip_prefix('v').unwrap_or(word);
+            word.starts_with(|c: char| c.is_ascii_digit()).then_some(word)
+        })?;
+        let number: String = word
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+        let mut parts = number.split('.').map(|part| part.parse::<u32>().ok());
+        let major = parts.next()??;
+        let minor = parts.next().flatten().unwrap_or(0);
+        let patch = parts.next().flatten().unwrap_or(0);
+        Some(Self::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl Editor {
+    pub fn program(&self) -> &'static str {
+        match self {
+            Editor::Neovim => "nvim",
+            Editor::Vim => "vim",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Editor::Neovim => "Neovim",
+            Editor::Vim => "Vim",
+        }
+    }
+
+    // vim.on_key によるキー入力の記録（キー効率スコアに使う）は Neovim だけ
+    pub fn captures_keystrokes(&self) -> bool {
+        *self == Editor::Neovim
+    }
+
+    // 端末なしで起動してスクリプトを実行する引数（--test で使う）
+    pub fn headless_args(&self) -> &'static [&'static str] {
+        match self {
+            Editor::Neovim => &["--headless"],
+            Editor::Vim => &["-N", "-u", "NONE", "-i", "NONE", "-es"],
+        }
+    }
+}
+
+// 検出したエディタとその機能
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorInfo {
+    pub editor: Editor,
+    pub version: String, // --version の1行目
+    pub clientserver: bool, // Vim の +clientserver（--servername / --remote-expr）
+    pub timers: bool,
+}
+
+impl EditorInfo {
+    pub fn from_version_output(editor: Editor, output: &str) -> Self {
+        let has_feature = |name: &str| {
+            output
+                .split_whitespace()
+                .any(|word| word == format!("+{}", name))
+        };
+        Self {
+            editor,
+            version: output.lines().next().unwrap_or("不明").trim().to_string(),
+            clientserver: editor == Editor::Vim && has_feature("clientserver"),
+            // Neovim は常にタイマーを使える
+            timers: editor == Editor::Neovim || has_feature("timers"),
+        }
+    }
+
+    // --version の1行目から読んだバージョン（読めなければ None）
+    pub fn parsed_version(&self) -> Option<Version> {
+        Version::parse(&self.version)
+    }
+
+    // 練習を始められない理由（バージョンや機能が足りない場合）
+    pub fn unsupported_reason(&self) -> Option<String> {
+        let minimum = match self.editor {
+            Editor::Neovim => MIN_NEOVIM_VERSION,
+            Editor::Vim => MIN_VIM_VERSION,
+        };
+        if let Some(version) = self.parsed_version()
+            && version < minimum
+        {
+            return Some(format!(
+                "{} {} 以上が必要です（検出: {}）",
+                self.editor.label(),
+                minimum,
+                version
+            ));
+        }
+        if !self.timers {
+            return Some("Vim の +timers 機能が必要です（状態の定期的な書き出しに使います）".to_string());
+        }
+        None
+    }
+
+    // キー入力を記録できるか（Vim と 0.8 未満の Neovim では記録しない。バージョン不明なら試す）
+    pub fn captures_keystrokes(&self) -> bool {
+        self.editor.captures_keystrokes()
+            && self
+                .parsed_version()
+                .is_none_or(|version| version >= KEY_CAPTURE_NEOVIM_VERSION)
+    }
+
+    // treesitter の目標を判定できるか（Vim には treesitter が無い。バージョン不明なら試す）
+    pub fn supports_treesitter(&self) -> bool {
+        self.editor == Editor::Neovim
+            && self
+                .parsed_version()
+                .is_none_or(|version| version >= TREESITTER_NEOVIM_VERSION)
+    }
+
+    // 外から式を評価させられるか（Neovim のソケットか Vim の +clientserver。無ければ状態ファイルを読むだけ）
+    pub fn evaluates_expressions(&self) -> bool {
+        self.editor == Editor::Neovim || self.clientserver
+    }
+
+    // このエディタでは使えない機能（doctor で表示する）
+    pub fn unavailable_features(&self) -> Vec<&'static str> {
+        match self.editor {
+            Editor::Neovim => {
+                let mut features = Vec::new();
+                if !self.captures_keystrokes() {
+                    features.push("キー効率スコアと . の繰り返し・テキストオブジェクトの目標（vim.on_key によるキー入力の記録には Neovim 0.8 以上が必要）");
+                }
+                if !self.supports_treesitter() {
+                    features.push("treesitter の目標（vim.treesitter.get_node には Neovim 0.9 以上が必要）");
+                }
+                features
+            }
+            Editor::Vim => {
+                let mut features = vec![
+                    "キー効率スコアと . の繰り返し・テキストオブジェクトの目標（vim.on_key によるキー入力の記録は Neovim のみ）",
+                    "treesitter の目標（Neovim のみ）",
+                ];
+                if !self.clientserver {
+                    features.push(
+                        "--remote-send による終了処理（+clientserver が無いため tmux のペインを閉じて終了）",
+                    );
+                    features.push("置換の目標（置き換える前の数をエディタに数えさせるには +clientserver が必要）");
+                }
+                features
+            }
+        }
+    }
+}
+
+fn detect_editor(editor: Editor) -> Option<EditorInfo> {
+    let output = Command::new(editor.program()).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(EditorInfo::from_version_output(
+        editor,
+        &String::from_utf8_lossy(&output.stdout),
+    ))
+}
+
+// 見つかったエディタ（バージョンや機能は確かめない）
+fn detect_any(choice: EditorChoice) -> Result<EditorInfo> {
+    let missing = |editor: &str| TutorialError::EditorMissing {
+        editor: editor.to_string(),
+    };
+    let info = match choice {
+        EditorChoice::Nvim => detect_editor(Editor::Neovim).ok_or_else(|| missing("Neovim（nvim）"))?,
+        EditorChoice::Vim => detect_editor(Editor::Vim).ok_or_else(|| missing("Vim（vim）"))?,
+        EditorChoice::Auto => detect_editor(Editor::Neovim)
+            .or_else(|| detect_editor(Editor::Vim))
+            .ok_or_else(|| missing("Neovim も Vim も"))?,
+    };
+    Ok(info)
+}
+
+pub fn detect(choice: EditorChoice) -> Result<EditorInfo> {
+    let info = detect_any(choice)?;
+    if let Some(reason) = info.unsupported_reason() {
+        return Err(TutorialError::EditorTooOld {
+            version: info.version,
+            reason,
+        }
+        .into());
+    }
+    Ok(info)
+}
+
+static SELECTED: OnceLock<EditorInfo> = OnceLock::new();
+
+// 起動時に一度だけ選ぶ（以降の練習は全て同じエディタで行う）
+pub fn select(choice: EditorChoice) -> Result<&'static EditorInfo> {
+    let info = detect(choice)?;
+    Ok(SELECTED.get_or_init(|| info))
+}
+
+// 簡易モードでは監視用のスクリプトを使わないため、古いエディタでもそのまま選ぶ
+pub fn select_for_simple_mode(choice: EditorChoice) -> Result<&'static EditorInfo> {
+    let info = detect_any(choice)?;
+    Ok(SELECTED.get_or_init(|| info))
+}
+
+// 選んだエディタ（select 前やテストでは Neovim）
+pub fn current() -> Editor {
+    current_info().map_or(Editor::Neovim, |info| info.editor)
+}
+
+// 選んだエディタの情報（バージョンで機能を切り替えるときに使う。select 前は None）
+pub fn current_info() -> Option<&'static EditorInfo> {
+    SELECTED.get()
+}
+
+// 選んだエディタで +clientserver を使えるか
+pub fn current_clientserver() -> bool {
+    current_info().is_some_and(|info| info.clientserver)
+}
+
+// 選んだエディタに式を評価させられるか（select 前やテストでは Neovim として扱う）
+pub fn current_evaluates_expressions() -> bool {
+    current_info().is_none_or(EditorInfo::evaluates_expressions)
+}
+
+// 選んだエディタで treesitter の目標を判定できるか（select 前やテストでは Neovim として扱う）
+pub fn current_supports_treesitter() -> bool {
+    current_info().is_none_or(EditorInfo::supports_treesitter)
+}
+
+// 選んだエディタでキー入力を記録できるか（select 前やテストでは Neovim として扱う）
+pub fn current_captures_keystrokes() -> bool {
+    current_info().is_none_or(EditorInfo::captures_keystrokes)
+}
+
+// Neovim の :terminal から起動された場合の、その Neovim のソケット（$NVIM）
+pub fn parent_neovim() -> Option<String> {
+    std::env::var("NVIM").ok().filter(|server| !server.is_empty())
+}
+
+static PARENT_SERVER: OnceLock<String> = OnceLock::new();
+
+// 練習を新しく起動したエディタではなく、起動元の Neovim で行う（起動時に一度だけ選ぶ）
+pub fn use_parent_server(server: String) {
+    let _ = PARENT_SERVER.set(server);
+}
+
+// 練習に使う起動元の Neovim（use_parent_server していなければ None）
+pub fn current_parent_server() -> Option<&'static str> {
+    PARENT_SERVER.get().map(String::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vim_version_output_detects_features() {
+        let output = "VIM - Vi IMproved 8.2 (2019 Dec 12, compiled Oct 01 2021)\n\
+                      Huge version without GUI.  Features included (+) or not (-):\n\
+                      +clientserver      +timers            -lua               +json";
+        let info = EditorInfo::from_version_output(Editor::Vim, output);
+        assert_eq!(info.parsed_version(), Some(Version::new(8, 2, 0)));
+        assert!(info.clientserver);
+        assert!(info.timers);
+        assert_eq!(info.unsupported_reason(), None);
+        assert_eq!(info.unavailable_features().len(), 2);
+
+        let old = EditorInfo::from_version_output(
+            Editor::Vim,
+            "VIM - Vi IMproved 8.1 (2018 May 18)\n-clientserver +timers",
+        );
+        assert!(!old.clientserver);
+        assert!(!old.evaluates_expressions());
+        assert!(info.evaluates_expressions());
+        assert_eq!(
+            old.unsupported_reason().as_deref(),
+            Some("Vim 8.2.0 以上が必要です（検出: 8.1.0）")
+        );
+    }
+
+    #[test]
+    fn test_neovim_has_every_feature() {
+        let info = EditorInfo::from_version_output(Editor::Neovim, "NVIM v0.10.0\nBuild type: Release");
+        assert_eq!(info.parsed_version(), Some(Version::new(0, 10, 0)));
+        assert!(info.unavailable_features().is_empty());
+        assert!(info.editor.captures_keystrokes());
+        assert!(!Editor::Vim.captures_keystrokes());
+    }
+
+    #[test]
+    fn test_parse_neovim_versions() {
+        assert_eq!(Version::parse("NVIM v0.9.5"), Some(Version::new(0, 9, 5)));
+        // ディストリビューションの付記やナイトリーの -dev は無視する
+        assert_eq!(
+            Version::parse("NVIM v0.7.2-3ubuntu1 (Ubuntu 0.7.2-3ubuntu1)"),
+            Some(Version::new(0, 7, 2))
+        );
+        assert_eq!(
+            Version::parse("NVIM v0.11.0-dev-123+gabc"),
+            Some(Version::new(0, 11, 0))
+        );
+        assert_eq!(Version::parse("NVIM"), None);
+
+        let old = EditorInfo::from_version_output(Editor::Neovim, "NVIM v0.4.4\nBuild type: Release");
+        assert_eq!(
+            old.unsupported_reason().as_deref(),
+            Some("Neovim 0.7.0 以上が必要です（検出: 0.4.4）")
+        );
+        // 0.7 では練習できるが、キー入力は記録しない
+        let without_keytrans = EditorInfo::from_version_output(Editor::Neovim, "NVIM v0.7.2");
+        assert_eq!(without_keytrans.unsupported_reason(), None);
+        assert!(!without_keytrans.captures_keystrokes());
+        assert!(!without_keytrans.supports_treesitter());
+        assert_eq!(without_keytrans.unavailable_features().len(), 2);
+        assert!(EditorInfo::from_version_output(Editor::Neovim, "NVIM v0.9.5").supports_treesitter());
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::vim_backend::temp_file;
+use crate::output::{DebugLog, debug_log};
+
+// debug_log! の書き先
+const DEBUG_LOG: DebugLog = DebugLog::ERROR;
+
+// 原因が分かっている失敗。初心者が次に何をすればよいかの提案を付けて表示する
+// （anyhow で返している関数からは .into() で返し、main で取り出して表示する）
+#[derive(Debug)]
+pub enum TutorialError {
+    EditorMissing { editor: String }, // 見つからなかったエディタ（"Neovim（nvim）" など）
+    EditorTooOld { version: String, reason: String },
+    TmuxMissing,
+    TmuxSessionFailed { action: &'static str, stderr: String },
+    SocketTimeout { socket: String, waited: Duration },
+    // path は誤りのある項目（"chapter.number" や "continuous_exercises[0].goals[1].type"）
+    ContentParse { file: PathBuf, path: Option<String>, line: Option<usize>, message: String },
+    StatusFileUnreadable { path: String, message: String },
+    PaneInjectionFailed { pane: String, stderr: String },
+    Other(anyhow::Error), // 分類していない失敗（提案なしでそのまま表示する）
+}
+
+// パッケージを入れるコマンド（OS ごと）
+fn install_command(package: &str) -> String {
+    if cfg!(windows) {
+        format!("winget install {}", package)
+    } else if cfg!(target_os = "macos") {
+        format!("brew install {}", package)
+    } else {
+        format!("sudo apt install {}  または  brew install {}", package, package)
+    }
+}
+
+impl TutorialError {
+    // 何が起きたか（1行）
+    pub fn cause(&self) -> String {
+        match self {
+            TutorialError::EditorMissing { editor } => format!("{}が見つかりません", editor),
+            TutorialError::EditorTooOld { version, reason } => format!("{}: {}", version, reason),
+            TutorialError::TmuxMissing => "tmuxが利用できません（従来モードの分割画面に必要です）".to_string(),
+            TutorialError::TmuxSessionFailed { action, stderr } => {
+                format!("tmuxの{}に失敗しました: {}", action, stderr.trim())
+            }
+            TutorialError::SocketTimeout { socket, waited } => format!(
+                "{}秒待ってもNeovimのソケット {} が作られませんでした",
+                waited.as_secs_f64(),
+                socket
+            ),
+            TutorialError::ContentParse {
+                file,
+                path: Some(path),
+                line,
+                message,
+            } => match line {
+                Some(line) => format!("章データ {}: {}（{}行目）: {}", file.display(), path, line, message),
+                None => format!("章データ {}: {}: {}", file.display(), path, message),
+            },
+            TutorialError::ContentParse {
+                file,
+                path: None,
+                line: Some(line),
+                message,
+            } => format!("章データ {}（{}行目）を読めません: {}", file.display(), line, message),
+            TutorialError::ContentParse { file, path: None, line: None, message } => {
+                format!("章データ {} を読めません: {}", file.display(), message)
+            }
+            TutorialError::StatusFileUnreadable { path, message } => {
+                format!("状態ファイル {} を読めません: {}", path, message)
+            }
+            TutorialError::PaneInjectionFailed { pane, stderr } => {
+                format!("tmuxのペイン{}にコマンドを送れませんでした: {}", pane, stderr.trim())
+            }
+            TutorialError::Other(error) => format!("{:#}", error),
+        }
+    }
+
+    // 次に試すこと（分類していない失敗には無い）
+    pub fn suggestion(&self) -> Option<String> {
+        let suggestion = match self {
+            TutorialError::EditorMissing { .. } => format!(
+                "{} でインストールし、`vim-tutorial-nvim doctor` で検出されるか確認してください",
+                install_command(if cfg!(windows) { "Neovim.Neovim" } else { "neovim" })
+            ),
+            TutorialError::EditorTooOld { .. } => "新しい版をインストールしてください（OS の標準パッケージが古い場合は Neovim の公式リリースを使えます）。`--editor` で別のエディタも選べます".to_string(),
+            // Windows には tmux が無いため、エディタ内に指示を出す連続学習モードを勧める
+            TutorialError::TmuxMissing if cfg!(windows) => {
+                "Windows では tmux を使えません。連続学習モード（-c）で練習してください".to_string()
+            }
+            TutorialError::TmuxMissing => format!(
+                "{} でインストールしてください。tmux なしで練習するには連続学習モード（-c）を使ってください",
+                install_command("tmux")
+            ),
+            TutorialError::TmuxSessionFailed { .. } => "前回のセッションが残っている可能性があります。`vim-tutorial-nvim clean` で片付けてからやり直してください".to_string(),
+            TutorialError::SocketTimeout { .. } => "Neovimが起動直後に終了していないか（設定ファイルのエラーなど）確認し、`vim-tutorial-nvim clean` で残ったソケットを片付けてからやり直してください".to_string(),
+            TutorialError::ContentParse { .. } => "その行の前後のインデント（タブではなくスペース）と引用符の対応を確認してください".to_string(),
+            TutorialError::StatusFileUnreadable { .. } => format!(
+                "一時ディレクトリ（{}）に書き込めるか、空き容量があるか確認してください",
+                temp_file("")
+            ),
+            TutorialError::PaneInjectionFailed { .. } => "練習中に tmux のペインを閉じていないか確認し、`vim-tutorial-nvim clean` の後にやり直してください".to_string(),
+            TutorialError::Other(_) => return None,
+        };
+        Some(suggestion)
+    }
+
+    // 端末に表示する行
+    pub fn render(&self) -> Vec<String> {
+        let mut lines = vec![format!("❌ {}", self.cause())];
+        if let Some(suggestion) = self.suggestion() {
+            lines.push(format!("💡 {}", suggestion));
+        }
+        lines.push(format!(
+            "📄 詳細はデバッグログにあります: {}",
+            temp_file("vim_tutorial_debug.log")
+        ));
+        lines
+    }
+
+    // 原因の連鎖を全てデバッグログに残す
+    pub fn log(&self) {
+        debug_log!("{:?}", self);
+    }
+}
+
+impl fmt::Display for TutorialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.cause())
+    }
+}
+
+impl std::error::Error for TutorialError {}
+
+// anyhow で返ってきた失敗から、分類済みのものを取り出す（途中で context を付けていても取り出せる）
+impl From<anyhow::Error> for TutorialError {
+    fn from(error: anyhow::Error) -> Self {
+        error.downcast::<TutorialError>().unwrap_or_else(TutorialError::Other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    fn missing_tmux() -> anyhow::Result<()> {
+        Err(TutorialError::TmuxMissing.into())
+    }
+
+    #[test]
+    fn test_missing_tmux_gives_install_guidance() {
+        // 呼び出し元で context を付けても、分類した失敗として表示する
+        let error = TutorialError::from(missing_tmux().context("練習を開始できません").unwrap_err());
+        let lines = error.render();
+        assert_eq!(lines[0], "❌ tmuxが利用できません（従来モードの分割画面に必要です）");
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            lines[1],
+            "💡 sudo apt install tmux  または  brew install tmux でインストールしてください。tmux なしで練習するには連続学習モード（-c）を使ってください"
+        );
+        assert!(lines[1].contains("連続学習モード（-c）"));
+    }
+
+    #[test]
+    fn test_unknown_errors_keep_their_chain() {
+        let error = TutorialError::from(
+            anyhow::anyhow!("ファイルがありません").context("記録を読めません"),
+        );
+        assert!(matches!(error, TutorialError::Other(_)));
+        assert_eq!(error.suggestion(), None);
+        assert_eq!(error.render()[0], "❌ 記録を読めません: ファイルがありません");
+
+        let parse = TutorialError::ContentParse {
+            file: PathBuf::from("data/chapters/chapter_01.yaml"),
+            path: None,
+            line: Some(12),
+            message: "did not find expected key".to_string(),
+        };
+        assert_eq!(
+            parse.cause(),
+            "章データ data/chapters/chapter_01.yaml（12行目）を読めません: did not find expected key"
+        );
+    }
+}
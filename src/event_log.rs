@@ -0,0 +1,401 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::vim_state::VimState;
+
+// 再生時、これより長い間隔は待たずに省略する（秒）
+const MAX_REPLAY_GAP_SECS: f64 = 5.0;
+
+// 連続学習モードの1セッションで起きたこと
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SessionEvent {
+    ExerciseStarted { title: String, goals: Vec<String> },
+    StateSample { state: Box<VimState> }, // バッファの中身を持つため大きい
+    GoalActivated { index: usize, description: String },
+    GoalCompleted { index: usize, description: String },
+    HintShown { index: usize, level: usize },
+    // 目標の on_activate / on_complete（trigger）で練習用Neovimに実行させたコマンド
+    GoalCommands { index: usize, trigger: String, commands: Vec<String> },
+    // :TutorialDemo で目標の解答のキーを再生した（DemoRestored は再生前の状態に戻したとき）
+    DemoStarted { index: usize, keys: String },
+    DemoRestored { index: usize },
+    PaneUpdated { lines: Vec<String> },
+    Paused { index: usize },
+    Resumed { index: usize },
+    // :TutorialRetry で練習を最初からやり直した（attempt は新しい取り組みの回数）
+    Retried { attempt: usize },
+    // 練習の終わりに保存した画面（tmux のペインの内容）のファイル
+    PanesCaptured { files: Vec<String> },
+    SessionEnded { result: String },
+}
+
+// JSONL の1行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: SessionEvent,
+}
+
+// 記録先のファイルは最初のイベントで作る。書き込めなくなったら記録をやめ、セッションは続ける
+pub struct EventLog {
+    dir: Option<PathBuf>,
+    path: Option<PathBuf>,
+    writer: Option<BufWriter<File>>,
+}
+
+impl EventLog {
+    pub fn in_dir<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: Some(dir.as_ref().to_path_buf()),
+            path: None,
+            writer: None,
+        }
+    }
+
+    // 記録しない（テストなど）
+    pub fn disabled() -> Self {
+        Self {
+            dir: None,
+            path: None,
+            writer: None,
+        }
+    }
+
+    #[cfg(test)] // テストで記録先を確認する
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn record(&mut self, event: SessionEvent) {
+        if self.writer.is_none() && !self.open() {
+            return;
+        }
+        let record = EventRecord {
+            at: Utc::now(),
+            event,
+        };
+        let written = match (&mut self.writer, serde_json::to_string(&record)) {
+            (Some(writer), Ok(line)) => writeln!(writer, "{}", line).is_ok(),
+            _ => false,
+        };
+        if !written {
+            self.give_up("書き込みに失敗しました");
+        }
+    }
+
+    // セッションの区切りでディスクに書き出す
+    pub fn flush(&mut self) {
+        if let Some(writer) = &mut self.writer
+            && writer.flush().is_err()
+        {
+            self.give_up("書き込みに失敗しました");
+        }
+    }
+
+    fn open(&mut self) -> bool {
+        let Some(dir) = self.dir.take() else {
+            return false;
+        };
+        let path = dir.join(format!(
+            "session-{}-{}.jsonl",
+            Utc::now().format("%Y%m%d-%H%M%S"),
+            std::process::id()
+        ));
+        let file = fs::create_dir_all(&dir).and_then(|_| File::create(&path));
+        match file {
+            Ok(file) => {
+                crate::output::info!("📼 セッションの記録: {}", path.display());
+                self.writer = Some(BufWriter::new(file));
+                self.path = Some(path);
+                true
+            }
+            Err(e) => {
+                crate::output::warning!("⚠️ セッションの記録を作成できません（{}）: {}", path.display(), e);
+                false
+            }
+        }
+    }
+
+    fn give_up(&mut self, reason: &str) {
+        self.writer = None;
+        if let Some(path) = &self.path {
+            crate::output::warning!("⚠️ セッションの記録を中止しました（{}）: {}", path.display(), reason);
+        }
+    }
+}
+
+impl Drop for EventLog {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+// 記録ファイルを読む。壊れた行は飛ばして件数を返す
+pub fn read_events(path: &Path) -> Result<(Vec<EventRecord>, usize)> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("記録ファイル {} を読めません: {}", path.display(), e))?;
+    let mut records = Vec::new();
+    let mut skipped = 0;
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(record) => records.push(record),
+            Err(_) => skipped += 1,
+        }
+    }
+    Ok((records, skipped))
+}
+
+// 再生中の目標の達成状況
+#[derive(Debug, Default)]
+pub struct ReplayState {
+    goals: Vec<String>,
+    completed: Vec<bool>,
+    current: Option<usize>,
+}
+
+impl ReplayState {
+    fn goal_status(&self) -> String {
+        self.completed
+            .iter()
+            .enumerate()
+            .map(|(i, &done)| {
+                let mark = if done {
+                    "✅"
+                } else if self.current == Some(i) {
+                    "▶️"
+                } else {
+                    "⬜"
+                };
+                format!("{}{}", mark, i + 1)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn goal_name(&self, index: usize) -> String {
+        match self.goals.get(index) {
+            Some(description) => format!("{}. {}", index + 1, description),
+            None => format!("{}.", index + 1),
+        }
+    }
+}
+
+// 1件のイベントを表示する行にする（目標の状況を更新しながら）
+pub fn describe_event(record: &EventRecord, state: &mut ReplayState) -> Vec<String> {
+    match &record.event {
+        SessionEvent::ExerciseStarted { title, goals } => {
+            *state = ReplayState {
+                goals: goals.clone(),
+                completed: vec![false; goals.len()],
+                current: None,
+            };
+            let mut lines = vec![format!("🎯 練習開始: {}", title)];
+            lines.extend(
+                goals
+                    .iter()
+                    .enumerate()
+                    .map(|(i, goal)| format!("   {}. {}", i + 1, goal)),
+            );
+            lines
+        }
+        SessionEvent::StateSample { state: vim_state } => vec![format!(
+            "📍 {}行{}列 {:?} (tick {}){}  目標: {}",
+            vim_state.cursor_line + 1,
+            vim_state.cursor_col + 1,
+            vim_state.mode,
+            vim_state.changedtick,
+            if vim_state.modified { " [+]" } else { "" },
+            state.goal_status()
+        )],
+        SessionEvent::GoalActivated { index, .. } => {
+            state.current = Some(*index);
+            vec![format!("▶️ 目標{}が有効になりました  目標: {}", state.goal_name(*index), state.goal_status())]
+        }
+        SessionEvent::GoalCompleted { index, .. } => {
+            if let Some(done) = state.completed.get_mut(*index) {
+                *done = true;
+            }
+            vec![format!("✅ 目標{}を達成  目標: {}", state.goal_name(*index), state.goal_status())]
+        }
+        SessionEvent::HintShown { index, level } => {
+            vec![format!("💡 目標{}のヒント（段階 {}）", index + 1, level)]
+        }
+        SessionEvent::GoalCommands { index, trigger, commands } => {
+            vec![format!("⚙️ 目標{}の{}: {}", index + 1, trigger, commands.join(" | "))]
+        }
+        SessionEvent::DemoStarted { index, keys } => vec![format!("🎬 目標{}の解答を再生: {}", index + 1, keys)],
+        SessionEvent::DemoRestored { index } => vec![format!("↩️ 目標{}の解答を再生する前の状態に戻しました", index + 1)],
+        SessionEvent::PaneUpdated { lines } => {
+            let mut shown = vec!["🖥️ 指示ペイン:".to_string()];
+            shown.extend(lines.iter().map(|line| format!("   │ {}", line)));
+            shown
+        }
+        SessionEvent::Paused { index } => {
+            vec![format!("⏸️ 一時停止（目標{}）  目標: {}", index + 1, state.goal_status())]
+        }
+        SessionEvent::Resumed { index } => {
+            vec![format!("▶️ 再開（目標{}）  目標: {}", index + 1, state.goal_status())]
+        }
+        SessionEvent::Retried { attempt } => {
+            state.completed.iter_mut().for_each(|done| *done = false);
+            vec![format!("🔁 練習を最初からやり直し（{}回目）  目標: {}", attempt, state.goal_status())]
+        }
+        SessionEvent::PanesCaptured { files } => vec![format!("📄 画面を保存: {}", files.join(", "))],
+        SessionEvent::SessionEnded { result } => vec![format!("🏁 終了: {}", result)],
+    }
+}
+
+pub fn run_replay(path: &Path, speed: f64) -> Result<()> {
+    if speed.is_nan() || speed <= 0.0 {
+        return Err(anyhow::anyhow!("--speed には 0 より大きい値を指定してください"));
+    }
+    let (records, skipped) = read_events(path)?;
+    println!("📼 === セッションの再生: {} ({}件) ===", path.display(), records.len());
+    if skipped > 0 {
+        println!("⚠️ 読めない行を{}行飛ばしました", skipped);
+    }
+    let Some(first) = records.first() else {
+        println!("記録がありません。");
+        return Ok(());
+    };
+
+    let started = first.at;
+    let mut previous = first.at;
+    let mut state = ReplayState::default();
+    for record in &records {
+        let gap = (record.at - previous).num_milliseconds().max(0) as f64 / 1000.0 / speed;
+        if gap > MAX_REPLAY_GAP_SECS {
+            println!("⏩ {:.1}秒の間隔を省略", gap * speed);
+        } else if gap > 0.0 {
+            thread::sleep(Duration::from_secs_f64(gap));
+        }
+        previous = record.at;
+
+        let elapsed = (record.at - started).num_milliseconds() as f64 / 1000.0;
+        for (i, line) in describe_event(record, &mut state).iter().enumerate() {
+            if i == 0 {
+                println!("[{:>7.1}s] {}", elapsed, line);
+            } else {
+                println!("           {}", line);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vim_state::VimMode;
+    use tempfile::tempdir;
+
+    fn sample_state() -> VimState {
+        VimState {
+            mode: VimMode::Insert,
+            cursor_line: 2,
+            cursor_col: 4,
+            buffer_content: vec!["a".to_string()],
+            changedtick: 7,
+            modified: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_event_log_round_trip() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let mut log = EventLog::in_dir(tmp_dir.path().join("sessions"));
+        assert!(log.path().is_none());
+        log.record(SessionEvent::ExerciseStarted {
+            title: "練習".to_string(),
+            goals: vec!["移動".to_string()],
+        });
+        log.record(SessionEvent::StateSample {
+            state: Box::new(sample_state()),
+        });
+        log.flush();
+
+        let path = log.path().unwrap().to_path_buf();
+        let content = fs::read_to_string(&path)?;
+        assert!(content.lines().next().unwrap().contains(r#""event":"exercise_started""#));
+        fs::write(&path, format!("{}not json\n", content))?;
+
+        let (records, skipped) = read_events(&path)?;
+        assert_eq!(skipped, 1);
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[1].event,
+            SessionEvent::StateSample {
+                state: Box::new(sample_state())
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_log_never_fails_when_directory_unusable() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let blocker = tmp_dir.path().join("file");
+        fs::write(&blocker, "")?;
+        // ディレクトリを作れない場所でも記録が無くなるだけ
+        let mut log = EventLog::in_dir(blocker.join("sessions"));
+        log.record(SessionEvent::SessionEnded {
+            result: "completed".to_string(),
+        });
+        log.flush();
+        assert!(log.path().is_none());
+
+        let mut disabled = EventLog::disabled();
+        disabled.record(SessionEvent::SessionEnded {
+            result: "completed".to_string(),
+        });
+        assert!(disabled.path().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_describe_event_tracks_goal_status() {
+        let at = Utc::now();
+        let record = |event| EventRecord { at, event };
+        let mut state = ReplayState::default();
+
+        describe_event(
+            &record(SessionEvent::ExerciseStarted {
+                title: "練習".to_string(),
+                goals: vec!["移動".to_string(), "入力".to_string()],
+            }),
+            &mut state,
+        );
+        describe_event(
+            &record(SessionEvent::GoalActivated {
+                index: 0,
+                description: "移動".to_string(),
+            }),
+            &mut state,
+        );
+        let lines = describe_event(
+            &record(SessionEvent::StateSample {
+                state: Box::new(sample_state()),
+            }),
+            &mut state,
+        );
+        assert_eq!(lines, vec!["📍 3行5列 Insert (tick 7) [+]  目標: ▶️1 ⬜2"]);
+
+        let lines = describe_event(
+            &record(SessionEvent::GoalCompleted {
+                index: 0,
+                description: "移動".to_string(),
+            }),
+            &mut state,
+        );
+        assert_eq!(lines, vec!["✅ 目標1. 移動を達成  目標: ✅1 ⬜2"]);
+    }
+}
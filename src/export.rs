@@ -0,0 +1,602 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use unicode_width::UnicodeWidthStr;
+
+use crate::keystrokes;
+use crate::progress::{ProgressData, ProgressStore};
+use crate::summary::{self, ExerciseStatus};
+use crate::theme::say;
+
+// エクスポートファイルの形式の版（読めない新しい版は取り込まない）
+const EXPORT_VERSION: u32 = 1;
+
+// CSV の列（1行 = 練習1回分の挑戦）
+pub const CSV_COLUMNS: &[&str] = &[
+    "learner",
+    "kind",
+    "mode",
+    "chapter_number",
+    "chapter_title",
+    "exercise_title",
+    "status",
+    "duration_secs",
+    "hints_used",
+    "goals_skipped",
+    "optimal_keystrokes",
+    "actual_keystrokes",
+    "keystroke_efficiency",
+    "time_limit_secs",
+    "finished_at",
+];
+
+pub const EXPORT_LONG_HELP: &str = "\
+進捗ファイル（data/progress.json）と練習ごとの集計を、学習者の識別子付きで書き出します。
+識別子は --learner、設定ファイルの learner_id、$USER@ホスト名 の順に決まります。
+
+json: 進捗ファイルの内容（章の記録・タイムチャレンジ）と練習ごとの集計
+      （挑戦回数・完了回数・最短時間・最高キー効率）。import で取り込めます。
+
+csv: 1行 = 練習1回分の挑戦（未実施の練習は含みません）。列:
+  learner               学習者の識別子
+  kind                  chapter（章の中での練習）/ challenge（タイムチャレンジ）
+  mode                  continuous / classic
+  chapter_number        章番号
+  chapter_title         章のタイトル（challenge では空）
+  exercise_title        練習のタイトル
+  status                completed / incomplete / failed / timed_out
+  duration_secs         かかった秒数
+  hints_used            ヒントの回数（challenge では空）
+  goals_skipped         スキップした目標の数（challenge では空）
+  optimal_keystrokes    最適キー数の合計（採点対象が無ければ空）
+  actual_keystrokes     実際のキー数の合計（採点対象が無ければ空）
+  keystroke_efficiency  キー効率 0〜1（採点対象が無ければ空）
+  time_limit_secs       制限時間（challenge のみ）
+  finished_at           終了日時（RFC 3339, UTC）";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+// 練習ごとの集計（最短時間の挑戦を最良の記録とする）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExerciseStats {
+    pub mode: crate::progress::LearningMode,
+    pub chapter_number: u8,
+    pub exercise_title: String,
+    pub attempts: usize,
+    pub completions: usize,
+    pub hints_used: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_duration_secs: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_attempt_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_keystroke_efficiency: Option<f64>,
+}
+
+// export の JSON 出力（import の入力）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportFile {
+    pub version: u32,
+    pub learner: String,
+    pub exported_at: DateTime<Utc>,
+    pub progress: ProgressData,
+    #[serde(default)]
+    pub exercise_stats: Vec<ExerciseStats>,
+}
+
+// 集計ファイルの学習者1人分（全ての挑戦の記録を残す）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LearnerRecord {
+    pub learner: String,
+    pub last_exported_at: DateTime<Utc>,
+    pub progress: ProgressData,
+    pub exercise_stats: Vec<ExerciseStats>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Aggregate {
+    pub learners: Vec<LearnerRecord>,
+}
+
+impl Aggregate {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("集計ファイル {} を読めません: {}", path.display(), e))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    // 同じ学習者の記録は1つにまとめる。同じ日時の記録は重複として1件だけ残す
+    pub fn merge(&mut self, export: ExportFile) {
+        let index = match self.learners.iter().position(|l| l.learner == export.learner) {
+            Some(index) => index,
+            None => {
+                self.learners.push(LearnerRecord {
+                    learner: export.learner.clone(),
+                    last_exported_at: export.exported_at,
+                    progress: ProgressData::default(),
+                    exercise_stats: Vec::new(),
+                });
+                self.learners.len() - 1
+            }
+        };
+        let record = &mut self.learners[index];
+        record.last_exported_at = record.last_exported_at.max(export.exported_at);
+        merge_progress(&mut record.progress, export.progress);
+        record.exercise_stats = exercise_stats(&record.progress);
+        self.learners.sort_by(|a, b| a.learner.cmp(&b.learner));
+    }
+}
+
+fn merge_progress(into: &mut ProgressData, other: ProgressData) {
+    for summary in other.summaries {
+        let duplicate = into.summaries.iter().any(|s| {
+            s.mode == summary.mode
+                && s.chapter_number == summary.chapter_number
+                && s.finished_at == summary.finished_at
+        });
+        if !duplicate {
+            into.summaries.push(summary);
+        }
+    }
+    into.summaries.sort_by_key(|s| s.finished_at);
+
+    for attempt in other.challenges {
+        let duplicate = into.challenges.iter().any(|a| {
+            a.chapter_number == attempt.chapter_number
+                && a.exercise_title == attempt.exercise_title
+                && a.attempted_at == attempt.attempted_at
+        });
+        if !duplicate {
+            into.challenges.push(attempt);
+        }
+    }
+    into.challenges.sort_by_key(|a| a.attempted_at);
+}
+
+// 章の記録から練習ごとの挑戦回数と最良の記録を集計する（未実施の練習は数えない）
+pub fn exercise_stats(progress: &ProgressData) -> Vec<ExerciseStats> {
+    let mut stats: Vec<ExerciseStats> = Vec::new();
+    for summary in &progress.summaries {
+        for exercise in &summary.exercises {
+            if exercise.status == ExerciseStatus::NotStarted {
+                continue;
+            }
+            let index = match stats.iter().position(|s| {
+                s.mode == summary.mode
+                    && s.chapter_number == summary.chapter_number
+                    && s.exercise_title == exercise.title
+            }) {
+                Some(index) => index,
+                None => {
+                    stats.push(ExerciseStats {
+                        mode: summary.mode,
+                        chapter_number: summary.chapter_number,
+                        exercise_title: exercise.title.clone(),
+                        attempts: 0,
+                        completions: 0,
+                        hints_used: 0,
+                        best_duration_secs: None,
+                        best_attempt_at: None,
+                        best_keystroke_efficiency: None,
+                    });
+                    stats.len() - 1
+                }
+            };
+            let entry = &mut stats[index];
+            entry.attempts += 1;
+            entry.hints_used += exercise.hints_used;
+            if exercise.status == ExerciseStatus::Completed {
+                entry.completions += 1;
+                if entry.best_duration_secs.is_none_or(|best| exercise.duration_secs < best) {
+                    entry.best_duration_secs = Some(exercise.duration_secs);
+                    entry.best_attempt_at = Some(summary.finished_at);
+                }
+            }
+            if let Some(efficiency) = keystrokes::overall_efficiency(&exercise.keystroke_scores) {
+                entry.best_keystroke_efficiency = Some(
+                    entry
+                        .best_keystroke_efficiency
+                        .map_or(efficiency, |best| best.max(efficiency)),
+                );
+            }
+        }
+    }
+    stats.sort_by(|a, b| {
+        (a.chapter_number, &a.exercise_title).cmp(&(b.chapter_number, &b.exercise_title))
+    });
+    stats
+}
+
+// $USER@ホスト名（分からない部分は unknown）
+pub fn default_learner_id() -> String {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    let host = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| {
+            std::process::Command::new("hostname")
+                .output()
+                .ok()
+                .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        })
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    format!("{}@{}", user, host)
+}
+
+fn serde_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+// CSV の値（カンマ・引用符・改行を含む場合は引用符で囲む）
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn csv_rows(learner: &str, progress: &ProgressData) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    for summary in &progress.summaries {
+        for exercise in &summary.exercises {
+            if exercise.status == ExerciseStatus::NotStarted {
+                continue;
+            }
+            let optimal: usize = exercise.keystroke_scores.iter().map(|s| s.optimal).sum();
+            let actual: usize = exercise.keystroke_scores.iter().map(|s| s.actual).sum();
+            let scored = !exercise.keystroke_scores.is_empty();
+            rows.push(vec![
+                learner.to_string(),
+                "chapter".to_string(),
+                serde_name(&summary.mode),
+                summary.chapter_number.to_string(),
+                summary.chapter_title.clone(),
+                exercise.title.clone(),
+                serde_name(&exercise.status),
+                format!("{:.1}", exercise.duration_secs),
+                exercise.hints_used.to_string(),
+                exercise.goals_skipped.to_string(),
+                if scored { optimal.to_string() } else { String::new() },
+                if scored { actual.to_string() } else { String::new() },
+                keystrokes::overall_efficiency(&exercise.keystroke_scores)
+                    .map(|efficiency| format!("{:.3}", efficiency))
+                    .unwrap_or_default(),
+                String::new(),
+                summary.finished_at.to_rfc3339(),
+            ]);
+        }
+    }
+    for attempt in &progress.challenges {
+        let status = if attempt.passed {
+            ExerciseStatus::Completed
+        } else {
+            ExerciseStatus::TimedOut
+        };
+        rows.push(vec![
+            learner.to_string(),
+            "challenge".to_string(),
+            serde_name(&crate::progress::LearningMode::Continuous),
+            attempt.chapter_number.to_string(),
+            String::new(),
+            attempt.exercise_title.clone(),
+            serde_name(&status),
+            format!("{:.1}", attempt.duration_secs),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            attempt.time_limit_secs.to_string(),
+            attempt.attempted_at.to_rfc3339(),
+        ]);
+    }
+    rows
+}
+
+pub fn render_csv(learner: &str, progress: &ProgressData) -> String {
+    let mut lines = vec![CSV_COLUMNS.join(",")];
+    lines.extend(csv_rows(learner, progress).iter().map(|row| {
+        row.iter()
+            .map(|value| csv_field(value))
+            .collect::<Vec<_>>()
+            .join(",")
+    }));
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+pub fn run_export(format: ExportFormat, out: Option<&Path>, learner: String) -> Result<()> {
+    let mut progress = ProgressStore::load_default().data().clone();
+    progress.paused = None; // 一時停止中の練習は端末ごとの状態なので含めない
+    progress.heartbeat = None;
+    let content = match format {
+        ExportFormat::Json => {
+            let export = ExportFile {
+                version: EXPORT_VERSION,
+                exercise_stats: exercise_stats(&progress),
+                learner: learner.clone(),
+                exported_at: Utc::now(),
+                progress,
+            };
+            serde_json::to_string_pretty(&export)? + "\n"
+        }
+        ExportFormat::Csv => render_csv(&learner, &progress),
+    };
+    match out {
+        Some(path) => {
+            fs::write(path, content)?;
+            say!("📤 {} の学習記録を書き出しました: {}", learner, path.display());
+        }
+        None => {
+            std::io::stdout().write_all(content.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+// エクスポートしたファイルを集計ファイルにまとめる（merge なら既存の集計に追加する）
+pub fn run_import(files: &[PathBuf], merge: bool, out: &Path) -> Result<()> {
+    let mut aggregate = if merge && out.exists() {
+        Aggregate::load(out)?
+    } else {
+        Aggregate::default()
+    };
+    for file in files {
+        let content = fs::read_to_string(file)
+            .map_err(|e| anyhow::anyhow!("{} を読めません: {}", file.display(), e))?;
+        let export: ExportFile = serde_json::from_str(&content).map_err(|e| {
+            anyhow::anyhow!("{} は export --format json の出力ではありません: {}", file.display(), e)
+        })?;
+        if export.version > EXPORT_VERSION {
+            return Err(anyhow::anyhow!(
+                "{} は新しい形式（版 {}）のため取り込めません",
+                file.display(),
+                export.version
+            ));
+        }
+        say!(
+            "📥 {}: {}（章の記録 {}件・チャレンジ {}件）",
+            file.display(),
+            export.learner,
+            export.progress.summaries.len(),
+            export.progress.challenges.len()
+        );
+        aggregate.merge(export);
+    }
+
+    if let Some(parent) = out.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(out, serde_json::to_string_pretty(&aggregate)?)?;
+    say!(
+        "📊 {}人分の集計を保存しました: {}（stats --aggregate で確認できます）",
+        aggregate.learners.len(),
+        out.display()
+    );
+    Ok(())
+}
+
+// 集計ファイルの内容を学習者ごと・練習ごとの表で表示する
+pub fn print_aggregate(path: &Path) -> Result<()> {
+    let aggregate = Aggregate::load(path)?;
+    if aggregate.learners.is_empty() {
+        say!("📊 集計に学習者がいません。");
+        return Ok(());
+    }
+    say!("📊 === 集計: {}人 ({}) ===", aggregate.learners.len(), path.display());
+    for learner in &aggregate.learners {
+        say!(
+            "\n👤 {}（章の記録 {}件・チャレンジ {}件、最終エクスポート {}）",
+            learner.learner,
+            learner.progress.summaries.len(),
+            learner.progress.challenges.len(),
+            learner
+                .last_exported_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+        );
+        for line in render_stats_table(&learner.exercise_stats) {
+            say!("  {}", line);
+        }
+    }
+    Ok(())
+}
+
+fn render_stats_table(stats: &[ExerciseStats]) -> Vec<String> {
+    let headers = ["章", "練習", "挑戦", "完了", "最短", "キー効率"];
+    let rows: Vec<[String; 6]> = stats
+        .iter()
+        .map(|s| {
+            [
+                s.chapter_number.to_string(),
+                s.exercise_title.clone(),
+                s.attempts.to_string(),
+                s.completions.to_string(),
+                s.best_duration_secs.map(summary::format_duration).unwrap_or_else(|| "-".to_string()),
+                s.best_keystroke_efficiency
+                    .map(|efficiency| format!("{:.0}%", efficiency * 100.0))
+                    .unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+    let mut widths = headers.map(UnicodeWidthStr::width);
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row.iter()) {
+            *w = (*w).max(UnicodeWidthStr::width(cell.as_str()));
+        }
+    }
+    let format_row = |cells: Vec<&str>| -> String {
+        cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, &w)| summary::pad(cell, w))
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_string()
+    };
+    let mut lines = vec![
+        format_row(headers.to_vec()),
+        widths.iter().map(|&w| "-".repeat(w)).collect::<Vec<_>>().join("-+-"),
+    ];
+    lines.extend(rows.iter().map(|row| format_row(row.iter().map(String::as_str).collect())));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keystrokes::KeystrokeScore;
+    use crate::progress::{ChallengeAttempt, LearningMode};
+    use crate::summary::{ChapterSummary, ExerciseSummary};
+    use chrono::TimeZone;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 14, hour, 0, 0).unwrap()
+    }
+
+    fn attempt(hour: u32, status: ExerciseStatus, duration_secs: f64) -> ChapterSummary {
+        ChapterSummary {
+            mode: LearningMode::Continuous,
+            chapter_number: 1,
+            chapter_title: "基本移動".to_string(),
+            finished_at: at(hour),
+            exercises: vec![
+                ExerciseSummary {
+                    title: "hjkl, 基本".to_string(),
+                    status,
+                    duration_secs,
+                    hints_used: 1,
+                    goals_skipped: 0,
+                    attempts: 1,
+                    keystroke_scores: vec![KeystrokeScore {
+                        label: "移動".to_string(),
+                        optimal: 2,
+                        actual: 4,
+                    }],
+                    content_hash: None,
+                },
+                ExerciseSummary {
+                    title: "未実施".to_string(),
+                    status: ExerciseStatus::NotStarted,
+                    duration_secs: 0.0,
+                    hints_used: 0,
+                    goals_skipped: 0,
+                    attempts: 1,
+                    keystroke_scores: Vec::new(),
+                    content_hash: None,
+                },
+            ],
+        }
+    }
+
+    fn export(learner: &str, summaries: Vec<ChapterSummary>) -> ExportFile {
+        let progress = ProgressData {
+            summaries,
+            ..Default::default()
+        };
+        ExportFile {
+            version: EXPORT_VERSION,
+            learner: learner.to_string(),
+            exported_at: at(12),
+            exercise_stats: exercise_stats(&progress),
+            progress,
+        }
+    }
+
+    #[test]
+    fn test_csv_has_one_row_per_attempt() {
+        let progress = ProgressData {
+            summaries: vec![attempt(9, ExerciseStatus::Completed, 42.0)],
+            challenges: vec![ChallengeAttempt {
+                chapter_number: 1,
+                exercise_title: "hjkl, 基本".to_string(),
+                time_limit_secs: 60,
+                duration_secs: 61.0,
+                passed: false,
+                attempted_at: at(10),
+            }],
+            ..Default::default()
+        };
+        let csv = render_csv("alice@pc1", &progress);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3); // 見出し + 章の練習1件（未実施は除く）+ チャレンジ1件
+        assert_eq!(lines[0].split(',').count(), CSV_COLUMNS.len());
+        assert_eq!(
+            lines[1],
+            "alice@pc1,chapter,continuous,1,基本移動,\"hjkl, 基本\",completed,42.0,1,0,2,4,0.500,,2026-10-14T09:00:00+00:00"
+        );
+        assert_eq!(
+            lines[2],
+            "alice@pc1,challenge,continuous,1,,\"hjkl, 基本\",timed_out,61.0,,,,,,60,2026-10-14T10:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_history_and_best_attempt() {
+        let mut aggregate = Aggregate::default();
+        aggregate.merge(export("bob@pc2", vec![attempt(9, ExerciseStatus::Incomplete, 80.0)]));
+        aggregate.merge(export(
+            "alice@pc1",
+            vec![attempt(9, ExerciseStatus::Completed, 50.0)],
+        ));
+        // 同じ学習者の別のエクスポート（同じ記録は重複させない）
+        aggregate.merge(export(
+            "alice@pc1",
+            vec![
+                attempt(9, ExerciseStatus::Completed, 50.0),
+                attempt(11, ExerciseStatus::Completed, 30.0),
+                attempt(10, ExerciseStatus::Failed, 5.0),
+            ],
+        ));
+
+        assert_eq!(aggregate.learners.len(), 2);
+        let alice = &aggregate.learners[0];
+        assert_eq!(alice.learner, "alice@pc1");
+        let history: Vec<DateTime<Utc>> =
+            alice.progress.summaries.iter().map(|s| s.finished_at).collect();
+        assert_eq!(history, vec![at(9), at(10), at(11)]);
+
+        let stats = &alice.exercise_stats;
+        assert_eq!(stats.len(), 1);
+        assert_eq!((stats[0].attempts, stats[0].completions), (3, 2));
+        assert_eq!(stats[0].best_duration_secs, Some(30.0));
+        assert_eq!(stats[0].best_attempt_at, Some(at(11)));
+        assert_eq!(stats[0].best_keystroke_efficiency, Some(0.5));
+
+        let bob = &aggregate.learners[1];
+        assert_eq!(bob.exercise_stats[0].best_duration_secs, None);
+    }
+
+    #[test]
+    fn test_export_file_round_trip_and_stats_table() -> Result<()> {
+        let file = export("alice@pc1", vec![attempt(9, ExerciseStatus::Completed, 75.0)]);
+        let json = serde_json::to_string(&file)?;
+        let parsed: ExportFile = serde_json::from_str(&json)?;
+        assert_eq!(parsed.exercise_stats, file.exercise_stats);
+
+        let lines = render_stats_table(&parsed.exercise_stats);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[2].contains("1分15秒"));
+        assert!(lines[2].ends_with("50%"));
+        assert!(default_learner_id().contains('@'));
+        Ok(())
+    }
+}
@@ -4,46 +4,54 @@ use std::fs::OpenOptions;
 use std::process::Command;
 use tempfile::NamedTempFile;
 // crossterm は使用しない（WSL環境で問題が発生するため）
+use crate::chapter_report::{self, ChapterReport};
+use crate::config::TutorialConfig;
 use crate::content::{ChapterData, ContentLoader, ExerciseData, StepData};
+use crate::editor;
+use crate::menu::{self, ChapterChoice, ChapterFilter, ExerciseChoice, ExerciseEntry};
+use crate::output::info;
+use crate::paths;
+use crate::progress::{self, LearningMode, ProgressStore};
+use crate::summary::{self, ChapterSummary, ExerciseStatus, ExerciseSummary, NextAction};
+use crate::error::TutorialError;
+use crate::theme::say;
+use crate::vim_backend::{self, InstructionSink, TmuxInstructionPane, temp_file};
+use crate::status_line;
+use crate::vim_state::VimMode;
+use crate::output::{DebugLog, debug_log};
 use std::io::{self, Write};
-use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 pub struct VimTutorialGame {
     content_loader: ContentLoader,
     current_chapter: Option<ChapterData>,
     current_exercise_index: usize,
     current_step_index: usize,
+    exercise_started_at: Instant,
+    chapter_started_at: chrono::DateTime<chrono::Utc>, // 結果ファイルに書く章の開始日時
+    exercise_summaries: Vec<ExerciseSummary>,
+    auto_advance: bool, // 章を完了したら確認せずに次の章へ進む
+    filter: Option<ChapterFilter>, // 章選択メニューの絞り込み
+    ignore_prereqs: bool,          // 前提の章が未完了でも選べるようにする
 }
 
-// デバッグログ用のマクロ
-macro_rules! debug_log {
-    ($($arg:tt)*) => {
-        let log_message = format!("[{}] 🔧 DEBUG: {}",
-            chrono::Utc::now().format("%Y-%m-%d %H:%M:%S%.3f"),
-            format!($($arg)*));
-
-        // ファイルにのみログを出力（コンソール出力は削除）
-        if let Ok(mut file) = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("/tmp/vim_tutorial_debug.log") {
-            let _ = writeln!(file, "{}", log_message);
-        }
-    };
-}
+// debug_log! の書き先
+const DEBUG_LOG: DebugLog = DebugLog::TUTORIAL;
 
 impl VimTutorialGame {
     pub fn new() -> Result<Self> {
-        let content_loader = ContentLoader::new()?;
+        let (content_loader, report) = ContentLoader::load()?;
+        report.print();
 
         // ログファイルを初期化
         if let Ok(mut file) = OpenOptions::new()
             .create(true)
             .write(true)
             .truncate(true)
-            .open("/tmp/vim_tutorial_debug.log")
+            .open(paths::cache_file("vim_tutorial_debug.log"))
         {
             let _ = writeln!(file, "=== Vim Tutorial Debug Log ===");
             let _ = writeln!(
@@ -60,12 +68,30 @@ impl VimTutorialGame {
             current_chapter: None,
             current_exercise_index: 0,
             current_step_index: 0,
+            exercise_started_at: Instant::now(),
+            chapter_started_at: chrono::Utc::now(),
+            exercise_summaries: Vec::new(),
+            auto_advance: false,
+            filter: None,
+            ignore_prereqs: false,
         })
     }
 
+    pub fn set_auto_advance(&mut self, auto_advance: bool) {
+        self.auto_advance = auto_advance;
+    }
+
+    pub fn set_filter(&mut self, filter: Option<ChapterFilter>) {
+        self.filter = filter;
+    }
+
+    pub fn set_ignore_prereqs(&mut self, ignore_prereqs: bool) {
+        self.ignore_prereqs = ignore_prereqs;
+    }
+
     pub fn run(&mut self) -> Result<()> {
-        println!("=== Vim Tutorial Game (Neovim版) ===\n");
-        println!("📄 デバッグログ: /tmp/vim_tutorial_debug.log");
+        say!("=== Vim Tutorial Game (Neovim版) ===\n");
+        info!("📄 デバッグログ: {}", temp_file("vim_tutorial_debug.log"));
         debug_log!("ゲーム開始");
 
         // 章選択メニューを表示
@@ -77,72 +103,119 @@ impl VimTutorialGame {
 
     fn show_chapter_menu(&mut self) -> Result<()> {
         loop {
-            println!("\n📚 === 章選択メニュー ===");
-            self.content_loader.list_chapters();
-
-            println!(
-                "章番号を選択してください (1-{}, q=終了):",
-                self.content_loader.get_chapter_count()
-            );
-            print!("選択: ");
-            io::stdout().flush()?;
-
-            let mut input = String::new();
-            match io::stdin().read_line(&mut input) {
-                Ok(0) => {
-                    // EOF - デモモードで第1章を実行
-                    println!("デモモード: 第1章を自動選択します。");
-                    return self.start_chapter(1);
+            say!("\n📚 === 章選択メニュー ===");
+            // 章を終えるたびに進捗が変わるため、毎回読み込み直す
+            let store = ProgressStore::load_default();
+            let ignore_prereqs = self.ignore_prereqs;
+            let is_complete =
+                |n| ignore_prereqs || store.is_chapter_complete(LearningMode::Classic, n);
+            let completed = |n| store.is_chapter_complete(LearningMode::Classic, n);
+            let chapter_numbers = self.content_loader.filtered_chapter_numbers(self.filter.as_ref());
+
+            // 端末では矢印キーで選ぶ一覧、それ以外（と ":" を押した場合）は番号を入力する
+            let chapter_lines = self.content_loader.menu_lines(self.filter.as_ref(), &is_complete, &completed);
+            let choice = match menu::choose_chapter("📚 章選択メニュー", &chapter_lines, self.filter.as_ref()) {
+                Some(choice) => Some(Ok(choice)),
+                None => {
+                    self.content_loader.list_chapters(self.filter.as_ref(), &is_complete);
+                    menu::read_chapter_choice(&chapter_numbers)
                 }
-                Ok(_) => {
-                    let input = input.trim();
-
-                    if input == "q" || input == "quit" {
-                        println!("ゲームを終了します。");
-                        break;
-                    }
-
-                    if let Ok(chapter_num) = input.parse::<u8>() {
-                        if chapter_num >= 1
-                            && chapter_num <= self.content_loader.get_chapter_count() as u8
-                        {
-                            return self.start_chapter(chapter_num);
-                        } else {
-                            println!(
-                                "❌ 無効な章番号です。1-{} の範囲で入力してください。",
-                                self.content_loader.get_chapter_count()
-                            );
+            };
+            match choice {
+                None => {
+                    // EOF - デモモードで最初の章を実行
+                    let first = self.content_loader.chapter_numbers()[0];
+                    say!("デモモード: 第{}章を自動選択します。", first);
+                    let start = self.choose_start_exercise(&store, first).unwrap_or(0);
+                    return self.start_chapter(first, start);
+                }
+                Some(choice) => match choice {
+                    Ok(ChapterChoice::Chapter(chapter_num)) => {
+                        let unmet = self.content_loader.unmet_prerequisites(chapter_num, &is_complete);
+                        if unmet.is_empty() {
+                            // q で章選択に戻る
+                            match self.choose_start_exercise(&store, chapter_num) {
+                                Some(start) => return self.start_chapter(chapter_num, start),
+                                None => continue,
+                            }
                         }
-                    } else {
-                        println!("❌ 数字または 'q' を入力してください。");
+                        say!("{}", menu::locked_message(chapter_num, &unmet));
                     }
-                }
-                Err(_) => {
-                    println!("デモモード: 第1章を自動選択します。");
-                    return self.start_chapter(1);
-                }
+                    Ok(ChapterChoice::Filter(filter)) => self.filter = filter,
+                    Ok(ChapterChoice::Quit) => {
+                        say!("ゲームを終了します。");
+                        break;
+                    }
+                    Err(message) => say!("{}", message),
+                },
             }
         }
 
         Ok(())
     }
 
-    fn start_chapter(&mut self, chapter_num: u8) -> Result<()> {
+    // 練習メニューで始める練習を選ぶ（練習が1つの章は選ばずに最初から。None は章選択に戻る）
+    fn choose_start_exercise(&self, store: &ProgressStore, chapter_num: u8) -> Option<usize> {
+        let chapter = self.content_loader.get_chapter(chapter_num)?;
+        if chapter.exercises.len() <= 1 {
+            return Some(0);
+        }
+        let entries: Vec<ExerciseEntry> = chapter
+            .exercises
+            .iter()
+            .map(|exercise| ExerciseEntry {
+                title: &exercise.title,
+                count: exercise.steps.len(),
+                completed: store.is_exercise_complete(LearningMode::Classic, chapter_num, &exercise.title),
+                content_changed: false,
+                best: None,
+            })
+            .collect();
+        let resumed = store.resume_exercises(LearningMode::Classic, chapter_num).len();
+        let resume_at = Some(resumed).filter(|&index| index > 0 && index < entries.len());
+        match menu::prompt_exercise_choice(&entries, "ステップ数", resume_at, false) {
+            ExerciseChoice::Exercise(index) => Some(index),
+            ExerciseChoice::Beginning => Some(0),
+            ExerciseChoice::Resume => resume_at,
+            ExerciseChoice::Back | ExerciseChoice::Lessons => None,
+        }
+    }
+
+    // start_exercise_index の練習の最初のステップから始める
+    // （それより前の練習は、前回の続きの記録があれば引き継ぎ、無ければ未実施として記録する）
+    fn start_chapter(&mut self, chapter_num: u8, start_exercise_index: usize) -> Result<()> {
         if let Some(chapter) = self.content_loader.get_chapter(chapter_num) {
+            let resumed = ProgressStore::load_default()
+                .resume_exercises(LearningMode::Classic, chapter.chapter.number);
             self.current_chapter = Some(chapter.clone());
-            self.current_exercise_index = 0;
+            self.current_exercise_index = start_exercise_index.min(chapter.exercises.len().saturating_sub(1));
             self.current_step_index = 0;
-
-            println!(
+            self.exercise_started_at = Instant::now();
+            self.chapter_started_at = chrono::Utc::now();
+            self.exercise_summaries = chapter
+                .exercises
+                .iter()
+                .take(self.current_exercise_index)
+                .enumerate()
+                .map(|(index, exercise)| {
+                    resumed
+                        .get(index)
+                        .filter(|record| record.title == exercise.title)
+                        .cloned()
+                        .unwrap_or_else(|| ExerciseSummary::not_started(&exercise.title))
+                })
+                .collect();
+
+            say!(
                 "\n🎯 === 第{}章: {} ===",
                 chapter.chapter.number, chapter.chapter.title
             );
-            println!("{}", chapter.chapter.description);
-            println!();
+            say!("{}", chapter.chapter.description);
+            say!();
 
             self.game_loop()?;
         } else {
-            println!("❌ 第{}章が見つかりません。", chapter_num);
+            say!("❌ 第{}章が見つかりません。", chapter_num);
         }
 
         Ok(())
@@ -151,105 +224,178 @@ impl VimTutorialGame {
     fn game_loop(&mut self) -> Result<()> {
         while let Some(chapter) = &self.current_chapter.clone() {
             if self.current_exercise_index >= chapter.exercises.len() {
-                println!(
+                say!(
                     "🎉 第{}章「{}」を完了しました！",
                     chapter.chapter.number, chapter.chapter.title
                 );
-                println!("\nお疲れ様でした！");
-                println!("\n他の章も学習してみましょう！");
+                say!("\nお疲れ様でした！");
+
+                let chapter_summary = ChapterSummary {
+                    mode: LearningMode::Classic,
+                    chapter_number: chapter.chapter.number,
+                    chapter_title: chapter.chapter.title.clone(),
+                    finished_at: chrono::Utc::now(),
+                    exercises: std::mem::take(&mut self.exercise_summaries),
+                };
+                chapter_report::save(&ChapterReport::new(&chapter_summary, self.chapter_started_at, None, None));
+                let next_chapter = progress::record_chapter_summary(
+                    chapter_summary.clone(),
+                    &self.content_loader.chapter_numbers(),
+                )
+                .and_then(|n| self.content_loader.get_chapter(n))
+                .map(|c| (c.chapter.number, c.chapter.title.as_str()));
+                summary::print_chapter_summary(&chapter_summary, next_chapter, TutorialConfig::load().display());
+                let next_action = summary::prompt_next_action(
+                    &chapter_summary,
+                    next_chapter.map(|(number, _)| number),
+                    self.auto_advance,
+                    false,
+                );
 
                 // 章の状態をリセット
                 self.current_chapter = None;
                 self.current_exercise_index = 0;
                 self.current_step_index = 0;
 
-                // メニューに戻る
-                return self.show_chapter_menu();
+                return match next_action {
+                    NextAction::Advance(number) => self.start_chapter(number, 0),
+                    NextAction::Quit => Ok(()),
+                    // 従来モードにドリルは無いのでメニューに戻る
+                    NextAction::Drill | NextAction::Menu => self.show_chapter_menu(),
+                };
             }
 
             let exercise = &chapter.exercises[self.current_exercise_index];
 
             if self.current_step_index >= exercise.steps.len() {
-                println!("✅ 練習「{}」を完了しました！", exercise.title);
+                say!("✅ 練習「{}」を完了しました！", exercise.title);
+                self.exercise_summaries.push(ExerciseSummary {
+                    title: exercise.title.clone(),
+                    status: ExerciseStatus::Completed,
+                    duration_secs: self.exercise_started_at.elapsed().as_secs_f64(),
+                    hints_used: 0,
+                    goals_skipped: 0,
+                    attempts: 1,
+                    keystroke_scores: Vec::new(),
+                    content_hash: None,
+                });
+                self.exercise_started_at = Instant::now();
                 self.current_exercise_index += 1;
                 self.current_step_index = 0;
-                println!("\n次の練習に進みます...\n");
+                say!("\n次の練習に進みます...\n");
                 continue;
             }
 
             let step = &exercise.steps[self.current_step_index];
 
             // 練習情報を表示
-            println!(
+            say!(
                 "📝 === 練習 {}/{}: {} ===",
                 self.current_exercise_index + 1,
                 chapter.exercises.len(),
                 exercise.title
             );
-            println!("{}", exercise.description);
-            println!();
+            say!("{}", exercise.description);
+            say!();
 
             // サンプルコードを表示
-            println!("サンプルコード:");
+            say!("サンプルコード:");
             for (i, line) in exercise.sample_code.iter().enumerate() {
-                println!("{:2}: {}", i + 1, line);
+                say!("{:2}: {}", i + 1, line);
             }
-            println!();
+            say!();
 
             // ステップ情報を表示
-            println!(
+            say!(
                 "📍 ステップ {}/{}: {}",
                 self.current_step_index + 1,
                 exercise.steps.len(),
                 step.instruction
             );
-            println!("💡 解説: {}", step.explanation);
-            println!("🎯 期待されるキー入力: {}", step.expected_input);
+            say!("💡 解説: {}", step.explanation);
+            say!("🎯 期待されるキー入力: {}", step.expected_input);
 
             // カーソル位置情報を表示
             if let Some(cursor_start) = step.cursor_start {
-                println!(
+                say!(
                     "📌 開始位置: {}行{}列",
                     cursor_start[0] + 1,
                     cursor_start[1] + 1
                 );
             }
             if let Some(cursor_end) = step.cursor_end {
-                println!(
+                say!(
                     "🎯 目標位置: {}行{}列",
                     cursor_end[0] + 1,
                     cursor_end[1] + 1
                 );
             }
-            println!();
-            println!("🚀 tmux分割画面でNeovimを起動します...");
-            println!("上下の画面が表示されます。下の画面で実際にVim操作を練習してください！");
-            println!();
-
-            // 直接インタラクティブモードで実行
-            if self.run_interactive_neovim(step)? {
+            say!();
+            say!("🚀 tmux分割画面でNeovimを起動します...");
+            say!("上下の画面が表示されます。下の画面で実際にVim操作を練習してください！");
+            say!();
+
+            // 直接インタラクティブモードで実行（止まった場合も、それまでの結果ファイルは書く）
+            let completed = match self.run_interactive_neovim(step) {
+                Ok(completed) => completed,
+                Err(error) => {
+                    self.save_partial_report(chapter, &error);
+                    return Err(error);
+                }
+            };
+            if completed {
                 self.current_step_index += 1;
-                println!("\n--- 次のステップ ---\n");
+                say!("\n--- 次のステップ ---\n");
             }
         }
 
         Ok(())
     }
 
+    // 途中の練習をエラー、それより後の練習を未実施とした結果ファイル
+    fn save_partial_report(&self, chapter: &ChapterData, error: &anyhow::Error) {
+        let current = &chapter.exercises[self.current_exercise_index];
+        let exercises = self
+            .exercise_summaries
+            .iter()
+            .cloned()
+            .chain(std::iter::once(ExerciseSummary {
+                status: ExerciseStatus::Failed,
+                duration_secs: self.exercise_started_at.elapsed().as_secs_f64(),
+                ..ExerciseSummary::not_started(&current.title)
+            }))
+            .chain(
+                chapter.exercises[self.current_exercise_index + 1..]
+                    .iter()
+                    .map(|exercise| ExerciseSummary::not_started(&exercise.title)),
+            )
+            .collect();
+        let summary = ChapterSummary {
+            mode: LearningMode::Classic,
+            chapter_number: chapter.chapter.number,
+            chapter_title: chapter.chapter.title.clone(),
+            finished_at: chrono::Utc::now(),
+            exercises,
+        };
+        chapter_report::save(&ChapterReport::new(
+            &summary,
+            self.chapter_started_at,
+            None,
+            Some(format!("{:#}", error)),
+        ));
+    }
+
     fn run_interactive_neovim(&self, step: &StepData) -> Result<bool> {
         if let Some(chapter) = &self.current_chapter {
             let exercise = &chapter.exercises[self.current_exercise_index];
 
-            // tmuxが利用可能かチェック
-            if Command::new("tmux").arg("-V").output().is_ok() {
-                println!("\n=== 🖥️ tmux分割画面モードで学習 ===");
-                println!("指示とNeovim操作を同時に確認できます");
-                return self.run_split_screen_neovim(exercise, step);
-            } else {
-                println!("❌ tmuxが利用できません。インストールしてください:");
-                println!("sudo apt install tmux  または  brew install tmux");
-                return Ok(false);
+            // tmuxが利用可能かチェック（無ければインストール方法を付けて終了する）
+            if !vim_backend::tmux_available() {
+                return Err(TutorialError::TmuxMissing.into());
             }
+            say!("\n=== 🖥️ tmux分割画面モードで学習 ===");
+            say!("指示とNeovim操作を同時に確認できます");
+            return self.run_split_screen_neovim(exercise, step);
         }
 
         Ok(false)
@@ -258,17 +404,19 @@ impl VimTutorialGame {
     // 不要なメソッドを削除（tmuxのみ使用）
 
     fn run_split_screen_neovim(&self, exercise: &ExerciseData, step: &StepData) -> Result<bool> {
-        println!("\n=== 🖥️  分割画面モードで練習 ===");
+        say!("\n=== 🖥️  分割画面モードで練習 ===");
         debug_log!("分割画面モード開始");
 
         // サンプルファイルを作成
         let sample_content = exercise.sample_code.join("\n");
-        let sample_file = NamedTempFile::new()?;
+        let sample_file = NamedTempFile::new_in(paths::cache_dir())?;
         fs::write(&sample_file, sample_content)?;
         debug_log!("サンプルファイル作成: {}", sample_file.path().display());
 
         // 状態監視用ファイル
-        let status_file = "/tmp/vim_tutorial_status.json";
+        let status_file = temp_file("vim_tutorial_status.json");
+        let status_file = status_file.as_str();
+        let _ = fs::remove_file(status_file); // 前回の練習の状態で到達と判定しないよう削除
         debug_log!("状態監視ファイル: {}", status_file);
 
         // カーソル開始位置を決定
@@ -282,24 +430,25 @@ impl VimTutorialGame {
         // Neovim設定スクリプトを作成（状態監視付き）
         let nvim_script = format!(
             r#"
-" 自動的にカーソル位置を監視（シンプル形式）
+" 自動的にカーソル位置を監視（連続学習モードと同じ形式）
+{status_functions}
 function! UpdateStatus()
-  let line_num = line('.')
-  let col_num = col('.')
-  let mode_str = mode()
-  let status_line = 'LINE:' . line_num . ',COL:' . col_num . ',MODE:' . mode_str
-  call writefile([status_line], '{}')
+  call writefile([TutorialStatusLine()], '{}')
 endfunction
 
 " カーソル移動時に状態更新
 autocmd CursorMoved,CursorMovedI * call UpdateStatus()
 autocmd InsertEnter,InsertLeave * call UpdateStatus()
+" ModeChanged は Vim 8.2.3430 より前には無い
+if exists('##ModeChanged')
+  autocmd ModeChanged * call UpdateStatus()
+endif
 
 " 初期状態を記録
 call UpdateStatus()
 
 " 指定された開始位置に移動（{}行{}列）
-call cursor({}, {})
+call TutorialSetCursor({}, {})
 
 " 起動完了メッセージ
 echo '🎯 学習開始！目標キー: {} | 開始位置: {}行{}列'
@@ -311,10 +460,11 @@ echo '🎯 学習開始！目標キー: {} | 開始位置: {}行{}列'
             start_col,
             step.expected_input,
             start_row,
-            start_col
+            start_col,
+            status_functions = status_line::vim_functions(),
         );
 
-        let script_file = NamedTempFile::new()?;
+        let script_file = NamedTempFile::new_in(paths::cache_dir())?;
         fs::write(&script_file, nvim_script)?;
         debug_log!("Vimスクリプト作成: {}", script_file.path().display());
 
@@ -346,10 +496,11 @@ echo '🎯 学習開始！目標キー: {} | 開始位置: {}行{}列'
 
         if !new_session_result.status.success() {
             debug_log!("セッション作成失敗により処理中断");
-            return Err(anyhow::anyhow!(
-                "tmuxセッション作成に失敗: {}",
-                String::from_utf8_lossy(&new_session_result.stderr)
-            ));
+            return Err(TutorialError::TmuxSessionFailed {
+                action: "セッション作成",
+                stderr: String::from_utf8_lossy(&new_session_result.stderr).into_owned(),
+            }
+            .into());
         }
 
         // 画面を水平分割（下側ペイン：Neovim）
@@ -365,10 +516,11 @@ echo '🎯 学習開始！目標キー: {} | 開始位置: {}行{}列'
 
         if !split_result.status.success() {
             debug_log!("画面分割失敗により処理中断");
-            return Err(anyhow::anyhow!(
-                "tmux画面分割に失敗: {}",
-                String::from_utf8_lossy(&split_result.stderr)
-            ));
+            return Err(TutorialError::TmuxSessionFailed {
+                action: "画面分割",
+                stderr: String::from_utf8_lossy(&split_result.stderr).into_owned(),
+            }
+            .into());
         }
 
         // ペイン一覧を取得してデバッグ
@@ -408,43 +560,16 @@ echo '🎯 学習開始！目標キー: {} | 開始位置: {}行{}列'
             .get(1)
             .ok_or_else(|| anyhow::anyhow!("下ペインが見つかりません"))?;
 
-        // 上側ペインで指示を表示（成功メッセージ監視付き）
+        // 上側ペインで指示を表示（内容は監視スレッドが描き直す。ペインでは何も判定しない）
         thread::sleep(Duration::from_millis(200));
-
-        // 成功フラグファイル
-        let success_flag = "/tmp/vim_tutorial_success.flag";
-        let _ = fs::remove_file(success_flag); // 既存のフラグを削除
-
-        let instruction_command = format!(
-            r#"bash -c "clear; echo '=== 🎯 学習目標 ==='; echo '📝 {}'; echo '💡 解説: {}'; echo '🎯 期待キー: {}'; echo ''; echo '=== 📊 カーソル位置監視 ==='; echo '目標位置: {}行{}列'; echo '下のNeovimで操作してください！完了したら :q で終了'; echo ''; echo '📍 現在の状態: 学習中...'; while true; do if [ -f {} ]; then clear; echo '=== 🎯 学習目標 ==='; echo '📝 {}'; echo '💡 解説: {}'; echo '🎯 期待キー: {}'; echo ''; echo '=== 🎉 成功！ ==='; echo '✨ 目標達成しました！{}行{}列に到達！'; echo '素晴らしい！次のステップに進みましょう。'; echo '下のNeovimで :q を入力して終了してください。'; rm {}; sleep 2; break; else sleep 0.2; fi; done""#,
-            step.instruction.replace("'", "'\"'\"'"),
-            step.explanation.replace("'", "'\"'\"'"),
-            step.expected_input.replace("'", "'\"'\"'"),
-            step.cursor_end.map(|c| c[0] + 1).unwrap_or(1),
-            step.cursor_end.map(|c| c[1] + 1).unwrap_or(1),
-            success_flag,
-            step.instruction.replace("'", "'\"'\"'"),
-            step.explanation.replace("'", "'\"'\"'"),
-            step.expected_input.replace("'", "'\"'\"'"),
-            step.cursor_end.map(|c| c[0] + 1).unwrap_or(1),
-            step.cursor_end.map(|c| c[1] + 1).unwrap_or(1),
-            success_flag
-        );
-
+        let mut instruction_pane = TmuxInstructionPane::new(top_pane);
         debug_log!("上ペイン({})に指示送信中...", top_pane);
-        debug_log!("指示コマンド: {}", instruction_command);
-        let instruction_result = Command::new("tmux")
-            .args(["send-keys", "-t", top_pane, &instruction_command, "Enter"])
-            .output()?;
-        debug_log!(
-            "指示送信結果: success={}, stderr={}",
-            instruction_result.status.success(),
-            String::from_utf8_lossy(&instruction_result.stderr)
-        );
+        instruction_pane.show(&Self::step_pane_lines(step, &Self::learning_lines(step)))?;
 
         // 下側ペインでNeovimを起動（終了時にtmuxも終了するように）
         let nvim_command = format!(
-            "nvim -S {} {}; tmux detach-client",
+            "{} -S {} {}; tmux detach-client",
+            editor::current().program(),
             script_file.path().display(),
             sample_file.path().display()
         );
@@ -466,10 +591,11 @@ echo '🎯 学習開始！目標キー: {} | 開始位置: {}行{}列'
         // Neovim起動失敗チェック
         if !nvim_result.status.success() {
             debug_log!("Neovim起動失敗により処理中断");
-            return Err(anyhow::anyhow!(
-                "Neovim起動に失敗: {}",
-                String::from_utf8_lossy(&nvim_result.stderr)
-            ));
+            return Err(TutorialError::PaneInjectionFailed {
+                pane: bottom_pane.clone(),
+                stderr: String::from_utf8_lossy(&nvim_result.stderr).into_owned(),
+            }
+            .into());
         }
 
         debug_log!("Neovim起動成功、アタッチ準備中...");
@@ -502,19 +628,27 @@ echo '🎯 学習開始！目標キー: {} | 開始位置: {}行{}列'
         );
 
         // tmuxセッションにアタッチ
-        println!("🚀 分割画面でNeovimを起動します...");
-        println!("上部：指示とリアルタイム監視");
-        println!("下部：Neovim操作画面");
-        println!("終了：下部のNeovimで :q");
+        say!("🚀 分割画面でNeovimを起動します...");
+        say!("上部：指示とリアルタイム監視");
+        say!("下部：Neovim操作画面");
+        say!("終了：下部のNeovimで :q");
 
-        // バックグラウンドで状態監視を開始
+        // バックグラウンドで状態監視を開始（到達を判定して上部ペインを描き直す）
         debug_log!("状態監視スレッド開始");
         let status_file_copy = status_file.to_string();
         let step_copy = step.clone();
-        let top_pane_copy = top_pane.clone();
-        thread::spawn(move || {
+        let stop_monitor = Arc::new(AtomicBool::new(false));
+        let stop_monitor_copy = Arc::clone(&stop_monitor);
+        let started_at = Instant::now();
+        let monitor = thread::spawn(move || {
             debug_log!("監視スレッド内開始");
-            Self::monitor_neovim_status(&status_file_copy, step_copy, &top_pane_copy);
+            Self::monitor_neovim_status(
+                &status_file_copy,
+                step_copy,
+                instruction_pane,
+                started_at,
+                &stop_monitor_copy,
+            )
         });
 
         // tmuxにアタッチ前の最終チェック
@@ -573,12 +707,12 @@ echo '🎯 学習開始！目標キー: {} | 開始位置: {}行{}列'
 
         // tmuxにアタッチ（分割表示を維持）
         debug_log!("tmuxセッションにアタッチ中...");
-        println!("📱 tmuxセッションに接続中... (Ctrl+Dまたは:detachで終了)");
-        println!("💡 上下の画面が表示されます。");
-        println!("📌 操作方法:");
-        println!("  - Ctrl+b ↑/↓: ペイン間の移動");
-        println!("  - 下のペインでVim操作を行ってください");
-        println!("  - 完了したら :q でVimを終了");
+        say!("📱 tmuxセッションに接続中... (Ctrl+Dまたは:detachで終了)");
+        say!("💡 上下の画面が表示されます。");
+        say!("📌 操作方法:");
+        say!("  - Ctrl+b ↑/↓: ペイン間の移動");
+        say!("  - 下のペインでVim操作を行ってください");
+        say!("  - 完了したら :q でVimを終了");
 
         // アタッチする前に画面をリフレッシュ
         let _ = Command::new("tmux")
@@ -609,102 +743,180 @@ echo '🎯 学習開始！目標キー: {} | 開始位置: {}行{}列'
             .output();
         debug_log!("セッション削除結果: {:?}", cleanup_result);
 
+        // 監視スレッドを止めて、達成までの時間を受け取る
+        stop_monitor.store(true, Ordering::Relaxed);
+        let reached_after = monitor.join().unwrap_or(None);
+
         // 状態ファイルを削除
         let _ = fs::remove_file(status_file);
-        let _ = fs::remove_file("/tmp/vim_tutorial_success.flag");
         debug_log!("状態ファイル削除完了");
 
         // ターミナルをクリア（元の画面に戻す）
         print!("\x1b[2J\x1b[H"); // 画面クリア + カーソルを左上に移動
         io::stdout().flush().unwrap_or(());
 
-        println!("=== 練習完了 ===");
-        println!("🎉 お疲れ様でした！分割画面での学習はいかがでしたか？");
+        say!("=== 練習完了 ===");
+        if let Some(elapsed) = reached_after {
+            say!("✨ {:.1}秒で目標を達成しました", elapsed.as_secs_f64());
+        }
+        say!("🎉 お疲れ様でした！分割画面での学習はいかがでしたか？");
         debug_log!("分割画面モード終了");
         Ok(true)
     }
 
     // 不要なメソッドを削除（tmuxのみ使用）
 
-    fn monitor_neovim_status(status_file: &str, step: StepData, _top_pane: &str) {
+    // 手順の指示（上部ペインの先頭に常に出す）と、その下の進み具合
+    fn step_pane_lines(step: &StepData, status: &[String]) -> Vec<String> {
+        let mut lines = vec![
+            "=== 🎯 学習目標 ===".to_string(),
+            format!("📝 {}", step.instruction),
+            format!("💡 解説: {}", step.explanation),
+            format!("🎯 期待キー: {}", step.expected_input),
+            String::new(),
+        ];
+        lines.extend_from_slice(status);
+        lines
+    }
+
+    fn target_description(step: &StepData) -> String {
+        let mut targets = Vec::new();
+        if let Some([line, col]) = step.cursor_end {
+            targets.push(format!("{}行{}列", line + 1, col + 1));
+        }
+        if let Some(mode) = &step.mode_change {
+            targets.push(format!("{}モード", mode));
+        }
+        targets.join("・")
+    }
+
+    fn learning_lines(step: &StepData) -> Vec<String> {
+        vec![
+            "=== 📊 カーソル位置監視 ===".to_string(),
+            format!("目標: {}", Self::target_description(step)),
+            format!(
+                "下の{}で操作してください！完了したら :q で終了",
+                editor::current().label()
+            ),
+            String::new(),
+            "📍 現在の状態: 学習中...".to_string(),
+        ]
+    }
+
+    fn success_lines(step: &StepData, elapsed: Duration) -> Vec<String> {
+        vec![
+            "=== 🎉 成功！ ===".to_string(),
+            format!(
+                "✨ 目標達成しました！{}に{:.1}秒で到達！",
+                Self::target_description(step),
+                elapsed.as_secs_f64()
+            ),
+            "素晴らしい！次のステップに進みましょう。".to_string(),
+            format!(
+                "下の{}で :q を入力して終了してください。",
+                editor::current().label()
+            ),
+        ]
+    }
+
+    // 状態ファイルを読んで到達を判定し、上部ペインを描き直す（stop が立つまで続け、到達までの時間を返す）
+    fn monitor_neovim_status(
+        status_file: &str,
+        step: StepData,
+        mut pane: TmuxInstructionPane,
+        started_at: Instant,
+        stop: &AtomicBool,
+    ) -> Option<Duration> {
         // 最初のログのみ出力
         debug_log!(
-            "状態監視開始 - 目標: {}行{}列",
+            "状態監視開始 - 目標: {}行{}列 モード: {}",
             step.cursor_end.map(|c| c[0] + 1).unwrap_or(1),
-            step.cursor_end.map(|c| c[1] + 1).unwrap_or(1)
+            step.cursor_end.map(|c| c[1] + 1).unwrap_or(1),
+            step.mode_change.as_deref().unwrap_or("指定なし")
         );
 
-        let mut last_position = (1, 1);
-        let mut success_triggered = false;
-        let target_position = if let Some(cursor_end) = step.cursor_end {
-            (cursor_end[0] as i32 + 1, cursor_end[1] as i32 + 1)
-        } else {
-            return; // 目標位置が設定されていない場合は監視しない
-        };
-
-        loop {
-            if let Ok(content) = fs::read_to_string(status_file) {
-                // シンプルな形式で解析: "LINE:1,COL:2,MODE:n"
-                for line in content.lines() {
-                    if line.starts_with("LINE:") {
-                        let parts: Vec<&str> = line.split(',').collect();
-                        if parts.len() >= 2
-                            && let (Ok(line_num), Ok(col_num)) = (
-                                parts[0].strip_prefix("LINE:").unwrap_or("1").parse::<i32>(),
-                                parts[1].strip_prefix("COL:").unwrap_or("1").parse::<i32>(),
-                            )
-                        {
-                            let current_position = (line_num, col_num);
-
-                            if current_position != last_position {
-                                // 位置変更時のみログ出力
-                                debug_log!("カーソル移動: {}行{}列", line_num, col_num);
-
-                                // 目標達成時の処理
-                                if current_position == target_position && !success_triggered {
-                                    debug_log!(
-                                        "🎉 目標達成！カーソル位置: {}行{}列",
-                                        line_num,
-                                        col_num
-                                    );
-
-                                    // 成功フラグファイルを作成
-                                    let success_flag = "/tmp/vim_tutorial_success.flag";
-                                    if let Ok(mut file) = OpenOptions::new()
-                                        .create(true)
-                                        .write(true)
-                                        .truncate(true)
-                                        .open(success_flag)
-                                    {
-                                        let _ = writeln!(file, "SUCCESS");
-                                        debug_log!("成功フラグファイル作成: {}", success_flag);
-                                    }
-                                    success_triggered = true;
-                                } else if current_position != target_position {
-                                    debug_log!(
-                                        "カーソル位置: {}行{}列 (目標: {}行{}列)",
-                                        line_num,
-                                        col_num,
-                                        target_position.0,
-                                        target_position.1
-                                    );
-                                }
-
-                                last_position = current_position;
-                            }
-                            break;
+        let target_position = step
+            .cursor_end
+            .map(|cursor_end| (cursor_end[0] + 1, cursor_end[1] + 1));
+        if target_position.is_none() && step.mode_change.is_none() {
+            return None; // 目標位置もモードも設定されていない場合は監視しない
+        }
+        let mut last_seen: Option<((usize, usize), VimMode)> = None;
+        let mut reached_after = None;
+
+        while !stop.load(Ordering::Relaxed) {
+            if reached_after.is_none()
+                && let Ok(content) = fs::read_to_string(status_file)
+                // 連続学習モードと共通の形式で解析する（status_line を参照）
+                && let Some(line) = content.lines().find(|line| status_line::is_status_line(line))
+            {
+                let state = status_line::parse(line);
+                let current_position = (state.cursor_line + 1, state.cursor_col + 1);
+                let current = (current_position, state.mode.clone());
+
+                if last_seen.as_ref() != Some(&current) {
+                    // 位置やモードが変わったときのみログ出力
+                    debug_log!(
+                        "カーソル移動: {}行{}列 モード: {:?}",
+                        current_position.0,
+                        current_position.1,
+                        state.mode
+                    );
+
+                    let position_reached =
+                        target_position.is_none_or(|target| target == current_position);
+                    let mode_reached = step
+                        .mode_change
+                        .as_deref()
+                        .is_none_or(|expected| Self::mode_matches(expected, &state.mode));
+
+                    // 目標達成時は上部ペインを成功の表示に描き直す
+                    if position_reached && mode_reached {
+                        let elapsed = started_at.elapsed();
+                        debug_log!(
+                            "🎉 目標達成！カーソル位置: {}行{}列 ({:.1}秒)",
+                            current_position.0,
+                            current_position.1,
+                            elapsed.as_secs_f64()
+                        );
+                        let lines =
+                            Self::step_pane_lines(&step, &Self::success_lines(&step, elapsed));
+                        if let Err(e) = pane.show(&lines) {
+                            debug_log!("上部ペインの更新に失敗: {}", e);
                         }
+                        reached_after = Some(elapsed);
+                    } else {
+                        debug_log!(
+                            "カーソル位置: {}行{}列 (目標: {:?} モード: {:?})",
+                            current_position.0,
+                            current_position.1,
+                            target_position,
+                            step.mode_change
+                        );
                     }
+
+                    last_seen = Some(current);
                 }
             }
 
             thread::sleep(Duration::from_millis(200));
+        }
+        debug_log!("状態監視終了");
+        reached_after
+    }
 
-            // ファイルが存在しなくなったら監視終了
-            if !Path::new(status_file).exists() {
-                debug_log!("状態監視終了");
-                break;
-            }
+    // 手順の mode_change（"Normal" / "Insert" / "Visual" / "Command"）に今のモードが当てはまるか
+    fn mode_matches(expected: &str, mode: &VimMode) -> bool {
+        match expected.to_ascii_lowercase().as_str() {
+            "normal" => *mode == VimMode::Normal,
+            "insert" => *mode == VimMode::Insert,
+            // V や Ctrl-V の手順も "Visual" と書いている
+            "visual" => matches!(
+                mode,
+                VimMode::Visual | VimMode::VisualLine | VimMode::VisualBlock
+            ),
+            "command" => *mode == VimMode::Command,
+            _ => true, // 知らない指定は判定に使わない
         }
     }
 }
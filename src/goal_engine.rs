@@ -0,0 +1,762 @@
+//! 練習の目標の進み方を決めるエンジンと、エンジンに渡す状態の列。
+//!
+//! [`GoalEngine`] は練習の定義（目標とフローの種類）から作り、時刻の付いたエディタの状態
+//! （[`TimedState`]）を受け取るたびに、目標の達成・次の目標の有効化・自動のヒント・練習の完了を
+//! [`GoalEvent`] として返します。エディタとの通信や画面の更新は行わないため、同じ状態の列からは
+//! 常に同じイベントの列が返ります。状態の列は [`StateStream`] から読みます（練習中はエディタから
+//! [`BackendStates`] で、テストでは記録した状態を [`RecordedStates`] で）。
+//!
+//! ```
+//! use std::time::Duration;
+//! use vim_tutorial_nvim::continuous_content::ContinuousContentLoader;
+//! use vim_tutorial_nvim::goal_engine::{GoalEngine, GoalEvent, HintDelays, RecordedStates, StateStream, StreamItem, TimedState};
+//! use vim_tutorial_nvim::vim_state::{GoalDetector, parse_status_content};
+//!
+//! let yaml = r#"
+//! chapter: { number: 1, title: "モード", description: "" }
+//! continuous_exercises:
+//!   - title: "i と Esc"
+//!     description: ""
+//!     flow_type: "sequential"
+//!     sample_code: ["hello"]
+//!     goals:
+//!       - { type: "mode", target: "insert", description: "Insert モードに入る" }
+//!       - { type: "mode", target: "normal", description: "Normal モードに戻る" }
+//! "#;
+//! let chapter = ContinuousContentLoader::parse_chapter(yaml, "chapter.yaml").unwrap();
+//! let delays = HintDelays { hint: Duration::from_secs(30), solution: Duration::from_secs(30) };
+//! let mut engine = GoalEngine::new(&chapter.continuous_exercises[0], delays).unwrap();
+//! let mut detector = GoalDetector::new();
+//!
+//! let at = |secs, status| TimedState { at: Duration::from_secs(secs), state: parse_status_content(status) };
+//! let mut states = RecordedStates::new(vec![
+//!     at(0, "LINE:1,COL:1,MODE:n"),
+//!     at(1, "LINE:1,COL:1,MODE:i"),
+//!     at(2, "LINE:1,COL:1,MODE:n"),
+//! ]);
+//! let mut events = Vec::new();
+//! while let StreamItem::Fresh(state) = states.next_state().unwrap() {
+//!     events.extend(engine.feed(&mut detector, &state));
+//! }
+//! assert_eq!(
+//!     events,
+//!     [
+//!         GoalEvent::Completed { index: 0 },
+//!         GoalEvent::Activated { index: 1 },
+//!         GoalEvent::Completed { index: 1 },
+//!         GoalEvent::ExerciseDone,
+//!     ]
+//! );
+//! ```
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::continuous_session::{ContinuousExercise, FlowType};
+use crate::keystrokes;
+use crate::vim_backend::{StateRead, VimBackend};
+use crate::vim_state::{Goal, GoalActivation, GoalCheck, GoalDetector, VimMode, VimState};
+
+/// 時刻の付いたエディタの状態。`at` は練習の開始からの経過で、学習者が画面を離れていた時間は含めない
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedState {
+    pub at: Duration,
+    pub state: VimState,
+}
+
+/// 状態の列から1回読んだ結果
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamItem {
+    /// 新しい状態
+    Fresh(TimedState),
+    /// 最後に届いてから新しい状態が無い（エディタが落ちた場合など）
+    Stale { state: VimState, age: Duration },
+    /// まだ一度も届いていない（エディタの起動中など）
+    Unavailable,
+    /// 列の終わり（記録した状態を読み終えた）
+    Ended,
+}
+
+/// エンジンに渡す状態の列
+pub trait StateStream {
+    fn next_state(&mut self) -> Result<StreamItem>;
+}
+
+/// エディタから読む状態の時計。練習の開始から数え、学習者が画面を離れている間は進めない
+#[derive(Debug, Clone)]
+pub struct BackendStates {
+    last_read: Instant,
+    elapsed: Duration,
+}
+
+impl Default for BackendStates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackendStates {
+    pub fn new() -> Self {
+        Self {
+            last_read: Instant::now(),
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// `backend` から読む状態の列
+    pub fn stream<'a>(&'a mut self, backend: &'a mut dyn VimBackend) -> BackendStream<'a> {
+        BackendStream { clock: self, backend }
+    }
+}
+
+/// [`BackendStates::stream`] の返す列
+pub struct BackendStream<'a> {
+    clock: &'a mut BackendStates,
+    backend: &'a mut dyn VimBackend,
+}
+
+impl StateStream for BackendStream<'_> {
+    fn next_state(&mut self) -> Result<StreamItem> {
+        Ok(match self.backend.get_state()? {
+            StateRead::Fresh(state) => {
+                let now = Instant::now();
+                let tick = now.duration_since(self.clock.last_read);
+                self.clock.last_read = now;
+                if self.backend.learner_attached() {
+                    self.clock.elapsed += tick;
+                }
+                StreamItem::Fresh(TimedState {
+                    at: self.clock.elapsed,
+                    state,
+                })
+            }
+            StateRead::Stale { state, age } => StreamItem::Stale { state, age },
+            StateRead::Unavailable => StreamItem::Unavailable,
+        })
+    }
+}
+
+/// 記録した状態の列（先頭から順に返し、読み終えたら [`StreamItem::Ended`]）
+#[derive(Debug, Clone, Default)]
+pub struct RecordedStates {
+    states: VecDeque<TimedState>,
+}
+
+impl RecordedStates {
+    pub fn new(states: Vec<TimedState>) -> Self {
+        Self { states: states.into() }
+    }
+}
+
+impl StateStream for RecordedStates {
+    fn next_state(&mut self) -> Result<StreamItem> {
+        Ok(self.states.pop_front().map_or(StreamItem::Ended, StreamItem::Fresh))
+    }
+}
+
+/// 状態が変わらないまま、ヒントの段階を自動で上げるまでの時間
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct HintDelays {
+    /// ヒントまで（目標の hint_delay_seconds が優先）
+    pub hint: Duration,
+    /// ヒントの後、解答（solution_keys のある目標だけ）まで
+    pub solution: Duration,
+}
+
+/// 状態を受け取ったときに起きたこと
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoalEvent {
+    /// 順番通りの練習で、次の目標が有効になった
+    Activated { index: usize },
+    Completed { index: usize },
+    /// 状態が変わらないまま時間がたち、ヒントの段階が上がった
+    Hinted { index: usize, level: usize },
+    /// 許していないモードに入ったため、目標をやり直しにした
+    ModeRefused { index: usize, mode: VimMode },
+    /// 目標に届いたが、決められたオペレーターを使っていないためやり直しにした
+    OperatorMissing { index: usize, operator: String },
+    /// 全ての目標を達成した
+    ExerciseDone,
+}
+
+// 目標の allowed_modes / required_operator を確かめるため、目標が有効になってから見た操作
+#[derive(Debug, Clone, Default)]
+struct GoalConduct {
+    disallowed_mode: Option<VimMode>, // 許していないモードに入ったまま
+    operators: Vec<&'static str>,     // 使ったオペレーター
+    // 許していない操作の後の状態（ここから変化して届くまで達成にしない）
+    rearm: Option<GoalActivation>,
+}
+
+/// 練習の目標の達成状況。状態を受け取り、フローの種類に従って目標を進める
+#[derive(Debug, Clone, Default)]
+pub struct GoalEngine {
+    flow_type: FlowType,
+    goals: Vec<Goal>,
+    allowed_modes: Vec<Vec<String>>,
+    required_operators: Vec<Option<String>>,
+    hint_delays: Vec<Duration>,
+    solution_delays: Vec<Option<Duration>>,
+    completed: Vec<bool>,
+    current: usize,
+    // 順番通りの練習で、現在の目標が有効になった時点の記録（None なら次に受け取った状態から）
+    activation: Option<GoalActivation>,
+    conduct: Vec<GoalConduct>,
+    hint_level: usize,
+    // 状態が変わらないまま過ぎた時間と、最後に数えた時刻（None なら次の状態から数える）
+    stuck: Duration,
+    last_tick: Option<Duration>,
+    // リセット直後の状態（ここから変化するまで目標を判定しない）
+    held: Option<VimState>,
+}
+
+impl GoalEngine {
+    pub fn new(exercise: &ContinuousExercise, delays: HintDelays) -> Result<Self> {
+        let goals = exercise.goals.iter().map(|goal_def| goal_def.to_goal()).collect::<Result<Vec<_>>>()?;
+        let count = goals.len();
+        Ok(Self {
+            flow_type: exercise.flow_type.clone(),
+            goals,
+            allowed_modes: (0..count).map(|i| exercise.allowed_modes_for(i).to_vec()).collect(),
+            required_operators: exercise.goals.iter().map(|goal_def| goal_def.required_operator.clone()).collect(),
+            hint_delays: exercise
+                .goals
+                .iter()
+                .map(|goal_def| goal_def.hint_delay_seconds.map_or(delays.hint, Duration::from_secs))
+                .collect(),
+            solution_delays: exercise
+                .goals
+                .iter()
+                .map(|goal_def| goal_def.solution_keys.as_ref().map(|_| delays.solution))
+                .collect(),
+            completed: vec![false; count],
+            current: 0,
+            activation: None,
+            conduct: vec![GoalConduct::default(); count],
+            hint_level: 0,
+            stuck: Duration::ZERO,
+            last_tick: Some(Duration::ZERO),
+            held: None,
+        })
+    }
+
+    /// 達成した目標（目標と同じ順）
+    pub fn completed(&self) -> &[bool] {
+        &self.completed
+    }
+
+    /// 現在の目標（最初の未達成の目標。全て達成したら目標の数）
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    /// 現在の目標で出しているヒントの段階（0 は無し、1 はヒント、2 は解答）
+    pub fn hint_level(&self) -> usize {
+        self.hint_level
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.completed.iter().all(|&done| done)
+    }
+
+    /// 判定の対象の目標（順番通りなら現在の目標だけ、それ以外は未達成の目標）
+    pub fn active_goals(&self) -> Vec<usize> {
+        match self.flow_type {
+            FlowType::Sequential if self.current < self.goals.len() => vec![self.current],
+            FlowType::Sequential => Vec::new(),
+            _ => (0..self.goals.len()).filter(|&i| !self.completed[i]).collect(),
+        }
+    }
+
+    /// 一時停止した時点の達成状況から続ける（練習と合わない・全て達成した記録なら false を返し、最初から）
+    pub fn restore(&mut self, completed: Vec<bool>) -> bool {
+        if completed.len() != self.goals.len() || completed.iter().all(|&done| done) {
+            return false;
+        }
+        self.current = completed.iter().position(|&done| !done).unwrap_or(0);
+        self.completed = completed;
+        self.activation = None;
+        true
+    }
+
+    // テスト用: 達成状況を直接決める
+    #[cfg(test)]
+    pub(crate) fn set_completed(&mut self, completed: Vec<bool>) {
+        self.current = completed.iter().position(|&done| !done).unwrap_or(completed.len());
+        self.completed = completed;
+    }
+
+    /// 全ての目標を未達成に戻す（順不同の練習のやり直し）
+    pub fn restart(&mut self) {
+        self.completed.iter_mut().for_each(|done| *done = false);
+        self.current = 0;
+        self.hint_level = 0;
+    }
+
+    /// 目標ごとに見てきた操作（使ったモード・オペレーター）を忘れる
+    pub fn clear_conduct(&mut self) {
+        self.conduct.iter_mut().for_each(|conduct| *conduct = GoalConduct::default());
+    }
+
+    /// 順番通りの練習で、現在の目標を `state` から変化したときに達成にする
+    pub fn activate_from(&mut self, detector: &GoalDetector, state: &VimState) {
+        self.activation = self.goals.get(self.current).map(|goal| GoalActivation::new(detector.observe(goal, state)));
+    }
+
+    /// まだ有効になった時点が無ければ `state` にする（順番通りの練習だけ）
+    pub fn arm(&mut self, detector: &GoalDetector, state: &VimState) {
+        if matches!(self.flow_type, FlowType::Sequential) && self.activation.is_none() {
+            self.activate_from(detector, state);
+        }
+    }
+
+    /// 次に受け取った状態から変化を待つ（一時停止や目標のコマンドの後）
+    pub fn wait_for_change(&mut self) {
+        self.activation = None;
+    }
+
+    /// `state` から位置・モード・内容が変わるまで目標を判定しない（リセット自体を達成扱いにしない）
+    pub fn hold_until_moved(&mut self, state: &VimState) {
+        self.held = Some(state.clone());
+    }
+
+    /// `state` で目標を判定してよいか。止めていた状態から変わっていれば、止めるのをやめる
+    pub fn accepts(&mut self, state: &VimState) -> bool {
+        if self.held.as_ref().is_some_and(|held| !Self::moved(held, state)) {
+            return false;
+        }
+        self.held = None;
+        true
+    }
+
+    // 位置・モード・内容のどれかが変わったか
+    fn moved(from: &VimState, to: &VimState) -> bool {
+        from.cursor_line != to.cursor_line
+            || from.cursor_col != to.cursor_col
+            || from.mode != to.mode
+            || from.changedtick != to.changedtick
+    }
+
+    /// 目標を1つ判定する（順番通りの練習では、有効になった後の変化だけで判定する）。達成にはしない
+    pub fn check(&mut self, detector: &GoalDetector, index: usize, state: &VimState) -> GoalCheck {
+        let goal = &self.goals[index];
+        match self.activation.as_mut() {
+            Some(activation) if matches!(self.flow_type, FlowType::Sequential) => {
+                detector.check_goal_since(goal, activation, state)
+            }
+            _ => detector.check_goal(goal, state),
+        }
+    }
+
+    // 次のヒント段階までの待ち時間（これ以上の段階が無ければ None）
+    fn next_hint_delay(&self) -> Option<Duration> {
+        match self.hint_level {
+            0 => self.hint_delays.get(self.current).copied(),
+            1 => self.solution_delays.get(self.current).copied().flatten(),
+            _ => None,
+        }
+    }
+
+    /// 次のヒント段階が自動で出るまでの残り時間
+    pub fn next_hint_in(&self) -> Option<Duration> {
+        self.next_hint_delay().map(|delay| delay.saturating_sub(self.stuck))
+    }
+
+    /// 学習者がヒントを求めた。次の段階があれば上げて true を返す
+    pub fn request_hint(&mut self) -> bool {
+        if self.next_hint_delay().is_none() {
+            return false;
+        }
+        self.hint_level += 1;
+        self.stuck = Duration::ZERO;
+        true
+    }
+
+    /// 状態が変わらない時間を数え直す
+    pub fn reset_hint_timer(&mut self) {
+        self.stuck = Duration::ZERO;
+    }
+
+    /// 次の状態までの時間をヒントの時間に数えない（一時停止から戻ったとき）
+    pub fn hold_hint_timer(&mut self) {
+        self.last_tick = None;
+    }
+
+    /// ヒントの時間を進める。`previous` から位置・モード・内容が変わっていれば数え直し、
+    /// 待ち時間を過ぎたら1段階だけヒントを上げる
+    pub fn tick(&mut self, now: &TimedState, previous: Option<&VimState>) -> Option<GoalEvent> {
+        let elapsed = self.last_tick.map_or(Duration::ZERO, |last| now.at.saturating_sub(last));
+        self.last_tick = Some(now.at);
+        self.stuck += elapsed;
+        if previous.is_some_and(|last| Self::moved(last, &now.state)) {
+            self.stuck = Duration::ZERO;
+        }
+        let delay = self.next_hint_delay()?;
+        if self.stuck < delay {
+            return None;
+        }
+        self.hint_level += 1;
+        self.stuck = Duration::ZERO;
+        Some(GoalEvent::Hinted {
+            index: self.current,
+            level: self.hint_level,
+        })
+    }
+
+    /// オペレーターを使った（未達成の全ての目標に数える）
+    pub fn record_operator(&mut self, operator: &'static str) {
+        for (conduct, _) in self.conduct.iter_mut().zip(&self.completed).filter(|(_, done)| !**done) {
+            conduct.operators.push(operator);
+        }
+    }
+
+    /// 有効な目標ごとに、許していないモードに入ったかを記録する。入ったら目標をやり直しにし、
+    /// 許したモードに戻った時点から数え直す
+    pub fn follow_conduct(&mut self, detector: &GoalDetector, state: &VimState) -> Vec<GoalEvent> {
+        // キー入力を記録できないエディタでも、オペレーター待機の状態が届けば使ったとみなす
+        if let VimMode::OperatorPending(operator) = &state.mode
+            && let Some(&known) = keystrokes::OPERATORS.iter().find(|known| **known == operator.as_str())
+        {
+            self.record_operator(known);
+        }
+        let mut events = Vec::new();
+        for index in self.active_goals() {
+            let goal = &self.goals[index];
+            let conduct = &mut self.conduct[index];
+            // やり直しの後は、目標に届いていない間の変化も数える
+            if let Some(rearm) = conduct.rearm.as_mut() {
+                detector.check_goal_since(goal, rearm, state);
+            }
+            let allowed = &self.allowed_modes[index];
+            if allowed.is_empty() {
+                continue;
+            }
+            match (&conduct.disallowed_mode, Self::mode_permitted(&state.mode, allowed)) {
+                (None, false) => {
+                    conduct.disallowed_mode = Some(state.mode.clone());
+                    events.push(GoalEvent::ModeRefused {
+                        index,
+                        mode: state.mode.clone(),
+                    });
+                }
+                (Some(_), true) => {
+                    *conduct = GoalConduct {
+                        rearm: Some(GoalActivation::new(detector.observe(goal, state))),
+                        ..GoalConduct::default()
+                    };
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+
+    // ノーマル・オペレーター待機・コマンドライン（:TutorialHint などを打つ）のモードは常に許す
+    fn mode_permitted(mode: &VimMode, allowed: &[String]) -> bool {
+        matches!(mode, VimMode::Normal | VimMode::OperatorPending(_) | VimMode::Command)
+            || allowed
+                .iter()
+                .filter_map(|name| VimMode::from_goal_name(name))
+                .any(|allowed_mode| std::mem::discriminant(&allowed_mode) == std::mem::discriminant(mode))
+    }
+
+    // 目標に届いた状態が、allowed_modes / required_operator も満たしているか。
+    // オペレーターを使わずに届いた場合はやり直しにし、その状態から変化するまで達成にしない
+    fn permits(&mut self, index: usize, detector: &GoalDetector, state: &VimState, events: &mut Vec<GoalEvent>) -> bool {
+        let goal = &self.goals[index];
+        let Some(conduct) = self.conduct.get_mut(index) else {
+            return true;
+        };
+        if conduct.disallowed_mode.is_some() {
+            return false;
+        }
+        if let Some(rearm) = conduct.rearm.as_mut()
+            && !detector.check_goal_since(goal, rearm, state).is_met()
+        {
+            return false;
+        }
+        let Some(operator) = &self.required_operators[index] else {
+            return true;
+        };
+        if conduct.operators.iter().any(|used| used == operator) {
+            return true;
+        }
+        *conduct = GoalConduct {
+            rearm: Some(GoalActivation::new(detector.observe(goal, state))),
+            ..GoalConduct::default()
+        };
+        events.push(GoalEvent::OperatorMissing {
+            index,
+            operator: operator.clone(),
+        });
+        false
+    }
+
+    /// 状態を1つ受け取り、フローの種類に従って目標を判定する
+    pub fn feed(&mut self, detector: &mut GoalDetector, now: &TimedState) -> Vec<GoalEvent> {
+        let state = &now.state;
+        let previous_index = self.current;
+        let previously_completed = self.completed.clone();
+        let mut events = Vec::new();
+        let mut progressed = false;
+
+        match self.flow_type {
+            FlowType::Sequential => {
+                let index = self.current;
+                if index < self.goals.len() {
+                    // 目標が有効になった後の変化だけで判定する（先回りして満たした状態では達成にしない）
+                    let goal = &self.goals[index];
+                    let activation = self
+                        .activation
+                        .get_or_insert_with(|| GoalActivation::new(detector.observe(goal, state)));
+                    let met = detector.check_goal_since(goal, activation, state).is_met();
+                    if met && self.permits(index, detector, state, &mut events) {
+                        self.completed[index] = true;
+                        progressed = true;
+                        detector.clear_motions();
+                        // 次の目標はこの時点の状態から変化を待つ
+                        self.activation = self
+                            .goals
+                            .get(index + 1)
+                            .map(|next| GoalActivation::new(detector.observe(next, state)));
+                    }
+                }
+            }
+            FlowType::AnyOrder => {
+                for index in 0..self.goals.len() {
+                    if self.completed[index] {
+                        continue;
+                    }
+                    if detector.check_goal(&self.goals[index], state).is_met()
+                        && self.permits(index, detector, state, &mut events)
+                    {
+                        self.completed[index] = true;
+                        progressed = true;
+                    }
+                }
+            }
+            FlowType::Parallel => {
+                // 全ての目標を同時に満たす必要がある
+                let all_satisfied = (0..self.goals.len()).all(|index| {
+                    detector.check_goal(&self.goals[index], state).is_met()
+                        && self.permits(index, detector, state, &mut events)
+                });
+                if all_satisfied {
+                    self.completed.iter_mut().for_each(|done| *done = true);
+                    progressed = true;
+                }
+            }
+        }
+        if !progressed {
+            return events;
+        }
+
+        // 表示用の現在の目標 = 最初の未達成の目標
+        self.current = self.completed.iter().position(|&done| !done).unwrap_or(self.goals.len());
+        events.extend(
+            (0..self.goals.len())
+                .filter(|&i| self.completed[i] && !previously_completed[i])
+                .map(|index| GoalEvent::Completed { index }),
+        );
+        if self.current != previous_index {
+            self.hint_level = 0;
+            if matches!(self.flow_type, FlowType::Sequential) && self.current < self.goals.len() {
+                self.conduct[self.current] = GoalConduct::default();
+                events.push(GoalEvent::Activated { index: self.current });
+            }
+        }
+        self.stuck = Duration::ZERO;
+        if self.is_done() {
+            events.push(GoalEvent::ExerciseDone);
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuous_session::ExerciseGoal;
+    use crate::vim_state::parse_status_content;
+    use serde_json::json;
+
+    fn exercise(flow_type: FlowType, goals: Vec<ExerciseGoal>) -> ContinuousExercise {
+        ContinuousExercise {
+            title: "記録".to_string(),
+            sample_code: vec!["hello world".to_string()],
+            goals,
+            flow_type,
+            ..Default::default()
+        }
+    }
+
+    fn goal(goal_type: &str, target: serde_json::Value) -> ExerciseGoal {
+        ExerciseGoal {
+            goal_type: goal_type.to_string(),
+            target,
+            description: goal_type.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn delays() -> HintDelays {
+        HintDelays {
+            hint: Duration::from_secs(10),
+            solution: Duration::from_secs(5),
+        }
+    }
+
+    // 記録した状態の列（"秒 状態" の行）を最後まで流し、起きたイベントを返す
+    fn replay(engine: &mut GoalEngine, records: &[(u64, &str)]) -> Vec<GoalEvent> {
+        let mut detector = GoalDetector::new();
+        let mut states = RecordedStates::new(
+            records
+                .iter()
+                .map(|&(secs, status)| TimedState {
+                    at: Duration::from_secs(secs),
+                    state: parse_status_content(status),
+                })
+                .collect(),
+        );
+        let mut events = Vec::new();
+        let mut previous: Option<VimState> = None;
+        while let StreamItem::Fresh(now) = states.next_state().unwrap() {
+            events.extend(engine.tick(&now, previous.as_ref()));
+            events.extend(engine.follow_conduct(&detector, &now.state));
+            events.extend(engine.feed(&mut detector, &now));
+            previous = Some(now.state);
+        }
+        events
+    }
+
+    #[test]
+    fn test_sequential_waits_for_change_after_activation() {
+        let mut engine = GoalEngine::new(
+            &exercise(FlowType::Sequential, vec![goal("position", json!([0, 3])), goal("position", json!([0, 3]))]),
+            delays(),
+        )
+        .unwrap();
+        let events = replay(
+            &mut engine,
+            &[
+                (0, "LINE:1,COL:1,MODE:n"),
+                (1, "LINE:1,COL:4,MODE:n"),
+                // 2つ目の目標は同じ位置だが、有効になった後に動くまで達成にしない
+                (2, "LINE:1,COL:4,MODE:n"),
+                (3, "LINE:1,COL:5,MODE:n"),
+                (4, "LINE:1,COL:4,MODE:n"),
+            ],
+        );
+        assert_eq!(
+            events,
+            [
+                GoalEvent::Completed { index: 0 },
+                GoalEvent::Activated { index: 1 },
+                GoalEvent::Completed { index: 1 },
+                GoalEvent::ExerciseDone,
+            ]
+        );
+        assert_eq!(engine.current_index(), 2);
+    }
+
+    #[test]
+    fn test_any_order_and_parallel_flows() {
+        let goals = || vec![goal("position", json!([0, 3])), goal("mode", json!("insert"))];
+        let mut any_order = GoalEngine::new(&exercise(FlowType::AnyOrder, goals()), delays()).unwrap();
+        let events = replay(&mut any_order, &[(0, "LINE:1,COL:1,MODE:i"), (1, "LINE:1,COL:4,MODE:n")]);
+        assert_eq!(
+            events,
+            [
+                GoalEvent::Completed { index: 1 },
+                GoalEvent::Completed { index: 0 },
+                GoalEvent::ExerciseDone
+            ]
+        );
+
+        // 同時に満たすまで、片方だけでは達成にしない
+        let mut parallel = GoalEngine::new(&exercise(FlowType::Parallel, goals()), delays()).unwrap();
+        let events = replay(
+            &mut parallel,
+            &[(0, "LINE:1,COL:4,MODE:n"), (1, "LINE:1,COL:1,MODE:i"), (2, "LINE:1,COL:4,MODE:i")],
+        );
+        assert_eq!(
+            events,
+            [
+                GoalEvent::Completed { index: 0 },
+                GoalEvent::Completed { index: 1 },
+                GoalEvent::ExerciseDone
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hints_rise_while_state_stays_the_same() {
+        let mut goals = vec![goal("position", json!([0, 5]))];
+        goals[0].solution_keys = Some("4l".to_string());
+        let mut engine = GoalEngine::new(&exercise(FlowType::Sequential, goals), delays()).unwrap();
+        let events = replay(
+            &mut engine,
+            &[
+                (0, "LINE:1,COL:1,MODE:n"),
+                (6, "LINE:1,COL:1,MODE:n"),
+                // 動くと数え直す
+                (8, "LINE:1,COL:2,MODE:n"),
+                (17, "LINE:1,COL:2,MODE:n"),
+                (18, "LINE:1,COL:2,MODE:n"),
+                (23, "LINE:1,COL:2,MODE:n"),
+                (60, "LINE:1,COL:2,MODE:n"),
+            ],
+        );
+        assert_eq!(
+            events,
+            [GoalEvent::Hinted { index: 0, level: 1 }, GoalEvent::Hinted { index: 0, level: 2 }]
+        );
+        assert_eq!(engine.next_hint_in(), None);
+        assert!(!engine.request_hint());
+    }
+
+    #[test]
+    fn test_conduct_refusals_are_events() {
+        let mut goals = vec![
+            ExerciseGoal {
+                required_operator: Some("d".to_string()),
+                ..goal("buffer", json!(["world"]))
+            },
+            ExerciseGoal {
+                allowed_modes: vec!["insert".to_string()],
+                ..goal("position", json!([0, 2]))
+            },
+        ];
+        goals[1].description = "動く".to_string();
+        let mut engine = GoalEngine::new(&exercise(FlowType::AnyOrder, goals), delays()).unwrap();
+        let events = replay(
+            &mut engine,
+            &[
+                (0, "LINE:1,COL:1,MODE:v"),
+                (1, "LINE:1,COL:1,MODE:n"),
+                (2, "LINE:1,COL:3,MODE:n\nBUF:[\"world\"]"),
+            ],
+        );
+        assert_eq!(
+            events,
+            [
+                GoalEvent::ModeRefused { index: 1, mode: VimMode::Visual },
+                GoalEvent::OperatorMissing { index: 0, operator: "d".to_string() },
+                GoalEvent::Completed { index: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_held_state_is_not_judged_until_it_moves() {
+        let mut engine = GoalEngine::new(&exercise(FlowType::Sequential, vec![goal("mode", json!("normal"))]), delays()).unwrap();
+        let reset = parse_status_content("LINE:1,COL:1,MODE:n");
+        assert!(engine.accepts(&reset));
+
+        engine.hold_until_moved(&reset);
+        assert!(!engine.accepts(&reset));
+        assert!(engine.accepts(&parse_status_content("LINE:1,COL:2,MODE:n")));
+        // 一度動いたら、元の状態に戻っても判定する
+        assert!(engine.accepts(&reset));
+    }
+}
@@ -0,0 +1,603 @@
+//! 目標の種類（章データの目標の `type`）の登録。
+//!
+//! 組み込みの種類（`position`・`text` など）も、ライブラリを使う側が足す種類も、名前で登録した
+//! [`GoalKind`] が target を読んで [`GoalCheckable`] にします。独自の種類は練習のセッションを作る前に
+//! [`register`] で登録すると、章データの読み込み・検証・判定で組み込みの種類と同じように使えます。
+//!
+//! ```
+//! use anyhow::Result;
+//! use serde_json::{Value, json};
+//! use vim_tutorial_nvim::goal_registry::{self, GoalCheckable, GoalContext, GoalKind};
+//! use vim_tutorial_nvim::vim_state::{GoalCheck, GoalGap};
+//!
+//! // カーソルが target の行より下にある
+//! #[derive(Debug)]
+//! struct Below(usize);
+//!
+//! impl GoalCheckable for Below {
+//!     fn check(&self, ctx: &GoalContext) -> GoalCheck {
+//!         let line_delta = self.0 as i64 + 1 - ctx.state.cursor_line as i64;
+//!         match line_delta <= 0 {
+//!             true => GoalCheck::Met,
+//!             false => GoalCheck::Unmet { reason: GoalGap::Position { line_delta, col_delta: 0 } },
+//!         }
+//!     }
+//! }
+//!
+//! struct BelowKind;
+//!
+//! impl GoalKind for BelowKind {
+//!     fn parse(&self, target: &Value) -> Result<Box<dyn GoalCheckable>> {
+//!         let line = target.as_u64().ok_or_else(|| anyhow::anyhow!("行の番号で書きます"))?;
+//!         Ok(Box::new(Below(line as usize)))
+//!     }
+//! }
+//!
+//! goal_registry::register("below_line", BelowKind).unwrap();
+//! assert!(goal_registry::is_registered("below_line"));
+//! // 組み込みの種類と同じ名前は登録できない
+//! assert!(goal_registry::register("position", BelowKind).is_err());
+//! assert!(goal_registry::parse("below_line", &json!("2")).is_err());
+//! ```
+use anyhow::{Result, anyhow};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::continuous_session::GOAL_TYPES;
+use crate::keystrokes;
+use crate::normalize::Normalize;
+use crate::vim_rpc::VimRpcClient;
+use crate::vim_state::{GoalCheck, GoalDetector, GoalType, RegisterMatch, TextMatch, VimMode, VimState};
+
+/// 判定の最中にエディタへ問い合わせる手段（Vim の式を評価させ、結果を文字列で返す）
+pub trait GoalQuery {
+    fn eval(&self, expr: &str) -> Result<String>;
+}
+
+impl GoalQuery for VimRpcClient {
+    fn eval(&self, expr: &str) -> Result<String> {
+        self.eval_expr(expr)
+    }
+}
+
+/// 目標を判定するときに渡すもの
+pub struct GoalContext<'a> {
+    /// 今の状態
+    pub state: &'a VimState,
+    /// 1つ前に判定したときの状態（最初の判定では None）
+    pub previous: Option<&'a VimState>,
+    /// 目標が有効になってから学習者が押したキーのログ（1行 = "モード\tキー列"）
+    pub key_log: &'a [String],
+    /// エディタへの問い合わせ（式を評価させられないエディタでは None）
+    pub rpc: Option<&'a dyn GoalQuery>,
+    /// 移動・. ・置換などの記録（組み込みの種類の判定に使う）
+    pub detector: &'a GoalDetector,
+}
+
+/// target を読んだ後の目標
+pub trait GoalCheckable: fmt::Debug + Send + Sync {
+    fn check(&self, ctx: &GoalContext) -> GoalCheck;
+
+    /// 章データで description を省いたときの説明（None なら種類の名前から作る）
+    fn description(&self) -> Option<String> {
+        None
+    }
+
+    /// カーソルやモードを見る目標か（file を付けた目標で、別のファイルを表示している間は満たさない）
+    fn follows_cursor(&self) -> bool {
+        false
+    }
+
+    /// 目標が有効になった時点の記録。これが変わるまでは満たしていても達成にしない
+    /// （既定はカーソル・モード・changedtick）
+    fn observe(&self, ctx: &GoalContext) -> String {
+        let state = ctx.state;
+        format!("{}:{}:{:?}:{}", state.cursor_line, state.cursor_col, state.mode, state.changedtick)
+    }
+
+    /// 組み込みの種類の目標（独自の種類では None）
+    fn builtin(&self) -> Option<&GoalType> {
+        None
+    }
+}
+
+// 組み込みの種類は GoalType のまま判定する（記録を使う種類があるため GoalDetector が受け持つ）。
+// 独自の種類を入れた GoalType::Custom は、登録した種類が読んだ目標で判定する
+impl GoalCheckable for GoalType {
+    fn check(&self, ctx: &GoalContext) -> GoalCheck {
+        match self {
+            GoalType::Custom(custom) => custom.goal.check(ctx),
+            _ => ctx.detector.check_builtin(self, ctx.state),
+        }
+    }
+
+    fn description(&self) -> Option<String> {
+        Some(self.default_description())
+    }
+
+    fn follows_cursor(&self) -> bool {
+        GoalType::follows_cursor(self)
+    }
+
+    fn builtin(&self) -> Option<&GoalType> {
+        Some(self)
+    }
+}
+
+/// 目標の種類。章データの target を読んで目標にする（target の形が誤っていればエラー）
+pub trait GoalKind: Send + Sync {
+    fn parse(&self, target: &Value) -> Result<Box<dyn GoalCheckable>>;
+}
+
+/// 独自の種類の目標（[`GoalType::Custom`] に入れる）
+#[derive(Clone)]
+pub struct CustomGoal {
+    pub kind: String,
+    pub goal: Arc<dyn GoalCheckable>,
+}
+
+impl fmt::Debug for CustomGoal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({:?})", self.kind, self.goal)
+    }
+}
+
+// 同じ種類で、読んだ target が同じなら同じ目標
+impl PartialEq for CustomGoal {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && format!("{:?}", self.goal) == format!("{:?}", other.goal)
+    }
+}
+
+type ParseBuiltin = fn(&Value) -> Result<GoalType>;
+
+// 組み込みの種類（関数で target を GoalType にする）
+struct Builtin(ParseBuiltin);
+
+impl GoalKind for Builtin {
+    fn parse(&self, target: &Value) -> Result<Box<dyn GoalCheckable>> {
+        Ok(Box::new((self.0)(target)?))
+    }
+}
+
+type Registry = BTreeMap<String, Arc<dyn GoalKind>>;
+
+fn registry() -> &'static RwLock<Registry> {
+    static REGISTRY: OnceLock<RwLock<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let builtins: [(&str, ParseBuiltin); 17] = [
+            ("position", position),
+            ("mode", mode),
+            ("text", text),
+            ("register", register_content),
+            ("text_block", text_block),
+            ("buffer", buffer),
+            ("buffer_change", |_| Ok(GoalType::BufferChange)),
+            ("buffer_active", buffer_active),
+            ("counted_motion", counted_motion),
+            ("window_count", window_count),
+            ("window_layout", window_layout),
+            ("substitution", substitution),
+            ("undo_performed", |_| Ok(GoalType::UndoPerformed)),
+            ("redo_performed", |_| Ok(GoalType::RedoPerformed)),
+            ("dot_repeat", dot_repeat),
+            ("operator_textobject", operator_textobject),
+            ("syntax_node", syntax_node),
+        ];
+        let kinds = builtins
+            .into_iter()
+            .map(|(name, parse)| (name.to_string(), Arc::new(Builtin(parse)) as Arc<dyn GoalKind>))
+            .collect();
+        RwLock::new(kinds)
+    })
+}
+
+/// 目標の種類を name で登録する（組み込みの種類や登録済みの名前とは重ねられない）
+pub fn register(name: &str, kind: impl GoalKind + 'static) -> Result<()> {
+    let mut kinds = registry().write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if kinds.contains_key(name) || GOAL_TYPES.contains(&name) {
+        return Err(anyhow!("目標の種類「{}」は既にあります", name));
+    }
+    kinds.insert(name.to_string(), Arc::new(kind));
+    Ok(())
+}
+
+/// name の種類があるか（position_at は練習の開始時に position にするため含めない）
+pub fn is_registered(name: &str) -> bool {
+    registry().read().unwrap_or_else(|poisoned| poisoned.into_inner()).contains_key(name)
+}
+
+/// 登録されている種類の名前（名前の順）
+pub fn names() -> Vec<String> {
+    registry().read().unwrap_or_else(|poisoned| poisoned.into_inner()).keys().cloned().collect()
+}
+
+/// name の種類で target を読む
+pub fn parse(name: &str, target: &Value) -> Result<Box<dyn GoalCheckable>> {
+    let kind = registry()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(name)
+        .cloned()
+        .ok_or_else(|| anyhow!("Unknown goal type: {}", name))?;
+    kind.parse(target)
+}
+
+/// name の種類で target を読み、判定用の [`GoalType`] にする
+pub fn goal_type(name: &str, target: &Value) -> Result<GoalType> {
+    let goal = parse(name, target)?;
+    Ok(match goal.builtin() {
+        Some(goal_type) => goal_type.clone(),
+        None => GoalType::Custom(CustomGoal {
+            kind: name.to_string(),
+            goal: Arc::from(goal),
+        }),
+    })
+}
+
+pub(crate) fn required_u64(target: &Map<String, Value>, key: &str) -> Result<u64> {
+    target
+        .get(key)
+        .and_then(|value| value.as_u64())
+        .ok_or_else(|| anyhow!("Target field '{}' must be a non-negative integer", key))
+}
+
+pub(crate) fn required_str(target: &Map<String, Value>, key: &str) -> Result<String> {
+    target
+        .get(key)
+        .and_then(|value| value.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("Target field '{}' must be a string", key))
+}
+
+// 文字列の配列で書かれた複数行の目標を読む
+fn string_lines(value: &Value) -> Result<Vec<String>> {
+    let items = value
+        .as_array()
+        .ok_or_else(|| anyhow!("Expected lines must be an array of strings"))?;
+    items
+        .iter()
+        .map(|item| {
+            item.as_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("Expected line must be a string: {}", item))
+        })
+        .collect()
+}
+
+fn object<'a>(target: &'a Value, what: &str) -> Result<&'a Map<String, Value>> {
+    target
+        .as_object()
+        .ok_or_else(|| anyhow!("{} target must be an object", what))
+}
+
+fn position(target: &Value) -> Result<GoalType> {
+    let pair = target
+        .as_array()
+        .ok_or_else(|| anyhow!("Position target must be an array"))?;
+    let [line, col] = pair.as_slice() else {
+        return Err(anyhow!("Position target must be [line, column]: {}", target));
+    };
+    let (Some(line), Some(col)) = (line.as_u64(), col.as_u64()) else {
+        return Err(anyhow!("Position target must contain non-negative integers: {}", target));
+    };
+    Ok(GoalType::Position {
+        line: line as usize,
+        col: col as usize,
+    })
+}
+
+fn mode(target: &Value) -> Result<GoalType> {
+    let mode_str = target
+        .as_str()
+        .ok_or_else(|| anyhow!("Mode target must be a string"))?;
+    let vim_mode = VimMode::from_goal_name(mode_str).ok_or_else(|| anyhow!("Unknown mode: {}", mode_str))?;
+    Ok(GoalType::Mode(vim_mode))
+}
+
+fn text(target: &Value) -> Result<GoalType> {
+    let target = object(target, "Text")?;
+    let line = required_u64(target, "line")? as usize;
+    let expected = required_str(target, "expected")?;
+    let match_mode = match target.get("match_mode").and_then(|m| m.as_str()) {
+        None => TextMatch::default(),
+        Some(name) => TextMatch::from_name(name).ok_or_else(|| anyhow!("Unknown text match mode: {}", name))?,
+    };
+    let normalize = normalize(target)?;
+    if match_mode == TextMatch::Regex {
+        regex::Regex::new(&expected).map_err(|e| anyhow!("Invalid text regex '{}': {}", expected, e))?;
+        if !normalize.is_none() {
+            return Err(anyhow!("normalize cannot be used with the regex match mode"));
+        }
+    }
+    Ok(GoalType::TextContent {
+        line,
+        expected,
+        match_mode,
+        normalize,
+    })
+}
+
+// テキストの目標の target.normalize（"width"・"quotes"・"trailing_space" の並び。無ければ正規化しない）
+fn normalize(target: &Map<String, Value>) -> Result<Normalize> {
+    let Some(names) = target.get("normalize") else {
+        return Ok(Normalize::default());
+    };
+    let names = names
+        .as_array()
+        .and_then(|names| names.iter().map(Value::as_str).collect::<Option<Vec<_>>>())
+        .ok_or_else(|| anyhow!("normalize must be a list of strings"))?;
+    Normalize::from_names(names).map_err(|name| {
+        anyhow!("Unknown normalize option: {} (use {})", name, crate::normalize::NAMES.join(", "))
+    })
+}
+
+fn register_content(target: &Value) -> Result<GoalType> {
+    let target = object(target, "Register")?;
+    let register = required_str(target, "register")?;
+    let expected = required_str(target, "expected")?;
+    let match_mode = match target.get("match").and_then(|m| m.as_str()) {
+        None => RegisterMatch::default(),
+        Some(name) => RegisterMatch::from_name(name).ok_or_else(|| anyhow!("Unknown register match: {}", name))?,
+    };
+    if match_mode == RegisterMatch::Regex {
+        regex::Regex::new(&expected).map_err(|e| anyhow!("Invalid register regex '{}': {}", expected, e))?;
+    }
+    Ok(GoalType::RegisterContent {
+        register,
+        expected,
+        match_mode,
+    })
+}
+
+fn text_block(target: &Value) -> Result<GoalType> {
+    let target = object(target, "Text block")?;
+    let start_line = required_u64(target, "start_line")? as usize;
+    let expected = string_lines(target.get("expected").unwrap_or(&Value::Null))?;
+    let normalize = normalize(target)?;
+    Ok(GoalType::TextBlock {
+        start_line,
+        expected,
+        normalize,
+    })
+}
+
+fn buffer(target: &Value) -> Result<GoalType> {
+    Ok(GoalType::BufferEquals {
+        expected: string_lines(target)?,
+    })
+}
+
+fn dot_repeat(target: &Value) -> Result<GoalType> {
+    let target = object(target, "Dot repeat")?;
+    let min_times = required_u64(target, "min_times")? as usize;
+    if min_times == 0 {
+        return Err(anyhow!("Dot repeat min_times must be at least 1"));
+    }
+    Ok(GoalType::DotRepeatUsed { min_times })
+}
+
+fn operator_textobject(target: &Value) -> Result<GoalType> {
+    let target = object(target, "Operator text object")?;
+    let operator = required_str(target, "operator")?;
+    let text_object = required_str(target, "text_object")?;
+    if !keystrokes::OPERATORS.contains(&operator.as_str()) {
+        return Err(anyhow!("Unknown operator: {}", operator));
+    }
+    if !keystrokes::is_text_object(&text_object) {
+        return Err(anyhow!("Unknown text object: {}", text_object));
+    }
+    Ok(GoalType::OperatorTextObject { operator, text_object })
+}
+
+// node と language は treesitter の名前（英数字と _）だけを受け付ける（エディタへの問い合わせに埋め込むため）
+fn syntax_node(target: &Value) -> Result<GoalType> {
+    let target = object(target, "Syntax node")?;
+    let is_name = |name: &str| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let node = required_str(target, "node")?;
+    if !is_name(&node) {
+        return Err(anyhow!("Syntax node must be a treesitter node type: {}", node));
+    }
+    let language = match target.get("language") {
+        None => None,
+        Some(_) => Some(required_str(target, "language")?),
+    };
+    if let Some(language) = &language
+        && !is_name(language)
+    {
+        return Err(anyhow!("Syntax node language must be a treesitter language name: {}", language));
+    }
+    Ok(GoalType::SyntaxNode { node, language })
+}
+
+fn buffer_active(target: &Value) -> Result<GoalType> {
+    let target = object(target, "Buffer active")?;
+    Ok(GoalType::BufferActive {
+        name: required_str(target, "name")?,
+    })
+}
+
+fn counted_motion(target: &Value) -> Result<GoalType> {
+    let target = object(target, "Counted motion")?;
+    let motion = required_str(target, "motion")?;
+    let min_count = required_u64(target, "min_count")? as usize;
+    if min_count < 2 {
+        return Err(anyhow!("Counted motion min_count must be at least 2: {}", min_count));
+    }
+    // 着地する位置（省略可。[行, 列]）
+    let position = match target.get("position") {
+        None => None,
+        Some(position) => {
+            let pair = position.as_array().and_then(|pair| match pair.as_slice() {
+                [line, col] => line.as_u64().zip(col.as_u64()),
+                _ => None,
+            });
+            let (line, col) =
+                pair.ok_or_else(|| anyhow!("Counted motion position must be [line, column]: {}", position))?;
+            Some((line as usize, col as usize))
+        }
+    };
+    Ok(GoalType::CountedMotion {
+        motion,
+        min_count,
+        position,
+    })
+}
+
+fn window_count(value: &Value) -> Result<GoalType> {
+    let target = object(value, "Window count")?;
+    let expected = required_u64(target, "expected")? as usize;
+    // 今いるウィンドウの番号（省略可。上または左から1始まり）
+    let current = match target.get("current") {
+        None => None,
+        Some(_) => Some(required_u64(target, "current")? as usize),
+    };
+    if expected == 0 || current.is_some_and(|current| current == 0 || current > expected) {
+        return Err(anyhow!("Window count must be at least 1 and current within it: {}", value));
+    }
+    Ok(GoalType::WindowCount { expected, current })
+}
+
+fn window_layout(target: &Value) -> Result<GoalType> {
+    let target = object(target, "Window layout")?;
+    let min_splits = required_u64(target, "min_splits")? as usize;
+    if min_splits == 0 {
+        return Err(anyhow!("Window layout min_splits must be at least 1"));
+    }
+    Ok(GoalType::WindowLayout { min_splits })
+}
+
+fn substitution(target: &Value) -> Result<GoalType> {
+    let target = object(target, "Substitution")?;
+    let pattern = required_str(target, "pattern")?;
+    if pattern.is_empty() {
+        return Err(anyhow!("Substitution pattern must not be empty"));
+    }
+    let replacement = required_str(target, "replacement")?;
+    let min_count = required_u64(target, "min_count")? as usize;
+    if min_count == 0 {
+        return Err(anyhow!("Substitution min_count must be at least 1"));
+    }
+    // 置き換える範囲（省略可。[最初の行, 最後の行]、0始まり）
+    let lines = match target.get("lines") {
+        None => None,
+        Some(lines) => {
+            let pair = lines.as_array().and_then(|pair| match pair.as_slice() {
+                [first, last] => first.as_u64().zip(last.as_u64()),
+                _ => None,
+            });
+            match pair {
+                Some((first, last)) if first <= last => Some((first as usize, last as usize)),
+                _ => {
+                    return Err(anyhow!(
+                        "Substitution lines must be [first, last] with first <= last: {}",
+                        lines
+                    ));
+                }
+            }
+        }
+    };
+    Ok(GoalType::SubstitutionResult {
+        pattern,
+        replacement,
+        min_count,
+        lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuous_session::ExerciseGoal;
+    use crate::vim_state::GoalGap;
+    use serde_json::json;
+
+    // 前の判定から下へ動いたときに満たす（ctx の前の状態とキーログを使う）
+    #[derive(Debug)]
+    struct MovedDown;
+
+    impl GoalCheckable for MovedDown {
+        fn check(&self, ctx: &GoalContext) -> GoalCheck {
+            let moved = ctx.previous.is_some_and(|previous| ctx.state.cursor_line > previous.cursor_line);
+            let typed = ctx.key_log.iter().any(|line| line.ends_with('j'));
+            match moved && typed {
+                true => GoalCheck::Met,
+                false => GoalCheck::Unmet {
+                    reason: GoalGap::NoChangeSinceActivation,
+                },
+            }
+        }
+
+        fn description(&self) -> Option<String> {
+            Some("j で下へ".to_string())
+        }
+    }
+
+    struct MovedDownKind;
+
+    impl GoalKind for MovedDownKind {
+        fn parse(&self, _target: &Value) -> Result<Box<dyn GoalCheckable>> {
+            Ok(Box::new(MovedDown))
+        }
+    }
+
+    #[test]
+    fn test_registered_kind_is_read_and_checked_like_builtin() -> Result<()> {
+        register("moved_down_test", MovedDownKind)?;
+        assert!(register("moved_down_test", MovedDownKind).is_err());
+        assert!(names().iter().any(|name| name == "moved_down_test"));
+
+        let mut goal_def = ExerciseGoal {
+            goal_type: "moved_down_test".to_string(),
+            target: json!(null),
+            ..Default::default()
+        };
+        goal_def.fill_description();
+        assert_eq!(goal_def.description, "j で下へ");
+        let goal = goal_def.to_goal()?;
+
+        let mut detector = GoalDetector::new();
+        let mut state = crate::vim_state::parse_status_content("LINE:1,COL:1,MODE:n");
+        assert!(!detector.check_goal(&goal, &state).is_met());
+        detector.record_checked_state(&state);
+        detector.record_key_log(&["n\tj".to_string()]);
+        state.cursor_line = 1;
+        assert!(detector.check_goal(&goal, &state).is_met());
+        // キーログは次の目標では数え直す
+        detector.clear_motions();
+        assert!(!detector.check_goal(&goal, &state).is_met());
+        Ok(())
+    }
+
+    #[test]
+    fn test_builtin_kind_is_checked_through_the_registry() -> Result<()> {
+        let goal = parse("position", &json!([1, 0]))?;
+        let detector = GoalDetector::new();
+        let away = crate::vim_state::parse_status_content("LINE:1,COL:1,MODE:n");
+        let there = crate::vim_state::parse_status_content("LINE:2,COL:1,MODE:n");
+        let ctx = |state| GoalContext {
+            state,
+            previous: None,
+            key_log: &[],
+            rpc: None,
+            detector: &detector,
+        };
+        assert!(matches!(
+            goal.check(&ctx(&away)).gap(),
+            Some(GoalGap::Position { line_delta: 1, col_delta: 0 })
+        ));
+        assert!(goal.check(&ctx(&there)).is_met());
+        Ok(())
+    }
+
+    #[test]
+    fn test_builtin_kinds_cover_goal_types() {
+        for name in GOAL_TYPES.iter().filter(|name| **name != "position_at") {
+            assert!(is_registered(name), "{}", name);
+        }
+        assert_eq!(goal_type("position", &json!([2, 3])).unwrap(), GoalType::Position { line: 2, col: 3 });
+        assert_eq!(parse("no_such_goal", &json!(null)).unwrap_err().to_string(), "Unknown goal type: no_such_goal");
+    }
+}
@@ -0,0 +1,244 @@
+// 練習・章を完了したときに、設定の on_exercise_complete / on_chapter_complete のコマンドを実行する。
+// 結果の記録（ExerciseSummary / ChapterSummary）の JSON を標準入力に渡し、章番号・練習の題名・時間は環境変数でも渡す。
+// コマンドは sh -c で実行し、hook_timeout_seconds 秒で終わらなければ止める。失敗してもセッションは続ける
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::TutorialConfig;
+use crate::output::{DebugLog, debug_log, warning};
+use crate::summary::{ChapterSummary, ExerciseSummary};
+
+// debug_log! の書き先
+const DEBUG_LOG: DebugLog = DebugLog::HOOK;
+
+// 終わったかを確かめる間隔
+const WAIT_INTERVAL: Duration = Duration::from_millis(20);
+// エラーのメッセージに付ける標準エラーの長さ（文字数）
+const MAX_STDERR_CHARS: usize = 200;
+
+pub struct Hooks {
+    on_exercise_complete: Option<String>,
+    on_chapter_complete: Option<String>,
+    timeout: Duration,
+    // 失敗を警告したか（2回目からはデバッグログにだけ書く）
+    reported: bool,
+}
+
+impl Hooks {
+    // 設定に書いたフックだけを実行する（空の文字列は書いていないものとする）
+    pub fn from_config(config: &TutorialConfig) -> Self {
+        let command = |hook: &Option<String>| hook.as_ref().filter(|command| !command.trim().is_empty()).cloned();
+        Self {
+            on_exercise_complete: command(&config.on_exercise_complete),
+            on_chapter_complete: command(&config.on_chapter_complete),
+            timeout: Duration::from_secs(config.hook_timeout_seconds),
+            reported: false,
+        }
+    }
+
+    pub fn exercise_completed(&mut self, chapter_number: u8, record: &ExerciseSummary) {
+        let Some(command) = self.on_exercise_complete.clone() else {
+            return;
+        };
+        let env = [
+            ("VIM_TUTORIAL_EVENT", "exercise_complete".to_string()),
+            ("VIM_TUTORIAL_CHAPTER", chapter_number.to_string()),
+            ("VIM_TUTORIAL_EXERCISE", record.title.clone()),
+            ("VIM_TUTORIAL_DURATION_SECS", format!("{:.1}", record.duration_secs)),
+        ];
+        self.run("on_exercise_complete", &command, record, &env);
+    }
+
+    pub fn chapter_completed(&mut self, summary: &ChapterSummary) {
+        let Some(command) = self.on_chapter_complete.clone() else {
+            return;
+        };
+        let duration: f64 = summary.exercises.iter().map(|e| e.duration_secs).sum();
+        let env = [
+            ("VIM_TUTORIAL_EVENT", "chapter_complete".to_string()),
+            ("VIM_TUTORIAL_CHAPTER", summary.chapter_number.to_string()),
+            ("VIM_TUTORIAL_CHAPTER_TITLE", summary.chapter_title.clone()),
+            ("VIM_TUTORIAL_DURATION_SECS", format!("{:.1}", duration)),
+        ];
+        self.run("on_chapter_complete", &command, summary, &env);
+    }
+
+    fn run(&mut self, name: &str, command: &str, payload: &impl Serialize, env: &[(&str, String)]) {
+        let result = serde_json::to_string(payload)
+            .map_err(|e| anyhow!("結果を JSON にできません: {}", e))
+            .and_then(|payload| run_hook(command, &payload, env, self.timeout));
+        match result {
+            Ok(()) => {
+                debug_log!("{} を実行しました: {}", name, command);
+            }
+            Err(e) => {
+                debug_log!("{} が失敗しました: {}: {}", name, command, e);
+                if !self.reported {
+                    self.reported = true;
+                    warning!("⚠️ フック {} が失敗しました（学習はそのまま続けます）: {}", name, e);
+                }
+            }
+        }
+    }
+}
+
+// command を sh -c で実行し、payload を標準入力に渡す。timeout を過ぎたら止めてエラーにする
+pub fn run_hook(command: &str, payload: &str, env: &[(&str, String)], timeout: Duration) -> Result<()> {
+    let mut command_line = Command::new("sh");
+    command_line
+        .args(["-c", command])
+        .envs(env.iter().map(|(key, value)| (*key, value)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    // 止めるときにコマンドが起動したプロセスもまとめて止められるよう、別のプロセスグループで起動する
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command_line, 0);
+    let mut child = command_line.spawn().map_err(|e| anyhow!("起動できません: {}", e))?;
+
+    // 標準入力を読まないコマンドでも止まらないよう、書き込みと標準エラーの読み取りは別スレッドで行う
+    let mut stdin = child.stdin.take();
+    let payload = payload.to_string();
+    thread::spawn(move || {
+        if let Some(stdin) = stdin.as_mut() {
+            let _ = stdin.write_all(payload.as_bytes());
+        }
+    });
+    let mut stderr = child.stderr.take();
+    let stderr_reader = thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(stderr) = stderr.as_mut() {
+            let _ = stderr.read_to_string(&mut text);
+        }
+        text
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            kill_group(&mut child);
+            let _ = child.wait();
+            return Err(anyhow!("{}秒で終わらなかったため止めました", timeout.as_secs()));
+        }
+        thread::sleep(WAIT_INTERVAL);
+    };
+    if status.success() {
+        return Ok(());
+    }
+    let stderr = stderr_reader.join().unwrap_or_default();
+    let stderr: String = stderr.trim().chars().take(MAX_STDERR_CHARS).collect();
+    let code = status.code().map_or_else(|| "シグナル".to_string(), |code| code.to_string());
+    if stderr.is_empty() {
+        Err(anyhow!("終了コード {}", code))
+    } else {
+        Err(anyhow!("終了コード {}: {}", code, stderr))
+    }
+}
+
+// コマンドをプロセスグループごと止める
+#[cfg(unix)]
+fn kill_group(child: &mut Child) {
+    use nix::sys::signal::{Signal, killpg};
+    use nix::unistd::Pid;
+
+    if killpg(Pid::from_raw(child.id() as i32), Signal::SIGKILL).is_err() {
+        let _ = child.kill();
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_group(child: &mut Child) {
+    let _ = child.kill();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::summary::ExerciseStatus;
+    use std::fs;
+
+    fn record() -> ExerciseSummary {
+        ExerciseSummary {
+            status: ExerciseStatus::Completed,
+            duration_secs: 12.5,
+            ..ExerciseSummary::not_started("hjkl, 基本")
+        }
+    }
+
+    #[test]
+    fn test_exercise_hook_receives_payload_and_env() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let script = dir.path().join("record.sh");
+        let stdin_path = dir.path().join("stdin.json");
+        let env_path = dir.path().join("env.txt");
+        fs::write(
+            &script,
+            format!(
+                "cat > '{}'\nprintf '%s|%s|%s' \"$VIM_TUTORIAL_CHAPTER\" \"$VIM_TUTORIAL_EXERCISE\" \
+                 \"$VIM_TUTORIAL_DURATION_SECS\" > '{}'\n",
+                stdin_path.display(),
+                env_path.display()
+            ),
+        )?;
+        let config = TutorialConfig {
+            on_exercise_complete: Some(format!("sh '{}'", script.display())),
+            ..TutorialConfig::default()
+        };
+        let mut hooks = Hooks::from_config(&config);
+        hooks.exercise_completed(3, &record());
+
+        let payload: serde_json::Value = serde_json::from_str(&fs::read_to_string(&stdin_path)?)?;
+        assert_eq!(payload["title"], "hjkl, 基本");
+        assert_eq!(payload["status"], serde_json::to_value(ExerciseStatus::Completed)?);
+        assert_eq!(fs::read_to_string(&env_path)?, "3|hjkl, 基本|12.5");
+        assert!(!hooks.reported);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failing_and_slow_hooks_are_errors() {
+        let err = run_hook("echo 'no such webhook' >&2; exit 3", "{}", &[], Duration::from_secs(5)).unwrap_err();
+        assert_eq!(err.to_string(), "終了コード 3: no such webhook");
+
+        let started = Instant::now();
+        let err = run_hook("sleep 5", "{}", &[], Duration::from_millis(200)).unwrap_err();
+        assert!(err.to_string().contains("終わらなかったため止めました"));
+        assert!(started.elapsed() < Duration::from_secs(3));
+
+        // コマンドが起動したプロセスも止める
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("marker");
+        let command = format!("(sleep 0.5; touch '{}') & wait", marker.display());
+        assert!(run_hook(&command, "{}", &[], Duration::from_millis(100)).is_err());
+        thread::sleep(Duration::from_millis(1000));
+        assert!(!marker.exists());
+
+        // 標準入力を読まないコマンドでも待ち続けない
+        assert!(run_hook("true", &"x".repeat(1 << 20), &[], Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn test_hooks_are_off_unless_configured() {
+        let mut hooks = Hooks::from_config(&TutorialConfig {
+            on_chapter_complete: Some("  ".to_string()),
+            ..TutorialConfig::default()
+        });
+        assert!(hooks.on_exercise_complete.is_none() && hooks.on_chapter_complete.is_none());
+        hooks.exercise_completed(1, &record());
+        assert!(!hooks.reported);
+
+        let mut hooks = Hooks::from_config(&TutorialConfig {
+            on_exercise_complete: Some("exit 1".to_string()),
+            ..TutorialConfig::default()
+        });
+        hooks.exercise_completed(1, &record());
+        assert!(hooks.reported);
+    }
+}
@@ -0,0 +1,594 @@
+use serde::{Deserialize, Serialize};
+
+// キー入力効率の採点
+//
+// 練習用Neovimは学習者が実際に打ったキー（マッピング展開前）を
+// 「モード<TAB>キー列」の形式でキーログに記録する。数え方は次の通り:
+// - ノーマル・ビジュアル・オペレータ待機・コマンドラインモードのキーは全て1キーとして数える
+//   （カウント数字、<Esc>、: で始まるコマンドの各文字と <CR> を含む）
+// - 挿入・置換モードで入力した文字（表示可能な文字、<Space> <Tab> <CR> <BS> <Del>）は数えない。
+//   テキストを打つ目標が文章の長さで不利にならないようにするため。
+//   挿入モード中でも <Esc> や矢印キー、<C-o> などの操作キーは数える
+// - マウス操作やフォーカス変化などキーボード以外のイベントは数えない
+// - :TutorialHint / :TutorialReset の入力は練習用Neovim側で記録から除く
+
+// 挿入系モードで文字入力として扱う特殊キー
+const TEXT_INPUT_KEYS: &[&str] = &["<Space>", "<Tab>", "<CR>", "<BS>", "<Del>", "<lt>"];
+
+// キーボード以外のイベントとして扱うキー名の一部
+const NON_KEYBOARD_EVENTS: &[&str] = &["Mouse", "ScrollWheel", "Focus", "Ignore", "Cursor"];
+
+// keytrans 形式のキー列を1キーずつに分割する（例: "d<Esc>w" -> ["d", "<Esc>", "w"]）
+pub fn split_keys(keys: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut rest = keys;
+    while let Some(c) = rest.chars().next() {
+        if c == '<'
+            && let Some(end) = rest.find('>')
+            && end > 1
+        {
+            result.push(rest[..=end].to_string());
+            rest = &rest[end + 1..];
+            continue;
+        }
+        result.push(c.to_string());
+        rest = &rest[c.len_utf8()..];
+    }
+    result
+}
+
+fn is_insert_mode(mode: &str) -> bool {
+    mode.starts_with('i') || mode.starts_with('R')
+}
+
+// 1キーが効率の計算対象になるか
+pub fn counts_toward_score(mode: &str, key: &str) -> bool {
+    if key.starts_with('<') && NON_KEYBOARD_EVENTS.iter().any(|event| key.contains(event)) {
+        return false;
+    }
+    if is_insert_mode(mode) {
+        let is_text = key.chars().count() == 1 || TEXT_INPUT_KEYS.contains(&key);
+        return !is_text;
+    }
+    true
+}
+
+// キーログ1行（"モード\tキー列"）のうち数えるキーの数
+pub fn count_log_line(line: &str) -> usize {
+    let Some((mode, keys)) = line.split_once('\t') else {
+        return 0;
+    };
+    split_keys(keys)
+        .iter()
+        .filter(|key| counts_toward_score(mode, key))
+        .count()
+}
+
+// ノーマルモードで使った移動などのキー（数を付けた移動の目標に使う）
+// 例: "5j" -> count: Some(5), motion: "j"、"jjj" -> count: None の "j" が3つ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MotionUse {
+    pub count: Option<usize>,
+    pub motion: String,
+}
+
+// 次のキーと組み合わせて1つのコマンドになる前置キー（"gg" "gj" など）
+const PREFIX_KEYS: &[&str] = &["g", "z", "["];
+
+// キーログからカウント付きのキーを組み立てる
+// キーログは1キーずつ別の行で届く（カウントの数字と移動が別の行になる）ため、
+// 読み途中のカウントを行をまたいで持ち越す
+#[derive(Debug, Default)]
+pub struct MotionParser {
+    count: Option<usize>,
+    prefix: Option<String>,
+    register: bool, // " の直後（次のキーはレジスタ名）
+}
+
+impl MotionParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 読み途中のカウントを捨てる（一時停止中の入力を読み飛ばしたときなど）
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    // キーログ1行を読み、確定したキーを返す
+    // ノーマルモード以外の行（オペレータ待機中の移動や挿入モードの文字）は読み途中の状態を捨てる
+    pub fn feed(&mut self, line: &str) -> Vec<MotionUse> {
+        let Some((mode, keys)) = line.split_once('\t') else {
+            return Vec::new();
+        };
+        if mode != "n" {
+            self.reset();
+            return Vec::new();
+        }
+        let mut uses = Vec::new();
+        for key in split_keys(keys) {
+            if !counts_toward_score(mode, &key) {
+                continue;
+            }
+            if self.register {
+                self.register = false;
+                continue;
+            }
+            if let Some(prefix) = self.prefix.take() {
+                uses.push(self.finish(prefix + &key));
+                continue;
+            }
+            // 1〜9 はカウントを始め、0 はカウントの途中なら数字、そうでなければ行頭への移動
+            let digit = key.parse::<usize>().ok().filter(|_| key.len() == 1);
+            match digit {
+                Some(digit) if digit > 0 || self.count.is_some() => {
+                    self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                }
+                _ if key == "\"" => self.register = true,
+                _ if PREFIX_KEYS.contains(&key.as_str()) => self.prefix = Some(key),
+                _ => uses.push(self.finish(key)),
+            }
+        }
+        uses
+    }
+
+    fn finish(&mut self, motion: String) -> MotionUse {
+        MotionUse {
+            count: self.count.take(),
+            motion,
+        }
+    }
+}
+
+// 目標の required_operator に書けるオペレーター（2文字のものを先に調べる）
+pub const OPERATORS: &[&str] = &[
+    "g~", "gu", "gU", "g?", "g@", "gq", "gw", "zf", "c", "d", "y", "<", ">", "!", "=",
+];
+
+// オペレーター待機中に次に打つキーの例（オペレーターを重ねると行全体が対象になる）
+pub fn operator_suggestions(operator: &str) -> Vec<String> {
+    let objects: &[&str] = match operator {
+        "c" | "d" | "y" => &["iw", "aw", "i\"", "w", "$"],
+        "g~" | "gu" | "gU" => &["iw", "w", "$"],
+        "<" | ">" | "=" => &["ip", "j", "G"],
+        "gq" | "gw" => &["ip", "ap"],
+        _ => &["iw", "w"],
+    };
+    let mut suggestions: Vec<String> = objects.iter().map(|object| object.to_string()).collect();
+    // g で始まるものは2文字目だけを重ねて書ける（gUU）
+    let doubled = match operator.strip_prefix('g') {
+        Some(rest) => format!("{}{}", operator, rest),
+        None => operator.repeat(2),
+    };
+    if OPERATORS.contains(&operator) && !matches!(operator, "zf" | "g@") {
+        suggestions.push(doubled);
+    }
+    suggestions
+}
+
+// キーログから使ったオペレーターを読む。オペレーター待機（"no" で始まるモード）の行が届いたら、
+// その直前にノーマルモードで打ったキーの末尾をオペレーターとする
+#[derive(Debug, Default)]
+pub struct OperatorParser {
+    recent: Vec<String>, // 直前にノーマルモードで打ったキー（オペレーターの長さの分だけ持つ）
+}
+
+impl OperatorParser {
+    pub fn feed(&mut self, line: &str) -> Option<&'static str> {
+        let (mode, keys) = line.split_once('\t')?;
+        if mode == "n" {
+            for key in split_keys(keys) {
+                self.recent.push(if key == "<lt>" { "<".to_string() } else { key });
+            }
+            let excess = self.recent.len().saturating_sub(2);
+            self.recent.drain(..excess);
+            return None;
+        }
+        let typed = std::mem::take(&mut self.recent).concat();
+        if !mode.starts_with("no") {
+            return None;
+        }
+        OPERATORS.iter().copied().find(|operator| typed.ends_with(operator))
+    }
+}
+
+// テキストオブジェクトの2文字目（"iw" の "w"。i か a の後に打つ）
+pub const TEXT_OBJECTS: &[&str] = &[
+    "w", "W", "s", "p", "(", ")", "b", "[", "]", "<", ">", "t", "{", "}", "B", "\"", "'", "`",
+];
+
+pub fn is_text_object(text_object: &str) -> bool {
+    text_object
+        .strip_prefix(['i', 'a'])
+        .is_some_and(|object| TEXT_OBJECTS.contains(&object))
+}
+
+// オペレーターの直後に打ったテキストオブジェクト（"ciw" なら operator: "c"、text_object: "iw"）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextObjectUse {
+    pub operator: &'static str,
+    pub text_object: String,
+}
+
+// オペレーター待機で打ち始めたキー（カウントは除く）
+#[derive(Debug)]
+struct PendingTextObject {
+    operator: &'static str,
+    keys: Vec<String>,
+    counting: bool,
+}
+
+// キーログから、オペレーターに続けて打ったテキストオブジェクトを読む。
+// オペレーター待機のモードは短く状態ファイルでは見逃すことがあるため、キーログの並びだけで判定する
+#[derive(Debug, Default)]
+pub struct TextObjectParser {
+    operators: OperatorParser,
+    pending: Option<PendingTextObject>,
+}
+
+impl TextObjectParser {
+    pub fn feed(&mut self, line: &str) -> Option<TextObjectUse> {
+        let operator = self.operators.feed(line);
+        let (mode, keys) = line.split_once('\t')?;
+        if !mode.starts_with("no") {
+            self.pending = None;
+            return None;
+        }
+        if let Some(operator) = operator {
+            self.pending = Some(PendingTextObject {
+                operator,
+                keys: Vec::new(),
+                counting: false,
+            });
+        }
+        let mut pending = self.pending.take()?;
+        for key in split_keys(keys) {
+            // オペレーター待機で打ったカウント（"d2iw" の 2）は読み飛ばす
+            let digit = key.len() == 1 && key.chars().all(|c| c.is_ascii_digit());
+            if pending.keys.is_empty() && digit && (key != "0" || pending.counting) {
+                pending.counting = true;
+                continue;
+            }
+            pending.keys.push(if key == "<lt>" { "<".to_string() } else { key });
+            if pending.keys.len() == 2 {
+                break;
+            }
+        }
+        match pending.keys.as_slice() {
+            [] => {}
+            [scope] if scope == "i" || scope == "a" => {}
+            [scope, object] if scope == "i" || scope == "a" => {
+                return Some(TextObjectUse {
+                    operator: pending.operator,
+                    text_object: format!("{}{}", scope, object),
+                });
+            }
+            // 移動（"dw" など）はテキストオブジェクトではない
+            _ => return None,
+        }
+        self.pending = Some(pending);
+        None
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+// 縦の移動（数を付けた移動の目標では、着地した列を問わない）
+pub fn is_linewise_motion(motion: &str) -> bool {
+    matches!(motion, "j" | "k" | "+" | "-" | "_" | "G" | "gg" | "<CR>" | "<Down>" | "<Up>")
+}
+
+// motion をカウントなしで続けて押した最長の回数
+pub fn longest_repeat(uses: &[MotionUse], motion: &str) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    for motion_use in uses {
+        if motion_use.motion == motion && motion_use.count.is_none() {
+            run += 1;
+            longest = longest.max(run);
+        } else {
+            run = 0;
+        }
+    }
+    longest
+}
+
+// 同じキーを数を付けずに続けて押している回数（キーログの読み取りをまたいで数える）
+#[derive(Debug, Default)]
+pub struct RepeatRun {
+    motion: Option<String>,
+    times: usize,
+}
+
+impl RepeatRun {
+    pub fn record(&mut self, motion_use: &MotionUse) {
+        if motion_use.count.is_some() {
+            self.reset();
+        } else if self.motion.as_deref() == Some(motion_use.motion.as_str()) {
+            self.times += 1;
+        } else {
+            self.motion = Some(motion_use.motion.clone());
+            self.times = 1;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn current(&self) -> Option<(&str, usize)> {
+        self.motion.as_deref().map(|motion| (motion, self.times))
+    }
+}
+
+// 次の1文字を引数に取るキー（"f." や "r." の . は繰り返しではない）
+const CHARACTER_ARGUMENT_KEYS: &[&str] = &["f", "F", "t", "T", "r", "m", "'", "`", "q", "@"];
+
+// . で直前の変更を繰り返そうとした回数（引数を待つキーの直後かを、キーログの読み取りをまたいで持ち越す）
+#[derive(Debug, Default)]
+pub struct DotRepeatCounter {
+    awaiting_argument: bool,
+}
+
+impl DotRepeatCounter {
+    pub fn feed(&mut self, uses: &[MotionUse]) -> usize {
+        let mut repeats = 0;
+        for motion_use in uses {
+            if std::mem::take(&mut self.awaiting_argument) {
+                continue;
+            }
+            if motion_use.motion == "." {
+                repeats += 1;
+            } else {
+                self.awaiting_argument = CHARACTER_ARGUMENT_KEYS.contains(&motion_use.motion.as_str());
+            }
+        }
+        repeats
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+// 同じキーを繰り返している学習者に勧める、より良い移動
+pub fn coaching_tip(motion: &str) -> Option<&'static str> {
+    let tip = match motion {
+        "l" => "f<文字> で目的の文字へ、w で次の単語へ、$ で行末へ一度に移動できます",
+        "h" => "F<文字> で前の文字へ、b で前の単語へ、0 や ^ で行頭へ一度に移動できます",
+        "j" => "5j のように数を付けるか、} で次の段落へ、G で最後の行へ移動できます",
+        "k" => "5k のように数を付けるか、{ で前の段落へ、gg で最初の行へ移動できます",
+        "w" => "3w のように数を付けるか、f<文字> で目的の文字へ、$ で行末へ移動できます",
+        "b" => "3b のように数を付けるか、F<文字> で前の文字へ、0 で行頭へ移動できます",
+        "e" => "3e のように数を付けるか、$ で行末へ移動できます",
+        "x" => "5x のように数を付けるか、dw で単語を、d$ で行末まで削除できます",
+        _ => return None,
+    };
+    Some(tip)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeystrokeScore {
+    pub label: String, // 目標の説明（同時に達成した目標は " + " で連結）
+    pub optimal: usize,
+    pub actual: usize,
+}
+
+impl KeystrokeScore {
+    // 効率 = 最適キー数 / 実際のキー数（最適解より少なくても100%まで）
+    pub fn efficiency(&self) -> f64 {
+        if self.actual == 0 {
+            return 1.0;
+        }
+        (self.optimal as f64 / self.actual as f64).min(1.0)
+    }
+}
+
+// 複数の目標をまとめた効率（キー数の合計で比較する）
+pub fn overall_efficiency<'a, I>(scores: I) -> Option<f64>
+where
+    I: IntoIterator<Item = &'a KeystrokeScore>,
+{
+    let (optimal, actual) = scores
+        .into_iter()
+        .fold((0, 0), |(optimal, actual), score| {
+            (optimal + score.optimal, actual + score.actual)
+        });
+    if optimal == 0 {
+        return None;
+    }
+    if actual == 0 {
+        return Some(1.0);
+    }
+    Some((optimal as f64 / actual as f64).min(1.0))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grade {
+    S,
+    A,
+    B,
+    C,
+}
+
+impl Grade {
+    pub fn from_efficiency(efficiency: f64) -> Self {
+        if efficiency >= 0.9 {
+            Grade::S
+        } else if efficiency >= 0.75 {
+            Grade::A
+        } else if efficiency >= 0.5 {
+            Grade::B
+        } else {
+            Grade::C
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Grade::S => "S",
+            Grade::A => "A",
+            Grade::B => "B",
+            Grade::C => "C",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_keys() {
+        assert_eq!(split_keys("d<Esc>w"), vec!["d", "<Esc>", "w"]);
+        assert_eq!(split_keys("<"), vec!["<"]);
+        assert_eq!(split_keys("<>x"), vec!["<", ">", "x"]);
+        assert_eq!(split_keys("あい"), vec!["あ", "い"]);
+    }
+
+    #[test]
+    fn test_insert_mode_text_is_not_counted() {
+        assert_eq!(count_log_line("n\t5l"), 2);
+        assert_eq!(count_log_line("i\tHello<Space>World"), 0);
+        assert_eq!(count_log_line("i\t<Esc>"), 1);
+        assert_eq!(count_log_line("niI\tdd"), 2);
+        assert_eq!(count_log_line("n\t<LeftMouse>"), 0);
+        assert_eq!(count_log_line("c\tw<CR>"), 2);
+        assert_eq!(count_log_line("broken line"), 0);
+    }
+
+    fn parse_log(lines: &[&str]) -> Vec<MotionUse> {
+        let mut parser = MotionParser::new();
+        lines.iter().flat_map(|line| parser.feed(line)).collect()
+    }
+
+    fn motion(count: Option<usize>, motion: &str) -> MotionUse {
+        MotionUse {
+            count,
+            motion: motion.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_operator_parser_reads_key_before_operator_pending() {
+        let mut parser = OperatorParser::default();
+        let mut feed = |lines: &[&str]| lines.iter().filter_map(|line| parser.feed(line)).collect::<Vec<_>>();
+        assert_eq!(feed(&["n	3", "n	d", "no	w"]), vec!["d"]);
+        assert_eq!(feed(&["n	g", "n	U", "no	i", "no	w"]), vec!["gU"]);
+        assert_eq!(feed(&["n	<lt>", "no	<lt>"]), vec!["<"]);
+        // 挿入モードを挟んだキーや、オペレーターでないキーは使ったことにしない
+        assert_eq!(feed(&["n	d", "i	x", "no	w"]), Vec::<&str>::new());
+        assert_eq!(feed(&["n	x", "no	w"]), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_operator_suggestions_end_with_the_doubled_operator() {
+        assert_eq!(operator_suggestions("d"), vec!["iw", "aw", "i\"", "w", "$", "dd"]);
+        assert_eq!(operator_suggestions("gU"), vec!["iw", "w", "$", "gUU"]);
+        assert_eq!(operator_suggestions(">"), vec!["ip", "j", "G", ">>"]);
+        // 知らないオペレーターは重ねた形を出さない
+        assert_eq!(operator_suggestions("zz"), vec!["iw", "w"]);
+    }
+
+    #[test]
+    fn test_text_object_parser_follows_key_order() {
+        let mut parser = TextObjectParser::default();
+        let mut feed = |lines: &[&str]| lines.iter().filter_map(|line| parser.feed(line)).collect::<Vec<_>>();
+        let used = |operator, text_object: &str| TextObjectUse {
+            operator,
+            text_object: text_object.to_string(),
+        };
+        // 状態ファイルがオペレーター待機を見逃しても、キーログの並びで読む
+        assert_eq!(feed(&["n	c", "no	i", "no	w", "i	x"]), vec![used("c", "iw")]);
+        assert_eq!(feed(&["n	d", "no	2", "no	i(", "n	j"]), vec![used("d", "i(")]);
+        assert_eq!(feed(&["n	c", "no	a", "no	<lt>"]), vec![used("c", "a<")]);
+        // 移動や、間に別のモードを挟んだキーはテキストオブジェクトではない
+        assert_eq!(feed(&["n	d", "no	w", "n	i", "n	w"]), Vec::new());
+        assert_eq!(feed(&["n	c", "no	i", "n	w"]), Vec::new());
+        assert!(is_text_object("i(") && is_text_object("at") && !is_text_object("iw(") && !is_text_object("w"));
+    }
+
+    #[test]
+    fn test_motion_parser_groups_counts_across_lines() {
+        // 1キーずつ届く行でもカウントと移動をまとめる
+        assert_eq!(
+            parse_log(&["n\t1", "n\t2", "n\tj", "n\tj", "n\t5", "n\tG"]),
+            vec![motion(Some(12), "j"), motion(None, "j"), motion(Some(5), "G")]
+        );
+        // 0 はカウントの途中なら数字、そうでなければ行頭への移動
+        assert_eq!(
+            parse_log(&["n\t0", "n\t10l", "n\t0"]),
+            vec![motion(None, "0"), motion(Some(10), "l"), motion(None, "0")]
+        );
+        // 前置キーとレジスタ指定、別のモードに入った後のカウント
+        assert_eq!(
+            parse_log(&["n\t3gg", "n\t\"a2", "n\tj", "n\t2", "no\tj", "n\tw"]),
+            vec![motion(Some(3), "gg"), motion(Some(2), "j"), motion(None, "w")]
+        );
+        // 挿入モードで打った数字はカウントにしない
+        assert_eq!(parse_log(&["i\t5", "n\tj"]), vec![motion(None, "j")]);
+    }
+
+    #[test]
+    fn test_longest_repeat() {
+        let uses = parse_log(&["n\tjj", "n\t2j", "n\tjjj", "n\tk"]);
+        assert_eq!(longest_repeat(&uses, "j"), 3);
+        assert_eq!(longest_repeat(&uses, "k"), 1);
+        assert_eq!(longest_repeat(&uses, "l"), 0);
+        assert!(is_linewise_motion("gg") && !is_linewise_motion("l"));
+    }
+
+    #[test]
+    fn test_repeat_run_spans_log_lines() {
+        let mut run = RepeatRun::default();
+        for uses in [parse_log(&["n\tlll"]), parse_log(&["n\tll"])] {
+            uses.iter().for_each(|motion_use| run.record(motion_use));
+        }
+        assert_eq!(run.current(), Some(("l", 5)));
+        assert!(coaching_tip("l").is_some_and(|tip| tip.contains("f<文字>")));
+        // 数を付けた移動や別のキーで数え直す
+        run.record(&motion(Some(3), "l"));
+        assert_eq!(run.current(), None);
+        parse_log(&["n\tjjw"]).iter().for_each(|motion_use| run.record(motion_use));
+        assert_eq!(run.current(), Some(("w", 1)));
+        assert_eq!(coaching_tip("dd"), None);
+    }
+
+    #[test]
+    fn test_dot_repeat_counter_skips_character_arguments() {
+        let mut counter = DotRepeatCounter::default();
+        assert_eq!(counter.feed(&parse_log(&["n\tj.", "n\t3.", "n\tf."])), 2);
+        // 引数を待つキーの直後の . は、次の読み取りに届いても数えない
+        assert_eq!(counter.feed(&parse_log(&["n\tr"])), 0);
+        assert_eq!(counter.feed(&parse_log(&["n\t.", "n\t."])), 1);
+        // 挿入モードで打った . は数えない
+        assert_eq!(counter.feed(&parse_log(&["i\t.", "n\t<Esc>"])), 0);
+    }
+
+    #[test]
+    fn test_efficiency_and_grade() {
+        let scores = [
+            KeystrokeScore {
+                label: "a".to_string(),
+                optimal: 2,
+                actual: 5,
+            },
+            KeystrokeScore {
+                label: "b".to_string(),
+                optimal: 3,
+                actual: 2,
+            },
+        ];
+        assert_eq!(scores[0].efficiency(), 0.4);
+        assert_eq!(scores[1].efficiency(), 1.0);
+        assert_eq!(overall_efficiency(&scores), Some(5.0 / 7.0));
+        assert_eq!(overall_efficiency(&[]), None);
+
+        assert_eq!(Grade::from_efficiency(0.95), Grade::S);
+        assert_eq!(Grade::from_efficiency(0.8), Grade::A);
+        assert_eq!(Grade::from_efficiency(0.5), Grade::B);
+        assert_eq!(Grade::from_efficiency(0.2), Grade::C);
+    }
+}
@@ -0,0 +1,193 @@
+use std::process::{Command, Stdio};
+use unicode_width::UnicodeWidthStr;
+
+// 上下分割で指示と Neovim の両方が十分に見える端末の行数
+const MIN_FULL_LINES: usize = 30;
+// 行数が足りないとき、左右分割に切り替えられる端末の桁数（各ペイン80桁）
+const MIN_SIDE_BY_SIDE_COLUMNS: usize = 160;
+// 簡易表示での指示ペインの高さ（3行の表示とシェルのプロンプト）
+pub const COMPACT_PANE_LINES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaneLayout {
+    Full,       // 上に指示、下に Neovim
+    SideBySide, // 左に指示、右に Neovim
+    Compact,    // 上下分割で、指示は現在の目標とヒントだけの3行
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalSize {
+    pub columns: usize,
+    pub lines: usize,
+}
+
+// 現在の端末の大きさ（stty が使えなければ環境変数 COLUMNS / LINES）
+pub fn terminal_size() -> Option<TerminalSize> {
+    let from_stty = Command::new("stty")
+        .arg("size")
+        .stdin(Stdio::inherit())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| parse_stty_size(&String::from_utf8_lossy(&output.stdout)));
+    from_stty.or_else(|| {
+        let columns = std::env::var("COLUMNS").ok()?.parse().ok()?;
+        let lines = std::env::var("LINES").ok()?.parse().ok()?;
+        Some(TerminalSize { columns, lines })
+    })
+}
+
+// `stty size` の出力（"行数 桁数"）を読む
+fn parse_stty_size(output: &str) -> Option<TerminalSize> {
+    let mut parts = output.split_whitespace();
+    let lines = parts.next()?.parse().ok()?;
+    let columns = parts.next()?.parse().ok()?;
+    if lines == 0 || columns == 0 {
+        return None;
+    }
+    Some(TerminalSize { columns, lines })
+}
+
+// 大きさが分からない場合はこれまで通りの上下分割にする
+pub fn choose_layout(size: Option<TerminalSize>) -> PaneLayout {
+    match size {
+        None => PaneLayout::Full,
+        Some(size) if size.lines >= MIN_FULL_LINES => PaneLayout::Full,
+        Some(size) if size.columns >= MIN_SIDE_BY_SIDE_COLUMNS => PaneLayout::SideBySide,
+        Some(_) => PaneLayout::Compact,
+    }
+}
+
+// 表示幅が width を超える場合は切り詰めて末尾を … にする（全角文字は2桁として数える）
+// 絵文字と異体字セレクタの組み合わせも数えられるよう、文字列全体の幅で比べる。幅0では … も入らないため空にする
+pub fn truncate_to_width(text: &str, width: usize) -> String {
+    if UnicodeWidthStr::width(text) <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut result = String::new();
+    for c in text.chars() {
+        let mut candidate = result.clone();
+        candidate.push(c);
+        if UnicodeWidthStr::width(candidate.as_str()) + 1 > width {
+            break;
+        }
+        result = candidate;
+    }
+    result.push('…');
+    result
+}
+
+// 数の表示（経過時間・残り時間・ヒントまでの秒数・進捗・スコア）をするか。
+// Zen では目標とヒントだけを出す（記録はそのまま残し、stats で見られる）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayPolicy {
+    #[default]
+    Full,
+    Zen,
+}
+
+impl DisplayPolicy {
+    pub fn shows_numbers(self) -> bool {
+        self == DisplayPolicy::Full
+    }
+}
+
+// 指示ペインの進捗の表示に使う値（elapsed_secs が None なら時計を出さない）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressCounts {
+    pub completed: usize,
+    pub total: usize,
+    pub elapsed_secs: Option<u64>,
+}
+
+// 進捗バーの長さの上限と、これより短くなるなら数だけの形にする長さ
+const MAX_PROGRESS_BAR: usize = 20;
+const MIN_PROGRESS_BAR: usize = 5;
+
+// "📊 ████░░░░ 4/9 目標  ⏱️ 1:05"。バーは width に収まる長さにし、収まらなければ "📊 4/9 ⏱️ 1:05" にする
+pub fn progress_line(snapshot: &ProgressCounts, width: usize) -> String {
+    let completed = snapshot.completed.min(snapshot.total);
+    let count = format!("{}/{}", completed, snapshot.total);
+    let clock = snapshot
+        .elapsed_secs
+        .map(|secs| format!("⏱️ {}:{:02}", secs / 60, secs % 60));
+    let text = match &clock {
+        Some(clock) => format!("{} 目標  {}", count, clock),
+        None => format!("{} 目標", count),
+    };
+    let bar = width
+        .saturating_sub(UnicodeWidthStr::width(format!("📊  {}", text).as_str()))
+        .min(MAX_PROGRESS_BAR);
+    if bar < MIN_PROGRESS_BAR || snapshot.total == 0 {
+        return match clock {
+            Some(clock) => format!("📊 {} {}", count, clock),
+            None => format!("📊 {}", count),
+        };
+    }
+    let filled = completed * bar / snapshot.total;
+    format!("📊 {}{} {}", "█".repeat(filled), "░".repeat(bar - filled), text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_layout() {
+        let size = |columns, lines| Some(TerminalSize { columns, lines });
+        assert_eq!(choose_layout(None), PaneLayout::Full);
+        assert_eq!(choose_layout(size(80, 40)), PaneLayout::Full);
+        assert_eq!(choose_layout(size(200, 20)), PaneLayout::SideBySide);
+        assert_eq!(choose_layout(size(80, 20)), PaneLayout::Compact);
+    }
+
+    #[test]
+    fn test_parse_stty_size() {
+        assert_eq!(
+            parse_stty_size("20 80\n"),
+            Some(TerminalSize {
+                columns: 80,
+                lines: 20
+            })
+        );
+        assert_eq!(parse_stty_size("0 0"), None);
+        assert_eq!(parse_stty_size(""), None);
+    }
+
+    #[test]
+    fn test_truncate_to_width() {
+        assert_eq!(truncate_to_width("hello", 5), "hello");
+        assert_eq!(truncate_to_width("hello world", 6), "hello…");
+        // 全角文字は2桁として数え、途中で切らない
+        assert_eq!(truncate_to_width("カーソルを移動", 7), "カーソ…");
+        // 幅0では何も表示しない
+        assert_eq!(truncate_to_width("abc", 0), "");
+        assert_eq!(truncate_to_width("abc", 1), "…");
+        assert_eq!(truncate_to_width("⌨️ ab", 4), "⌨️ …");
+    }
+
+    #[test]
+    fn test_progress_line_fits_the_pane_width() {
+        let snapshot = ProgressCounts {
+            completed: 4,
+            total: 9,
+            elapsed_secs: Some(65),
+        };
+        assert_eq!(progress_line(&snapshot, 79), "📊 ████████░░░░░░░░░░░░ 4/9 目標  ⏱️ 1:05");
+        let line = progress_line(&snapshot, 30);
+        assert_eq!(line, "📊 ████░░░░░ 4/9 目標  ⏱️ 1:05");
+        assert!(UnicodeWidthStr::width(line.as_str()) <= 30);
+        // 狭いペインでは数と時計だけの1行にする
+        assert_eq!(progress_line(&snapshot, 20), "📊 4/9 ⏱️ 1:05");
+        let done = ProgressCounts {
+            completed: 3,
+            total: 3,
+            elapsed_secs: None,
+        };
+        assert_eq!(progress_line(&done, 30), "📊 ██████████████████ 3/3 目標");
+        assert_eq!(progress_line(&done, 0), "📊 3/3");
+    }
+}
@@ -0,0 +1,121 @@
+// 練習の合間に端末で読む解説のページ（章データの lessons）
+use std::io::{self, Write};
+
+use crate::continuous_content::LessonPage;
+use crate::theme::say;
+
+// 1ページ分の表示（number は 1始まり）
+pub fn page_lines(page: &LessonPage, number: usize, total: usize) -> Vec<String> {
+    let mut lines = vec![format!("📖 === {} ({}/{}) ===", page.title, number, total)];
+    let mut in_code = false;
+    for line in page.body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            lines.push(format!("    {}", line));
+        } else if let Some(heading) = line.strip_prefix("# ") {
+            lines.push(format!("【{}】", heading.trim()));
+        } else if let Some(item) = line.strip_prefix("- ") {
+            lines.push(format!("  ・{}", item));
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    lines
+}
+
+// 入力を待つ（EOF のデモモードでは待たずに進む）
+fn wait_for_enter(prompt: &str) {
+    print!("{}", crate::theme::current().line(prompt));
+    let _ = io::stdout().flush();
+    let mut input = String::new();
+    if matches!(io::stdin().read_line(&mut input), Ok(0) | Err(_)) {
+        say!();
+    }
+}
+
+// 練習の前に読むページを順に表示する（paginate なら1ページごとに Enter を待つ）
+pub fn show_pages(pages: &[&LessonPage], paginate: bool) {
+    for (i, page) in pages.iter().enumerate() {
+        say!();
+        for line in page_lines(page, i + 1, pages.len()) {
+            say!("{}", line);
+        }
+        say!();
+        if paginate {
+            if i + 1 < pages.len() {
+                wait_for_enter("▶️ Enter で次のページへ...");
+            } else {
+                wait_for_enter("▶️ Enter で練習に進みます...");
+            }
+        }
+    }
+}
+
+// 章の解説を一覧から選んで読み直す（空の入力か q でメニューに戻る）
+pub fn browse(pages: &[LessonPage]) {
+    if pages.is_empty() {
+        say!("📖 この章には解説がありません。");
+        return;
+    }
+    loop {
+        say!("\n📖 === 解説 ===");
+        for (i, page) in pages.iter().enumerate() {
+            say!("  {}. {}（練習{}の前）", i + 1, page.title, page.exercise_index() + 1);
+        }
+        print!(
+            "{}",
+            crate::theme::current().line(&format!("読むページの番号を選択してください (1-{}, Enter=戻る): ", pages.len()))
+        );
+        let _ = io::stdout().flush();
+
+        let mut input = String::new();
+        if matches!(io::stdin().read_line(&mut input), Ok(0) | Err(_)) {
+            say!();
+            return;
+        }
+        let input = input.trim();
+        if input.is_empty() || input == "q" {
+            return;
+        }
+        match input.parse::<usize>() {
+            Ok(number) if (1..=pages.len()).contains(&number) => {
+                say!();
+                for line in page_lines(&pages[number - 1], number, pages.len()) {
+                    say!("{}", line);
+                }
+                say!();
+                wait_for_enter("▶️ Enter で一覧に戻ります...");
+            }
+            _ => say!("❌ 1-{} の中から選んでください。", pages.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_lines_render_headings_lists_and_code() {
+        let page = LessonPage {
+            title: "モード".to_string(),
+            body: "説明\n# 見出し\n- 項目\n```\ni  入力\n```\n終わり".to_string(),
+            before: None,
+        };
+        assert_eq!(
+            page_lines(&page, 1, 2),
+            vec![
+                "📖 === モード (1/2) ===",
+                "説明",
+                "【見出し】",
+                "  ・項目",
+                "    i  入力",
+                "終わり",
+            ]
+        );
+        assert_eq!(page.exercise_index(), 0);
+    }
+}
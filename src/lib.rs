@@ -0,0 +1,141 @@
+//! Vim/Neovim チュートリアルのエンジン。
+//!
+//! 章データの読み込みと検証（[`continuous_content`]・[`validator`]）、エディタの状態と目標の判定
+//! （[`vim_state`]）、エディタとの通信（[`vim_rpc`]）、練習のセッション（[`continuous_session`]）を
+//! ライブラリとして使えます。`vim-tutorial-nvim` コマンドはこのライブラリの上の CLI です。
+//!
+//! 章データを文字列から読み、目標をエディタの状態に対して判定する例:
+//!
+//! ```
+//! use vim_tutorial_nvim::continuous_content::ContinuousContentLoader;
+//! use vim_tutorial_nvim::vim_state::{GoalDetector, VimMode, parse_status_content};
+//!
+//! let yaml = r#"
+//! chapter:
+//!   number: 1
+//!   title: "モード"
+//!   description: "Insert モードに入ります"
+//! continuous_exercises:
+//!   - title: "i で入力"
+//!     description: "i で Insert モードに入ります"
+//!     flow_type: "sequential"
+//!     sample_code: ["hello"]
+//!     goals:
+//!       - type: "mode"
+//!         target: "insert"
+//!         description: "Insert モードに入る"
+//! "#;
+//! let chapter = ContinuousContentLoader::parse_chapter(yaml, "chapter.yaml").unwrap();
+//! assert!(vim_tutorial_nvim::validator::validate_chapter(&chapter).is_empty());
+//! let goal = chapter.continuous_exercises[0].goals[0].to_goal().unwrap();
+//!
+//! // 状態ファイルと同じ形式（行・列は1始まり）からエディタの状態を作る
+//! let normal = parse_status_content("LINE:1,COL:1,MODE:n");
+//! let insert = parse_status_content("LINE:1,COL:1,MODE:i");
+//! assert_eq!(insert.mode, VimMode::Insert);
+//!
+//! let detector = GoalDetector::new();
+//! assert!(!detector.check_goal(&goal, &normal).is_met());
+//! assert!(detector.check_goal(&goal, &insert).is_met());
+//! ```
+
+/// 従来形式の章データと、章の読み込み結果（[`content::LoadReport`]）
+pub mod content;
+/// 連続学習の章データ（[`continuous_content::ContinuousChapterData`]）の読み込み
+pub mod continuous_content;
+/// 練習のセッション。エディタを起動し、状態を監視して目標の達成を判定する
+pub mod continuous_session;
+/// 目標の進み方（[`goal_engine::GoalEngine`]）と、判定に渡す状態の列
+pub mod goal_engine;
+/// 目標の種類の登録（独自の種類の目標を足す）
+pub mod goal_registry;
+/// 章データの検証（作者向けの警告と、読み込みを止める誤り）
+pub mod validator;
+/// エディタ（Neovim の RPC / Vim のチャンネル）との通信
+pub mod vim_rpc;
+/// エディタの状態（[`vim_state::VimState`]）と目標の判定（[`vim_state::GoalDetector`]）
+pub mod vim_state;
+/// テキストの目標の比べる前の正規化（全角と半角・引用符・行末の空白）
+pub mod normalize;
+
+/// 設定ファイル（[`config::TutorialConfig`]）
+pub mod config;
+/// ファイルの置き場所（データ・キャッシュ・設定のディレクトリ）と、以前の置き場所からの移行
+pub mod paths;
+/// 使うエディタの選択
+pub mod editor;
+/// エディタの起動と指示の表示先（[`vim_backend::VimBackend`]。テストでは差し替えられる）
+pub mod vim_backend;
+
+// 以下は CLI のための部品（外から使う API としては保証しない）
+#[doc(hidden)]
+pub mod chapter_report;
+#[doc(hidden)]
+pub mod cheatsheet;
+#[doc(hidden)]
+pub mod clean;
+#[doc(hidden)]
+pub mod diff;
+#[doc(hidden)]
+pub mod doctor;
+#[doc(hidden)]
+pub mod drill;
+#[doc(hidden)]
+pub mod error;
+#[doc(hidden)]
+pub mod event_log;
+#[doc(hidden)]
+pub mod export;
+#[doc(hidden)]
+pub mod game;
+#[doc(hidden)]
+pub mod hooks;
+#[doc(hidden)]
+pub mod keystrokes;
+#[doc(hidden)]
+pub mod layout;
+#[doc(hidden)]
+pub mod lesson;
+#[doc(hidden)]
+pub mod menu;
+#[doc(hidden)]
+pub mod notify;
+#[doc(hidden)]
+pub mod output;
+#[doc(hidden)]
+pub mod packs;
+#[doc(hidden)]
+pub mod pane;
+#[doc(hidden)]
+pub mod panel;
+#[doc(hidden)]
+pub mod practice_file;
+#[doc(hidden)]
+pub mod preview;
+#[doc(hidden)]
+pub mod progress;
+#[doc(hidden)]
+pub mod recovery;
+#[doc(hidden)]
+pub mod sample_chapters;
+#[doc(hidden)]
+pub mod session_progress;
+#[doc(hidden)]
+pub mod share;
+#[doc(hidden)]
+pub mod simple_mode;
+#[doc(hidden)]
+pub mod stats;
+#[doc(hidden)]
+pub mod status_line;
+#[doc(hidden)]
+pub mod summary;
+#[doc(hidden)]
+pub mod theme;
+#[doc(hidden)]
+pub mod tui;
+#[doc(hidden)]
+pub mod warmup;
+
+#[cfg(test)]
+mod test_support;
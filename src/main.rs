@@ -1,20 +1,30 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::fs;
-use std::process::Command;
+use std::process::{Command, ExitCode};
 use tempfile::NamedTempFile;
 
-mod content;
-mod continuous_content;
-mod continuous_session;
-mod game;
-mod vim_rpc;
-mod vim_state;
+use vim_tutorial_nvim::{
+    chapter_report, cheatsheet, clean, config, content, continuous_content, continuous_session, doctor, drill,
+    editor, error, event_log, export, game, hooks, info, keystrokes, lesson, menu, notify, output, pane, packs,
+    panel, paths, practice_file, preview, progress, recovery, sample_chapters, say, share, simple_mode, stats, summary, theme,
+    validator, vim_backend, vim_rpc, warmup, warning,
+};
 
+use chapter_report::ChapterReport;
+use config::TutorialConfig;
+use error::TutorialError;
 use continuous_content::ContinuousContentLoader;
-use continuous_session::{ContinuousVimSession, ExerciseResult};
+use continuous_session::{ContinuousExercise, ContinuousVimSession, ExerciseResult, PauseTarget};
 use game::VimTutorialGame;
+use hooks::Hooks;
+use output::Verbosity;
+use theme::Theme;
+use menu::{ChapterChoice, ChapterFilter, ExerciseChoice, ExerciseEntry};
+use progress::{ChallengeAttempt, Completion, LearningMode, PausedExercise, ProgressStore};
+use recovery::SessionManifest;
 use std::io::{self, Write};
+use summary::{ChapterSummary, ExerciseStatus, ExerciseSummary, NextAction};
 
 #[derive(Parser)]
 #[command(name = "vim-tutorial-nvim")]
@@ -23,66 +33,665 @@ struct Args {
     #[arg(short, long, help = "テストモードを実行")]
     test: bool,
 
-    #[arg(short, long, help = "連続学習モードを使用")]
+    #[arg(short, long, global = true, help = "連続学習モードを使用")]
     continuous: bool,
 
-    #[arg(long, help = "サンプル章を生成")]
+    #[arg(
+        long,
+        value_name = "TOPIC|PATH",
+        help = "サンプル章を生成（motions/words/operators/search/registers か all。それ以外は motions の章を書くパス）"
+    )]
     generate_sample: Option<String>,
+
+    #[arg(long, default_value_os_t = paths::chapters_dir(), help = "--generate-sample のトピックの章を書くディレクトリ")]
+    out_dir: std::path::PathBuf,
+
+    #[arg(
+        long,
+        requires = "generate_sample",
+        help = "--generate-sample の章を1つの章のまとめ（continuous_bundle_samples.yaml か指定のパス）に書く"
+    )]
+    bundle: bool,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "手元のファイルのコピーで、そのファイルに合わせた練習をする（元のファイルは変更しない。連続学習モード）"
+    )]
+    practice_file: Option<std::path::PathBuf>,
+
+    #[arg(long, help = "章を完了したら確認せずに次の章へ進む")]
+    auto_advance: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "章の一覧をタグまたは難易度（beginner/intermediate/advanced）で絞り込む"
+    )]
+    filter: Option<String>,
+
+    #[arg(long, global = true, help = "前提の章が未完了でも全ての章を選べるようにする（講師向け）")]
+    ignore_prereqs: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = editor::EditorChoice::Auto,
+        help = "練習に使うエディタ（auto は Neovim を優先し、無ければ Vim 8.2 以上）"
+    )]
+    editor: editor::EditorChoice,
+
+    #[arg(
+        long,
+        global = true,
+        help = "講師の watch 向けに、カーソル位置・モード・目標の進み具合をこのディレクトリに書き出す（キー入力は共有しない）"
+    )]
+    share_dir: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "章の終わりに結果ファイル（JSON）を書くパス（既定は設定の summary_out か、データの置き場所の reports/ の章ごとのファイル）"
+    )]
+    summary_out: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        value_parser = clap::value_parser!(u64).range(20..=1000),
+        help = "エディタの状態を確かめる間隔（ミリ秒。設定の poll_interval_ms と同じ。変化が無い間は 500 まで空ける）"
+    )]
+    poll_interval_ms: Option<u64>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "失敗したときだけでなく毎回、練習の終わりのエディタと指示ペインの画面を保存する（設定の capture_on: always と同じ）"
+    )]
+    capture_on_exit: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "絵文字を文字の言い換えにし、画面の更新を控えるスクリーンリーダー向けの表示（設定の accessible と同じ）"
+    )]
+    accessible: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help_heading = "デバッグ用",
+        help = "練習用の Neovim が待ち受けるソケット（nvim --server <PATH> で接続できる。既定は PID ごとの一時ファイル）"
+    )]
+    socket_path: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        help_heading = "デバッグ用",
+        help = "練習の終わりにエディタと tmux のセッションを止めずに残し、居場所を表示する（clean で片付ける）"
+    )]
+    keep_session: bool,
+
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "keep_session",
+        help = "tmux もエディタとの通信も使わず、1つの端末で練習する（エディタを閉じるたびに目標を判定する。連続学習モード）"
+    )]
+    simple: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "時間・進捗・スコアを表示せず、目標とヒントだけを出す（記録は残し stats で見られる。設定の zen = true と同じ）"
+    )]
+    zen: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "練習ごとの自己ベストを記録も表示もしない（教室の共用の端末向け。設定の records = false と同じ）"
+    )]
+    no_records: bool,
+
+    #[arg(short, long, global = true, conflicts_with = "verbose", help = "案内を表示せず、エラーだけを表示する")]
+    quiet: bool,
+
+    #[arg(short, long, global = true, help = "デバッグログの内容も標準エラーに表示する")]
+    verbose: bool,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+#[derive(Subcommand)]
+enum Commands {
+    #[command(about = "これまでの章ごとの結果を表示")]
+    Stats {
+        #[arg(long, help = "import で作った集計ファイルを学習者ごとに表示")]
+        aggregate: Option<std::path::PathBuf>,
+    },
+
+    #[command(about = "実行環境（エディタ・tmux）を確認し、使えない機能を表示")]
+    Doctor,
+
+    #[command(about = "章の一覧を表示（-c で連続学習版）")]
+    List {
+        #[arg(long, help = "JSON形式で出力")]
+        json: bool,
+    },
+
+    #[command(about = "異常終了で残ったtmuxセッション・ソケット・一時ファイルを片付ける")]
+    Clean {
+        #[arg(short, long, help = "確認せずに削除")]
+        yes: bool,
+    },
+
+    #[command(about = "連続学習モードで記録したセッションを再生")]
+    Replay {
+        #[arg(help = "記録ファイル（data/sessions/*.jsonl）")]
+        file: std::path::PathBuf,
+
+        #[arg(long, default_value_t = 1.0, help = "再生速度の倍率（2 で2倍速）")]
+        speed: f64,
+    },
+
+    #[command(about = "章で学ぶキーの一覧（チートシート）を章データから作成（-c で連続学習版）")]
+    Cheatsheet {
+        #[arg(long, required_unless_present = "all", conflicts_with = "all", help = "章番号")]
+        chapter: Option<u8>,
+
+        #[arg(long, help = "全ての章について章ごとのファイルを作成")]
+        all: bool,
+
+        #[arg(long, help = "出力先のファイル（--all ではディレクトリ）。未指定なら標準出力（--all では現在のディレクトリ）")]
+        out: Option<std::path::PathBuf>,
+
+        #[arg(long, value_enum, default_value_t = cheatsheet::CheatsheetFormat::Markdown, help = "出力形式")]
+        format: cheatsheet::CheatsheetFormat,
+    },
+
+    #[command(about = "章データを読み込み、読めない章と章データの問題を表示（作者向け）")]
+    Validate {
+        #[arg(long, default_value_os_t = paths::chapters_dir(), help = "章データのディレクトリ")]
+        dir: std::path::PathBuf,
+        #[arg(
+            long,
+            default_value_t = validator::DEFAULT_LINE_WIDTH,
+            help = "これより右にある位置の目標を警告する桁数（練習用のウィンドウの幅）"
+        )]
+        line_width: usize,
+    },
+
+    #[command(about = "連続学習の章の練習で表示する画面を、Neovim を起動せずに順に表示（作者向け）")]
+    Preview {
+        #[arg(long, help = "章番号")]
+        chapter: u8,
+        #[arg(long, help = "練習の番号（1始まり）。未指定なら章の全ての練習")]
+        exercise: Option<usize>,
+        #[arg(long, default_value_t = pane::DEFAULT_PANE_WIDTH, help = "指示ペインの桁数")]
+        width: usize,
+        #[arg(long, default_value_os_t = paths::chapters_dir(), help = "章データのディレクトリ")]
+        dir: std::path::PathBuf,
+    },
+
+    #[command(
+        about = "学習者が --share-dir で共有している練習の状況を表示（講師向け・読み取り専用）",
+        long_about = share::WATCH_LONG_HELP
+    )]
+    Watch {
+        #[arg(long, help = "学習者の --share-dir と同じディレクトリ")]
+        session_dir: std::path::PathBuf,
+    },
+
+    #[command(
+        about = "学習記録と練習ごとの集計を JSON / CSV で書き出す（クラスでの集計用）",
+        long_about = export::EXPORT_LONG_HELP
+    )]
+    Export {
+        #[arg(long, value_enum, default_value_t = export::ExportFormat::Json, help = "出力形式")]
+        format: export::ExportFormat,
+
+        #[arg(long, help = "出力先のファイル。未指定なら標準出力")]
+        out: Option<std::path::PathBuf>,
+
+        #[arg(long, help = "学習者の識別子（既定は設定の learner_id か $USER@ホスト名）")]
+        learner: Option<String>,
+    },
+
+    #[command(about = "export --format json で書き出したファイルを1つの集計にまとめる")]
+    Import {
+        #[arg(required = true, help = "export で書き出した JSON ファイル")]
+        files: Vec<std::path::PathBuf>,
+
+        #[arg(long, help = "既存の集計ファイルに追加する（未指定なら作り直す）")]
+        merge: bool,
 
-    // Neovimが利用可能かチェック
-    match check_neovim_available() {
-        Ok(_) => println!("✓ Neovim が見つかりました"),
+        #[arg(long, default_value_os_t = paths::aggregate_file(), help = "集計ファイルの保存先")]
+        out: std::path::PathBuf,
+    },
+
+    #[command(about = "章のパックを URL・git のリポジトリ・ローカルのパスや tar から取得してインストール（同じパックは更新）")]
+    Install {
+        #[arg(help = "http(s) の URL、git のリポジトリ（.git で終わるか git+ を付ける）、YAML・ディレクトリ・tar のパス")]
+        source: String,
+
+        #[arg(long, default_value_os_t = paths::chapters_dir(), help = "章データのディレクトリ")]
+        dir: std::path::PathBuf,
+    },
+
+    #[command(about = "インストールした章のパックを削除")]
+    Uninstall {
+        #[arg(help = "パックの名前")]
+        pack: String,
+
+        #[arg(long, default_value_os_t = paths::chapters_dir(), help = "章データのディレクトリ")]
+        dir: std::path::PathBuf,
+    },
+
+    // tmux の指示ペインで内部的に使う（指示のファイルが変わるたびに描き直す）
+    #[command(hide = true)]
+    Panel {
+        file: std::path::PathBuf,
+    },
+
+    #[command(about = "完了した章からランダムに出題して復習")]
+    Drill {
+        #[arg(short = 'n', long, default_value_t = DEFAULT_DRILL_COUNT, help = "出題数")]
+        count: usize,
+
+        #[arg(long, help = "乱数シード（同じ値で同じ出題順を再現）")]
+        seed: Option<u64>,
+
+        #[arg(long, help = "出題する章を限定")]
+        chapter: Option<u8>,
+
+        #[arg(long, help = "練習単位ではなく目標1つずつを出題")]
+        goals: bool,
+    },
+
+    #[command(about = "制限時間付きで練習に挑戦")]
+    Challenge {
+        #[arg(help = "章番号")]
+        chapter: u8,
+
+        #[arg(default_value_t = 1, help = "練習番号（1から）")]
+        exercise: usize,
+
+        #[arg(long, help = "制限時間（秒）。未指定なら練習の設定か既定値を使用")]
+        time_limit: Option<u64>,
+    },
+}
+
+// 練習に制限時間が設定されていない場合のチャレンジの制限時間（秒）
+const DEFAULT_CHALLENGE_SECONDS: u64 = 60;
+
+// 復習ドリルの既定の出題数（最後の章を終えた後のドリルでも使う）
+const DEFAULT_DRILL_COUNT: usize = 5;
+
+// 分類した失敗は原因と次に試すことを表示し、全ての原因はデバッグログに残す
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
         Err(e) => {
-            eprintln!("✗ Neovim が見つかりません: {}", e);
-            eprintln!("  インストール方法: sudo apt install neovim  または  brew install neovim");
-            return Err(e);
+            let error = TutorialError::from(e);
+            error.log();
+            for line in error.render() {
+                eprintln!("{}", theme::current().line(&line));
+            }
+            ExitCode::FAILURE
         }
     }
+}
+
+fn run() -> Result<()> {
+    let args = Args::parse();
+    output::set_verbosity(Verbosity::from_flags(args.quiet, args.verbose));
+    // 以前の版がカレントディレクトリの data と一時ディレクトリに置いたものを、新しい置き場所へ移す
+    for moved in paths::migrate_legacy() {
+        let verb = if moved.copied { "写しました" } else { "移しました" };
+        info!("📦 {} を {} に{}", moved.from.display(), moved.to.display(), verb);
+    }
+    let synced = paths::sync_bundled_chapters();
+    if !synced.is_empty() {
+        info!("📦 同梱の章 {} 個を {} に写しました", synced.len(), paths::chapters_dir().display());
+    }
+    theme::set(Theme::from_accessible(args.accessible || TutorialConfig::load().accessible));
+    if args.no_records {
+        config::set_no_records();
+    }
+    if args.zen {
+        config::set_zen();
+    }
 
-    if let Some(output_path) = args.generate_sample {
+    // Neovimを必要としないサブコマンド
+    if let Some(Commands::Panel { file }) = &args.command {
+        return panel::run_panel(file);
+    }
+    if let Some(Commands::Stats { aggregate }) = &args.command {
+        match aggregate {
+            Some(path) => export::print_aggregate(path)?,
+            None => show_stats(),
+        }
+        return Ok(());
+    }
+    if let Some(Commands::Doctor) = &args.command {
+        doctor::run_doctor(args.editor);
+        return Ok(());
+    }
+    if let Some(Commands::Clean { yes }) = &args.command {
+        return clean::run_clean(*yes);
+    }
+    if let Some(Commands::Replay { file, speed }) = &args.command {
+        return event_log::run_replay(file, *speed);
+    }
+    if let Some(Commands::Cheatsheet { chapter, all, out, format }) = &args.command {
+        return cheatsheet::run_cheatsheet(args.continuous, *chapter, *all, out.as_deref(), *format);
+    }
+    if let Some(Commands::Validate { dir, line_width }) = &args.command {
+        validator::set_line_width(*line_width);
+        return validator::run_validate(dir);
+    }
+    if let Some(Commands::Preview { chapter, exercise, width, dir }) = &args.command {
+        return preview::run_preview(dir, *chapter, *exercise, *width);
+    }
+    if let Some(Commands::Install { source, dir }) = &args.command {
+        return packs::run_install(source, dir);
+    }
+    if let Some(Commands::Uninstall { pack, dir }) = &args.command {
+        return packs::run_uninstall(pack, dir);
+    }
+    if let Some(Commands::Watch { session_dir }) = &args.command {
+        return share::run_watch(session_dir);
+    }
+    if let Some(Commands::Export { format, out, learner }) = &args.command {
+        let learner = learner
+            .clone()
+            .or(TutorialConfig::load().learner_id)
+            .unwrap_or_else(export::default_learner_id);
+        return export::run_export(*format, out.as_deref(), learner);
+    }
+    if let Some(Commands::Import { files, merge, out }) = &args.command {
+        return export::run_import(files, *merge, out);
+    }
+    let filter = args.filter.as_deref().map(ChapterFilter::parse);
+    if let Some(Commands::List { json }) = &args.command {
+        return list_chapters(args.continuous, *json, filter.as_ref(), args.ignore_prereqs);
+    }
+
+    let auto_advance = args.auto_advance || TutorialConfig::load().auto_advance;
+    let share_dir = args.share_dir.as_deref();
+    if let Some(path) = &args.summary_out {
+        chapter_report::write_to(path.clone());
+    }
+    if let Some(ms) = args.poll_interval_ms {
+        config::set_poll_interval_ms(ms);
+    }
+    if args.capture_on_exit {
+        config::set_capture_always();
+    }
+    if let Some(path) = &args.socket_path {
+        // --auto-advance は全体の引数ではないため clap の conflicts_with は使えない（--keep-session と同じくここで断る）
+        if auto_advance {
+            return Err(anyhow::anyhow!("--socket-path は auto_advance（自動で次の章へ進む）と一緒には使えません"));
+        }
+        vim_rpc::set_address(path.clone());
+    }
+    if args.keep_session {
+        // 設定の auto_advance でも、学習者のいない実行でセッションを残し続けないよう断る
+        if auto_advance {
+            return Err(anyhow::anyhow!("--keep-session は auto_advance（自動で次の章へ進む）と一緒には使えません"));
+        }
+        config::set_keep_session();
+    }
+
+    // 簡易モードで練習できるのは連続学習モードの章だけ
+    let simple_capable = args.continuous
+        && args.command.is_none()
+        && args.practice_file.is_none()
+        && args.generate_sample.is_none()
+        && !args.test;
+    if args.simple {
+        if !simple_capable {
+            return Err(anyhow::anyhow!("--simple は連続学習モード（-c）の章でだけ使えます"));
+        }
+        simple_mode::enable();
+    }
+
+    // 練習に使うエディタ（Neovim / Vim）を選ぶ
+    // 見つからなければインストール方法を付けて終了する（main で表示する）
+    let info = if simple_mode::enabled() {
+        editor::select_for_simple_mode(args.editor)?
+    } else {
+        match editor::select(args.editor) {
+            Ok(info) => info,
+            // 練習を監視できない古いエディタでも、連続学習モードは最後の手段として簡易モードで続ける
+            Err(e) if simple_capable && matches!(e.downcast_ref(), Some(TutorialError::EditorTooOld { .. })) => {
+                warning!("⚠️ {:#}", e);
+                warning!("💡 簡易モードで続けます（エディタを閉じるたびに目標を判定します）");
+                simple_mode::enable();
+                editor::select_for_simple_mode(args.editor)?
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    info!("✓ {} が見つかりました（{}）", info.editor.label(), info.version);
+    if !info.unavailable_features().is_empty() {
+        info!("  ⚠️ {} では使えない機能があります（doctor で確認できます）", info.editor.label());
+    }
+    // Neovim の :terminal から練習を始めた場合は、その Neovim の新しいタブで練習するか尋ねる
+    let uses_session = args.continuous
+        || args.practice_file.is_some()
+        || matches!(args.command, Some(Commands::Drill { .. } | Commands::Challenge { .. }));
+    if uses_session
+        && !simple_mode::enabled()
+        && info.editor == editor::Editor::Neovim
+        && let Some(server) = editor::parent_neovim()
+        && prompt_use_parent_neovim()
+    {
+        editor::use_parent_server(server);
+    }
+    // 同じ進捗ファイルを別のセッションが使っていれば知らせる（止めはしない）
+    if let Some(heartbeat) = ProgressStore::load_default().live_session(chrono::Utc::now()) {
+        info!(
+            "⚠️ 別の学習セッション（PID {}）が実行中のようです。進捗の記録が混ざることがあります",
+            heartbeat.pid
+        );
+    }
+
+    if let Some(Commands::Drill {
+        count,
+        seed,
+        chapter,
+        goals,
+    }) = args.command
+    {
+        // 復習ドリル
+        run_drill_mode(count, seed, chapter, goals, share_dir)?;
+    } else if let Some(Commands::Challenge {
+        chapter,
+        exercise,
+        time_limit,
+    }) = args.command
+    {
+        // タイムチャレンジ
+        run_challenge_mode(chapter, exercise, time_limit, share_dir)?;
+    } else if let Some(path) = &args.practice_file {
+        // 手元のファイルのコピーで練習
+        run_practice_file(path, share_dir)?;
+    } else if let Some(topic) = args.generate_sample {
         // サンプル章を生成
-        let loader = ContinuousContentLoader::empty();
-        loader.create_sample_chapter(&output_path)?;
-        println!("✓ サンプル章を生成しました: {}", output_path);
+        generate_samples(&topic, &args.out_dir, args.bundle)?;
     } else if args.test {
         // テストモード
         test_neovim_integration()?;
     } else if args.continuous {
         // 連続学習モード
-        run_continuous_mode()?;
+        run_continuous_mode(auto_advance, filter, args.ignore_prereqs, share_dir)?;
     } else {
         // 従来のゲームモード
         let mut game = VimTutorialGame::new()?;
+        game.set_auto_advance(auto_advance);
+        game.set_filter(filter);
+        game.set_ignore_prereqs(args.ignore_prereqs);
         game.run()?;
     }
 
     Ok(())
 }
 
-fn check_neovim_available() -> Result<()> {
-    let output = Command::new("nvim")
-        .arg("--version")
-        .output()
-        .map_err(|_| anyhow::anyhow!("Neovim が見つかりません"))?;
-
-    if output.status.success() {
-        let version = String::from_utf8_lossy(&output.stdout);
-        println!(
-            "Neovim バージョン: {}",
-            version.lines().next().unwrap_or("不明")
-        );
-        Ok(())
+// 空の入力はこの Neovim を使う（入力が無いデモモードでは、これまで通り別に起動する）
+fn prompt_use_parent_neovim() -> bool {
+    say!("💡 Neovim の中から起動されています");
+    print!("{}", theme::current().line("この Neovim の新しいタブで練習しますか？（終わるとタブを閉じて元に戻します） [Y/n]: "));
+    let _ = io::stdout().flush();
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => false,
+        Ok(_) => !matches!(input.trim().to_lowercase().as_str(), "n" | "no"),
+    }
+}
+
+// JSON で出力する章の情報（章データの chapter に練習数と、読んだパックの名前を加えたもの）
+#[derive(serde::Serialize)]
+struct ChapterListing<'a> {
+    #[serde(flatten)]
+    chapter: &'a content::ChapterInfo,
+    exercises: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pack: Option<&'a str>,
+}
+
+// list で章を並べる読み込み結果（通常版と連続学習版）
+trait ChapterList {
+    fn print_chapters(&self, filter: Option<&ChapterFilter>, is_complete: &dyn Fn(u8) -> bool);
+    fn listings(&self) -> Vec<ChapterListing<'_>>;
+}
+
+impl ChapterList for content::ContentLoader {
+    fn print_chapters(&self, filter: Option<&ChapterFilter>, is_complete: &dyn Fn(u8) -> bool) {
+        self.list_chapters(filter, is_complete);
+    }
+
+    fn listings(&self) -> Vec<ChapterListing<'_>> {
+        self.chapters()
+            .iter()
+            .map(|ch| ChapterListing {
+                chapter: &ch.chapter,
+                exercises: ch.exercises.len(),
+                pack: None,
+            })
+            .collect()
+    }
+}
+
+impl ChapterList for ContinuousContentLoader {
+    fn print_chapters(&self, filter: Option<&ChapterFilter>, is_complete: &dyn Fn(u8) -> bool) {
+        self.list_chapters(filter, is_complete);
+    }
+
+    fn listings(&self) -> Vec<ChapterListing<'_>> {
+        self.chapters()
+            .iter()
+            .map(|ch| ChapterListing {
+                chapter: &ch.chapter,
+                exercises: ch.continuous_exercises.len(),
+                pack: self.pack_of(ch.chapter.number),
+            })
+            .collect()
+    }
+}
+
+fn list_chapters(
+    continuous: bool,
+    json: bool,
+    filter: Option<&ChapterFilter>,
+    ignore_prereqs: bool,
+) -> Result<()> {
+    let store = ProgressStore::load_default();
+    let mode = if continuous { LearningMode::Continuous } else { LearningMode::Classic };
+    let is_complete = |n| ignore_prereqs || store.is_chapter_complete(mode, n);
+    if continuous {
+        let (loader, report) = ContinuousContentLoader::load()?;
+        print_chapter_list(&loader, &report, json, filter, &is_complete)
     } else {
-        Err(anyhow::anyhow!("Neovim の実行に失敗しました"))
+        let (loader, report) = content::ContentLoader::load()?;
+        print_chapter_list(&loader, &report, json, filter, &is_complete)
     }
 }
 
+// 章の一覧を表示する（json では絞り込んだ章を、練習の数とパックを添えて JSON で書く）
+fn print_chapter_list(
+    loader: &impl ChapterList,
+    report: &content::LoadReport,
+    json: bool,
+    filter: Option<&ChapterFilter>,
+    is_complete: &dyn Fn(u8) -> bool,
+) -> Result<()> {
+    if !json {
+        report.print();
+        loader.print_chapters(filter, is_complete);
+        return Ok(());
+    }
+    let listings: Vec<_> = loader
+        .listings()
+        .into_iter()
+        .filter(|listing| filter.is_none_or(|f| f.matches(&listing.chapter.difficulty, &listing.chapter.tags)))
+        .collect();
+    report.print_warnings();
+    println!("{}", serde_json::to_string_pretty(&listings)?);
+    Ok(())
+}
+
+// トピック（all は全て）の章を out_dir に番号のファイル名で書く。トピック以外はパスとして motions の章を書く
+// bundle ではトピックの章を out_dir の1つの章のまとめに、パスには motions と words の2章のまとめを書く
+fn generate_samples(topic: &str, out_dir: &std::path::Path, bundle: bool) -> Result<()> {
+    let topics: Vec<&str> = match topic {
+        "all" => sample_chapters::TOPICS.to_vec(),
+        topic if sample_chapters::TOPICS.contains(&topic) => vec![topic],
+        output_path if bundle => {
+            ContinuousContentLoader::empty().create_sample_bundle(output_path)?;
+            info!("✓ サンプルの章のまとめを生成しました: {}", output_path);
+            return Ok(());
+        }
+        output_path => {
+            ContinuousContentLoader::empty().create_sample_chapter(output_path)?;
+            info!("✓ サンプル章を生成しました: {}", output_path);
+            return Ok(());
+        }
+    };
+    if bundle {
+        let chapters = topics.into_iter().filter_map(sample_chapters::generate).collect();
+        let path = out_dir.join(format!("{}samples.yaml", continuous_content::BUNDLE_PREFIX));
+        if sample_chapters::write_bundle(chapters, &path)? {
+            info!("✓ サンプルの章のまとめを生成しました: {}", path.display());
+        } else {
+            output::warning!("⚠️ {} が既にあるため書きませんでした", path.display());
+        }
+        return Ok(());
+    }
+    for topic in topics {
+        let Some(chapter) = sample_chapters::generate(topic) else {
+            continue;
+        };
+        match sample_chapters::write(&chapter, out_dir)? {
+            Some(path) => info!("✓ サンプル章を生成しました: {}（{}）", path.display(), chapter.chapter.title),
+            None => output::warning!(
+                "⚠️ {} には第{}章のファイルが既にあるため書きませんでした（{}）",
+                out_dir.display(),
+                chapter.chapter.number,
+                topic
+            ),
+        }
+    }
+    Ok(())
+}
+
 fn test_neovim_integration() -> Result<()> {
-    println!("\n=== Neovim連携テスト（Vimスクリプトアプローチ） ===");
+    say!("\n=== {}連携テスト（Vimスクリプトアプローチ） ===", editor::current().label());
 
     // サンプルテキストファイルを作成
     let sample_content = r#"function example() {
@@ -90,243 +699,913 @@ fn test_neovim_integration() -> Result<()> {
   console.log('Hello, ' + name);
 }"#;
 
-    let sample_file = NamedTempFile::new()?;
+    let sample_file = NamedTempFile::new_in(paths::cache_dir())?;
     fs::write(&sample_file, sample_content)?;
 
-    println!("✓ サンプルファイルを作成しました: {:?}", sample_file.path());
+    say!("✓ サンプルファイルを作成しました: {:?}", sample_file.path());
 
-    // Vimスクリプトを作成してキー入力をテスト
+    // Vimスクリプトを作成してキー入力をテスト（パスは一時ディレクトリ。Windows では %TEMP%）
+    let output_file = vim_backend::temp_file("vim_test_output.txt");
     let vim_script = format!(
         r#"
 " ファイルを開く
-edit {}
+execute 'edit ' . fnameescape('{sample_path}')
 
 " 初期位置に移動 (1行目, 1列目)
 normal! gg0
 
 " 現在のカーソル位置を出力
 let initial_pos = [line('.'), col('.')]
-call writefile(['INITIAL:' . initial_pos[0] . ',' . initial_pos[1]], '/tmp/vim_test_output.txt')
+call writefile(['INITIAL:' . initial_pos[0] . ',' . initial_pos[1]], '{output_file}')
 
 " キー入力をシミュレート: jjl (下下右)
 normal! jjl
 
 " 新しいカーソル位置を出力
 let final_pos = [line('.'), col('.')]
-call writefile(['FINAL:' . final_pos[0] . ',' . final_pos[1]], '/tmp/vim_test_output.txt', 'a')
+call writefile(['FINAL:' . final_pos[0] . ',' . final_pos[1]], '{output_file}', 'a')
 
 " 期待される位置と比較 (3行目, 2列目)
 if final_pos == [3, 2]
-    call writefile(['RESULT:SUCCESS'], '/tmp/vim_test_output.txt', 'a')
+    call writefile(['RESULT:SUCCESS'], '{output_file}', 'a')
 else
-    call writefile(['RESULT:FAILED'], '/tmp/vim_test_output.txt', 'a')
+    call writefile(['RESULT:FAILED'], '{output_file}', 'a')
 endif
 
 " 終了
 qa!
 "#,
-        sample_file.path().display()
+        sample_path = sample_file.path().display().to_string().replace('\'', "''"),
+        output_file = output_file.replace('\'', "''"),
     );
 
-    let script_file = NamedTempFile::new()?;
+    let script_file = NamedTempFile::new_in(paths::cache_dir())?;
     fs::write(&script_file, vim_script)?;
 
-    println!("✓ Vimスクリプトを作成しました");
+    say!("✓ Vimスクリプトを作成しました");
 
-    // Neovimでスクリプトを実行
-    let output = Command::new("nvim")
-        .arg("--headless")
+    // 選んだエディタでスクリプトを実行（Vim は -es で端末なしに実行する）
+    let editor = editor::current();
+    let output = Command::new(editor.program())
+        .args(editor.headless_args())
         .arg("-S")
         .arg(script_file.path())
         .output()?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow::anyhow!("Neovim実行エラー: {}", stderr));
+        return Err(anyhow::anyhow!("{}実行エラー: {}", editor.label(), stderr));
     }
 
-    println!("✓ Neovimスクリプトを実行しました");
+    say!("✓ {}スクリプトを実行しました", editor.label());
 
     // 結果を読み取り
-    if let Ok(result_content) = fs::read_to_string("/tmp/vim_test_output.txt") {
-        println!("=== テスト結果 ===");
+    if let Ok(result_content) = fs::read_to_string(&output_file) {
+        say!("=== テスト結果 ===");
         for line in result_content.lines() {
             if let Some(pos) = line.strip_prefix("INITIAL:") {
-                println!("初期カーソル位置: {}", pos);
+                say!("初期カーソル位置: {}", pos);
             } else if let Some(pos) = line.strip_prefix("FINAL:") {
-                println!("最終カーソル位置: {}", pos);
+                say!("最終カーソル位置: {}", pos);
             } else if let Some(result) = line.strip_prefix("RESULT:") {
                 if result == "SUCCESS" {
-                    println!("✓ キー入力の正解判定: 成功");
+                    say!("✓ キー入力の正解判定: 成功");
                 } else {
-                    println!("✗ キー入力の正解判定: 失敗");
+                    say!("✗ キー入力の正解判定: 失敗");
                 }
             }
         }
 
         // 一時ファイルをクリーンアップ
-        let _ = fs::remove_file("/tmp/vim_test_output.txt");
+        let _ = fs::remove_file(&output_file);
     } else {
         return Err(anyhow::anyhow!("テスト結果ファイルの読み取りに失敗"));
     }
 
-    println!("✓ Neovim連携テスト完了");
+    say!("✓ {}連携テスト完了", editor::current().label());
 
     Ok(())
 }
 
-fn run_continuous_mode() -> Result<()> {
-    println!("=== 🚀 連続学習モード ===\n");
+fn run_continuous_mode(
+    auto_advance: bool,
+    filter: Option<ChapterFilter>,
+    ignore_prereqs: bool,
+    share_dir: Option<&std::path::Path>,
+) -> Result<()> {
+    say!("=== 🚀 連続学習モード ===\n");
 
     // コンテンツローダーを初期化
-    let content_loader = match ContinuousContentLoader::new() {
-        Ok(loader) => loader,
+    let content_loader = match ContinuousContentLoader::load() {
+        Ok((loader, report)) => {
+            report.print();
+            loader
+        }
+        // 章ファイルはあるのに読み込めない場合（前提条件の誤りなど）はそのまま報告する
+        Err(e)
+            if !content::chapter_file_numbers(
+                &paths::chapters_dir(),
+                "continuous_chapter_",
+            )
+            .is_empty() =>
+        {
+            return Err(e);
+        }
         Err(_) => {
-            println!("📝 連続学習用のコンテンツが見つかりません。");
-            println!("サンプル章を生成しますか？ [y/N]: ");
+            say!("📝 連続学習用のコンテンツが見つかりません。");
+            say!("サンプル章を生成しますか？ [y/N]: ");
 
             let mut input = String::new();
             io::stdin().read_line(&mut input)?;
 
             if input.trim().to_lowercase() == "y" {
-                let sample_path = "data/chapters/continuous_chapter_01.yaml";
-                std::fs::create_dir_all("data/chapters")?;
+                let chapters_dir = paths::chapters_dir();
+                std::fs::create_dir_all(&chapters_dir)?;
+                let sample_path = chapters_dir.join("continuous_chapter_01.yaml");
 
                 let empty_loader = ContinuousContentLoader::empty();
-                empty_loader.create_sample_chapter(sample_path)?;
+                empty_loader.create_sample_chapter(&sample_path.to_string_lossy())?;
 
-                println!("\n✓ サンプル章を生成しました: {}", sample_path);
-                println!("プログラムを再起動してください。");
+                say!("\n✓ サンプル章を生成しました: {}", sample_path.display());
+                say!("プログラムを再起動してください。");
                 return Ok(());
             } else {
-                println!("連続学習モードを終了します。");
+                say!("連続学習モードを終了します。");
                 return Ok(());
             }
         }
     };
 
+    // 前回のプロセスが練習中に終了していれば、残ったエディタに再接続して続ける
+    let manifest_path = &paths::manifest_file();
+    if let Some(manifest) =
+        recovery::find_orphan(manifest_path, clean::pid_alive, recovery::tmux_session_exists)
+    {
+        if recovery::prompt_reattach(&manifest) {
+            let chapter_num = manifest.progress.chapter_number;
+            if let Ok(NextAction::Quit) = run_chapters(
+                &content_loader,
+                chapter_num,
+                (false, None),
+                auto_advance,
+                share_dir,
+                Some(manifest),
+            ) {
+                return Ok(());
+            }
+        } else {
+            recovery::discard(manifest_path, &manifest);
+        }
+    }
+
     // 章選択メニュー
+    let mut filter = filter;
     loop {
-        content_loader.list_chapters();
+        // 章を終えるたびに進捗が変わるため、毎回読み込み直す
+        let store = ProgressStore::load_default();
+        let is_complete =
+            |n| ignore_prereqs || store.is_chapter_complete(LearningMode::Continuous, n);
+        let completed = |n| store.is_chapter_complete(LearningMode::Continuous, n);
+        let chapter_numbers = content_loader.filtered_chapter_numbers(filter.as_ref());
 
-        println!(
-            "章番号を選択してください (1-{}, q=終了):",
-            content_loader.get_chapter_count()
-        );
-        print!("選択: ");
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(0) => {
-                println!("デモモード: 第1章を自動選択します。");
-                let _ = start_continuous_chapter(&content_loader, 1);
-                // 章完了後、メニューに戻る
-                continue;
+        // 端末では矢印キーで選ぶ一覧、それ以外（と ":" を押した場合）は番号を入力する
+        let chapter_lines = content_loader.menu_lines(filter.as_ref(), &is_complete, &completed);
+        let choice = match menu::choose_chapter("🚀 章選択（連続学習版）", &chapter_lines, filter.as_ref()) {
+            Some(choice) => Some(Ok(choice)),
+            None => {
+                content_loader.list_chapters(filter.as_ref(), &is_complete);
+                menu::read_chapter_choice(&chapter_numbers)
             }
-            Ok(_) => {
-                let input = input.trim();
-
-                if input == "q" || input == "quit" {
-                    println!("連続学習モードを終了します。");
-                    break;
-                }
-
-                if let Ok(chapter_num) = input.parse::<u8>() {
-                    if chapter_num >= 1 && chapter_num <= content_loader.get_chapter_count() as u8 {
-                        let _ = start_continuous_chapter(&content_loader, chapter_num);
-                        // 章完了後、メニューに戻る
+        };
+        let chapter_num = match choice {
+            None => {
+                // EOF - デモモードで最初の章を実行
+                let first = content_loader.chapter_numbers()[0];
+                say!("デモモード: 第{}章を自動選択します。", first);
+                first
+            }
+            Some(choice) => match choice {
+                Ok(ChapterChoice::Chapter(chapter_num)) => {
+                    let unmet = content_loader.unmet_prerequisites(chapter_num, &is_complete);
+                    if !unmet.is_empty() {
+                        say!("{}", menu::locked_message(chapter_num, &unmet));
                         continue;
-                    } else {
-                        println!(
-                            "❌ 無効な章番号です。1-{} の範囲で入力してください。",
-                            content_loader.get_chapter_count()
-                        );
                     }
-                } else {
-                    println!("❌ 数字または 'q' を入力してください。");
+                    chapter_num
                 }
-            }
-            Err(_) => {
-                println!("デモモード: 第1章を自動選択します。");
-                let _ = start_continuous_chapter(&content_loader, 1);
-                // 章完了後、メニューに戻る
-                continue;
-            }
+                Ok(ChapterChoice::Filter(new_filter)) => {
+                    filter = new_filter;
+                    continue;
+                }
+                Ok(ChapterChoice::Quit) => {
+                    say!("連続学習モードを終了します。");
+                    break;
+                }
+                Err(message) => {
+                    say!("{}", message);
+                    continue;
+                }
+            },
+        };
+
+        // 章の中のどの練習から始めるか選ぶ（q で章選択に戻る）
+        let Some(start) = choose_start_exercise(&content_loader, chapter_num) else {
+            continue;
+        };
+        if let Ok(NextAction::Quit) =
+            run_chapters(&content_loader, chapter_num, start, auto_advance, share_dir, None)
+        {
+            break;
         }
+        // 章完了後、メニューに戻る
     }
 
     Ok(())
 }
 
+// 練習メニューで始める練習を選ぶ（練習が1つの章は選ばずに始める）。
+// 戻り値は (前回の続きから始めるか, 始める練習)。None は章選択に戻る
+// 前回の記録のうち、先頭から続けていまの章データの練習（題名と内容が同じ）を指している分
+fn current_records(records: Vec<ExerciseSummary>, exercises: &[ContinuousExercise]) -> Vec<ExerciseSummary> {
+    records
+        .into_iter()
+        .zip(exercises)
+        .take_while(|(record, exercise)| record.refers_to(&exercise.title, &exercise.content_hash()))
+        .map(|(record, _)| record)
+        .collect()
+}
+
+// 一時停止した練習が、いまの章データにも同じ内容で残っているか
+fn paused_still_current(paused: &PausedExercise, exercises: &[ContinuousExercise]) -> bool {
+    exercises.get(paused.exercise_index).is_some_and(|exercise| {
+        exercise.title == paused.exercise_title
+            && paused.content_hash.as_ref().is_none_or(|hash| *hash == exercise.content_hash())
+    })
+}
+
+fn choose_start_exercise(
+    content_loader: &ContinuousContentLoader,
+    chapter_num: u8,
+) -> Option<(bool, Option<usize>)> {
+    let Some(chapter) = content_loader.get_chapter(chapter_num) else {
+        return Some((false, None));
+    };
+    let exercises = &chapter.continuous_exercises;
+    if exercises.len() <= 1 && chapter.lessons.is_empty() {
+        return Some((false, None));
+    }
+    let store = ProgressStore::load_default();
+    let records = TutorialConfig::load().records();
+    let entries: Vec<ExerciseEntry> = exercises
+        .iter()
+        .map(|exercise| {
+            let hash = exercise.content_hash();
+            let completion = store.exercise_completion(LearningMode::Continuous, chapter_num, &exercise.title, &hash);
+            ExerciseEntry {
+                title: &exercise.title,
+                count: exercise.goals.len(),
+                completed: completion == Completion::Current,
+                content_changed: completion == Completion::Outdated,
+                best: store.personal_record(&hash).filter(|_| records).map(stats::best_label),
+            }
+        })
+        .collect();
+    // 一時停止した練習、または前回の記録で先頭から続けて完了した練習の次（内容が変わった練習からは続けない）
+    let resumed = current_records(store.resume_exercises(LearningMode::Continuous, chapter_num), exercises).len();
+    let resume_at = store
+        .paused_exercise(chapter_num)
+        .filter(|paused| paused_still_current(paused, exercises))
+        .map(|paused| paused.exercise_index)
+        .or((resumed > 0).then_some(resumed))
+        .filter(|&index| index < exercises.len());
+    loop {
+        match menu::prompt_exercise_choice(&entries, "目標数", resume_at, !chapter.lessons.is_empty()) {
+            ExerciseChoice::Exercise(index) => return Some((false, Some(index))),
+            ExerciseChoice::Beginning => return Some((false, Some(0))),
+            ExerciseChoice::Resume => return Some((true, None)),
+            ExerciseChoice::Lessons => lesson::browse(&chapter.lessons),
+            ExerciseChoice::Back => return None,
+        }
+    }
+}
+
+// 章を実行し、完了後の選択に応じて次の章やドリルへ続ける（最後にメニューに戻るか終了するかを返す）。
+// start は最初の章の (前回の続きから始めるか, 始める練習)。
+// reattach があれば最初の章は前回のプロセスが残したエディタで続ける
+fn run_chapters(
+    content_loader: &ContinuousContentLoader,
+    chapter_num: u8,
+    start: (bool, Option<usize>),
+    auto_advance: bool,
+    share_dir: Option<&std::path::Path>,
+    reattach: Option<SessionManifest>,
+) -> Result<NextAction> {
+    let mut chapter_num = chapter_num;
+    let (mut resume, mut start_exercise_index) = start;
+    let mut reattach = reattach;
+    loop {
+        match start_continuous_chapter(
+            content_loader,
+            chapter_num,
+            resume,
+            start_exercise_index.take(),
+            auto_advance,
+            share_dir,
+            reattach.take(),
+        )? {
+            NextAction::Advance(next) => {
+                // 自動で進んだ章は、途中まで終えていれば続きから始める
+                chapter_num = next;
+                resume = true;
+            }
+            NextAction::Drill => {
+                run_drill_mode(DEFAULT_DRILL_COUNT, None, None, false, share_dir)?;
+                return Ok(NextAction::Menu);
+            }
+            action => return Ok(action),
+        }
+    }
+}
+
+// start_exercise_index を指定した場合はその練習から始める（一時停止した練習の確認もしない）
 fn start_continuous_chapter(
     content_loader: &ContinuousContentLoader,
     chapter_num: u8,
-) -> Result<()> {
+    resume: bool,
+    start_exercise_index: Option<usize>,
+    auto_advance: bool,
+    share_dir: Option<&std::path::Path>,
+    reattach: Option<SessionManifest>,
+) -> Result<NextAction> {
     if let Some(chapter) = content_loader.get_chapter(chapter_num) {
-        println!(
+        say!(
             "\n🎯 === 第{}章: {} ===",
             chapter.chapter.number, chapter.chapter.title
         );
-        println!("{}\n", chapter.chapter.description);
+        say!("{}\n", chapter.chapter.description);
+        let started_at = chrono::Utc::now();
 
         // 一意なソケットパスを生成
-        let socket_path = format!("/tmp/vim_tutorial_continuous_{}.sock", std::process::id());
-        let mut session = ContinuousVimSession::new(socket_path);
-
-        // 各練習を実行
-        for (exercise_index, exercise) in chapter.continuous_exercises.iter().enumerate() {
-            println!(
-                "📚 === 練習 {}/{}: {} ===",
-                exercise_index + 1,
-                chapter.continuous_exercises.len(),
-                exercise.title
+        let socket_path = vim_rpc::session_address();
+        let config = TutorialConfig::load();
+        let desktop_notifications = config.desktop_notifications;
+        let warmup = config.warmup;
+        let records = config.records();
+        let display = config.display();
+        let mut hooks = Hooks::from_config(&config);
+        let mut session = ContinuousVimSession::with_config(socket_path, config);
+        if let Some(dir) = share_dir {
+            session.share_status_in(dir);
+        }
+        session.set_pacing(!auto_advance);
+
+        // 章の結果表示用に練習ごとの記録を集める
+        let mut exercise_summaries: Vec<ExerciseSummary> = chapter
+            .continuous_exercises
+            .iter()
+            .map(|exercise| ExerciseSummary::not_started(&exercise.title))
+            .collect();
+
+        // 前回完了した練習の記録を引き継ぎ、最初の未完了の練習から始める
+        // （練習を選んだ場合は、その練習より前の分だけ引き継ぐ）
+        let mut start_index = 0;
+        if resume || start_exercise_index.is_some() {
+            let resumed = current_records(
+                ProgressStore::load_default().resume_exercises(LearningMode::Continuous, chapter.chapter.number),
+                &chapter.continuous_exercises,
             );
+            let limit = start_exercise_index.unwrap_or(usize::MAX);
+            for record in resumed.into_iter().take(limit) {
+                exercise_summaries[start_index] = record;
+                start_index += 1;
+            }
+            if let Some(index) = start_exercise_index {
+                start_index = index.min(chapter.continuous_exercises.len().saturating_sub(1));
+                if start_index > 0 {
+                    say!("⏭️ 練習{}から始めます\n", start_index + 1);
+                }
+            } else if start_index > 0 {
+                say!("↩️ 前回の続き（練習{}）から再開します\n", start_index + 1);
+            }
+        }
 
-            // サンプルファイルを作成
-            let sample_content = exercise.sample_code.join("\n");
-            let sample_file = NamedTempFile::new()?;
-            fs::write(&sample_file, sample_content)?;
-
-            // 練習を開始
-            session.start_exercise(exercise.clone(), sample_file.path().to_str().unwrap())?;
-
-            // 進行を監視
-            match session.monitor_progress()? {
-                ExerciseResult::Completed => {
-                    // 個別タスク完了時は即座に次へ（メッセージなし）
-                    if exercise_index < chapter.continuous_exercises.len() - 1 {
-                        // tmuxセッションをデタッチして次の練習の準備
-                        let _ = std::process::Command::new("tmux")
-                            .args(["detach-client", "-s", "vim_tutorial_continuous"])
-                            .output();
-                        
-                        // セッションを停止（次の練習のため）
-                        session.stop_exercise()?;
-                        std::thread::sleep(std::time::Duration::from_millis(500));
-                    } else {
-                        // 最後の練習完了 = 章完了
-                        session.stop_exercise()?;
-                        println!("🎉 第{}章「{}」を完了しました！", chapter.chapter.number, chapter.chapter.title);
-                        println!("お疲れ様でした！");
-                        break; // 練習ループを抜けてメニューに戻る
-                    }
+        let still_exists = |paused: &PausedExercise| paused_still_current(paused, &chapter.continuous_exercises);
+        let restore_earlier = |summaries: &mut [ExerciseSummary], paused: &PausedExercise| {
+            for (slot, record) in summaries.iter_mut().zip(&paused.earlier_exercises) {
+                if slot.title == record.title {
+                    *slot = record.clone();
                 }
-                ExerciseResult::Incomplete => {
-                    println!("⏸️ 練習が未完了です。セッションを終了します。");
-                    session.stop_exercise()?;
-                    break;
+            }
+        };
+
+        // 前回のプロセスが残したエディタに再接続する場合は、記録した目標から続ける
+        let mut paused_exercise = None;
+        if let Some(manifest) = reattach {
+            if still_exists(&manifest.progress) {
+                restore_earlier(&mut exercise_summaries, &manifest.progress);
+                start_index = manifest.progress.exercise_index;
+                say!(
+                    "▶️ 練習{}を目標{}から続けます\n",
+                    start_index + 1,
+                    manifest.progress.current_goal_index + 1
+                );
+                session.reattach_to(manifest.running);
+                paused_exercise = Some(manifest.progress);
+            } else {
+                say!("⚠️ 前回の練習が章データに見つからないか内容が変わったため、再接続せずに始めます");
+                recovery::discard(&paths::manifest_file(), &manifest);
+            }
+        }
+
+        // 一時停止したまま終了した練習があれば、その目標から再開するか確認する
+        if let Some(paused) = ProgressStore::load_default()
+            .paused_exercise(chapter.chapter.number)
+            .cloned()
+            .filter(|_| paused_exercise.is_none() && start_exercise_index.is_none() && !simple_mode::enabled())
+        {
+            if !still_exists(&paused) {
+                say!("⚠️ 一時停止した練習の内容が変わったため、続きからは再開しません");
+            } else if summary::prompt_resume_paused(&paused) {
+                restore_earlier(&mut exercise_summaries, &paused);
+                start_index = paused.exercise_index;
+                say!("▶️ 練習{}を一時停止した目標から再開します\n", start_index + 1);
+                paused_exercise = Some(paused);
+            }
+            progress::record_paused_exercise(None);
+        }
+
+        // 初めてのセッションでは、最初の練習の前にキーが届くかを確かめる
+        let simple = simple_mode::enabled();
+        if warmup && !simple && paused_exercise.is_none() && !ProgressStore::load_default().warmup_done() {
+            run_warmup(&mut session)?;
+        }
+
+        // 各練習を実行（エディタとの通信などで止まっても、それまでの結果ファイルは書く）
+        let outcome = (|| -> Result<()> {
+            for (exercise_index, exercise) in chapter
+                .continuous_exercises
+                .iter()
+                .enumerate()
+                .skip(start_index)
+            {
+                say!(
+                    "📚 === 練習 {}/{}: {} ===",
+                    exercise_index + 1,
+                    chapter.continuous_exercises.len(),
+                    exercise.title
+                );
+
+                // 一時停止したら、それまでの練習の記録と一緒に進捗ファイルへ残す
+                session.set_pause_target(Some(PauseTarget {
+                    chapter_number: chapter.chapter.number,
+                    exercise_index,
+                    earlier_exercises: exercise_summaries[..exercise_index].to_vec(),
+                }));
+                // 解説のページを読んでから練習を始める（途中の目標から続ける場合は読んだものとする）
+                if paused_exercise.is_none() {
+                    lesson::show_pages(&chapter.lessons_before(exercise_index), !auto_advance);
                 }
-                ExerciseResult::Failed(error) => {
-                    println!("❌ 練習でエラーが発生しました: {}", error);
-                    session.stop_exercise()?;
-                    break;
+                let (result, record) = if simple {
+                    simple_mode::run_exercise(exercise, editor::current())?
+                } else {
+                    run_exercise(&mut session, exercise, paused_exercise.take())?
+                };
+                if result == ExerciseResult::Completed {
+                    hooks.exercise_completed(chapter.chapter.number, &record);
+                    // Zen でも自己ベストは記録する（表示だけしない）
+                    if records
+                        && let Some(update) = progress::record_personal_best(chapter.chapter.number, &record)
+                        && display.shows_numbers()
+                    {
+                        for line in stats::record_lines(&update) {
+                            say!("{}", line);
+                        }
+                    }
+                }
+                exercise_summaries[exercise_index] = record;
+
+                match result {
+                    ExerciseResult::Completed => {
+                        // 個別タスク完了時は即座に次へ（メッセージなし）
+                        if exercise_index < chapter.continuous_exercises.len() - 1 {
+                            // tmuxセッションをデタッチして次の練習の準備
+                            if !simple {
+                                let _ = std::process::Command::new("tmux")
+                                    .args(["detach-client", "-s", "vim_tutorial_continuous"])
+                                    .output();
+                            }
+
+                            // セッションを停止（次の練習のため）
+                            stop_exercise(&mut session, simple)?;
+                            session.show_outro(exercise);
+                            std::thread::sleep(std::time::Duration::from_millis(500));
+                        } else {
+                            // 最後の練習完了 = 章完了
+                            stop_exercise(&mut session, simple)?;
+                            session.show_outro(exercise);
+                            say!("🎉 第{}章「{}」を完了しました！", chapter.chapter.number, chapter.chapter.title);
+                            if desktop_notifications {
+                                notify::send_desktop_notification(
+                                    "🎉 章完了",
+                                    &format!("第{}章「{}」を完了しました", chapter.chapter.number, chapter.chapter.title),
+                                );
+                            }
+                            say!("お疲れ様でした！");
+                            break; // 練習ループを抜けてメニューに戻る
+                        }
+                    }
+                    ExerciseResult::Incomplete | ExerciseResult::TimedOut => {
+                        say!("⏸️ 練習が未完了です。セッションを終了します。");
+                        stop_exercise(&mut session, simple)?;
+                        break;
+                    }
+                    ExerciseResult::Failed(error) => {
+                        say!("❌ 練習でエラーが発生しました: {}", error);
+                        stop_exercise(&mut session, simple)?;
+                        break;
+                    }
                 }
             }
+            Ok(())
+        })();
+
+        let summary = ChapterSummary {
+            mode: LearningMode::Continuous,
+            chapter_number: chapter.chapter.number,
+            chapter_title: chapter.chapter.title.clone(),
+            finished_at: chrono::Utc::now(),
+            exercises: exercise_summaries,
+        };
+        let error = outcome.as_ref().err().map(|e| format!("{:#}", e));
+        chapter_report::save(&ChapterReport::new(&summary, started_at, Some(chapter.content_hash()), error));
+        outcome?;
+        if summary.is_complete() {
+            hooks.chapter_completed(&summary);
         }
+        let next_chapter = progress::record_chapter_summary(summary.clone(), &content_loader.chapter_numbers())
+            .and_then(|n| content_loader.get_chapter(n))
+            .map(|c| (c.chapter.number, c.chapter.title.as_str()));
+        summary::print_chapter_summary(&summary, next_chapter, display);
+        Ok(summary::prompt_next_action(
+            &summary,
+            next_chapter.map(|(number, _)| number),
+            auto_advance,
+            true,
+        ))
     } else {
-        println!("❌ 第{}章が見つかりません。", chapter_num);
+        say!("❌ 第{}章が見つかりません。", chapter_num);
+        Ok(NextAction::Menu)
+    }
+}
+
+// 1つの練習を実行し、結果と記録を返す（セッションの停止は呼び出し側で行う）。
+// paused があれば一時停止した時点のファイルの内容と目標から始める
+fn run_exercise(
+    session: &mut ContinuousVimSession,
+    exercise: &ContinuousExercise,
+    paused: Option<PausedExercise>,
+) -> Result<(ExerciseResult, ExerciseSummary)> {
+    // サンプルファイルを作成
+    let sample_content = match &paused {
+        Some(paused) => paused.buffer.join("\n"),
+        None => exercise.sample_code.join("\n"),
+    };
+    let sample_file = NamedTempFile::new_in(paths::cache_dir())?;
+    fs::write(&sample_file, sample_content)?;
+    if let Some(paused) = paused {
+        session.resume_from(paused);
+    }
+
+    // 練習を開始
+    session.start_exercise(exercise.clone(), sample_file.path().to_str().unwrap())?;
+
+    // 進行を監視
+    let result = session.monitor_progress()?;
+    let record = ExerciseSummary {
+        title: exercise.title.clone(),
+        status: match &result {
+            ExerciseResult::Completed => ExerciseStatus::Completed,
+            ExerciseResult::Incomplete => ExerciseStatus::Incomplete,
+            ExerciseResult::Failed(_) => ExerciseStatus::Failed,
+            ExerciseResult::TimedOut => ExerciseStatus::TimedOut,
+        },
+        duration_secs: session.active_duration().as_secs_f64(),
+        hints_used: session.hints_used(),
+        goals_skipped: 0,
+        attempts: session.attempts(),
+        keystroke_scores: session.keystroke_scores().to_vec(),
+        content_hash: Some(exercise.content_hash()),
+    };
+
+    Ok((result, record))
+}
+
+// 練習のセッションを止める（簡易モードではエディタが既に閉じているため何もしない）
+fn stop_exercise(session: &mut ContinuousVimSession, simple: bool) -> Result<()> {
+    if simple {
+        return Ok(());
+    }
+    session.stop_exercise()
+}
+
+// 慣らしの練習を行う。通れば進捗ファイルに残して次からは行わず、通らなければ手がかりを出して本来の練習へ進む
+fn run_warmup(session: &mut ContinuousVimSession) -> Result<()> {
+    let exercise = warmup::exercise();
+    say!("🔰 === 慣らし: l・i・Esc が届くかを確かめます（{}秒） ===", warmup::TIME_LIMIT_SECONDS);
+    session.set_time_limit(exercise.time_limit_seconds);
+    let outcome = run_exercise(session, &exercise, None);
+    session.set_time_limit(None);
+    let (result, _) = outcome?;
+    session.stop_exercise()?;
+    match warmup::failure_message(&result, session.completed_goals()) {
+        None => {
+            say!("✅ キーは正しく届いています\n");
+            progress::record_warmup_done();
+        }
+        Some(message) => {
+            say!("⚠️ {}", message);
+            say!("💡 慣らしは次のセッションでもう一度行います（設定の warmup = false で行いません）\n");
+        }
+    }
+    Ok(())
+}
+
+fn run_drill_mode(
+    count: usize,
+    seed: Option<u64>,
+    chapter: Option<u8>,
+    per_goal: bool,
+    share_dir: Option<&std::path::Path>,
+) -> Result<()> {
+    say!("=== 🔁 復習ドリル ===\n");
+
+    let (content_loader, report) = ContinuousContentLoader::load()?;
+    report.print();
+    let store = ProgressStore::load_default();
+
+    // 完了済みの章だけを出題対象にする
+    let chapters: Vec<_> = content_loader
+        .chapter_numbers()
+        .into_iter()
+        .filter(|&n| chapter.is_none_or(|c| c == n))
+        .filter(|&n| store.is_chapter_complete(LearningMode::Continuous, n))
+        .filter_map(|n| content_loader.get_chapter(n))
+        .collect();
+
+    if chapters.is_empty() {
+        match chapter {
+            Some(c) => say!("📝 第{}章はまだ完了していません。先に章を完了してください。", c),
+            None => say!("📝 完了した章がありません。まずは連続学習モード（-c）で章を完了してください。"),
+        }
+        return Ok(());
+    }
+
+    let pool = drill::build_pool(&chapters, per_goal);
+    let seed = seed.unwrap_or_else(|| chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0) as u64);
+    let items = drill::sample(&pool, count, seed);
+    say!(
+        "📚 {}問を出題します（候補 {}問, シード {}）\n",
+        items.len(),
+        pool.len(),
+        seed
+    );
+
+    let socket_path = vim_rpc::session_address();
+    let mut session = ContinuousVimSession::with_config(socket_path, TutorialConfig::load());
+    if let Some(dir) = share_dir {
+        session.share_status_in(dir);
+    }
+    session.set_pacing(true);
+    let mut records = Vec::new();
+
+    for (index, item) in items.iter().enumerate() {
+        say!("📚 === ドリル {}/{}: {} ===", index + 1, items.len(), item.title);
+        let (result, record) = run_exercise(&mut session, item, None)?;
+        records.push(record);
+
+        let _ = std::process::Command::new("tmux")
+            .args(["detach-client", "-s", "vim_tutorial_continuous"])
+            .output();
+        session.stop_exercise()?;
+
+        if result != ExerciseResult::Completed {
+            say!("⏸️ ドリルを中断します。");
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(500));
+    }
+
+    say!("\n📊 === ドリルの結果 ===");
+    for line in summary::render_exercise_table(&records) {
+        say!("{}", line);
     }
 
     Ok(())
 }
+
+fn run_practice_file(path: &std::path::Path, share_dir: Option<&std::path::Path>) -> Result<()> {
+    // エディタを起動する前に、使えないファイルを断る
+    let exercise = practice_file::load(path)?;
+    say!("=== 📄 手元のファイルで練習 ===");
+    say!("{}（目標 {} 個）", exercise.title, exercise.goals.len());
+    say!("📝 元のファイル {} は変更しません。練習はコピーで行います", path.display());
+
+    let socket_path = vim_rpc::session_address();
+    let mut session = ContinuousVimSession::with_config(socket_path, TutorialConfig::load());
+    if let Some(dir) = share_dir {
+        session.share_status_in(dir);
+    }
+    session.set_pacing(true);
+    let (result, _) = run_exercise(&mut session, &exercise, None)?;
+    session.stop_exercise()?;
+
+    match result {
+        ExerciseResult::Completed => say!("🎉 全ての目標を達成しました"),
+        ExerciseResult::Failed(error) => say!("❌ 練習でエラーが発生しました: {}", error),
+        _ => say!("⏸️ 練習を中断しました"),
+    }
+    say!("📝 元のファイル {} は変更していません", path.display());
+    Ok(())
+}
+
+fn run_challenge_mode(
+    chapter_number: u8,
+    exercise_number: usize,
+    time_limit: Option<u64>,
+    share_dir: Option<&std::path::Path>,
+) -> Result<()> {
+    let (content_loader, report) = ContinuousContentLoader::load()?;
+    report.print();
+    let Some(chapter) = content_loader.get_chapter(chapter_number) else {
+        say!("❌ 第{}章が見つかりません", chapter_number);
+        return Ok(());
+    };
+    let Some(exercise) = exercise_number
+        .checked_sub(1)
+        .and_then(|index| chapter.continuous_exercises.get(index))
+    else {
+        say!(
+            "❌ 第{}章に練習{}はありません（1-{}）",
+            chapter_number,
+            exercise_number,
+            chapter.continuous_exercises.len()
+        );
+        return Ok(());
+    };
+
+    let limit = time_limit
+        .or(exercise.time_limit_seconds)
+        .unwrap_or(DEFAULT_CHALLENGE_SECONDS);
+    say!("=== ⏱️ タイムチャレンジ ===");
+    say!(
+        "第{}章「{}」: {}（制限時間 {}）",
+        chapter_number,
+        chapter.chapter.title,
+        exercise.title,
+        summary::format_duration(limit as f64)
+    );
+
+    let socket_path = vim_rpc::session_address();
+    let mut session = ContinuousVimSession::with_config(socket_path, TutorialConfig::load());
+    if let Some(dir) = share_dir {
+        session.share_status_in(dir);
+    }
+    session.set_pacing(true);
+    session.set_time_limit(Some(limit));
+    let (result, record) = run_exercise(&mut session, exercise, None)?;
+    session.stop_exercise()?;
+
+    if let ExerciseResult::Failed(error) = &result {
+        say!("❌ 練習でエラーが発生しました: {}", error);
+    }
+
+    // 中断した場合は記録しない
+    let previous_best = match result {
+        ExerciseResult::Completed | ExerciseResult::TimedOut => {
+            progress::record_challenge_attempt(ChallengeAttempt {
+                chapter_number,
+                exercise_title: exercise.title.clone(),
+                time_limit_secs: limit,
+                duration_secs: record.duration_secs,
+                passed: result == ExerciseResult::Completed,
+                attempted_at: chrono::Utc::now(),
+            })
+        }
+        _ => None,
+    };
+    summary::print_challenge_summary(&record, limit, previous_best);
+
+    Ok(())
+}
+
+fn show_stats() {
+    let store = ProgressStore::load_default();
+    let summaries = &store.data().summaries;
+    let challenges = &store.data().challenges;
+    if summaries.is_empty() && challenges.is_empty() {
+        say!("📊 まだ学習記録がありません。");
+        return;
+    }
+
+    say!("📊 === 学習履歴 ({}件) ===", summaries.len());
+    for summary in summaries {
+        let mode_label = match summary.mode {
+            LearningMode::Continuous => "連続学習",
+            LearningMode::Classic => "従来モード",
+        };
+        let grade_label = summary
+            .keystroke_efficiency()
+            .map(|efficiency| {
+                format!(
+                    " ⌨️ 評価 {} ({:.0}%)",
+                    keystrokes::Grade::from_efficiency(efficiency).label(),
+                    efficiency * 100.0
+                )
+            })
+            .unwrap_or_default();
+        say!(
+            "\n[{}] {} 第{}章「{}」{}",
+            summary
+                .finished_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M"),
+            mode_label,
+            summary.chapter_number,
+            summary.chapter_title,
+            grade_label
+        );
+        for line in summary::render_summary_table(summary) {
+            say!("  {}", line);
+        }
+    }
+
+    // 章ごとのキー効率の推移（古い順）
+    let mut trends: Vec<(u8, Vec<String>)> = Vec::new();
+    for summary in summaries {
+        let (Some(efficiency), Some(grade)) = (summary.keystroke_efficiency(), summary.grade()) else {
+            continue;
+        };
+        let entry = format!("{}({:.0}%)", grade.label(), efficiency * 100.0);
+        match trends.iter_mut().find(|(number, _)| *number == summary.chapter_number) {
+            Some((_, entries)) => entries.push(entry),
+            None => trends.push((summary.chapter_number, vec![entry])),
+        }
+    }
+    if !trends.is_empty() {
+        say!("\n⌨️ === キー効率の推移 ===");
+        for (number, entries) in trends {
+            say!("  第{}章: {}", number, entries.join(" → "));
+        }
+    }
+
+    // 完了した後に章データを書き換えた練習の記録
+    if let Ok((loader, _)) = ContinuousContentLoader::load() {
+        let outdated = store.outdated_completions(|number, title| {
+            let exercises = &loader.get_chapter(number)?.continuous_exercises;
+            exercises.iter().find(|exercise| exercise.title == title).map(|exercise| exercise.content_hash())
+        });
+        if outdated > 0 {
+            say!("\n📝 完了の記録のうち{}件は、その後に内容が変わった練習のものです（メニューでは ✓* と表示します）", outdated);
+        }
+    }
+
+    let records = &store.data().records;
+    if !records.is_empty() && TutorialConfig::load().records() {
+        say!("\n🏆 === 自己ベスト ({}件) ===", records.len());
+        for line in stats::render_records_table(records) {
+            say!("  {}", line);
+        }
+    }
+
+    if !challenges.is_empty() {
+        say!("\n⏱️ === タイムチャレンジ ({}回) ===", challenges.len());
+        let mut seen: Vec<(u8, &str)> = Vec::new();
+        for attempt in challenges {
+            let key = (attempt.chapter_number, attempt.exercise_title.as_str());
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+            let best = store
+                .best_challenge_time(key.0, key.1)
+                .map(summary::format_duration)
+                .unwrap_or_else(|| "未クリア".to_string());
+            say!("  第{}章 {}: 自己ベスト {}", key.0, key.1, best);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    // 引数の定義の誤り（全体の引数から全体でない引数への conflicts_with など）はサブコマンドを解析するときに
+    // 初めて panic するため、全てのサブコマンドをまとめて確かめる
+    #[test]
+    fn test_cli_definition_is_consistent() {
+        Args::command().debug_assert();
+        assert!(Args::try_parse_from(["vim-tutorial-nvim", "validate"]).is_ok());
+        assert!(Args::try_parse_from(["vim-tutorial-nvim", "--keep-session", "--socket-path", "/tmp/s", "list"]).is_ok());
+    }
+}
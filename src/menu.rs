@@ -0,0 +1,565 @@
+// 章選択メニューの入力の解釈（通常モードと連続学習モードで共通）
+use crate::content::Difficulty;
+use crate::theme::say;
+use crate::tui::{self, ListMenu, MenuEvent, MenuItem};
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChapterChoice {
+    Chapter(u8),
+    Filter(Option<ChapterFilter>), // None で絞り込みを解除
+    Quit,
+}
+
+// 章一覧の絞り込み条件
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChapterFilter {
+    Tag(String),
+    Difficulty(Difficulty),
+}
+
+impl ChapterFilter {
+    // --filter の値。難易度の名前ならその難易度、それ以外はタグとして扱う
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim();
+        match Difficulty::from_name(value) {
+            Some(difficulty) => ChapterFilter::Difficulty(difficulty),
+            None => ChapterFilter::Tag(value.to_string()),
+        }
+    }
+
+    pub fn matches(&self, difficulty: &Option<Difficulty>, tags: &[String]) -> bool {
+        match self {
+            ChapterFilter::Tag(tag) => tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            ChapterFilter::Difficulty(wanted) => difficulty.as_ref() == Some(wanted),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            ChapterFilter::Tag(tag) => format!("タグ #{}", tag),
+            ChapterFilter::Difficulty(difficulty) => format!("難易度 {}", difficulty.label()),
+        }
+    }
+}
+
+// 章一覧に添える難易度・目安時間・タグ（どれもなければ None）
+pub fn format_chapter_meta(
+    difficulty: Option<Difficulty>,
+    estimated_minutes: Option<u32>,
+    tags: &[String],
+) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(difficulty) = difficulty {
+        parts.push(format!("📶 {}", difficulty.label()));
+    }
+    if let Some(minutes) = estimated_minutes {
+        parts.push(format!("⏱️ 約{}分", minutes));
+    }
+    if !tags.is_empty() {
+        let tags: Vec<String> = tags.iter().map(|tag| format!("#{}", tag)).collect();
+        parts.push(format!("🏷️ {}", tags.join(" ")));
+    }
+    (!parts.is_empty()).then(|| parts.join("  "))
+}
+
+// まだ完了していない前提の章
+pub fn unmet_prerequisites(requires: &[u8], is_complete: &dyn Fn(u8) -> bool) -> Vec<u8> {
+    requires.iter().copied().filter(|&n| !is_complete(n)).collect()
+}
+
+fn chapter_names(numbers: &[u8]) -> String {
+    numbers
+        .iter()
+        .map(|n| format!("第{}章", n))
+        .collect::<Vec<_>>()
+        .join("・")
+}
+
+// 章一覧で鍵付きの章に添える説明
+pub fn lock_note(unmet: &[u8]) -> String {
+    format!("🔒 {}を完了すると選べます", chapter_names(unmet))
+}
+
+// 鍵付きの章を選んだときの案内
+pub fn locked_message(number: u8, unmet: &[u8]) -> String {
+    format!(
+        "🔒 第{}章はまだ選べません。先に{}を完了してください（講師は --ignore-prereqs で解除できます）。",
+        number,
+        chapter_names(unmet)
+    )
+}
+
+// メニューで使える絞り込みのコマンドの説明
+pub const FILTER_HELP: &str = "t タグ=タグで絞り込み, d 難易度=難易度で絞り込み, a=全て表示";
+
+// 選べる章の番号を示す入力の案内
+pub fn selection_prompt(numbers: &[u8]) -> String {
+    if numbers.is_empty() {
+        "条件に合う章がありません (a=全て表示, q=終了):".to_string()
+    } else {
+        format!("章番号を選択してください ({}, q=終了):", format_chapter_numbers(numbers))
+    }
+}
+
+// 章番号の一覧を "1-3, 5" のように連続する番号をまとめて表示する
+pub fn format_chapter_numbers(numbers: &[u8]) -> String {
+    let mut sorted = numbers.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    let mut ranges: Vec<(u8, u8)> = Vec::new();
+    for number in sorted {
+        match ranges.last_mut() {
+            Some((_, end)) if end.checked_add(1) == Some(number) => *end = number,
+            _ => ranges.push((number, number)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// 章選択の入力を1行読む（None は入力の終わり。EOF のデモモードでは呼び出し側が最初の章を選ぶ）
+pub fn read_chapter_choice(numbers: &[u8]) -> Option<Result<ChapterChoice, String>> {
+    say!("{}", selection_prompt(numbers));
+    say!("  {}", FILTER_HELP);
+    print!("選択: ");
+    let _ = io::stdout().flush();
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(parse_chapter_choice(&input, numbers)),
+    }
+}
+
+// 一覧のメニューに並べる章
+pub struct ChapterLine {
+    pub number: u8,
+    pub title: String,
+    pub completed: bool,
+    pub unmet: Vec<u8>, // まだ完了していない前提の章
+    pub details: Vec<String>,
+}
+
+const CHAPTER_MENU_FOOTER: &str = "↑↓/jk=移動, Enter=開く, :=番号や絞り込みを入力, q=終了";
+
+fn chapter_menu(title: &str, chapters: &[ChapterLine], filter: Option<&ChapterFilter>) -> ListMenu {
+    let items = chapters
+        .iter()
+        .map(|chapter| MenuItem {
+            label: format!(
+                "{} 第{}章: {}",
+                if !chapter.unmet.is_empty() {
+                    "🔒"
+                } else if chapter.completed {
+                    "✅"
+                } else {
+                    "⬜"
+                },
+                chapter.number,
+                chapter.title
+            ),
+            details: (!chapter.unmet.is_empty())
+                .then(|| lock_note(&chapter.unmet))
+                .into_iter()
+                .chain(chapter.details.iter().cloned())
+                .collect(),
+        })
+        .collect();
+    // 未完了の最初の章から選び始める
+    let cursor = chapters.iter().position(|chapter| !chapter.completed && chapter.unmet.is_empty()).unwrap_or(0);
+    let mut menu = ListMenu::new(title, items, cursor, CHAPTER_MENU_FOOTER);
+    menu.message = filter.map(|filter| format!("🔎 絞り込み: {}（: a で全て表示）", filter.describe()));
+    menu
+}
+
+// 章選択のキー。鍵付きの章を開こうとした場合は理由を出して選び直させる。
+// ":" は番号入力のメニューに切り替える（None）
+fn chapter_decision(chapters: &[ChapterLine], menu: &mut ListMenu, event: MenuEvent) -> Option<Option<ChapterChoice>> {
+    match event {
+        MenuEvent::Selected(index) => {
+            let chapter = &chapters[index];
+            if chapter.unmet.is_empty() {
+                return Some(Some(ChapterChoice::Chapter(chapter.number)));
+            }
+            menu.message = Some(locked_message(chapter.number, &chapter.unmet));
+            None
+        }
+        MenuEvent::Key('q') | MenuEvent::Back => Some(Some(ChapterChoice::Quit)),
+        MenuEvent::Key(':') => Some(None),
+        MenuEvent::Key(_) => None,
+    }
+}
+
+// 章選択の一覧を矢印キーで選ばせる（None は番号入力のメニューで選ぶ）
+pub fn choose_chapter(title: &str, chapters: &[ChapterLine], filter: Option<&ChapterFilter>) -> Option<ChapterChoice> {
+    let mut menu = chapter_menu(title, chapters, filter);
+    tui::run(&mut menu, |menu, event| chapter_decision(chapters, menu, event)).flatten()
+}
+
+// 入力を読み込まれている章の番号と照らし合わせる（エラーは表示用のメッセージ）
+pub fn parse_chapter_choice(input: &str, numbers: &[u8]) -> Result<ChapterChoice, String> {
+    let input = input.trim();
+    if input == "q" || input == "quit" {
+        return Ok(ChapterChoice::Quit);
+    }
+    if input == "a" || input == "all" {
+        return Ok(ChapterChoice::Filter(None));
+    }
+    if let Some((command, value)) = input.split_once(char::is_whitespace) {
+        let value = value.trim();
+        match command {
+            "t" | "tag" => return Ok(ChapterChoice::Filter(Some(ChapterFilter::Tag(value.to_string())))),
+            "d" | "difficulty" => {
+                return Difficulty::from_name(value)
+                    .map(|difficulty| ChapterChoice::Filter(Some(ChapterFilter::Difficulty(difficulty))))
+                    .ok_or_else(|| {
+                        format!(
+                            "❌ 難易度「{}」はありません。beginner / intermediate / advanced（初級 / 中級 / 上級）から選んでください。",
+                            value
+                        )
+                    });
+            }
+            _ => {}
+        }
+    }
+    if input.is_empty() || !input.chars().all(|c| c.is_ascii_digit()) {
+        return Err("❌ 数字または 'q' を入力してください。".to_string());
+    }
+    // "999" のように u8 に収まらない番号も、存在しない章として案内する
+    match input.parse::<u8>() {
+        Ok(number) if numbers.contains(&number) => Ok(ChapterChoice::Chapter(number)),
+        _ => {
+            let digits = input.trim_start_matches('0');
+            Err(format!(
+                "❌ 第{}章はありません。{} の中から選んでください。",
+                if digits.is_empty() { "0" } else { digits },
+                format_chapter_numbers(numbers)
+            ))
+        }
+    }
+}
+
+// 章を選んだ後の練習メニューの入力
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExerciseChoice {
+    Exercise(usize), // この練習から始める（0始まり）
+    Beginning,       // 最初の練習から
+    Resume,          // 前回の続きから
+    Lessons,         // 章の解説を読む
+    Back,            // 章選択に戻る
+}
+
+// 練習メニューの1行分（unit は "目標数" / "ステップ数"）
+pub struct ExerciseEntry<'a> {
+    pub title: &'a str,
+    pub count: usize,
+    pub completed: bool,
+    pub content_changed: bool, // 完了した後に練習の内容が変わった
+    pub best: Option<String>,  // 自己ベスト（stats::best_label。記録が無いか記録しない設定では None）
+}
+
+// 練習の一覧（完了の印と、続きから始める練習の印を付ける）
+pub fn exercise_menu_lines(entries: &[ExerciseEntry], unit: &str, resume_at: Option<usize>) -> Vec<String> {
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            format!(
+                "  {} {}. {}（{}: {}）{}{}{}",
+                if entry.completed {
+                    "✅"
+                } else if entry.content_changed {
+                    "✓*"
+                } else {
+                    "⬜"
+                },
+                i + 1,
+                entry.title,
+                unit,
+                entry.count,
+                entry.best.as_ref().map(|best| format!(" {}", best)).unwrap_or_default(),
+                if entry.content_changed && !entry.completed { " ※完了後に内容が変わりました" } else { "" },
+                if resume_at == Some(i) { " ← 前回の続き" } else { "" }
+            )
+        })
+        .collect()
+}
+
+// 未完了の最初の練習（全て完了していれば最初の練習）
+pub fn first_incomplete_exercise(entries: &[ExerciseEntry]) -> usize {
+    entries.iter().position(|entry| !entry.completed).unwrap_or(0)
+}
+
+pub fn exercise_selection_prompt(count: usize, resume_at: Option<usize>, has_lessons: bool) -> String {
+    let resume = match resume_at {
+        Some(index) => format!(", r=続きから（練習{}）, Enter=続きから", index + 1),
+        None => ", Enter=最初から".to_string(),
+    };
+    format!(
+        "練習番号を選択してください (1-{}, b=最初から{}{}, q=章選択に戻る):",
+        count,
+        resume,
+        if has_lessons { ", l=解説を読む" } else { "" }
+    )
+}
+
+// 練習メニューの入力を解釈する（空の入力は続きがあれば続きから、無ければ最初から）
+pub fn parse_exercise_choice(
+    input: &str,
+    count: usize,
+    can_resume: bool,
+    has_lessons: bool,
+) -> Result<ExerciseChoice, String> {
+    let input = input.trim();
+    match input {
+        "q" | "quit" => return Ok(ExerciseChoice::Back),
+        "b" | "begin" => return Ok(ExerciseChoice::Beginning),
+        "r" | "resume" if can_resume => return Ok(ExerciseChoice::Resume),
+        "r" | "resume" => return Err("❌ 続きから再開できる記録がありません。".to_string()),
+        "l" | "lessons" if has_lessons => return Ok(ExerciseChoice::Lessons),
+        "l" | "lessons" => return Err("❌ この章には解説がありません。".to_string()),
+        "" if can_resume => return Ok(ExerciseChoice::Resume),
+        "" => return Ok(ExerciseChoice::Beginning),
+        _ => {}
+    }
+    if !input.chars().all(|c| c.is_ascii_digit()) {
+        return Err("❌ 数字または b / r / l / q を入力してください。".to_string());
+    }
+    match input.parse::<usize>() {
+        Ok(number) if (1..=count).contains(&number) => Ok(ExerciseChoice::Exercise(number - 1)),
+        _ => Err(format!("❌ 練習{}はありません。1-{} の中から選んでください。", input, count)),
+    }
+}
+
+// 練習の一覧のメニューのキー（b・r・l は番号入力と同じ。q・Esc で章選択に戻る）
+fn exercise_decision(event: MenuEvent, can_resume: bool, has_lessons: bool) -> Option<ExerciseChoice> {
+    match event {
+        MenuEvent::Selected(index) => Some(ExerciseChoice::Exercise(index)),
+        MenuEvent::Key('b') => Some(ExerciseChoice::Beginning),
+        MenuEvent::Key('r') if can_resume => Some(ExerciseChoice::Resume),
+        MenuEvent::Key('l') if has_lessons => Some(ExerciseChoice::Lessons),
+        MenuEvent::Key('q') | MenuEvent::Back => Some(ExerciseChoice::Back),
+        MenuEvent::Key(_) => None,
+    }
+}
+
+fn exercise_menu(entries: &[ExerciseEntry], unit: &str, resume_at: Option<usize>, has_lessons: bool) -> ListMenu {
+    let items = exercise_menu_lines(entries, unit, resume_at)
+        .into_iter()
+        .map(|line| MenuItem {
+            label: line.trim_start().to_string(),
+            details: Vec::new(),
+        })
+        .collect();
+    let footer = format!(
+        "↑↓/jk=移動, Enter=この練習から, b=最初から{}{}, q=章選択に戻る",
+        if resume_at.is_some() { ", r=続きから" } else { "" },
+        if has_lessons { ", l=解説を読む" } else { "" }
+    );
+    let cursor = resume_at.unwrap_or_else(|| first_incomplete_exercise(entries));
+    ListMenu::new("📋 練習の選択", items, cursor, &footer)
+}
+
+// 練習メニューを表示して選ばせる（端末では矢印キーの一覧、それ以外では番号入力。
+// EOF のデモモードでは未完了の最初の練習を選ぶ）
+pub fn prompt_exercise_choice(
+    entries: &[ExerciseEntry],
+    unit: &str,
+    resume_at: Option<usize>,
+    has_lessons: bool,
+) -> ExerciseChoice {
+    let mut menu = exercise_menu(entries, unit, resume_at, has_lessons);
+    if let Some(choice) = tui::run(&mut menu, |_, event| exercise_decision(event, resume_at.is_some(), has_lessons)) {
+        return choice;
+    }
+    say!("\n📋 === 練習の選択 ===");
+    for line in exercise_menu_lines(entries, unit, resume_at) {
+        say!("{}", line);
+    }
+    loop {
+        say!("{}", exercise_selection_prompt(entries.len(), resume_at, has_lessons));
+        print!("選択: ");
+        let _ = io::stdout().flush();
+
+        let mut input = String::new();
+        match io::stdin().read_line(&mut input) {
+            Ok(0) | Err(_) => {
+                let first = first_incomplete_exercise(entries);
+                say!("デモモード: 練習{}を自動選択します。", first + 1);
+                return ExerciseChoice::Exercise(first);
+            }
+            Ok(_) => match parse_exercise_choice(&input, entries.len(), resume_at.is_some(), has_lessons) {
+                Ok(choice) => return choice,
+                Err(message) => say!("{}", message),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_chapter_numbers() {
+        assert_eq!(format_chapter_numbers(&[1, 2, 3]), "1-3");
+        assert_eq!(format_chapter_numbers(&[5, 2]), "2, 5");
+        assert_eq!(format_chapter_numbers(&[1, 2, 4, 10, 11, 12]), "1-2, 4, 10-12");
+        assert_eq!(format_chapter_numbers(&[254, 255]), "254-255");
+        assert_eq!(format_chapter_numbers(&[]), "");
+    }
+
+    #[test]
+    fn test_parse_chapter_choice_uses_loaded_numbers() {
+        let numbers = [2, 5];
+        assert_eq!(parse_chapter_choice("5", &numbers), Ok(ChapterChoice::Chapter(5)));
+        assert_eq!(parse_chapter_choice(" 02\n", &numbers), Ok(ChapterChoice::Chapter(2)));
+        assert_eq!(parse_chapter_choice("q", &numbers), Ok(ChapterChoice::Quit));
+        assert_eq!(
+            parse_chapter_choice("1", &numbers),
+            Err("❌ 第1章はありません。2, 5 の中から選んでください。".to_string())
+        );
+        assert_eq!(
+            parse_chapter_choice("999", &numbers),
+            Err("❌ 第999章はありません。2, 5 の中から選んでください。".to_string())
+        );
+        assert_eq!(
+            parse_chapter_choice("abc", &numbers),
+            Err("❌ 数字または 'q' を入力してください。".to_string())
+        );
+        assert!(parse_chapter_choice("-1", &numbers).is_err());
+        assert!(parse_chapter_choice("0", &numbers).unwrap_err().contains("第0章"));
+    }
+
+    #[test]
+    fn test_parse_filter_commands() {
+        let numbers = [1];
+        assert_eq!(
+            parse_chapter_choice("t motion", &numbers),
+            Ok(ChapterChoice::Filter(Some(ChapterFilter::Tag("motion".to_string()))))
+        );
+        assert_eq!(
+            parse_chapter_choice("d 中級", &numbers),
+            Ok(ChapterChoice::Filter(Some(ChapterFilter::Difficulty(Difficulty::Intermediate))))
+        );
+        assert_eq!(parse_chapter_choice("a", &numbers), Ok(ChapterChoice::Filter(None)));
+        assert!(parse_chapter_choice("d expert", &numbers).unwrap_err().contains("expert"));
+    }
+
+    #[test]
+    fn test_chapter_filter_matches() {
+        let tags = vec!["motion".to_string(), "basics".to_string()];
+        assert_eq!(ChapterFilter::parse("Beginner"), ChapterFilter::Difficulty(Difficulty::Beginner));
+        assert!(ChapterFilter::parse("Motion").matches(&None, &tags));
+        assert!(!ChapterFilter::parse("registers").matches(&None, &tags));
+        assert!(ChapterFilter::parse("beginner").matches(&Some(Difficulty::Beginner), &[]));
+        assert!(!ChapterFilter::parse("advanced").matches(&None, &tags));
+    }
+
+    #[test]
+    fn test_unmet_prerequisites() {
+        let complete = |n: u8| n == 1;
+        assert_eq!(unmet_prerequisites(&[1, 3, 4], &complete), vec![3, 4]);
+        assert!(unmet_prerequisites(&[1], &complete).is_empty());
+        assert_eq!(lock_note(&[3, 4]), "🔒 第3章・第4章を完了すると選べます");
+        assert!(locked_message(5, &[3]).starts_with("🔒 第5章はまだ選べません。先に第3章を完了してください"));
+    }
+
+    #[test]
+    fn test_format_chapter_meta() {
+        assert_eq!(format_chapter_meta(None, None, &[]), None);
+        assert_eq!(
+            format_chapter_meta(
+                Some(Difficulty::Beginner),
+                Some(15),
+                &["motion".to_string(), "basics".to_string()]
+            ),
+            Some("📶 初級  ⏱️ 約15分  🏷️ #motion #basics".to_string())
+        );
+        assert_eq!(format_chapter_meta(None, Some(5), &[]), Some("⏱️ 約5分".to_string()));
+    }
+
+    #[test]
+    fn test_parse_exercise_choice() {
+        assert_eq!(parse_exercise_choice("2", 3, false, false), Ok(ExerciseChoice::Exercise(1)));
+        assert_eq!(parse_exercise_choice("b", 3, true, false), Ok(ExerciseChoice::Beginning));
+        assert_eq!(parse_exercise_choice("q", 3, true, false), Ok(ExerciseChoice::Back));
+        assert_eq!(parse_exercise_choice("r", 3, true, false), Ok(ExerciseChoice::Resume));
+        assert!(parse_exercise_choice("r", 3, false, false).is_err());
+        // 空の入力は続きがあれば続きから
+        assert_eq!(parse_exercise_choice("\n", 3, true, false), Ok(ExerciseChoice::Resume));
+        assert_eq!(parse_exercise_choice("\n", 3, false, false), Ok(ExerciseChoice::Beginning));
+        assert_eq!(
+            parse_exercise_choice("4", 3, false, false),
+            Err("❌ 練習4はありません。1-3 の中から選んでください。".to_string())
+        );
+        assert!(parse_exercise_choice("0", 3, false, false).is_err());
+        assert!(parse_exercise_choice("x", 3, false, false).unwrap_err().contains("b / r / l / q"));
+        // 解説のある章だけ l で解説を読める
+        assert_eq!(parse_exercise_choice("l", 3, false, true), Ok(ExerciseChoice::Lessons));
+        assert!(parse_exercise_choice("l", 3, false, false).is_err());
+    }
+
+    #[test]
+    fn test_exercise_menu_marks_completed_and_resume_point() {
+        let entries = [
+            ExerciseEntry { title: "移動", count: 2, completed: true, content_changed: false, best: Some("🏆 42秒".to_string()) },
+            ExerciseEntry { title: "削除", count: 3, completed: false, content_changed: false, best: None },
+        ];
+        assert_eq!(
+            exercise_menu_lines(&entries, "目標数", Some(1)),
+            vec!["  ✅ 1. 移動（目標数: 2） 🏆 42秒", "  ⬜ 2. 削除（目標数: 3） ← 前回の続き"]
+        );
+        // 完了した後に内容が変わった練習は別の印にする
+        let changed = [ExerciseEntry { title: "移動", count: 2, completed: false, content_changed: true, best: None }];
+        assert_eq!(
+            exercise_menu_lines(&changed, "目標数", None),
+            vec!["  ✓* 1. 移動（目標数: 2） ※完了後に内容が変わりました"]
+        );
+        assert_eq!(first_incomplete_exercise(&changed), 0);
+        assert_eq!(first_incomplete_exercise(&entries), 1);
+        assert_eq!(first_incomplete_exercise(&entries[..1]), 0);
+        assert!(exercise_selection_prompt(2, Some(1), false).contains("r=続きから（練習2）"));
+        assert!(exercise_selection_prompt(2, None, true).contains("l=解説を読む"));
+    }
+
+    #[test]
+    fn test_chapter_menu_keys_skip_locked_chapters() {
+        use crate::tui::{Key, feed};
+        let chapters = [
+            ChapterLine { number: 1, title: "移動".to_string(), completed: true, unmet: Vec::new(), details: Vec::new() },
+            ChapterLine { number: 2, title: "削除".to_string(), completed: false, unmet: vec![3], details: Vec::new() },
+            ChapterLine { number: 3, title: "挿入".to_string(), completed: false, unmet: Vec::new(), details: Vec::new() },
+        ];
+        let mut menu = chapter_menu("章選択", &chapters, None);
+        // 未完了で選べる最初の章から始まる
+        assert_eq!(menu.cursor, 2);
+        let mut decide = |menu: &mut ListMenu, event| chapter_decision(&chapters, menu, event);
+        assert_eq!(feed(&mut menu, [Key::Up, Key::Enter], &mut decide), None);
+        assert_eq!(menu.message.as_deref(), Some(locked_message(2, &[3]).as_str()));
+        assert!(menu.lines().contains(&"▶️ 🔒 第2章: 削除".to_string()));
+        assert_eq!(feed(&mut menu, [Key::Char('k'), Key::Enter], &mut decide), Some(Some(ChapterChoice::Chapter(1))));
+        assert_eq!(feed(&mut menu, [Key::Char(':')], &mut decide), Some(None));
+        assert_eq!(feed(&mut menu, [Key::Escape], &mut decide), Some(Some(ChapterChoice::Quit)));
+
+        // 練習の一覧は続きの練習から選び始め、続きが無ければ r を無視する
+        let entries = [
+            ExerciseEntry { title: "移動", count: 2, completed: true, content_changed: false, best: None },
+            ExerciseEntry { title: "削除", count: 3, completed: false, content_changed: false, best: None },
+        ];
+        assert_eq!(exercise_menu(&entries, "目標数", None, false).cursor, 1);
+        assert_eq!(exercise_decision(MenuEvent::Key('r'), false, false), None);
+        assert_eq!(exercise_decision(MenuEvent::Key('r'), true, false), Some(ExerciseChoice::Resume));
+        assert_eq!(exercise_decision(MenuEvent::Selected(0), false, false), Some(ExerciseChoice::Exercise(0)));
+    }
+}
@@ -0,0 +1,167 @@
+// テキストの目標で、比べる前に期待する行と実際の行の両方にかける正規化（YAMLの target.normalize）。
+// IME で入力した全角の英数字や空白、飾りの引用符、行末の空白の違いで練習が終わらなくなるのを防ぐ。
+// 目標の判定と、指示の画面の差分・比較の表示は同じこの関数を使い、見せる違いと判定する違いを揃える
+use std::borrow::Cow;
+
+// normalize に書ける名前（この順にかける）
+pub const NAMES: &[&str] = &["width", "quotes", "trailing_space"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Normalize {
+    pub width: bool,          // 全角の英数字・記号・空白を半角にする
+    pub quotes: bool,         // 飾りの引用符（“ ” ‘ ’ など）をまっすぐな引用符にする
+    pub trailing_space: bool, // 行末の空白（全角の空白を含む）を取り除く
+}
+
+impl Normalize {
+    // 名前の並びから作る。知らない名前があればその名前を返す
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let mut normalize = Normalize::default();
+        for name in names {
+            match name {
+                "width" => normalize.width = true,
+                "quotes" => normalize.quotes = true,
+                "trailing_space" => normalize.trailing_space = true,
+                other => return Err(other.to_string()),
+            }
+        }
+        Ok(normalize)
+    }
+
+    pub fn is_none(&self) -> bool {
+        *self == Normalize::default()
+    }
+
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.is_none() {
+            return Cow::Borrowed(text);
+        }
+        let folded: String = text
+            .chars()
+            .map(|c| if self.width { half_width(c) } else { c })
+            .map(|c| if self.quotes { straight_quote(c) } else { c })
+            .collect();
+        if self.trailing_space {
+            Cow::Owned(folded.trim_end().to_string())
+        } else {
+            Cow::Owned(folded)
+        }
+    }
+
+    pub fn apply_lines<'a>(&self, lines: &'a [String]) -> Cow<'a, [String]> {
+        if self.is_none() {
+            return Cow::Borrowed(lines);
+        }
+        Cow::Owned(lines.iter().map(|line| self.apply(line).into_owned()).collect())
+    }
+}
+
+// 全角の英数字・記号（U+FF01〜U+FF5E）と全角の空白を半角にする（NFKC の幅の畳み込みのうち ASCII に当たるもの）
+pub fn half_width(c: char) -> char {
+    match c {
+        '\u{3000}' => ' ',
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        other => other,
+    }
+}
+
+// 飾りの引用符（スマートクォート）とプライム記号をまっすぐな引用符にする
+pub fn straight_quote(c: char) -> char {
+    match c {
+        '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => '\'',
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => '"',
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all() -> Normalize {
+        Normalize::from_names(NAMES.iter().copied()).unwrap()
+    }
+
+    #[test]
+    fn test_from_names() {
+        assert_eq!(Normalize::from_names([]), Ok(Normalize::default()));
+        assert!(Normalize::from_names([]).unwrap().is_none());
+        assert_eq!(
+            Normalize::from_names(["quotes"]),
+            Ok(Normalize {
+                quotes: true,
+                ..Normalize::default()
+            })
+        );
+        assert_eq!(
+            all(),
+            Normalize {
+                width: true,
+                quotes: true,
+                trailing_space: true,
+            }
+        );
+        assert_eq!(Normalize::from_names(["width", "nfkc"]), Err("nfkc".to_string()));
+    }
+
+    #[test]
+    fn test_half_width_covers_every_full_width_ascii_character() {
+        for ascii in '!'..='~' {
+            let full = char::from_u32(ascii as u32 + 0xFEE0).unwrap();
+            assert_eq!(half_width(full), ascii, "{}", full);
+        }
+        assert_eq!(half_width('\u{3000}'), ' ');
+        // 範囲の外と、ASCII に当たらない全角・半角の文字はそのまま
+        for c in ['a', ' ', 'あ', 'ア', 'ｱ', '、', '。', '「', '\u{FF00}', '\u{FF5F}', '￥', '“'] {
+            assert_eq!(half_width(c), c, "{}", c);
+        }
+    }
+
+    #[test]
+    fn test_straight_quote_covers_curly_quotes_and_primes() {
+        for c in ['‘', '’', '‚', '‛', '′'] {
+            assert_eq!(straight_quote(c), '\'', "{}", c);
+        }
+        for c in ['“', '”', '„', '‟', '″'] {
+            assert_eq!(straight_quote(c), '"', "{}", c);
+        }
+        for c in ['\'', '"', '`', '「', '」', '＂', 'a'] {
+            assert_eq!(straight_quote(c), c, "{}", c);
+        }
+    }
+
+    #[test]
+    fn test_apply_each_option_alone() {
+        let text = "print（“ｈｉ”）\u{3000}; ";
+        let only = |name| Normalize::from_names([name]).unwrap();
+        assert_eq!(Normalize::default().apply(text), text);
+        assert_eq!(only("width").apply(text), "print(“hi”) ; ");
+        assert_eq!(only("quotes").apply(text), "print（\"ｈｉ\"）\u{3000}; ");
+        assert_eq!(only("trailing_space").apply(text), "print（“ｈｉ”）\u{3000};");
+        assert_eq!(all().apply(text), "print(\"hi\") ;");
+    }
+
+    #[test]
+    fn test_apply_trailing_space_removes_full_width_and_tab_but_keeps_leading() {
+        let trailing = Normalize::from_names(["trailing_space"]).unwrap();
+        assert_eq!(trailing.apply("  x = 1;\t \u{3000}"), "  x = 1;");
+        assert_eq!(trailing.apply("   "), "");
+        assert_eq!(trailing.apply(""), "");
+    }
+
+    #[test]
+    fn test_apply_is_idempotent_and_keeps_ascii() {
+        let ascii = "let s = \"it's\";";
+        assert_eq!(all().apply(ascii), ascii);
+        let once = all().apply("ｌｅｔ　ｓ　＝　‘ａ’；　").into_owned();
+        assert_eq!(once, "let s = 'a';");
+        assert_eq!(all().apply(&once), once);
+    }
+
+    #[test]
+    fn test_apply_lines_borrows_when_nothing_is_enabled() {
+        let lines = vec!["ａ ".to_string(), "b".to_string()];
+        assert!(matches!(Normalize::default().apply_lines(&lines), Cow::Borrowed(_)));
+        assert_eq!(all().apply_lines(&lines).as_ref(), ["a".to_string(), "b".to_string()]);
+    }
+}
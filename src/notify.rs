@@ -0,0 +1,68 @@
+use std::process::Command;
+use std::thread;
+
+// デスクトップ通知を出すコマンド（Linux は notify-send、macOS は osascript。それ以外は無し）
+pub fn desktop_command(os: &str, title: &str, body: &str) -> Option<Command> {
+    match os {
+        "linux" => {
+            let mut command = Command::new("notify-send");
+            command.args(["--app-name", "vim-tutorial-nvim", title, body]);
+            Some(command)
+        }
+        "macos" => {
+            let mut command = Command::new("osascript");
+            command.args([
+                "-e",
+                &format!(
+                    "display notification \"{}\" with title \"{}\"",
+                    applescript_escape(body),
+                    applescript_escape(title)
+                ),
+            ]);
+            Some(command)
+        }
+        _ => None,
+    }
+}
+
+fn applescript_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// 通知を別スレッドで出す。コマンドが無い・失敗した場合も学習は続ける
+pub fn send_desktop_notification(title: &str, body: &str) {
+    let Some(mut command) = desktop_command(std::env::consts::OS, title, body) else {
+        return;
+    };
+    thread::spawn(move || {
+        let _ = command.output();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_desktop_command_per_platform() {
+        let linux = desktop_command("linux", "章完了", "第1章").unwrap();
+        assert_eq!(linux.get_program(), "notify-send");
+        assert_eq!(args(&linux)[2..], ["章完了", "第1章"]);
+
+        let macos = desktop_command("macos", "完了", "練習「\"a\"」").unwrap();
+        assert_eq!(macos.get_program(), "osascript");
+        assert_eq!(
+            args(&macos)[1],
+            r#"display notification "練習「\"a\"」" with title "完了""#
+        );
+
+        assert!(desktop_command("windows", "完了", "").is_none());
+    }
+}
@@ -0,0 +1,153 @@
+// 端末に出す案内の詳しさ（--quiet / --verbose）。
+// 起動時の経過や操作方法などの案内は info!（標準出力）、警告は warning!（標準エラー）を通す。
+// エラーと、メニュー・確認などの入力を待つ表示は、どの詳しさでも直接表示する
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,   // エラーだけ
+    Normal,  // 案内と警告も
+    Verbose, // デバッグログの行も標準エラーに出す
+}
+
+impl Verbosity {
+    pub fn from_flags(quiet: bool, verbose: bool) -> Self {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, true) => Verbosity::Verbose,
+            (false, false) => Verbosity::Normal,
+        }
+    }
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        _ => Verbosity::Verbose,
+    }
+}
+
+pub fn shows_info() -> bool {
+    verbosity() >= Verbosity::Normal
+}
+
+// debug_log! が書いた行を、--verbose のときは標準エラーにも出す
+pub fn mirror_debug(line: &str) {
+    if verbosity() == Verbosity::Verbose {
+        eprintln!("{}", line);
+    }
+}
+
+// デバッグログの書き先（キャッシュのディレクトリのファイル名と、行の見出し）。
+// debug_log! を使うファイルは、自分の書き先を DEBUG_LOG という名前の定数で決める
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DebugLog {
+    pub file: &'static str,
+    pub tag: &'static str,
+}
+
+impl DebugLog {
+    pub const TUTORIAL: DebugLog = DebugLog {
+        file: "vim_tutorial_debug.log",
+        tag: "DEBUG",
+    };
+    pub const ERROR: DebugLog = DebugLog {
+        file: "vim_tutorial_debug.log",
+        tag: "ERROR",
+    };
+    pub const CONTINUOUS: DebugLog = DebugLog {
+        file: "vim_continuous_debug.log",
+        tag: "CONTINUOUS_DEBUG",
+    };
+    pub const HOOK: DebugLog = DebugLog {
+        file: "vim_continuous_debug.log",
+        tag: "HOOK",
+    };
+
+    fn line(self, message: std::fmt::Arguments) -> String {
+        format!(
+            "[{}] 🔧 {}: {}",
+            chrono::Utc::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            self.tag,
+            message
+        )
+    }
+
+    pub fn write(self, message: std::fmt::Arguments) {
+        let line = self.line(message);
+        if let Ok(mut file) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(crate::paths::cache_file(self.file))
+        {
+            let _ = writeln!(file, "{}", line);
+        }
+        mirror_debug(&line);
+    }
+}
+
+// 案内（--quiet では表示しない）
+#[macro_export]
+macro_rules! info {
+    () => {
+        if $crate::output::shows_info() {
+            println!();
+        }
+    };
+    ($($arg:tt)*) => {
+        if $crate::output::shows_info() {
+            println!("{}", $crate::theme::current().line(&format!($($arg)*)));
+        }
+    };
+}
+
+// 続けられる問題の警告（--quiet では表示しない）
+#[macro_export]
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        if $crate::output::shows_info() {
+            eprintln!("{}", $crate::theme::current().line(&format!($($arg)*)));
+        }
+    };
+}
+
+// デバッグログに1行書く（書き先は呼び出したファイルの DEBUG_LOG）
+#[macro_export]
+macro_rules! debug_log {
+    ($($arg:tt)*) => {
+        DEBUG_LOG.write(format_args!($($arg)*))
+    };
+}
+
+pub use crate::{debug_log, info, warning};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quiet_wins_over_verbose() {
+        assert_eq!(Verbosity::from_flags(true, true), Verbosity::Quiet);
+        assert_eq!(Verbosity::from_flags(false, true), Verbosity::Verbose);
+        assert_eq!(Verbosity::from_flags(false, false), Verbosity::Normal);
+        assert!(Verbosity::Verbose > Verbosity::Normal && Verbosity::Normal > Verbosity::Quiet);
+    }
+
+    #[test]
+    fn test_debug_log_line_has_time_and_tag() {
+        let line = DebugLog::HOOK.line(format_args!("{} を実行", "on_complete"));
+        assert!(line.starts_with('['), "{}", line);
+        assert!(line.ends_with("] 🔧 HOOK: on_complete を実行"), "{}", line);
+        assert_eq!(DebugLog::ERROR.file, DebugLog::TUTORIAL.file);
+        assert_eq!(DebugLog::HOOK.file, DebugLog::CONTINUOUS.file);
+    }
+}
@@ -0,0 +1,620 @@
+// 章のパック（チームで配る連続学習の章のまとめ）のインストールとアンインストール。
+// パックは章データのディレクトリの packs/<名前>/ に置き、packs/installed.json に記録する。
+// 取得・展開・検証は packs/ の中の一時ディレクトリで行い、問題が無いときだけ置き換えるので、
+// 取得や検証に失敗してもインストール済みのパックはそのまま残る。
+// ダウンロードと tar（.tar.gz）の展開はこのプログラムの中で行い、git のリポジトリだけは git コマンドで取得する
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::content;
+use crate::continuous_content::{self, ContinuousContentLoader};
+use crate::output::{info, warning};
+use crate::paths;
+use crate::progress::ProgressStore;
+
+// 章データのディレクトリの中のパックの置き場所と、インストールの記録
+pub const PACKS_DIR: &str = "packs";
+const MANIFEST_FILE: &str = "installed.json";
+// パックに付ける名前と版（無ければ取得元の名前を使い、版は無し）
+const PACK_INFO_FILE: &str = "pack.yaml";
+// ダウンロードを諦めるまでの秒数
+const DOWNLOAD_TIMEOUT_SECS: u64 = 60;
+
+/// インストールしたパックの記録
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstalledPack {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// install に指定した URL かパス
+    pub source: String,
+    /// パックの章の内容のハッシュ（章ごとの content_hash をまとめたもの）
+    pub hash: String,
+    pub installed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    packs: Vec<InstalledPack>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PackInfo {
+    name: Option<String>,
+    version: Option<String>,
+}
+
+// 取得元の種類（git の URL は .git で終わるか git+ を付ける）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Http(String),
+    Git(String),
+    Local(PathBuf),
+}
+
+impl Source {
+    pub fn parse(source: &str) -> Self {
+        if let Some(url) = source.strip_prefix("git+") {
+            return Source::Git(url.to_string());
+        }
+        let is_url = source.starts_with("http://") || source.starts_with("https://");
+        if source.starts_with("git@") || source.starts_with("git://") || (is_url && source.ends_with(".git")) {
+            Source::Git(source.to_string())
+        } else if is_url {
+            Source::Http(source.to_string())
+        } else {
+            Source::Local(PathBuf::from(source))
+        }
+    }
+
+    // パックの名前の既定（URL やパスの最後の部分から拡張子を除いたもの）
+    fn default_name(&self) -> String {
+        let last = match self {
+            Source::Http(url) | Source::Git(url) => {
+                let url = url.split(['?', '#']).next().unwrap_or_default();
+                url.trim_end_matches('/').rsplit(['/', ':']).next().unwrap_or_default().to_string()
+            }
+            Source::Local(path) => path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+        };
+        [".git", ".tar.gz", ".tgz", ".tar", ".yaml", ".yml"]
+            .iter()
+            .find_map(|ext| last.strip_suffix(ext))
+            .unwrap_or(&last)
+            .to_string()
+    }
+}
+
+/// install の結果（previous は同じ名前のパックを置き換えた場合の前の記録）
+#[derive(Debug)]
+pub struct InstallOutcome {
+    pub pack: InstalledPack,
+    pub chapters: Vec<u8>,
+    pub previous: Option<InstalledPack>,
+    /// 内容が変わったため完了の印が「内容変更」になる完了の記録の数
+    pub outdated_completions: usize,
+}
+
+pub fn packs_dir(chapters_dir: &Path) -> PathBuf {
+    chapters_dir.join(PACKS_DIR)
+}
+
+pub fn pack_dir(chapters_dir: &Path, name: &str) -> PathBuf {
+    packs_dir(chapters_dir).join(name)
+}
+
+// インストールしたパック（名前順）。記録が無いか読めなければ空
+pub fn installed(chapters_dir: &Path) -> Vec<InstalledPack> {
+    load_manifest(chapters_dir).packs
+}
+
+fn load_manifest(chapters_dir: &Path) -> Manifest {
+    fs::read(packs_dir(chapters_dir).join(MANIFEST_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+        .unwrap_or_default()
+}
+
+// 一時ファイルに書いてから置き換える
+fn save_manifest(chapters_dir: &Path, manifest: &mut Manifest) -> Result<()> {
+    manifest.packs.sort_by(|a, b| a.name.cmp(&b.name));
+    let dir = packs_dir(chapters_dir);
+    fs::create_dir_all(&dir)?;
+    let mut partial = tempfile::NamedTempFile::new_in(&dir)?;
+    partial.write_all(serde_json::to_string_pretty(manifest)?.as_bytes())?;
+    partial.persist(dir.join(MANIFEST_FILE))?;
+    Ok(())
+}
+
+// パックの名前はディレクトリ名にするため、英数字・-・_ だけにする
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(anyhow!(
+            "パックの名前「{}」は使えません（英数字・-・_ だけにするか、{} の name で指定してください）",
+            name,
+            PACK_INFO_FILE
+        ));
+    }
+    Ok(())
+}
+
+fn run(command: &mut Command, what: &str) -> Result<()> {
+    let output = command
+        .output()
+        .map_err(|e| anyhow!("{}できません（{:?} を実行できません: {}）", what, command.get_program(), e))?;
+    if !output.status.success() {
+        return Err(anyhow!("{}できません: {}", what, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+// git のリポジトリを取得する前に、git コマンドがあるかを確かめる
+fn require_git() -> Result<()> {
+    match Command::new("git").arg("--version").output() {
+        Ok(output) if output.status.success() => Ok(()),
+        _ => Err(anyhow!(
+            "git のリポジトリからインストールするには git コマンドが必要です\
+             （git をインストールするか、http(s) の URL・ローカルのパスを指定してください）"
+        )),
+    }
+}
+
+fn download(url: &str, path: &Path) -> Result<()> {
+    let response = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(DOWNLOAD_TIMEOUT_SECS))
+        .build()
+        .get(url)
+        .call()
+        .map_err(|e| anyhow!("{} をダウンロードできません: {}", url, e))?;
+    let mut file = File::create(path)?;
+    io::copy(&mut response.into_reader(), &mut file)
+        .map_err(|e| anyhow!("{} をダウンロードできません: {}", url, e))?;
+    Ok(())
+}
+
+// 取得元を staging の中に取ってくる（ローカルのパスはそのまま返す）
+fn fetch(source: &Source, staging: &Path) -> Result<PathBuf> {
+    match source {
+        Source::Http(url) => {
+            let name = match source.default_name() {
+                name if url.ends_with(".tar.gz") || url.ends_with(".tgz") => format!("{}.tar.gz", name),
+                name if url.ends_with(".tar") => format!("{}.tar", name),
+                name => format!("{}.yaml", name),
+            };
+            let path = staging.join(name);
+            download(url, &path)?;
+            Ok(path)
+        }
+        Source::Git(url) => {
+            require_git()?;
+            let path = staging.join("clone");
+            run(
+                Command::new("git").args(["clone", "--depth", "1", "--quiet", url]).arg(&path),
+                &format!("{} を取得", url),
+            )?;
+            Ok(path)
+        }
+        Source::Local(path) if path.exists() => Ok(path.clone()),
+        Source::Local(path) => Err(anyhow!("{} がありません", path.display())),
+    }
+}
+
+// tar を out に展開する（gzip で圧縮したものは中身で見分ける。out の外に出るパスは tar が拒む）
+fn unpack(archive: &Path, out: &Path) -> Result<()> {
+    let mut magic = [0u8; 2];
+    let gzipped = File::open(archive)?.read_exact(&mut magic).is_ok() && magic == [0x1f, 0x8b];
+    let file = File::open(archive)?;
+    let unpacked = if gzipped {
+        tar::Archive::new(flate2::read::GzDecoder::new(file)).unpack(out)
+    } else {
+        tar::Archive::new(file).unpack(out)
+    };
+    unpacked.map_err(|e| anyhow!("{} を展開できません: {}", archive.display(), e))
+}
+
+fn is_tarball(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(".tar.gz") || name.ends_with(".tgz") || name.ends_with(".tar")
+}
+
+// パックの章ファイル（continuous_chapter_NN.yaml と continuous_bundle_*.yaml）の名前か
+fn is_chapter_file(name: &str) -> bool {
+    let numbered = name
+        .strip_prefix("continuous_chapter_")
+        .and_then(|rest| rest.strip_suffix(".yaml"))
+        .is_some_and(|digits| !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()));
+    numbered || (name.starts_with(continuous_content::BUNDLE_PREFIX) && name.ends_with(".yaml"))
+}
+
+// 取ってきたもの（YAML のファイルかディレクトリ）から、章ファイルと pack.yaml を out に集める。
+// 1つの YAML のファイルは、章のまとめとして読めるよう continuous_bundle_<名前>.yaml にする
+fn collect(fetched: &Path, out: &Path, name: &str) -> Result<PackInfo> {
+    fs::create_dir_all(out)?;
+    if fetched.is_file() {
+        let file_name = fetched.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let target = if is_chapter_file(&file_name) {
+            file_name
+        } else {
+            format!("{}{}.yaml", continuous_content::BUNDLE_PREFIX, name)
+        };
+        fs::copy(fetched, out.join(target))?;
+        return Ok(PackInfo::default());
+    }
+    // tar で固めたディレクトリは、展開すると1つのディレクトリの中に入っている
+    let mut root = fetched.to_path_buf();
+    let entries: Vec<PathBuf> = fs::read_dir(&root)?.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    if let [only] = entries.as_slice()
+        && only.is_dir()
+    {
+        root = only.clone();
+    }
+    let mut copied = 0;
+    for entry in fs::read_dir(&root)?.filter_map(|e| e.ok()) {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if is_chapter_file(&file_name) || file_name == PACK_INFO_FILE {
+            fs::copy(entry.path(), out.join(&file_name))?;
+            copied += usize::from(file_name != PACK_INFO_FILE);
+        }
+    }
+    if copied == 0 {
+        return Err(anyhow!(
+            "{} に章ファイル（continuous_chapter_NN.yaml か continuous_bundle_*.yaml）がありません",
+            root.display()
+        ));
+    }
+    match fs::read_to_string(out.join(PACK_INFO_FILE)) {
+        Ok(text) => Ok(content::parse_yaml(&text, PACK_INFO_FILE)?),
+        Err(_) => Ok(PackInfo::default()),
+    }
+}
+
+/// `source`（http(s) の URL・git のリポジトリ・ローカルの YAML / ディレクトリ / tar）のパックを
+/// `chapters_dir` にインストールする。同じ名前のパックは置き換え、内容が変わった練習の完了の記録を数える
+pub fn install(source: &str, chapters_dir: &Path, progress_path: &Path) -> Result<InstallOutcome> {
+    let parsed = Source::parse(source);
+    let packs_dir = packs_dir(chapters_dir);
+    fs::create_dir_all(&packs_dir)?;
+    // 置き換えを rename で済ませるよう、一時ディレクトリは packs/ の中に作る（失敗すると消える）
+    let staging = tempfile::Builder::new().prefix(".install-").tempdir_in(&packs_dir)?;
+    let mut fetched = fetch(&parsed, staging.path())?;
+    if fetched.is_file() && is_tarball(&fetched) {
+        let unpacked = staging.path().join("unpacked");
+        fs::create_dir_all(&unpacked)?;
+        unpack(&fetched, &unpacked)?;
+        fetched = unpacked;
+    }
+    let staged = staging.path().join("pack");
+    let info = collect(&fetched, &staged, &parsed.default_name())?;
+    let name = info.name.unwrap_or_else(|| parsed.default_name());
+    check_name(&name)?;
+
+    // validate と同じ検証を、インストール済みの章と合わせて行う（番号の重複と前提条件も調べる）
+    let (loader, report) = ContinuousContentLoader::load_tree(chapters_dir, Some((&name, &staged)))
+        .map_err(|e| anyhow!("パック「{}」をインストールできません: {:#}", name, e))?;
+    let chapters: Vec<&continuous_content::ContinuousChapterData> = loader
+        .chapters()
+        .iter()
+        .filter(|ch| loader.pack_of(ch.chapter.number) == Some(name.as_str()))
+        .collect();
+    let numbers: Vec<u8> = chapters.iter().map(|ch| ch.chapter.number).collect();
+    let staged_path = staged.display().to_string();
+    let problems: Vec<String> = report
+        .chapters
+        .iter()
+        .filter_map(|load| match load {
+            content::ChapterLoad::Issue { number, message } if numbers.contains(number) => {
+                Some(format!("第{}章: {}", number, message))
+            }
+            content::ChapterLoad::FailedFile { file, error } if file.starts_with(&staged_path) => {
+                Some(format!("{}: {}", file.trim_start_matches(&staged_path).trim_start_matches('/'), error))
+            }
+            _ => None,
+        })
+        .collect();
+    if !problems.is_empty() {
+        return Err(anyhow!(
+            "パック「{}」の章データに{}件の問題があるため、インストールしませんでした:\n  - {}",
+            name,
+            problems.len(),
+            problems.join("\n  - ")
+        ));
+    }
+    if numbers.is_empty() {
+        return Err(anyhow!("パック「{}」に読み込める章がありません", name));
+    }
+    let hashes: Vec<String> = chapters.iter().map(|ch| ch.content_hash()).collect();
+    let pack = InstalledPack {
+        name: name.clone(),
+        version: info.version,
+        source: source.to_string(),
+        hash: crate::continuous_session::fnv1a_hex(&hashes.join("\n")),
+        installed_at: Utc::now(),
+    };
+
+    // 前のパックを退避してから入れ替え、記録を書けなければ元に戻す
+    let mut manifest = load_manifest(chapters_dir);
+    let previous = manifest.packs.iter().position(|p| p.name == name).map(|i| manifest.packs.remove(i));
+    let target = pack_dir(chapters_dir, &name);
+    let backup = staging.path().join("previous");
+    if target.exists() {
+        fs::rename(&target, &backup)?;
+    }
+    manifest.packs.push(pack.clone());
+    let replaced = fs::rename(&staged, &target)
+        .map_err(anyhow::Error::from)
+        .and_then(|()| save_manifest(chapters_dir, &mut manifest));
+    if let Err(error) = replaced {
+        let _ = fs::remove_dir_all(&target);
+        if backup.exists() {
+            let _ = fs::rename(&backup, &target);
+        }
+        return Err(error);
+    }
+
+    // 完了の記録は練習の内容のハッシュ付きなので、内容が変わった練習の完了は「内容変更」の印になる。
+    // 一時停止した練習の内容が変わった場合は再開できないため、その記録を消す
+    let current = |number: u8, title: &str| {
+        chapters
+            .iter()
+            .find(|ch| ch.chapter.number == number)?
+            .continuous_exercises
+            .iter()
+            .find(|exercise| exercise.title == title)
+            .map(|exercise| exercise.content_hash())
+    };
+    let outdated_completions = if previous.as_ref().is_some_and(|p| p.hash != pack.hash) {
+        ProgressStore::update(progress_path, |store| {
+            if let Some(paused) = store.data().paused.as_ref()
+                && numbers.contains(&paused.chapter_number)
+                && current(paused.chapter_number, &paused.exercise_title) != paused.content_hash
+            {
+                store.set_paused(None);
+            }
+            store.outdated_completions_in(&numbers, current)
+        })?
+    } else {
+        0
+    };
+    Ok(InstallOutcome {
+        pack,
+        chapters: numbers,
+        previous,
+        outdated_completions,
+    })
+}
+
+/// インストールしたパックを章ファイルごと削除する
+pub fn uninstall(name: &str, chapters_dir: &Path) -> Result<InstalledPack> {
+    let mut manifest = load_manifest(chapters_dir);
+    let index = manifest.packs.iter().position(|p| p.name == name).ok_or_else(|| {
+        let names: Vec<&str> = manifest.packs.iter().map(|p| p.name.as_str()).collect();
+        match names.is_empty() {
+            true => anyhow!("パック「{}」はインストールされていません", name),
+            false => anyhow!("パック「{}」はインストールされていません（インストール済み: {}）", name, names.join(", ")),
+        }
+    })?;
+    let removed = manifest.packs.remove(index);
+    save_manifest(chapters_dir, &mut manifest)?;
+    let dir = pack_dir(chapters_dir, name);
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(removed)
+}
+
+// install サブコマンド
+pub fn run_install(source: &str, chapters_dir: &Path) -> Result<()> {
+    let outcome = install(source, chapters_dir, &paths::progress_file())?;
+    let pack = &outcome.pack;
+    let version = pack.version.as_deref().map(|v| format!(" {}", v)).unwrap_or_default();
+    let chapters: Vec<String> = outcome.chapters.iter().map(|n| format!("第{}章", n)).collect();
+    match &outcome.previous {
+        Some(previous) if previous.hash == pack.hash => {
+            info!("✓ パック「{}」{}を入れ直しました（内容は同じです）", pack.name, version)
+        }
+        Some(previous) => info!(
+            "✓ パック「{}」を{}から{}に更新しました",
+            pack.name,
+            previous.version.as_deref().unwrap_or("前の版"),
+            pack.version.as_deref().unwrap_or("新しい版")
+        ),
+        None => info!("✓ パック「{}」{}をインストールしました", pack.name, version),
+    }
+    info!("  {}（{}）", chapters.join("・"), pack_dir(chapters_dir, &pack.name).display());
+    if outcome.outdated_completions > 0 {
+        warning!(
+            "⚠️ 内容が変わった練習の完了の記録が{}件あります（練習メニューでは「内容変更」と表示します）",
+            outcome.outdated_completions
+        );
+    }
+    Ok(())
+}
+
+// uninstall サブコマンド
+pub fn run_uninstall(name: &str, chapters_dir: &Path) -> Result<()> {
+    let removed = uninstall(name, chapters_dir)?;
+    info!("✓ パック「{}」をアンインストールしました（取得元: {}）", removed.name, removed.source);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter_yaml(number: u8, goal_target: &str) -> String {
+        format!(
+            r#"chapter: {{ number: {number}, title: "第{number}章", description: "" }}
+continuous_exercises:
+  - title: "右へ"
+    description: "l で右に移動します"
+    flow_type: "sequential"
+    sample_code: ["hello"]
+    goals:
+      - type: "position"
+        target: {goal_target}
+        description: "移動する"
+"#
+        )
+    }
+
+    fn write_pack(dir: &Path, version: &str, goal_target: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(PACK_INFO_FILE), format!("name: team\nversion: \"{}\"\n", version)).unwrap();
+        fs::write(dir.join("continuous_chapter_07.yaml"), chapter_yaml(7, goal_target)).unwrap();
+        fs::write(dir.join("README.md"), "使い方").unwrap();
+    }
+
+    #[test]
+    fn test_install_upgrade_and_uninstall_a_pack() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let chapters_dir = tmp.path().join("chapters");
+        fs::create_dir_all(&chapters_dir)?;
+        fs::write(chapters_dir.join("continuous_chapter_01.yaml"), chapter_yaml(1, "[0, 1]"))?;
+        let progress_path = tmp.path().join("progress.json");
+        let source = tmp.path().join("team-pack");
+        write_pack(&source, "1.0", "[0, 4]");
+
+        let outcome = install(&source.display().to_string(), &chapters_dir, &progress_path)?;
+        assert_eq!(outcome.pack.name, "team");
+        assert_eq!(outcome.pack.version.as_deref(), Some("1.0"));
+        assert_eq!(outcome.chapters, vec![7]);
+        assert!(outcome.previous.is_none());
+        assert!(!pack_dir(&chapters_dir, "team").join("README.md").exists());
+        let (loader, _) = ContinuousContentLoader::load_from(&chapters_dir)?;
+        assert_eq!(loader.chapter_numbers(), vec![1, 7]);
+        assert_eq!(loader.pack_of(7), Some("team"));
+        assert_eq!(loader.pack_of(1), None);
+
+        // 第7章を完了した記録は、内容が変わると「内容変更」になる
+        let hash = loader.get_chapter(7).unwrap().continuous_exercises[0].content_hash();
+        ProgressStore::update(&progress_path, |store| {
+            store.append_summary(crate::summary::ChapterSummary {
+                mode: crate::progress::LearningMode::Continuous,
+                chapter_number: 7,
+                chapter_title: "第7章".to_string(),
+                finished_at: Utc::now(),
+                exercises: vec![crate::summary::ExerciseSummary {
+                    status: crate::summary::ExerciseStatus::Completed,
+                    content_hash: Some(hash),
+                    ..crate::summary::ExerciseSummary::not_started("右へ")
+                }],
+            })
+        })?;
+        write_pack(&source, "1.1", "[0, 3]");
+        let outcome = install(&source.display().to_string(), &chapters_dir, &progress_path)?;
+        assert_eq!(outcome.previous.unwrap().version.as_deref(), Some("1.0"));
+        assert_eq!(outcome.outdated_completions, 1);
+        assert_eq!(installed(&chapters_dir).len(), 1);
+
+        uninstall("team", &chapters_dir)?;
+        assert!(installed(&chapters_dir).is_empty());
+        assert!(!pack_dir(&chapters_dir, "team").exists());
+        assert!(uninstall("team", &chapters_dir).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_install_leaves_the_existing_pack_untouched() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let chapters_dir = tmp.path().join("chapters");
+        let progress_path = tmp.path().join("progress.json");
+        let source = tmp.path().join("team");
+        write_pack(&source, "1.0", "[0, 4]");
+        install(&source.display().to_string(), &chapters_dir, &progress_path)?;
+        let before = fs::read_to_string(pack_dir(&chapters_dir, "team").join("continuous_chapter_07.yaml"))?;
+        let manifest = installed(&chapters_dir);
+
+        // 範囲外の位置の目標（validate の問題）・取得できない URL・章ファイルの無いディレクトリ
+        write_pack(&source, "2.0", "[5, 0]");
+        let error = install(&source.display().to_string(), &chapters_dir, &progress_path).unwrap_err();
+        assert!(error.to_string().contains("インストールしませんでした"), "{}", error);
+        let error = install("http://127.0.0.1:9/team.yaml", &chapters_dir, &progress_path).unwrap_err();
+        assert!(error.to_string().contains("をダウンロードできません"), "{}", error);
+        let empty = tmp.path().join("empty");
+        fs::create_dir_all(&empty)?;
+        assert!(install(&empty.display().to_string(), &chapters_dir, &progress_path).is_err());
+
+        assert_eq!(
+            fs::read_to_string(pack_dir(&chapters_dir, "team").join("continuous_chapter_07.yaml"))?,
+            before
+        );
+        assert_eq!(installed(&chapters_dir), manifest);
+        // 一時ディレクトリは残らない
+        let leftovers: Vec<_> = fs::read_dir(packs_dir(&chapters_dir))?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().starts_with('.'))
+            .collect();
+        assert!(leftovers.is_empty());
+        Ok(())
+    }
+
+    // 1回だけ body を返す http のサーバー（取得元の URL を返す）
+    fn serve_once(path: &str, body: Vec<u8>) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/{}", listener.local_addr().unwrap(), path);
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            let header = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+            stream.write_all(header.as_bytes()).unwrap();
+            stream.write_all(&body).unwrap();
+        });
+        url
+    }
+
+    fn tar_gz(dir: &Path) -> Vec<u8> {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+        builder.append_dir_all("team-pack", dir).unwrap();
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn test_install_from_http_and_tarball() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let chapters_dir = tmp.path().join("chapters");
+        let progress_path = tmp.path().join("progress.json");
+
+        // 1つの YAML は章のまとめとして置く
+        let url = serve_once("course.yaml", chapter_yaml(8, "[0, 2]").into_bytes());
+        let outcome = install(&url, &chapters_dir, &progress_path)?;
+        assert_eq!(outcome.pack.name, "course");
+        assert_eq!(outcome.chapters, vec![8]);
+        assert!(pack_dir(&chapters_dir, "course").join("continuous_bundle_course.yaml").exists());
+
+        // tar.gz は拡張子ではなく中身で見分け、1つのディレクトリに入っていれば中を使う
+        let source = tmp.path().join("team-pack");
+        write_pack(&source, "1.0", "[0, 4]");
+        let archive = tar_gz(&source);
+        let local = tmp.path().join("team-pack.tgz");
+        fs::write(&local, &archive)?;
+        assert_eq!(install(&local.display().to_string(), &chapters_dir, &progress_path)?.chapters, vec![7]);
+        let url = serve_once("team-pack.tar.gz", archive);
+        let outcome = install(&url, &chapters_dir, &progress_path)?;
+        assert_eq!(outcome.pack.version.as_deref(), Some("1.0"));
+        assert_eq!(installed(&chapters_dir).len(), 2);
+
+        // 壊れた tar は展開できない
+        let broken = tmp.path().join("broken.tar.gz");
+        fs::write(&broken, [0x1f, 0x8b, 0, 1, 2])?;
+        let error = install(&broken.display().to_string(), &chapters_dir, &progress_path).unwrap_err();
+        assert!(error.to_string().contains("を展開できません"), "{}", error);
+        Ok(())
+    }
+
+    #[test]
+    fn test_source_kinds_and_default_names() {
+        assert_eq!(Source::parse("https://example.com/team.git"), Source::Git("https://example.com/team.git".into()));
+        assert_eq!(Source::parse("git+https://example.com/team"), Source::Git("https://example.com/team".into()));
+        assert_eq!(Source::parse("https://example.com/course.yaml?raw=1").default_name(), "course");
+        assert_eq!(Source::parse("packs/vim-basics.tar.gz").default_name(), "vim-basics");
+        assert_eq!(Source::parse("git@example.com:team/lessons.git").default_name(), "lessons");
+        assert!(check_name("team pack").is_err());
+    }
+}
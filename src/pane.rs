@@ -0,0 +1,548 @@
+// 指示ペインの描画。練習の内容・進み具合（ProgressSnapshot）・その時々の表示（PaneNotes）から行を組み立てる
+// 純粋な関数で、練習のセッションと作者向けの preview が同じものを使う（エディタも時計も使わない）
+use crate::continuous_session::{ContinuousExercise, ExerciseGoal, FlowType};
+use crate::layout::{self, DisplayPolicy, PaneLayout, ProgressCounts};
+use crate::session_progress::{GoalStatus, ProgressSnapshot};
+use crate::vim_state::VimMode;
+
+// これより目標が多い練習では一覧をまとめて表示する（指示ペインを20行程度に収める）
+const MAX_CHECKLIST_GOALS: usize = 8;
+// まとめて表示するときに並べる未達成の目標の数
+const COLLAPSED_CHECKLIST_GOALS: usize = 5;
+// 許していない操作でやり直しにしたときに添える、練習全体のやり直しの案内
+const RETRY_OFFER: &str = "🔁 :TutorialRetry で練習を最初からやり直せます";
+// 端末の大きさが分からない場合の指示ペインの桁数
+pub const DEFAULT_PANE_WIDTH: usize = 80;
+// 端末の大きさが分からないときに見積もる指示ペインの行数
+pub const DEFAULT_PANE_HEIGHT: usize = 20;
+pub const COMMAND_FOOTER: &str = "⌨️  :TutorialHint でヒント / :TutorialReset で最初からやり直し";
+
+// 指示ペインの大きさと見せ方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaneView {
+    pub layout: PaneLayout,
+    pub width: usize,  // 桁数（簡易表示の切り詰めに使う）
+    pub height: usize, // 行数（参考の枠に使える行の見積もりに使う）
+    pub display: DisplayPolicy,
+}
+
+// 進み具合の他に、その時々でペインに出す表示（セッションでは表示中の値）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaneNotes {
+    pub intro: bool, // 練習の intro を出す
+    pub position: Option<String>,
+    pub operator_pending: Option<String>,
+    pub conduct_notice: Option<String>,
+    pub demo: Option<Option<bool>>, // 解答を再生している（中は送り終えて目標を達成したか。送っている間は None）
+    pub stalled_text: Vec<String>,
+    pub text_diff: Vec<String>,
+    pub nudge: Option<String>,
+    pub coaching: Option<String>,
+    pub goal_command_failure: Option<String>,
+    pub countdown: Option<u64>, // ヒントまでの秒数
+    pub remaining: Option<u64>, // 制限時間の残り秒数
+}
+
+// 練習中の指示ペインの行（全ての目標を達成していれば完了の表示）
+pub fn render(exercise: &ContinuousExercise, snapshot: &ProgressSnapshot, notes: &PaneNotes, view: &PaneView) -> Vec<String> {
+    let Some(current) = snapshot.current_goal_index else {
+        return completion(exercise, view.display);
+    };
+    if view.layout == PaneLayout::Compact {
+        return compact(exercise, snapshot, current, notes, view);
+    }
+
+    let current_goal = &exercise.goals[current];
+    let hint_level = snapshot.hint.level;
+    let any_order = matches!(exercise.flow_type, FlowType::AnyOrder);
+    let mut lines = vec![
+        format!("=== 🎯 {} ===", exercise.title),
+        exercise.description.clone(),
+    ];
+    if notes.intro
+        && let Some(intro) = &exercise.intro
+    {
+        lines.extend(intro.lines().map(|line| format!("📖 {}", line)));
+    }
+    if let Some(position) = &notes.position {
+        lines.push(format!("📍 {}", position));
+    }
+    lines.push(String::new());
+    if view.display.shows_numbers() && (exercise.goals.len() > 1 || any_order) {
+        lines.push(if any_order {
+            "=== ☑️ 目標一覧（どの順番でも可） ===".to_string()
+        } else {
+            "=== ☑️ 目標一覧 ===".to_string()
+        });
+        lines.extend(goal_checklist(
+            &exercise.goals,
+            &completed(snapshot),
+            current,
+            view.width.saturating_sub(1),
+        ));
+    }
+    // 順不同の練習では一覧が主な表示で、ヒントの対象（残りの最初の目標）だけを詳しく出す
+    lines.push(if any_order {
+        "=== 👉 まだの目標から ===".to_string()
+    } else {
+        "=== 📋 現在の目標 ===".to_string()
+    });
+    lines.push(format!("  {}. {}", current + 1, current_goal.description));
+    if let Some(pending) = &notes.operator_pending {
+        lines.push(format!("     {}", pending));
+    }
+    if let Some(rule) = conduct_rule(exercise, current) {
+        lines.push(format!("     📏 {}", rule));
+    }
+    if let Some(notice) = &notes.conduct_notice {
+        lines.push(format!("     {}", notice));
+        lines.push(format!("     {}", RETRY_OFFER));
+    }
+
+    // ヒントは要求された（または時間経過で自動表示された）段階に応じて表示
+    if hint_level >= 1 {
+        if let Some(hint) = &current_goal.hint {
+            lines.push(format!("     💡 {}", hint));
+        }
+        if let Some(escalated) = escalated_hint(current_goal) {
+            lines.push(format!("     🔍 {}", escalated));
+        }
+    }
+    if hint_level >= 2
+        && let Some(keys) = &current_goal.solution_keys
+    {
+        lines.push(format!("     🔑 解答: {}", keys));
+    }
+    if let Some(demo) = demo_line(notes.demo, hint_level, current_goal) {
+        lines.push(format!("     {}", demo));
+    }
+    if !notes.stalled_text.is_empty() {
+        // 残りがこの目標だけで止まっているときは、差分の代わりに行を並べた比較を出す
+        lines.push("     🔎 あと少しです。目標の行と違うところ:".to_string());
+        let width = view.width.saturating_sub(8);
+        lines.extend(
+            notes
+                .stalled_text
+                .iter()
+                .map(|line| format!("       {}", layout::truncate_to_width(line, width))),
+        );
+    } else if !notes.text_diff.is_empty() {
+        lines.push("     📝 目標との違い:".to_string());
+        // ペインに入らない長い行は右端を … にして、省いたことを知らせる
+        let width = view.width.saturating_sub(8);
+        let mut clipped = false;
+        for diff_line in &notes.text_diff {
+            let shown = layout::truncate_to_width(diff_line, width);
+            clipped |= shown != *diff_line;
+            lines.push(format!("       {}", shown));
+        }
+        if clipped {
+            lines.push("       ↔ 長い行は右端を省いています（エディタでは横にスクロールして見られます）".to_string());
+        }
+    }
+    if let Some(nudge) = &notes.nudge {
+        lines.push(format!("     🧭 {}", nudge));
+    }
+    if let Some(tip) = &notes.coaching {
+        lines.push(format!("     💬 {}", tip));
+    }
+    if let Some(failure) = &notes.goal_command_failure {
+        lines.push(format!("     ⚠️ {}", failure));
+    }
+    if view.display.shows_numbers() {
+        if let Some(remaining) = notes.countdown {
+            lines.push(format!("     ⏳ ヒントまで: {}s", remaining));
+        }
+        if let Some(remaining) = notes.remaining {
+            lines.push(format!("⏱️ 残り時間: {}s", remaining));
+        }
+        lines.push(layout::progress_line(
+            &progress_counts(snapshot, true),
+            view.width.saturating_sub(1),
+        ));
+    }
+    lines.push("下のNeovimで操作してください！".to_string());
+    // 参考は目標やヒントを出した残りの行に入れる（最後の行はシェルのプロンプトのために空けておく）
+    let room = view.height.saturating_sub(lines.len() + 2);
+    lines.extend(reference_block(&exercise.reference, room, view.width.saturating_sub(1)));
+    lines.push(COMMAND_FOOTER.to_string());
+    lines
+}
+
+// 小さい端末向けの3行表示: 現在の目標、ヒント（または操作の案内）、残り時間など。
+// 折り返すと行数が増えるため、各行をペインの幅に切り詰める
+fn compact(
+    exercise: &ContinuousExercise,
+    snapshot: &ProgressSnapshot,
+    current: usize,
+    notes: &PaneNotes,
+    view: &PaneView,
+) -> Vec<String> {
+    let current_goal = &exercise.goals[current];
+    let hint_level = snapshot.hint.level;
+    let goal_line = if view.display.shows_numbers() {
+        format!("🎯 {}/{} {}", current + 1, exercise.goals.len(), current_goal.description)
+    } else {
+        format!("🎯 {}", current_goal.description)
+    };
+
+    let hint_line = match (&current_goal.solution_keys, &current_goal.hint) {
+        _ if notes.demo.is_some() => demo_line(notes.demo, hint_level, current_goal).unwrap_or_default(),
+        _ if notes.conduct_notice.is_some() => notes.conduct_notice.clone().unwrap_or_default(),
+        _ if notes.operator_pending.is_some() => notes.operator_pending.clone().unwrap_or_default(),
+        (Some(keys), _) if hint_level >= 2 => format!("🔑 解答: {}", keys),
+        (_, Some(hint)) if hint_level >= 1 => format!("💡 {}", hint),
+        _ => match (notes.text_diff.get(1), &notes.nudge, &notes.coaching) {
+            // 差分は「現在:」の行だけを出す
+            (Some(actual), _, _) => format!("📝 {}", actual),
+            (None, Some(nudge), _) => format!("🧭 {}", nudge),
+            (None, None, Some(tip)) => format!("💬 {}", tip),
+            (None, None, None) => "⌨️ :TutorialHint でヒント / :TutorialReset でやり直し".to_string(),
+        },
+    };
+
+    let mut status = Vec::new();
+    if view.display.shows_numbers() {
+        if let Some(remaining) = notes.remaining {
+            status.push(format!("⏱️ 残り {}s", remaining));
+        }
+        if let Some(remaining) = notes.countdown {
+            status.push(format!("⏳ ヒントまで {}s", remaining));
+        }
+    }
+    if let Some(position) = &notes.position {
+        status.push(format!("📍 {}", position));
+    }
+    // 進捗は数だけの短い形にし、制限時間があれば経過時間の代わりに残り時間を出す。
+    // 切り詰めるときは残り時間などを優先して残し、他に出すものが無ければ練習の名前を添える
+    let progress = layout::progress_line(&progress_counts(snapshot, notes.remaining.is_none()), 0);
+    let status_line = if !view.display.shows_numbers() {
+        status.insert(0, format!("📚 {}", exercise.title));
+        status.join("  ")
+    } else if status.is_empty() {
+        format!("{}  📚 {}", progress, exercise.title)
+    } else {
+        status.push(progress);
+        status.join("  ")
+    };
+
+    // 最後の桁まで使うと端末によっては折り返すため1桁残す
+    let width = view.width.saturating_sub(1);
+    [goal_line, hint_line, status_line]
+        .iter()
+        .map(|line| layout::truncate_to_width(line, width))
+        .collect()
+}
+
+// 一時停止している間の指示ペイン（resume_line は再開のしかた）
+pub fn paused(exercise: &ContinuousExercise, snapshot: &ProgressSnapshot, resume_line: &str, view: &PaneView) -> Vec<String> {
+    let current = snapshot.current_goal_index.unwrap_or(0);
+    let width = view.width.saturating_sub(1);
+    if view.layout == PaneLayout::Compact {
+        let goal_line = format!(
+            "⏸️ 一時停止中 {}/{} {}",
+            current + 1,
+            exercise.goals.len(),
+            exercise.goals[current].description
+        );
+        return [goal_line, resume_line.to_string(), format!("📚 {}", exercise.title)]
+            .iter()
+            .map(|line| layout::truncate_to_width(line, width))
+            .collect();
+    }
+
+    let mut lines = vec![
+        "=== ⏸️ 一時停止中 ===".to_string(),
+        exercise.title.clone(),
+        String::new(),
+    ];
+    lines.extend(goal_checklist(&exercise.goals, &completed(snapshot), current, width));
+    lines.push(String::new());
+    lines.push("目標の判定とヒントの時間を止めています".to_string());
+    lines.push(resume_line.to_string());
+    lines
+}
+
+// 全ての目標を達成したときの指示ペイン
+pub fn completion(exercise: &ContinuousExercise, display: DisplayPolicy) -> Vec<String> {
+    if !display.shows_numbers() {
+        return vec![format!("=== 🌿 {} ===", exercise.title), "✅ 次へ進みます".to_string()];
+    }
+    let mut lines = vec![
+        "=== 🎉 章完了！ ===".to_string(),
+        exercise.title.clone(),
+        String::new(),
+        "✅ 全ての目標を達成しました！".to_string(),
+        String::new(),
+        "📋 達成した目標:".to_string(),
+    ];
+    lines.extend(
+        exercise
+            .goals
+            .iter()
+            .enumerate()
+            .map(|(i, goal)| format!("  {}. {}", i + 1, goal.description)),
+    );
+    lines.push(String::new());
+    lines
+}
+
+// 制限時間を過ぎたときの指示ペイン
+pub fn timed_out(exercise: &ContinuousExercise, snapshot: &ProgressSnapshot, display: DisplayPolicy) -> Vec<String> {
+    let mut lines = vec!["=== ⌛ 時間切れ！ ===".to_string(), exercise.title.clone()];
+    if display.shows_numbers() {
+        lines.push(String::new());
+        lines.push(format!("📋 達成した目標: {}/{}", snapshot.counters.completed, exercise.goals.len()));
+    }
+    lines
+}
+
+fn completed(snapshot: &ProgressSnapshot) -> Vec<bool> {
+    snapshot.goals.iter().map(|goal| goal.status == GoalStatus::Done).collect()
+}
+
+// 達成した目標の数（順番通りでも順不同でも、達成の印の数）と経過時間
+fn progress_counts(snapshot: &ProgressSnapshot, with_clock: bool) -> ProgressCounts {
+    ProgressCounts {
+        completed: snapshot.counters.completed,
+        total: snapshot.counters.total,
+        elapsed_secs: with_clock.then_some(snapshot.elapsed_secs),
+    }
+}
+
+// 練習の参考を room 行までに収める。入りきらない行は省き、省いた行数を … の行で示す
+fn reference_block(reference: &[String], room: usize, width: usize) -> Vec<String> {
+    if reference.is_empty() || room == 0 {
+        return Vec::new();
+    }
+    if room == 1 {
+        return vec![format!("=== 📌 参考（{}行は画面に入りません …） ===", reference.len())];
+    }
+    let mut lines = vec!["=== 📌 参考 ===".to_string()];
+    if reference.len() < room {
+        lines.extend(reference.iter().map(|line| layout::truncate_to_width(&format!("  {}", line), width)));
+    } else {
+        let shown = room - 2;
+        lines.extend(
+            reference[..shown].iter().map(|line| layout::truncate_to_width(&format!("  {}", line), width)),
+        );
+        lines.push(format!("  …（ほか{}行）", reference.len() - shown));
+    }
+    lines
+}
+
+// 全ての目標を ✅（達成）/ ▶️（現在）/ ⬜（これから）付きで1行ずつ並べる。
+// 目標が多いときは達成済みの数と、現在の目標から未達成のものをいくつか出す
+fn goal_checklist(goals: &[ExerciseGoal], completed: &[bool], current: usize, width: usize) -> Vec<String> {
+    let is_done = |i: usize| completed.get(i).copied().unwrap_or(false);
+    let entry = |i: usize| {
+        let mark = if is_done(i) {
+            "✅"
+        } else if i == current {
+            "▶️"
+        } else {
+            "⬜"
+        };
+        layout::truncate_to_width(&format!("  {} {}. {}", mark, i + 1, goals[i].description), width)
+    };
+
+    if goals.len() <= MAX_CHECKLIST_GOALS {
+        return (0..goals.len()).map(entry).collect();
+    }
+
+    let done = (0..goals.len()).filter(|&i| is_done(i)).count();
+    let pending: Vec<usize> = (current..goals.len())
+        .chain(0..current)
+        .filter(|&i| !is_done(i))
+        .collect();
+    let mut lines = Vec::new();
+    if done > 0 {
+        lines.push(format!("  ✅ 達成済み: {}件", done));
+    }
+    lines.extend(pending.iter().take(COLLAPSED_CHECKLIST_GOALS).map(|&i| entry(i)));
+    if pending.len() > COLLAPSED_CHECKLIST_GOALS {
+        lines.push(format!("  ⬜ ほか{}件", pending.len() - COLLAPSED_CHECKLIST_GOALS));
+    }
+    lines
+}
+
+// 目標の達成のしかたの決まり（使えるモードと、使うオペレーター）を1行で
+fn conduct_rule(exercise: &ContinuousExercise, index: usize) -> Option<String> {
+    let mut rules = Vec::new();
+    let allowed = exercise.allowed_modes_for(index);
+    if !allowed.is_empty() {
+        // ノーマルモードは常に使える
+        let labels: Vec<String> = std::iter::once(VimMode::Normal.label())
+            .chain(
+                allowed
+                    .iter()
+                    .filter_map(|name| VimMode::from_goal_name(name))
+                    .filter(|mode| *mode != VimMode::Normal)
+                    .map(|mode| mode.label()),
+            )
+            .collect();
+        rules.push(format!("使えるモード: {}", labels.join("・")));
+    }
+    if let Some(operator) = exercise.goals.get(index).and_then(|goal| goal.required_operator.as_ref()) {
+        rules.push(format!("オペレーター {} を使う", operator));
+    }
+    (!rules.is_empty()).then(|| rules.join(" / "))
+}
+
+// 解答の再生の案内（再生中・再生後の選択、または解答を表示した後の再生の勧め）
+fn demo_line(demo: Option<Option<bool>>, hint_level: usize, goal_def: &ExerciseGoal) -> Option<String> {
+    match demo {
+        Some(None) => Some("🎬 解答を再生しています...".to_string()),
+        Some(Some(true)) => Some(
+            "🎬 解答の操作で目標を達成しました。:TutorialDemoRestore で戻して自分で試す / :TutorialDemoKeep でこのまま進む"
+                .to_string(),
+        ),
+        Some(Some(false)) => Some(
+            "🎬 再生しても目標を達成できませんでした。:TutorialDemoRestore で戻す / :TutorialDemoKeep でこのまま続ける"
+                .to_string(),
+        ),
+        None if hint_level >= 2 && goal_def.solution_keys.is_some() => {
+            Some("🎬 :TutorialDemo（または :TutorialHint）で解答の操作を再生します".to_string())
+        }
+        None => None,
+    }
+}
+
+// ヒントを要求された時に追加で表示する、目標そのものに基づく詳しいヒント
+fn escalated_hint(goal_def: &ExerciseGoal) -> Option<String> {
+    match goal_def.goal_type.as_str() {
+        "position" => {
+            let target = goal_def.target.as_array()?;
+            let line = target.first()?.as_u64()?;
+            let col = target.get(1)?.as_u64()?;
+            Some(format!("目標位置は {}行{}列 です", line + 1, col + 1))
+        }
+        "mode" => Some(format!(
+            "目標のモードは '{}' です",
+            goal_def.target.as_str()?
+        )),
+        "text" => Some(format!(
+            "{}行目を「{}」にしてください",
+            goal_def.target["line"].as_u64()? + 1,
+            goal_def.target["expected"].as_str()?
+        )),
+        "text_block" => Some(format!(
+            "{}行目から {} 行を目標の内容にしてください",
+            goal_def.target["start_line"].as_u64().unwrap_or(0) + 1,
+            goal_def.target["expected"].as_array()?.len()
+        )),
+        "buffer" => Some(format!(
+            "バッファ全体を目標の {} 行にしてください",
+            goal_def.target.as_array()?.len()
+        )),
+        "register" => Some(format!(
+            "レジスタ \"{} に「{}」が入るようにしてください",
+            goal_def.target["register"].as_str()?,
+            goal_def.target["expected"].as_str()?
+        )),
+        "window_count" => Some(format!(
+            "ウィンドウを {} 個にしてください",
+            goal_def.target["expected"].as_u64()?
+        )),
+        "window_layout" => Some(format!(
+            "画面を {} 回以上分割してください",
+            goal_def.target["min_splits"].as_u64()?
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuous_content::ContinuousContentLoader;
+    use serde_json::json;
+
+    #[test]
+    fn test_escalated_hint() {
+        let position = ExerciseGoal {
+            goal_type: "position".to_string(),
+            target: json!([1, 4]),
+            description: "Move".to_string(),
+            hint: None,
+            ..Default::default()
+        };
+        assert_eq!(escalated_hint(&position).as_deref(), Some("目標位置は 2行5列 です"));
+
+        let malformed = ExerciseGoal {
+            goal_type: "position".to_string(),
+            target: json!([1]),
+            description: "Broken".to_string(),
+            hint: None,
+            ..Default::default()
+        };
+        assert!(escalated_hint(&malformed).is_none());
+    }
+
+    #[test]
+    fn test_goal_checklist_collapses_long_exercises() {
+        let goals: Vec<ExerciseGoal> = (1..=10)
+            .map(|i| ExerciseGoal {
+                description: format!("目標{}", i),
+                ..Default::default()
+            })
+            .collect();
+
+        let short = goal_checklist(&goals[..3], &[true, false, false], 1, 79);
+        assert_eq!(short, vec!["  ✅ 1. 目標1", "  ▶️ 2. 目標2", "  ⬜ 3. 目標3"]);
+
+        let mut completed = vec![false; 10];
+        completed[0] = true;
+        completed[1] = true;
+        completed[3] = true;
+        let collapsed = goal_checklist(&goals, &completed, 2, 79);
+        assert_eq!(
+            collapsed,
+            vec![
+                "  ✅ 達成済み: 3件",
+                "  ▶️ 3. 目標3",
+                "  ⬜ 5. 目標5",
+                "  ⬜ 6. 目標6",
+                "  ⬜ 7. 目標7",
+                "  ⬜ 8. 目標8",
+                "  ⬜ ほか2件",
+            ]
+        );
+
+        // 長い説明はペインの幅に切り詰める
+        let long = vec![ExerciseGoal {
+            description: "とても長い説明".repeat(10),
+            ..Default::default()
+        }];
+        let lines = goal_checklist(&long, &[false], 0, 20);
+        assert!(lines[0].ends_with('…'));
+    }
+
+    #[test]
+    fn test_render_shows_completion_when_every_goal_is_done() {
+        let exercise = &ContinuousContentLoader::sample_chapter().continuous_exercises[0];
+        let descriptions: Vec<String> = exercise.goals.iter().map(|goal| goal.description.clone()).collect();
+        let view = PaneView {
+            layout: PaneLayout::Full,
+            width: 80,
+            height: 24,
+            display: DisplayPolicy::Full,
+        };
+        let done = ProgressSnapshot::new(&exercise.title, &descriptions, &vec![true; descriptions.len()], None);
+        assert_eq!(
+            render(exercise, &done, &PaneNotes::default(), &view),
+            completion(exercise, DisplayPolicy::Full)
+        );
+
+        // 練習中はヒントの段階に応じて目標の下にヒントを足す
+        let mut snapshot =
+            ProgressSnapshot::new(&exercise.title, &descriptions, &vec![false; descriptions.len()], Some(0));
+        let plain = render(exercise, &snapshot, &PaneNotes::default(), &view);
+        assert!(plain.contains(&format!("  1. {}", exercise.goals[0].description)));
+        assert!(!plain.iter().any(|line| line.contains("💡")));
+        assert_eq!(plain.last().map(String::as_str), Some(COMMAND_FOOTER));
+        snapshot.hint.level = 1;
+        let hinted = render(exercise, &snapshot, &PaneNotes::default(), &view);
+        assert_eq!(hinted.len() > plain.len(), exercise.goals[0].hint.is_some());
+    }
+}
@@ -0,0 +1,48 @@
+use anyhow::Result;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+// 指示のファイルが変わったか確かめる間隔
+const PANEL_INTERVAL: Duration = Duration::from_millis(100);
+
+// tmux の指示ペインで動かし、監視側が書いたファイルが変わるたびに描き直す
+// （更新のたびにペインへキーを送らないので、カーソル位置のように頻繁に変わる表示でもちらつかない）
+// セッションを閉じると一緒に終了する
+pub fn run_panel(path: &Path) -> Result<()> {
+    let mut shown: Option<String> = None;
+    loop {
+        // 監視側は別名で書いてから置き換えるため、書き込み途中の内容は読まない
+        if let Ok(content) = fs::read_to_string(path)
+            && shown.as_ref() != Some(&content)
+        {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(frame(&content).as_bytes())?;
+            stdout.flush()?;
+            shown = Some(content);
+        }
+        thread::sleep(PANEL_INTERVAL);
+    }
+}
+
+// 画面を消して先頭から描き直す（カーソルは隠す。端末は生の改行では行頭に戻らないことがある）
+fn frame(content: &str) -> String {
+    let body: Vec<&str> = content.lines().collect();
+    format!("\x1b[?25l\x1b[2J\x1b[H{}", body.join("\r\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_redraws_from_top_left() {
+        let frame = frame("=== 🎯 練習 ===\n行 3 / 列 7 | NORMAL\n");
+        assert_eq!(
+            frame,
+            "\x1b[?25l\x1b[2J\x1b[H=== 🎯 練習 ===\r\n行 3 / 列 7 | NORMAL"
+        );
+    }
+}
@@ -0,0 +1,464 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+// ファイルの置き場所。全てのモジュールはここで決めたパスを使い、自分でパスを組み立てない
+//   データ（章データ・進捗・セッションの印・記録）: ~/.local/share/vim-tutorial/
+//   キャッシュ（練習ごとに作り直す一時ファイル・デバッグログ・ソケット）: ~/.cache/vim-tutorial/
+//   設定: ~/.config/vim-tutorial/
+// それぞれ VIM_TUTORIAL_DATA_DIR・VIM_TUTORIAL_CACHE_DIR・VIM_TUTORIAL_CONFIG_DIR、
+// XDG_DATA_HOME・XDG_CACHE_HOME・XDG_CONFIG_HOME、ホームディレクトリの順に決める
+const APP_DIR: &str = "vim-tutorial";
+
+// 以前の版がカレントディレクトリに置いていたデータの場所（移行元）
+pub const LEGACY_DATA_DIR: &str = "data";
+// リポジトリに同梱の章データ（テストで読む。起動時に chapters_dir へ写し、変わっていれば写し直す）
+pub const BUNDLED_CHAPTERS_DIR: &str = "data/chapters";
+// 以前の版が一時ディレクトリに書いていたデバッグログ（移行元）
+const LEGACY_DEBUG_LOGS: &[&str] = &["vim_tutorial_debug.log", "vim_continuous_debug.log"];
+
+// 3つの置き場所の根
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Roots {
+    pub data: PathBuf,
+    pub cache: PathBuf,
+    pub config: PathBuf,
+}
+
+impl Roots {
+    // 環境変数を var で読んで決める（テストでは環境を差し替える）。
+    // ホームディレクトリも分からなければ、データと設定は以前と同じ data、キャッシュは一時ディレクトリに置く
+    pub fn resolve(var: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+        let home = var("HOME").or_else(|| var("USERPROFILE"));
+        let root = |override_var: &str, xdg_var: &str, home_dir: &[&str], fallback: PathBuf| {
+            var(override_var)
+                .or_else(|| var(xdg_var).map(|xdg| xdg.join(APP_DIR)))
+                .or_else(|| {
+                    home.as_ref()
+                        .map(|home| home_dir.iter().fold(home.clone(), |dir, part| dir.join(part)).join(APP_DIR))
+                })
+                .unwrap_or(fallback)
+        };
+        Self {
+            data: root(
+                "VIM_TUTORIAL_DATA_DIR",
+                "XDG_DATA_HOME",
+                &[".local", "share"],
+                PathBuf::from(LEGACY_DATA_DIR),
+            ),
+            cache: root(
+                "VIM_TUTORIAL_CACHE_DIR",
+                "XDG_CACHE_HOME",
+                &[".cache"],
+                std::env::temp_dir().join(APP_DIR),
+            ),
+            config: root(
+                "VIM_TUTORIAL_CONFIG_DIR",
+                "XDG_CONFIG_HOME",
+                &[".config"],
+                PathBuf::from(LEGACY_DATA_DIR),
+            ),
+        }
+    }
+
+    // path がどれかの置き場所の中にあるか
+    pub fn contains(&self, path: &Path) -> bool {
+        [&self.data, &self.cache, &self.config]
+            .iter()
+            .any(|root| path.starts_with(root))
+    }
+}
+
+// このプロセスの置き場所（最初に使ったときに環境変数から決める）
+pub fn roots() -> &'static Roots {
+    static ROOTS: OnceLock<Roots> = OnceLock::new();
+    ROOTS.get_or_init(|| Roots::resolve(|name| std::env::var(name).ok()))
+}
+
+// このプロセスで決めたパスの記録（テストで置き場所の外に書いていないことを確かめる）
+fn registry() -> &'static Mutex<BTreeSet<PathBuf>> {
+    static REGISTRY: OnceLock<Mutex<BTreeSet<PathBuf>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(BTreeSet::new()))
+}
+
+fn recorded_path(path: PathBuf) -> PathBuf {
+    registry()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(path.clone());
+    path
+}
+
+// これまでに決めたパス
+pub fn recorded() -> Vec<PathBuf> {
+    registry()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .cloned()
+        .collect()
+}
+
+// 章データ（同梱の章とインストールしたパック）
+pub fn chapters_dir() -> PathBuf {
+    recorded_path(roots().data.join("chapters"))
+}
+
+// 学習記録（progress::ProgressStore）
+pub fn progress_file() -> PathBuf {
+    recorded_path(roots().data.join("progress.json"))
+}
+
+// セッションの記録（event_log。replay で再生する）
+pub fn event_log_dir() -> PathBuf {
+    recorded_path(roots().data.join("sessions"))
+}
+
+// 練習中の印（recovery。落ちた後の再接続に使う）
+pub fn manifest_file() -> PathBuf {
+    recorded_path(roots().data.join("running_session.json"))
+}
+
+// 章の終わりに書く結果ファイル（chapter_report）の既定のディレクトリ
+pub fn reports_dir() -> PathBuf {
+    recorded_path(roots().data.join("reports"))
+}
+
+// import でまとめた集計ファイルの既定のパス
+pub fn aggregate_file() -> PathBuf {
+    recorded_path(roots().data.join("aggregate.json"))
+}
+
+// 設定ファイル（VIM_TUTORIAL_CONFIG で別のファイルを指定できる）
+pub fn config_file() -> PathBuf {
+    recorded_path(roots().config.join("config.toml"))
+}
+
+// 練習ごとの一時ファイル・デバッグログ・ソケットの置き場所（無ければ作る）
+pub fn cache_dir() -> PathBuf {
+    let dir = recorded_path(roots().cache.clone());
+    let _ = fs::create_dir_all(&dir);
+    dir
+}
+
+// キャッシュの置き場所の中のファイル
+pub fn cache_file(name: &str) -> PathBuf {
+    recorded_path(cache_dir().join(name))
+}
+
+// 以前の置き場所から移したもの
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migrated {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub copied: bool, // 元の場所にも残した
+}
+
+// 移行を済ませた印（データの置き場所にあれば移行しない）
+const MIGRATION_MARKER: &str = ".migrated";
+// 同梱の章を最後に写したときの内容のハッシュ（ファイル名ごと。chapters_dir に置く）
+const BUNDLED_STATE: &str = ".bundled.json";
+
+// 以前の版のデータを一度だけ新しい置き場所へ移す。移すのは一時ディレクトリのデバッグログ（中身が
+// このプログラムのログのもの）と、カレントディレクトリの data/progress.json（進捗ファイルとして読めるもの）だけ。
+// 学習記録は元の場所にも残す。新しい置き場所に既にあるものは上書きしない
+pub fn migrate_legacy() -> Vec<Migrated> {
+    migrate_from(Path::new(LEGACY_DATA_DIR), &std::env::temp_dir(), roots())
+}
+
+fn migrate_from(legacy_data: &Path, legacy_tmp: &Path, roots: &Roots) -> Vec<Migrated> {
+    let marker = roots.data.join(MIGRATION_MARKER);
+    if marker.exists() {
+        return Vec::new();
+    }
+    let mut migrated = Vec::new();
+    let progress = legacy_data.join("progress.json");
+    if fs::read(&progress).is_ok_and(|content| crate::progress::ProgressData::is_progress_file(&content)) {
+        migrate(progress, roots.data.join("progress.json"), true, &mut migrated);
+    }
+    for name in LEGACY_DEBUG_LOGS {
+        let from = legacy_tmp.join(name);
+        if is_legacy_debug_log(&from) {
+            migrate(from, roots.cache.join(name), false, &mut migrated);
+        }
+    }
+    // 移せなかったものがあっても、次からは行わない
+    if let Err(e) = fs::create_dir_all(&roots.data).and_then(|()| fs::write(&marker, "")) {
+        crate::output::warning!("⚠️ {} を書けませんでした: {}", marker.display(), e);
+    }
+    migrated
+}
+
+fn migrate(from: PathBuf, to: PathBuf, copy: bool, migrated: &mut Vec<Migrated>) {
+    // data を置き場所に指定している場合など、同じ場所なら何もしない
+    if to.exists() || same_place(&from, &to) {
+        return;
+    }
+    match transfer(&from, &to, copy) {
+        Ok(()) => migrated.push(Migrated { from, to, copied: copy }),
+        Err(e) => crate::output::warning!("⚠️ {} を {} に移せませんでした: {}", from.display(), to.display(), e),
+    }
+}
+
+// 以前の版が書いたデバッグログか（1行目が見出しか、タイムスタンプ付きのデバッグの行）
+fn is_legacy_debug_log(path: &Path) -> bool {
+    let Ok(text) = fs::read_to_string(path) else {
+        return false;
+    };
+    let first = text.lines().next().unwrap_or_default();
+    first == "=== Vim Tutorial Debug Log ==="
+        || (first.starts_with('[') && (first.contains("] 🔧 DEBUG: ") || first.contains("] 🔧 CONTINUOUS_DEBUG: ")))
+}
+
+// リポジトリに同梱の章（カレントディレクトリの data/chapters）を chapters_dir に写し、写したファイル名を返す。
+// 同梱の章が前に写したときから変わっていれば写し直す。写した後に書き換えたファイルは上書きしない
+pub fn sync_bundled_chapters() -> Vec<String> {
+    sync_chapters(Path::new(BUNDLED_CHAPTERS_DIR), &chapters_dir())
+}
+
+fn sync_chapters(bundled: &Path, chapters: &Path) -> Vec<String> {
+    if !bundled.is_dir() || same_place(bundled, chapters) {
+        return Vec::new();
+    }
+    let state_path = chapters.join(BUNDLED_STATE);
+    let mut state: BTreeMap<String, String> = fs::read(&state_path)
+        .ok()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+        .unwrap_or_default();
+    let known = state.clone();
+    let hash_of = |path: &Path| fs::read_to_string(path).ok().map(|text| crate::continuous_session::fnv1a_hex(&text));
+
+    let mut synced = Vec::new();
+    for name in bundled_chapter_files(bundled) {
+        let Some(hash) = hash_of(&bundled.join(&name)) else {
+            continue;
+        };
+        let to = chapters.join(&name);
+        match hash_of(&to) {
+            Some(current) if current == hash => {}
+            // 前に写した内容から書き換えられている
+            Some(current) if known.get(&name).is_some_and(|copied| *copied != current) => continue,
+            _ => {
+                if let Err(e) = fs::create_dir_all(chapters).and_then(|()| fs::copy(bundled.join(&name), &to)) {
+                    crate::output::warning!("⚠️ {} を写せませんでした: {}", to.display(), e);
+                    continue;
+                }
+                synced.push(name.clone());
+            }
+        }
+        state.insert(name, hash);
+    }
+    if state != known
+        && let Err(e) = serde_json::to_vec_pretty(&state).map_err(std::io::Error::from).and_then(|json| fs::write(&state_path, json))
+    {
+        crate::output::warning!("⚠️ {} を書けませんでした: {}", state_path.display(), e);
+    }
+    synced
+}
+
+// 同梱の章のファイル（章データの名前のものだけ。ファイル名順）
+fn bundled_chapter_files(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_file())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| {
+                    name.ends_with(".yaml")
+                        && ["chapter_", "continuous_chapter_", "continuous_bundle_"]
+                            .iter()
+                            .any(|prefix| name.starts_with(prefix))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+fn same_place(from: &Path, to: &Path) -> bool {
+    match (fs::canonicalize(from), to.parent().map(fs::canonicalize)) {
+        (Ok(from), Some(Ok(parent))) => to.file_name().is_some_and(|name| parent.join(name) == from),
+        _ => false,
+    }
+}
+
+// 写すか、名前を変えて移す（別のファイルシステムへは写してから消す）
+fn transfer(from: &Path, to: &Path, copy: bool) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if copy || fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        if !copy {
+            fs::remove_file(from)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn resolve(vars: &[(&str, &str)]) -> Roots {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Roots::resolve(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_overrides_take_precedence_over_xdg_and_home() {
+        let home_only = resolve(&[("HOME", "/home/a")]);
+        assert_eq!(home_only.data, PathBuf::from("/home/a/.local/share/vim-tutorial"));
+        assert_eq!(home_only.cache, PathBuf::from("/home/a/.cache/vim-tutorial"));
+        assert_eq!(home_only.config, PathBuf::from("/home/a/.config/vim-tutorial"));
+
+        let xdg = resolve(&[("HOME", "/home/a"), ("XDG_DATA_HOME", "/xdg/data"), ("XDG_CACHE_HOME", "")]);
+        assert_eq!(xdg.data, PathBuf::from("/xdg/data/vim-tutorial"));
+        // 空の値は指定が無いものとして扱う
+        assert_eq!(xdg.cache, PathBuf::from("/home/a/.cache/vim-tutorial"));
+
+        let overridden = resolve(&[
+            ("HOME", "/home/a"),
+            ("XDG_DATA_HOME", "/xdg/data"),
+            ("VIM_TUTORIAL_DATA_DIR", "/srv/tutorial"),
+            ("VIM_TUTORIAL_CACHE_DIR", "/run/tutorial"),
+            ("VIM_TUTORIAL_CONFIG_DIR", "/etc/tutorial"),
+        ]);
+        assert_eq!(overridden.data, PathBuf::from("/srv/tutorial"));
+        assert_eq!(overridden.cache, PathBuf::from("/run/tutorial"));
+        assert_eq!(overridden.config, PathBuf::from("/etc/tutorial"));
+
+        let nowhere = resolve(&[]);
+        assert_eq!(nowhere.data, PathBuf::from(LEGACY_DATA_DIR));
+        assert_eq!(nowhere.cache, std::env::temp_dir().join(APP_DIR));
+    }
+
+    #[test]
+    fn test_every_resolved_path_stays_inside_the_roots() {
+        for path in [
+            chapters_dir(),
+            progress_file(),
+            event_log_dir(),
+            manifest_file(),
+            reports_dir(),
+            aggregate_file(),
+            config_file(),
+            cache_file("vim_continuous_status.json"),
+        ] {
+            assert!(roots().contains(&path), "{}", path.display());
+        }
+        // 他のテストが使ったものを含め、このプロセスで決めた全てのパス
+        for path in recorded() {
+            assert!(roots().contains(&path), "置き場所の外: {}", path.display());
+        }
+    }
+
+    #[test]
+    fn test_no_module_builds_paths_outside_this_module() {
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let forbidden = ["temp_dir()", "\"data/", "NamedTempFile::new()"];
+        let mut found = Vec::new();
+        for entry in fs::read_dir(&src).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "rs") || path.file_name().is_some_and(|name| name == "paths.rs") {
+                continue;
+            }
+            let text = fs::read_to_string(&path).unwrap();
+            // テストは一時ディレクトリを自由に使う
+            let code = text.split("#[cfg(test)]").next().unwrap_or_default();
+            for (number, line) in code.lines().enumerate() {
+                let line = line.split("//").next().unwrap_or_default();
+                if forbidden.iter().any(|pattern| line.contains(pattern)) {
+                    found.push(format!("{}:{}", path.display(), number + 1));
+                }
+            }
+        }
+        assert!(found.is_empty(), "paths を通さずに組み立てたパス: {:?}", found);
+    }
+
+    fn roots_in(dir: &Path) -> Roots {
+        Roots {
+            data: dir.join("data"),
+            cache: dir.join("cache"),
+            config: dir.join("config"),
+        }
+    }
+
+    #[test]
+    fn test_migration_takes_only_our_files_and_runs_once() -> Result<()> {
+        let legacy = tempfile::tempdir()?;
+        let tmp = tempfile::tempdir()?;
+        let target = tempfile::tempdir()?;
+        fs::write(legacy.path().join("progress.json"), r#"{"version":2,"warmup_done":true}"#)?;
+        fs::write(legacy.path().join("config.toml"), "bell = false")?;
+        fs::write(tmp.path().join("vim_tutorial_debug.log"), "=== Vim Tutorial Debug Log ===\n")?;
+        fs::write(tmp.path().join("vim_continuous_debug.log"), "someone else's log")?;
+        let roots = roots_in(target.path());
+
+        let migrated = migrate_from(legacy.path(), tmp.path(), &roots);
+        assert_eq!(migrated.len(), 2);
+        // 学習記録は写すだけで、元の場所にも残す
+        assert!(roots.data.join("progress.json").exists());
+        assert!(legacy.path().join("progress.json").exists());
+        assert!(roots.cache.join("vim_tutorial_debug.log").exists());
+        assert!(!tmp.path().join("vim_tutorial_debug.log").exists());
+        // このプログラムのものと分からないファイルや、移す対象でないファイルには触れない
+        assert!(tmp.path().join("vim_continuous_debug.log").exists());
+        assert!(!roots.cache.join("vim_continuous_debug.log").exists());
+        assert!(!roots.config.join("config.toml").exists());
+
+        // 一度済ませたら、新しく置かれたものがあっても行わない
+        fs::remove_file(roots.data.join("progress.json"))?;
+        assert!(migrate_from(legacy.path(), tmp.path(), &roots).is_empty());
+        assert!(!roots.data.join("progress.json").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_migration_skips_files_that_are_not_progress() -> Result<()> {
+        let target = tempfile::tempdir()?;
+        for content in ["{}", r#"{"name":"other"}"#, "[1, 2]", "not json"] {
+            let legacy = tempfile::tempdir()?;
+            let roots = roots_in(&target.path().join(format!("{:x}", content.len())));
+            fs::write(legacy.path().join("progress.json"), content)?;
+            assert!(migrate_from(legacy.path(), legacy.path(), &roots).is_empty(), "{}", content);
+            assert!(!roots.data.join("progress.json").exists());
+            assert!(legacy.path().join("progress.json").exists());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundled_chapters_are_resynced_unless_edited() -> Result<()> {
+        let bundled = tempfile::tempdir()?;
+        let chapters = tempfile::tempdir()?;
+        let chapters = chapters.path().join("chapters");
+        fs::write(bundled.path().join("chapter_01.yaml"), "v1")?;
+        fs::write(bundled.path().join("chapter_02.yaml"), "v1")?;
+        fs::write(bundled.path().join("notes.txt"), "not a chapter")?;
+
+        assert_eq!(sync_chapters(bundled.path(), &chapters), ["chapter_01.yaml", "chapter_02.yaml"]);
+        assert!(!chapters.join("notes.txt").exists());
+        assert!(sync_chapters(bundled.path(), &chapters).is_empty());
+
+        // 同梱の章が変われば写し直すが、利用者が書き換えた章はそのまま残す
+        fs::write(chapters.join("chapter_02.yaml"), "mine")?;
+        fs::write(bundled.path().join("chapter_01.yaml"), "v2")?;
+        fs::write(bundled.path().join("chapter_02.yaml"), "v2")?;
+        assert_eq!(sync_chapters(bundled.path(), &chapters), ["chapter_01.yaml"]);
+        assert_eq!(fs::read_to_string(chapters.join("chapter_01.yaml"))?, "v2");
+        assert_eq!(fs::read_to_string(chapters.join("chapter_02.yaml"))?, "mine");
+
+        // 消した章はまた写す
+        fs::remove_file(chapters.join("chapter_02.yaml"))?;
+        assert_eq!(sync_chapters(bundled.path(), &chapters), ["chapter_02.yaml"]);
+        assert_eq!(fs::read_to_string(chapters.join("chapter_02.yaml"))?, "v2");
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+// --practice-file: 学習者の手元のファイルのコピーで練習する。
+// 読み込んだ時点でファイルを調べ、そのファイルに合わせた目標（最終行へ・一番長い識別子の検索・最初の空行の削除）を作る。
+// 練習には内容のコピー（練習用の一時ディレクトリの同じ名前のファイル）を使い、元のファイルには書き込まない
+use anyhow::{Result, anyhow};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+use crate::continuous_session::{ContinuousExercise, ExerciseFile, ExerciseGoal};
+
+// これより行の多いファイルは、状態の監視が重くなるため練習に使わない
+pub const MAX_LINES: usize = 10_000;
+
+// ファイルを読み、練習にする（バイナリ・空・行が多すぎるファイルは理由を付けて断る）
+pub fn load(path: &Path) -> Result<ContinuousExercise> {
+    let bytes = fs::read(path).map_err(|e| anyhow!("{} を読めません: {}", path.display(), e))?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "practice.txt".to_string());
+    let text = match String::from_utf8(bytes) {
+        Ok(text) if !text.contains('\0') => text,
+        _ => {
+            return Err(anyhow!(
+                "{} はテキストのファイルではないようです（UTF-8 のテキストのファイルを指定してください）",
+                path.display()
+            ));
+        }
+    };
+    exercise_for(&name, &text).map_err(|e| anyhow!("{}: {}", path.display(), e))
+}
+
+// ファイルの名前と内容から練習を作る
+pub fn exercise_for(name: &str, text: &str) -> Result<ContinuousExercise> {
+    let lines: Vec<String> = text.lines().map(str::to_string).collect();
+    if lines.iter().all(|line| line.trim().is_empty()) {
+        return Err(anyhow!("ファイルが空です"));
+    }
+    if lines.len() > MAX_LINES {
+        return Err(anyhow!(
+            "{}行あります。練習に使えるのは{}行までです（一部を別のファイルに写して指定してください）",
+            lines.len(),
+            MAX_LINES
+        ));
+    }
+
+    let last = lines.len() - 1;
+    let mut goals = Vec::new();
+    // 開始位置は1行目の先頭。1行だけのファイルでは最終行への移動を出さない
+    if last > 0 {
+        goals.push(ExerciseGoal {
+            goal_type: "position".to_string(),
+            target: json!([last, 0]),
+            description: format!("最終行（{}行目）の先頭へ移動", last + 1),
+            hint: Some("G で最終行へ、0 で行頭へ移動します".to_string()),
+            solution_keys: Some("G0".to_string()),
+            optimal_keystrokes: Some(2),
+            keys: vec!["G".to_string(), "0".to_string()],
+            ..Default::default()
+        });
+    }
+    if let Some((identifier, (line, col))) = longest_identifier(&lines, (last, 0)) {
+        let pattern = format!("/\\<{}\\><CR>", identifier);
+        goals.push(ExerciseGoal {
+            goal_type: "position".to_string(),
+            target: json!([line, col]),
+            description: format!("一番長い識別子「{}」を検索して移動", identifier),
+            hint: Some(format!("/ の後に {} と打って Enter で検索します（\\< \\> で単語全体に一致させます）", identifier)),
+            optimal_keystrokes: Some(pattern.chars().count() - "<CR>".len() + 1),
+            solution_keys: Some(pattern),
+            keys: vec!["/".to_string()],
+            ..Default::default()
+        });
+    }
+    if let Some(blank) = lines.iter().position(|line| line.trim().is_empty()) {
+        let mut expected = lines.clone();
+        expected.remove(blank);
+        goals.push(ExerciseGoal {
+            goal_type: "buffer".to_string(),
+            target: json!(expected),
+            description: format!("最初の空行（{}行目）を削除", blank + 1),
+            hint: Some(format!("{}G で{}行目へ移動し、dd で行を削除します", blank + 1, blank + 1)),
+            keys: vec!["dd".to_string()],
+            ..Default::default()
+        });
+    }
+    if goals.is_empty() {
+        return Err(anyhow!("このファイルからは練習の目標を作れません（2行以上か、識別子のあるファイルを指定してください）"));
+    }
+
+    Ok(ContinuousExercise {
+        title: format!("{} で練習", name),
+        description: format!("手元のファイル {} のコピーで、移動・検索・削除を練習します", name),
+        files: vec![ExerciseFile {
+            name: name.to_string(),
+            content: lines,
+        }],
+        goals,
+        cursor_start: Some([0, 0]),
+        intro: Some(format!(
+            "📄 {} のコピーを開きます。\n📝 元のファイルは変更しません（練習で書き換えても保存されるのはコピーです）",
+            name
+        )),
+        outro: Some(format!("📝 元のファイル {} は変更していません", name)),
+        ..Default::default()
+    })
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// 一番長い識別子（同じ長さなら先に出てくるもの）と、from の後で / の検索が止まる位置（末尾から先頭へ折り返す）。
+// 列は状態の列と同じ文字単位
+fn longest_identifier(lines: &[String], from: (usize, usize)) -> Option<(String, (usize, usize))> {
+    let mut words: Vec<(String, (usize, usize))> = Vec::new();
+    for (line, content) in lines.iter().enumerate() {
+        let chars: Vec<char> = content.chars().collect();
+        let mut col = 0;
+        while col < chars.len() {
+            if !is_identifier_char(chars[col]) {
+                col += 1;
+                continue;
+            }
+            let start = col;
+            while col < chars.len() && is_identifier_char(chars[col]) {
+                col += 1;
+            }
+            if !chars[start].is_ascii_digit() {
+                words.push((chars[start..col].iter().collect(), (line, start)));
+            }
+        }
+    }
+    let longest = words
+        .iter()
+        .map(|(word, _)| word)
+        .fold(None::<&String>, |best, word| match best {
+            Some(best) if best.len() >= word.len() => Some(best),
+            _ => Some(word),
+        })?
+        .clone();
+    let positions: Vec<(usize, usize)> =
+        words.iter().filter(|(word, _)| *word == longest).map(|(_, position)| *position).collect();
+    let next = positions.iter().find(|&&position| position > from).or(positions.first())?;
+    Some((longest, *next))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator;
+
+    #[test]
+    fn test_goals_are_computed_from_the_file() {
+        let text = "fn main() {\n    let total_count = 1;\n\n    println!(\"{}\", total_count);\n}\n";
+        let exercise = exercise_for("main.rs", text).unwrap();
+        assert_eq!(exercise.files[0].name, "main.rs");
+        let targets: Vec<&serde_json::Value> = exercise.goals.iter().map(|goal| &goal.target).collect();
+        assert_eq!(targets[0], &json!([4, 0]));
+        // 最終行から検索すると、折り返して最初の total_count に止まる
+        assert_eq!(targets[1], &json!([1, 8]));
+        assert_eq!(exercise.goals[1].solution_keys.as_deref(), Some("/\\<total_count\\><CR>"));
+        assert_eq!(
+            targets[2],
+            &json!(["fn main() {", "    let total_count = 1;", "    println!(\"{}\", total_count);", "}"])
+        );
+        assert!(validator::validate_exercise(&exercise).is_empty());
+        // 目標は全て読み込めて判定できる
+        assert!(exercise.goals.iter().all(|goal| goal.to_goal().is_ok()));
+    }
+
+    #[test]
+    fn test_unusable_files_are_rejected() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let binary = dir.path().join("image.png");
+        fs::write(&binary, [0x89, b'P', b'N', b'G', 0, 1, 2])?;
+        assert!(load(&binary).unwrap_err().to_string().contains("テキストのファイルではない"));
+        let long = dir.path().join("long.txt");
+        fs::write(&long, "x\n".repeat(MAX_LINES + 1))?;
+        assert!(load(&long).unwrap_err().to_string().contains("10000行まで"));
+        assert!(exercise_for("empty.txt", "\n  \n").is_err());
+        // 読み込んでも元のファイルはそのまま
+        let source = dir.path().join("notes.md");
+        fs::write(&source, "# メモ\n\nhello_world\n")?;
+        let exercise = load(&source)?;
+        assert_eq!(exercise.goals.len(), 3);
+        assert_eq!(fs::read_to_string(&source)?, "# メモ\n\nhello_world\n");
+        Ok(())
+    }
+}
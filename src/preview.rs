@@ -0,0 +1,194 @@
+// preview サブコマンド: Neovim を起動せずに、練習で学習者が見る画面（端末の説明・指示ペインの各場面・outro）を
+// 順に標準出力へ書き出す。指示ペインはセッションと同じ pane::render で描き、目標の定義と検証の警告を添える（作者向け）
+use anyhow::Result;
+use std::path::Path;
+use unicode_width::UnicodeWidthStr;
+
+use crate::continuous_content::{ContinuousChapterData, ContinuousContentLoader};
+use crate::continuous_session::{ContinuousExercise, ContinuousVimSession};
+use crate::layout::{DisplayPolicy, PaneLayout};
+use crate::pane::{self, DEFAULT_PANE_HEIGHT, PaneNotes, PaneView};
+use crate::session_progress::ProgressSnapshot;
+use crate::theme::Theme;
+use crate::validator;
+
+pub fn run_preview(chapters_dir: &Path, chapter: u8, exercise: Option<usize>, width: usize) -> Result<()> {
+    let (loader, report) = ContinuousContentLoader::load_from(chapters_dir)?;
+    report.print_warnings();
+    let data = loader
+        .get_chapter(chapter)
+        .ok_or_else(|| anyhow::anyhow!("第{}章が見つかりません", chapter))?;
+    for line in preview_chapter(data, exercise, width, crate::theme::current())? {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+// 章（exercise を指定すればその練習だけ。1始まり）の場面を並べた行
+pub fn preview_chapter(
+    chapter: &ContinuousChapterData,
+    exercise: Option<usize>,
+    width: usize,
+    theme: Theme,
+) -> Result<Vec<String>> {
+    let count = chapter.continuous_exercises.len();
+    let indices: Vec<usize> = match exercise {
+        Some(number) if number == 0 || number > count => {
+            return Err(anyhow::anyhow!(
+                "第{}章に練習{}はありません（練習は{}個）",
+                chapter.chapter.number,
+                number,
+                count
+            ));
+        }
+        Some(number) => vec![number - 1],
+        None => (0..count).collect(),
+    };
+
+    let mut header = vec![format!("# 第{}章「{}」", chapter.chapter.number, chapter.chapter.title)];
+    header.extend(validator::lesson_issues(chapter).iter().map(|issue| format!("#   ⚠️ {}", issue)));
+    let mut lines = theme.lines(&header);
+    for index in indices {
+        lines.push(String::new());
+        lines.extend(preview_exercise(&chapter.continuous_exercises[index], index, count, width, theme));
+    }
+    Ok(lines)
+}
+
+fn preview_exercise(
+    exercise: &ContinuousExercise,
+    index: usize,
+    count: usize,
+    width: usize,
+    theme: Theme,
+) -> Vec<String> {
+    let issues = validator::exercise_issues(exercise);
+    let mut notes = vec![format!("# 練習 {}/{}「{}」", index + 1, count, exercise.title)];
+    notes.extend(
+        issues
+            .iter()
+            .filter(|issue| issue.goal.is_none())
+            .map(|issue| format!("#   ⚠️ {}", issue)),
+    );
+    for (goal_index, goal) in exercise.goals.iter().enumerate() {
+        let mut definition = format!("# 目標{}: type={}", goal_index + 1, goal.goal_type);
+        if !goal.target.is_null() {
+            definition.push_str(&format!(" target={}", goal.target));
+        }
+        if let Some(file) = &goal.file {
+            definition.push_str(&format!(" file={}", file));
+        }
+        notes.push(definition);
+        notes.extend(
+            issues
+                .iter()
+                .filter(|issue| issue.goal == Some(goal_index))
+                .map(|issue| format!("#   ⚠️ {}", issue)),
+        );
+    }
+
+    let mut lines = theme.lines(&notes);
+
+    // セッションと同じく、位置を文字列で書いた目標はサンプルコードの中の位置にしてから描く
+    let mut resolved = exercise.clone();
+    resolved.resolve_anchors();
+    for (label, frame) in frames(&resolved, width) {
+        lines.push(ruler(&label, width));
+        lines.extend(theme.lines(&frame));
+    }
+    lines.push(ruler("おわり", width));
+    lines
+}
+
+// 場面の名前と、その場面で出す行（端末の説明 → 目標ごとの指示ペインとヒント・解答 → 完了 → outro）
+fn frames(exercise: &ContinuousExercise, width: usize) -> Vec<(String, Vec<String>)> {
+    let view = PaneView {
+        layout: PaneLayout::Full,
+        width,
+        height: DEFAULT_PANE_HEIGHT,
+        display: DisplayPolicy::Full,
+    };
+    let descriptions: Vec<String> = exercise.goals.iter().map(|goal| goal.description.clone()).collect();
+    let mut frames = vec![("端末: 練習の説明".to_string(), ContinuousVimSession::briefing_lines(exercise))];
+    for current in 0..exercise.goals.len() {
+        let completed: Vec<bool> = (0..exercise.goals.len()).map(|index| index < current).collect();
+        let mut snapshot = ProgressSnapshot::new(&exercise.title, &descriptions, &completed, Some(current));
+        // intro は目標が進むまで出す
+        let notes = PaneNotes {
+            intro: current == 0,
+            ..PaneNotes::default()
+        };
+        let plain = pane::render(exercise, &snapshot, &notes, &view);
+        frames.push((format!("指示ペイン: 目標{}", current + 1), plain.clone()));
+        // ヒント・解答で表示が変わる段階だけを出す
+        let mut previous = plain;
+        for (level, name) in [(1, "ヒント"), (2, "解答")] {
+            snapshot.hint.level = level;
+            let hinted = pane::render(exercise, &snapshot, &notes, &view);
+            if hinted != previous {
+                frames.push((format!("指示ペイン: 目標{}（{}）", current + 1, name), hinted.clone()));
+            }
+            previous = hinted;
+        }
+    }
+    frames.push(("指示ペイン: 完了".to_string(), pane::completion(exercise, view.display)));
+    let outro = ContinuousVimSession::outro_lines(exercise);
+    if !outro.is_empty() {
+        frames.push(("端末: outro".to_string(), outro));
+    }
+    frames
+}
+
+// 場面の区切り（ペインの幅いっぱいの罫線に場面の名前を入れる）
+fn ruler(label: &str, width: usize) -> String {
+    let head = format!("──── {} ", label);
+    let rest = width.saturating_sub(UnicodeWidthStr::width(head.as_str()));
+    format!("{}{}", head, "─".repeat(rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::assert_snapshot;
+
+    #[test]
+    fn test_ruler_fills_the_pane_width() {
+        assert_eq!(UnicodeWidthStr::width(ruler("指示ペイン: 目標1", 40).as_str()), 40);
+        // 名前が幅より長くても切らない
+        assert!(ruler("とても長い場面の名前", 4).starts_with("──── とても長い"));
+    }
+
+    #[test]
+    fn test_preview_rejects_missing_exercise() {
+        let chapter = ContinuousContentLoader::sample_chapter();
+        let count = chapter.continuous_exercises.len();
+        assert!(preview_chapter(&chapter, Some(0), 80, Theme::Emoji).is_err());
+        assert!(preview_chapter(&chapter, Some(count + 1), 80, Theme::Emoji).is_err());
+
+        let single = preview_chapter(&chapter, Some(1), 80, Theme::Emoji).unwrap();
+        let all = preview_chapter(&chapter, None, 80, Theme::Emoji).unwrap();
+        assert!(single.iter().any(|line| line.starts_with("# 練習 1/")));
+        assert!(!single.iter().any(|line| line.starts_with("# 練習 2/")));
+        assert!(all.len() > single.len());
+    }
+
+    #[test]
+    fn test_preview_puts_goal_warnings_under_the_goal() {
+        let mut chapter = ContinuousContentLoader::sample_chapter();
+        let exercise = &mut chapter.continuous_exercises[1];
+        exercise.goals[2].target.as_object_mut().unwrap().remove("normalize");
+        exercise.cursor_start = Some([99, 0]);
+        let lines = preview_chapter(&chapter, Some(2), 80, Theme::Emoji).unwrap();
+        let position = |prefix: &str| lines.iter().position(|line| line.starts_with(prefix)).unwrap();
+        let cursor_warning = position("#   ⚠️ cursor_start");
+        let goal_warning = position("#   ⚠️ 目標3の expected");
+        assert!(cursor_warning < position("# 目標1:"));
+        assert!(position("# 目標3:") < goal_warning && goal_warning < position("# 目標4:"));
+    }
+
+    #[test]
+    fn test_sample_chapter_preview_snapshot() {
+        let lines = preview_chapter(&ContinuousContentLoader::sample_chapter(), None, 60, Theme::Emoji).unwrap();
+        assert_snapshot("preview_sample.txt", &(lines.join("\n") + "\n"));
+    }
+}
@@ -0,0 +1,732 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::output::warning;
+use crate::paths;
+use crate::stats::{self, PersonalRecord, RecordUpdate};
+use crate::summary::{ChapterSummary, ExerciseStatus, ExerciseSummary};
+
+// この秒数より古いハートビートは終了したセッションのものとみなす（書き込みは30秒ごと）
+pub const HEARTBEAT_EXPIRES_SECS: i64 = 120;
+// 進捗ファイルの形式の版。項目を増やすときは上げる
+// （古い版のファイルも読めるよう、追加する項目は #[serde(default)] にする）
+pub const PROGRESS_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LearningMode {
+    #[serde(rename = "continuous")]
+    Continuous, // 連続学習モード
+    #[serde(rename = "classic")]
+    Classic, // 従来のゲームモード
+}
+
+// 練習メニューに出す完了の状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Completion {
+    None,
+    Current,  // いまの内容のまま完了した
+    Outdated, // 完了した後に章データの内容が変わった
+}
+
+// タイムチャレンジ1回分の記録
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChallengeAttempt {
+    pub chapter_number: u8,
+    pub exercise_title: String,
+    pub time_limit_secs: u64,
+    pub duration_secs: f64,
+    pub passed: bool,
+    pub attempted_at: DateTime<Utc>,
+}
+
+// 一時停止したまま終了した練習（同じ章を始めたときに続きから再開できる）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PausedExercise {
+    pub chapter_number: u8,
+    pub exercise_index: usize,
+    pub exercise_title: String,
+    // この練習より前に章の中で完了した練習の記録
+    #[serde(default)]
+    pub earlier_exercises: Vec<ExerciseSummary>,
+    pub current_goal_index: usize,
+    pub completed_goals: Vec<bool>,
+    pub elapsed_secs: f64, // 一時停止までに練習に取り組んだ時間
+    pub hints_used: usize,
+    pub buffer: Vec<String>, // 一時停止した時点のサンプルファイルの内容
+    pub cursor: [usize; 2],  // 一時停止した時点のカーソル位置（0始まりの行・列）
+    pub paused_at: DateTime<Utc>,
+    // 一時停止した時点の練習の内容のハッシュ（章データを書き換えた後は再開しない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+}
+
+// 実行中のセッションが定期的に書き込む印（外部のツールが動いているセッションか確かめるのに使う）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionHeartbeat {
+    pub pid: u32,
+    pub at: DateTime<Utc>,
+    #[serde(default)]
+    pub idle: bool, // 操作が無いまま続けるか尋ねている・一時停止している
+}
+
+impl SessionHeartbeat {
+    pub fn now(idle: bool) -> Self {
+        Self {
+            pid: std::process::id(),
+            at: Utc::now(),
+            idle,
+        }
+    }
+
+    pub fn is_live(&self, now: DateTime<Utc>) -> bool {
+        (now - self.at).num_seconds() < HEARTBEAT_EXPIRES_SECS
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProgressData {
+    // 版の無いファイルは版0（この項目より前の形式）
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub summaries: Vec<ChapterSummary>,
+    #[serde(default)]
+    pub challenges: Vec<ChallengeAttempt>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paused: Option<PausedExercise>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat: Option<SessionHeartbeat>,
+    // 慣らしの練習を通ったか（通った後は行わない）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub warmup_done: bool,
+    // 練習ごとの自己ベスト（版2から。練習の内容のハッシュごと）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub records: Vec<PersonalRecord>,
+    // 新しい版が書いた知らない項目（読み書きしても失わないよう、そのまま持っておく）
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ProgressData {
+    // content がこのプログラムの進捗ファイルか（知っている項目だけを持つ、空でない JSON のオブジェクト）。
+    // 以前の版の置き場所にあった同じ名前の別のファイルを取り込まないために使う
+    pub fn is_progress_file(content: &[u8]) -> bool {
+        let Ok(serde_json::Value::Object(fields)) = serde_json::from_slice::<serde_json::Value>(content) else {
+            return false;
+        };
+        !fields.is_empty()
+            && serde_json::from_slice::<ProgressData>(content).is_ok_and(|data| data.extra.is_empty())
+    }
+}
+
+pub struct ProgressStore {
+    path: PathBuf,
+    data: ProgressData,
+}
+
+impl ProgressStore {
+    pub fn load_default() -> Self {
+        Self::load(paths::progress_file())
+    }
+
+    // ファイルが無い場合は空の進捗から始める。
+    // 壊れている場合は progress.json.corrupt-<日時> に退避してから空の進捗で始める（元の内容は退避先に残る）
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let data = match fs::read(&path) {
+            Ok(content) => match serde_json::from_slice(&content) {
+                Ok(data) => data,
+                Err(e) => {
+                    set_aside_corrupt(&path, &e.to_string());
+                    ProgressData::default()
+                }
+            },
+            Err(_) => ProgressData::default(),
+        };
+        Self { path, data }
+    }
+
+    // 同じディレクトリの一時ファイルに書いてから置き換え、書きかけのファイルを読ませない
+    pub fn save(&mut self) -> Result<()> {
+        let parent = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(parent)?;
+        // 新しい版のファイルを書き直しても版は下げない（知らない項目は extra に残っている）
+        self.data.version = self.data.version.max(PROGRESS_VERSION);
+        let mut partial = tempfile::NamedTempFile::new_in(parent)?;
+        partial.write_all(serde_json::to_string_pretty(&self.data)?.as_bytes())?;
+        partial.as_file().sync_all()?;
+        partial.persist(&self.path)?;
+        Ok(())
+    }
+
+    // ロックを取ってから読み直し、変更して保存する
+    // （同時に動く別のプロセスやスレッドの追記を上書きして失わないよう、記録の更新は全てここを通す）
+    pub fn update<P: AsRef<Path>, T>(path: P, change: impl FnOnce(&mut ProgressStore) -> T) -> Result<T> {
+        let path = path.as_ref();
+        let _lock = lock(path)?;
+        let mut store = Self::load(path);
+        let result = change(&mut store);
+        store.save()?;
+        Ok(result)
+    }
+
+    pub fn data(&self) -> &ProgressData {
+        &self.data
+    }
+
+    pub fn append_summary(&mut self, summary: ChapterSummary) {
+        self.data.summaries.push(summary);
+    }
+
+    pub fn is_chapter_complete(&self, mode: LearningMode, chapter_number: u8) -> bool {
+        self.data
+            .summaries
+            .iter()
+            .any(|s| s.mode == mode && s.chapter_number == chapter_number && s.is_complete())
+    }
+
+    // 途中で終えた章の再開用に、最後の記録のうち先頭から続けて完了した練習を返す
+    // （章を完了したことがあれば最初からやり直すので空）
+    pub fn resume_exercises(&self, mode: LearningMode, chapter_number: u8) -> Vec<ExerciseSummary> {
+        if self.is_chapter_complete(mode, chapter_number) {
+            return Vec::new();
+        }
+        self.data
+            .summaries
+            .iter()
+            .rev()
+            .find(|s| s.mode == mode && s.chapter_number == chapter_number)
+            .map(|s| {
+                s.exercises
+                    .iter()
+                    .take_while(|e| e.status == ExerciseStatus::Completed)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // 章の練習を、いまの内容（content_hash）のまま完了したことがあるか（練習メニューの完了の印）。
+    // 完了の記録が全て書き換える前の内容のものなら Outdated
+    pub fn exercise_completion(
+        &self,
+        mode: LearningMode,
+        chapter_number: u8,
+        title: &str,
+        content_hash: &str,
+    ) -> Completion {
+        let mut completions = self
+            .data
+            .summaries
+            .iter()
+            .filter(|s| s.mode == mode && s.chapter_number == chapter_number)
+            .flat_map(|s| &s.exercises)
+            .filter(|e| e.title == title && e.status == ExerciseStatus::Completed)
+            .peekable();
+        if completions.peek().is_none() {
+            Completion::None
+        } else if completions.any(|e| e.refers_to(title, content_hash)) {
+            Completion::Current
+        } else {
+            Completion::Outdated
+        }
+    }
+
+    // 完了の記録のうち、その後に内容が変わった（または無くなった）練習のもの。
+    // current は章番号と題名から、いまの練習の内容のハッシュを返す
+    pub fn outdated_completions(&self, current: impl Fn(u8, &str) -> Option<String>) -> usize {
+        let chapters: Vec<u8> = self.data.summaries.iter().map(|s| s.chapter_number).collect();
+        self.outdated_completions_in(&chapters, current)
+    }
+
+    // 指定した章（install で更新したパックの章など）についての outdated_completions
+    pub fn outdated_completions_in(&self, chapters: &[u8], current: impl Fn(u8, &str) -> Option<String>) -> usize {
+        self.data
+            .summaries
+            .iter()
+            .filter(|s| s.mode == LearningMode::Continuous && chapters.contains(&s.chapter_number))
+            .flat_map(|s| s.exercises.iter().map(move |e| (s.chapter_number, e)))
+            .filter(|(_, e)| e.status == ExerciseStatus::Completed && e.content_hash.is_some())
+            .filter(|(number, e)| !current(*number, &e.title).is_some_and(|hash| e.refers_to(&e.title, &hash)))
+            .count()
+    }
+
+    // 章の練習を一度でも完了したことがあるか（従来モードの練習メニューの完了の印）
+    pub fn is_exercise_complete(&self, mode: LearningMode, chapter_number: u8, title: &str) -> bool {
+        self.data
+            .summaries
+            .iter()
+            .filter(|s| s.mode == mode && s.chapter_number == chapter_number)
+            .flat_map(|s| &s.exercises)
+            .any(|e| e.title == title && e.status == ExerciseStatus::Completed)
+    }
+
+    // 指定した章で一時停止したままの練習
+    pub fn paused_exercise(&self, chapter_number: u8) -> Option<&PausedExercise> {
+        self.data
+            .paused
+            .as_ref()
+            .filter(|paused| paused.chapter_number == chapter_number)
+    }
+
+    // 一時停止の記録は1件だけ持つ（None で消す）
+    pub fn set_paused(&mut self, paused: Option<PausedExercise>) {
+        self.data.paused = paused;
+    }
+
+    // ハートビートは実行中のセッションの1件だけ持つ（None で消す）
+    pub fn set_heartbeat(&mut self, heartbeat: Option<SessionHeartbeat>) {
+        self.data.heartbeat = heartbeat;
+    }
+
+    // 別のプロセスで動いているセッションのハートビート
+    pub fn live_session(&self, now: DateTime<Utc>) -> Option<&SessionHeartbeat> {
+        self.data
+            .heartbeat
+            .as_ref()
+            .filter(|heartbeat| heartbeat.pid != std::process::id() && heartbeat.is_live(now))
+    }
+
+    pub fn warmup_done(&self) -> bool {
+        self.data.warmup_done
+    }
+
+    pub fn set_warmup_done(&mut self) {
+        self.data.warmup_done = true;
+    }
+
+    pub fn append_challenge(&mut self, attempt: ChallengeAttempt) {
+        self.data.challenges.push(attempt);
+    }
+
+    // 制限時間内にクリアした記録の中で最短のもの
+    pub fn best_challenge_time(&self, chapter_number: u8, exercise_title: &str) -> Option<f64> {
+        self.data
+            .challenges
+            .iter()
+            .filter(|a| {
+                a.passed && a.chapter_number == chapter_number && a.exercise_title == exercise_title
+            })
+            .map(|a| a.duration_secs)
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
+    // 練習の自己ベスト（いまの内容の練習の記録が無ければ None）
+    pub fn personal_record(&self, content_hash: &str) -> Option<&PersonalRecord> {
+        stats::find(&self.data.records, content_hash)
+    }
+
+    pub fn update_records(&mut self, chapter_number: u8, record: &ExerciseSummary) -> Option<RecordUpdate> {
+        stats::update_records(&mut self.data.records, chapter_number, record, Utc::now())
+    }
+
+    // 未完了の章のうち、指定した章の次に来るものを優先して返す
+    pub fn next_incomplete_chapter(
+        &self,
+        mode: LearningMode,
+        chapter_numbers: &[u8],
+        after: u8,
+    ) -> Option<u8> {
+        let mut numbers = chapter_numbers.to_vec();
+        numbers.sort_unstable();
+        let incomplete: Vec<u8> = numbers
+            .into_iter()
+            .filter(|&n| !self.is_chapter_complete(mode, n))
+            .collect();
+        incomplete
+            .iter()
+            .copied()
+            .find(|&n| n > after)
+            .or_else(|| incomplete.first().copied())
+    }
+}
+
+// 更新のあいだ持つ助言ロック（progress.json.lock。ファイルを閉じると外れる）
+fn lock(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path)?;
+    file.lock()?;
+    Ok(file)
+}
+
+// 読めない進捗ファイルを退避する（退避にも失敗した場合は、次の保存で上書きされる）
+fn set_aside_corrupt(path: &Path, reason: &str) {
+    let mut corrupt = path.as_os_str().to_owned();
+    corrupt.push(format!(".corrupt-{}", Utc::now().format("%Y%m%d-%H%M%S%.3f")));
+    let corrupt = PathBuf::from(corrupt);
+    match fs::rename(path, &corrupt) {
+        Ok(()) => warning!(
+            "⚠️ 進捗ファイルが壊れていたため {} に退避し、新しい記録を始めます（{}）",
+            corrupt.display(),
+            reason
+        ),
+        Err(e) => warning!("⚠️ 壊れた進捗ファイルを退避できません: {}", e),
+    }
+}
+
+fn report_save_error(e: anyhow::Error) {
+    warning!("⚠️ 進捗ファイルの保存に失敗: {}", e);
+}
+
+// 章の結果を進捗ファイルに追記し、次に取り組むべき章番号を返す
+pub fn record_chapter_summary(summary: ChapterSummary, chapter_numbers: &[u8]) -> Option<u8> {
+    let mode = summary.mode;
+    let chapter_number = summary.chapter_number;
+    ProgressStore::update(paths::progress_file(), |store| {
+        store.append_summary(summary);
+        store.next_incomplete_chapter(mode, chapter_numbers, chapter_number)
+    })
+    .unwrap_or_else(|e| {
+        report_save_error(e);
+        None
+    })
+}
+
+// チャレンジの結果を進捗ファイルに追記し、それまでの自己ベストを返す
+pub fn record_challenge_attempt(attempt: ChallengeAttempt) -> Option<f64> {
+    ProgressStore::update(paths::progress_file(), |store| {
+        let previous_best =
+            store.best_challenge_time(attempt.chapter_number, &attempt.exercise_title);
+        store.append_challenge(attempt);
+        previous_best
+    })
+    .unwrap_or_else(|e| {
+        report_save_error(e);
+        None
+    })
+}
+
+// 完了した練習の記録で自己ベストを更新し、比べた結果を返す
+pub fn record_personal_best(chapter_number: u8, record: &ExerciseSummary) -> Option<RecordUpdate> {
+    ProgressStore::update(paths::progress_file(), |store| store.update_records(chapter_number, record))
+        .unwrap_or_else(|e| {
+            report_save_error(e);
+            None
+        })
+}
+
+// 一時停止の記録を進捗ファイルに保存する（None で消す）
+pub fn record_paused_exercise(paused: Option<PausedExercise>) {
+    if paused.is_none() && ProgressStore::load_default().data().paused.is_none() {
+        return;
+    }
+    if let Err(e) = ProgressStore::update(paths::progress_file(), |store| store.set_paused(paused)) {
+        report_save_error(e);
+    }
+}
+
+// 慣らしの練習を通ったことを進捗ファイルに保存する
+pub fn record_warmup_done() {
+    if let Err(e) = ProgressStore::update(paths::progress_file(), |store| store.set_warmup_done()) {
+        report_save_error(e);
+    }
+}
+
+// ハートビートを進捗ファイルに保存する（None で消す）
+pub fn record_heartbeat(heartbeat: Option<SessionHeartbeat>) {
+    if heartbeat.is_none() && ProgressStore::load_default().data().heartbeat.is_none() {
+        return;
+    }
+    if let Err(e) =
+        ProgressStore::update(paths::progress_file(), |store| store.set_heartbeat(heartbeat))
+    {
+        report_save_error(e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn summary(chapter_number: u8, status: ExerciseStatus) -> ChapterSummary {
+        ChapterSummary {
+            mode: LearningMode::Continuous,
+            chapter_number,
+            chapter_title: format!("第{}章", chapter_number),
+            finished_at: chrono::Utc::now(),
+            exercises: vec![ExerciseSummary {
+                title: "練習".to_string(),
+                status,
+                duration_secs: 12.0,
+                hints_used: 0,
+                goals_skipped: 0,
+                attempts: 1,
+                keystroke_scores: Vec::new(),
+                content_hash: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_progress_round_trip() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let path = tmp_dir.path().join("progress.json");
+
+        let mut store = ProgressStore::load(&path);
+        assert!(store.data().summaries.is_empty());
+        store.append_summary(summary(1, ExerciseStatus::Completed));
+        store.save()?;
+
+        let reloaded = ProgressStore::load(&path);
+        assert_eq!(reloaded.data().summaries.len(), 1);
+        assert!(reloaded.is_chapter_complete(LearningMode::Continuous, 1));
+        assert!(!reloaded.is_chapter_complete(LearningMode::Classic, 1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_best_challenge_time_ignores_failed_attempts() {
+        let tmp_dir = tempdir().unwrap();
+        let mut store = ProgressStore::load(tmp_dir.path().join("progress.json"));
+        let attempt = |duration_secs, passed| ChallengeAttempt {
+            chapter_number: 1,
+            exercise_title: "hjkl移動マスター".to_string(),
+            time_limit_secs: 60,
+            duration_secs,
+            passed,
+            attempted_at: chrono::Utc::now(),
+        };
+
+        assert_eq!(store.best_challenge_time(1, "hjkl移動マスター"), None);
+        store.append_challenge(attempt(42.0, true));
+        store.append_challenge(attempt(60.0, false));
+        store.append_challenge(attempt(35.5, true));
+        assert_eq!(store.best_challenge_time(1, "hjkl移動マスター"), Some(35.5));
+        assert_eq!(store.best_challenge_time(2, "hjkl移動マスター"), None);
+    }
+
+    #[test]
+    fn test_next_incomplete_chapter() {
+        let tmp_dir = tempdir().unwrap();
+        let mut store = ProgressStore::load(tmp_dir.path().join("progress.json"));
+        store.append_summary(summary(1, ExerciseStatus::Completed));
+        store.append_summary(summary(2, ExerciseStatus::Incomplete));
+
+        let chapters = [1, 2, 3];
+        assert_eq!(
+            store.next_incomplete_chapter(LearningMode::Continuous, &chapters, 1),
+            Some(2)
+        );
+        assert_eq!(
+            store.next_incomplete_chapter(LearningMode::Continuous, &chapters, 3),
+            Some(2)
+        );
+
+        store.append_summary(summary(2, ExerciseStatus::Completed));
+        store.append_summary(summary(3, ExerciseStatus::Completed));
+        assert_eq!(
+            store.next_incomplete_chapter(LearningMode::Continuous, &chapters, 3),
+            None
+        );
+    }
+
+    #[test]
+    fn test_resume_exercises_from_latest_partial_summary() {
+        let tmp_dir = tempdir().unwrap();
+        let mut store = ProgressStore::load(tmp_dir.path().join("progress.json"));
+        assert!(store.resume_exercises(LearningMode::Continuous, 2).is_empty());
+
+        let mut partial = summary(2, ExerciseStatus::Completed);
+        let mut second = partial.exercises[0].clone();
+        second.title = "練習2".to_string();
+        second.status = ExerciseStatus::Incomplete;
+        partial.exercises.push(second.clone());
+        second.title = "練習3".to_string();
+        second.status = ExerciseStatus::Completed;
+        partial.exercises.push(second);
+        store.append_summary(partial);
+
+        // 未完了の練習より後に完了したものがあっても、先頭から続く分だけ
+        let resumed = store.resume_exercises(LearningMode::Continuous, 2);
+        assert_eq!(resumed.len(), 1);
+        assert_eq!(resumed[0].title, "練習");
+        assert!(store.resume_exercises(LearningMode::Classic, 2).is_empty());
+        // 練習メニューの完了の印は、続きに関係なく一度でも完了した練習に付ける
+        assert!(store.is_exercise_complete(LearningMode::Continuous, 2, "練習3"));
+        assert!(!store.is_exercise_complete(LearningMode::Continuous, 2, "練習2"));
+        assert!(!store.is_exercise_complete(LearningMode::Classic, 2, "練習3"));
+
+        store.append_summary(summary(2, ExerciseStatus::Completed));
+        assert!(store.resume_exercises(LearningMode::Continuous, 2).is_empty());
+    }
+
+    #[test]
+    fn test_completions_of_changed_content_are_outdated() {
+        let tmp_dir = tempdir().unwrap();
+        let mut store = ProgressStore::load(tmp_dir.path().join("progress.json"));
+        let mut old = summary(2, ExerciseStatus::Completed);
+        old.exercises[0].content_hash = Some("aaaa".to_string());
+        store.append_summary(old);
+
+        let completion = |store: &ProgressStore, hash| store.exercise_completion(LearningMode::Continuous, 2, "練習", hash);
+        assert_eq!(completion(&store, "aaaa"), Completion::Current);
+        assert_eq!(completion(&store, "bbbb"), Completion::Outdated);
+        assert_eq!(store.exercise_completion(LearningMode::Continuous, 2, "練習2", "aaaa"), Completion::None);
+        assert_eq!(store.outdated_completions(|_, _| Some("bbbb".to_string())), 1);
+        // 練習が無くなった場合も古い内容の記録
+        assert_eq!(store.outdated_completions(|_, _| None), 1);
+        assert_eq!(store.outdated_completions(|_, _| Some("aaaa".to_string())), 0);
+
+        // ハッシュの無い古い記録は、いまの内容のものとみなす
+        store.append_summary(summary(2, ExerciseStatus::Completed));
+        assert_eq!(completion(&store, "bbbb"), Completion::Current);
+        assert_eq!(store.outdated_completions(|_, _| Some("bbbb".to_string())), 1);
+    }
+
+    #[test]
+    fn test_paused_exercise_round_trip() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let path = tmp_dir.path().join("progress.json");
+        let mut store = ProgressStore::load(&path);
+        store.set_paused(Some(PausedExercise {
+            chapter_number: 3,
+            exercise_index: 1,
+            exercise_title: "練習2".to_string(),
+            earlier_exercises: summary(3, ExerciseStatus::Completed).exercises,
+            current_goal_index: 2,
+            completed_goals: vec![true, true, false],
+            elapsed_secs: 41.5,
+            hints_used: 1,
+            buffer: vec!["hello".to_string(), "world".to_string()],
+            cursor: [1, 2],
+            paused_at: chrono::Utc::now(),
+            content_hash: None,
+        }));
+        store.save()?;
+
+        let reloaded = ProgressStore::load(&path);
+        assert!(reloaded.paused_exercise(1).is_none());
+        let paused = reloaded.paused_exercise(3).unwrap();
+        assert_eq!(paused.completed_goals, vec![true, true, false]);
+        assert_eq!(paused.buffer[1], "world");
+        assert_eq!(paused.earlier_exercises.len(), 1);
+
+        // 一時停止の記録が無い進捗ファイルには項目自体を書かない
+        let mut store = reloaded;
+        store.set_paused(None);
+        store.save()?;
+        assert!(!fs::read_to_string(&path)?.contains("paused"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_heartbeat_tells_live_sessions_from_dead() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let path = tmp_dir.path().join("progress.json");
+        let mut store = ProgressStore::load(&path);
+        let now = chrono::Utc::now();
+        store.set_heartbeat(Some(SessionHeartbeat {
+            pid: std::process::id() + 1,
+            at: now,
+            idle: true,
+        }));
+        store.save()?;
+
+        let reloaded = ProgressStore::load(&path);
+        let heartbeat = reloaded.live_session(now).unwrap();
+        assert!(heartbeat.idle);
+        // 書き込みが止まったセッションは終了したものとみなす
+        let later = now + chrono::Duration::seconds(HEARTBEAT_EXPIRES_SECS);
+        assert!(reloaded.live_session(later).is_none());
+        // 自分のプロセスのハートビートは別のセッションとして扱わない
+        let mut store = reloaded;
+        store.set_heartbeat(Some(SessionHeartbeat::now(false)));
+        assert!(store.live_session(now).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_updates_keep_every_record() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let path = tmp_dir.path().join("progress.json");
+        let writers: Vec<_> = (0..8u8)
+            .map(|chapter| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..5 {
+                        ProgressStore::update(&path, |store| {
+                            store.append_summary(summary(chapter + 1, ExerciseStatus::Completed))
+                        })
+                        .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let store = ProgressStore::load(&path);
+        assert_eq!(store.data().summaries.len(), 40);
+        assert_eq!(store.data().version, PROGRESS_VERSION);
+        for chapter in 1..=8 {
+            assert!(store.is_chapter_complete(LearningMode::Continuous, chapter));
+        }
+        // 一時ファイルは残さない
+        let mut names: Vec<_> = fs::read_dir(tmp_dir.path())?
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["progress.json", "progress.json.lock"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_file_is_set_aside_and_unknown_fields_kept() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let path = tmp_dir.path().join("progress.json");
+        let mut store = ProgressStore::load(&path);
+        store.append_summary(summary(1, ExerciseStatus::Completed));
+        store.save()?;
+        let content = fs::read(&path)?;
+        let truncated = &content[..content.len() / 2];
+        fs::write(&path, truncated)?;
+
+        // 書きかけのファイルは退避して空の進捗から始める（元の内容は退避先にそのまま残る）
+        let mut store = ProgressStore::load(&path);
+        assert!(store.data().summaries.is_empty());
+        assert!(!path.exists());
+        let corrupt: Vec<_> = fs::read_dir(tmp_dir.path())?
+            .map(|entry| entry.unwrap().path())
+            .filter(|p| p.to_string_lossy().contains("progress.json.corrupt-"))
+            .collect();
+        assert_eq!(corrupt.len(), 1);
+        assert_eq!(fs::read(&corrupt[0])?, truncated);
+        store.append_summary(summary(2, ExerciseStatus::Completed));
+        store.save()?;
+        assert_eq!(ProgressStore::load(&path).data().summaries.len(), 1);
+
+        // 新しい版が書いた知らない項目と版の番号は、書き直しても残す
+        fs::write(
+            &path,
+            r#"{"version": 9, "summaries": [], "streaks": {"days": 3}}"#,
+        )?;
+        ProgressStore::update(&path, |store| {
+            store.append_summary(summary(3, ExerciseStatus::Completed))
+        })?;
+        let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        assert_eq!(saved["version"], 9);
+        assert_eq!(saved["streaks"]["days"], 3);
+        assert_eq!(saved["summaries"].as_array().unwrap().len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+
+use crate::editor::Editor;
+use crate::progress::PausedExercise;
+use crate::theme::say;
+use crate::vim_backend::RunningSession;
+
+// 練習の開始時と目標の達成ごとに書き、stop_exercise で消す印。
+// 監視側のプロセスが落ちて印が残った場合は、次の起動で同じエディタに再接続して続きから始める
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionManifest {
+    pub pid: u32,
+    pub updated_at: DateTime<Utc>,
+    pub running: RunningSession,
+    // 目標の進み具合（buffer は空。再接続ではエディタのバッファをそのまま使う）
+    pub progress: PausedExercise,
+}
+
+// 一時ファイルに書いてから置き換え、書きかけの印を読ませない
+pub fn write(path: &Path, manifest: &SessionManifest) -> Result<()> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    let partial = path.with_extension("json.partial");
+    fs::write(&partial, serde_json::to_string_pretty(manifest)?)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+pub fn load(path: &Path) -> Option<SessionManifest> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn remove(path: &Path) {
+    let _ = fs::remove_file(path);
+}
+
+// 持ち主のプロセスが終了したのに、エディタの tmux セッションが残っている印
+// （セッションも無くなっていれば、再接続できないため印だけを消す）
+pub fn find_orphan(
+    path: &Path,
+    is_alive: impl Fn(u32) -> bool,
+    session_exists: impl Fn(&str) -> bool,
+) -> Option<SessionManifest> {
+    let manifest = load(path)?;
+    if manifest.pid == std::process::id() || is_alive(manifest.pid) {
+        return None;
+    }
+    if !session_exists(&manifest.running.session_name) {
+        remove(path);
+        return None;
+    }
+    Some(manifest)
+}
+
+pub fn tmux_session_exists(name: &str) -> bool {
+    Command::new("tmux")
+        .args(["has-session", "-t", &format!("={}", name)])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+// 再接続するか尋ねる（空の入力は再接続）
+pub fn prompt_reattach(manifest: &SessionManifest) -> bool {
+    let progress = &manifest.progress;
+    say!("🔌 前回の練習がtmuxに残っています（プロセスが途中で終了したようです）");
+    say!(
+        "   第{}章 練習{}「{}」 目標 {}/{}",
+        progress.chapter_number,
+        progress.exercise_index + 1,
+        progress.exercise_title,
+        progress.current_goal_index + 1,
+        progress.completed_goals.len()
+    );
+    print!("{}", crate::theme::current().line("編集中のバッファのまま再接続して続けますか？ [Y/n]: "));
+    let _ = io::stdout().flush();
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input).is_err() {
+        return false;
+    }
+    !matches!(input.trim().to_lowercase().as_str(), "n" | "no")
+}
+
+// 再接続しない場合の片付け（tmux セッションごとエディタを終了し、ソケットと印を消す）
+pub fn discard(path: &Path, manifest: &SessionManifest) {
+    let _ = Command::new("tmux")
+        .args(["kill-session", "-t", &format!("={}", manifest.running.session_name)])
+        .output();
+    if manifest.running.editor == Editor::Neovim.program() {
+        let _ = fs::remove_file(&manifest.running.server);
+    }
+    remove(path);
+    say!("🧹 前回のセッションを片付けました");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn manifest(pid: u32) -> SessionManifest {
+        SessionManifest {
+            pid,
+            updated_at: Utc::now(),
+            running: RunningSession {
+                session_name: "vim_tutorial_continuous".to_string(),
+                editor: "nvim".to_string(),
+                server: "/tmp/vim_tutorial_continuous_1.sock".to_string(),
+                editor_pane: "%1".to_string(),
+                instruction_pane: "%0".to_string(),
+            },
+            progress: PausedExercise {
+                chapter_number: 2,
+                exercise_index: 1,
+                exercise_title: "テキスト入力練習".to_string(),
+                earlier_exercises: Vec::new(),
+                current_goal_index: 1,
+                completed_goals: vec![true, false],
+                elapsed_secs: 12.0,
+                hints_used: 0,
+                buffer: Vec::new(),
+                cursor: [0, 0],
+                paused_at: Utc::now(),
+                content_hash: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_orphan_needs_dead_owner_and_live_session() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("running_session.json");
+        write(&path, &manifest(4242)).unwrap();
+        assert!(!dir.path().join("running_session.json.partial").exists());
+
+        // 持ち主が動いていれば別のチュートリアルの練習
+        assert!(find_orphan(&path, |_| true, |_| true).is_none());
+        let orphan = find_orphan(&path, |_| false, |name| name == "vim_tutorial_continuous").unwrap();
+        assert_eq!(orphan.running.editor_pane, "%1");
+        assert_eq!(orphan.progress.current_goal_index, 1);
+        assert!(path.exists());
+
+        // セッションが無くなっていれば印だけを消す
+        assert!(find_orphan(&path, |_| false, |_| false).is_none());
+        assert!(!path.exists());
+        assert!(find_orphan(&path, |_| false, |_| true).is_none());
+    }
+}
@@ -0,0 +1,578 @@
+// --generate-sample で書き出すサンプル章（トピックごとの連続学習の章）。
+// 位置の目標はサンプルコードの文字列を目印にする position_at で書き、作者が書き換えても位置を直さずに済むようにする
+use anyhow::Result;
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::content::Difficulty;
+use crate::continuous_content::{ChapterBundle, ChapterInfo, ContinuousChapterData, ContinuousContentLoader};
+use crate::continuous_session::{ContinuousExercise, ExerciseGoal, FlowType};
+
+// トピックの名前（並びが章の番号の順。all はこの全て）
+pub const TOPICS: &[&str] = &["motions", "words", "operators", "search", "registers"];
+
+pub fn generate(topic: &str) -> Option<ContinuousChapterData> {
+    match topic {
+        "motions" => Some(ContinuousContentLoader::sample_chapter()),
+        "words" => Some(word_chapter()),
+        "operators" => Some(operator_chapter()),
+        "search" => Some(search_chapter()),
+        "registers" => Some(register_chapter()),
+        _ => None,
+    }
+}
+
+// 章の番号のファイル名で out_dir に書く。既にあるファイルは上書きせず None を返す
+pub fn write(chapter: &ContinuousChapterData, out_dir: &Path) -> Result<Option<PathBuf>> {
+    fs::create_dir_all(out_dir)?;
+    let path = out_dir.join(format!("continuous_chapter_{:02}.yaml", chapter.chapter.number));
+    if path.exists() {
+        return Ok(None);
+    }
+    fs::write(&path, serde_yaml::to_string(chapter)?)?;
+    Ok(Some(path))
+}
+
+// 章のまとめとして path に書く。既にあるファイルは上書きせず false を返す
+pub fn write_bundle(chapters: Vec<ContinuousChapterData>, path: &Path) -> Result<bool> {
+    if path.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_yaml::to_string(&ChapterBundle { chapters })?)?;
+    Ok(true)
+}
+
+fn chapter(
+    info: ChapterInfo,
+    continuous_exercises: Vec<ContinuousExercise>,
+) -> ContinuousChapterData {
+    ContinuousChapterData {
+        chapter: info,
+        continuous_exercises,
+        lessons: Vec::new(),
+    }
+}
+
+fn info(number: u8, title: &str, description: &str, difficulty: Difficulty, tags: &[&str], requires: &[u8]) -> ChapterInfo {
+    ChapterInfo {
+        number,
+        title: title.to_string(),
+        description: description.to_string(),
+        difficulty: Some(difficulty),
+        estimated_minutes: Some(10),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        requires: requires.to_vec(),
+    }
+}
+
+fn exercise(title: &str, description: &str, sample_code: &[&str], goals: Vec<ExerciseGoal>) -> ContinuousExercise {
+    ContinuousExercise {
+        title: title.to_string(),
+        description: description.to_string(),
+        sample_code: sample_code.iter().map(|line| line.to_string()).collect(),
+        goals,
+        ..Default::default()
+    }
+}
+
+// チートシートに載せるキーも一緒に書く
+fn goal(goal_type: &str, target: serde_json::Value, description: &str, hint: &str, keys: &[&str]) -> ExerciseGoal {
+    ExerciseGoal {
+        goal_type: goal_type.to_string(),
+        target,
+        description: description.to_string(),
+        hint: Some(hint.to_string()),
+        keys: keys.iter().map(|key| key.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+fn word_chapter() -> ContinuousChapterData {
+    chapter(
+        info(
+            2,
+            "単語と行の移動",
+            "w・e・b で単語ごとに、0・^・$ で行の端へ移動します",
+            Difficulty::Beginner,
+            &["motion", "word"],
+            &[1],
+        ),
+        vec![
+            exercise(
+                "w と b で単語を移動",
+                "w で次の単語の先頭へ、b で前の単語の先頭へ移動します",
+                &["const total = price * count;"],
+                vec![
+                    goal(
+                        "position_at",
+                        json!({"text": "total"}),
+                        "w で次の単語 total の先頭へ移動してください",
+                        "w は次の単語の先頭へ移動します",
+                        &["w"],
+                    ),
+                    goal(
+                        "position_at",
+                        json!({"text": "count"}),
+                        "w を続けて count の先頭へ移動してください",
+                        "= や * も1つの単語として止まります",
+                        &["w"],
+                    ),
+                    goal(
+                        "position_at",
+                        json!({"text": "price"}),
+                        "b で price の先頭に戻ってください",
+                        "b は前の単語の先頭へ移動します",
+                        &["b"],
+                    ),
+                ],
+            ),
+            exercise(
+                "e で単語の末尾へ",
+                "e で今の単語（末尾にいれば次の単語）の末尾へ移動します",
+                &["let message = greet(name);"],
+                vec![
+                    goal(
+                        "position_at",
+                        json!({"text": "let", "offset": 2}),
+                        "e で let の末尾へ移動してください",
+                        "e は単語の最後の文字へ移動します",
+                        &["e"],
+                    ),
+                    goal(
+                        "position_at",
+                        json!({"text": "message", "offset": 6}),
+                        "もう一度 e で message の末尾へ移動してください",
+                        "単語の末尾で e を押すと次の単語の末尾へ進みます",
+                        &["e"],
+                    ),
+                    goal(
+                        "position_at",
+                        json!({"text": "greet", "offset": 4}),
+                        "e を続けて greet の末尾へ移動してください",
+                        "= も1つの単語として止まります",
+                        &["e"],
+                    ),
+                ],
+            ),
+            ContinuousExercise {
+                cursor_start: Some([0, 11]),
+                ..exercise(
+                    "0・^・$ で行の端へ",
+                    "$ で行末へ、0 で行頭へ、^ で行の最初の空白でない文字へ移動します",
+                    &["    return value + 1;"],
+                    vec![
+                        goal("position", json!([0, 20]), "$ で行末へ移動してください", "$ は行の最後の文字へ移動します", &["$"]),
+                        goal("position", json!([0, 0]), "0 で行頭へ移動してください", "0 は字下げの空白も含めた行の先頭です", &["0"]),
+                        goal(
+                            "position_at",
+                            json!({"text": "return"}),
+                            "^ で return の先頭へ移動してください",
+                            "^ は字下げを飛ばした最初の文字へ移動します",
+                            &["^"],
+                        ),
+                    ],
+                )
+            },
+            exercise(
+                "数を付けて単語を移動",
+                "w の前に数を付けて、何単語か先へ一度で移動します",
+                &["one two three four five six"],
+                vec![goal(
+                    "counted_motion",
+                    json!({"motion": "w", "min_count": 3, "position": [0, 14]}),
+                    "3w で4つ目の単語 four へ移動してください",
+                    "w を3回押す代わりに 3w と打ちます",
+                    &["3w"],
+                )],
+            ),
+        ],
+    )
+}
+
+fn operator_chapter() -> ContinuousChapterData {
+    chapter(
+        info(
+            3,
+            "オペレーターとテキストオブジェクト",
+            "d・c・y に移動やテキストオブジェクトを組み合わせて編集します",
+            Difficulty::Intermediate,
+            &["operator", "text_object"],
+            &[2],
+        ),
+        vec![
+            ContinuousExercise {
+                cursor_start: Some([0, 4]),
+                ..exercise(
+                    "dw で単語を消す",
+                    "d に w を組み合わせて、カーソルから次の単語の前までを消します",
+                    &["let unused value = 1;"],
+                    vec![ExerciseGoal {
+                        required_operator: Some("d".to_string()),
+                        solution_keys: Some("dw".to_string()),
+                        ..goal(
+                            "text",
+                            json!({"line": 0, "expected": "let value = 1;", "normalize": ["width", "quotes"]}),
+                            "dw で unused を消してください",
+                            "dw は後ろの空白も一緒に消します",
+                            &["dw"],
+                        )
+                    }],
+                )
+            },
+            ContinuousExercise {
+                cursor_start: Some([0, 13]),
+                ..exercise(
+                    "ciw で単語を書き換える",
+                    "ciw でカーソルのある単語を消して、そのまま入力します",
+                    &["let color = 'red';"],
+                    vec![
+                        goal(
+                            "operator_textobject",
+                            json!({"operator": "c", "text_object": "iw"}),
+                            "ciw で red を消してください",
+                            "iw は単語の中（inner word）です。単語のどこにいても使えます",
+                            &["ciw"],
+                        ),
+                        goal(
+                            "text",
+                            json!({"line": 0, "expected": "let color = 'blue';", "normalize": ["width", "quotes"]}),
+                            "blue と入力してください",
+                            "ciw の後は Insert モードです",
+                            &[],
+                        ),
+                        goal("mode", json!("normal"), "Esc でノーマルモードに戻ってください", "Esc を押します", &["<Esc>"]),
+                    ],
+                )
+            },
+            ContinuousExercise {
+                cursor_start: Some([0, 6]),
+                flow_type: FlowType::AnyOrder,
+                ..exercise(
+                    "di( で括弧の中を消す",
+                    "括弧の中にいるとき、di( で括弧を残して中身だけを消します",
+                    &["print(first, second);"],
+                    vec![
+                        goal(
+                            "operator_textobject",
+                            json!({"operator": "d", "text_object": "i("}),
+                            "di( で括弧の中を消してください",
+                            "i( は括弧の中（括弧は含まない）です",
+                            &["di("],
+                        ),
+                        goal(
+                            "text",
+                            json!({"line": 0, "expected": "print();", "normalize": ["width", "quotes"]}),
+                            "括弧の中を空にしてください",
+                            "x で1文字ずつ消しても、di( の目標は達成になりません",
+                            &[],
+                        ),
+                    ],
+                )
+            },
+            ContinuousExercise {
+                cursor_start: Some([0, 14]),
+                ..exercise(
+                    "yiw と P で単語を複製",
+                    "yiw で単語をヤンクし、別の場所に P で貼り付けます",
+                    &["const name = 'Alice';", "const copy = '';"],
+                    vec![
+                        goal(
+                            "register",
+                            json!({"register": "0", "expected": "Alice"}),
+                            "yiw で Alice をヤンクしてください",
+                            "ヤンクしたテキストはレジスタ 0 に入ります",
+                            &["yiw"],
+                        ),
+                        goal(
+                            "text",
+                            json!({"line": 1, "expected": "const copy = 'Alice';", "normalize": ["width", "quotes"]}),
+                            "2行目の '' の間に貼り付けてください",
+                            "j で下の行の2つ目の ' に移り、P でその前に貼り付けます",
+                            &["P"],
+                        ),
+                    ],
+                )
+            },
+        ],
+    )
+}
+
+fn search_chapter() -> ContinuousChapterData {
+    chapter(
+        info(
+            4,
+            "検索で移動",
+            "/ ? n N * で文字列を探して移動します",
+            Difficulty::Beginner,
+            &["search", "motion"],
+            &[2],
+        ),
+        vec![
+            exercise(
+                "/ と ? で検索",
+                "/ で下へ、? で上へ文字列を探して移動します",
+                &["function load(url) {", "  const data = fetch(url);", "  return parse(data);", "}"],
+                vec![
+                    ExerciseGoal {
+                        solution_keys: Some("/parse<CR>".to_string()),
+                        ..goal(
+                            "position_at",
+                            json!({"text": "parse"}),
+                            "/parse で parse へ移動してください",
+                            "/ に続けて探す文字列を打ち、Enter で移動します",
+                            &["/"],
+                        )
+                    },
+                    ExerciseGoal {
+                        solution_keys: Some("?fetch<CR>".to_string()),
+                        ..goal(
+                            "position_at",
+                            json!({"text": "fetch"}),
+                            "?fetch で上の fetch に戻ってください",
+                            "? は / と逆に上へ探します",
+                            &["?"],
+                        )
+                    },
+                ],
+            ),
+            exercise(
+                "n と N で次の一致へ",
+                "n で同じ検索を繰り返し、N で逆の向きに戻ります",
+                &["let a = item;", "let b = item;", "let c = item;"],
+                vec![
+                    ExerciseGoal {
+                        solution_keys: Some("/item<CR>".to_string()),
+                        ..goal(
+                            "position_at",
+                            json!({"text": "item"}),
+                            "/item で最初の item へ移動してください",
+                            "/ に続けて item と打ち、Enter を押します",
+                            &["/"],
+                        )
+                    },
+                    goal(
+                        "position_at",
+                        json!({"text": "item", "occurrence": 3}),
+                        "n を2回押して3つ目の item へ移動してください",
+                        "n は直前の検索をもう一度行います",
+                        &["n"],
+                    ),
+                    goal(
+                        "position_at",
+                        json!({"text": "item", "occurrence": 2}),
+                        "N で1つ前の item に戻ってください",
+                        "N は直前の検索を逆の向きに行います",
+                        &["N"],
+                    ),
+                ],
+            ),
+            ContinuousExercise {
+                cursor_start: Some([3, 0]),
+                ..exercise(
+                    "? で後方検索",
+                    "下の行から ? で上にある TODO を探します",
+                    &["# TODO: tests", "run()", "# TODO: docs", "done()"],
+                    vec![
+                        ExerciseGoal {
+                            solution_keys: Some("?TODO<CR>".to_string()),
+                            ..goal(
+                                "position_at",
+                                json!({"text": "TODO", "occurrence": 2}),
+                                "?TODO ですぐ上の TODO へ移動してください",
+                                "? に続けて TODO と打ち、Enter を押します",
+                                &["?"],
+                            )
+                        },
+                        goal(
+                            "position_at",
+                            json!({"text": "TODO"}),
+                            "n でさらに上の TODO へ移動してください",
+                            "? で検索した後の n は上へ進みます",
+                            &["n"],
+                        ),
+                    ],
+                )
+            },
+            ContinuousExercise {
+                cursor_start: Some([0, 4]),
+                ..exercise(
+                    "* でカーソルの単語を検索",
+                    "* でカーソルのある単語と同じ単語を下へ探します",
+                    &["let count = 0;", "count += 1;", "print(count);"],
+                    vec![
+                        goal(
+                            "position_at",
+                            json!({"text": "count", "occurrence": 2}),
+                            "* で次の count へ移動してください",
+                            "* は検索する単語を打たずに済みます",
+                            &["*"],
+                        ),
+                        goal(
+                            "position_at",
+                            json!({"text": "count", "occurrence": 3}),
+                            "n で3つ目の count へ移動してください",
+                            "* の後の n は同じ単語を探し続けます",
+                            &["n"],
+                        ),
+                    ],
+                )
+            },
+        ],
+    )
+}
+
+fn register_chapter() -> ContinuousChapterData {
+    chapter(
+        info(
+            5,
+            "レジスタと貼り付け",
+            "ヤンクや削除したテキストを、レジスタを選んで貼り付けます",
+            Difficulty::Intermediate,
+            &["register", "paste"],
+            &[3],
+        ),
+        vec![
+            exercise(
+                "yy と p で行を複製",
+                "yy で行をヤンクし、p で下に貼り付けます",
+                &["let x = 1;"],
+                vec![
+                    goal(
+                        "register",
+                        json!({"register": "0", "expected": "let x = 1;", "match": "trimmed"}),
+                        "yy で行をヤンクしてください",
+                        "yy は行全体をヤンクします",
+                        &["yy"],
+                    ),
+                    goal(
+                        "buffer",
+                        json!(["let x = 1;", "let x = 1;"]),
+                        "p で下の行に貼り付けてください",
+                        "行をヤンクした後の p は下の行に貼り付けます",
+                        &["p"],
+                    ),
+                ],
+            ),
+            ContinuousExercise {
+                cursor_start: Some([0, 13]),
+                ..exercise(
+                    "名前付きレジスタにヤンク",
+                    "\"a を付けてレジスタ a にヤンクし、別の行に貼り付けます",
+                    &["const key = 'secret';", "const other = '';"],
+                    vec![
+                        ExerciseGoal {
+                            solution_keys: Some("\"ayiw".to_string()),
+                            ..goal(
+                                "register",
+                                json!({"register": "a", "expected": "secret"}),
+                                "\"ayiw で secret をレジスタ a にヤンクしてください",
+                                "\" に続けてレジスタの名前を打ってから yiw を打ちます",
+                                &["\"a"],
+                            )
+                        },
+                        goal(
+                            "text",
+                            json!({"line": 1, "expected": "const other = 'secret';", "normalize": ["width", "quotes"]}),
+                            "2行目の '' の間にレジスタ a から貼り付けてください",
+                            "j の後 f' で ' に移り、\"ap で後ろに貼り付けます",
+                            &["\"ap"],
+                        ),
+                    ],
+                )
+            },
+            exercise(
+                "ddp で行を入れ替える",
+                "dd で切り取った行を p で下に貼り付けて、2行の順番を入れ替えます",
+                &["second", "first"],
+                vec![ExerciseGoal {
+                    solution_keys: Some("ddp".to_string()),
+                    ..goal(
+                        "buffer",
+                        json!(["first", "second"]),
+                        "1行目と2行目を入れ替えてください",
+                        "dd で消した行はレジスタに入り、p で貼り付けられます",
+                        &["dd", "p"],
+                    )
+                }],
+            ),
+            exercise(
+                "\"0 でヤンクした行を貼る",
+                "削除で無名レジスタが上書きされても、ヤンクした行はレジスタ 0 に残ります",
+                &["keep", "junk"],
+                vec![
+                    goal(
+                        "register",
+                        json!({"register": "0", "expected": "keep", "match": "trimmed"}),
+                        "yy で keep をヤンクしてください",
+                        "ヤンクした行はレジスタ 0 に入ります",
+                        &["yy"],
+                    ),
+                    ExerciseGoal {
+                        solution_keys: Some("jdd\"0p".to_string()),
+                        ..goal(
+                            "buffer",
+                            json!(["keep", "keep"]),
+                            "junk を dd で消してから、\"0p で keep を貼り付けてください",
+                            "dd で消した junk は無名レジスタに入りますが、レジスタ 0 には keep が残っています",
+                            &["\"0p"],
+                        )
+                    },
+                ],
+            ),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator;
+
+    #[test]
+    fn test_every_topic_generates_a_valid_numbered_chapter() {
+        for (index, topic) in TOPICS.iter().enumerate() {
+            let chapter = generate(topic).unwrap();
+            assert_eq!(chapter.chapter.number as usize, index + 1, "{}", topic);
+            assert!((3..=7).contains(&chapter.continuous_exercises.len()), "{}", topic);
+            assert_eq!(validator::validate_chapter(&chapter), Vec::<String>::new(), "{}", topic);
+            // 書き出した YAML も同じように読める
+            let yaml = serde_yaml::to_string(&chapter).unwrap();
+            let parsed = ContinuousContentLoader::parse_chapter(&yaml, &format!("{}.yaml", topic)).unwrap();
+            assert!(validator::validate_chapter(&parsed).is_empty(), "{}", topic);
+        }
+        assert!(generate("unknown").is_none());
+    }
+
+    #[test]
+    fn test_write_keeps_existing_chapters() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let words = generate("words").unwrap();
+        let path = write(&words, dir.path())?.unwrap();
+        assert_eq!(path, dir.path().join("continuous_chapter_02.yaml"));
+        fs::write(&path, "edited")?;
+        assert!(write(&words, dir.path())?.is_none());
+        assert_eq!(fs::read_to_string(&path)?, "edited");
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_bundle_loads_and_validates() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("continuous_bundle_samples.yaml");
+        ContinuousContentLoader::empty().create_sample_bundle(&path.display().to_string())?;
+        let (loader, report) = ContinuousContentLoader::load_from(dir.path())?;
+        assert_eq!(loader.chapter_numbers(), vec![1, 2]);
+        assert_eq!(report.issue_count(), 0);
+        validator::run_validate(dir.path())?;
+
+        // 同じ章を番号のファイルにも書くと、validate も重複を問題として数える
+        write(&generate("words").unwrap(), dir.path())?;
+        assert!(validator::run_validate(dir.path()).is_err());
+        assert!(!write_bundle(Vec::new(), &path)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+// 練習の進み具合（ProgressSnapshot）。監視側のセッションが目標の達成・ヒント・一時停止・終了の節目ごとに
+// 一時ディレクトリの進捗ファイルへ JSON で書き出し、講師の watch が読む共有の状態ファイルにも同じ形で入れる。
+// 項目を変えるときは PROGRESS_SCHEMA を上げる（読む側は知らない版を読まない）
+use anyhow::{Result, anyhow};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// 進捗の形式の版
+pub const PROGRESS_SCHEMA: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalStatus {
+    Done,
+    Current,
+    Pending,
+}
+
+impl GoalStatus {
+    pub fn mark(self) -> &'static str {
+        match self {
+            GoalStatus::Done => "✅",
+            GoalStatus::Current => "▶️",
+            GoalStatus::Pending => "⬜",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoalProgress {
+    pub description: String,
+    pub status: GoalStatus,
+}
+
+// 現在の目標のヒントの段階（0 はまだ出していない）と、練習全体で出したヒントの数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct HintState {
+    pub level: usize,
+    pub hints_used: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ProgressCounters {
+    pub completed: usize,
+    pub total: usize,
+    pub keystrokes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgressSnapshot {
+    pub schema: u32,
+    pub exercise_title: String,
+    pub goals: Vec<GoalProgress>,
+    pub current_goal_index: Option<usize>, // 全ての目標を達成したら None
+    pub hint: HintState,
+    pub elapsed_secs: u64, // 練習に取り組んだ秒数（一時停止していた時間を除く）
+    pub counters: ProgressCounters,
+    #[serde(default)]
+    pub paused: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished: Option<String>, // 練習を終えたときの結果
+}
+
+impl ProgressSnapshot {
+    // 目標の説明と達成の印から作る（current が None か達成済みなら「現在の目標」は無い）
+    pub fn new(exercise_title: &str, descriptions: &[String], completed: &[bool], current: Option<usize>) -> Self {
+        let done = |index: usize| completed.get(index).copied().unwrap_or(false);
+        let current = current.filter(|&index| index < descriptions.len() && !done(index));
+        let goals = descriptions
+            .iter()
+            .enumerate()
+            .map(|(index, description)| GoalProgress {
+                description: description.clone(),
+                status: if done(index) {
+                    GoalStatus::Done
+                } else if Some(index) == current {
+                    GoalStatus::Current
+                } else {
+                    GoalStatus::Pending
+                },
+            })
+            .collect::<Vec<_>>();
+        Self {
+            schema: PROGRESS_SCHEMA,
+            exercise_title: exercise_title.to_string(),
+            counters: ProgressCounters {
+                completed: goals.iter().filter(|goal| goal.status == GoalStatus::Done).count(),
+                total: goals.len(),
+                keystrokes: 0,
+            },
+            goals,
+            current_goal_index: current,
+            hint: HintState::default(),
+            elapsed_secs: 0,
+            paused: false,
+            finished: None,
+        }
+    }
+}
+
+// schema を確かめてから読む（ProgressSnapshot と、それを含む共有の状態ファイルに使う）
+pub fn parse_versioned<T: DeserializeOwned>(content: &str) -> Result<T> {
+    let value: serde_json::Value = serde_json::from_str(content)?;
+    match value.get("schema").and_then(serde_json::Value::as_u64) {
+        Some(schema) if schema == u64::from(PROGRESS_SCHEMA) => Ok(serde_json::from_value(value)?),
+        Some(schema) => Err(anyhow!(
+            "進捗の形式の版 {} には対応していません（このバージョンは版 {}）",
+            schema,
+            PROGRESS_SCHEMA
+        )),
+        None => Err(anyhow!("進捗の形式の版（schema）がありません")),
+    }
+}
+
+pub fn read(path: &Path) -> Result<ProgressSnapshot> {
+    parse_versioned(&fs::read_to_string(path)?)
+}
+
+// 書きかけの JSON を読ませないよう、同じディレクトリの別名に書いてから置き換える
+pub fn write_atomic<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("ファイル名がありません: {}", path.display()))?
+        .to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.tmp", name));
+    fs::write(&temp, serde_json::to_string_pretty(value)?)?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> ProgressSnapshot {
+        let descriptions = vec!["jで下へ".to_string(), "wで次の単語へ".to_string(), "xで消す".to_string()];
+        ProgressSnapshot {
+            hint: HintState { level: 1, hints_used: 2 },
+            elapsed_secs: 42,
+            ..ProgressSnapshot::new("基本移動", &descriptions, &[true, false, false], Some(1))
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trips_through_the_progress_file() -> Result<()> {
+        let snapshot = snapshot();
+        assert_eq!(
+            snapshot.goals.iter().map(|goal| goal.status).collect::<Vec<_>>(),
+            vec![GoalStatus::Done, GoalStatus::Current, GoalStatus::Pending]
+        );
+        assert_eq!((snapshot.counters.completed, snapshot.counters.total), (1, 3));
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("progress.json");
+        write_atomic(&path, &snapshot)?;
+        assert_eq!(read(&path)?, snapshot);
+        assert!(!dir.path().join(".progress.json.tmp").exists());
+        let json = fs::read_to_string(&path)?;
+        assert!(json.contains("\"schema\": 1"));
+        assert!(json.contains("\"status\": \"current\""));
+
+        // 全て達成した練習には現在の目標が無い
+        let done = ProgressSnapshot::new("基本移動", &["jで下へ".to_string()], &[true], Some(0));
+        assert_eq!(done.current_goal_index, None);
+        let finished = ProgressSnapshot { finished: Some("完了".to_string()), ..done };
+        assert_eq!(parse_versioned::<ProgressSnapshot>(&serde_json::to_string(&finished)?)?, finished);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_or_missing_schema_is_rejected() -> Result<()> {
+        let mut value = serde_json::to_value(snapshot())?;
+        value["schema"] = serde_json::json!(PROGRESS_SCHEMA + 1);
+        let error = parse_versioned::<ProgressSnapshot>(&value.to_string()).unwrap_err();
+        assert_eq!(error.to_string(), "進捗の形式の版 2 には対応していません（このバージョンは版 1）");
+        assert!(parse_versioned::<ProgressSnapshot>("2").is_err());
+        assert!(parse_versioned::<ProgressSnapshot>("{\"exercise_title\": \"x\"}").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::session_progress::{self, GoalStatus, ProgressSnapshot};
+use crate::vim_state::{VimMode, VimState};
+
+// 共有ディレクトリに書き出す状態ファイルの名前
+pub const SHARE_STATUS_FILE: &str = "status.json";
+// 状態が変わらなくてもこの間隔で書き出す（ハートビート）
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+// 最後の書き出しからこの秒数を過ぎたら接続が切れたとみなす
+const DISCONNECTED_AFTER_SECS: i64 = 10;
+// watch が状態ファイルを読み直す間隔
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+pub const WATCH_LONG_HELP: &str = "\
+学習者が --share-dir で指定したディレクトリ（同期フォルダや SSH 先）の状態ファイルを読み、
+現在の練習・目標の一覧・カーソル位置・モード・現在の目標に取り組んでいる時間を表示します（読み取り専用）。
+
+共有されるのは位置・モード・目標の進み具合だけです。入力したキーやバッファの内容は共有されません。
+学習者側は2秒ごとに状態を書き出し、10秒以上更新が無いと「接続が切れています」と表示します。
+Ctrl-C で終了します。";
+
+// 講師に見せる学習者の状態（キー入力やバッファの内容は含めない）。練習の進み具合は
+// 進捗ファイルと同じ ProgressSnapshot を同じ階層に並べる
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedStatus {
+    pub updated_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub progress: ProgressSnapshot,
+    pub mode: VimMode,
+    pub cursor: [usize; 2], // 1始まりの行・列
+    pub goal_started_at: DateTime<Utc>,
+}
+
+impl SharedStatus {
+    pub fn new(progress: ProgressSnapshot, state: &VimState) -> Self {
+        let now = Utc::now();
+        Self {
+            updated_at: now,
+            progress,
+            mode: state.mode.clone(),
+            cursor: [state.cursor_line + 1, state.cursor_col + 1],
+            goal_started_at: now,
+        }
+    }
+
+    // 記録時刻と経過秒数を除いて同じ内容か
+    fn same_content(&self, other: &SharedStatus) -> bool {
+        let mut other = other.clone();
+        other.updated_at = self.updated_at;
+        other.goal_started_at = self.goal_started_at;
+        other.progress.elapsed_secs = self.progress.elapsed_secs;
+        *self == other
+    }
+}
+
+// 共有ディレクトリへの書き出し。書き込めなくなったら共有をやめ、セッションは続ける
+pub struct StatusShare {
+    dir: Option<PathBuf>,
+    last: Option<SharedStatus>,
+    last_written: Option<Instant>,
+}
+
+impl StatusShare {
+    pub fn in_dir<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: Some(dir.as_ref().to_path_buf()),
+            last: None,
+            last_written: None,
+        }
+    }
+
+    // 共有しない（--share-dir 未指定・テストなど）
+    pub fn disabled() -> Self {
+        Self {
+            dir: None,
+            last: None,
+            last_written: None,
+        }
+    }
+
+    #[cfg(test)] // テストで書き出した内容を確認する
+    pub fn last(&self) -> Option<&SharedStatus> {
+        self.last.as_ref()
+    }
+
+    // 内容が変わったとき、またはハートビートの間隔が過ぎたときに書き出す
+    pub fn update(&mut self, mut status: SharedStatus) {
+        if self.dir.is_none() {
+            return;
+        }
+        if let Some(last) = &self.last {
+            // 同じ練習の同じ目標なら、取り組み始めた時刻を引き継ぐ
+            if last.progress.exercise_title == status.progress.exercise_title
+                && last.progress.current_goal_index == status.progress.current_goal_index
+            {
+                status.goal_started_at = last.goal_started_at;
+            }
+            let heartbeat_due = self
+                .last_written
+                .is_none_or(|written| written.elapsed() >= HEARTBEAT_INTERVAL);
+            if last.same_content(&status) && !heartbeat_due {
+                return;
+            }
+        }
+        self.write(&status);
+        self.last = Some(status);
+    }
+
+    // 練習の終了を書き出す（直前の状態に結果を付ける）
+    pub fn finish(&mut self, result: &str) {
+        if let Some(mut status) = self.last.take() {
+            status.updated_at = Utc::now();
+            status.progress.finished = Some(result.to_string());
+            self.write(&status);
+        }
+    }
+
+    fn write(&mut self, status: &SharedStatus) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        // 書きかけのファイルを watch に読ませないよう、別名で書いてから置き換える
+        let path = dir.join(SHARE_STATUS_FILE);
+        let written = fs::create_dir_all(dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| session_progress::write_atomic(&path, status));
+        match written {
+            Ok(()) => self.last_written = Some(Instant::now()),
+            Err(e) => {
+                crate::output::warning!("⚠️ 状態を共有できません（{}）: {}", path.display(), e);
+                self.dir = None;
+            }
+        }
+    }
+}
+
+fn format_seconds(secs: i64) -> String {
+    let secs = secs.max(0);
+    if secs < 60 {
+        format!("{}秒", secs)
+    } else {
+        format!("{}分{:02}秒", secs / 60, secs % 60)
+    }
+}
+
+// watch の表示（now は表示する時点）
+pub fn render_status(status: &SharedStatus, now: DateTime<Utc>) -> Vec<String> {
+    let mut lines = Vec::new();
+    let silent = (now - status.updated_at).num_seconds();
+    let progress = &status.progress;
+    let disconnected = progress.finished.is_none() && silent >= DISCONNECTED_AFTER_SECS;
+    if disconnected {
+        lines.push(format!(
+            "🔌 接続が切れています（最後の更新から{}）",
+            format_seconds(silent)
+        ));
+    } else if progress.paused {
+        lines.push("⏸️ 一時停止中".to_string());
+    } else {
+        lines.push("🟢 接続中".to_string());
+    }
+    lines.push(format!("📝 練習: {}", progress.exercise_title));
+    if let Some(result) = &progress.finished {
+        lines.push(format!("🏁 練習を終了しました: {}", result));
+    }
+    lines.push(format!(
+        "📍 カーソル: {}行 {}列  モード: {}",
+        status.cursor[0],
+        status.cursor[1],
+        status.mode.label()
+    ));
+    if progress.finished.is_none() {
+        // 接続が切れている間は最後の更新の時点で止める
+        let until = if disconnected { status.updated_at } else { now };
+        lines.push(format!(
+            "⏱️ 現在の目標: {}",
+            format_seconds((until - status.goal_started_at).num_seconds())
+        ));
+    }
+    lines.push(String::new());
+    lines.push("=== ☑️ 目標一覧 ===".to_string());
+    for (i, goal) in progress.goals.iter().enumerate() {
+        let mark = match goal.status {
+            GoalStatus::Current if progress.finished.is_some() => GoalStatus::Pending.mark(),
+            status => status.mark(),
+        };
+        lines.push(format!("  {} {}. {}", mark, i + 1, goal.description));
+    }
+    lines
+}
+
+pub fn run_watch(dir: &Path) -> Result<()> {
+    let path = dir.join(SHARE_STATUS_FILE);
+    println!("👀 {} を監視します（Ctrl-C で終了）", path.display());
+    let mut shown: Option<Vec<String>> = None;
+    loop {
+        let lines = match fs::read_to_string(&path) {
+            Ok(content) => match session_progress::parse_versioned::<SharedStatus>(&content) {
+                Ok(status) => render_status(&status, Utc::now()),
+                Err(e) => vec![format!("⚠️ 状態ファイルを読めません: {}", e)],
+            },
+            Err(_) => vec![format!(
+                "⌛ 学習者の開始を待っています（{} がまだありません）",
+                path.display()
+            )],
+        };
+        if shown.as_ref() != Some(&lines) {
+            // 画面を消して先頭から描き直す
+            print!("\x1b[2J\x1b[H");
+            for line in &lines {
+                println!("{}", line);
+            }
+            shown = Some(lines);
+        }
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn status(line: usize, goal_index: usize) -> SharedStatus {
+        let state = VimState {
+            cursor_line: line,
+            buffer_content: vec!["secret".to_string()],
+            ..Default::default()
+        };
+        let goals = ["jで下へ".to_string(), "wで次の単語へ".to_string()];
+        let progress = ProgressSnapshot::new("基本移動", &goals, &[goal_index > 0, false], Some(goal_index));
+        SharedStatus::new(progress, &state)
+    }
+
+    #[test]
+    fn test_share_writes_positions_without_buffer_contents() -> Result<()> {
+        let dir = TempDir::new()?;
+        let mut share = StatusShare::in_dir(dir.path());
+        share.update(status(1, 0));
+        let first_started = share.last().unwrap().goal_started_at;
+        share.update(status(2, 0));
+        // 同じ目標のあいだは取り組み始めた時刻を引き継ぐ
+        assert_eq!(share.last().unwrap().goal_started_at, first_started);
+
+        let content = fs::read_to_string(dir.path().join(SHARE_STATUS_FILE))?;
+        assert!(!content.contains("secret"));
+        let written: SharedStatus = session_progress::parse_versioned(&content)?;
+        assert_eq!(written.cursor, [3, 1]);
+        // 進み具合は進捗ファイルと同じ項目で、同じ階層に並ぶ
+        assert!(content.contains("\"schema\": 1"));
+        assert_eq!(written.progress.current_goal_index, Some(0));
+
+        share.finish("Completed");
+        let content = fs::read_to_string(dir.path().join(SHARE_STATUS_FILE))?;
+        let written: SharedStatus = session_progress::parse_versioned(&content)?;
+        assert_eq!(written.progress.finished.as_deref(), Some("Completed"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_shows_disconnected_after_silence() {
+        let mut shared = status(3, 1);
+        shared.goal_started_at = shared.updated_at - chrono::Duration::seconds(75);
+        let lines = render_status(&shared, shared.updated_at + chrono::Duration::seconds(1));
+        assert_eq!(lines[0], "🟢 接続中");
+        assert!(lines.contains(&"📍 カーソル: 4行 1列  モード: ノーマル".to_string()));
+        assert!(lines.contains(&"⏱️ 現在の目標: 1分16秒".to_string()));
+        assert!(lines.contains(&"  ✅ 1. jで下へ".to_string()));
+        assert!(lines.contains(&"  ▶️ 2. wで次の単語へ".to_string()));
+
+        let lines = render_status(&shared, shared.updated_at + chrono::Duration::seconds(30));
+        assert_eq!(lines[0], "🔌 接続が切れています（最後の更新から30秒）");
+    }
+}
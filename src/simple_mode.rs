@@ -0,0 +1,262 @@
+// 簡易モード（--simple）: tmux も監視用スクリプトのタイマーも使わず、1つの端末で練習する。
+// 目標を端末に表示してからエディタを前面で起動し、学習者がエディタを閉じたら、終了時（VimLeavePre）に
+// 書き出した最後の状態とバッファを GoalDetector で判定する。結果と次の目標を表示して起動し直す。
+// 監視のできない古いエディタ（タイマーの無い Vim など）では、連続学習モードが自動でこのモードになる
+use anyhow::Result;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::continuous_session::{ContinuousExercise, ExerciseGoal, ExerciseResult, FlowType};
+use crate::editor::Editor;
+use crate::output::warning;
+use crate::summary::{ExerciseStatus, ExerciseSummary};
+use crate::theme::say;
+use crate::vim_state::{GoalDetector, VimState, parse_status_content};
+
+// 最後の状態だけで判定できる目標の種類（モードやキー入力の目標は、エディタを閉じた後には分からない）
+const SNAPSHOT_GOAL_TYPES: &[&str] = &["position", "text", "text_block", "buffer", "window_count"];
+
+static SIMPLE: OnceLock<()> = OnceLock::new();
+
+pub fn enable() {
+    let _ = SIMPLE.set(());
+}
+
+pub fn enabled() -> bool {
+    SIMPLE.get().is_some()
+}
+
+// エディタを閉じるときに最後の状態の行とバッファを書き出すスクリプト。
+// タイマーも JSON も使わないため古い Vim でも動く（列は col('.') のバイト単位なので版3の行にする）
+pub fn leave_script(status_path: &Path, buffer_path: &Path) -> String {
+    let quote = |path: &Path| path.display().to_string().replace('\'', "''");
+    format!(
+        "augroup VimTutorialSimple\n\
+         autocmd!\n\
+         autocmd VimLeavePre * call writefile(['V:3,LINE:' . line('.') . ',COL:' . col('.') . ',MODE:n,WINS:' . winnr('$') \
+         . ',WIN:' . winnr()], '{}')\n\
+         autocmd VimLeavePre * call writefile(getline(1, '$'), '{}')\n\
+         augroup END\n",
+        quote(status_path),
+        quote(buffer_path)
+    )
+}
+
+// 書き出された最後の状態とバッファ（どちらかが無ければ None）
+pub fn read_final_state(status_path: &Path, buffer_path: &Path) -> Option<VimState> {
+    let status = fs::read_to_string(status_path).ok()?;
+    let buffer = fs::read_to_string(buffer_path).ok()?;
+    let mut state = parse_status_content(&format!(
+        "{}\nBUF:{}",
+        status.trim(),
+        serde_json::to_string(&buffer.lines().collect::<Vec<_>>()).ok()?
+    ));
+    if state.buffer_content.is_empty() {
+        state.buffer_content.push(String::new());
+    }
+    Some(state)
+}
+
+// 1つの目標を最後の状態で判定した結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Met,
+    Unmet(String),
+    // 最後の状態からは判定できない種類の目標（飛ばす）
+    Unjudged,
+}
+
+pub fn judge(goal_def: &ExerciseGoal, state: &VimState) -> Result<Verdict> {
+    if !SNAPSHOT_GOAL_TYPES.contains(&goal_def.goal_type.as_str()) || goal_def.file.is_some() {
+        return Ok(Verdict::Unjudged);
+    }
+    let check = GoalDetector::new().check_goal(&goal_def.to_goal()?, state);
+    Ok(match check.gap() {
+        None => Verdict::Met,
+        Some(gap) => Verdict::Unmet(gap.to_string()),
+    })
+}
+
+// 順番通り以外の練習は、簡易モードでは目標を上から1つずつ判定する（その旨の警告を返す）
+pub fn degraded_flow_warning(exercise: &ContinuousExercise) -> Option<&'static str> {
+    match exercise.flow_type {
+        FlowType::Sequential => None,
+        FlowType::AnyOrder => Some("⚠️ 簡易モードでは、順不同の練習も目標を上から順に1つずつ判定します"),
+        FlowType::Parallel => Some("⚠️ 簡易モードでは、同時に達成する練習も目標を上から順に1つずつ判定します"),
+    }
+}
+
+// 1つの練習を簡易モードで行い、結果と記録を返す。目標ごとにエディタを起動し、閉じたら判定する
+pub fn run_exercise(original: &ContinuousExercise, editor: Editor) -> Result<(ExerciseResult, ExerciseSummary)> {
+    let mut exercise = original.clone();
+    for issue in exercise.resolve_anchors() {
+        warning!("⚠️ {}", issue);
+    }
+    if let Some(message) = degraded_flow_warning(&exercise) {
+        warning!("{}", message);
+    }
+    say!("{}", exercise.description);
+
+    let dir = tempfile::tempdir()?;
+    let sample_file = dir.path().join("practice.txt");
+    let status_file = dir.path().join("status.txt");
+    let buffer_file = dir.path().join("buffer.txt");
+    let script_file = dir.path().join("simple.vim");
+    fs::write(&sample_file, exercise.sample_code.join("\n"))?;
+    fs::write(&script_file, leave_script(&status_file, &buffer_file))?;
+
+    let mut cursor = exercise.cursor_start.unwrap_or([0, 0]);
+    let mut active = Duration::ZERO;
+    let mut goals_skipped = 0;
+    let mut result = ExerciseResult::Completed;
+    let total = exercise.goals.len();
+    let mut index = 0;
+    while index < total {
+        let goal_def = &exercise.goals[index];
+        say!("\n🎯 目標 {}/{}: {}", index + 1, total, goal_def.description);
+        if let Some(hint) = &goal_def.hint {
+            say!("💡 {}", hint);
+        }
+        say!("⌨️ Enter でエディタを開きます。終えたら :q（保存しなくても構いません）で閉じると判定します");
+        if read_line().is_none() {
+            result = ExerciseResult::Incomplete;
+            break;
+        }
+
+        let _ = fs::remove_file(&status_file);
+        let started = Instant::now();
+        let status = Command::new(editor.program())
+            .arg("-n")
+            .arg(format!("+call cursor({}, {})", cursor[0] + 1, byte_col(&exercise.sample_code, cursor)))
+            .arg("-S")
+            .arg(&script_file)
+            .arg(&sample_file)
+            .status();
+        active += started.elapsed();
+        if let Err(e) = status {
+            result = ExerciseResult::Failed(format!("{} を起動できません: {}", editor.program(), e));
+            break;
+        }
+        let Some(state) = read_final_state(&status_file, &buffer_file) else {
+            result = ExerciseResult::Failed("エディタを閉じたときの状態が書き出されませんでした".to_string());
+            break;
+        };
+        // 保存せずに閉じても、次に開くときは閉じたときの内容から続ける
+        fs::write(&sample_file, state.buffer_content.join("\n"))?;
+        exercise.sample_code = state.buffer_content.clone();
+        cursor = [state.cursor_line, state.cursor_col];
+
+        match judge(goal_def, &state)? {
+            Verdict::Met => {
+                say!("✅ 達成しました");
+                index += 1;
+            }
+            Verdict::Unjudged => {
+                say!("⏭️ この目標は簡易モードでは判定できないため、飛ばします");
+                goals_skipped += 1;
+                index += 1;
+            }
+            Verdict::Unmet(reason) => {
+                say!("❌ まだ達成していません: {}", reason);
+                print!("{}", crate::theme::current().line("Enter でもう一度 / s で飛ばす / q でやめる: "));
+                let _ = io::stdout().flush();
+                match read_line().as_deref().map(str::trim) {
+                    None | Some("q") => {
+                        result = ExerciseResult::Incomplete;
+                        break;
+                    }
+                    Some("s") => {
+                        goals_skipped += 1;
+                        index += 1;
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+
+    let record = ExerciseSummary {
+        title: original.title.clone(),
+        status: match &result {
+            ExerciseResult::Completed => ExerciseStatus::Completed,
+            ExerciseResult::Incomplete => ExerciseStatus::Incomplete,
+            ExerciseResult::Failed(_) => ExerciseStatus::Failed,
+            ExerciseResult::TimedOut => ExerciseStatus::TimedOut,
+        },
+        duration_secs: active.as_secs_f64(),
+        hints_used: 0,
+        goals_skipped,
+        attempts: 1,
+        keystroke_scores: Vec::new(),
+        content_hash: Some(original.content_hash()),
+    };
+    Ok((result, record))
+}
+
+// 0始まりの [行, 文字単位の列] を cursor() に渡す1始まりのバイト単位の列にする
+fn byte_col(lines: &[String], [line, col]: [usize; 2]) -> usize {
+    lines
+        .get(line)
+        .map_or(0, |text| text.chars().take(col).map(char::len_utf8).sum::<usize>())
+        + 1
+}
+
+fn read_line() -> Option<String> {
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn goal(goal_type: &str, target: serde_json::Value) -> ExerciseGoal {
+        ExerciseGoal {
+            goal_type: goal_type.to_string(),
+            target,
+            description: "目標".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_final_snapshot_is_judged_with_goal_detector() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let status = dir.path().join("status.txt");
+        let buffer = dir.path().join("buffer.txt");
+        // 列はバイト単位で書き出されるため、「あい」の後ろの3文字目は7バイト目
+        fs::write(&status, "V:3,LINE:1,COL:7,MODE:n,WINS:1,WIN:1\n")?;
+        fs::write(&buffer, "あいう\nworld\n")?;
+        let state = read_final_state(&status, &buffer).unwrap();
+        assert_eq!((state.cursor_line, state.cursor_col), (0, 2));
+        assert_eq!(state.buffer_content, vec!["あいう", "world"]);
+
+        assert_eq!(judge(&goal("position", json!([0, 2])), &state)?, Verdict::Met);
+        assert!(matches!(judge(&goal("position", json!([1, 0])), &state)?, Verdict::Unmet(_)));
+        assert_eq!(judge(&goal("mode", json!("insert")), &state)?, Verdict::Unjudged);
+        assert_eq!(byte_col(&state.buffer_content, [0, 2]), 7);
+        assert!(read_final_state(&dir.path().join("missing"), &buffer).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_leave_script_and_flow_degradation() {
+        let script = leave_script(Path::new("/tmp/it's/status"), Path::new("/tmp/buffer"));
+        assert!(script.contains("autocmd VimLeavePre"));
+        assert!(script.contains("'/tmp/it''s/status'"));
+        assert!(!script.contains("timer_start"));
+
+        let mut exercise = crate::warmup::exercise();
+        assert_eq!(degraded_flow_warning(&exercise), None);
+        exercise.flow_type = FlowType::AnyOrder;
+        assert!(degraded_flow_warning(&exercise).unwrap().contains("順不同"));
+    }
+}
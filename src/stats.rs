@@ -0,0 +1,237 @@
+// 練習ごとの自己ベスト（最短の完了時間と最高のキー効率）。進捗ファイルの records に練習の内容のハッシュごとに
+// 保存するため、章データを書き換えた練習は記録をやり直す。練習メニュー・練習の終わり・stats で表示する
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthStr;
+
+use crate::keystrokes;
+use crate::summary::{ExerciseStatus, ExerciseSummary, format_duration, pad};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersonalRecord {
+    pub chapter_number: u8,
+    pub exercise_title: String,
+    pub content_hash: String, // ContinuousExercise::content_hash
+    pub best_time_secs: f64,
+    // キー効率（最適キー数の設定された目標が無い練習では None）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_efficiency: Option<f64>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// 自己ベストを比べた結果（previous はそれまでの記録。初めての記録なら None）
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordUpdate {
+    pub previous: Option<PersonalRecord>,
+    pub current: PersonalRecord,
+    pub time: bool,
+    pub efficiency: bool,
+}
+
+impl RecordUpdate {
+    pub fn is_new_record(&self) -> bool {
+        self.time || self.efficiency
+    }
+}
+
+// 完了した練習の記録で自己ベストを更新する（未完了とハッシュの無い記録は比べない）。
+// 同じ章・題名で内容のハッシュが違う記録は、書き換える前の練習のものとして消す
+pub fn update_records(
+    records: &mut Vec<PersonalRecord>,
+    chapter_number: u8,
+    record: &ExerciseSummary,
+    at: DateTime<Utc>,
+) -> Option<RecordUpdate> {
+    if record.status != ExerciseStatus::Completed {
+        return None;
+    }
+    let hash = record.content_hash.as_deref()?;
+    records.retain(|r| r.content_hash == hash || r.chapter_number != chapter_number || r.exercise_title != record.title);
+
+    let efficiency = keystrokes::overall_efficiency(&record.keystroke_scores);
+    let Some(best) = records.iter_mut().find(|r| r.content_hash == hash) else {
+        let current = PersonalRecord {
+            chapter_number,
+            exercise_title: record.title.clone(),
+            content_hash: hash.to_string(),
+            best_time_secs: record.duration_secs,
+            best_efficiency: efficiency,
+            updated_at: at,
+        };
+        records.push(current.clone());
+        return Some(RecordUpdate { previous: None, current, time: true, efficiency: efficiency.is_some() });
+    };
+    let previous = best.clone();
+    let time = record.duration_secs < best.best_time_secs;
+    let efficiency = efficiency.filter(|&e| best.best_efficiency.is_none_or(|b| e > b));
+    if time {
+        best.best_time_secs = record.duration_secs;
+    }
+    if let Some(efficiency) = efficiency {
+        best.best_efficiency = Some(efficiency);
+    }
+    if time || efficiency.is_some() {
+        best.updated_at = at;
+    }
+    Some(RecordUpdate { previous: Some(previous), current: best.clone(), time, efficiency: efficiency.is_some() })
+}
+
+// いまの内容の練習の自己ベスト
+pub fn find<'a>(records: &'a [PersonalRecord], content_hash: &str) -> Option<&'a PersonalRecord> {
+    records.iter().find(|r| r.content_hash == content_hash)
+}
+
+fn efficiency_label(efficiency: f64) -> String {
+    format!("{:.0}%", efficiency * 100.0)
+}
+
+// 練習メニューに出す自己ベスト（"🏆 1分05秒・効率 92%"）
+pub fn best_label(record: &PersonalRecord) -> String {
+    match record.best_efficiency {
+        Some(efficiency) => format!(
+            "🏆 {}・効率 {}",
+            format_duration(record.best_time_secs),
+            efficiency_label(efficiency)
+        ),
+        None => format!("🏆 {}", format_duration(record.best_time_secs)),
+    }
+}
+
+// 練習の終わりに出す、自己ベストとの比較
+pub fn record_lines(update: &RecordUpdate) -> Vec<String> {
+    let Some(previous) = &update.previous else {
+        return vec![format!("🏆 初めての記録: {}", best_label(&update.current).trim_start_matches("🏆 "))];
+    };
+    let mut lines = Vec::new();
+    if update.time {
+        lines.push(format!(
+            "🏆 最短時間を更新! {} → {}",
+            format_duration(previous.best_time_secs),
+            format_duration(update.current.best_time_secs)
+        ));
+    }
+    if update.efficiency
+        && let Some(efficiency) = update.current.best_efficiency
+    {
+        lines.push(match previous.best_efficiency {
+            Some(before) => format!("🏆 最高のキー効率を更新! {} → {}", efficiency_label(before), efficiency_label(efficiency)),
+            None => format!("🏆 キー効率の初めての記録: {}", efficiency_label(efficiency)),
+        });
+    }
+    if lines.is_empty() {
+        lines.push(format!("自己ベスト: {}", best_label(previous).trim_start_matches("🏆 ")));
+    }
+    lines
+}
+
+// stats で出す自己ベストの表（章・題名の順）
+pub fn render_records_table(records: &[PersonalRecord]) -> Vec<String> {
+    let headers = ["章", "練習", "最短時間", "最高効率", "更新日"];
+    let mut sorted: Vec<&PersonalRecord> = records.iter().collect();
+    sorted.sort_by(|a, b| (a.chapter_number, &a.exercise_title).cmp(&(b.chapter_number, &b.exercise_title)));
+    let rows: Vec<[String; 5]> = sorted
+        .iter()
+        .map(|r| {
+            [
+                r.chapter_number.to_string(),
+                r.exercise_title.clone(),
+                format_duration(r.best_time_secs),
+                r.best_efficiency.map_or_else(|| "-".to_string(), efficiency_label),
+                r.updated_at.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string(),
+            ]
+        })
+        .collect();
+
+    let mut widths = headers.map(UnicodeWidthStr::width);
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row.iter()) {
+            *w = (*w).max(UnicodeWidthStr::width(cell.as_str()));
+        }
+    }
+    let format_row = |cells: &[String]| -> String {
+        cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, &w)| pad(cell, w))
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_string()
+    };
+    let header_cells: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    let mut lines = vec![
+        format_row(&header_cells),
+        widths.iter().map(|&w| "-".repeat(w)).collect::<Vec<_>>().join("-+-"),
+    ];
+    lines.extend(rows.iter().map(|row| format_row(row)));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keystrokes::KeystrokeScore;
+
+    fn completed(duration_secs: f64, hash: &str, keys: Option<(usize, usize)>) -> ExerciseSummary {
+        ExerciseSummary {
+            status: ExerciseStatus::Completed,
+            duration_secs,
+            keystroke_scores: keys
+                .map(|(optimal, actual)| KeystrokeScore { label: "w".to_string(), optimal, actual })
+                .into_iter()
+                .collect(),
+            content_hash: Some(hash.to_string()),
+            ..ExerciseSummary::not_started("単語の移動")
+        }
+    }
+
+    #[test]
+    fn test_records_detect_faster_and_more_efficient_runs() {
+        let mut records = Vec::new();
+        let at = Utc::now();
+        let first = update_records(&mut records, 2, &completed(80.0, "h1", Some((4, 8))), at).unwrap();
+        assert!(first.previous.is_none() && first.is_new_record());
+        assert_eq!(record_lines(&first), vec!["🏆 初めての記録: 1分20秒・効率 50%"]);
+
+        // 遅いが効率の良い回は効率だけを更新する
+        let slower = update_records(&mut records, 2, &completed(95.0, "h1", Some((4, 5))), at).unwrap();
+        assert!(!slower.time && slower.efficiency);
+        assert_eq!(record_lines(&slower), vec!["🏆 最高のキー効率を更新! 50% → 80%"]);
+
+        let faster = update_records(&mut records, 2, &completed(61.0, "h1", Some((4, 8))), at).unwrap();
+        assert!(faster.time && !faster.efficiency);
+        assert_eq!(record_lines(&faster), vec!["🏆 最短時間を更新! 1分20秒 → 1分01秒"]);
+        assert_eq!(best_label(find(&records, "h1").unwrap()), "🏆 1分01秒・効率 80%");
+
+        let same = update_records(&mut records, 2, &completed(70.0, "h1", None), at).unwrap();
+        assert!(!same.is_new_record());
+        assert_eq!(record_lines(&same), vec!["自己ベスト: 1分01秒・効率 80%"]);
+
+        // 未完了の回とハッシュの無い記録は比べない
+        let incomplete = ExerciseSummary { status: ExerciseStatus::Incomplete, ..completed(1.0, "h1", None) };
+        assert!(update_records(&mut records, 2, &incomplete, at).is_none());
+        let unhashed = ExerciseSummary { content_hash: None, ..completed(1.0, "h1", None) };
+        assert!(update_records(&mut records, 2, &unhashed, at).is_none());
+        assert_eq!(records.len(), 1);
+    }
+
+    #[test]
+    fn test_edited_exercise_resets_records() {
+        let mut records = Vec::new();
+        let at = Utc::now();
+        update_records(&mut records, 2, &completed(30.0, "old", None), at);
+        update_records(&mut records, 3, &completed(40.0, "other", None), at);
+
+        // 内容が変わった練習は、前より遅くても初めての記録になる
+        let edited = update_records(&mut records, 2, &completed(90.0, "new", None), at).unwrap();
+        assert!(edited.previous.is_none());
+        assert!(find(&records, "old").is_none());
+        assert_eq!(find(&records, "new").unwrap().best_time_secs, 90.0);
+        assert_eq!(records.len(), 2);
+
+        let table = render_records_table(&records);
+        assert_eq!(table.len(), 4);
+        assert!(table[0].starts_with("章 | 練習"));
+        assert!(table[2].starts_with("2  | 単語の移動 | 1分30秒  | -"));
+    }
+}
@@ -0,0 +1,301 @@
+use crate::vim_state::{VimMode, VimState};
+
+// 状態の行の形式の版。項目を増やすときは上げ、読み取り側は知らない項目を無視する
+// （古い版の行も読めるよう、追加する項目は欠けていても既定値で埋められるものにする）
+pub const STATUS_LINE_VERSION: u32 = 4;
+
+// 状態の行（エディタが書き、監視側が読む。連続学習モードと従来のゲームモードで共通）
+// 形式: "V:3,LINE:1,COL:1,MODE:n,DETAILED:n,OP:d,TICK:3,MOD:0,WINS:2,WIN:1,UNDO:4"
+//   V        形式の版（無ければ版0 = 以前のゲームモードの "LINE:..,COL:..,MODE:.." 形式）
+//   LINE/COL カーソル位置（1ベース）。COL は版4から文字単位（版3までは col('.') のバイト単位）
+//   MODE     mode() の値、DETAILED は mode(1) の値（制御文字は CTRL_V / CTRL_S に置き換える）
+//   OP       v:operator（オペレーター待機中のみ意味を持つ）
+//   TICK     b:changedtick、MOD は &modified
+//   WINS/WIN ウィンドウの数と今いるウィンドウの番号（版2から。指示ウィンドウは数えない）
+//   UNDO     undotree().seq_cur（版3から。取り消しの目標に使う）
+// 欠けた項目は既定値で読む。
+//
+// カーソルの列を文字単位で返す式（charcol() の無い古いエディタでは行の先頭からの文字数で数える）
+pub const CHAR_COL_EXPR: &str =
+    "exists('*charcol') ? charcol('.') : strchars(strpart(getline('.'), 0, col('.') - 1)) + 1";
+
+// last 番までのウィンドウのうち、指示ウィンドウ（b:tutorial_instructions を持つバッファ）以外の数を返す式
+pub fn window_count_expr(last: &str) -> String {
+    format!(
+        "len(filter(range(1, {}), '!getbufvar(winbufnr(v:val), \"tutorial_instructions\", 0)'))",
+        last
+    )
+}
+
+// 状態の行を組み立てるVimスクリプト関数（TutorialStatusLine() を呼ぶ）。
+// 章データの列も文字単位のため、カーソルを置くときは cursor() ではなく TutorialSetCursor() を使う
+// （Ctrl-V / Ctrl-S などの制御文字は writefile 経由で失われることがあるため、トークンに変換する）
+pub fn vim_functions() -> String {
+    format!(
+        r#"
+function! TutorialModeToken(mode)
+  let token = substitute(a:mode, nr2char(22), 'CTRL_V', 'g')
+  return substitute(token, nr2char(19), 'CTRL_S', 'g')
+endfunction
+
+" last 番までのウィンドウのうち、指示ウィンドウ以外の数
+function! TutorialWindowCount(last)
+  return {window_count}
+endfunction
+
+" 1ベースの行と文字単位の列にカーソルを置く（行末を越える列は行末にする）
+function! TutorialSetCursor(line, col)
+  if exists('*setcharpos')
+    call setcharpos('.', [0, a:line, a:col, 0])
+    return
+  endif
+  let byte = byteidx(getline(a:line), a:col - 1)
+  call cursor(a:line, byte < 0 ? col([a:line, '$']) : byte + 1)
+endfunction
+
+function! TutorialCharCol()
+  return {char_col}
+endfunction
+
+function! TutorialStatusLine()
+  return 'V:{version},LINE:' . line('.') . ',COL:' . TutorialCharCol() . ',MODE:' . TutorialModeToken(mode()) . ',DETAILED:' . TutorialModeToken(mode(1)) . ',OP:' . v:operator . ',TICK:' . b:changedtick . ',MOD:' . &modified . ',WINS:' . TutorialWindowCount(winnr('$')) . ',WIN:' . TutorialWindowCount(winnr()) . ',UNDO:' . undotree().seq_cur
+endfunction
+"#,
+        version = STATUS_LINE_VERSION,
+        char_col = CHAR_COL_EXPR,
+        window_count = window_count_expr("a:last")
+    )
+}
+
+// 状態の行か（版0の行は "LINE:" で始まる）
+pub fn is_status_line(line: &str) -> bool {
+    line.starts_with("V:") || line.starts_with("LINE:")
+}
+
+// モードトークンを mode() の元の文字列に戻す
+fn decode_mode_token(token: &str) -> String {
+    token.replace("CTRL_V", "\u{16}").replace("CTRL_S", "\u{13}")
+}
+
+#[cfg(test)]
+fn encode_mode_token(mode: &str) -> String {
+    mode.replace('\u{16}', "CTRL_V").replace('\u{13}', "CTRL_S")
+}
+
+// 状態の行の形式の版（V が無い行は版0）
+pub fn version(line: &str) -> u32 {
+    line.trim()
+        .split(',')
+        .find_map(|part| part.strip_prefix("V:"))
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0)
+}
+
+// 版3までの行のバイト単位の列（0ベース）を、その行の文字単位の列にする
+// （文字の途中を指すバイトはその文字の列にし、行末を越える分はそのまま足す）
+pub fn char_column(text: &str, byte_col: usize) -> usize {
+    let before = text.char_indices().filter(|(byte, c)| byte + c.len_utf8() <= byte_col).count();
+    before + byte_col.saturating_sub(text.len())
+}
+
+// 状態の行を書く（TutorialStatusLine() と同じ形式。モードは mode() / mode(1) の代表的な値にする）
+#[cfg(test)] // 形式の往復をテストで確かめる
+pub fn emit(state: &VimState) -> String {
+    let (mode, detailed, operator) = match &state.mode {
+        VimMode::Normal => ("n", "n", ""),
+        VimMode::Insert => ("i", "i", ""),
+        VimMode::Visual => ("v", "v", ""),
+        VimMode::VisualLine => ("V", "V", ""),
+        VimMode::VisualBlock => ("\u{16}", "\u{16}", ""),
+        VimMode::OperatorPending(operator) => ("n", "no", operator.as_str()),
+        VimMode::Command => ("c", "c", ""),
+        VimMode::Replace => ("R", "R", ""),
+        VimMode::Select => ("s", "s", ""),
+        VimMode::Terminal => ("t", "t", ""),
+    };
+    format!(
+        "V:{},LINE:{},COL:{},MODE:{},DETAILED:{},OP:{},TICK:{},MOD:{},WINS:{},WIN:{},UNDO:{}",
+        STATUS_LINE_VERSION,
+        state.cursor_line + 1,
+        state.cursor_col + 1,
+        encode_mode_token(mode),
+        encode_mode_token(detailed),
+        operator,
+        state.changedtick,
+        u8::from(state.modified),
+        state.window_count,
+        state.current_window,
+        state.undo_seq
+    )
+}
+
+// 状態の行を読む（バッファとレジスタは空のまま。状態ファイルの他の行は vim_state が読む）
+pub fn parse(line: &str) -> VimState {
+    let mut line_num: usize = 1;
+    let mut col_num: usize = 1;
+    let mut mode_str = "n".to_string();
+    let mut mode_detailed: Option<String> = None;
+    let mut operator = None;
+    let mut changedtick = 0;
+    let mut modified = false;
+    let mut window_count = 1;
+    let mut current_window = 1;
+    let mut undo_seq = 0;
+
+    // V は今のところ読み方を変えない（新しい版の知らない項目は無視する）
+    for part in line.trim().split(',') {
+        if let Some(value) = part.strip_prefix("LINE:") {
+            line_num = value.parse().unwrap_or(1);
+        } else if let Some(value) = part.strip_prefix("COL:") {
+            col_num = value.parse().unwrap_or(1);
+        } else if let Some(value) = part.strip_prefix("MODE:") {
+            mode_str = decode_mode_token(value);
+        } else if let Some(value) = part.strip_prefix("DETAILED:") {
+            mode_detailed = Some(decode_mode_token(value));
+        } else if let Some(value) = part.strip_prefix("OP:") {
+            operator = Some(value.to_string()).filter(|op| !op.is_empty());
+        } else if let Some(value) = part.strip_prefix("TICK:") {
+            changedtick = value.parse().unwrap_or(0);
+        } else if let Some(value) = part.strip_prefix("MOD:") {
+            modified = value == "1";
+        } else if let Some(value) = part.strip_prefix("WINS:") {
+            window_count = value.parse().unwrap_or(1);
+        } else if let Some(value) = part.strip_prefix("WIN:") {
+            current_window = value.parse().unwrap_or(1);
+        } else if let Some(value) = part.strip_prefix("UNDO:") {
+            undo_seq = value.parse().unwrap_or(0);
+        }
+    }
+
+    // DETAILED が無い古い形式では mode() の値をそのまま使う
+    let mode_detailed = mode_detailed.unwrap_or_else(|| mode_str.clone());
+    // operator の情報はオペレーター待機中のみ意味を持つ
+    let operator = operator.filter(|_| mode_detailed.starts_with("no"));
+    VimState {
+        mode: VimMode::from_vim_mode(&mode_str, &mode_detailed, operator.clone()),
+        cursor_line: line_num.saturating_sub(1), // Vimは1ベース、内部は0ベース
+        cursor_col: col_num.saturating_sub(1),
+        operator,
+        buffer_content: vec!["".to_string()],
+        changedtick,
+        modified,
+        window_count,
+        current_window,
+        undo_seq,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status_line_modes() {
+        let mode_of = |line: &str| parse(line).mode;
+        assert_eq!(mode_of("LINE:1,COL:1,MODE:n,DETAILED:n"), VimMode::Normal);
+        assert_eq!(mode_of("LINE:1,COL:1,MODE:i,DETAILED:i"), VimMode::Insert);
+        assert_eq!(mode_of("LINE:1,COL:1,MODE:v,DETAILED:v"), VimMode::Visual);
+        assert_eq!(mode_of("LINE:1,COL:1,MODE:V,DETAILED:V"), VimMode::VisualLine);
+        assert_eq!(
+            mode_of("LINE:1,COL:1,MODE:CTRL_V,DETAILED:CTRL_V"),
+            VimMode::VisualBlock
+        );
+        assert_eq!(
+            mode_of("LINE:1,COL:1,MODE:n,DETAILED:no,OP:d"),
+            VimMode::OperatorPending("d".to_string())
+        );
+        assert_eq!(
+            mode_of("LINE:1,COL:1,MODE:n,DETAILED:noCTRL_V,OP:y"),
+            VimMode::OperatorPending("y".to_string())
+        );
+        assert_eq!(mode_of("LINE:1,COL:1,MODE:c,DETAILED:c"), VimMode::Command);
+        assert_eq!(mode_of("LINE:1,COL:1,MODE:R,DETAILED:Rv"), VimMode::Replace);
+        assert_eq!(mode_of("LINE:1,COL:1,MODE:CTRL_S,DETAILED:CTRL_S"), VimMode::Select);
+        // 制御文字がそのまま残っていても判定できる
+        assert_eq!(mode_of("LINE:1,COL:1,MODE:\u{16}"), VimMode::VisualBlock);
+    }
+
+    #[test]
+    fn test_parse_status_line_fields() {
+        let state = parse("LINE:3,COL:7,MODE:n,DETAILED:n,OP:d,TICK:12,MOD:1\n");
+        assert_eq!((state.cursor_line, state.cursor_col), (2, 6));
+        assert_eq!(state.mode, VimMode::Normal);
+        assert_eq!(state.operator, None); // オペレーター待機中以外は無視
+        assert_eq!(state.changedtick, 12);
+        assert!(state.modified);
+        // 版1の行にはウィンドウの項目が無い
+        assert_eq!((state.window_count, state.current_window), (1, 1));
+        let state = parse("V:2,LINE:1,COL:1,MODE:n,WINS:3,WIN:2");
+        assert_eq!((state.window_count, state.current_window), (3, 2));
+        assert_eq!(state.undo_seq, 0);
+        assert_eq!(parse("V:3,LINE:1,COL:1,MODE:n,UNDO:7").undo_seq, 7);
+
+        // 従来のゲームモードの形式（DETAILED なし）
+        let state = parse("LINE:2,COL:5,MODE:i");
+        assert_eq!((state.cursor_line, state.cursor_col), (1, 4));
+        assert_eq!(state.mode, VimMode::Insert);
+
+        // 新しい版の知らない項目は無視する
+        let state = parse("V:9,LINE:4,COL:2,MODE:v,DETAILED:v,WIN:3");
+        assert_eq!((state.cursor_line, state.cursor_col), (3, 1));
+        assert_eq!(state.mode, VimMode::Visual);
+
+        // 壊れた行は既定値
+        let state = parse("garbage");
+        assert_eq!((state.cursor_line, state.cursor_col), (0, 0));
+        assert_eq!(state.mode, VimMode::Normal);
+    }
+
+    #[test]
+    fn test_window_count_expression_is_shared() {
+        // 状態ファイルの関数と RPC の問い合わせで、同じく指示ウィンドウを除いて数える
+        let expr = window_count_expr("winnr('$')");
+        assert_eq!(
+            expr,
+            "len(filter(range(1, winnr('$')), '!getbufvar(winbufnr(v:val), \"tutorial_instructions\", 0)'))"
+        );
+        assert!(vim_functions().contains(&format!("return {}", window_count_expr("a:last"))));
+    }
+
+    #[test]
+    fn test_emit_round_trips_every_mode_and_field() {
+        let modes = [
+            VimMode::Normal,
+            VimMode::Insert,
+            VimMode::Visual,
+            VimMode::VisualLine,
+            VimMode::VisualBlock,
+            VimMode::OperatorPending("d".to_string()),
+            VimMode::OperatorPending("gU".to_string()),
+            VimMode::Command,
+            VimMode::Replace,
+            VimMode::Select,
+            VimMode::Terminal,
+        ];
+        for (i, mode) in modes.into_iter().enumerate() {
+            let operator = match &mode {
+                VimMode::OperatorPending(operator) => Some(operator.clone()),
+                _ => None,
+            };
+            let state = VimState {
+                mode,
+                cursor_line: i,
+                cursor_col: i * 3,
+                operator,
+                buffer_content: vec!["".to_string()],
+                changedtick: 40 + i as u64,
+                modified: i % 2 == 1,
+                window_count: 1 + i % 3,
+                current_window: 1 + i % 2,
+                undo_seq: i as u64,
+                ..Default::default()
+            };
+            let line = emit(&state);
+            assert!(is_status_line(&line));
+            // 制御文字はトークンにして書く
+            assert!(!line.contains('\u{16}') && !line.contains('\u{13}'), "{}", line);
+            assert_eq!(parse(&line), state, "{}", line);
+        }
+    }
+}
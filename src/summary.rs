@@ -0,0 +1,545 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use unicode_width::UnicodeWidthStr;
+
+use crate::keystrokes::{self, Grade, KeystrokeScore};
+use crate::layout::DisplayPolicy;
+use crate::progress::{LearningMode, PausedExercise};
+use crate::theme::say;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExerciseStatus {
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "incomplete")]
+    Incomplete,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "not_started")]
+    NotStarted, // 途中で章を抜けたため未実施
+    #[serde(rename = "timed_out")]
+    TimedOut, // チャレンジモードの制限時間切れ
+}
+
+impl ExerciseStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExerciseStatus::Completed => "✅ 完了",
+            ExerciseStatus::Incomplete => "⏸️ 未完了",
+            ExerciseStatus::Failed => "❌ エラー",
+            ExerciseStatus::NotStarted => "⬜ 未実施",
+            ExerciseStatus::TimedOut => "⌛ 時間切れ",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExerciseSummary {
+    pub title: String,
+    pub status: ExerciseStatus,
+    pub duration_secs: f64,
+    pub hints_used: usize,
+    pub goals_skipped: usize,
+    // 何回目の取り組みで終えたか（:TutorialRetry でやり直すと増える。古い記録では1）
+    #[serde(default = "first_attempt")]
+    pub attempts: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keystroke_scores: Vec<KeystrokeScore>,
+    // 記録した時点の練習の内容のハッシュ（ContinuousExercise::content_hash。従来モードと古い記録では無い）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+}
+
+fn first_attempt() -> usize {
+    1
+}
+
+impl ExerciseSummary {
+    // 章を途中から始めたり抜けたりして取り組まなかった練習の記録
+    pub fn not_started(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            status: ExerciseStatus::NotStarted,
+            duration_secs: 0.0,
+            hints_used: 0,
+            goals_skipped: 0,
+            attempts: 1,
+            keystroke_scores: Vec::new(),
+            content_hash: None,
+        }
+    }
+
+    // 表の「状態」の欄（やり直して完了した練習は何回目で完了したかを出す）
+    pub fn status_label(&self) -> String {
+        match self.status {
+            ExerciseStatus::Completed if self.attempts > 1 => format!("✅ {}回目で完了", self.attempts),
+            status => status.label().to_string(),
+        }
+    }
+
+    // いまの章データの練習（題名と内容のハッシュ）の記録か。ハッシュの無い記録は題名だけで比べる
+    pub fn refers_to(&self, title: &str, content_hash: &str) -> bool {
+        self.title == title && self.content_hash.as_deref().is_none_or(|hash| hash == content_hash)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChapterSummary {
+    pub mode: LearningMode,
+    pub chapter_number: u8,
+    pub chapter_title: String,
+    pub finished_at: DateTime<Utc>,
+    pub exercises: Vec<ExerciseSummary>,
+}
+
+impl ChapterSummary {
+    pub fn is_complete(&self) -> bool {
+        !self.exercises.is_empty()
+            && self
+                .exercises
+                .iter()
+                .all(|e| e.status == ExerciseStatus::Completed)
+    }
+
+    // 最適キー数が設定された目標全体でのキー効率（対象が無ければ None）
+    pub fn keystroke_efficiency(&self) -> Option<f64> {
+        keystrokes::overall_efficiency(self.exercises.iter().flat_map(|e| &e.keystroke_scores))
+    }
+
+    pub fn grade(&self) -> Option<Grade> {
+        self.keystroke_efficiency().map(Grade::from_efficiency)
+    }
+}
+
+pub fn format_duration(secs: f64) -> String {
+    let total = secs.round() as u64;
+    if total >= 60 {
+        format!("{}分{:02}秒", total / 60, total % 60)
+    } else {
+        format!("{}秒", total)
+    }
+}
+
+// 全角文字を考慮して右側を空白で埋める
+pub fn pad(text: &str, width: usize) -> String {
+    let current = UnicodeWidthStr::width(text);
+    format!("{}{}", text, " ".repeat(width.saturating_sub(current)))
+}
+
+pub fn render_summary_table(summary: &ChapterSummary) -> Vec<String> {
+    render_exercise_table(&summary.exercises)
+}
+
+// 練習ごとの結果を合計行付きの表にする（章以外のドリルなどでも使用）
+pub fn render_exercise_table(exercises: &[ExerciseSummary]) -> Vec<String> {
+    let headers = ["#", "練習", "状態", "時間", "ヒント", "スキップ"];
+    let completed = exercises
+        .iter()
+        .filter(|e| e.status == ExerciseStatus::Completed)
+        .count();
+    let mut rows: Vec<[String; 6]> = exercises
+        .iter()
+        .enumerate()
+        .map(|(i, e)| {
+            [
+                (i + 1).to_string(),
+                e.title.clone(),
+                e.status_label(),
+                format_duration(e.duration_secs),
+                e.hints_used.to_string(),
+                e.goals_skipped.to_string(),
+            ]
+        })
+        .collect();
+    rows.push([
+        String::new(),
+        "合計".to_string(),
+        format!("{}/{}", completed, exercises.len()),
+        format_duration(exercises.iter().map(|e| e.duration_secs).sum()),
+        exercises.iter().map(|e| e.hints_used).sum::<usize>().to_string(),
+        exercises.iter().map(|e| e.goals_skipped).sum::<usize>().to_string(),
+    ]);
+
+    let mut widths = headers.map(UnicodeWidthStr::width);
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row.iter()) {
+            *w = (*w).max(UnicodeWidthStr::width(cell.as_str()));
+        }
+    }
+
+    let format_row = |cells: &[String]| -> String {
+        cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, &w)| pad(cell, w))
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_string()
+    };
+    let separator = widths
+        .iter()
+        .map(|&w| "-".repeat(w))
+        .collect::<Vec<_>>()
+        .join("-+-");
+
+    let header_cells: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    let mut lines = vec![format_row(&header_cells), separator.clone()];
+    let total_row = rows.pop();
+    lines.extend(rows.iter().map(|row| format_row(row)));
+    lines.push(separator);
+    if let Some(total_row) = total_row {
+        lines.push(format_row(&total_row));
+    }
+    lines
+}
+
+// 目標ごとのキー効率と総合評価（採点対象が無ければ空）
+pub fn render_keystroke_lines(exercises: &[ExerciseSummary]) -> Vec<String> {
+    let scores: Vec<&KeystrokeScore> = exercises.iter().flat_map(|e| &e.keystroke_scores).collect();
+    let Some(efficiency) = keystrokes::overall_efficiency(scores.iter().copied()) else {
+        return Vec::new();
+    };
+
+    let mut lines = vec!["⌨️ キー効率（最適キー数 / 実際のキー数）".to_string()];
+    for score in &scores {
+        lines.push(format!(
+            "  {}: {}/{}キー ({:.0}%)",
+            score.label,
+            score.optimal,
+            score.actual,
+            score.efficiency() * 100.0
+        ));
+    }
+    lines.push(format!(
+        "  評価: {} ({:.0}%)",
+        Grade::from_efficiency(efficiency).label(),
+        efficiency * 100.0
+    ));
+    lines
+}
+
+// 章の結果を表示する（続けて prompt_next_action で次の行動を選ぶ）。
+// Zen では時間・スコアの表を出さない（記録には残るため stats で見られる）
+pub fn print_chapter_summary(summary: &ChapterSummary, next_chapter: Option<(u8, &str)>, display: DisplayPolicy) {
+    say!();
+    say!(
+        "📊 === 第{}章「{}」の結果 ===",
+        summary.chapter_number, summary.chapter_title
+    );
+    if display.shows_numbers() {
+        for line in render_summary_table(summary) {
+            say!("{}", line);
+        }
+        say!();
+        let keystroke_lines = render_keystroke_lines(&summary.exercises);
+        if !keystroke_lines.is_empty() {
+            for line in keystroke_lines {
+                say!("{}", line);
+            }
+            say!();
+        }
+    }
+
+    if summary.is_complete() {
+        say!("🎉 全ての練習を完了しました！");
+    } else {
+        say!("💪 もう少しです！未完了の練習に再挑戦してみましょう。");
+    }
+    match next_chapter {
+        Some((number, title)) => say!("👉 次のおすすめ: 第{}章「{}」", number, title),
+        None => say!("🏆 全ての章を完了しました！復習で定着させましょう。"),
+    }
+}
+
+// 章の結果表示の後に選べる次の行動
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NextAction {
+    Advance(u8), // 指定した章に進む
+    Drill,       // 復習ドリルに進む
+    Menu,        // 章選択メニューに戻る
+    Quit,        // 学習を終了する
+}
+
+// 「次の章に進みますか？」への答え（空の入力は Y、解釈できなければ None）
+pub fn parse_advance_answer(input: &str, next_chapter: u8) -> Option<NextAction> {
+    match input.trim().to_lowercase().as_str() {
+        "" | "y" | "yes" => Some(NextAction::Advance(next_chapter)),
+        "n" | "no" => Some(NextAction::Quit),
+        "m" | "menu" | "メニュー" => Some(NextAction::Menu),
+        _ => None,
+    }
+}
+
+// 「復習ドリルに進みますか？」への答え（既定は N でメニューに戻る）
+pub fn parse_drill_answer(input: &str) -> NextAction {
+    match input.trim().to_lowercase().as_str() {
+        "y" | "yes" => NextAction::Drill,
+        _ => NextAction::Menu,
+    }
+}
+
+// 「一時停止した練習から再開しますか？」への答え（既定は Y）
+pub fn parse_resume_answer(input: &str) -> bool {
+    !matches!(input.trim().to_lowercase().as_str(), "n" | "no")
+}
+
+// 一時停止したまま終了した練習を続きから始めるか確認する（入力が無ければ最初から）
+pub fn prompt_resume_paused(paused: &PausedExercise) -> bool {
+    let prompt = format!(
+        "\n⏸️ 前回一時停止した練習{}「{}」（目標 {}/{} 達成）から再開しますか？ [Y/n]: ",
+        paused.exercise_index + 1,
+        paused.exercise_title,
+        paused.completed_goals.iter().filter(|&&done| done).count(),
+        paused.completed_goals.len()
+    );
+    read_answer(&prompt).is_some_and(|input| parse_resume_answer(&input))
+}
+
+fn read_answer(prompt: &str) -> Option<String> {
+    print!("{}", crate::theme::current().line(prompt));
+    let _ = io::stdout().flush();
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input),
+    }
+}
+
+// 章を完了したら次の章（最後の章ならドリルかメニュー）を選ばせる。
+// auto_advance では確認せずに次の章へ進む
+pub fn prompt_next_action(
+    summary: &ChapterSummary,
+    next_chapter: Option<u8>,
+    auto_advance: bool,
+    offer_drill: bool,
+) -> NextAction {
+    if !summary.is_complete() {
+        let _ = read_answer("\nEnterキーでメニューに戻ります...");
+        return NextAction::Menu;
+    }
+
+    match next_chapter {
+        Some(number) if auto_advance => {
+            say!("\n⏩ 第{}章に進みます", number);
+            NextAction::Advance(number)
+        }
+        Some(number) => loop {
+            let prompt = format!("\n第{}章に進みますか？ [Y/n/メニュー]: ", number);
+            let Some(input) = read_answer(&prompt) else {
+                return NextAction::Menu;
+            };
+            match parse_advance_answer(&input, number) {
+                Some(action) => return action,
+                None => say!("❌ Y、n、メニュー（m）のいずれかを入力してください。"),
+            }
+        },
+        None if offer_drill && !auto_advance => read_answer("\n復習ドリルに進みますか？ [y/N]: ")
+            .map_or(NextAction::Menu, |input| parse_drill_answer(&input)),
+        None => {
+            let _ = read_answer("\nEnterキーでメニューに戻ります...");
+            NextAction::Menu
+        }
+    }
+}
+
+// 自己ベストとの比較メッセージ（更新していなければ None）
+pub fn personal_best_message(previous_best: Option<f64>, duration_secs: f64) -> Option<String> {
+    match previous_best {
+        None => Some(format!("🏅 初クリア! 自己ベスト: {}", format_duration(duration_secs))),
+        Some(best) if duration_secs < best => Some(format!(
+            "🏅 自己ベスト更新! {} → {}",
+            format_duration(best),
+            format_duration(duration_secs)
+        )),
+        Some(_) => None,
+    }
+}
+
+// チャレンジの結果を表示する
+pub fn print_challenge_summary(
+    record: &ExerciseSummary,
+    time_limit_secs: u64,
+    previous_best: Option<f64>,
+) {
+    say!();
+    say!("📊 === チャレンジ結果 ===");
+    for line in render_exercise_table(std::slice::from_ref(record)) {
+        say!("{}", line);
+    }
+    say!();
+    for line in render_keystroke_lines(std::slice::from_ref(record)) {
+        say!("{}", line);
+    }
+
+    match record.status {
+        ExerciseStatus::Completed => {
+            say!(
+                "⏱️ クリア! {} / 制限時間 {}",
+                format_duration(record.duration_secs),
+                format_duration(time_limit_secs as f64)
+            );
+            match personal_best_message(previous_best, record.duration_secs) {
+                Some(message) => say!("{}", message),
+                None => {
+                    if let Some(best) = previous_best {
+                        say!("🎯 自己ベスト: {}", format_duration(best));
+                    }
+                }
+            }
+        }
+        ExerciseStatus::TimedOut => {
+            say!("⌛ 時間切れです。もう一度挑戦してみましょう！");
+            if let Some(best) = previous_best {
+                say!("🎯 自己ベスト: {}", format_duration(best));
+            }
+        }
+        _ => say!("⏸️ チャレンジを中断しました。"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_summary() -> ChapterSummary {
+        ChapterSummary {
+            mode: LearningMode::Continuous,
+            chapter_number: 1,
+            chapter_title: "基本移動".to_string(),
+            finished_at: Utc::now(),
+            exercises: vec![
+                ExerciseSummary {
+                    title: "hjkl移動の基本".to_string(),
+                    status: ExerciseStatus::Completed,
+                    duration_secs: 75.4,
+                    hints_used: 1,
+                    goals_skipped: 0,
+                    attempts: 1,
+                    keystroke_scores: Vec::new(),
+                    content_hash: None,
+                },
+                ExerciseSummary {
+                    title: "free".to_string(),
+                    status: ExerciseStatus::Incomplete,
+                    duration_secs: 9.0,
+                    hints_used: 0,
+                    goals_skipped: 2,
+                    attempts: 1,
+                    keystroke_scores: Vec::new(),
+                    content_hash: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(9.0), "9秒");
+        assert_eq!(format_duration(75.4), "1分15秒");
+    }
+
+    #[test]
+    fn test_summary_table_is_aligned() {
+        let summary = create_test_summary();
+        let lines = render_summary_table(&summary);
+
+        // ヘッダー + 区切り + 2行 + 区切り + 合計
+        assert_eq!(lines.len(), 6);
+        assert!(lines[5].contains("合計"));
+        assert!(lines[5].contains("1/2"));
+        assert!(lines[5].contains("1分24秒"));
+
+        // 区切り文字の表示位置が全行で揃っている
+        let column_of_first_bar = |line: &str| {
+            let idx = line.find(" | ").unwrap();
+            UnicodeWidthStr::width(&line[..idx])
+        };
+        let expected = column_of_first_bar(&lines[0]);
+        for line in [&lines[2], &lines[3], &lines[5]] {
+            assert_eq!(column_of_first_bar(line), expected);
+        }
+        let second_bar = |line: &str| {
+            let rest_start = line.find(" | ").unwrap() + 3;
+            let idx = rest_start + line[rest_start..].find(" | ").unwrap();
+            UnicodeWidthStr::width(&line[..idx])
+        };
+        assert_eq!(second_bar(&lines[2]), second_bar(&lines[3]));
+    }
+
+    #[test]
+    fn test_attempts_are_shown_for_retried_exercises() -> anyhow::Result<()> {
+        let mut summary = create_test_summary();
+        assert_eq!(summary.exercises[0].status_label(), "✅ 完了");
+        summary.exercises[0].attempts = 2;
+        assert_eq!(summary.exercises[0].status_label(), "✅ 2回目で完了");
+        assert!(render_summary_table(&summary)[2].contains("✅ 2回目で完了"));
+
+        // 回数の無い古い記録は1回目とみなす
+        let mut value = serde_json::to_value(&summary.exercises[1])?;
+        value.as_object_mut().unwrap().remove("attempts");
+        assert_eq!(serde_json::from_value::<ExerciseSummary>(value)?.attempts, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_personal_best_message() {
+        assert_eq!(
+            personal_best_message(Some(42.0), 35.0).as_deref(),
+            Some("🏅 自己ベスト更新! 42秒 → 35秒")
+        );
+        assert_eq!(personal_best_message(Some(30.0), 35.0), None);
+        assert!(personal_best_message(None, 35.0).unwrap().contains("初クリア"));
+    }
+
+    #[test]
+    fn test_chapter_grade_from_keystrokes() {
+        let mut summary = create_test_summary();
+        assert_eq!(summary.grade(), None);
+        assert!(render_keystroke_lines(&summary.exercises).is_empty());
+
+        summary.exercises[0].keystroke_scores = vec![
+            KeystrokeScore {
+                label: "'W' に移動".to_string(),
+                optimal: 2,
+                actual: 2,
+            },
+            KeystrokeScore {
+                label: "行頭に移動".to_string(),
+                optimal: 2,
+                actual: 6,
+            },
+        ];
+        assert_eq!(summary.grade(), Some(Grade::B));
+        let lines = render_keystroke_lines(&summary.exercises);
+        assert_eq!(lines.len(), 4);
+        assert!(lines[2].contains("2/6キー (33%)"));
+        assert_eq!(lines[3], "  評価: B (50%)");
+    }
+
+    #[test]
+    fn test_next_action_answers() {
+        assert_eq!(parse_advance_answer("\n", 2), Some(NextAction::Advance(2)));
+        assert_eq!(parse_advance_answer("Y", 2), Some(NextAction::Advance(2)));
+        assert_eq!(parse_advance_answer("n", 2), Some(NextAction::Quit));
+        assert_eq!(parse_advance_answer("メニュー\n", 2), Some(NextAction::Menu));
+        assert_eq!(parse_advance_answer("m", 2), Some(NextAction::Menu));
+        assert_eq!(parse_advance_answer("3", 2), None);
+
+        assert_eq!(parse_drill_answer("y"), NextAction::Drill);
+        assert_eq!(parse_drill_answer(""), NextAction::Menu);
+
+        assert!(parse_resume_answer("\n"));
+        assert!(parse_resume_answer("y"));
+        assert!(!parse_resume_answer("N"));
+    }
+
+    #[test]
+    fn test_chapter_completion() {
+        let mut summary = create_test_summary();
+        assert!(!summary.is_complete());
+        summary.exercises[1].status = ExerciseStatus::Completed;
+        assert!(summary.is_complete());
+    }
+}
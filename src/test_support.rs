@@ -0,0 +1,14 @@
+// テストで共通に使う補助
+use std::fs;
+use std::path::Path;
+
+// 期待する出力は src/snapshots に置く（UPDATE_SNAPSHOTS=1 で書き直す）
+pub fn assert_snapshot(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/snapshots").join(name);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_default();
+    assert_eq!(actual, expected, "スナップショット {} と一致しません", name);
+}
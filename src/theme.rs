@@ -0,0 +1,183 @@
+// 表示の見た目（絵文字を使う通常の表示と、スクリーンリーダー向けの文字だけの表示）。
+// 表示する文字列はここを通して描き、絵文字の言い換えや罫線の除去は呼び出し側で分岐させない
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    #[default]
+    Emoji,
+    Accessible, // --accessible / 設定の accessible
+}
+
+// 読み上げ向けの表示で、続けて届く指示ペインの更新をまとめる間隔
+// （カーソル位置やカウントダウンのたびに読み上げが止められないようにする）
+const ACCESSIBLE_PANE_DEBOUNCE: Duration = Duration::from_secs(2);
+
+// 絵文字と、読み上げ向けの表示での言い換え（空の言い換えは絵文字ごと消す）
+const LABELS: &[(&str, &str)] = &[
+    ("🎯", "GOAL:"),
+    ("✅", "DONE:"),
+    ("✓", "OK:"),
+    ("✗", "NG:"),
+    ("🎉", "DONE:"),
+    ("🏁", "FINISHED:"),
+    ("▶️", "NOW:"),
+    ("⬜", "TODO:"),
+    ("💡", "HINT:"),
+    ("🔍", "HINT:"),
+    ("🔑", "ANSWER:"),
+    ("🎬", "DEMO:"),
+    ("⚠️", "WARNING:"),
+    ("❌", "ERROR:"),
+    ("⏱️", "TIME:"),
+    ("⏳", "TIME:"),
+    ("⌛", "TIMEOUT:"),
+    ("⏸️", "PAUSED:"),
+    ("💤", "IDLE:"),
+    ("🔌", "CONNECTION:"),
+    ("📍", "POSITION:"),
+    ("🧭", "NUDGE:"),
+    ("💬", "TIP:"),
+    ("📝", "NOTE:"),
+    ("📖", "INTRO:"),
+    ("📄", "FILE:"),
+    ("📊", "PROGRESS:"),
+    ("📋", "LIST:"),
+    ("🔒", "LOCKED:"),
+    ("📶", "LEVEL:"),
+    ("🏷️", "TAGS:"),
+    ("⌨️", "KEYS:"),
+    ("🔎", "FILTER:"),
+    ("📏", "RULE:"),
+    ("🚫", "NG:"),
+    ("📦", "PACK:"),
+];
+
+static ACCESSIBLE: AtomicBool = AtomicBool::new(false);
+
+pub fn set(theme: Theme) {
+    ACCESSIBLE.store(theme == Theme::Accessible, Ordering::Relaxed);
+}
+
+// 端末に直接書く表示で使うテーマ（練習のセッションは設定から自分のテーマを持つ）
+pub fn current() -> Theme {
+    if ACCESSIBLE.load(Ordering::Relaxed) {
+        Theme::Accessible
+    } else {
+        Theme::Emoji
+    }
+}
+
+// 絵文字・記号・罫線として読み上げ向けの表示から除く文字
+pub fn is_pictograph(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF // 絵文字
+            | 0x2190..=0x21FF // 矢印
+            | 0x2300..=0x23FF // ⌨ ⏱ ⏳ など
+            | 0x2500..=0x25FF // 罫線・ブロック・図形（進捗バーや ▶）
+            | 0x2600..=0x27BF // ⚠ ✅ ❌ など
+            | 0x2B00..=0x2BFF // ⬜ ⭐ など
+            | 0xFE0F | 0x200D | 0x20E3 // 異体字セレクタ・結合子
+    )
+}
+
+impl Theme {
+    pub fn from_accessible(accessible: bool) -> Self {
+        if accessible { Theme::Accessible } else { Theme::Emoji }
+    }
+
+    // 1行（改行を含んでもよい）を描く
+    pub fn line(&self, text: &str) -> String {
+        match self {
+            Theme::Emoji => text.to_string(),
+            Theme::Accessible => text.split('\n').map(plain_line).collect::<Vec<_>>().join("\n"),
+        }
+    }
+
+    pub fn lines(&self, lines: &[String]) -> Vec<String> {
+        lines.iter().map(|line| self.line(line)).collect()
+    }
+
+    pub fn pane_debounce(&self) -> Duration {
+        match self {
+            Theme::Emoji => Duration::ZERO,
+            Theme::Accessible => ACCESSIBLE_PANE_DEBOUNCE,
+        }
+    }
+
+    // 目標を達成するたびに、次の目標を1行で知らせるか（通常の表示は設定の bell に従う）
+    pub fn announces_goals(&self) -> bool {
+        *self == Theme::Accessible
+    }
+}
+
+// 絵文字を言い換え、"=== 見出し ===" の飾りを外す（字下げや文中の空白はそのまま残す）
+fn plain_line(line: &str) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        // 異体字セレクタの有無にかかわらず言い換える
+        let labeled = LABELS.iter().find_map(|(emoji, label)| {
+            rest.strip_prefix(emoji.trim_end_matches('\u{fe0f}')).map(|after| (after, label))
+        });
+        if let Some((after, label)) = labeled {
+            out.push_str(label);
+            rest = after.strip_prefix('\u{fe0f}').unwrap_or(after);
+            continue;
+        }
+        rest = &rest[c.len_utf8()..];
+        if !is_pictograph(c) {
+            out.push(c);
+            continue;
+        }
+        // 消した絵文字の後の空白は、行頭か空白の直後なら消す
+        if out.is_empty() || out.ends_with(' ') {
+            rest = rest.strip_prefix(' ').unwrap_or(rest);
+        }
+    }
+    let indent = out.len() - out.trim_start().len();
+    let body = out.trim();
+    // 言い換えが飾りの前に来る "GOAL: === 見出し ===" も外す
+    let body = match body.strip_suffix("===").and_then(|inner| inner.find("===").map(|at| (inner, at))) {
+        Some((inner, at)) => format!("{}{}", &inner[..at], inner[at + 3..].trim_start()).trim_end().to_string(),
+        None => body.to_string(),
+    };
+    format!("{}{}", &out[..indent], body)
+}
+
+// 通常の println! と同じ書き方で、現在のテーマで描いて表示する
+#[macro_export]
+macro_rules! say {
+    () => {
+        println!()
+    };
+    ($($arg:tt)*) => {
+        println!("{}", $crate::theme::current().line(&format!($($arg)*)))
+    };
+}
+
+pub use crate::say;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accessible_lines_use_text_labels() {
+        let theme = Theme::Accessible;
+        assert_eq!(theme.line("=== 🎯 移動の練習 ==="), "GOAL: 移動の練習");
+        assert_eq!(theme.line("🎯 === 移動の練習 ==="), "GOAL: 移動の練習");
+        assert_eq!(theme.line("  ✅ 1. 目標1"), "  DONE: 1. 目標1");
+        assert_eq!(theme.line("  ▶️ 2. 目標2"), "  NOW: 2. 目標2");
+        assert_eq!(theme.line("     💡 上に移動します"), "     HINT: 上に移動します");
+        assert_eq!(theme.line("=== ☑️ 目標一覧 ==="), "目標一覧");
+        assert_eq!(theme.line("🚀 起動します\n🎉 完了"), "起動します\nDONE: 完了");
+        assert_eq!(theme.line("⬜ 2. 削除 ← 前回の続き"), "TODO: 2. 削除 前回の続き");
+        assert_eq!(theme.line("進捗 ████░░ 4/6"), "進捗 4/6");
+        // 通常の表示はそのまま
+        assert_eq!(Theme::Emoji.line("=== 🎯 移動 ==="), "=== 🎯 移動 ===");
+        assert!(Theme::Emoji.pane_debounce().is_zero());
+    }
+}
@@ -0,0 +1,234 @@
+// 矢印キー・j/k で選ぶ一覧のメニュー（章選択と練習の選択）。
+// crossterm は WSL で問題が出たため使わず、端末の raw モードは stty で切り替え、描画は ANSI のエスケープで行う。
+// 端末でない場合や stty が使えない場合は None を返し、呼び出し側で従来の番号入力のメニューにする
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+
+use crate::theme::{self, Theme};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Enter,
+    Escape,
+    Char(char),
+}
+
+// 読んだバイト列をキーに分ける（矢印キーは ESC [ A / ESC O A の形で届く）
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut rest = bytes;
+    while let Some((&first, after)) = rest.split_first() {
+        rest = after;
+        let key = match first {
+            0x1b => match rest {
+                [b'[' | b'O', b'A', more @ ..] => {
+                    rest = more;
+                    Key::Up
+                }
+                [b'[' | b'O', b'B', more @ ..] => {
+                    rest = more;
+                    Key::Down
+                }
+                // 他のエスケープの並び（左右の矢印など）は読み捨てる
+                [b'[' | b'O', _, more @ ..] => {
+                    rest = more;
+                    continue;
+                }
+                _ => Key::Escape,
+            },
+            b'\r' | b'\n' => Key::Enter,
+            byte if byte.is_ascii_graphic() || byte == b' ' => Key::Char(byte as char),
+            _ => continue,
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+// 一覧の1項目（details は選択中の項目の下にだけ出す）
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuItem {
+    pub label: String,
+    pub details: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuEvent {
+    Selected(usize),
+    Key(char), // j・k 以外の文字のキー
+    Back,      // Esc
+}
+
+pub struct ListMenu {
+    pub title: String,
+    pub items: Vec<MenuItem>,
+    pub cursor: usize,
+    pub footer: String,
+    pub message: Option<String>, // 選べなかった理由など（次のキーで消す）
+}
+
+impl ListMenu {
+    pub fn new(title: &str, items: Vec<MenuItem>, cursor: usize, footer: &str) -> Self {
+        let cursor = cursor.min(items.len().saturating_sub(1));
+        Self {
+            title: title.to_string(),
+            items,
+            cursor,
+            footer: footer.to_string(),
+            message: None,
+        }
+    }
+
+    // カーソルの移動は一覧の中で完結させ、それ以外のキーを呼び出し側に渡す
+    pub fn handle(&mut self, key: Key) -> Option<MenuEvent> {
+        self.message = None;
+        match key {
+            Key::Up | Key::Char('k') => {
+                self.cursor = self.cursor.saturating_sub(1);
+                None
+            }
+            Key::Down | Key::Char('j') => {
+                self.cursor = (self.cursor + 1).min(self.items.len().saturating_sub(1));
+                None
+            }
+            Key::Enter if self.items.is_empty() => None,
+            Key::Enter => Some(MenuEvent::Selected(self.cursor)),
+            Key::Escape => Some(MenuEvent::Back),
+            Key::Char(c) => Some(MenuEvent::Key(c)),
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("=== {} ===", self.title), String::new()];
+        for (i, item) in self.items.iter().enumerate() {
+            if i == self.cursor {
+                lines.push(format!("▶️ {}", item.label));
+                lines.extend(item.details.iter().map(|detail| format!("     {}", detail)));
+            } else {
+                lines.push(format!("   {}", item.label));
+            }
+        }
+        lines.push(String::new());
+        if let Some(message) = &self.message {
+            lines.push(message.clone());
+        }
+        lines.push(self.footer.clone());
+        lines
+    }
+}
+
+// キーを順に一覧へ渡し、decide が値を返したところで止める（テストではキーを直接渡す）
+pub fn feed<T>(
+    menu: &mut ListMenu,
+    keys: impl IntoIterator<Item = Key>,
+    decide: &mut impl FnMut(&mut ListMenu, MenuEvent) -> Option<T>,
+) -> Option<T> {
+    for key in keys {
+        if let Some(event) = menu.handle(key)
+            && let Some(decided) = decide(menu, event)
+        {
+            return Some(decided);
+        }
+    }
+    None
+}
+
+// 端末を1文字ずつ読む設定にし、終わったら元の設定に戻す
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> Option<Self> {
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "min", "1", "time", "0"])?;
+        Some(Self {
+            saved: saved.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[self.saved.as_str()]);
+        print!("\x1b[?25h");
+        let _ = io::stdout().flush();
+    }
+}
+
+// stty は標準入力の端末に働くため、標準入力を引き継いで実行する
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// 一覧を表示してキーで選ばせる。端末でない・raw モードにできない・読み上げ向けの表示・入力の終わりでは None
+pub fn run<T>(menu: &mut ListMenu, mut decide: impl FnMut(&mut ListMenu, MenuEvent) -> Option<T>) -> Option<T> {
+    // 読み上げ向けの表示では、画面を描き直さない番号入力のメニューの方が読みやすい
+    if theme::current() == Theme::Accessible || !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return None;
+    }
+    let _raw = RawMode::enable()?;
+    let mut stdin = io::stdin().lock();
+    let mut buffer = [0u8; 32];
+    loop {
+        draw(menu);
+        let read = match stdin.read(&mut buffer) {
+            Ok(0) | Err(_) => return None,
+            Ok(read) => read,
+        };
+        if let Some(decided) = feed(menu, parse_keys(&buffer[..read]), &mut decide) {
+            print!("\x1b[2J\x1b[H");
+            let _ = io::stdout().flush();
+            return Some(decided);
+        }
+    }
+}
+
+fn draw(menu: &ListMenu) {
+    let theme = theme::current();
+    // 画面を消してから描き、描いている間はカーソルを隠す
+    print!("\x1b[?25l\x1b[2J\x1b[H");
+    for line in menu.lines() {
+        print!("{}\r\n", theme.line(&line));
+    }
+    let _ = io::stdout().flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys_reads_arrows_and_plain_keys() {
+        assert_eq!(
+            parse_keys(b"\x1b[Aj\x1bOB\r\x1b[Cq\x1b"),
+            vec![Key::Up, Key::Char('j'), Key::Down, Key::Enter, Key::Char('q'), Key::Escape]
+        );
+    }
+
+    #[test]
+    fn test_menu_moves_within_items_and_reports_selection() {
+        let item = |label: &str| MenuItem {
+            label: label.to_string(),
+            details: vec![format!("{} の説明", label)],
+        };
+        let mut menu = ListMenu::new("章選択", vec![item("第1章"), item("第2章")], 5, "q=終了");
+        assert_eq!(menu.cursor, 1);
+        let mut decide = |_: &mut ListMenu, event: MenuEvent| Some(event);
+        assert_eq!(feed(&mut menu, [Key::Down, Key::Enter], &mut decide), Some(MenuEvent::Selected(1)));
+        assert_eq!(feed(&mut menu, [Key::Up, Key::Up, Key::Char('q')], &mut decide), Some(MenuEvent::Key('q')));
+        assert_eq!(menu.cursor, 0);
+        assert_eq!(
+            menu.lines(),
+            vec!["=== 章選択 ===", "", "▶️ 第1章", "     第1章 の説明", "   第2章", "", "q=終了"]
+        );
+    }
+}
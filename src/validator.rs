@@ -0,0 +1,964 @@
+use std::path::Path;
+use std::sync::OnceLock;
+use unicode_width::UnicodeWidthStr;
+
+use crate::content::{self, ContentLoader, LoadReport};
+use crate::continuous_content::{self, ContinuousChapterData, ContinuousContentLoader};
+use crate::continuous_session::{self, ContinuousExercise, FlowType};
+use crate::editor;
+use crate::keystrokes;
+use crate::theme::say;
+use crate::vim_state::{GoalType, TextMatch, VimMode};
+
+// validate サブコマンド: 両方の形式の章を読み込み、起動時と同じ形で読めない章と章データの問題を表示する
+pub fn run_validate(chapters_dir: &Path) -> anyhow::Result<()> {
+    let mut problems = 0;
+    let mut count = |present: bool, loaded: anyhow::Result<LoadReport>| {
+        if !present {
+            return;
+        }
+        match loaded {
+            Ok(report) => {
+                report.print();
+                problems += report.failures().len() + report.issue_count();
+            }
+            // 1章も読めないか、前提条件に誤りがある
+            Err(error) => {
+                crate::output::warning!("❌ {:#}", error);
+                problems += 1;
+            }
+        }
+    };
+    let has_files = |prefix: &str| !content::chapter_file_numbers(chapters_dir, prefix).is_empty();
+    count(has_files("chapter_"), ContentLoader::load_from(chapters_dir).map(|(_, report)| report));
+    count(
+        has_files("continuous_chapter_")
+            || !continuous_content::bundle_files(chapters_dir).is_empty()
+            || !crate::packs::installed(chapters_dir).is_empty(),
+        ContinuousContentLoader::load_from(chapters_dir).map(|(_, report)| report),
+    );
+    if problems > 0 {
+        return Err(anyhow::anyhow!("章データに{}件の問題があります", problems));
+    }
+    say!("✓ {} の章データに問題はありません", chapters_dir.display());
+    Ok(())
+}
+
+// 前提条件と章番号の重複の検証に使う章の情報
+pub struct ChapterRequirement<'a> {
+    pub number: u8,
+    pub requires: &'a [u8],
+    pub file: &'a str, // 読み込み元（章のまとめの中の章は "file（chapters[i]）"）
+}
+
+// 章の前提条件（requires）の検証。存在しない章の参照と循環を、原因のファイル名付きで報告する
+pub fn validate_prerequisites(chapters: &[ChapterRequirement]) -> Vec<String> {
+    let mut issues = Vec::new();
+    let find = |number: u8| chapters.iter().find(|ch| ch.number == number);
+
+    for chapter in chapters {
+        for &required in chapter.requires {
+            if find(required).is_none() {
+                issues.push(format!(
+                    "{}: 第{}章の requires にある第{}章は存在しません",
+                    chapter.file, chapter.number, required
+                ));
+            }
+        }
+    }
+
+    // 深さ優先探索で、探索中の章に戻る辺を循環として報告する
+    #[derive(Clone, Copy, PartialEq)]
+    enum Visit {
+        New,
+        InProgress,
+        Done,
+    }
+    fn visit(
+        index: usize,
+        chapters: &[ChapterRequirement],
+        states: &mut [Visit],
+        path: &mut Vec<usize>,
+        issues: &mut Vec<String>,
+    ) {
+        states[index] = Visit::InProgress;
+        path.push(index);
+        for &required in chapters[index].requires {
+            let Some(next) = chapters.iter().position(|ch| ch.number == required) else {
+                continue;
+            };
+            match states[next] {
+                Visit::New => visit(next, chapters, states, path, issues),
+                Visit::InProgress => {
+                    let start = path.iter().position(|&i| i == next).unwrap_or(0);
+                    let cycle: Vec<String> = path[start..]
+                        .iter()
+                        .chain(std::iter::once(&next))
+                        .map(|&i| format!("第{}章 ({})", chapters[i].number, chapters[i].file))
+                        .collect();
+                    issues.push(format!("前提条件が循環しています: {}", cycle.join(" → ")));
+                }
+                Visit::Done => {}
+            }
+        }
+        path.pop();
+        states[index] = Visit::Done;
+    }
+
+    let mut states = vec![Visit::New; chapters.len()];
+    for index in 0..chapters.len() {
+        if states[index] == Visit::New {
+            visit(index, chapters, &mut states, &mut Vec::new(), &mut issues);
+        }
+    }
+    issues
+}
+
+// 前提条件の誤りは章の選択に関わるため、警告ではなく読み込みのエラーにする
+pub fn check_prerequisites(chapters: &[ChapterRequirement]) -> anyhow::Result<()> {
+    let issues = validate_prerequisites(chapters);
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "章の前提条件（requires）に誤りがあります:\n  - {}",
+            issues.join("\n  - ")
+        ))
+    }
+}
+
+// 同じ番号の章が複数の読み込み元にあると、どちらを使うかを黙って決めずに読み込みのエラーにする
+pub fn check_duplicate_chapters(chapters: &[ChapterRequirement]) -> anyhow::Result<()> {
+    let issues: Vec<String> = chapters
+        .iter()
+        .enumerate()
+        .filter_map(|(index, chapter)| {
+            let first = chapters[..index].iter().find(|other| other.number == chapter.number)?;
+            Some(format!("第{}章が {} と {} の両方にあります", chapter.number, first.file, chapter.file))
+        })
+        .collect();
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("章の番号が重複しています:\n  - {}", issues.join("\n  - ")))
+    }
+}
+
+// intro がこの行数を超えると、練習の前の画面で目標の一覧が押し出される
+const INTRO_MAX_LINES: usize = 15;
+
+// 練習用のウィンドウに収まるとみなす桁数（validate --line-width で変えられる）
+pub const DEFAULT_LINE_WIDTH: usize = 80;
+
+static LINE_WIDTH: OnceLock<usize> = OnceLock::new();
+
+pub fn set_line_width(width: usize) {
+    let _ = LINE_WIDTH.set(width);
+}
+
+fn line_width() -> usize {
+    LINE_WIDTH.get().copied().unwrap_or(DEFAULT_LINE_WIDTH)
+}
+
+/// 練習の検証で見つかった問題。特定の目標についての問題は、その目標の番号（0始まり）を持つ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub goal: Option<usize>,
+    pub message: String,
+}
+
+impl Issue {
+    fn exercise(message: impl Into<String>) -> Self {
+        Self {
+            goal: None,
+            message: message.into(),
+        }
+    }
+
+    fn goal(index: usize, message: String) -> Self {
+        Self {
+            goal: Some(index),
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+fn messages(issues: Vec<Issue>) -> Vec<String> {
+    issues.into_iter().map(|issue| issue.message).collect()
+}
+
+/// 章データの検証（読み込みは止めず、作者向けの警告として表示する）。問題ごとに1行の説明を返す
+pub fn validate_chapter(chapter: &ContinuousChapterData) -> Vec<String> {
+    chapter
+        .continuous_exercises
+        .iter()
+        .enumerate()
+        .flat_map(|(index, exercise)| {
+            exercise_issues(exercise)
+                .into_iter()
+                .map(move |issue| format!("練習{}「{}」: {}", index + 1, exercise.title, issue))
+        })
+        .chain(lesson_issues(chapter))
+        .collect()
+}
+
+// 解説のページの問題（表示する練習が無い before）
+pub fn lesson_issues(chapter: &ContinuousChapterData) -> Vec<String> {
+    let exercise_count = chapter.continuous_exercises.len();
+    chapter
+        .lessons
+        .iter()
+        .filter_map(|page| match page.before {
+            Some(before) if before == 0 || before > exercise_count => Some(format!(
+                "解説「{}」: before: {} の練習がありません（練習は{}個）",
+                page.title, before, exercise_count
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 練習を始められない問題（[`validate_exercise`]）と警告（[`exercise_warnings`]）を合わせたもの
+pub fn exercise_issues(exercise: &ContinuousExercise) -> Vec<Issue> {
+    let mut issues = blocking_issues(exercise);
+    issues.extend(warning_issues(exercise));
+    issues
+}
+
+// 練習は始められるが、作者に直してほしい点
+pub fn exercise_warnings(exercise: &ContinuousExercise) -> Vec<String> {
+    messages(warning_issues(exercise))
+}
+
+fn warning_issues(exercise: &ContinuousExercise) -> Vec<Issue> {
+    let mut warnings = Vec::new();
+    for (index, goal) in exercise.goals.iter().enumerate() {
+        if goal.description_generated {
+            warnings.push(Issue::goal(
+                index,
+                format!(
+                    "目標{}に description が無いため「{}」と表示します",
+                    index + 1,
+                    goal.description
+                ),
+            ));
+        }
+    }
+    // サンプルコードのファイルには拡張子が無いため、言語を決められない
+    for (index, goal) in exercise.goals.iter().enumerate() {
+        let has_extension = goal
+            .file
+            .as_deref()
+            .is_some_and(|file| std::path::Path::new(file).extension().is_some());
+        if goal.goal_type == "syntax_node" && goal.target.get("language").is_none() && !has_extension {
+            warnings.push(Issue::goal(
+                index,
+                format!(
+                    "目標{}は treesitter の言語を決められません（target に language を書くか、拡張子のある file を指定してください）",
+                    index + 1
+                ),
+            ));
+        }
+    }
+    warnings.extend(normalize_warnings(exercise));
+    warnings.extend(long_line_warnings(exercise, line_width()));
+    if let Some(intro) = &exercise.intro
+        && intro.lines().count() > INTRO_MAX_LINES
+    {
+        warnings.push(Issue::exercise(format!(
+            "intro が{}行あります（{}行以内にすると目標の一覧と一緒に読めます）",
+            intro.lines().count(),
+            INTRO_MAX_LINES
+        )));
+    }
+    warnings
+}
+
+// 期待する行が半角の文字だけのテキストの目標で、normalize を書いていないもの。
+// IME で入力した全角の文字（「（」や全角の空白）や飾りの引用符が混ざると、理由が分からないまま達成できなくなる。
+// 区別させたい練習は normalize: [] と書けば警告しない
+fn normalize_warnings(exercise: &ContinuousExercise) -> Vec<Issue> {
+    let mut warnings = Vec::new();
+    for (index, goal) in exercise.goals.iter().enumerate() {
+        if !matches!(goal.goal_type.as_str(), "text" | "text_block") || goal.target.get("normalize").is_some() {
+            continue;
+        }
+        let Ok(goal_type) = goal.to_goal().map(|goal| goal.goal_type) else {
+            continue;
+        };
+        let expected = match &goal_type {
+            GoalType::TextContent {
+                match_mode: TextMatch::Regex,
+                ..
+            } => continue,
+            GoalType::TextContent { expected, .. } => std::slice::from_ref(expected),
+            GoalType::TextBlock { expected, .. } => expected.as_slice(),
+            _ => continue,
+        };
+        if expected.iter().all(|line| line.is_ascii()) && expected.iter().any(|line| !line.trim().is_empty()) {
+            warnings.push(Issue::goal(
+                index,
+                format!(
+                    "目標{}の expected は半角の文字だけです。IME で入力した全角の文字や飾りの引用符でも達成できるよう、\
+                     target に normalize: [\"width\", \"quotes\"] を書くことを勧めます（区別させたい場合は normalize: [] と書きます）",
+                    index + 1
+                ),
+            ));
+        }
+    }
+    warnings
+}
+
+// 位置の目標が width 桁より右にある長い行。練習では折り返さずに横へスクロールするため、
+// 学習者の画面では行の途中から先が隠れていて、目標の列が見つけにくい
+fn long_line_warnings(exercise: &ContinuousExercise, width: usize) -> Vec<Issue> {
+    let mut resolved = exercise.clone();
+    resolved.resolve_anchors();
+    let mut warnings = Vec::new();
+    for (index, goal) in resolved.goals.iter().enumerate() {
+        if goal.goal_type != "position" {
+            continue;
+        }
+        let Some([line, col]) = goal.target.as_array().and_then(|target| match target.as_slice() {
+            [line, col] => Some([line.as_u64()? as usize, col.as_u64()? as usize]),
+            _ => None,
+        }) else {
+            continue;
+        };
+        let Some(text) = resolved.file_lines(goal.file.as_deref()).and_then(|lines| lines.get(line)) else {
+            continue;
+        };
+        let before: String = text.chars().take(col).collect();
+        if UnicodeWidthStr::width(text.as_str()) > width && UnicodeWidthStr::width(before.as_str()) >= width {
+            warnings.push(Issue::goal(
+                index,
+                format!(
+                    "目標{}の位置（{}行{}列）は{}桁より右にあります（長い行は折り返さずに横へスクロールするため、\
+                     学習者の画面では見つけにくくなります。行を短くするか、position_at で目印の文字を指定してください）",
+                    index + 1,
+                    line + 1,
+                    col + 1,
+                    width
+                ),
+            ));
+        }
+    }
+    warnings
+}
+
+pub fn validate_exercise(exercise: &ContinuousExercise) -> Vec<String> {
+    messages(blocking_issues(exercise))
+}
+
+fn blocking_issues(exercise: &ContinuousExercise) -> Vec<Issue> {
+    // 文字列で書いた位置の目標は、サンプルコードの中の位置にしてから確かめる
+    let mut resolved = exercise.clone();
+    let mut issues: Vec<Issue> = resolved
+        .resolve_anchor_goals()
+        .into_iter()
+        .map(|(index, issue)| {
+            Issue::goal(index, format!("目標{}「{}」: {}", index + 1, exercise.goals[index].description, issue))
+        })
+        .collect();
+    let exercise = &resolved;
+
+    if let Some([line, col]) = exercise.cursor_start {
+        match exercise.starting_lines().get(line) {
+            None => issues.push(Issue::exercise(format!(
+                "cursor_start [{}, {}] の行がサンプルコード（{}行）の範囲外です",
+                line,
+                col,
+                exercise.starting_lines().len()
+            ))),
+            // 列は文字単位。空行では0列目だけを許す
+            Some(text) if col >= text.chars().count().max(1) => issues.push(Issue::exercise(format!(
+                "cursor_start [{}, {}] の列が{}行目の長さ（{}）の範囲外です",
+                line,
+                col,
+                line + 1,
+                text.chars().count()
+            ))),
+            Some(_) => {}
+        }
+    }
+
+    // 許可していないオプションは練習用のエディタに渡さない
+    for option in &exercise.editor_options {
+        if continuous_session::editor_option_argument(option).is_none() {
+            issues.push(Issue::exercise(format!(
+                "editor_options の「{}」は使えません（使えるオプション: {}）",
+                option,
+                continuous_session::EDITOR_OPTIONS.join(", ")
+            )));
+        }
+    }
+
+    issues.extend(conduct_issues(exercise));
+    issues.extend(file_issues(exercise));
+
+    // 変換できない目標は、その理由を目標の説明と一緒に報告する
+    let mut goal_types = Vec::new();
+    for (index, goal_def) in exercise.goals.iter().enumerate() {
+        match goal_def.to_goal() {
+            Ok(goal) => goal_types.push((index, goal.goal_type)),
+            // 位置を決められなかった理由は報告済み
+            Err(_) if goal_def.goal_type == "position_at" => {}
+            Err(e) => issues.push(Issue::goal(index, format!("目標{}「{}」: {}", index + 1, goal_def.description, e))),
+        }
+    }
+
+    for (index, goal_type) in &goal_types {
+        let description = &exercise.goals[*index].description;
+        let (file, goal_type) = goal_file(goal_type);
+        // 取り消し・やり直しは、1つ前の目標を達成した時点の undo の番号と比べる
+        if matches!(goal_type, GoalType::UndoPerformed | GoalType::RedoPerformed) {
+            if !matches!(exercise.flow_type, FlowType::Sequential) {
+                issues.push(Issue::goal(*index, format!(
+                    "目標{}「{}」: 取り消し・やり直しの目標は flow_type: sequential の練習でだけ使えます",
+                    index + 1,
+                    description
+                )));
+            }
+            if file.is_some() {
+                issues.push(Issue::goal(*index, format!("目標{}「{}」: 取り消し・やり直しの目標には file を指定できません", index + 1, description)));
+            }
+            continue;
+        }
+        // . の繰り返しとテキストオブジェクトは、キー入力の記録から打ったキーを読む
+        if matches!(goal_type, GoalType::DotRepeatUsed { .. } | GoalType::OperatorTextObject { .. })
+            && !editor::current_captures_keystrokes()
+        {
+            issues.push(Issue::goal(*index, format!(
+                "目標{}「{}」: キー入力を記録できないエディタでは判定できない目標です（Neovim 0.8 以上で練習します）",
+                index + 1,
+                description
+            )));
+        }
+        if matches!(goal_type, GoalType::SyntaxNode { .. }) && !editor::current_supports_treesitter() {
+            issues.push(Issue::goal(*index, format!(
+                "目標{}「{}」: treesitter の目標は Neovim 0.9 以上でだけ判定できます",
+                index + 1,
+                description
+            )));
+        }
+        // 置換の目標は、有効になった時点の数を練習用ウィンドウのバッファからエディタに数えさせる
+        if let GoalType::SubstitutionResult { lines, .. } = goal_type {
+            if !editor::current_evaluates_expressions() {
+                issues.push(Issue::goal(*index, format!(
+                    "目標{}「{}」: 置換の目標は +clientserver の無い Vim では判定できません（Neovim か +clientserver の Vim で練習します）",
+                    index + 1,
+                    description
+                )));
+            }
+            if file.is_some() {
+                issues.push(Issue::goal(*index, format!("目標{}「{}」: 置換の目標には file を指定できません", index + 1, description)));
+            }
+            let line_count = exercise.starting_lines().len();
+            if let Some((_, last)) = lines
+                && *last >= line_count
+            {
+                issues.push(Issue::goal(*index, format!(
+                    "目標{}「{}」: lines の最後の行 {} がサンプルコード（{}行）の範囲外です",
+                    index + 1,
+                    description,
+                    last,
+                    line_count
+                )));
+            }
+            continue;
+        }
+        let referenced = match goal_type {
+            GoalType::BufferActive { name } => Some(name.as_str()),
+            _ => file,
+        };
+        if let Some(name) = referenced
+            && exercise.file_lines(Some(name)).is_none()
+        {
+            issues.push(Issue::goal(*index, format!(
+                "目標{}「{}」: ファイル「{}」は files にありません",
+                index + 1,
+                description,
+                name
+            )));
+            continue;
+        }
+        // 位置は同じファイルのテキストの目標と合わせて、そのファイルの中で到達できるか確かめる
+        if let GoalType::Position { line, col }
+        | GoalType::CountedMotion {
+            position: Some((line, col)),
+            ..
+        } = *goal_type
+            && let Some(lines) = exercise.file_lines(file)
+        {
+            let same_file = goal_types
+                .iter()
+                .map(|(_, other)| goal_file(other))
+                .filter(|(other_file, _)| *other_file == file)
+                .map(|(_, other)| other);
+            if let Some(issue) = position_issue(&buffer_extent(lines, same_file), line, col) {
+                issues.push(Issue::goal(*index, format!("目標{}「{}」: {}", index + 1, description, issue)));
+            }
+        }
+    }
+
+    issues
+}
+
+// allowed_modes のモード名と required_operator のオペレーターが分かるものか
+fn conduct_issues(exercise: &ContinuousExercise) -> Vec<Issue> {
+    let unknown_modes = |modes: &[String]| -> Vec<String> {
+        modes
+            .iter()
+            .filter(|name| VimMode::from_goal_name(name).is_none())
+            .map(|name| format!("allowed_modes の「{}」はモード名ではありません", name))
+            .collect()
+    };
+    let mut issues: Vec<Issue> = unknown_modes(&exercise.allowed_modes).into_iter().map(Issue::exercise).collect();
+    for (index, goal_def) in exercise.goals.iter().enumerate() {
+        let prefix = format!("目標{}「{}」: ", index + 1, goal_def.description);
+        issues.extend(
+            unknown_modes(&goal_def.allowed_modes)
+                .into_iter()
+                .map(|issue| Issue::goal(index, format!("{}{}", prefix, issue))),
+        );
+        if let Some(operator) = &goal_def.required_operator
+            && !keystrokes::OPERATORS.contains(&operator.as_str())
+        {
+            issues.push(Issue::goal(
+                index,
+                format!(
+                    "{}required_operator の「{}」はオペレーターではありません（使えるもの: {}）",
+                    prefix,
+                    operator,
+                    keystrokes::OPERATORS.join(" ")
+                ),
+            ));
+        }
+    }
+    issues
+}
+
+// 目標が対象にするファイル（None は最初に開くファイル）と、ファイルの指定を外した目標
+fn goal_file(goal_type: &GoalType) -> (Option<&str>, &GoalType) {
+    match goal_type {
+        GoalType::InFile { file, goal } => (Some(file.as_str()), goal),
+        _ => (None, goal_type),
+    }
+}
+
+// files の名前は練習用ディレクトリの中の重複しないファイル名にする
+fn file_issues(exercise: &ContinuousExercise) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    if !exercise.files.is_empty() && !exercise.sample_code.is_empty() {
+        issues.push(Issue::exercise("sample_code と files は同時に使えません"));
+    }
+    for (index, file) in exercise.files.iter().enumerate() {
+        let name = file.name.as_str();
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+            issues.push(Issue::exercise(format!(
+                "files の「{}」はファイル名にできません（ディレクトリを含めない名前にします）",
+                name
+            )));
+        } else if exercise.files[..index].iter().any(|earlier| earlier.name == name) {
+            issues.push(Issue::exercise(format!("files の「{}」が重複しています", name)));
+        }
+    }
+    issues
+}
+
+// 練習中のバッファが取りうる大きさ。学習者が行を追加する練習もあるため、
+// サンプルコードに加えてテキストの目標が期待する行も到達できるものとして扱う
+struct BufferExtent {
+    lines: usize,
+    // 最も長い行の文字数。ヤンクと貼り付けで行が入れ替わるため行ごとには比べない。
+    // 正規表現の目標があると長さが分からないため None
+    longest_line: Option<usize>,
+}
+
+impl BufferExtent {
+    fn expect_lines(&mut self, first_line: usize, texts: &[String]) {
+        self.lines = self.lines.max(first_line + texts.len());
+        for text in texts {
+            self.longest_line = self.longest_line.map(|longest| longest.max(text.chars().count()));
+        }
+    }
+}
+
+fn buffer_extent<'a>(
+    sample_lines: &[String],
+    goal_types: impl Iterator<Item = &'a GoalType>,
+) -> BufferExtent {
+    let mut extent = BufferExtent {
+        lines: sample_lines.len(),
+        longest_line: sample_lines.iter().map(|l| l.chars().count()).max().or(Some(0)),
+    };
+    for goal_type in goal_types {
+        match goal_type {
+            GoalType::TextContent {
+                line,
+                match_mode: TextMatch::Regex,
+                ..
+            } => {
+                extent.lines = extent.lines.max(line + 1);
+                extent.longest_line = None;
+            }
+            GoalType::TextContent { line, expected, .. } => {
+                extent.expect_lines(*line, std::slice::from_ref(expected))
+            }
+            GoalType::TextBlock {
+                start_line,
+                expected,
+                ..
+            } => extent.expect_lines(*start_line, expected),
+            GoalType::BufferEquals { expected } => extent.expect_lines(0, expected),
+            _ => {}
+        }
+    }
+    extent
+}
+
+// 位置の目標が到達できる範囲にあるか。Insert モードでは行末の次の列にも移動できる
+fn position_issue(extent: &BufferExtent, line: usize, col: usize) -> Option<String> {
+    if line >= extent.lines {
+        return Some(format!(
+            "位置 [{}, {}] の行がサンプルコードと目標のテキスト（{}行）の範囲外です",
+            line, col, extent.lines
+        ));
+    }
+    match extent.longest_line {
+        Some(longest) if col > longest => Some(format!(
+            "位置 [{}, {}] の列がサンプルコードと目標のテキストの最も長い行（{}）の範囲外です",
+            line, col, longest
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::continuous_content::ContinuousContentLoader;
+    use crate::continuous_session::{ExerciseFile, ExerciseGoal};
+
+    fn exercise_with_cursor(cursor_start: Option<[usize; 2]>) -> ContinuousExercise {
+        ContinuousExercise {
+            title: "開始位置".to_string(),
+            sample_code: vec!["let x = 1;".to_string(), String::new()],
+            cursor_start,
+            ..Default::default()
+        }
+    }
+
+    fn goal(goal_type: &str, target: serde_json::Value) -> ExerciseGoal {
+        ExerciseGoal {
+            goal_type: goal_type.to_string(),
+            target,
+            description: "目標".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn exercise_with_goals(goals: Vec<ExerciseGoal>) -> ContinuousExercise {
+        ContinuousExercise {
+            goals,
+            ..exercise_with_cursor(None)
+        }
+    }
+
+    #[test]
+    fn test_position_goal_within_sample() {
+        let exercise = exercise_with_goals(vec![
+            goal("position", serde_json::json!([0, 9])),
+            goal("position", serde_json::json!([0, 10])),
+            goal("position", serde_json::json!([1, 0])),
+        ]);
+        assert!(validate_exercise(&exercise).is_empty());
+    }
+
+    #[test]
+    fn test_position_goal_outside_sample() {
+        let exercise = exercise_with_goals(vec![goal("position", serde_json::json!([10, 0]))]);
+        assert_eq!(
+            validate_exercise(&exercise),
+            vec!["目標1「目標」: 位置 [10, 0] の行がサンプルコードと目標のテキスト（2行）の範囲外です"]
+        );
+        let exercise = exercise_with_goals(vec![goal("position", serde_json::json!([0, 11]))]);
+        assert_eq!(
+            validate_exercise(&exercise),
+            vec!["目標1「目標」: 位置 [0, 11] の列がサンプルコードと目標のテキストの最も長い行（10）の範囲外です"]
+        );
+    }
+
+    #[test]
+    fn test_position_goal_on_line_added_by_text_goal() {
+        // 行を追加する練習では、テキストの目標が期待する行と長さまで到達できる
+        let exercise = exercise_with_goals(vec![
+            goal("text", serde_json::json!({"line": 2, "expected": "let answer = 42;"})),
+            goal("position", serde_json::json!([2, 16])),
+            // 貼り付けた行は他の行と同じ長さになりうる
+            goal("position", serde_json::json!([1, 10])),
+            goal("text_block", serde_json::json!({"start_line": 3, "expected": ["", ""]})),
+            goal("position", serde_json::json!([4, 0])),
+        ]);
+        assert!(validate_exercise(&exercise).is_empty());
+
+        // 正規表現の目標だけがある行は列を確かめない
+        let exercise = exercise_with_goals(vec![
+            goal("text", serde_json::json!({"line": 2, "expected": "^y", "match_mode": "regex"})),
+            goal("position", serde_json::json!([2, 40])),
+        ]);
+        assert!(validate_exercise(&exercise).is_empty());
+    }
+
+    #[test]
+    fn test_malformed_goal_is_reported() {
+        let exercise = exercise_with_goals(vec![
+            goal("position", serde_json::json!([1])),
+            goal("text", serde_json::json!({"expected": "x"})),
+        ]);
+        let issues = validate_exercise(&exercise);
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].starts_with("目標1「目標」: Position target must be [line, column]"));
+        assert!(issues[1].contains("'line'"));
+    }
+
+    #[test]
+    fn test_unknown_modes_and_operators_are_reported() {
+        let mut exercise = exercise_with_goals(vec![ExerciseGoal {
+            allowed_modes: vec!["visual_line".to_string(), "insrt".to_string()],
+            required_operator: Some("x".to_string()),
+            ..goal("text", serde_json::json!({"line": 0, "expected": "x"}))
+        }]);
+        exercise.allowed_modes = vec!["replace".to_string()];
+        assert_eq!(
+            validate_exercise(&exercise),
+            vec![
+                "目標1「目標」: allowed_modes の「insrt」はモード名ではありません".to_string(),
+                "目標1「目標」: required_operator の「x」はオペレーターではありません（使えるもの: g~ gu gU g? g@ gq gw zf c d y < > ! =）".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_editor_options_are_reported() {
+        let exercise = ContinuousExercise {
+            editor_options: ["relativenumber", "set nowrap", "list!", "shell=/bin/sh", "number | !rm -rf ~"]
+                .map(String::from)
+                .to_vec(),
+            ..exercise_with_cursor(None)
+        };
+        let issues = validate_exercise(&exercise);
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].starts_with("editor_options の「shell=/bin/sh」は使えません"));
+        assert!(issues[1].contains("「number | !rm -rf ~」"));
+    }
+
+    #[test]
+    fn test_substitution_goal_lines_within_sample() {
+        let substitution = |lines: serde_json::Value| {
+            goal(
+                "substitution",
+                serde_json::json!({"pattern": "x", "replacement": "y", "min_count": 1, "lines": lines}),
+            )
+        };
+        assert!(validate_exercise(&exercise_with_goals(vec![substitution(serde_json::json!([0, 1]))])).is_empty());
+        assert_eq!(
+            validate_exercise(&exercise_with_goals(vec![substitution(serde_json::json!([0, 2]))])),
+            vec!["目標1「目標」: lines の最後の行 2 がサンプルコード（2行）の範囲外です"]
+        );
+        assert_eq!(
+            validate_exercise(&exercise_with_goals(vec![substitution(serde_json::json!([1, 0]))])).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_files_and_file_goals_are_checked() {
+        let file = |name: &str, content: &[&str]| ExerciseFile {
+            name: name.to_string(),
+            content: content.iter().map(|line| line.to_string()).collect(),
+        };
+        let exercise = ContinuousExercise {
+            sample_code: Vec::new(),
+            operator_hints: false,
+            files: vec![file("a.txt", &["short"]), file("b.txt", &["a much longer line"]), file("../x", &[])],
+            goals: vec![
+                goal("buffer_active", serde_json::json!({"name": "b.txt"})),
+                // b.txt の中では届く列
+                ExerciseGoal {
+                    file: Some("b.txt".to_string()),
+                    ..goal("position", serde_json::json!([0, 10]))
+                },
+                goal("position", serde_json::json!([0, 10])),
+                goal("buffer_active", serde_json::json!({"name": "c.txt"})),
+            ],
+            ..exercise_with_cursor(None)
+        };
+        let issues = validate_exercise(&exercise);
+        assert_eq!(issues.len(), 3, "{:?}", issues);
+        assert!(issues[0].starts_with("files の「../x」はファイル名にできません"));
+        assert!(issues[1].starts_with("目標3「目標」: "));
+        assert_eq!(issues[2], "目標4「目標」: ファイル「c.txt」は files にありません");
+        // 目標についての問題は、その目標の番号（0始まり）を持つ
+        let goals: Vec<Option<usize>> = exercise_issues(&exercise).iter().map(|issue| issue.goal).collect();
+        assert_eq!(goals, vec![None, Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn test_long_intro_warns_without_blocking() {
+        let exercise = ContinuousExercise {
+            intro: Some((1..=16).map(|i| format!("{}行目", i)).collect::<Vec<_>>().join("\n")),
+            ..exercise_with_cursor(None)
+        };
+        assert!(validate_exercise(&exercise).is_empty());
+        assert_eq!(
+            exercise_warnings(&exercise),
+            vec!["intro が16行あります（15行以内にすると目標の一覧と一緒に読めます）".to_string()]
+        );
+        let short = ContinuousExercise {
+            intro: Some("はじめに\n短い説明".to_string()),
+            ..exercise
+        };
+        assert!(exercise_warnings(&short).is_empty());
+    }
+
+    #[test]
+    fn test_ascii_text_goals_recommend_normalize() {
+        let exercise = exercise_with_goals(vec![
+            goal("text", serde_json::json!({"line": 0, "expected": "let x = 1;"})),
+            goal("text", serde_json::json!({"line": 0, "expected": "「x」"})),
+            goal("text", serde_json::json!({"line": 0, "expected": "let x = 1;", "normalize": []})),
+            goal("text", serde_json::json!({"line": 0, "expected": "^let", "match_mode": "regex"})),
+            goal("text", serde_json::json!({"line": 0, "expected": ""})),
+            goal("text_block", serde_json::json!({"start_line": 0, "expected": ["a", "b"]})),
+            goal("text_block", serde_json::json!({"start_line": 0, "expected": ["a", "べ"]})),
+            goal("text_block", serde_json::json!({"start_line": 0, "expected": ["a"], "normalize": ["width", "quotes"]})),
+        ]);
+        let warnings = normalize_warnings(&exercise);
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[0].message.starts_with("目標1の expected は半角の文字だけです"));
+        assert!(warnings[0].message.contains("normalize: [\"width\", \"quotes\"]"));
+        assert_eq!(warnings[1].goal, Some(5));
+        // 推奨は警告で、練習は始められる
+        assert!(validate_exercise(&exercise).is_empty());
+    }
+
+    #[test]
+    fn test_far_right_position_goal_on_long_line_warns() {
+        let mut exercise = exercise_with_goals(vec![goal("position", serde_json::json!([0, 95])), goal("position", serde_json::json!([1, 3]))]);
+        exercise.sample_code = vec![format!("{}target{}", "x".repeat(95), "y".repeat(30)), "short".to_string()];
+        assert!(validate_exercise(&exercise).is_empty());
+        let warnings = long_line_warnings(&exercise, 80);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.starts_with("目標1の位置（1行96列）は80桁より右にあります"));
+        // 全角の文字は2桁として数える
+        exercise.sample_code[0] = format!("{}target", "あ".repeat(45));
+        exercise.goals[0].target = serde_json::json!([0, 45]);
+        assert_eq!(long_line_warnings(&exercise, 80).len(), 1);
+        assert!(long_line_warnings(&exercise, 120).is_empty());
+    }
+
+    #[test]
+    fn test_bundled_chapters_are_valid() {
+        let (loader, report) = ContinuousContentLoader::load_from(Path::new(crate::paths::BUNDLED_CHAPTERS_DIR)).unwrap();
+        // 読めない章は chapter_numbers に入らないため、読み込みの失敗も確かめる
+        assert!(report.failures().is_empty(), "{:?}", report.failures());
+        for number in loader.chapter_numbers() {
+            let chapter = loader.get_chapter(number).unwrap();
+            assert_eq!(validate_chapter(chapter), Vec::<String>::new(), "第{}章", number);
+        }
+    }
+
+    #[test]
+    fn test_lesson_positions_reference_exercises() {
+        let mut chapter = ContinuousContentLoader::sample_chapter();
+        assert!(validate_chapter(&chapter).is_empty());
+        chapter.lessons[0].before = Some(8);
+        assert_eq!(
+            validate_chapter(&chapter),
+            vec!["解説「モードで編集する」: before: 8 の練習がありません（練習は7個）".to_string()]
+        );
+        chapter.lessons[0].before = Some(0);
+        assert_eq!(validate_chapter(&chapter).len(), 1);
+        chapter.lessons[0].before = None;
+        assert!(validate_chapter(&chapter).is_empty());
+    }
+
+    #[test]
+    fn test_validate_prerequisites() {
+        let chapter = |number, requires: &'static [u8], file: &'static str| ChapterRequirement {
+            number,
+            requires,
+            file,
+        };
+        let valid = [chapter(1, &[], "a.yaml"), chapter(2, &[1], "b.yaml"), chapter(3, &[1, 2], "c.yaml")];
+        assert!(validate_prerequisites(&valid).is_empty());
+
+        let missing = [chapter(1, &[], "a.yaml"), chapter(2, &[9], "b.yaml")];
+        assert_eq!(
+            validate_prerequisites(&missing),
+            vec!["b.yaml: 第2章の requires にある第9章は存在しません"]
+        );
+
+        let cyclic = [
+            chapter(1, &[3], "a.yaml"),
+            chapter(2, &[1], "b.yaml"),
+            chapter(3, &[2], "c.yaml"),
+            chapter(4, &[4], "d.yaml"),
+        ];
+        assert_eq!(
+            validate_prerequisites(&cyclic),
+            vec![
+                "前提条件が循環しています: 第1章 (a.yaml) → 第3章 (c.yaml) → 第2章 (b.yaml) → 第1章 (a.yaml)",
+                "前提条件が循環しています: 第4章 (d.yaml) → 第4章 (d.yaml)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cursor_start_within_sample() {
+        assert!(validate_exercise(&exercise_with_cursor(None)).is_empty());
+        assert!(validate_exercise(&exercise_with_cursor(Some([0, 9]))).is_empty());
+        // 空行の0列目は有効
+        assert!(validate_exercise(&exercise_with_cursor(Some([1, 0]))).is_empty());
+    }
+
+    #[test]
+    fn test_cursor_start_outside_sample() {
+        let issues = validate_exercise(&exercise_with_cursor(Some([2, 0])));
+        assert_eq!(
+            issues,
+            vec!["cursor_start [2, 0] の行がサンプルコード（2行）の範囲外です"]
+        );
+        let issues = validate_exercise(&exercise_with_cursor(Some([0, 10])));
+        assert_eq!(
+            issues,
+            vec!["cursor_start [0, 10] の列が1行目の長さ（10）の範囲外です"]
+        );
+        assert_eq!(validate_exercise(&exercise_with_cursor(Some([1, 1]))).len(), 1);
+    }
+
+    #[test]
+    fn test_columns_count_characters_in_multibyte_lines() {
+        // 「名前を表示」は5文字・15バイト
+        let multibyte = |cursor_start, goals| ContinuousExercise {
+            sample_code: vec!["名前を表示".to_string()],
+            goals,
+            ..exercise_with_cursor(cursor_start)
+        };
+        assert!(validate_exercise(&multibyte(Some([0, 4]), vec![goal("position", serde_json::json!([0, 5]))])).is_empty());
+        assert_eq!(
+            validate_exercise(&multibyte(Some([0, 5]), vec![goal("position", serde_json::json!([0, 6]))])),
+            vec![
+                "cursor_start [0, 5] の列が1行目の長さ（5）の範囲外です",
+                "目標1「目標」: 位置 [0, 6] の列がサンプルコードと目標のテキストの最も長い行（5）の範囲外です",
+            ]
+        );
+    }
+}
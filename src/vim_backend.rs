@@ -0,0 +1,1642 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::editor::{self, Editor};
+use crate::error::TutorialError;
+use crate::layout::{self, PaneLayout, TerminalSize};
+use crate::output::info;
+use crate::paths;
+use crate::goal_registry::GoalQuery;
+use crate::session_progress::{self, ProgressSnapshot};
+use crate::vim_rpc::VimRpcClient;
+use crate::vim_state::{StatusRecord, VimState, parse_status_record};
+use crate::output::{DebugLog, debug_log};
+
+// debug_log! の書き先
+const DEBUG_LOG: DebugLog = DebugLog::CONTINUOUS;
+
+// 以下の一時ファイルは temp_file でキャッシュの置き場所（paths::cache_dir）に置く
+// 練習用Neovimが書き出す状態ファイル
+pub const STATUS_FILE: &str = "vim_continuous_status.json";
+// 練習用Neovimからのコマンド（:TutorialHint など）を受け取るファイル
+pub const COMMAND_FILE: &str = "vim_continuous_command.txt";
+// 学習者のキー入力ログ（1行 = "モード\tキー列"、数え方は keystrokes.rs を参照）
+pub const KEY_LOG_FILE: &str = "vim_continuous_keys.log";
+// 練習の進み具合（session_progress::ProgressSnapshot の JSON。節目ごとに置き換える）
+pub const PROGRESS_FILE: &str = "vim_continuous_progress.json";
+// 監視側から練習用Neovimへの知らせ（1行 = 1件のメッセージ）
+pub const NOTICE_FILE: &str = "vim_continuous_notice.txt";
+// 監視側から練習用Neovimへ、目標の on_activate / on_complete で実行させる Ex コマンド（1行 = 1コマンド）
+pub const GOAL_COMMAND_FILE: &str = "vim_continuous_goal_commands.txt";
+// tmux が無い場合に、エディタ内の指示ウィンドウへ表示する内容
+pub const INSTRUCTIONS_FILE: &str = "vim_continuous_instructions.txt";
+// tmuxの指示ペインに cat で表示させる内容（指示の文章をシェルのコマンドに埋め込まない）
+pub const PANE_FILE: &str = "vim_tutorial_pane.txt";
+// 練習ごとに作り直し、終了時に削除する一時ファイル
+pub const STATE_FILES: &[&str] = &[
+    PROGRESS_FILE,
+    STATUS_FILE,
+    COMMAND_FILE,
+    KEY_LOG_FILE,
+    NOTICE_FILE,
+    GOAL_COMMAND_FILE,
+    INSTRUCTIONS_FILE,
+];
+
+// キャッシュの置き場所の中のパス（paths::cache_file）
+pub fn temp_file(name: &str) -> String {
+    paths::cache_file(name).to_string_lossy().into_owned()
+}
+
+// Vimスクリプトの単一引用符リテラルに埋め込む一時ファイルのパス
+pub fn script_temp_file(name: &str) -> String {
+    temp_file(name).replace('\'', "''")
+}
+
+pub const SESSION_NAME: &str = "vim_tutorial_continuous";
+// エディタは100msごとに状態を書き出すため、この時間更新が無ければ接続が切れたとみなす
+const STALE_STATUS_AFTER: Duration = Duration::from_secs(3);
+
+// 練習用Neovimの起動に必要なもの
+pub struct LaunchRequest<'a> {
+    pub vim_script: &'a Path,
+    pub file_path: &'a str,
+    pub layout: PaneLayout,
+    pub terminal_size: Option<TerminalSize>,
+    pub instructions: &'a [String], // 指示ペインの初回表示
+}
+
+// 動いている練習用エディタの居場所（監視側のプロセスが落ちても、次の起動で再接続できるよう記録する）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunningSession {
+    pub session_name: String,
+    pub editor: String, // エディタのコマンド名（別のエディタを選んで起動した場合は再接続しない）
+    pub server: String, // Neovim のソケットか Vim のサーバー名
+    pub editor_pane: String,
+    pub instruction_pane: String,
+}
+
+// 監視ループから見た練習用Neovim（起動・状態の取得・キーの送信など）
+pub trait VimBackend {
+    // 練習に使うエディタ（スクリプトの生成とキー入力の記録の有無に使う）
+    fn editor(&self) -> Editor;
+
+    // キー入力を記録できるか（Vim と 0.8 未満の Neovim では記録しない）
+    fn captures_keystrokes(&self) -> bool {
+        self.editor().captures_keystrokes()
+    }
+
+    // 学習者がすでに使っているエディタで練習するか（設定・マッピング・自動コマンドをバッファや
+    // タブに限り、終了時に全て元に戻すスクリプトを書く）
+    fn shares_editor(&self) -> bool {
+        false
+    }
+
+    // 端末の大きさ（分からなければ None）
+    fn terminal_size(&self) -> Option<TerminalSize>;
+
+    // Neovimを起動し、指示の表示先を返す（表示先が無い場合は None）
+    fn start(&mut self, request: &LaunchRequest) -> Result<Option<Box<dyn InstructionSink>>>;
+
+    fn stop(&mut self) -> Result<()>;
+
+    // 練習の終わりにエディタを止めずに残す（--keep-session。以後はこのバックエンドから止めない）
+    fn keep_running(&mut self) {}
+
+    // 再接続に使える居場所（外から操作できない場合や tmux を使っていない場合は None）
+    fn running_session(&self) -> Option<RunningSession> {
+        None
+    }
+
+    // 前回のプロセスが残したエディタに接続し直す（起動はせず、監視用のスクリプトだけを読み直させる）
+    fn reattach(
+        &mut self,
+        _running: &RunningSession,
+        _request: &LaunchRequest,
+    ) -> Result<Option<Box<dyn InstructionSink>>> {
+        Err(anyhow::anyhow!("この環境では前回のエディタに再接続できません"))
+    }
+
+    // 最新の状態（まだ届いていない・しばらく更新が無い場合はそれと分かる形で返す）
+    fn get_state(&mut self) -> Result<StateRead>;
+
+    // 練習用Neovimにキーを送る（キー名は tmux の send-keys と同じ）
+    fn send_keys(&mut self, keys: &[&str]) -> Result<()>;
+
+    // 練習用Neovimの画面を学習者が閉じたか（tmux が無く、同じ端末で起動した場合）
+    fn editor_exited(&mut self) -> bool;
+
+    // 接続を再確立する（落ちたNeovimを起動し直すか、監視用のスクリプトを読み直させる）
+    fn reconnect(&mut self) -> Result<()>;
+
+    // 練習用Neovimから届いたコマンド（:TutorialHint など）
+    fn take_commands(&mut self) -> Vec<String>;
+
+    // 前回以降に学習者が押したキーのログ（1行 = "モード\tキー列"）
+    fn take_key_log(&mut self) -> Vec<String>;
+
+    // 学習者が画面を見ているか（離れている間はヒントの時間を数えない）
+    fn learner_attached(&mut self) -> bool;
+
+    // 練習の進み具合を書き出す（目標の達成・ヒント・一時停止・終了の節目ごと）
+    fn report_progress(&mut self, snapshot: &ProgressSnapshot);
+
+    // 練習用Neovimでベルを鳴らし、メッセージを強調表示する
+    fn announce(&mut self, message: &str);
+
+    // 練習用Neovimに Ex コマンドを実行させる（実行し終えると GOAL_COMMANDS_DONE のコマンドが届き、
+    // 失敗したコマンドは GOAL_COMMAND_FAILED で知らせる）
+    fn run_commands(&mut self, commands: &[String]) -> Result<()>;
+
+    // Vim の記法のキー（"<Esc>" など）を、学習者が打ったものとして練習用のエディタに送る
+    fn send_editor_keys(&mut self, keys: &str) -> Result<()>;
+
+    // 表示の反映を待つ
+    fn pause(&self, duration: Duration);
+
+    // 外からエディタに式を評価させられるか（+clientserver の無い Vim では状態ファイルを読むだけ）
+    fn evaluates_expressions(&self) -> bool {
+        false
+    }
+
+    // エディタが入力を待って止まっているか（スワップファイルの確認など。確かめられなければ None）
+    fn editor_blocked(&mut self) -> Option<bool> {
+        None
+    }
+
+    // 練習用ウィンドウのバッファで pattern（Vim の正規表現）に一致する数（lines は0始まりの行の範囲）
+    fn count_matches(&mut self, pattern: &str, _lines: Option<(usize, usize)>) -> Result<usize> {
+        Err(anyhow::anyhow!("この環境では「{}」の数をエディタに数えさせられません", pattern))
+    }
+
+    // 練習用ウィンドウのバッファの first 行から count 行（0始まり。バッファに無い行は返さない）
+    fn buffer_lines(&mut self, _first: usize, _count: usize) -> Result<Vec<String>> {
+        Err(anyhow::anyhow!("この環境ではバッファの行をエディタに問い合わせられません"))
+    }
+
+    // 練習用ウィンドウのカーソルを含む treesitter のノードの種類（内側から）。language が None なら
+    // バッファの filetype の言語のパーサーを使う。パーサーが無ければ Ok(None)
+    fn syntax_nodes(&mut self, _language: Option<&str>) -> Result<Option<Vec<String>>> {
+        Err(anyhow::anyhow!("この環境では treesitter のノードをエディタに問い合わせられません"))
+    }
+
+    // 独自の種類の目標が判定の最中にエディタへ問い合わせる手段（式を評価させられなければ None）
+    fn goal_query(&self) -> Option<Box<dyn GoalQuery>> {
+        None
+    }
+
+    // 練習の画面（ペインの名前と、スクロールバックを含む内容）。stop の前に呼ぶ。
+    // ペインが無い環境や、既に閉じたペインのものは返さない
+    fn capture_panes(&mut self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+}
+
+// capture_panes で遡るスクロールバックの行数
+const CAPTURE_SCROLLBACK_LINES: usize = 2000;
+// nvim_get_mode() がこの時間で返らなければ、エディタは入力待ちで止まっているとみなす
+const BLOCKED_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// 指示の表示先（tmuxでは上部のペイン）
+pub trait InstructionSink {
+    fn show(&mut self, lines: &[String]) -> Result<()>;
+
+    // 頻繁に表示し直してもちらつかないか（カーソル位置の表示はこの場合だけ出す）
+    fn live(&self) -> bool {
+        false
+    }
+}
+
+// tmux の分割画面を使えるか（Windows には tmux が無いため常に false）
+pub fn tmux_available() -> bool {
+    cfg!(unix)
+        && Command::new("tmux")
+            .arg("-V")
+            .output()
+            .is_ok_and(|output| output.status.success())
+}
+
+// tmuxで画面を分割し、状態ファイルとコマンドファイルでNeovimとやり取りする。
+// tmuxが無い場合（Windows を含む）は同じ端末でエディタを起動し、エディタの中に指示のウィンドウを開く。
+// 起動元の Neovim を使う場合（editor::use_parent_server）は、その Neovim の新しいタブで練習する
+pub struct TmuxBackend {
+    editor: Editor,
+    clientserver: bool, // Vim を --servername 付きで起動し、終了を --remote-send で伝える
+    window_mode: bool,  // tmux を使わず、エディタ内の指示ウィンドウで練習している
+    vim_client: VimRpcClient,
+    vim_pane_id: Option<String>,
+    instruction_pane_id: Option<String>,
+    attach_checked_at: Option<Instant>,
+    client_attached: bool,
+    status: StatusReader,
+    launch_command: Option<String>, // 落ちたエディタをペインで起動し直すためのコマンド
+    script_path: Option<PathBuf>,   // 再接続で読み直させる監視用スクリプト
+    in_parent: bool,                // 起動元の Neovim のタブで練習している
+    tab_checked_at: Option<Instant>,
+    tab_open: bool,
+    kept: bool, // --keep-session で残したエディタ（次の練習でセッションを作り直すまで止めない）
+}
+
+impl TmuxBackend {
+    pub fn new(socket_path: String) -> Self {
+        let editor = editor::current();
+        let clientserver = editor == Editor::Vim && editor::current_clientserver();
+        // Vim のサーバー名はソケットではなく名前で指定する
+        let server = match editor {
+            Editor::Neovim => socket_path,
+            Editor::Vim => format!("VIM_TUTORIAL_{}", std::process::id()),
+        };
+        let parent_server = editor::current_parent_server().filter(|_| editor == Editor::Neovim);
+        let vim_client = match parent_server {
+            Some(parent) => VimRpcClient::attach(parent.to_string(), editor),
+            None => VimRpcClient::new(server, editor),
+        };
+        Self {
+            editor,
+            clientserver,
+            window_mode: false,
+            vim_client,
+            vim_pane_id: None,
+            instruction_pane_id: None,
+            attach_checked_at: None,
+            client_attached: true,
+            status: StatusReader::new(temp_file(STATUS_FILE)),
+            launch_command: None,
+            script_path: None,
+            in_parent: parent_server.is_some(),
+            tab_checked_at: None,
+            tab_open: true,
+            kept: false,
+        }
+    }
+
+    // --listen（Neovim）または --servername（+clientserver の Vim）で外から操作できるか
+    fn has_server(&self) -> bool {
+        self.editor == Editor::Neovim || self.clientserver
+    }
+
+    // 下部ペインでエディタが動いているか（落ちるとシェルに戻る）
+    #[cfg(unix)]
+    fn pane_runs_editor(&self, pane_id: &str) -> bool {
+        Command::new("tmux")
+            .args(["display-message", "-p", "-t", pane_id, "#{pane_current_command}"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim() == self.editor.program())
+            .unwrap_or(true)
+    }
+
+    #[cfg(unix)]
+    fn start_tmux_session(&mut self, request: &LaunchRequest) -> Result<TmuxInstructionPane> {
+        // 既存セッションを削除
+        let _ = Command::new("tmux")
+            .args(["kill-session", "-t", SESSION_NAME])
+            .output();
+        // 残したエディタも今止めたので、ソケットを片付けられるクライアントに戻す
+        if self.kept {
+            self.vim_client = VimRpcClient::new(self.vim_client.server_name().to_string(), self.editor);
+            self.kept = false;
+        }
+
+        // 新しいセッションを作成（アタッチ前から端末と同じ大きさにしておく）
+        let mut new_session_args = vec!["new-session", "-d", "-s", SESSION_NAME]
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if let Some(size) = request.terminal_size {
+            new_session_args.extend([
+                "-x".to_string(),
+                size.columns.to_string(),
+                "-y".to_string(),
+                size.lines.to_string(),
+            ]);
+        }
+        let new_session_result = Command::new("tmux").args(&new_session_args).output()?;
+
+        if !new_session_result.status.success() {
+            return Err(TutorialError::TmuxSessionFailed {
+                action: "セッション作成",
+                stderr: String::from_utf8_lossy(&new_session_result.stderr).into_owned(),
+            }
+            .into());
+        }
+
+        // 画面を分割（左右分割のときだけ -h）
+        let split_direction = if request.layout == PaneLayout::SideBySide {
+            "-h"
+        } else {
+            "-v"
+        };
+        let split_result = Command::new("tmux")
+            .args(["split-window", split_direction, "-t", SESSION_NAME])
+            .output()?;
+
+        if !split_result.status.success() {
+            return Err(TutorialError::TmuxSessionFailed {
+                action: "画面分割",
+                stderr: String::from_utf8_lossy(&split_result.stderr).into_owned(),
+            }
+            .into());
+        }
+
+        // 分割後にペイン一覧を取得して正確なIDを確認
+        let pane_list_output = Command::new("tmux")
+            .args(["list-panes", "-t", SESSION_NAME, "-F", "#{pane_index}:#{pane_id}:#{pane_current_command}"])
+            .output()?;
+
+        let pane_info = String::from_utf8_lossy(&pane_list_output.stdout);
+        debug_log!("分割後ペイン一覧: {}", pane_info.trim());
+
+        // pane_index 0 = 上部（指示用）、pane_index 1 = 下部（Vim用）
+        let mut top_pane_id = String::new();
+        let mut bottom_pane_id = String::new();
+
+        for line in pane_info.lines() {
+            let parts: Vec<&str> = line.split(':').collect();
+            if parts.len() >= 3 {
+                let index = parts[0];
+                let pane_id = parts[1];
+                // index 0 = 上部（指示表示用）
+                // index 1 = 下部（Vim用）
+                if index == "0" {
+                    top_pane_id = pane_id.to_string();
+                } else if index == "1" {
+                    bottom_pane_id = pane_id.to_string();
+                }
+            }
+        }
+
+        debug_log!("上部ペインID: {}", top_pane_id);
+        debug_log!("下部ペインID: {}", bottom_pane_id);
+
+        // 簡易表示では指示ペインを3行分（とプロンプト）に縮め、Neovimに残りを使う
+        if request.layout == PaneLayout::Compact && !top_pane_id.is_empty() {
+            let _ = Command::new("tmux")
+                .args([
+                    "resize-pane",
+                    "-t",
+                    &top_pane_id,
+                    "-y",
+                    &layout::COMPACT_PANE_LINES.to_string(),
+                ])
+                .output();
+        }
+
+        // ペイン識別のためのテストメッセージ送信
+        if !top_pane_id.is_empty() {
+            let _ = Command::new("tmux")
+                .args(["send-keys", "-t", &top_pane_id, "echo 'TEST: 上部ペイン'", "Enter"])
+                .output();
+            debug_log!("上部ペインにテストメッセージ送信: {}", top_pane_id);
+        }
+
+        if !bottom_pane_id.is_empty() {
+            let _ = Command::new("tmux")
+                .args(["send-keys", "-t", &bottom_pane_id, "echo 'TEST: 下部ペイン'", "Enter"])
+                .output();
+            debug_log!("下部ペインにテストメッセージ送信: {}", bottom_pane_id);
+        }
+
+        thread::sleep(Duration::from_millis(1000)); // テストメッセージを確認するための待機
+
+        self.vim_pane_id = Some(bottom_pane_id.clone());
+        self.instruction_pane_id = Some(top_pane_id.clone());
+
+        // 取得したペインIDを使用
+        let top_pane = &top_pane_id;    // 上部ペイン（指示）
+        let bottom_pane = &bottom_pane_id; // 下部ペイン（Vim）
+
+        // 前回の練習の状態を読まないように片付けてから、上部ペインに指示を表示
+        for file in STATE_FILES {
+            let _ = fs::remove_file(temp_file(file));
+        }
+        let mut instruction_pane = TmuxInstructionPane::new(top_pane);
+        instruction_pane.show(request.instructions)?;
+
+        thread::sleep(Duration::from_millis(200));
+
+        // 下部ペインでVimを起動（再接続できるよう、Neovim はソケットで待ち受ける）
+        let _ = self.vim_client.remove_socket_file();
+        let server_args = match self.editor {
+            Editor::Neovim => format!("--listen {} ", self.vim_client.server_name()),
+            Editor::Vim if self.clientserver => {
+                format!("--servername {} ", self.vim_client.server_name())
+            }
+            Editor::Vim => String::new(),
+        };
+        // -n: スワップファイルを使わない（残ったスワップファイルの確認で入力待ちにならないように）
+        let nvim_command = format!(
+            "{} -n {}-S {} {}; tmux detach-client",
+            self.editor.program(),
+            server_args,
+            request.vim_script.display(),
+            request.file_path
+        );
+
+        debug_log!("下部ペイン({})にVimコマンド送信: {}", bottom_pane, nvim_command);
+        let vim_result = Command::new("tmux")
+            .args(["send-keys", "-t", bottom_pane, &nvim_command, "Enter"])
+            .output();
+        debug_log!("Vim起動結果: success={}", vim_result.as_ref().map(|r| r.status.success()).unwrap_or(false));
+        self.launch_command = Some(nvim_command);
+
+        thread::sleep(Duration::from_millis(500));
+
+        // 下部ペインにフォーカス
+        let _ = Command::new("tmux")
+            .args(["select-pane", "-t", bottom_pane])
+            .output();
+
+        // 現在のターミナルをtmuxセッションにアタッチ
+        info!("🖥️ 画面を分割してVim学習を開始します...");
+        info!("💡 操作方法:");
+        info!("   - 上部: 指示とリアルタイム進捗表示");
+        info!("   - 下部: Vim操作画面");
+        info!("   - Ctrl+b ↑/↓でペイン間移動可能");
+        info!();
+
+        // 少し待ってから現在のターミナルでtmuxセッションをアタッチ
+        thread::sleep(Duration::from_millis(1000));
+
+        debug_log!("tmuxアタッチ準備完了、Vimセッション開始！");
+
+        // tmuxセッションにアタッチ（非同期で実行）
+        info!("🖥️ tmuxセッションにアタッチ中...");
+        info!("💡 操作方法:");
+        info!("   - 上部: 指示とリアルタイム進捗表示");
+        info!("   - 下部: Vim操作画面");
+        info!("   - Ctrl+b ↑/↓でペイン間移動可能");
+        info!("   - 目標達成後、自動的に次の目標に進みます");
+        info!();
+
+        Self::attach_in_background(SESSION_NAME);
+
+        Ok(instruction_pane)
+    }
+
+    // 現在の端末を tmux セッションにアタッチする（監視ループを止めないよう別スレッドで待つ）
+    #[cfg(unix)]
+    fn attach_in_background(session_name: &str) {
+        let session_name = session_name.to_string();
+        thread::spawn(move || {
+            debug_log!("tmuxアタッチスレッド開始");
+            let attach_result = Command::new("tmux")
+                .args(["attach-session", "-t", &session_name])
+                .status();
+
+            match attach_result {
+                Ok(_) => {
+                    debug_log!("tmuxセッション正常終了");
+                }
+                Err(e) => {
+                    debug_log!("tmuxアタッチエラー: {}", e);
+                }
+            }
+        });
+
+        // 短時間待機してから戻る（監視スレッドを開始できるように）
+        thread::sleep(Duration::from_millis(500));
+    }
+
+    // 同じ端末でエディタを起動し、指示はエディタ内のウィンドウに表示する
+    fn start_window(&mut self, request: &LaunchRequest) -> Result<Box<dyn InstructionSink>> {
+        info!(
+            "🪟 tmuxが無いため、{}の中に指示のウィンドウを開いて学習を開始します",
+            self.editor.label()
+        );
+        for file in STATE_FILES {
+            let _ = fs::remove_file(temp_file(file));
+        }
+        let mut window = WindowInstructions {
+            path: temp_file(INSTRUCTIONS_FILE),
+        };
+        window.show(request.instructions)?;
+
+        // Neovim は常に、Vim は +clientserver があれば、終了を外から伝えられるようにする
+        self.vim_client
+            .start_interactive(request.file_path, request.vim_script, self.has_server())?;
+        self.window_mode = true;
+
+        // スクリプトの一時ファイルを消す前に、エディタが読み込んで状態を書き出すまで待つ
+        let started = Instant::now();
+        while !Path::new(&temp_file(STATUS_FILE)).exists()
+            && self.vim_client.is_running()
+            && started.elapsed() < Duration::from_secs(5)
+        {
+            thread::sleep(Duration::from_millis(50));
+        }
+        Ok(Box::new(window))
+    }
+
+    // 起動元の Neovim に新しいタブを開いて練習する（指示はタブの中のウィンドウに表示する）
+    fn start_in_parent(&mut self, request: &LaunchRequest) -> Result<Box<dyn InstructionSink>> {
+        info!("🪟 この Neovim の新しいタブで学習を開始します（終わるとタブを閉じて元に戻します）");
+        for file in STATE_FILES {
+            let _ = fs::remove_file(temp_file(file));
+        }
+        let mut window = WindowInstructions {
+            path: temp_file(INSTRUCTIONS_FILE),
+        };
+        window.show(request.instructions)?;
+        self.vim_client.open_in_new_tab(request.file_path, request.vim_script)?;
+        self.window_mode = true;
+        self.tab_checked_at = None;
+        self.tab_open = true;
+
+        let started = Instant::now();
+        while !Path::new(&temp_file(STATUS_FILE)).exists() && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(50));
+        }
+        Ok(Box::new(window))
+    }
+
+    // ファイルを退避してから読むことで、読み取り中の追記を取りこぼさない
+    fn take_lines(path: &str) -> Option<String> {
+        let processing = format!("{}.processing", path);
+        fs::rename(path, &processing).ok()?;
+        let content = fs::read_to_string(&processing).ok();
+        let _ = fs::remove_file(&processing);
+        content
+    }
+}
+
+impl VimBackend for TmuxBackend {
+    fn editor(&self) -> Editor {
+        self.editor
+    }
+
+    fn captures_keystrokes(&self) -> bool {
+        editor::current_captures_keystrokes()
+    }
+
+    fn shares_editor(&self) -> bool {
+        self.in_parent
+    }
+
+    fn terminal_size(&self) -> Option<TerminalSize> {
+        layout::terminal_size()
+    }
+
+    fn start(&mut self, request: &LaunchRequest) -> Result<Option<Box<dyn InstructionSink>>> {
+        self.vim_pane_id = None;
+        self.attach_checked_at = None;
+        self.client_attached = true;
+        self.status = StatusReader::new(temp_file(STATUS_FILE));
+        self.window_mode = false;
+        self.launch_command = None;
+        self.script_path = Some(request.vim_script.to_path_buf());
+
+        if self.in_parent {
+            return self.start_in_parent(request).map(Some);
+        }
+
+        // tmux分割画面でVimを起動
+        #[cfg(unix)]
+        if tmux_available() {
+            info!("🖥️ tmux分割画面モードで学習を開始します");
+            let pane = self.start_tmux_session(request)?;
+            return Ok(Some(Box::new(pane)));
+        }
+        self.start_window(request).map(Some)
+    }
+
+    fn running_session(&self) -> Option<RunningSession> {
+        if !self.has_server() {
+            return None;
+        }
+        Some(RunningSession {
+            session_name: SESSION_NAME.to_string(),
+            editor: self.editor.program().to_string(),
+            server: self.vim_client.server_name().to_string(),
+            editor_pane: self.vim_pane_id.clone()?,
+            instruction_pane: self.instruction_pane_id.clone()?,
+        })
+    }
+
+    #[cfg(unix)]
+    fn reattach(
+        &mut self,
+        running: &RunningSession,
+        request: &LaunchRequest,
+    ) -> Result<Option<Box<dyn InstructionSink>>> {
+        if running.editor != self.editor.program() {
+            return Err(anyhow::anyhow!(
+                "前回の練習は {} で動いています（今回は {}）",
+                running.editor,
+                self.editor.program()
+            ));
+        }
+        info!("🔌 前回のtmuxセッションに再接続します");
+        self.vim_client = VimRpcClient::new(running.server.clone(), self.editor);
+        self.vim_pane_id = Some(running.editor_pane.clone());
+        self.instruction_pane_id = Some(running.instruction_pane.clone());
+        self.attach_checked_at = None;
+        self.client_attached = true;
+        // 状態ファイルはエディタが書き続けているため消さずに読む
+        self.status = StatusReader::new(temp_file(STATUS_FILE));
+        self.window_mode = false;
+        self.launch_command = None;
+        self.script_path = Some(request.vim_script.to_path_buf());
+
+        // バッファには触れず、新しい監視用スクリプトを読み直させる
+        self.reconnect()?;
+        let mut instruction_pane = TmuxInstructionPane::new(&running.instruction_pane);
+        instruction_pane.show(request.instructions)?;
+        Self::attach_in_background(&running.session_name);
+        Ok(Some(Box::new(instruction_pane)))
+    }
+
+    fn keep_running(&mut self) {
+        self.vim_client.detach();
+        self.kept = true;
+        self.window_mode = false;
+        self.script_path = None;
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        // 起動元の Neovim は終了させず、練習用のタブと監視用のスクリプトが足したものを片付けてもらう
+        if self.in_parent {
+            if self.window_mode {
+                let _ = self.vim_client.eval_expr("execute(get(g:, 'tutorial_detach', []))");
+            }
+            self.window_mode = false;
+            self.script_path = None;
+            for file in STATE_FILES {
+                let _ = fs::remove_file(temp_file(file));
+            }
+            return Ok(());
+        }
+
+        // ペインのエディタには終了を伝え、スワップファイルを残さないようにする
+        if self.has_server() && self.vim_pane_id.is_some() {
+            let _ = self.vim_client.send_keys("<C-\\><C-N>:qa!<CR>");
+        }
+        self.window_mode = false;
+        self.launch_command = None;
+        self.script_path = None;
+
+        // tmuxセッションをクリーンアップ
+        let _ = Command::new("tmux")
+            .args(["kill-session", "-t", SESSION_NAME])
+            .output();
+        self.vim_pane_id = None;
+        self.instruction_pane_id = None;
+
+        // 状態ファイルをクリーンアップ
+        for file in STATE_FILES {
+            let _ = fs::remove_file(temp_file(file));
+        }
+
+        // RPC クライアントも停止（同じ端末で起動したエディタは :qa! で閉じ、端末の表示を戻す）
+        self.vim_client.stop()?;
+        Ok(())
+    }
+
+    fn get_state(&mut self) -> Result<StateRead> {
+        self.status.poll()
+    }
+
+    fn send_keys(&mut self, keys: &[&str]) -> Result<()> {
+        let Some(pane_id) = &self.vim_pane_id else {
+            return Ok(());
+        };
+        let mut args = vec!["send-keys", "-t", pane_id.as_str()];
+        args.extend_from_slice(keys);
+        let _ = Command::new("tmux").args(&args).output();
+        Ok(())
+    }
+
+    fn editor_exited(&mut self) -> bool {
+        if !self.in_parent {
+            return self.window_mode && !self.vim_client.is_running();
+        }
+        // 起動元の Neovim では、練習用のタブが閉じられたか（Neovim ごと終了した場合を含む）を1秒ごとに確かめる
+        let needs_check = self
+            .tab_checked_at
+            .is_none_or(|checked| checked.elapsed() >= Duration::from_secs(1));
+        if self.window_mode && needs_check {
+            self.tab_checked_at = Some(Instant::now());
+            self.tab_open = self
+                .vim_client
+                .eval_expr("len(filter(gettabinfo(), 'has_key(v:val.variables, \"vim_tutorial\")'))")
+                .is_ok_and(|tabs| tabs != "0");
+        }
+        self.window_mode && !self.tab_open
+    }
+
+    fn reconnect(&mut self) -> Result<()> {
+        let script = self
+            .script_path
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("監視用のスクリプトがありません"))?;
+
+        // ペインのエディタが落ちてシェルに戻っていれば、同じコマンドで起動し直す
+        #[cfg(unix)]
+        if let (Some(pane_id), Some(command)) = (&self.vim_pane_id, &self.launch_command)
+            && !self.pane_runs_editor(pane_id)
+        {
+            debug_log!("ペイン{}でエディタを起動し直します", pane_id);
+            let _ = self.vim_client.remove_socket_file();
+            let output = Command::new("tmux")
+                .args(["send-keys", "-t", pane_id.as_str(), command.as_str(), "Enter"])
+                .output()?;
+            if !output.status.success() {
+                return Err(TutorialError::PaneInjectionFailed {
+                    pane: pane_id.clone(),
+                    stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+                }
+                .into());
+            }
+            return Ok(());
+        }
+
+        if !self.has_server() {
+            return Err(anyhow::anyhow!(
+                "{}に接続する手段がありません（+clientserver が必要です）",
+                self.editor.label()
+            ));
+        }
+        // 動いているエディタには監視用のスクリプトを読み直させる（読み直しても重複しない）
+        self.vim_client.eval_expr("1")?;
+        let path = script.to_string_lossy().replace('\'', "''");
+        if self.in_parent {
+            // 学習者の端末のバッファにキーを送らない
+            return self
+                .vim_client
+                .eval_expr(&format!("execute('source ' . fnameescape('{}'))", path))
+                .map(|_| ());
+        }
+        self.vim_client.send_keys(&format!(
+            "<C-\\><C-N>:execute 'source ' . fnameescape('{}')<CR>",
+            path
+        ))
+    }
+
+    fn take_commands(&mut self) -> Vec<String> {
+        Self::take_lines(&temp_file(COMMAND_FILE))
+            .map(|content| {
+                content
+                    .lines()
+                    .map(|line| line.trim().to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn take_key_log(&mut self) -> Vec<String> {
+        Self::take_lines(&temp_file(KEY_LOG_FILE))
+            .map(|content| content.lines().map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+
+    // tmuxセッションにクライアントが接続しているか（1秒ごとに確認）
+    fn learner_attached(&mut self) -> bool {
+        if self.vim_pane_id.is_none() {
+            return true;
+        }
+        let needs_check = self
+            .attach_checked_at
+            .is_none_or(|checked| checked.elapsed() >= Duration::from_secs(1));
+        if needs_check {
+            self.attach_checked_at = Some(Instant::now());
+            if let Ok(output) = Command::new("tmux")
+                .args([
+                    "display-message",
+                    "-p",
+                    "-t",
+                    SESSION_NAME,
+                    "#{session_attached}",
+                ])
+                .output()
+            {
+                let attached = String::from_utf8_lossy(&output.stdout);
+                self.client_attached = attached.trim().parse::<u32>().unwrap_or(1) > 0;
+            }
+        }
+        self.client_attached
+    }
+
+    fn report_progress(&mut self, snapshot: &ProgressSnapshot) {
+        // 書き込めなくても練習は続ける
+        if let Err(e) = session_progress::write_atomic(Path::new(&temp_file(PROGRESS_FILE)), snapshot) {
+            debug_log!("進捗を書き出せません: {}", e);
+        }
+    }
+
+    fn announce(&mut self, message: &str) {
+        // Neovimがタイマーで読み取って表示する（書き込めなくても練習は続ける）
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(temp_file(NOTICE_FILE)) {
+            let _ = writeln!(file, "{}", message.replace('\n', " "));
+        }
+    }
+
+    fn run_commands(&mut self, commands: &[String]) -> Result<()> {
+        // 知らせと同じく、Neovimがタイマーで読み取って実行する
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(temp_file(GOAL_COMMAND_FILE))?;
+        for command in commands {
+            writeln!(file, "{}", command.replace('\n', " "))?;
+        }
+        Ok(())
+    }
+
+    fn send_editor_keys(&mut self, keys: &str) -> Result<()> {
+        self.vim_client.send_keys(keys)
+    }
+
+    fn pause(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+
+    fn evaluates_expressions(&self) -> bool {
+        self.has_server()
+    }
+
+    fn editor_blocked(&mut self) -> Option<bool> {
+        if self.editor != Editor::Neovim {
+            return None;
+        }
+        self.vim_client.editor_mode(BLOCKED_PROBE_TIMEOUT).ok().map(|mode| mode.blocking)
+    }
+
+    fn count_matches(&mut self, pattern: &str, lines: Option<(usize, usize)>) -> Result<usize> {
+        if !self.has_server() {
+            return Err(anyhow::anyhow!(
+                "{}に式を評価させる手段がありません（+clientserver が必要です）",
+                self.editor.label()
+            ));
+        }
+        let range = match lines {
+            Some((first, last)) => format!("{}, {}", first + 1, last + 1),
+            None => "1, '$'".to_string(),
+        };
+        let count = self.vim_client.eval_expr(&format!(
+            "TutorialCountMatches('{}', {})",
+            pattern.replace('\'', "''"),
+            range
+        ))?;
+        count
+            .parse()
+            .map_err(|_| anyhow::anyhow!("「{}」の数を読めません: {}", pattern, count))
+    }
+
+    fn buffer_lines(&mut self, first: usize, count: usize) -> Result<Vec<String>> {
+        if !self.has_server() || count == 0 {
+            return Err(anyhow::anyhow!("{}に式を評価させる手段がありません", self.editor.label()));
+        }
+        let lines = self
+            .vim_client
+            .eval_expr(&format!("TutorialBufferLines({}, {})", first + 1, first + count))?;
+        serde_json::from_str(&lines).map_err(|e| anyhow::anyhow!("バッファの行を読めません: {}: {}", lines, e))
+    }
+
+    fn syntax_nodes(&mut self, language: Option<&str>) -> Result<Option<Vec<String>>> {
+        if !self.has_server() {
+            return Err(anyhow::anyhow!("{}に式を評価させる手段がありません", self.editor.label()));
+        }
+        // language は英数字と _ だけ（章データの読み込み時に確かめている）
+        let nodes = self
+            .vim_client
+            .eval_expr(&format!("TutorialSyntaxNodes('{}')", language.unwrap_or_default()))?;
+        Ok((nodes != "UNSUPPORTED").then(|| {
+            nodes.split(',').filter(|node| !node.is_empty()).map(str::to_string).collect()
+        }))
+    }
+
+    fn goal_query(&self) -> Option<Box<dyn GoalQuery>> {
+        self.has_server().then(|| {
+            Box::new(VimRpcClient::attach(self.vim_client.server_name().to_string(), self.editor)) as Box<dyn GoalQuery>
+        })
+    }
+
+    fn capture_panes(&mut self) -> Vec<(String, String)> {
+        let panes = [("editor", &self.vim_pane_id), ("instructions", &self.instruction_pane_id)];
+        panes
+            .into_iter()
+            .filter_map(|(name, pane_id)| {
+                let pane_id = pane_id.as_ref()?;
+                let output = Command::new("tmux")
+                    .args(["capture-pane", "-p", "-J", "-S"])
+                    .arg(format!("-{}", CAPTURE_SCROLLBACK_LINES))
+                    .args(["-t", pane_id])
+                    .output()
+                    .ok()?;
+                // 既に閉じたペインは capture-pane が失敗する
+                output
+                    .status
+                    .success()
+                    .then(|| (name.to_string(), String::from_utf8_lossy(&output.stdout).into_owned()))
+            })
+            .collect()
+    }
+}
+
+// tmuxの指示ペイン（内容をファイルに書き、ペインで動かした panel がファイルの変化を見て描き直す。
+// 実行ファイルの場所が分からなければ、更新のたびに cat で描き直す。従来のゲームモードでも使う）
+pub struct TmuxInstructionPane {
+    pane_id: String,
+    path: String,
+    watching: bool, // ペインで panel が動いている
+}
+
+impl TmuxInstructionPane {
+    pub fn new(pane_id: &str) -> Self {
+        Self {
+            pane_id: pane_id.to_string(),
+            path: temp_file(PANE_FILE),
+            watching: false,
+        }
+    }
+}
+
+impl InstructionSink for TmuxInstructionPane {
+    fn show(&mut self, lines: &[String]) -> Result<()> {
+        if self.watching {
+            return write_lines_replacing(&self.path, lines);
+        }
+        let update_command = match panel_command(&self.path) {
+            Some(command) => {
+                write_lines_replacing(&self.path, lines)?;
+                self.watching = true;
+                command
+            }
+            None => pane_command(&self.path, lines)?,
+        };
+
+        // 上部ペインの内容を更新
+        debug_log!("上部ペイン({})を更新: {}", self.pane_id, update_command);
+        let interrupt_result = Command::new("tmux")
+            .args(["send-keys", "-t", &self.pane_id, "C-c"]) // 現在のコマンドを中断
+            .output();
+        debug_log!("中断送信結果: success={}", interrupt_result.as_ref().map(|r| r.status.success()).unwrap_or(false));
+
+        thread::sleep(Duration::from_millis(100));
+
+        let update_result = Command::new("tmux")
+            .args(["send-keys", "-t", &self.pane_id, &update_command, "Enter"])
+            .output();
+        debug_log!("更新送信結果: success={}", update_result.as_ref().map(|r| r.status.success()).unwrap_or(false));
+
+        Ok(())
+    }
+
+    fn live(&self) -> bool {
+        self.watching
+    }
+}
+
+// エディタ内の指示ウィンドウ（ファイルに書き、エディタがタイマーで読み取って表示する）
+pub struct WindowInstructions {
+    path: String,
+}
+
+impl InstructionSink for WindowInstructions {
+    fn show(&mut self, lines: &[String]) -> Result<()> {
+        write_lines_replacing(&self.path, lines)
+    }
+
+    fn live(&self) -> bool {
+        true
+    }
+}
+
+// 状態の読み取り結果
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateRead {
+    Fresh(VimState),
+    // 最後に届いてから STALE_STATUS_AFTER 以上新しい行が無い（エディタが落ちた場合など）
+    Stale { state: VimState, age: Duration },
+    // まだ一度も届いていない（エディタの起動中など）
+    Unavailable,
+}
+
+// 状態ファイルを追記された分だけ読む。改行まで届いていない行は書き込み途中とみなして次回に回し、
+// 番号が戻った行は捨てる（読み取りと書き込みが重なっても既定の状態を返さない）
+pub struct StatusReader {
+    path: String,
+    offset: u64,
+    last: Option<(u32, u64)>, // 最後に使った行の (pid, seq)
+    latest: Option<VimState>,
+    received_at: Option<Instant>, // 最後に新しい行を読めた時刻
+}
+
+impl StatusReader {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            offset: 0,
+            last: None,
+            latest: None,
+            received_at: None,
+        }
+    }
+
+    // 届いた中で最新の状態と、それが新しいかどうか
+    pub fn poll(&mut self) -> Result<StateRead> {
+        self.read_new_lines()?;
+        let (Some(state), Some(received_at)) = (&self.latest, self.received_at) else {
+            return Ok(StateRead::Unavailable);
+        };
+        let age = received_at.elapsed();
+        if age > STALE_STATUS_AFTER {
+            Ok(StateRead::Stale {
+                state: state.clone(),
+                age,
+            })
+        } else {
+            Ok(StateRead::Fresh(state.clone()))
+        }
+    }
+
+    fn read_new_lines(&mut self) -> Result<()> {
+        let path = self.path.clone();
+        let unreadable = |e: std::io::Error| TutorialError::StatusFileUnreadable {
+            path: path.clone(),
+            message: e.to_string(),
+        };
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(unreadable(e).into()),
+        };
+        // エディタは大きくなったファイルを最新の1行で書き直す
+        if file.metadata().map_err(unreadable)?.len() < self.offset {
+            debug_log!("状態ファイルが書き直されたため先頭から読みます");
+            self.offset = 0;
+        }
+        file.seek(SeekFrom::Start(self.offset)).map_err(unreadable)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes).map_err(unreadable)?;
+        let complete = bytes.iter().rposition(|&b| b == b'\n').map_or(0, |pos| pos + 1);
+        self.offset += complete as u64;
+
+        for line in String::from_utf8_lossy(&bytes[..complete]).lines() {
+            match parse_status_record(line) {
+                Some(record) => self.accept(record),
+                None => {
+                    debug_log!("状態ファイルの行を読めません: {}", line);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn accept(&mut self, record: StatusRecord) {
+        if let Some((pid, seq)) = self.last
+            && pid == record.pid
+            && record.seq <= seq
+        {
+            debug_log!("番号が戻った行を捨てます: {} <= {}", record.seq, seq);
+            return;
+        }
+        self.last = Some((record.pid, record.seq));
+        self.latest = Some(record.state);
+        self.received_at = Some(Instant::now());
+    }
+}
+
+// 書きかけの内容を読ませないよう、別名で書いてから置き換える
+fn write_lines_replacing(path: &str, lines: &[String]) -> Result<()> {
+    let temp = format!("{}.tmp", path);
+    fs::write(&temp, lines.join("\n") + "\n")?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+// シェルの単一引用符で囲む（中の ' は '\'' にする。$ や ` や改行もそのまま渡る）
+pub fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+// ペインで panel を動かすシェルのコマンド（この実行ファイルの場所が分からなければ None）
+fn panel_command(path: &str) -> Option<String> {
+    let exe = std::env::current_exe().ok()?;
+    Some(format!(
+        "clear; {} panel {}",
+        shell_quote(&exe.to_string_lossy()),
+        shell_quote(path)
+    ))
+}
+
+// 指示の行をファイルに書き、ペインでそれを表示するシェルのコマンドを返す（先に画面を消す）
+// 練習の文章はコマンドに入れないため、引用符や $() を含んでもそのまま表示される
+pub fn pane_command(path: &str, lines: &[String]) -> Result<String> {
+    write_lines_replacing(path, lines)?;
+    Ok(format!("clear; cat {}", shell_quote(path)))
+}
+
+// テスト用: 決められた状態を順に返し、表示や送ったキーを記録する
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    // 1回のポーリングで起きること
+    #[derive(Debug, Clone, Default)]
+    pub struct MockStep {
+        pub state: Option<VimState>, // None は状態ファイルがまだ無い
+        pub commands: Vec<String>,
+        pub key_log: Vec<String>,
+        pub detached: bool, // 学習者がtmuxから離れている
+        pub disconnected: bool, // 状態ファイルがしばらく更新されていない（state と合わせて使う）
+        pub blocking: bool,     // エディタが入力待ちで止まっている（nvim_get_mode() の blocking）
+    }
+
+    impl MockStep {
+        pub fn state(state: VimState) -> Self {
+            Self {
+                state: Some(state),
+                ..Default::default()
+            }
+        }
+
+        pub fn with_command(mut self, command: &str) -> Self {
+            self.commands.push(command.to_string());
+            self
+        }
+
+        // 数えるだけの入力（x を keystrokes 回）
+        pub fn with_keystrokes(mut self, keystrokes: usize) -> Self {
+            self.key_log.extend((0..keystrokes).map(|_| "n\tx".to_string()));
+            self
+        }
+
+        // キーログの行（"モード\tキー列"）
+        pub fn with_key_log(mut self, lines: &[&str]) -> Self {
+            self.key_log.extend(lines.iter().map(|line| line.to_string()));
+            self
+        }
+
+        pub fn detached(mut self) -> Self {
+            self.detached = true;
+            self
+        }
+
+        pub fn disconnected(mut self) -> Self {
+            self.disconnected = true;
+            self
+        }
+
+        pub fn blocking(mut self) -> Self {
+            self.blocking = true;
+            self
+        }
+    }
+
+    #[derive(Debug, Default)]
+    pub struct MockRecord {
+        pub started: usize,
+        pub stopped: usize,
+        pub kept: usize,
+        pub instructions: Vec<Vec<String>>, // 初回表示を含む、指示の表示の履歴
+        pub sent_keys: Vec<Vec<String>>,
+        pub progress: Vec<ProgressSnapshot>,
+        pub announcements: Vec<String>,
+        pub reconnects: usize,
+        pub reattached: usize,
+        pub commands_run: Vec<Vec<String>>,
+        pub editor_keys: Vec<String>,
+        pub buffer_reads: usize, // buffer_lines で行を問い合わせた回数
+    }
+
+    impl MockRecord {
+        // 書き出された進み具合の、現在の目標の番号（1始まり。全て達成したら "completed"）の移り変わり
+        pub fn goal_marks(&self) -> Vec<String> {
+            let mut marks: Vec<String> = self
+                .progress
+                .iter()
+                .map(|snapshot| snapshot.current_goal_index.map_or("completed".to_string(), |i| (i + 1).to_string()))
+                .collect();
+            marks.dedup();
+            marks
+        }
+    }
+
+    pub struct MockBackend {
+        steps: VecDeque<MockStep>,
+        pending_commands: Vec<String>,
+        pending_key_log: Vec<String>,
+        last_buffer: Vec<String>,
+        attached: bool,
+        editor: Editor,
+        live_pane: bool,
+        shared: bool,
+        captures: Vec<(String, String)>,
+        syntax_tree: Option<Vec<(usize, usize, String)>>,
+        last_cursor_line: usize,
+        blocking: bool,
+        record: Rc<RefCell<MockRecord>>,
+    }
+
+    impl MockBackend {
+        pub fn new(steps: Vec<MockStep>) -> Self {
+            Self {
+                steps: steps.into(),
+                pending_commands: Vec::new(),
+                pending_key_log: Vec::new(),
+                last_buffer: Vec::new(),
+                attached: true,
+                editor: Editor::Neovim,
+                live_pane: false,
+                shared: false,
+                captures: Vec::new(),
+                syntax_tree: None,
+                last_cursor_line: 0,
+                blocking: false,
+                record: Rc::new(RefCell::new(MockRecord::default())),
+            }
+        }
+
+        // Vim の練習として振る舞う（キー入力を記録しない）
+        pub fn with_editor(mut self, editor: Editor) -> Self {
+            self.editor = editor;
+            self
+        }
+
+        // 頻繁に表示し直せる指示の表示先を返す（カーソル位置の表示を確かめる）
+        pub fn with_live_pane(mut self) -> Self {
+            self.live_pane = true;
+            self
+        }
+
+        // 学習者の Neovim で練習する場合のスクリプトを書かせる
+        pub fn sharing_editor(mut self) -> Self {
+            self.shared = true;
+            self
+        }
+
+        // capture_panes で返すペインの内容
+        pub fn with_captures(mut self, captures: &[(&str, &str)]) -> Self {
+            self.captures = captures.iter().map(|(name, text)| (name.to_string(), text.to_string())).collect();
+            self
+        }
+
+        // syntax_nodes で返す treesitter のノード（最初の行・最後の行・種類を外側から。None ならパーサーが無い）
+        pub fn with_syntax_tree(mut self, tree: Option<&[(usize, usize, &str)]>) -> Self {
+            self.syntax_tree = tree.map(|tree| {
+                tree.iter().map(|(first, last, node)| (*first, *last, node.to_string())).collect()
+            });
+            self
+        }
+
+        pub fn from_states(states: Vec<VimState>) -> Self {
+            Self::new(states.into_iter().map(MockStep::state).collect())
+        }
+
+        // セッションに渡した後も記録を読めるようにする
+        pub fn record(&self) -> Rc<RefCell<MockRecord>> {
+            Rc::clone(&self.record)
+        }
+    }
+
+    struct RecordingSink {
+        record: Rc<RefCell<MockRecord>>,
+        live: bool,
+    }
+
+    impl InstructionSink for RecordingSink {
+        fn show(&mut self, lines: &[String]) -> Result<()> {
+            self.record.borrow_mut().instructions.push(lines.to_vec());
+            Ok(())
+        }
+
+        fn live(&self) -> bool {
+            self.live
+        }
+    }
+
+    impl VimBackend for MockBackend {
+        fn editor(&self) -> Editor {
+            self.editor
+        }
+
+        fn shares_editor(&self) -> bool {
+            self.shared
+        }
+
+        fn terminal_size(&self) -> Option<TerminalSize> {
+            None
+        }
+
+        fn start(&mut self, request: &LaunchRequest) -> Result<Option<Box<dyn InstructionSink>>> {
+            let mut record = self.record.borrow_mut();
+            record.started += 1;
+            record.instructions.push(request.instructions.to_vec());
+            Ok(Some(Box::new(RecordingSink {
+                record: Rc::clone(&self.record),
+                live: self.live_pane,
+            })))
+        }
+
+        fn stop(&mut self) -> Result<()> {
+            self.record.borrow_mut().stopped += 1;
+            Ok(())
+        }
+
+        fn keep_running(&mut self) {
+            self.record.borrow_mut().kept += 1;
+        }
+
+        fn running_session(&self) -> Option<RunningSession> {
+            Some(RunningSession {
+                session_name: SESSION_NAME.to_string(),
+                editor: self.editor.program().to_string(),
+                server: "/tmp/mock.sock".to_string(),
+                editor_pane: "%1".to_string(),
+                instruction_pane: "%0".to_string(),
+            })
+        }
+
+        fn reattach(
+            &mut self,
+            _running: &RunningSession,
+            request: &LaunchRequest,
+        ) -> Result<Option<Box<dyn InstructionSink>>> {
+            let mut record = self.record.borrow_mut();
+            record.reattached += 1;
+            record.instructions.push(request.instructions.to_vec());
+            Ok(Some(Box::new(RecordingSink {
+                record: Rc::clone(&self.record),
+                live: self.live_pane,
+            })))
+        }
+
+        // 状態を使い切ったら監視ループを終わらせるためにエラーにする
+        fn get_state(&mut self) -> Result<StateRead> {
+            let step = self
+                .steps
+                .pop_front()
+                .ok_or_else(|| anyhow::anyhow!("MockBackend: 再生する状態がありません"))?;
+            self.pending_commands.extend(step.commands);
+            self.pending_key_log.extend(step.key_log);
+            self.attached = !step.detached;
+            self.blocking = step.blocking;
+            if let Some(state) = &step.state {
+                self.last_buffer = state.buffer_content.clone();
+                self.last_cursor_line = state.cursor_line;
+            }
+            Ok(match step.state {
+                Some(state) if step.disconnected => StateRead::Stale {
+                    state,
+                    age: Duration::from_secs(5),
+                },
+                Some(state) => StateRead::Fresh(state),
+                None => StateRead::Unavailable,
+            })
+        }
+
+        fn send_keys(&mut self, keys: &[&str]) -> Result<()> {
+            self.record
+                .borrow_mut()
+                .sent_keys
+                .push(keys.iter().map(|key| key.to_string()).collect());
+            Ok(())
+        }
+
+        fn editor_exited(&mut self) -> bool {
+            false
+        }
+
+        fn reconnect(&mut self) -> Result<()> {
+            self.record.borrow_mut().reconnects += 1;
+            Err(anyhow::anyhow!("MockBackend: ソケットがありません"))
+        }
+
+        fn take_commands(&mut self) -> Vec<String> {
+            std::mem::take(&mut self.pending_commands)
+        }
+
+        fn take_key_log(&mut self) -> Vec<String> {
+            std::mem::take(&mut self.pending_key_log)
+        }
+
+        fn learner_attached(&mut self) -> bool {
+            self.attached
+        }
+
+        fn report_progress(&mut self, snapshot: &ProgressSnapshot) {
+            self.record.borrow_mut().progress.push(snapshot.clone());
+        }
+
+        fn announce(&mut self, message: &str) {
+            self.record.borrow_mut().announcements.push(message.to_string());
+        }
+
+        // エディタと同じく、次に状態を読んだときに実行し終えたことを知らせる
+        fn run_commands(&mut self, commands: &[String]) -> Result<()> {
+            self.record.borrow_mut().commands_run.push(commands.to_vec());
+            self.pending_commands.push("GOAL_COMMANDS_DONE".to_string());
+            Ok(())
+        }
+
+        fn send_editor_keys(&mut self, keys: &str) -> Result<()> {
+            self.record.borrow_mut().editor_keys.push(keys.to_string());
+            Ok(())
+        }
+
+        fn pause(&self, _duration: Duration) {}
+
+        fn evaluates_expressions(&self) -> bool {
+            true
+        }
+
+        fn editor_blocked(&mut self) -> Option<bool> {
+            Some(self.blocking)
+        }
+
+        // 最後に返した状態のバッファで、pattern を文字どおりに数える
+        fn count_matches(&mut self, pattern: &str, lines: Option<(usize, usize)>) -> Result<usize> {
+            let (first, last) = lines.unwrap_or((0, usize::MAX));
+            Ok(self
+                .last_buffer
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| (first..=last).contains(i))
+                .map(|(_, line)| line.matches(pattern).count())
+                .sum())
+        }
+
+        fn buffer_lines(&mut self, first: usize, count: usize) -> Result<Vec<String>> {
+            self.record.borrow_mut().buffer_reads += 1;
+            Ok(self.last_buffer.iter().skip(first).take(count).cloned().collect())
+        }
+
+        fn syntax_nodes(&mut self, _language: Option<&str>) -> Result<Option<Vec<String>>> {
+            let line = self.last_cursor_line;
+            Ok(self.syntax_tree.as_ref().map(|tree| {
+                tree.iter()
+                    .rev()
+                    .filter(|(first, last, _)| (*first..=*last).contains(&line))
+                    .map(|(_, _, node)| node.clone())
+                    .collect()
+            }))
+        }
+
+        fn capture_panes(&mut self) -> Vec<(String, String)> {
+            // stop の後のペインは閉じている
+            let record = self.record.borrow();
+            if record.stopped >= record.started { Vec::new() } else { self.captures.clone() }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::{MockBackend, MockStep};
+    use super::*;
+    use crate::keystrokes;
+    use crate::vim_state::VimMode;
+    use tempfile::tempdir;
+
+    fn fresh_state(reader: &mut StatusReader) -> Result<VimState> {
+        match reader.poll()? {
+            StateRead::Fresh(state) => Ok(state),
+            other => Err(anyhow::anyhow!("新しい状態がありません: {:?}", other)),
+        }
+    }
+
+    #[test]
+    fn test_status_file_includes_change_tracking() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let status_file = tmp_dir.path().join("status.json");
+        let mut reader = StatusReader::new(status_file.to_string_lossy().into_owned());
+        assert_eq!(reader.poll()?, StateRead::Unavailable);
+
+        fs::write(&status_file, "SEQ:7:1\tLINE:2,COL:4,MODE:i,DETAILED:i,TICK:17,MOD:1\tREGS:{}\n")?;
+        let state = fresh_state(&mut reader)?;
+        assert_eq!((state.cursor_line, state.cursor_col), (1, 3));
+        assert_eq!(state.changedtick, 17);
+        assert!(state.modified);
+
+        // 古い形式の項目（DETAILED などが無い）でも読める
+        let mut file = OpenOptions::new().append(true).open(&status_file)?;
+        writeln!(file, "SEQ:7:2\tLINE:1,COL:1,MODE:n")?;
+        let state = fresh_state(&mut reader)?;
+        assert_eq!(state.changedtick, 0);
+        assert!(!state.modified);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_status_reader_ignores_torn_and_stale_lines() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let status_file = tmp_dir.path().join("status.json");
+        let mut reader = StatusReader::new(status_file.to_string_lossy().into_owned());
+
+        // 2行目は書き込み途中（改行がまだ無い）
+        fs::write(&status_file, "SEQ:7:1\tLINE:3,COL:5,MODE:n\nSEQ:7:2\tLINE:1,CO")?;
+        let state = fresh_state(&mut reader)?;
+        assert_eq!((state.cursor_line, state.cursor_col), (2, 4));
+
+        // 残りが届いてから使う。番号が戻った行は捨てる
+        let mut file = OpenOptions::new().append(true).open(&status_file)?;
+        write!(file, "L:2,MODE:i\nSEQ:7:1\tLINE:9,COL:9,MODE:n\n")?;
+        let state = fresh_state(&mut reader)?;
+        assert_eq!((state.cursor_line, state.cursor_col), (0, 1));
+        assert_eq!(state.mode, VimMode::Insert);
+
+        // 書き直された（短くなった）ファイルは先頭から読み、起動し直したエディタの番号は数え直す
+        fs::write(&status_file, "SEQ:8:1\tLINE:2,COL:1,MODE:n\n")?;
+        let state = fresh_state(&mut reader)?;
+        assert_eq!((state.cursor_line, state.cursor_col), (1, 0));
+
+        // 書き直しの途中で空になっていても、直前の状態のまま
+        fs::write(&status_file, "")?;
+        assert_eq!(fresh_state(&mut reader)?.cursor_line, 1);
+        Ok(())
+    }
+
+    // 学習者が書いた練習の文章に入りうる、シェルで意味を持つ文字
+    fn adversarial_lines() -> Vec<String> {
+        [
+            "It's",
+            "\"quoted\" hint",
+            "$(touch /tmp/vim_tutorial_pwned) and `date`",
+            "$HOME \\n \\",
+            "1行目\n2行目",
+            "=== 🎯 絵文字 ✨ ===",
+            "-e",
+            "",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shell_quote_passes_text_unchanged() -> Result<()> {
+        for text in adversarial_lines() {
+            let output = Command::new("sh")
+                .args(["-c", &format!("printf %s {}", shell_quote(&text))])
+                .output()?;
+            assert_eq!(String::from_utf8(output.stdout)?, text);
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pane_command_shows_lines_exactly() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let path = tmp_dir.path().join("it's pane.txt").to_string_lossy().into_owned();
+        let lines = adversarial_lines();
+        let command = pane_command(&path, &lines)?;
+        // 練習の文章はコマンドに入らない
+        assert_eq!(command, format!("clear; cat {}", shell_quote(&path)));
+
+        let output = Command::new("sh")
+            .args(["-c", command.trim_start_matches("clear; ")])
+            .output()?;
+        assert_eq!(String::from_utf8(output.stdout)?, lines.join("\n") + "\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_instructions_replace_file() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let path = tmp_dir.path().join("instructions.txt");
+        let mut window = WindowInstructions {
+            path: path.to_string_lossy().into_owned(),
+        };
+        window.show(&["=== 🎯 基本移動 ===".to_string(), "jで下へ".to_string()])?;
+        window.show(&["=== 🎯 基本移動 ===".to_string()])?;
+        assert_eq!(fs::read_to_string(&path)?, "=== 🎯 基本移動 ===\n");
+        // 書きかけ用のファイルは残さない
+        assert_eq!(fs::read_dir(tmp_dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mock_backend_replays_steps() -> Result<()> {
+        let state = VimState {
+            mode: VimMode::Insert,
+            buffer_content: vec![String::new()],
+            ..Default::default()
+        };
+        let mut backend = MockBackend::new(vec![
+            MockStep::default().with_command("HINT_REQUEST"),
+            MockStep::state(state).with_keystrokes(2),
+        ]);
+        assert_eq!(backend.get_state()?, StateRead::Unavailable);
+        assert_eq!(backend.take_commands(), vec!["HINT_REQUEST".to_string()]);
+        assert!(backend.take_commands().is_empty());
+        assert!(matches!(backend.get_state()?, StateRead::Fresh(state) if state.mode == VimMode::Insert));
+        let key_log = backend.take_key_log();
+        assert_eq!(key_log.iter().map(|line| keystrokes::count_log_line(line)).sum::<usize>(), 2);
+        assert!(backend.get_state().is_err());
+        Ok(())
+    }
+}
@@ -1,31 +1,125 @@
-use crate::vim_state::{VimMode, VimState};
+use crate::editor::Editor;
+use crate::error::TutorialError;
+use crate::vim_state::{DEFAULT_REGISTERS, RegisterType, VimMode, VimState};
 use anyhow::{Result, anyhow};
 use std::collections::HashMap;
 use std::path::Path;
-use std::process::Command;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
 
+// :qa! を送ってから終了を待つ時間（過ぎたら kill する）
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
+// --socket-path で指定された接続先
+static ADDRESS: OnceLock<String> = OnceLock::new();
+
+pub fn set_address(address: String) {
+    let _ = ADDRESS.set(address);
+}
+
+/// 練習用のエディタの接続先（--socket-path の指定が無ければこのプロセスの [`default_address`]）
+pub fn session_address() -> String {
+    ADDRESS.get().cloned().unwrap_or_else(|| default_address(std::process::id()))
+}
+
+// 連続学習モードの --listen 先（Unix ではソケットファイル、Windows では名前付きパイプ）
+pub fn default_address(pid: u32) -> String {
+    if cfg!(windows) {
+        format!(r"\\.\pipe\vim_tutorial_continuous_{}", pid)
+    } else {
+        crate::paths::cache_file(&format!("vim_tutorial_continuous_{}.sock", pid))
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Neovim の `nvim_get_mode()` の結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorMode {
+    pub mode: String,
+    // 入力を待って止まっている（スワップファイルの確認・hit-enter の表示など）
+    pub blocking: bool,
+}
+
+// Neovim は --server <ソケット>、Vim は --servername <サーバー名>（+clientserver）で接続する
 pub struct VimRpcClient {
-    socket_path: String,
-    nvim_process_id: Option<u32>,
+    socket_path: String, // Vim ではサーバー名
+    editor: Editor,
+    nvim_process: Option<Child>, // kill コマンドを使わずに終了できるよう、起動したプロセスを持つ
+    with_server: bool,           // 起動したエディタに --remote-send で終了を伝えられる
+    attached: bool,              // 学習者が起動したエディタに接続している（ソケットは消さず、終了も頼まない）
 }
 
 impl VimRpcClient {
-    pub fn new(socket_path: String) -> Self {
+    /// `socket_path` で待ち受ける `editor` と通信するクライアント
+    pub fn new(socket_path: String, editor: Editor) -> Self {
         Self {
             socket_path,
-            nvim_process_id: None,
+            editor,
+            nvim_process: None,
+            with_server: false,
+            attached: false,
         }
     }
 
-    pub fn start_neovim(&mut self, file_path: &str, script_path: Option<&str>) -> Result<()> {
-        // 既存のソケットファイルを削除
-        if Path::new(&self.socket_path).exists() {
+    /// 学習者がすでに使っている `editor`（Neovim の `$NVIM` など）に接続するクライアント。
+    /// 停止してもエディタは終了させず、ソケットも残す
+    pub fn attach(socket_path: String, editor: Editor) -> Self {
+        let mut client = Self::new(socket_path, editor);
+        client.attached = true;
+        client
+    }
+
+    /// 起動したエディタをこのクライアントから止めない（ソケットも消さない）ようにする
+    pub fn detach(&mut self) {
+        self.nvim_process = None;
+        self.with_server = false;
+        self.attached = true;
+    }
+
+    pub fn server_name(&self) -> &str {
+        &self.socket_path
+    }
+
+    fn remote_command(&self) -> Command {
+        let mut cmd = Command::new(self.editor.program());
+        match self.editor {
+            Editor::Neovim => cmd.args(["--server", &self.socket_path]),
+            Editor::Vim => cmd.args(["--servername", &self.socket_path]),
+        };
+        cmd
+    }
+
+    // 名前付きパイプはファイルとして見えないため、Windows では式の評価で確かめる
+    fn server_ready(&self) -> bool {
+        if cfg!(windows) {
+            self.eval_expr("1").is_ok()
+        } else {
+            Path::new(&self.socket_path).exists()
+        }
+    }
+
+    pub fn remove_socket_file(&self) -> Result<()> {
+        if !cfg!(windows) && !self.attached && Path::new(&self.socket_path).exists() {
             std::fs::remove_file(&self.socket_path)?;
         }
+        Ok(())
+    }
+
+    pub fn start_neovim(&mut self, file_path: &str, script_path: Option<&str>) -> Result<()> {
+        // Vim には --headless が無く、端末なしでは起動できない
+        if self.editor == Editor::Vim {
+            return Err(anyhow!("Vim は端末なしで起動できません（端末なしの起動は Neovim のみ）"));
+        }
+
+        // 既存のソケットファイルを削除
+        self.remove_socket_file()?;
 
         // Neovimを起動
         let mut cmd = Command::new("nvim");
-        cmd.arg("--headless").arg("--listen").arg(&self.socket_path);
+        cmd.arg("--headless").arg("-n").arg("--listen").arg(&self.socket_path);
 
         if let Some(script) = script_path {
             cmd.arg("-S").arg(script);
@@ -33,27 +127,72 @@ impl VimRpcClient {
 
         cmd.arg(file_path);
 
-        let child = cmd.spawn()?;
-        self.nvim_process_id = Some(child.id());
+        self.nvim_process = Some(cmd.spawn()?);
+        self.with_server = true;
 
         // Neovimが起動するまで待機
         let mut retries = 10;
-        while retries > 0 && !Path::new(&self.socket_path).exists() {
+        while retries > 0 && !self.server_ready() {
             std::thread::sleep(std::time::Duration::from_millis(100));
             retries -= 1;
         }
 
-        if !Path::new(&self.socket_path).exists() {
-            return Err(anyhow!("Failed to start Neovim: socket not created"));
+        if !self.server_ready() {
+            return Err(TutorialError::SocketTimeout {
+                socket: self.socket_path.clone(),
+                waited: Duration::from_millis(100 * 10),
+            }
+            .into());
         }
 
         Ok(())
     }
 
+    // 現在の端末でエディタを起動する（tmux が無い場合）。with_server なら外から終了を伝えられる
+    pub fn start_interactive(&mut self, file_path: &str, script_path: &Path, with_server: bool) -> Result<()> {
+        self.remove_socket_file()?;
+        let mut cmd = Command::new(self.editor.program());
+        // スワップファイルを使わない（残ったスワップファイルの確認で入力待ちにならないように）
+        cmd.arg("-n");
+        if with_server {
+            match self.editor {
+                Editor::Neovim => cmd.arg("--listen").arg(&self.socket_path),
+                Editor::Vim => cmd.arg("--servername").arg(&self.socket_path),
+            };
+        }
+        cmd.arg("-S").arg(script_path).arg(file_path);
+        self.nvim_process = Some(
+            cmd.spawn()
+                .map_err(|e| anyhow!("{} を起動できません: {}", self.editor.program(), e))?,
+        );
+        self.with_server = with_server;
+        Ok(())
+    }
+
+    // 接続したエディタの新しいタブでファイルを開き、監視用のスクリプトを読ませる
+    // （練習用のタブには t:vim_tutorial の印を付け、終了時にそのタブだけを閉じる）
+    pub fn open_in_new_tab(&self, file_path: &str, script_path: &Path) -> Result<()> {
+        let quote = |text: &str| text.replace('\'', "''");
+        self.eval_expr(&format!(
+            "execute(['noswapfile tabnew ' . fnameescape('{}'), 'let t:vim_tutorial = 1', 'source ' . fnameescape('{}')])",
+            quote(file_path),
+            quote(&script_path.to_string_lossy())
+        ))?;
+        Ok(())
+    }
+
+    // 起動したエディタがまだ動いているか（学習者が :q で閉じると false）
+    pub fn is_running(&mut self) -> bool {
+        self.nvim_process
+            .as_mut()
+            .is_some_and(|child| matches!(child.try_wait(), Ok(None)))
+    }
+
     #[allow(unused)]
     pub fn send_keys(&self, keys: &str) -> Result<()> {
-        let output = Command::new("nvim")
-            .args(["--server", &self.socket_path, "--remote-send", keys])
+        let output = self
+            .remote_command()
+            .args(["--remote-send", keys])
             .output()?;
 
         if !output.status.success() {
@@ -69,11 +208,18 @@ impl VimRpcClient {
 
     #[allow(unused)]
     pub fn get_current_state(&self) -> Result<VimState> {
+        let registers: Vec<String> = DEFAULT_REGISTERS.iter().map(|r| r.to_string()).collect();
+        self.get_state_with_registers(&registers)
+    }
+
+    // 指定したレジスタも含めて現在の状態を取得する（練習の目標から必要なレジスタを決める）
+    pub fn get_state_with_registers(&self, register_names: &[String]) -> Result<VimState> {
         // 複数の情報を並行して取得
         let mode = self.eval_expr("mode()")?;
         let mode_detailed = self.eval_expr("mode(1)")?;
         let line = self.eval_expr("line('.')")?.parse::<usize>().unwrap_or(1);
-        let col = self.eval_expr("col('.')")?.parse::<usize>().unwrap_or(1);
+        // 列は章データと同じ文字単位
+        let col = self.eval_expr(crate::status_line::CHAR_COL_EXPR)?.parse::<usize>().unwrap_or(1);
 
         // オペレーターの取得
         let operator = match self.eval_expr("exists('v:operator') ? v:operator : ''") {
@@ -88,13 +234,35 @@ impl VimRpcClient {
             .map(|s| s.to_string())
             .collect();
 
+        // 変更の検出用
+        let changedtick = self
+            .eval_expr("b:changedtick")?
+            .parse::<u64>()
+            .unwrap_or(0);
+        let modified = self.eval_expr("&modified")? == "1";
+        let buffer_name = self.eval_expr("expand('%:t')")?;
+        // 状態ファイルの WINS/WIN と同じく、指示ウィンドウは数えない
+        let window_count = self
+            .eval_expr(&crate::status_line::window_count_expr("winnr('$')"))?
+            .parse::<usize>()
+            .unwrap_or(1);
+        let current_window = self
+            .eval_expr(&crate::status_line::window_count_expr("winnr()"))?
+            .parse::<usize>()
+            .unwrap_or(1);
+        let undo_seq = self.eval_expr("undotree().seq_cur")?.parse::<u64>().unwrap_or(0);
+
         // レジスタ情報の取得
         let mut registers = HashMap::new();
-        for reg in &["\"", "0", "1", "a", "b", "c"] {
+        let mut register_types = HashMap::new();
+        for reg in register_names {
             if let Ok(content) = self.eval_expr(&format!("@{}", reg))
                 && !content.is_empty()
             {
                 registers.insert(reg.to_string(), content);
+                if let Ok(regtype) = self.eval_expr(&format!("getregtype('{}')", reg)) {
+                    register_types.insert(reg.to_string(), RegisterType::from_vim(&regtype));
+                }
             }
         }
 
@@ -107,13 +275,22 @@ impl VimRpcClient {
             operator,
             buffer_content,
             registers,
+            changedtick,
+            modified,
+            register_types,
+            buffer_name,
+            window_count,
+            current_window,
+            undo_seq,
+            ..Default::default()
         })
     }
 
     #[allow(unused)]
     pub fn eval_expr(&self, expr: &str) -> Result<String> {
-        let output = Command::new("nvim")
-            .args(["--server", &self.socket_path, "--remote-expr", expr])
+        let output = self
+            .remote_command()
+            .args(["--remote-expr", expr])
             .output()?;
 
         if !output.status.success() {
@@ -128,24 +305,63 @@ impl VimRpcClient {
         Ok(result.trim().to_string())
     }
 
-    pub fn stop(&mut self) -> Result<()> {
-        if let Some(pid) = self.nvim_process_id {
-            // プロセスを終了
-            if let Ok(mut child) = std::process::Command::new("kill")
-                .arg(pid.to_string())
-                .spawn()
-            {
+    // nvim_get_mode() を問い合わせる。入力待ちのエディタは式を評価できず --remote-expr が返らないため、
+    // timeout までに返らなければ blocking として扱う（Neovim のみ）
+    pub fn editor_mode(&self, timeout: Duration) -> Result<EditorMode> {
+        if self.editor != Editor::Neovim {
+            return Err(anyhow!("nvim_get_mode() は Neovim でのみ使えます"));
+        }
+        let mut child = self
+            .remote_command()
+            .args(["--remote-expr", "json_encode(nvim_get_mode())"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let started = Instant::now();
+        while child.try_wait()?.is_none() {
+            if started.elapsed() >= timeout {
+                let _ = child.kill();
                 let _ = child.wait();
+                return Ok(EditorMode { mode: String::new(), blocking: true });
             }
-            self.nvim_process_id = None;
+            thread::sleep(Duration::from_millis(20));
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(anyhow!("nvim_get_mode() を問い合わせられません（{}）", self.socket_path));
         }
+        let mode: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        Ok(EditorMode {
+            mode: mode["mode"].as_str().unwrap_or_default().to_string(),
+            blocking: mode["blocking"].as_bool().unwrap_or(false),
+        })
+    }
+
+    // :qa! で終了を頼み、終わらなければ kill する。終了を待って回収した終了状態を返す
+    pub fn stop(&mut self) -> Result<Option<ExitStatus>> {
+        let status = match self.nvim_process.take() {
+            Some(mut child) => {
+                if self.with_server && matches!(child.try_wait(), Ok(None)) {
+                    let _ = self.send_keys("<C-\\><C-N>:qa!<CR>");
+                }
+                let started = Instant::now();
+                while matches!(child.try_wait(), Ok(None)) && started.elapsed() < STOP_TIMEOUT {
+                    thread::sleep(Duration::from_millis(20));
+                }
+                // Windows でも使えるよう kill コマンドではなく Child::kill
+                if matches!(child.try_wait(), Ok(None)) {
+                    let _ = child.kill();
+                }
+                Some(child.wait()?)
+            }
+            None => None,
+        };
+        self.with_server = false;
 
         // ソケットファイルをクリーンアップ
-        if Path::new(&self.socket_path).exists() {
-            let _ = std::fs::remove_file(&self.socket_path);
-        }
+        self.remove_socket_file()?;
 
-        Ok(())
+        Ok(status)
     }
 }
 
@@ -167,13 +383,13 @@ mod tests {
             .join("nvim_test.sock")
             .to_string_lossy()
             .to_string();
-        (VimRpcClient::new(socket_path), tmp_dir)
+        (VimRpcClient::new(socket_path, Editor::Neovim), tmp_dir)
     }
 
     #[test]
     fn test_vim_rpc_client_creation() {
         let (client, _tmp_dir) = create_test_client();
-        assert!(client.nvim_process_id.is_none());
+        assert!(client.nvim_process.is_none());
     }
 
     #[test]
@@ -186,7 +402,7 @@ mod tests {
 
         // Neovimを起動
         client.start_neovim(test_file.to_str().unwrap(), None)?;
-        assert!(client.nvim_process_id.is_some());
+        assert!(client.nvim_process.is_some());
 
         // 少し待ってから状態を確認
         std::thread::sleep(std::time::Duration::from_millis(500));
@@ -197,8 +413,53 @@ mod tests {
 
         // 停止
         client.stop()?;
-        assert!(client.nvim_process_id.is_none());
+        assert!(client.nvim_process.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeated_start_and_stop_reaps_neovim() -> Result<()> {
+        let (mut client, tmp_dir) = create_test_client();
+        let test_file = tmp_dir.path().join("test.txt");
+        std::fs::write(&test_file, "hello world")?;
+
+        for _ in 0..3 {
+            client.start_neovim(test_file.to_str().unwrap(), None)?;
+            assert!(client.is_running());
+            // 終了状態を回収できていればゾンビは残らない
+            assert!(client.stop()?.is_some());
+            assert!(!client.is_running());
+            assert!(!Path::new(client.server_name()).exists());
+        }
+        // 起動していなければ回収するものは無い
+        assert!(client.stop()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_starts_cleanly_when_a_swap_file_exists() -> Result<()> {
+        let (mut client, tmp_dir) = create_test_client();
+        let test_file = tmp_dir.path().join("test.txt");
+        std::fs::write(&test_file, "hello world")?;
 
+        // 前回落ちたセッションの代わりに、同じファイルを別の Neovim で開いたままにしてスワップファイルを残す
+        let mut crashed = Command::new("nvim").arg("--headless").arg("--clean").arg(&test_file).spawn()?;
+        std::thread::sleep(Duration::from_millis(500));
+
+        let started = client.start_neovim(test_file.to_str().unwrap(), None);
+        std::thread::sleep(Duration::from_millis(300));
+        let mode = started.and_then(|()| client.editor_mode(Duration::from_secs(2)));
+        let state = client.get_current_state();
+        let swapfile = client.eval_expr("&swapfile");
+        let _ = client.stop();
+        let _ = crashed.kill();
+        let _ = crashed.wait();
+
+        // スワップファイルの確認で入力待ちにならず、ファイルを読めている
+        assert_eq!(mode?, EditorMode { mode: "n".to_string(), blocking: false });
+        assert_eq!(state?.buffer_content, vec!["hello world"]);
+        assert_eq!(swapfile?, "0");
         Ok(())
     }
 
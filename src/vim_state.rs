@@ -1,4 +1,16 @@
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+use crate::goal_registry::{CustomGoal, GoalCheckable, GoalContext, GoalQuery};
+use crate::keystrokes::{self, MotionUse, TextObjectUse};
+use crate::normalize::Normalize;
+use crate::status_line;
+
+// 目標で指定されていなくても常に取得するレジスタ
+pub const DEFAULT_REGISTERS: &[&str] = &["\"", "0", "1", "a", "b", "c"];
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct VimState {
@@ -8,6 +20,68 @@ pub struct VimState {
     pub operator: Option<String>,
     pub buffer_content: Vec<String>,
     pub registers: std::collections::HashMap<String, String>,
+    #[serde(default)]
+    pub changedtick: u64, // b:changedtick（バッファが編集されるたびに増える）
+    #[serde(default)]
+    pub modified: bool, // &modified
+    #[serde(default)]
+    pub register_types: HashMap<String, RegisterType>,
+    #[serde(default)]
+    pub buffer_name: String, // 表示中のバッファのファイル名（expand('%:t')）
+    // 複数のファイルを開く練習で、ファイル名ごとのバッファの内容
+    #[serde(default)]
+    pub buffers: HashMap<String, Vec<String>>,
+    // ウィンドウの数と、上（左）から何番目のウィンドウにいるか（1始まり。tmux が無い場合の指示ウィンドウは数えない）
+    #[serde(default = "single_window")]
+    pub window_count: usize,
+    #[serde(default = "single_window")]
+    pub current_window: usize,
+    #[serde(default)]
+    pub undo_seq: u64, // undotree().seq_cur（u で戻り、Ctrl-r で進む。新しい変更は最大の番号の次になる）
+}
+
+fn single_window() -> usize {
+    1
+}
+
+// ノーマルモードで1行1列にいる、ウィンドウが1つの状態
+impl Default for VimState {
+    fn default() -> Self {
+        Self {
+            mode: VimMode::Normal,
+            cursor_line: 0,
+            cursor_col: 0,
+            operator: None,
+            buffer_content: Vec::new(),
+            registers: HashMap::new(),
+            changedtick: 0,
+            modified: false,
+            register_types: HashMap::new(),
+            buffer_name: String::new(),
+            buffers: HashMap::new(),
+            window_count: single_window(),
+            current_window: single_window(),
+            undo_seq: 0,
+        }
+    }
+}
+
+// getregtype() の結果（yy と yiw の区別などに使う）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegisterType {
+    Charwise,  // "v"
+    Linewise,  // "V"
+    Blockwise, // "<C-v>{幅}"
+}
+
+impl RegisterType {
+    pub fn from_vim(regtype: &str) -> Self {
+        match regtype.chars().next() {
+            Some('V') => RegisterType::Linewise,
+            Some('\u{16}') => RegisterType::Blockwise,
+            _ => RegisterType::Charwise,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -19,30 +93,422 @@ pub enum VimMode {
     VisualBlock,
     OperatorPending(String), // オペレーター待機モード（オペレーター名付き）
     Command,
+    Replace,  // R / 仮想置換（gR）
+    Select,   // 選択モード（gh など）
+    Terminal, // 端末バッファの端末ジョブ入力モード
 }
 
 impl VimMode {
+    // 位置の表示に使う短いモード名（Vim の showmode に合わせる）
+    pub fn status_name(&self) -> String {
+        match self {
+            VimMode::Normal => "NORMAL".to_string(),
+            VimMode::Insert => "INSERT".to_string(),
+            VimMode::Visual => "VISUAL".to_string(),
+            VimMode::VisualLine => "VISUAL LINE".to_string(),
+            VimMode::VisualBlock => "VISUAL BLOCK".to_string(),
+            VimMode::OperatorPending(operator) => format!("OPERATOR {}", operator),
+            VimMode::Command => "COMMAND".to_string(),
+            VimMode::Replace => "REPLACE".to_string(),
+            VimMode::Select => "SELECT".to_string(),
+            VimMode::Terminal => "TERMINAL".to_string(),
+        }
+    }
+
+    // 学習者に見せるモード名
+    pub fn label(&self) -> String {
+        match self {
+            VimMode::Normal => "ノーマル".to_string(),
+            VimMode::Insert => "挿入".to_string(),
+            VimMode::Visual => "ビジュアル".to_string(),
+            VimMode::VisualLine => "ビジュアル（行）".to_string(),
+            VimMode::VisualBlock => "ビジュアル（矩形）".to_string(),
+            VimMode::OperatorPending(operator) => format!("オペレーター待機（{}）", operator),
+            VimMode::Command => "コマンドライン".to_string(),
+            VimMode::Replace => "置換".to_string(),
+            VimMode::Select => "選択".to_string(),
+            VimMode::Terminal => "端末".to_string(),
+        }
+    }
+
+    // 章データでのモード名（mode の目標の target・allowed_modes）。"operator_d" のように
+    // オペレーターを付けたオペレーター待機も書ける
+    pub fn from_goal_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "normal" => VimMode::Normal,
+            "insert" => VimMode::Insert,
+            "visual" => VimMode::Visual,
+            "visual_line" => VimMode::VisualLine,
+            "visual_block" => VimMode::VisualBlock,
+            "command" => VimMode::Command,
+            "replace" => VimMode::Replace,
+            "select" => VimMode::Select,
+            "terminal" => VimMode::Terminal,
+            op => VimMode::OperatorPending(op.strip_prefix("operator_")?.to_string()),
+        })
+    }
+
     pub fn from_vim_mode(mode: &str, mode_detailed: &str, operator: Option<String>) -> Self {
         match (mode, mode_detailed) {
-            ("n", "no") => VimMode::OperatorPending(operator.unwrap_or_default()),
+            // no / nov / noV / no^V（強制的に文字・行・矩形単位にする場合を含む）
+            ("n", detailed) if detailed.starts_with("no") => {
+                VimMode::OperatorPending(operator.unwrap_or_default())
+            }
             ("n", _) => VimMode::Normal,
             ("i", _) => VimMode::Insert,
             ("v", _) => VimMode::Visual,
             ("V", _) => VimMode::VisualLine,
             (mode_str, _) if mode_str.contains('\u{16}') => VimMode::VisualBlock, // Ctrl-V
             ("c", _) => VimMode::Command,
+            (mode_str, _) if mode_str.starts_with('R') => VimMode::Replace, // R, Rv, Rc など
+            ("s" | "S" | "\u{13}", _) => VimMode::Select, // Ctrl-S は矩形選択
+            ("t", _) => VimMode::Terminal,
             _ => VimMode::Normal,
         }
     }
 }
 
+#[derive(Deserialize)]
+struct StatusRegister {
+    content: String,
+    #[serde(rename = "type")]
+    regtype: String,
+}
+
+/// 状態ファイル全体をパースする
+// 1行目は status_line の形式、続く "REGS:" 行は監視対象レジスタの json_encode() 結果
+// （改行やカンマを含む内容も安全に受け渡すため JSON にしている）。
+// テキストの目標がある練習では "BUF:" 行にバッファ全体の行リストが入る。
+// "FILE:" 行は表示中のファイル名、複数のファイルを開く練習の "FILES:" 行はファイルごとの行リスト
+pub fn parse_status_content(content: &str) -> VimState {
+    let status_line = content
+        .lines()
+        .find(|line| status_line::is_status_line(line))
+        .unwrap_or_default();
+    let mut state = status_line::parse(status_line);
+
+    if let Some(json) = content.lines().find_map(|line| line.strip_prefix("REGS:"))
+        && let Ok(registers) = serde_json::from_str::<HashMap<String, StatusRegister>>(json)
+    {
+        for (name, register) in registers {
+            state
+                .register_types
+                .insert(name.clone(), RegisterType::from_vim(&register.regtype));
+            state.registers.insert(name, register.content);
+        }
+    }
+    if let Some(json) = content.lines().find_map(|line| line.strip_prefix("BUF:"))
+        && let Ok(lines) = serde_json::from_str::<Vec<String>>(json)
+    {
+        state.buffer_content = lines;
+    }
+    // 版3までの列はバイト単位のため、バッファの行から文字単位に直す
+    if status_line::version(status_line) < 4
+        && let Some(text) = state.buffer_content.get(state.cursor_line)
+    {
+        state.cursor_col = status_line::char_column(text, state.cursor_col);
+    }
+    if let Some(json) = content.lines().find_map(|line| line.strip_prefix("FILE:"))
+        && let Ok(name) = serde_json::from_str::<String>(json)
+    {
+        state.buffer_name = name;
+    }
+    if let Some(json) = content.lines().find_map(|line| line.strip_prefix("FILES:"))
+        && let Ok(buffers) = serde_json::from_str::<HashMap<String, Vec<String>>>(json)
+    {
+        state.buffers = buffers;
+    }
+    state
+}
+
+// 追記形式の状態ファイルの1行（エディタが1回の更新ごとに1行追記する）
+// 形式: "SEQ:<pid>:<番号>" に続けて、parse_status_content の各行をタブ区切りで並べる
+// （JSON の中のタブはエスケープされるため、区切りと混ざらない）
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusRecord {
+    pub pid: u32,  // 書き出したエディタのプロセス（起動し直すと番号は1から数え直す）
+    pub seq: u64,  // エディタごとに単調に増える番号
+    pub state: VimState,
+}
+
+pub fn parse_status_record(line: &str) -> Option<StatusRecord> {
+    let (header, rest) = line.strip_prefix("SEQ:")?.split_once('\t')?;
+    let (pid, seq) = header.split_once(':')?;
+    // 状態の行が欠けたものは既定値で埋めずに捨てる
+    if !status_line::is_status_line(rest) {
+        return None;
+    }
+    Some(StatusRecord {
+        pid: pid.parse().ok()?,
+        seq: seq.parse().ok()?,
+        state: parse_status_content(&rest.replace('\t', "\n")),
+    })
+}
+
+// Ex コマンドの行が :s（:substitute・:&・:~ と、:g の後の :s を含む）か
+pub fn is_substitute_command(command_line: &str) -> bool {
+    substitute_args(command_line).is_some()
+}
+
+// :s/パターン/置き換える文字列/ の置き換える文字列（区切りを逃がした \/ は / に戻す）。
+// :&・:~ と区切りを書かない :s は前回の置換を繰り返すため、文字列は分からない（None）
+pub fn substitute_replacement(command_line: &str) -> Option<String> {
+    let args = substitute_args(command_line)?;
+    let delimiter = args.chars().next()?;
+    if delimiter.is_alphanumeric() || delimiter.is_whitespace() || "\\\"|&".contains(delimiter) {
+        return None;
+    }
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut chars = args[delimiter.len_utf8()..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == delimiter => part.push(next),
+                Some(next) => {
+                    part.push(c);
+                    part.push(next);
+                }
+                None => part.push(c),
+            },
+            c if c == delimiter => {
+                parts.push(std::mem::take(&mut part));
+                if parts.len() == 2 {
+                    break;
+                }
+            }
+            c => part.push(c),
+        }
+    }
+    match parts.len() {
+        // 置き換える文字列を省くと消す（:s/foo）
+        0 => Some(String::new()),
+        // 最後の区切りは省ける（:s/foo/bar）
+        1 => Some(part),
+        _ => parts.pop(),
+    }
+}
+
+// :s の名前の後ろ（:&・:~ は空）。:s でなければ None
+fn substitute_args(command_line: &str) -> Option<&str> {
+    let mut rest = command_line.trim_start_matches([':', ' ', '\t']);
+    // 範囲（行番号・%・. $ とマーク）を飛ばす
+    loop {
+        let trimmed = rest.trim_start_matches(|c: char| c.is_ascii_digit() || ",;.$%+- \t".contains(c));
+        match trimmed.strip_prefix('\'') {
+            Some(mark) => rest = mark.chars().next().map_or("", |c| &mark[c.len_utf8()..]),
+            None => {
+                rest = trimmed;
+                break;
+            }
+        }
+    }
+    let name_end = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+    let (name, args) = rest.split_at(name_end);
+    match name {
+        "" if args.starts_with(['&', '~']) => Some(""),
+        "" => None,
+        "g" | "global" | "v" | "vglobal" => {
+            let args = args.strip_prefix('!').unwrap_or(args);
+            let delimiter = args.chars().next()?;
+            let (_, command) = args[delimiter.len_utf8()..].split_once(delimiter)?;
+            substitute_args(command)
+        }
+        _ if "substitute".starts_with(name) => Some(args),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum GoalType {
     Position { line: usize, col: usize },
     Mode(VimMode),
-    TextContent { line: usize, expected: String },
+    // 比べる前に期待する行と実際の行の両方に normalize をかける
+    TextContent {
+        line: usize,
+        expected: String,
+        match_mode: TextMatch,
+        normalize: Normalize,
+    },
+    // start_line から連続する複数行が expected と一致する
+    TextBlock {
+        start_line: usize,
+        expected: Vec<String>,
+        normalize: Normalize,
+    },
+    BufferEquals {
+        expected: Vec<String>,
+    },
     BufferChange,
-    RegisterContent { register: String, expected: String },
+    RegisterContent {
+        register: String,
+        expected: String,
+        match_mode: RegisterMatch,
+    },
+    // motion を min_count 以上の数を付けて使う（"5j" など）。position があればそこに着地する
+    CountedMotion {
+        motion: String,
+        min_count: usize,
+        position: Option<(usize, usize)>,
+    },
+    // 表示中のバッファのファイル名が name
+    BufferActive { name: String },
+    // 複数のファイルを開く練習で、file のバッファについて goal を判定する
+    // （カーソルとモードの目標は file を表示しているときだけ満たす）
+    InFile { file: String, goal: Box<GoalType> },
+    // ウィンドウが expected 個ある。current があれば current 番目のウィンドウにいる
+    WindowCount { expected: usize, current: Option<usize> },
+    // :sp や :vs で min_splits 回以上分割している（ウィンドウが min_splits + 1 個以上）
+    WindowLayout { min_splits: usize },
+    // :s で pattern を置き換え、lines の範囲（0始まり。省略時はバッファ全体）に pattern が残っていない。
+    // 有効になった時点より min_count 個以上減っている（数は Vim の正規表現としてエディタに数えさせる）
+    SubstitutionResult {
+        pattern: String,
+        replacement: String,
+        min_count: usize,
+        lines: Option<(usize, usize)>,
+    },
+    // u で取り消した（undo の番号が有効になった時点より戻り、その番号で見たバッファに戻っている）
+    UndoPerformed,
+    // Ctrl-r でやり直した（取り消した番号に進み直し、有効になった時点のバッファから変わっている）
+    RedoPerformed,
+    // ノーマルモードの . で min_times 回以上繰り返した（バッファが変わらなかった . は数えない）
+    DotRepeatUsed { min_times: usize },
+    // operator に続けて text_object を打ち（"ciw" など）、その後にバッファが変わった
+    OperatorTextObject { operator: String, text_object: String },
+    // カーソルが treesitter の node の種類のノードの中にある（language が無ければバッファの filetype の言語）
+    SyntaxNode { node: String, language: Option<String> },
+    // goal_registry に登録した独自の種類
+    Custom(CustomGoal),
+}
+
+impl GoalType {
+    // 章データで description を省いた目標の説明（行・列は1始まりで書く）
+    pub fn default_description(&self) -> String {
+        match self {
+            GoalType::Position { line, col } => format!("カーソルを{}行{}列へ", line + 1, col + 1),
+            GoalType::Mode(mode) => format!("{}モードにする", mode.label()),
+            GoalType::TextContent { line, expected, .. } => format!("{}行目を「{}」にする", line + 1, expected),
+            GoalType::TextBlock { start_line, expected, .. } => {
+                format!("{}行目からの{}行を書き換える", start_line + 1, expected.len())
+            }
+            GoalType::BufferEquals { expected } => format!("バッファ全体を{}行の内容にする", expected.len()),
+            GoalType::BufferChange => "バッファを編集する".to_string(),
+            GoalType::RegisterContent { register, expected, .. } => {
+                format!("レジスタ\"{}に「{}」を入れる", register, expected)
+            }
+            GoalType::CountedMotion { motion, min_count, .. } => {
+                format!("{}を{}以上の数を付けて使う", motion, min_count)
+            }
+            GoalType::BufferActive { name } => format!("{}を表示する", name),
+            GoalType::InFile { file, goal } => format!("{}で{}", file, goal.default_description()),
+            GoalType::WindowCount { expected, .. } => format!("ウィンドウを{}個にする", expected),
+            GoalType::WindowLayout { min_splits } => format!("ウィンドウを{}回分割する", min_splits),
+            GoalType::SubstitutionResult {
+                pattern,
+                replacement,
+                min_count,
+                ..
+            } => format!("「{}」を :s で「{}」に{}か所置き換える", pattern, replacement, min_count),
+            GoalType::UndoPerformed => "u で変更を取り消す".to_string(),
+            GoalType::RedoPerformed => "Ctrl-r で取り消した変更をやり直す".to_string(),
+            GoalType::DotRepeatUsed { min_times } => format!(". で変更を{}回繰り返す", min_times),
+            GoalType::OperatorTextObject { operator, text_object } => format!("{}{} で編集する", operator, text_object),
+            GoalType::SyntaxNode { node, .. } => format!("カーソルを{}の中へ移動する", node),
+            GoalType::Custom(custom) => custom
+                .goal
+                .description()
+                .unwrap_or_else(|| format!("{} の目標", custom.kind)),
+        }
+    }
+
+    // カーソルやモードを見る目標か（別のファイルを表示している間は満たさない）
+    pub fn follows_cursor(&self) -> bool {
+        match self {
+            GoalType::Custom(custom) => custom.goal.follows_cursor(),
+            _ => matches!(
+                self,
+                GoalType::Position { .. } | GoalType::Mode(_) | GoalType::CountedMotion { .. } | GoalType::SyntaxNode { .. }
+            ),
+        }
+    }
+}
+
+// レジスタの内容の比べ方（YAMLの target.match）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RegisterMatch {
+    Exact,
+    #[default]
+    Trimmed, // 前後の空白・改行を無視（diw と daw の違いなど）
+    Regex,   // expected を正規表現として検索
+}
+
+impl RegisterMatch {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "exact" => Some(RegisterMatch::Exact),
+            "trimmed" => Some(RegisterMatch::Trimmed),
+            "regex" => Some(RegisterMatch::Regex),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, actual: &str, expected: &str) -> bool {
+        match self {
+            RegisterMatch::Exact => actual == expected,
+            RegisterMatch::Trimmed => actual.trim() == expected.trim(),
+            RegisterMatch::Regex => regex_matches(expected, actual),
+        }
+    }
+}
+
+// 行の内容の比べ方（YAMLの target.match_mode）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextMatch {
+    #[default]
+    Exact,
+    Trim,               // 前後の空白を無視
+    CollapseWhitespace, // 連続する空白（タブを含む）を1つのスペースとみなし、前後の空白も無視
+    Regex,              // expected を正規表現として検索
+}
+
+impl TextMatch {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "exact" => Some(TextMatch::Exact),
+            "trim" => Some(TextMatch::Trim),
+            "collapse_whitespace" => Some(TextMatch::CollapseWhitespace),
+            "regex" => Some(TextMatch::Regex),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, actual: &str, expected: &str) -> bool {
+        match self {
+            TextMatch::Exact => actual == expected,
+            TextMatch::Trim => actual.trim() == expected.trim(),
+            TextMatch::CollapseWhitespace => {
+                let collapse = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+                collapse(actual) == collapse(expected)
+            }
+            TextMatch::Regex => regex_matches(expected, actual),
+        }
+    }
+}
+
+// 正規表現の目標は状態を取るたびに判定するため、パターンごとに一度だけコンパイルして使い回す
+// （コンパイルできないパターンは一致しないものとして扱う。章データの読み込みで報告済み）
+fn regex_matches(pattern: &str, actual: &str) -> bool {
+    static COMPILED: OnceLock<Mutex<HashMap<String, Option<Regex>>>> = OnceLock::new();
+    let mut compiled = COMPILED
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    compiled
+        .entry(pattern.to_string())
+        .or_insert_with(|| Regex::new(pattern).ok())
+        .as_ref()
+        .is_some_and(|regex| regex.is_match(actual))
 }
 
 #[derive(Debug, Clone)]
@@ -52,56 +518,910 @@ pub struct Goal {
     pub description: String,
 }
 
-pub struct GoalDetector;
+// 目標の判定に関係する状態の一部（目標が有効になった時点の記録に使う）
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoalObservation {
+    Position { line: usize, col: usize },
+    Mode(VimMode),
+    Line(Option<String>),
+    Lines(Vec<String>),
+    Register(Option<String>),
+    ChangedTick(u64),
+    BufferName(String),
+    Windows { count: usize, current: usize },
+    // undo の番号と、それまでに見た最大の番号・バッファ
+    Undo { seq: u64, last_seen: u64, lines: Vec<String> },
+    // 独自の種類の目標が記録したもの
+    Custom(String),
+}
+
+// 目標が有効になった時点の記録と、その後に関係する状態が変化したか
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoalActivation {
+    observation: GoalObservation,
+    changed: bool,
+}
+
+impl GoalActivation {
+    pub fn new(observation: GoalObservation) -> Self {
+        Self {
+            observation,
+            changed: false,
+        }
+    }
+}
+
+// 目標に届いていない理由（目標の種類ごとに、どれだけ離れているか）
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoalGap {
+    // 目標までの行・列の差（正なら下・右）
+    Position { line_delta: i64, col_delta: i64 },
+    Mode { actual: VimMode, expected: VimMode },
+    // 最初に違う行（0始まり）。行が無い側は None
+    Line {
+        line: usize,
+        actual: Option<String>,
+        expected: Option<String>,
+    },
+    Unmodified, // バッファがまだ編集されていない
+    Register {
+        register: String,
+        actual: Option<String>,
+        expected: String,
+    },
+    NoChangeSinceActivation, // 目標が有効になってから関係する状態が変わっていない
+    // 数を付けずに同じキーを繰り返して着地した
+    RepeatedMotion { motion: String, times: usize },
+    // まだ数を付けて使っていない
+    NoCountedMotion { motion: String, min_count: usize },
+    // 別のファイルを表示している（actual が空なら名前の無いバッファ）
+    Buffer { actual: String, expected: String },
+    // ウィンドウの数が違う（at_least なら expected 個以上あればよい）
+    Windows { actual: usize, expected: usize, at_least: bool },
+    // 別のウィンドウにいる
+    CurrentWindow { actual: usize, expected: usize },
+    // 有効になってから :s を実行していない
+    NoSubstitution { pattern: String },
+    // 最後の :s で、目標と違う文字列に置き換えた
+    WrongReplacement { pattern: String, used: String, expected: String },
+    // 範囲にまだ pattern が残っている
+    PatternRemains { pattern: String, remaining: usize },
+    // 置き換えた数が足りない
+    TooFewSubstitutions { replaced: usize, min_count: usize },
+    NoUndo, // 有効になってから取り消していない
+    NoRedo, // 有効になってからやり直していない（取り消した後に新しく変更した場合を含む）
+    // . で繰り返した回数が足りない
+    TooFewDotRepeats { times: usize, min_times: usize },
+    // まだ operator と text_object を続けて使っていない
+    NoTextObject { operator: String, text_object: String },
+    // カーソルが node の中にない（inside はカーソルを含む一番内側のノード。まだ問い合わせていなければ None）
+    OutsideSyntaxNode { node: String, inside: Option<String> },
+}
+
+impl GoalGap {
+    // 指示ペインに出す距離の目安（「あと2文字右」など。目安を出せない目標は None）
+    pub fn nudge(&self) -> Option<String> {
+        match self {
+            GoalGap::Position {
+                line_delta,
+                col_delta,
+            } => {
+                let mut parts = Vec::new();
+                match line_delta.signum() {
+                    1 => parts.push(format!("{}行下", line_delta)),
+                    -1 => parts.push(format!("{}行上", -line_delta)),
+                    _ => {}
+                }
+                match col_delta.signum() {
+                    1 => parts.push(format!("{}文字右", col_delta)),
+                    -1 => parts.push(format!("{}文字左", -col_delta)),
+                    _ => {}
+                }
+                Some(format!("あと{}", parts.join("・")))
+            }
+            GoalGap::Mode { actual, expected } => Some(format!(
+                "今は{}モードです（{}モードにします）",
+                actual.label(),
+                expected.label()
+            )),
+            GoalGap::RepeatedMotion { motion, times } => Some(format!(
+                "{}を{}回押す代わりに、{}{} のように数を付けて一度で移動します",
+                motion, times, times, motion
+            )),
+            GoalGap::NoCountedMotion { motion, min_count } => Some(format!(
+                "{}{} のように数を付けて移動します",
+                min_count, motion
+            )),
+            GoalGap::Buffer { actual, expected } => Some(format!(
+                "今は{}を開いています（{}に切り替えます）",
+                display_file_name(actual),
+                expected
+            )),
+            GoalGap::Windows { actual, expected, .. } if actual < expected => Some(format!(
+                "あと{}回 :sp か :vs で分割します",
+                expected - actual
+            )),
+            GoalGap::Windows { actual, expected, .. } => Some(format!(
+                "あと{}個のウィンドウを :q で閉じます（Ctrl-w o で今のウィンドウだけを残せます）",
+                actual - expected
+            )),
+            GoalGap::CurrentWindow { actual, expected } => Some(format!(
+                "今は{}番目のウィンドウです（Ctrl-w j や Ctrl-w k で{}番目に移ります）",
+                actual, expected
+            )),
+            GoalGap::NoSubstitution { .. } => {
+                Some(":s/パターン/置き換える文字列/ で置き換えます（% を付けるとファイル全体）".to_string())
+            }
+            GoalGap::WrongReplacement { expected, .. } => Some(format!(
+                "u で取り消してから、「{}」に置き換え直します",
+                expected
+            )),
+            GoalGap::PatternRemains { remaining, .. } => Some(format!(
+                "あと{}か所（行の中の全てを置き換えるには g を、全ての行には % を付けます）",
+                remaining
+            )),
+            GoalGap::NoUndo => Some("u で直前の変更を取り消します".to_string()),
+            GoalGap::NoRedo => Some("Ctrl-r で取り消した変更をやり直します（新しく変更したら u で戻してから）".to_string()),
+            GoalGap::TooFewDotRepeats { times, min_times } => Some(format!(
+                "あと{}回（1回変更した後は、移動して . を押すと同じ変更を繰り返せます）",
+                min_times - times
+            )),
+            GoalGap::NoTextObject { operator, text_object } => Some(format!(
+                "{}{} のように、オペレーターに続けてテキストオブジェクトを打ちます（1文字ずつ消しても達成になりません）",
+                operator, text_object
+            )),
+            GoalGap::OutsideSyntaxNode { node, .. } => {
+                Some(format!("{}の中なら、どこにカーソルを置いても達成です", node))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn display_file_name(name: &str) -> &str {
+    if name.is_empty() { "名前の無いバッファ" } else { name }
+}
+
+impl fmt::Display for GoalGap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let quoted = |text: &Option<String>, missing: &str| {
+            text.as_ref()
+                .map_or(missing.to_string(), |text| format!("`{}`", text))
+        };
+        match self {
+            GoalGap::Position {
+                line_delta,
+                col_delta,
+            } => write!(f, "カーソルが目標から{}行・{}列ずれています", line_delta, col_delta),
+            GoalGap::Mode { actual, expected } => write!(
+                f,
+                "モードが{}です（期待: {}）",
+                actual.label(),
+                expected.label()
+            ),
+            GoalGap::Line {
+                line,
+                actual,
+                expected,
+            } => write!(
+                f,
+                "{}行目が{}です（期待: {}）",
+                line + 1,
+                quoted(actual, "（行がありません）"),
+                quoted(expected, "（行なし）")
+            ),
+            GoalGap::Unmodified => write!(f, "バッファがまだ編集されていません"),
+            GoalGap::Register {
+                register,
+                actual,
+                expected,
+            } => write!(
+                f,
+                "レジスタ\"{}が{}です（期待: `{}`）",
+                register,
+                quoted(actual, "空"),
+                expected
+            ),
+            GoalGap::NoChangeSinceActivation => {
+                write!(f, "目標が有効になってから関係する状態が変わっていません")
+            }
+            GoalGap::RepeatedMotion { motion, times } => {
+                write!(f, "{}を数を付けずに{}回繰り返しました", motion, times)
+            }
+            GoalGap::NoCountedMotion { motion, min_count } => {
+                write!(f, "{}を{}以上の数を付けて使っていません", motion, min_count)
+            }
+            GoalGap::Buffer { actual, expected } => write!(
+                f,
+                "表示中のファイルが{}です（期待: {}）",
+                display_file_name(actual),
+                expected
+            ),
+            GoalGap::Windows {
+                actual,
+                expected,
+                at_least,
+            } => write!(
+                f,
+                "ウィンドウが{}個です（期待: {}個{}）",
+                actual,
+                expected,
+                if *at_least { "以上" } else { "" }
+            ),
+            GoalGap::CurrentWindow { actual, expected } => {
+                write!(f, "{}番目のウィンドウにいます（期待: {}番目）", actual, expected)
+            }
+            GoalGap::NoSubstitution { pattern } => write!(f, "「{}」をまだ :s で置き換えていません", pattern),
+            GoalGap::WrongReplacement { pattern, used, expected } => {
+                write!(f, "「{}」を「{}」に置き換えています（期待: 「{}」）", pattern, used, expected)
+            }
+            GoalGap::PatternRemains { pattern, remaining } => {
+                write!(f, "「{}」がまだ{}か所残っています", pattern, remaining)
+            }
+            GoalGap::TooFewSubstitutions { replaced, min_count } => {
+                write!(f, "置き換えたのは{}か所です（期待: {}か所以上）", replaced, min_count)
+            }
+            GoalGap::NoUndo => write!(f, "まだ変更を取り消していません"),
+            GoalGap::NoRedo => write!(f, "まだ取り消した変更をやり直していません"),
+            GoalGap::TooFewDotRepeats { times, min_times } => {
+                write!(f, ". で繰り返したのは{}回です（期待: {}回以上）", times, min_times)
+            }
+            GoalGap::NoTextObject { operator, text_object } => {
+                write!(f, "まだ {}{} を使っていません", operator, text_object)
+            }
+            GoalGap::OutsideSyntaxNode { node, inside } => match inside {
+                Some(inside) => write!(f, "カーソルが{}の中にありません（今は{}の中）", node, inside),
+                None => write!(f, "カーソルが{}の中にありません", node),
+            },
+        }
+    }
+}
+
+// 目標の判定結果（満たしていなければ理由を持つ）
+#[derive(Debug, Clone, PartialEq)]
+pub enum GoalCheck {
+    Met,
+    Unmet { reason: GoalGap },
+}
+
+impl GoalCheck {
+    fn require(met: bool, reason: impl FnOnce() -> GoalGap) -> Self {
+        if met {
+            GoalCheck::Met
+        } else {
+            GoalCheck::Unmet { reason: reason() }
+        }
+    }
+
+    pub fn is_met(&self) -> bool {
+        matches!(self, GoalCheck::Met)
+    }
+
+    pub fn gap(&self) -> Option<&GoalGap> {
+        match self {
+            GoalCheck::Met => None,
+            GoalCheck::Unmet { reason } => Some(reason),
+        }
+    }
+}
+
+// 置換の目標の pattern の数（有効になった時点と、バッファが最後に変わった時点）
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubstitutionCount {
+    pub pattern: String,
+    pub lines: Option<(usize, usize)>,
+    pub before: usize,
+    pub after: usize,
+    pub changedtick: u64, // after を数えたときの changedtick
+    pub substituted: bool, // 数え始めてから :s を実行した
+    pub replacement: Option<String>, // 最後の :s で置き換えた文字列（繰り返しの :& などだけなら None）
+}
+
+// ノードを問い合わせたときのカーソル位置と changedtick
+type SyntaxKey = (usize, usize, u64);
+
+pub struct GoalDetector {
+    // 記録した移動（数を付けた移動の目標に使う。キー入力を記録しないエディタでは None）
+    motions: Option<Vec<MotionUse>>,
+    // . で繰り返して、バッファが変わった回数
+    dot_repeats: usize,
+    // オペレーターに続けて使ったテキストオブジェクトと、キーを打つ前の changedtick
+    text_objects: Vec<(TextObjectUse, u64)>,
+    // 最後にキー入力を記録したときの changedtick
+    key_tick: Option<u64>,
+    // 有効な置換の目標ごとの数
+    substitutions: Vec<SubstitutionCount>,
+    // undo の番号ごとに最後に見たバッファ（取り消しとやり直しで戻った先が同じ内容かを見る）
+    undo_history: BTreeMap<u64, Vec<String>>,
+    // 言語ごとの、カーソルを含む treesitter のノードの種類（内側から）と、問い合わせたときのカーソル位置・changedtick
+    syntax_nodes: BTreeMap<Option<String>, (Vec<String>, SyntaxKey)>,
+    // 1つ前に判定した状態と、目標が有効になってからのキーログ（独自の種類の目標に渡す）
+    previous_state: Option<VimState>,
+    key_log: Vec<String>,
+    // 独自の種類の目標がエディタに問い合わせる手段
+    query: Option<Box<dyn GoalQuery>>,
+}
+
+impl Default for GoalDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl GoalDetector {
     pub fn new() -> Self {
-        Self
+        Self {
+            motions: None,
+            dot_repeats: 0,
+            text_objects: Vec::new(),
+            key_tick: None,
+            substitutions: Vec::new(),
+            undo_history: BTreeMap::new(),
+            syntax_nodes: BTreeMap::new(),
+            previous_state: None,
+            key_log: Vec::new(),
+            query: None,
+        }
     }
 
-    pub fn check_goal(&self, goal: &Goal, current_state: &VimState) -> bool {
-        match &goal.goal_type {
-            GoalType::Position { line, col } => {
-                current_state.cursor_line == *line && current_state.cursor_col == *col
+    // 練習の開始時に呼ぶ（前の練習の状態とキーログは独自の種類の目標に渡さない）
+    pub fn set_query(&mut self, query: Option<Box<dyn GoalQuery>>) {
+        self.query = query;
+        self.previous_state = None;
+        self.key_log.clear();
+        self.syntax_nodes.clear();
+    }
+
+    pub fn record_key_log(&mut self, lines: &[String]) {
+        self.key_log.extend_from_slice(lines);
+    }
+
+    // エディタに問い合わせた、カーソルを含むノードの種類（内側から）を、問い合わせたときの状態と一緒に記録する
+    pub fn record_syntax_nodes(&mut self, language: Option<&str>, nodes: Vec<String>, state: &VimState) {
+        self.syntax_nodes
+            .insert(language.map(str::to_string), (nodes, Self::syntax_key(state)));
+    }
+
+    // カーソルが動くかバッファが変わったら問い合わせ直す
+    pub fn syntax_nodes_current(&self, language: Option<&str>, state: &VimState) -> bool {
+        self.current_syntax_nodes(language, state).is_some()
+    }
+
+    fn current_syntax_nodes(&self, language: Option<&str>, state: &VimState) -> Option<&Vec<String>> {
+        self.syntax_nodes
+            .get(&language.map(str::to_string))
+            .filter(|(_, key)| *key == Self::syntax_key(state))
+            .map(|(nodes, _)| nodes)
+    }
+
+    fn syntax_key(state: &VimState) -> SyntaxKey {
+        (state.cursor_line, state.cursor_col, state.changedtick)
+    }
+
+    // 判定した状態を、次の判定の「1つ前の状態」として記録する
+    pub fn record_checked_state(&mut self, state: &VimState) {
+        self.previous_state = Some(state.clone());
+    }
+
+    fn context<'a>(&'a self, state: &'a VimState) -> GoalContext<'a> {
+        GoalContext {
+            state,
+            previous: self.previous_state.as_ref(),
+            key_log: &self.key_log,
+            rpc: self.query.as_deref(),
+            detector: self,
+        }
+    }
+
+    // キー入力を記録できるときだけ移動を集める（記録できなければ数を付けた移動は位置だけで判定する）
+    pub fn track_motions(&mut self, tracking: bool) {
+        self.motions = tracking.then(Vec::new);
+    }
+
+    pub fn record_motions(&mut self, uses: impl IntoIterator<Item = MotionUse>) {
+        if let Some(motions) = &mut self.motions {
+            motions.extend(uses);
+        }
+    }
+
+    // 1回に読んだキーログの . を押した回数とテキストオブジェクトを、読んだ後の changedtick と一緒に記録する
+    // （状態を読むたびに呼ぶ）。. は前回から changedtick が進んだ分だけを繰り返しとして数え、
+    // 繰り返す変更が無い . は数えない
+    pub fn record_key_uses(&mut self, dots: usize, text_objects: Vec<TextObjectUse>, changedtick: u64) {
+        let before = self.key_tick.unwrap_or(changedtick);
+        if self.key_tick.is_some() && dots > 0 {
+            let advanced = changedtick.saturating_sub(before) as usize;
+            self.dot_repeats += dots.min(advanced);
+        }
+        self.text_objects.extend(text_objects.into_iter().map(|used| (used, before)));
+        self.key_tick = Some(changedtick);
+    }
+
+    // 次の目標は、その目標が有効になってからの移動と . で判定する
+    pub fn clear_motions(&mut self) {
+        if let Some(motions) = &mut self.motions {
+            motions.clear();
+        }
+        self.dot_repeats = 0;
+        self.text_objects.clear();
+        self.key_log.clear();
+    }
+
+    // pattern の数を記録する（初めて数えた数は有効になった時点の数にする）
+    pub fn count_substitution(
+        &mut self,
+        pattern: &str,
+        lines: Option<(usize, usize)>,
+        count: usize,
+        changedtick: u64,
+    ) {
+        match self.substitution_mut(pattern, lines) {
+            Some(substitution) => {
+                substitution.after = count;
+                substitution.changedtick = changedtick;
+            }
+            None => self.substitutions.push(SubstitutionCount {
+                pattern: pattern.to_string(),
+                lines,
+                before: count,
+                after: count,
+                changedtick,
+                substituted: false,
+                replacement: None,
+            }),
+        }
+    }
+
+    // changedtick の時点の数を記録済みか（バッファが変わったときだけ数え直す）
+    pub fn substitution_counted(&self, pattern: &str, lines: Option<(usize, usize)>, changedtick: u64) -> bool {
+        self.substitution(pattern, lines)
+            .is_some_and(|substitution| substitution.changedtick == changedtick)
+    }
+
+    // 学習者が実行した Ex コマンド（:s なら、実行したことと置き換える文字列を数えている全ての目標に記録する）
+    pub fn record_ex_command(&mut self, command_line: &str) {
+        if is_substitute_command(command_line) {
+            let replacement = substitute_replacement(command_line);
+            for substitution in &mut self.substitutions {
+                substitution.substituted = true;
+                // 前回の置換を繰り返す :& は、前回の置き換える文字列のまま
+                if replacement.is_some() {
+                    substitution.replacement = replacement.clone();
+                }
+            }
+        }
+    }
+
+    // 有効でなくなった置換の目標の数を捨てる（再び有効になれば、その時点から数え直す）
+    pub fn retain_substitutions(&mut self, active: &[(String, Option<(usize, usize)>)]) {
+        self.substitutions.retain(|substitution| {
+            active
+                .iter()
+                .any(|(pattern, lines)| substitution.pattern == *pattern && substitution.lines == *lines)
+        });
+    }
+
+    pub fn clear_substitutions(&mut self) {
+        self.substitutions.clear();
+    }
+
+    fn substitution(&self, pattern: &str, lines: Option<(usize, usize)>) -> Option<&SubstitutionCount> {
+        self.substitutions
+            .iter()
+            .find(|substitution| substitution.pattern == pattern && substitution.lines == lines)
+    }
+
+    fn substitution_mut(&mut self, pattern: &str, lines: Option<(usize, usize)>) -> Option<&mut SubstitutionCount> {
+        self.substitutions
+            .iter_mut()
+            .find(|substitution| substitution.pattern == pattern && substitution.lines == lines)
+    }
+
+    // 目標を判定した後の状態を、その undo の番号のバッファとして記録する
+    pub fn record_undo_state(&mut self, state: &VimState) {
+        self.undo_history.insert(state.undo_seq, state.buffer_content.clone());
+    }
+
+    pub fn clear_undo_history(&mut self) {
+        self.undo_history.clear();
+    }
+
+    /// `goal` を現在の状態だけで判定する（満たしていなければ [`GoalGap`] で理由を返す）。
+    /// 組み込みの種類も独自の種類も、goal_registry の種類が読んだ [`GoalCheckable`] として判定する
+    pub fn check_goal(&self, goal: &Goal, current_state: &VimState) -> GoalCheck {
+        goal.goal_type.check(&self.context(current_state))
+    }
+
+    // 組み込みの種類の判定（GoalType の GoalCheckable::check から呼ぶ）
+    pub(crate) fn check_builtin(&self, goal_type: &GoalType, current_state: &VimState) -> GoalCheck {
+        match goal_type {
+            GoalType::Custom(_) => goal_type.check(&self.context(current_state)),
+            GoalType::Position { line, col } => Self::position_check(*line, Some(*col), current_state),
+            GoalType::Mode(expected_mode) => {
+                GoalCheck::require(&current_state.mode == expected_mode, || GoalGap::Mode {
+                    actual: current_state.mode.clone(),
+                    expected: expected_mode.clone(),
+                })
+            }
+            GoalType::TextContent {
+                line,
+                expected,
+                match_mode,
+                normalize,
+            } => {
+                let actual_line = current_state.buffer_content.get(*line).map(|actual| normalize.apply(actual));
+                let expected = normalize.apply(expected);
+                let met = actual_line.as_ref().is_some_and(|actual| match_mode.matches(actual, &expected));
+                GoalCheck::require(met, || GoalGap::Line {
+                    line: *line,
+                    actual: actual_line.map(|actual| actual.into_owned()),
+                    expected: Some(expected.into_owned()),
+                })
+            }
+            // バッファが期待より短い場合は一致しない
+            GoalType::TextBlock {
+                start_line,
+                expected,
+                normalize,
+            } => Self::first_line_gap(
+                &normalize.apply_lines(&current_state.buffer_content),
+                &normalize.apply_lines(expected),
+                *start_line,
+                expected.len(),
+            ),
+            GoalType::BufferEquals { expected } => Self::first_line_gap(
+                &current_state.buffer_content,
+                expected,
+                0,
+                expected.len().max(current_state.buffer_content.len()),
+            ),
+            // 読み込み後に編集されていれば達成（いつ編集されたかは changedtick で判定する）
+            GoalType::BufferChange => GoalCheck::require(current_state.modified, || GoalGap::Unmodified),
+            GoalType::RegisterContent {
+                register,
+                expected,
+                match_mode,
+            } => {
+                // 行単位のレジスタは末尾に改行が付くため取り除いてから比べる
+                let actual = current_state.registers.get(register).map(|content| {
+                    let linewise = current_state.register_types.get(register)
+                        == Some(&RegisterType::Linewise);
+                    if linewise {
+                        content.strip_suffix('\n').unwrap_or(content)
+                    } else {
+                        content.as_str()
+                    }
+                });
+                let met = actual.is_some_and(|actual| match_mode.matches(actual, expected));
+                GoalCheck::require(met, || GoalGap::Register {
+                    register: register.clone(),
+                    actual: actual.map(str::to_string),
+                    expected: expected.clone(),
+                })
+            }
+            GoalType::BufferActive { name } => Self::buffer_check(name, current_state),
+            // 今のカーソル位置で問い合わせた結果だけを使う（動いた後の古い結果では判定しない）
+            GoalType::SyntaxNode { node, language } => {
+                let nodes = self.current_syntax_nodes(language.as_deref(), current_state);
+                GoalCheck::require(nodes.is_some_and(|nodes| nodes.contains(node)), || {
+                    GoalGap::OutsideSyntaxNode {
+                        node: node.clone(),
+                        inside: nodes.and_then(|nodes| nodes.first().cloned()),
+                    }
+                })
+            }
+            GoalType::InFile { file, goal } => {
+                if goal.follows_cursor() {
+                    let check = Self::buffer_check(file, current_state);
+                    if !check.is_met() {
+                        return check;
+                    }
+                }
+                goal.check(&self.context(&Self::file_view(file, current_state)))
+            }
+            GoalType::WindowCount { expected, current } => {
+                let check = Self::window_count_check(*expected, false, current_state);
+                match current {
+                    Some(current) if check.is_met() => {
+                        GoalCheck::require(current_state.current_window == *current, || {
+                            GoalGap::CurrentWindow {
+                                actual: current_state.current_window,
+                                expected: *current,
+                            }
+                        })
+                    }
+                    _ => check,
+                }
+            }
+            GoalType::WindowLayout { min_splits } => {
+                Self::window_count_check(min_splits + 1, true, current_state)
+            }
+            // 有効になった時点と比べないと判定できない（check_goal_since で判定する）
+            GoalType::UndoPerformed => GoalCheck::Unmet { reason: GoalGap::NoUndo },
+            GoalType::RedoPerformed => GoalCheck::Unmet { reason: GoalGap::NoRedo },
+            GoalType::SubstitutionResult {
+                pattern,
+                replacement,
+                min_count,
+                lines,
+            } => {
+                let no_substitution = || GoalCheck::Unmet {
+                    reason: GoalGap::NoSubstitution {
+                        pattern: pattern.clone(),
+                    },
+                };
+                let Some(substitution) = self.substitution(pattern, *lines) else {
+                    return no_substitution();
+                };
+                if !substitution.substituted {
+                    return no_substitution();
+                }
+                if let Some(used) = &substitution.replacement
+                    && used != replacement
+                {
+                    return GoalCheck::Unmet {
+                        reason: GoalGap::WrongReplacement {
+                            pattern: pattern.clone(),
+                            used: used.clone(),
+                            expected: replacement.clone(),
+                        },
+                    };
+                }
+                if substitution.after > 0 {
+                    return GoalCheck::Unmet {
+                        reason: GoalGap::PatternRemains {
+                            pattern: pattern.clone(),
+                            remaining: substitution.after,
+                        },
+                    };
+                }
+                let replaced = substitution.before.saturating_sub(substitution.after);
+                GoalCheck::require(replaced >= *min_count, || GoalGap::TooFewSubstitutions {
+                    replaced,
+                    min_count: *min_count,
+                })
             }
-            GoalType::Mode(expected_mode) => &current_state.mode == expected_mode,
-            GoalType::TextContent { line, expected } => {
-                if let Some(actual_line) = current_state.buffer_content.get(*line) {
-                    actual_line == expected
-                } else {
-                    false
+            GoalType::DotRepeatUsed { min_times } => GoalCheck::require(self.dot_repeats >= *min_times, || {
+                GoalGap::TooFewDotRepeats {
+                    times: self.dot_repeats,
+                    min_times: *min_times,
                 }
+            }),
+            GoalType::OperatorTextObject { operator, text_object } => {
+                let used = self.text_objects.iter().any(|(used, before)| {
+                    used.operator == operator
+                        && used.text_object == *text_object
+                        && current_state.changedtick > *before
+                });
+                GoalCheck::require(used, || GoalGap::NoTextObject {
+                    operator: operator.clone(),
+                    text_object: text_object.clone(),
+                })
+            }
+            GoalType::CountedMotion {
+                motion,
+                min_count,
+                position,
+            } => {
+                // 縦の移動は着地した行だけを見る（列は元の列や行末への補正で変わるため）
+                if let Some((line, col)) = *position {
+                    let col = (!keystrokes::is_linewise_motion(motion)).then_some(col);
+                    let check = Self::position_check(line, col, current_state);
+                    if !check.is_met() {
+                        return check;
+                    }
+                }
+                let Some(motions) = &self.motions else {
+                    return GoalCheck::Met;
+                };
+                let counted = motions.iter().any(|motion_use| {
+                    motion_use.motion == *motion
+                        && motion_use.count.is_some_and(|count| count >= *min_count)
+                });
+                if counted {
+                    return GoalCheck::Met;
+                }
+                let times = keystrokes::longest_repeat(motions, motion);
+                GoalCheck::Unmet {
+                    reason: if times > 1 {
+                        GoalGap::RepeatedMotion {
+                            motion: motion.clone(),
+                            times,
+                        }
+                    } else {
+                        GoalGap::NoCountedMotion {
+                            motion: motion.clone(),
+                            min_count: *min_count,
+                        }
+                    },
+                }
+            }
+        }
+    }
+
+    fn buffer_check(name: &str, current_state: &VimState) -> GoalCheck {
+        GoalCheck::require(current_state.buffer_name == name, || GoalGap::Buffer {
+            actual: current_state.buffer_name.clone(),
+            expected: name.to_string(),
+        })
+    }
+
+    fn window_count_check(expected: usize, at_least: bool, current_state: &VimState) -> GoalCheck {
+        let actual = current_state.window_count;
+        let met = if at_least { actual >= expected } else { actual == expected };
+        GoalCheck::require(met, || GoalGap::Windows {
+            actual,
+            expected,
+            at_least,
+        })
+    }
+
+    // file のバッファの内容を表示中のバッファとして見た状態（カーソルとモードはそのまま）
+    // 表示中のバッファも FILES 行に含まれるため、FILES 行の内容を優先する
+    fn file_view(file: &str, state: &VimState) -> VimState {
+        let mut view = state.clone();
+        match state.buffers.get(file) {
+            Some(lines) => view.buffer_content = lines.clone(),
+            None if state.buffer_name == file => {}
+            None => view.buffer_content = Vec::new(),
+        }
+        view
+    }
+
+    // col が None なら行だけを比べる
+    fn position_check(line: usize, col: Option<usize>, current_state: &VimState) -> GoalCheck {
+        let line_delta = line as i64 - current_state.cursor_line as i64;
+        let col_delta = col.map_or(0, |col| col as i64 - current_state.cursor_col as i64);
+        GoalCheck::require(line_delta == 0 && col_delta == 0, || GoalGap::Position {
+            line_delta,
+            col_delta,
+        })
+    }
+
+    // start_line から count 行を期待と比べ、最初に違う行を理由にする
+    fn first_line_gap(
+        buffer: &[String],
+        expected: &[String],
+        start_line: usize,
+        count: usize,
+    ) -> GoalCheck {
+        let mismatch = (0..count).find(|&i| {
+            let actual = start_line.checked_add(i).and_then(|line| buffer.get(line));
+            actual != expected.get(i)
+        });
+        match mismatch {
+            None => GoalCheck::Met,
+            Some(i) => GoalCheck::Unmet {
+                reason: GoalGap::Line {
+                    line: start_line.saturating_add(i),
+                    actual: start_line.checked_add(i).and_then(|line| buffer.get(line)).cloned(),
+                    expected: expected.get(i).cloned(),
+                },
+            },
+        }
+    }
+
+    // 取り消しは番号が戻り、その番号で見たバッファと同じ内容になったとき（別の変更で同じ内容に
+    // 戻しても番号は進むため取り消しにならない）。やり直しは見たことのある先の番号に進み直したとき
+    // （取り消した後の新しい変更は、それまでの最大の番号の次になる）
+    fn undo_check(
+        &self,
+        goal_type: &GoalType,
+        seq: u64,
+        last_seen: u64,
+        lines: &[String],
+        state: &VimState,
+    ) -> GoalCheck {
+        let current = state.undo_seq;
+        let seen = self.undo_history.get(&current);
+        match goal_type {
+            GoalType::RedoPerformed => {
+                let redone = current > seq
+                    && current <= last_seen
+                    && state.buffer_content != lines
+                    && seen.is_none_or(|seen| *seen == state.buffer_content);
+                GoalCheck::require(redone, || GoalGap::NoRedo)
+            }
+            _ => {
+                let restored = match seen {
+                    Some(seen) => *seen == state.buffer_content,
+                    None => state.buffer_content != lines,
+                };
+                GoalCheck::require(current < seq && restored, || GoalGap::NoUndo)
             }
-            GoalType::BufferChange => {
-                // バッファが変更されているかは前の状態と比較する必要があるため、
-                // この実装では単純化してtrueを返す
-                // 実際の実装では前の状態との比較が必要
-                true
-            }
-            GoalType::RegisterContent { register, expected } => {
-                if let Some(actual_content) = current_state.registers.get(register) {
-                    actual_content == expected
-                } else {
-                    false
+        }
+    }
+
+    pub fn observe(&self, goal: &Goal, state: &VimState) -> GoalObservation {
+        self.observe_goal_type(&goal.goal_type, state)
+    }
+
+    fn observe_goal_type(&self, goal_type: &GoalType, state: &VimState) -> GoalObservation {
+        match goal_type {
+            GoalType::Custom(custom) => GoalObservation::Custom(custom.goal.observe(&self.context(state))),
+            GoalType::Position { .. } | GoalType::CountedMotion { .. } | GoalType::SyntaxNode { .. } => {
+                GoalObservation::Position {
+                    line: state.cursor_line,
+                    col: state.cursor_col,
                 }
             }
+            GoalType::Mode(_) => GoalObservation::Mode(state.mode.clone()),
+            GoalType::TextContent { line, .. } => {
+                GoalObservation::Line(state.buffer_content.get(*line).cloned())
+            }
+            GoalType::TextBlock {
+                start_line,
+                expected,
+                ..
+            } => GoalObservation::Lines(
+                state
+                    .buffer_content
+                    .iter()
+                    .skip(*start_line)
+                    .take(expected.len())
+                    .cloned()
+                    .collect(),
+            ),
+            GoalType::BufferEquals { .. } => GoalObservation::Lines(state.buffer_content.clone()),
+            GoalType::BufferChange | GoalType::SubstitutionResult { .. } | GoalType::DotRepeatUsed { .. }
+            | GoalType::OperatorTextObject { .. } => {
+                GoalObservation::ChangedTick(state.changedtick)
+            }
+            GoalType::RegisterContent { register, .. } => {
+                GoalObservation::Register(state.registers.get(register).cloned())
+            }
+            GoalType::BufferActive { .. } => GoalObservation::BufferName(state.buffer_name.clone()),
+            GoalType::InFile { file, goal } => self.observe_goal_type(goal, &Self::file_view(file, state)),
+            GoalType::UndoPerformed | GoalType::RedoPerformed => GoalObservation::Undo {
+                seq: state.undo_seq,
+                last_seen: self
+                    .undo_history
+                    .keys()
+                    .next_back()
+                    .map_or(state.undo_seq, |&seen| seen.max(state.undo_seq)),
+                lines: state.buffer_content.clone(),
+            },
+            GoalType::WindowCount { .. } | GoalType::WindowLayout { .. } => GoalObservation::Windows {
+                count: state.window_count,
+                current: state.current_window,
+            },
+        }
+    }
+
+    // 目標が有効になった後の変化で達成されたか
+    // （有効になった時点で既に一致していた状態は、一度変化するまで達成とみなさない）
+    pub fn check_goal_since(
+        &self,
+        goal: &Goal,
+        activation: &mut GoalActivation,
+        current_state: &VimState,
+    ) -> GoalCheck {
+        if let GoalObservation::Undo { seq, last_seen, lines } = &activation.observation {
+            return self.undo_check(&goal.goal_type, *seq, *last_seen, lines, current_state);
+        }
+        if !activation.changed && self.observe(goal, current_state) != activation.observation {
+            activation.changed = true;
+        }
+        if !activation.changed {
+            return GoalCheck::Unmet {
+                reason: GoalGap::NoChangeSinceActivation,
+            };
         }
+        self.check_goal(goal, current_state)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
 
     fn create_test_state() -> VimState {
         VimState {
-            mode: VimMode::Normal,
             cursor_line: 1,
             cursor_col: 1,
-            operator: None,
             buffer_content: vec!["hello world".to_string(), "second line".to_string()],
-            registers: HashMap::new(),
+            changedtick: 3,
+            ..Default::default()
         }
     }
 
@@ -115,6 +1435,162 @@ mod tests {
         assert_eq!(VimMode::from_vim_mode("i", "i", None), VimMode::Insert);
         assert_eq!(VimMode::from_vim_mode("v", "v", None), VimMode::Visual);
         assert_eq!(VimMode::from_vim_mode("V", "V", None), VimMode::VisualLine);
+        assert_eq!(
+            VimMode::from_vim_mode("\u{16}", "\u{16}", None),
+            VimMode::VisualBlock
+        );
+        assert_eq!(VimMode::from_vim_mode("c", "c", None), VimMode::Command);
+        assert_eq!(VimMode::from_vim_mode("i", "ic", None), VimMode::Insert);
+    }
+
+    #[test]
+    fn test_vim_mode_replace_select_terminal() {
+        assert_eq!(VimMode::from_vim_mode("R", "R", None), VimMode::Replace);
+        assert_eq!(VimMode::from_vim_mode("R", "Rv", None), VimMode::Replace);
+        assert_eq!(VimMode::from_vim_mode("Rv", "Rv", None), VimMode::Replace);
+        assert_eq!(VimMode::from_vim_mode("R", "Rc", None), VimMode::Replace);
+        assert_eq!(VimMode::from_vim_mode("s", "s", None), VimMode::Select);
+        assert_eq!(VimMode::from_vim_mode("S", "S", None), VimMode::Select);
+        assert_eq!(
+            VimMode::from_vim_mode("\u{13}", "\u{13}", None),
+            VimMode::Select
+        );
+        assert_eq!(VimMode::from_vim_mode("t", "t", None), VimMode::Terminal);
+        // 未知のモードは従来通りノーマル扱い
+        assert_eq!(VimMode::from_vim_mode("r", "rm", None), VimMode::Normal);
+    }
+
+    #[test]
+    fn test_parse_status_content_registers() {
+        let content = concat!(
+            "LINE:1,COL:1,MODE:n,DETAILED:n\n",
+            r#"REGS:{"a":{"content":"x, y","type":"v"},"0":{"content":"line\n","type":"V"},"z":{"content":"ab\ncd","type":"\u00162"}}"#,
+            "\n"
+        );
+        let state = parse_status_content(content);
+        assert_eq!(state.registers["a"], "x, y");
+        assert_eq!(state.registers["0"], "line\n");
+        assert_eq!(state.registers["z"], "ab\ncd");
+        assert_eq!(state.register_types["a"], RegisterType::Charwise);
+        assert_eq!(state.register_types["0"], RegisterType::Linewise);
+        assert_eq!(state.register_types["z"], RegisterType::Blockwise);
+
+        // BUF 行があればバッファの内容を読む（無ければ空の1行のまま）
+        let state = parse_status_content("LINE:1,COL:1,MODE:n\nBUF:[\"a\\tb\",\"\"]");
+        assert_eq!(state.buffer_content, vec!["a\tb".to_string(), String::new()]);
+
+        // REGS 行が無い・壊れている場合はレジスタなし
+        let state = parse_status_content("LINE:2,COL:1,MODE:n\nREGS:{broken");
+        assert_eq!(state.cursor_line, 1);
+        assert!(state.registers.is_empty());
+    }
+
+    #[test]
+    fn test_position_goal_after_multibyte_text_uses_character_columns() {
+        let detector = GoalDetector::new();
+        // 「// 名前: x」の x は8文字目（col('.') では12バイト目）
+        let goal = Goal {
+            goal_type: GoalType::Position { line: 0, col: 7 },
+            description: "x へ".to_string(),
+        };
+        let current = parse_status_content("V:4,LINE:1,COL:8,MODE:n\nBUF:[\"// 名前: x\"]");
+        assert_eq!(current.cursor_col, 7);
+        assert!(detector.check_goal(&goal, &current).is_met());
+        // 版3までのバイト単位の列はバッファの行から直す（文字の途中を指すバイトはその文字）
+        let legacy = parse_status_content("V:3,LINE:1,COL:12,MODE:n\nBUF:[\"// 名前: x\"]");
+        assert!(detector.check_goal(&goal, &legacy).is_met());
+        assert_eq!(parse_status_content("V:3,LINE:1,COL:6,MODE:n\nBUF:[\"// 名前: x\"]").cursor_col, 3);
+        assert!(!detector.check_goal(&goal, &parse_status_content("V:4,LINE:1,COL:12,MODE:n")).is_met());
+    }
+
+    #[test]
+    fn test_window_goals() {
+        let detector = GoalDetector::new();
+        let goal = |goal_type| Goal {
+            goal_type,
+            description: "ウィンドウ".to_string(),
+        };
+        let split = goal(GoalType::WindowLayout { min_splits: 1 });
+        let lower = goal(GoalType::WindowCount {
+            expected: 2,
+            current: Some(2),
+        });
+        let single = goal(GoalType::WindowCount {
+            expected: 1,
+            current: None,
+        });
+        let windows = |window_count, current_window| VimState {
+            window_count,
+            current_window,
+            ..create_test_state()
+        };
+
+        let gap = detector.check_goal(&split, &windows(1, 1)).gap().cloned().unwrap();
+        assert_eq!(gap.nudge().as_deref(), Some("あと1回 :sp か :vs で分割します"));
+        assert_eq!(gap.to_string(), "ウィンドウが1個です（期待: 2個以上）");
+        assert!(detector.check_goal(&split, &windows(3, 1)).is_met());
+
+        // :sp の直後は上の新しいウィンドウにいる
+        assert!(matches!(
+            detector.check_goal(&lower, &windows(2, 1)).gap(),
+            Some(GoalGap::CurrentWindow { actual: 1, expected: 2 })
+        ));
+        assert!(detector.check_goal(&lower, &windows(2, 2)).is_met());
+
+        // 1つに戻す目標は、有効になってからウィンドウを閉じたときに達成
+        let mut activation = GoalActivation::new(detector.observe(&single, &windows(2, 2)));
+        let gap = detector
+            .check_goal_since(&single, &mut activation, &windows(2, 2))
+            .gap()
+            .cloned();
+        assert_eq!(gap, Some(GoalGap::NoChangeSinceActivation));
+        assert!(detector.check_goal_since(&single, &mut activation, &windows(1, 1)).is_met());
+    }
+
+    #[test]
+    fn test_file_goals_use_buffer_list() {
+        let detector = GoalDetector::new();
+        let state = parse_status_content(concat!(
+            "LINE:2,COL:1,MODE:n\n",
+            "FILE:\"main.rs\"\n",
+            r#"FILES:{"main.rs":["fn main() {","}"],"util.rs":["pub fn helper() {}"]}"#
+        ));
+        assert_eq!(state.buffer_name, "main.rs");
+        assert_eq!(state.buffers["util.rs"], vec!["pub fn helper() {}".to_string()]);
+
+        let goal = |goal_type| Goal {
+            goal_type,
+            description: "ファイル".to_string(),
+        };
+        let active = goal(GoalType::BufferActive {
+            name: "util.rs".to_string(),
+        });
+        let gap = detector.check_goal(&active, &state).gap().cloned().unwrap();
+        assert_eq!(gap.nudge().as_deref(), Some("今はmain.rsを開いています（util.rsに切り替えます）"));
+
+        // 表示していないファイルのテキストも判定できる
+        let text_in_util = goal(GoalType::InFile {
+            file: "util.rs".to_string(),
+            goal: Box::new(GoalType::TextContent {
+                line: 0,
+                expected: "pub fn helper() {}".to_string(),
+                match_mode: TextMatch::Exact,
+                normalize: Normalize::default(),
+            }),
+        });
+        assert!(detector.check_goal(&text_in_util, &state).is_met());
+        // 位置の目標はそのファイルを表示しているときだけ満たす
+        let position_in = |file: &str| {
+            goal(GoalType::InFile {
+                file: file.to_string(),
+                goal: Box::new(GoalType::Position { line: 1, col: 0 }),
+            })
+        };
+        assert!(detector.check_goal(&position_in("main.rs"), &state).is_met());
+        assert!(matches!(
+            detector.check_goal(&position_in("util.rs"), &state).gap(),
+            Some(GoalGap::Buffer { .. })
+        ));
     }
 
     #[test]
@@ -127,10 +1603,10 @@ mod tests {
             description: "Move to position 1,1".to_string(),
         };
 
-        assert!(detector.check_goal(&goal, &state));
+        assert!(detector.check_goal(&goal, &state).is_met());
 
         state.cursor_line = 2;
-        assert!(!detector.check_goal(&goal, &state));
+        assert!(!detector.check_goal(&goal, &state).is_met());
     }
 
     #[test]
@@ -143,10 +1619,10 @@ mod tests {
             description: "Enter insert mode".to_string(),
         };
 
-        assert!(!detector.check_goal(&insert_goal, &state));
+        assert!(!detector.check_goal(&insert_goal, &state).is_met());
 
         state.mode = VimMode::Insert;
-        assert!(detector.check_goal(&insert_goal, &state));
+        assert!(detector.check_goal(&insert_goal, &state).is_met());
     }
 
     #[test]
@@ -159,10 +1635,10 @@ mod tests {
             description: "Press 'd' for delete operation".to_string(),
         };
 
-        assert!(!detector.check_goal(&delete_op_goal, &state));
+        assert!(!detector.check_goal(&delete_op_goal, &state).is_met());
 
         state.mode = VimMode::OperatorPending("d".to_string());
-        assert!(detector.check_goal(&delete_op_goal, &state));
+        assert!(detector.check_goal(&delete_op_goal, &state).is_met());
     }
 
     #[test]
@@ -174,21 +1650,470 @@ mod tests {
             goal_type: GoalType::TextContent {
                 line: 0,
                 expected: "hello world".to_string(),
+                match_mode: TextMatch::Exact,
+                normalize: Normalize::default(),
             },
             description: "Check first line content".to_string(),
         };
 
-        assert!(detector.check_goal(&text_goal, &state));
+        assert!(detector.check_goal(&text_goal, &state).is_met());
 
         let wrong_text_goal = Goal {
             goal_type: GoalType::TextContent {
                 line: 0,
                 expected: "different text".to_string(),
+                match_mode: TextMatch::Exact,
+                normalize: Normalize::default(),
             },
             description: "Check wrong content".to_string(),
         };
 
-        assert!(!detector.check_goal(&wrong_text_goal, &state));
+        assert!(!detector.check_goal(&wrong_text_goal, &state).is_met());
+    }
+
+    fn text_goal(expected: &str, match_mode: TextMatch) -> Goal {
+        Goal {
+            goal_type: GoalType::TextContent {
+                line: 0,
+                expected: expected.to_string(),
+                match_mode,
+                normalize: Normalize::default(),
+            },
+            description: "Check first line".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_text_goal_trailing_whitespace() {
+        let detector = GoalDetector::new();
+        let mut state = create_test_state();
+        state.buffer_content[0] = "hello world  ".to_string();
+
+        assert!(!detector.check_goal(&text_goal("hello world", TextMatch::Exact), &state).is_met());
+        assert!(detector.check_goal(&text_goal("hello world", TextMatch::Trim), &state).is_met());
+        assert!(detector.check_goal(
+            &text_goal("hello world", TextMatch::CollapseWhitespace),
+            &state
+        ).is_met());
+    }
+
+    #[test]
+    fn test_text_goal_tab_versus_space() {
+        let detector = GoalDetector::new();
+        let mut state = create_test_state();
+        state.buffer_content[0] = "\tlet  x =\t1;".to_string();
+
+        assert!(!detector.check_goal(&text_goal("    let x = 1;", TextMatch::Trim), &state).is_met());
+        assert!(detector.check_goal(
+            &text_goal("    let x = 1;", TextMatch::CollapseWhitespace),
+            &state
+        ).is_met());
+        // 空白の有無そのものは区別する
+        assert!(!detector.check_goal(
+            &text_goal("let x=1;", TextMatch::CollapseWhitespace),
+            &state
+        ).is_met());
+    }
+
+    #[test]
+    fn test_text_goals_compare_normalized_lines() {
+        let detector = GoalDetector::new();
+        let mut state = create_test_state();
+        state.buffer_content = vec!["print（“ｈｉ”）\u{3000}".to_string(), "ｘ = 1;".to_string()];
+        let normalize = Normalize::from_names(["width", "quotes", "trailing_space"]).unwrap();
+        let goal = |goal_type| Goal {
+            goal_type,
+            description: "Normalize".to_string(),
+        };
+        let text = |normalize| {
+            goal(GoalType::TextContent {
+                line: 0,
+                expected: "print(\"hi\")".to_string(),
+                match_mode: TextMatch::Exact,
+                normalize,
+            })
+        };
+        assert!(!detector.check_goal(&text(Normalize::default()), &state).is_met());
+        assert!(detector.check_goal(&text(normalize), &state).is_met());
+        // 一部だけを有効にすると、残りの違いで達成にならない
+        let width_only = Normalize::from_names(["width"]).unwrap();
+        assert!(!detector.check_goal(&text(width_only), &state).is_met());
+
+        let block = goal(GoalType::TextBlock {
+            start_line: 0,
+            expected: vec!["print(\"hi\")".to_string(), "x = 1;".to_string()],
+            normalize,
+        });
+        assert!(detector.check_goal(&block, &state).is_met());
+        state.buffer_content[1] = "ｙ = 1;".to_string();
+        // 判定と同じく、正規化した行で違いを報告する
+        assert_eq!(
+            detector.check_goal(&block, &state),
+            GoalCheck::Unmet {
+                reason: GoalGap::Line {
+                    line: 1,
+                    actual: Some("y = 1;".to_string()),
+                    expected: Some("x = 1;".to_string()),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_text_goal_regex() {
+        let detector = GoalDetector::new();
+        let state = create_test_state();
+
+        assert!(detector.check_goal(&text_goal("^hello\\s+\\w+$", TextMatch::Regex), &state).is_met());
+        assert!(!detector.check_goal(&text_goal("^world", TextMatch::Regex), &state).is_met());
+        assert!(!detector.check_goal(&text_goal("(", TextMatch::Regex), &state).is_met());
+        assert_eq!(TextMatch::from_name("collapse_whitespace"), Some(TextMatch::CollapseWhitespace));
+        assert_eq!(TextMatch::from_name("fuzzy"), None);
+    }
+
+    #[test]
+    fn test_text_block_and_buffer_goals() {
+        let detector = GoalDetector::new();
+        let mut state = create_test_state();
+        state.buffer_content = vec!["one".to_string(), "three".to_string(), "two".to_string()];
+        let lines = |items: &[&str]| items.iter().map(|line| line.to_string()).collect::<Vec<_>>();
+        let block_goal = |start_line: usize, expected: &[&str]| Goal {
+            goal_type: GoalType::TextBlock {
+                start_line,
+                expected: lines(expected),
+                normalize: Normalize::default(),
+            },
+            description: "Reorder lines".to_string(),
+        };
+        let buffer_goal = |expected: &[&str]| Goal {
+            goal_type: GoalType::BufferEquals {
+                expected: lines(expected),
+            },
+            description: "Whole buffer".to_string(),
+        };
+
+        assert!(detector.check_goal(&block_goal(1, &["three", "two"]), &state).is_met());
+        assert!(!detector.check_goal(&block_goal(0, &["one", "two"]), &state).is_met());
+        // バッファが短くても panic しない
+        assert!(!detector.check_goal(&block_goal(2, &["two", "four"]), &state).is_met());
+        assert!(!detector.check_goal(&block_goal(usize::MAX, &["two"]), &state).is_met());
+
+        assert!(detector.check_goal(&buffer_goal(&["one", "three", "two"]), &state).is_met());
+        assert!(!detector.check_goal(&buffer_goal(&["one", "three"]), &state).is_met());
+
+        // 対象範囲外の変化は記録に影響しない
+        let goal = block_goal(0, &["one", "two"]);
+        let observation = detector.observe(&goal, &state);
+        state.buffer_content[2] = "changed".to_string();
+        assert_eq!(detector.observe(&goal, &state), observation);
+    }
+
+    #[test]
+    fn test_buffer_change_goal_uses_changedtick() {
+        let detector = GoalDetector::new();
+        let mut state = create_test_state();
+        let change_goal = Goal {
+            goal_type: GoalType::BufferChange,
+            description: "Edit the buffer".to_string(),
+        };
+        assert!(!detector.check_goal(&change_goal, &state).is_met());
+
+        // 既に編集済みの状態で有効になった場合は、次の編集まで達成にしない
+        state.modified = true;
+        let mut activation = GoalActivation::new(detector.observe(&change_goal, &state));
+        assert!(!detector.check_goal_since(&change_goal, &mut activation, &state).is_met());
+
+        // 内容が同じに見えても changedtick が進めば編集とみなす
+        state.changedtick += 1;
+        assert!(detector.check_goal_since(&change_goal, &mut activation, &state).is_met());
+    }
+
+    #[test]
+    fn test_vim_state_deserializes_without_change_fields() {
+        let json = r#"{"mode":"Normal","cursor_line":0,"cursor_col":0,"operator":null,"buffer_content":[""],"registers":{}}"#;
+        let state: VimState = serde_json::from_str(json).unwrap();
+        assert_eq!(state.changedtick, 0);
+        assert!(!state.modified);
+    }
+
+    #[test]
+    fn test_unmet_goals_report_gap() {
+        let detector = GoalDetector::new();
+        let state = create_test_state(); // カーソルは (1, 1)
+
+        let position = Goal {
+            goal_type: GoalType::Position { line: 1, col: 3 },
+            description: "右へ".to_string(),
+        };
+        let gap = GoalGap::Position {
+            line_delta: 0,
+            col_delta: 2,
+        };
+        assert_eq!(detector.check_goal(&position, &state), GoalCheck::Unmet { reason: gap.clone() });
+        assert_eq!(gap.nudge().as_deref(), Some("あと2文字右"));
+        let gap = GoalGap::Position {
+            line_delta: -1,
+            col_delta: -4,
+        };
+        assert_eq!(gap.nudge().as_deref(), Some("あと1行上・4文字左"));
+
+        let text = Goal {
+            goal_type: GoalType::TextContent {
+                line: 0,
+                expected: "hello world();".to_string(),
+                match_mode: TextMatch::Exact,
+                normalize: Normalize::default(),
+            },
+            description: "括弧を足す".to_string(),
+        };
+        let check = detector.check_goal(&text, &state);
+        assert_eq!(
+            check.gap().unwrap().to_string(),
+            "1行目が`hello world`です（期待: `hello world();`）"
+        );
+        assert_eq!(check.gap().unwrap().nudge(), None);
+
+        // バッファ全体の目標は最初に違う行を理由にする（足りない行を含む）
+        let buffer = Goal {
+            goal_type: GoalType::BufferEquals {
+                expected: vec!["hello world".to_string(), "second line".to_string(), "x".to_string()],
+            },
+            description: "行を足す".to_string(),
+        };
+        assert_eq!(
+            detector.check_goal(&buffer, &state).gap(),
+            Some(&GoalGap::Line {
+                line: 2,
+                actual: None,
+                expected: Some("x".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_counted_motion_goal_requires_count() {
+        let mut detector = GoalDetector::new();
+        let state = create_test_state(); // カーソルは (1, 1)
+        let goal = Goal {
+            goal_type: GoalType::CountedMotion {
+                motion: "j".to_string(),
+                min_count: 3,
+                position: Some((1, 0)),
+            },
+            description: "3j".to_string(),
+        };
+        // キー入力を記録しないエディタでは位置だけで判定する（縦の移動なので列は問わない）
+        assert!(detector.check_goal(&goal, &state).is_met());
+
+        detector.track_motions(true);
+        let repeated = MotionUse {
+            count: None,
+            motion: "j".to_string(),
+        };
+        detector.record_motions(vec![repeated.clone(), repeated.clone(), repeated]);
+        let gap = detector.check_goal(&goal, &state).gap().cloned().unwrap();
+        assert_eq!(
+            gap,
+            GoalGap::RepeatedMotion {
+                motion: "j".to_string(),
+                times: 3
+            }
+        );
+        assert_eq!(
+            gap.nudge().as_deref(),
+            Some("jを3回押す代わりに、3j のように数を付けて一度で移動します")
+        );
+
+        // 数が足りない移動は数えない
+        detector.clear_motions();
+        detector.record_motions(vec![MotionUse {
+            count: Some(2),
+            motion: "j".to_string(),
+        }]);
+        assert_eq!(
+            detector.check_goal(&goal, &state).gap(),
+            Some(&GoalGap::NoCountedMotion {
+                motion: "j".to_string(),
+                min_count: 3
+            })
+        );
+        detector.record_motions(vec![MotionUse {
+            count: Some(5),
+            motion: "j".to_string(),
+        }]);
+        assert!(detector.check_goal(&goal, &state).is_met());
+        // 着地していなければ位置の差を理由にする
+        let above = VimState {
+            cursor_line: 0,
+            ..state
+        };
+        assert!(matches!(
+            detector.check_goal(&goal, &above).gap(),
+            Some(GoalGap::Position { line_delta: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_undo_and_redo_follow_sequence_numbers() {
+        let mut detector = GoalDetector::new();
+        let at = |undo_seq, line: &str| VimState {
+            undo_seq,
+            buffer_content: vec![line.to_string()],
+            ..create_test_state()
+        };
+        let goal = |goal_type| Goal {
+            goal_type,
+            description: String::new(),
+        };
+        let (undo, redo) = (goal(GoalType::UndoPerformed), goal(GoalType::RedoPerformed));
+        detector.record_undo_state(&at(0, "old"));
+        detector.record_undo_state(&at(1, "new"));
+
+        // 打ち直して同じ内容に戻しても、番号が進むため取り消しではない
+        let mut activation = GoalActivation::new(detector.observe(&undo, &at(1, "new")));
+        assert_eq!(
+            detector.check_goal_since(&undo, &mut activation, &at(2, "old")).gap(),
+            Some(&GoalGap::NoUndo)
+        );
+        assert!(detector.check_goal_since(&undo, &mut activation, &at(0, "old")).is_met());
+
+        // 取り消した後の新しい変更は、やり直しではない
+        let mut activation = GoalActivation::new(detector.observe(&redo, &at(0, "old")));
+        assert_eq!(
+            detector.check_goal_since(&redo, &mut activation, &at(2, "other")).gap(),
+            Some(&GoalGap::NoRedo)
+        );
+        assert!(detector.check_goal_since(&redo, &mut activation, &at(1, "new")).is_met());
+        assert!(!detector.check_goal(&redo, &at(1, "new")).is_met());
+    }
+
+    #[test]
+    fn test_substitute_commands_are_recognized() {
+        for command in ["s/foo/bar/", "%s/foo/bar/g", "'<,'>s#a#b#", " 1,3substitute/x/y/", "&&", "g/foo/s//bar/"] {
+            assert!(is_substitute_command(command), "{}", command);
+        }
+        for command in ["sp", "set hls", "w", "g/foo/d", "TutorialHint"] {
+            assert!(!is_substitute_command(command), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_substitute_replacement() {
+        let replacement = substitute_replacement;
+        assert_eq!(replacement("s/foo/bar/").as_deref(), Some("bar"));
+        assert_eq!(replacement("%s/foo/bar/g").as_deref(), Some("bar"));
+        assert_eq!(replacement("'<,'>s#a/b#c/d#").as_deref(), Some("c/d"));
+        assert_eq!(replacement("s/foo/bar").as_deref(), Some("bar"));
+        assert_eq!(replacement("s/a\\/b/x\\/y/").as_deref(), Some("x/y"));
+        assert_eq!(replacement("s/\\(x\\)/[\\1]/").as_deref(), Some("[\\1]"));
+        assert_eq!(replacement("s/foo//").as_deref(), Some(""));
+        assert_eq!(replacement("s/foo").as_deref(), Some(""));
+        assert_eq!(replacement("g/foo/s//bar/").as_deref(), Some("bar"));
+        // 前回の置換を繰り返すものと、:s でないもの
+        for command in ["&&", "~", "s", "%s g", "sp", "g/foo/d"] {
+            assert_eq!(replacement(command), None, "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_substitution_goal_compares_counts_since_activation() {
+        let mut detector = GoalDetector::new();
+        let state = create_test_state();
+        let goal = Goal {
+            goal_type: GoalType::SubstitutionResult {
+                pattern: "foo".to_string(),
+                replacement: "bar".to_string(),
+                min_count: 3,
+                lines: None,
+            },
+            description: "foo を bar に".to_string(),
+        };
+        // 有効になった時点で数えるまでは達成しない
+        assert!(matches!(
+            detector.check_goal(&goal, &state).gap(),
+            Some(GoalGap::NoSubstitution { .. })
+        ));
+        detector.count_substitution("foo", None, 3, 3);
+        assert!(detector.substitution_counted("foo", None, 3));
+        assert!(!detector.substitution_counted("foo", None, 4));
+
+        // :s を使わずに消しても達成しない
+        detector.count_substitution("foo", None, 0, 4);
+        assert!(matches!(
+            detector.check_goal(&goal, &state).gap(),
+            Some(GoalGap::NoSubstitution { .. })
+        ));
+        // 違う文字列に置き換えても達成しない
+        detector.record_ex_command("s/foo/baz/");
+        assert_eq!(
+            detector.check_goal(&goal, &state).gap(),
+            Some(&GoalGap::WrongReplacement {
+                pattern: "foo".to_string(),
+                used: "baz".to_string(),
+                expected: "bar".to_string(),
+            })
+        );
+        // 繰り返しの :& は前回の文字列のまま
+        detector.record_ex_command("&&");
+        assert!(!detector.check_goal(&goal, &state).is_met());
+        detector.record_ex_command("s/foo/bar/");
+        assert!(detector.check_goal(&goal, &state).is_met());
+        detector.record_ex_command("&&");
+        assert!(detector.check_goal(&goal, &state).is_met());
+
+        // g を付けずに置き換えて残っている
+        detector.count_substitution("foo", None, 1, 5);
+        assert_eq!(
+            detector.check_goal(&goal, &state).gap(),
+            Some(&GoalGap::PatternRemains {
+                pattern: "foo".to_string(),
+                remaining: 1
+            })
+        );
+
+        // 有効でなくなった目標は、次に有効になった時点から数え直す
+        detector.retain_substitutions(&[]);
+        detector.count_substitution("foo", None, 2, 6);
+        detector.record_ex_command("%s/foo/bar/g");
+        detector.count_substitution("foo", None, 0, 7);
+        assert_eq!(
+            detector.check_goal(&goal, &state).gap(),
+            Some(&GoalGap::TooFewSubstitutions {
+                replaced: 2,
+                min_count: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_goal_requires_change_after_activation() {
+        let detector = GoalDetector::new();
+        let mut state = create_test_state();
+        let text_goal = Goal {
+            goal_type: GoalType::TextContent {
+                line: 0,
+                expected: "hello world".to_string(),
+                match_mode: TextMatch::Exact,
+                normalize: Normalize::default(),
+            },
+            description: "Type hello world".to_string(),
+        };
+
+        // 有効になった時点で既に一致している
+        let mut activation = GoalActivation::new(detector.observe(&text_goal, &state));
+        assert!(detector.check_goal(&text_goal, &state).is_met());
+        assert!(!detector.check_goal_since(&text_goal, &mut activation, &state).is_met());
+
+        // 関係のない変化では達成されない
+        state.cursor_col = 5;
+        state.mode = VimMode::Insert;
+        assert!(!detector.check_goal_since(&text_goal, &mut activation, &state).is_met());
+
+        // 一度変化してから元に戻せば達成
+        state.buffer_content[0] = "hello".to_string();
+        assert!(!detector.check_goal_since(&text_goal, &mut activation, &state).is_met());
+        state.buffer_content[0] = "hello world".to_string();
+        assert!(detector.check_goal_since(&text_goal, &mut activation, &state).is_met());
     }
 
     #[test]
@@ -204,20 +2129,75 @@ mod tests {
             goal_type: GoalType::RegisterContent {
                 register: "0".to_string(),
                 expected: "yanked_text".to_string(),
+                match_mode: RegisterMatch::Exact,
             },
             description: "Check yank register content".to_string(),
         };
 
-        assert!(detector.check_goal(&register_goal, &state));
+        assert!(detector.check_goal(&register_goal, &state).is_met());
 
         let wrong_register_goal = Goal {
             goal_type: GoalType::RegisterContent {
                 register: "1".to_string(),
                 expected: "yanked_text".to_string(),
+                match_mode: RegisterMatch::Exact,
             },
             description: "Check non-existent register".to_string(),
         };
 
-        assert!(!detector.check_goal(&wrong_register_goal, &state));
+        assert!(!detector.check_goal(&wrong_register_goal, &state).is_met());
+    }
+    fn register_goal(expected: &str, match_mode: RegisterMatch) -> Goal {
+        Goal {
+            goal_type: GoalType::RegisterContent {
+                register: "\"".to_string(),
+                expected: expected.to_string(),
+                match_mode,
+            },
+            description: "Check unnamed register".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_register_goal_linewise_newline_is_normalized() {
+        let detector = GoalDetector::new();
+        let mut state = create_test_state();
+        state
+            .registers
+            .insert("\"".to_string(), "hello world\n".to_string());
+        state
+            .register_types
+            .insert("\"".to_string(), RegisterType::Linewise);
+
+        // yy の末尾の改行は比較前に取り除く（exact でも同じ）
+        assert!(detector.check_goal(&register_goal("hello world", RegisterMatch::Exact), &state).is_met());
+        assert!(detector.check_goal(&register_goal("hello world", RegisterMatch::Trimmed), &state).is_met());
+
+        // 文字単位のレジスタでは exact は改行も区別する
+        state
+            .register_types
+            .insert("\"".to_string(), RegisterType::Charwise);
+        assert!(!detector.check_goal(&register_goal("hello world", RegisterMatch::Exact), &state).is_met());
+        assert!(detector.check_goal(&register_goal("hello world", RegisterMatch::Trimmed), &state).is_met());
+    }
+
+    #[test]
+    fn test_register_goal_trimmed_and_regex() {
+        let detector = GoalDetector::new();
+        let mut state = create_test_state();
+        // daw は後ろの空白も含む
+        state
+            .registers
+            .insert("\"".to_string(), "Alice ".to_string());
+
+        assert!(detector.check_goal(&register_goal("Alice", RegisterMatch::Trimmed), &state).is_met());
+        assert!(detector.check_goal(&register_goal(" Alice", RegisterMatch::Trimmed), &state).is_met());
+        assert!(!detector.check_goal(&register_goal("Alice", RegisterMatch::Exact), &state).is_met());
+        assert!(!detector.check_goal(&register_goal("Bob", RegisterMatch::Trimmed), &state).is_met());
+
+        assert!(detector.check_goal(&register_goal("^Ali\\w+\\s*$", RegisterMatch::Regex), &state).is_met());
+        assert!(!detector.check_goal(&register_goal("^Bob", RegisterMatch::Regex), &state).is_met());
+        // 不正な正規表現は一致しない
+        assert!(!detector.check_goal(&register_goal("(", RegisterMatch::Regex), &state).is_met());
     }
 }
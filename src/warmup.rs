@@ -0,0 +1,84 @@
+// 慣らしの練習: 初めてのセッションの最初の練習の前に、l・i・Esc の3つのキーが届くかを確かめる。
+// 目標は普段の練習と同じ監視の仕組みで判定し、通らなかった手順に合わせて環境の手がかりを出す
+use serde_json::json;
+
+use crate::continuous_session::{ContinuousExercise, ExerciseGoal, ExerciseResult};
+
+// 慣らしの制限時間（秒）
+pub const TIME_LIMIT_SECONDS: u64 = 20;
+
+// 手順ごとのキーと、検出されなかったときの手がかり（目標と同じ順）
+const STEPS: [(&str, &str); 3] = [
+    ("l", "キーがエディタに届いていないようです（下のエディタのペインを選んでから押してください）"),
+    ("i", "モードの変化が届いていないようです（vim-tutorial-nvim doctor でエディタとの通信を確認してください）"),
+    (
+        "Esc",
+        "ターミナルの設定を確認してください（Esc を別のキーに割り当てていないか、tmux の escape-time が長すぎないか）",
+    ),
+];
+
+pub fn exercise() -> ContinuousExercise {
+    let goal = |goal_type: &str, target, description: &str, key: &str| ExerciseGoal {
+        goal_type: goal_type.to_string(),
+        target,
+        description: description.to_string(),
+        hint: Some(format!("{} を1回押します", key)),
+        solution_keys: Some(if key == "Esc" { "<Esc>".to_string() } else { key.to_string() }),
+        keys: vec![key.to_string()],
+        ..Default::default()
+    };
+    ContinuousExercise {
+        title: "慣らし: キーの確認".to_string(),
+        description: "l・i・Esc の順に押して、キーがエディタに届くかを確かめます".to_string(),
+        sample_code: vec!["ab".to_string()],
+        goals: vec![
+            goal("position", json!([0, 1]), "l で右へ1文字移動", "l"),
+            goal("mode", json!("insert"), "i で Insert モードに入る", "i"),
+            goal("mode", json!("normal"), "Esc で Normal モードに戻る", "Esc"),
+        ],
+        time_limit_seconds: Some(TIME_LIMIT_SECONDS),
+        cursor_start: Some([0, 0]),
+        coaching: Some(false),
+        intro: Some(format!("🔰 {}秒ほどで終わります。表示された順にキーを1回ずつ押してください", TIME_LIMIT_SECONDS)),
+        ..Default::default()
+    }
+}
+
+// 慣らしの結果。通れば None、通らなければ最初に検出されなかった手順とその手がかり
+pub fn failure_message(result: &ExerciseResult, completed_goals: &[bool]) -> Option<String> {
+    if *result == ExerciseResult::Completed {
+        return None;
+    }
+    let step = completed_goals.iter().position(|&done| !done).unwrap_or(0).min(STEPS.len() - 1);
+    let (key, advice) = STEPS[step];
+    let mut message = format!("{}が検出されませんでした — {}", key, advice);
+    if let ExerciseResult::Failed(error) = result {
+        message.push_str(&format!("\n（エラー: {}）", error));
+    }
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator;
+
+    #[test]
+    fn test_warmup_exercise_is_valid() {
+        let exercise = exercise();
+        assert!(validator::validate_exercise(&exercise).is_empty());
+        assert!(exercise.goals.iter().all(|goal| goal.to_goal().is_ok()));
+        assert_eq!(exercise.goals.len(), STEPS.len());
+    }
+
+    #[test]
+    fn test_failure_names_the_first_missing_step() {
+        assert_eq!(failure_message(&ExerciseResult::Completed, &[true, true, true]), None);
+        let message = failure_message(&ExerciseResult::TimedOut, &[true, true, false]).unwrap();
+        assert!(message.starts_with("Escが検出されませんでした — ターミナルの設定を確認してください"));
+        let message = failure_message(&ExerciseResult::Failed("接続が切れました".to_string()), &[false, false, false])
+            .unwrap();
+        assert!(message.starts_with("lが検出されませんでした"));
+        assert!(message.contains("接続が切れました"));
+    }
+}